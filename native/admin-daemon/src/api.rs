@@ -0,0 +1,388 @@
+/*!
+ * Admin API routes
+ *
+ * Everything lives under `/v1/diamonds/<state>`, and only the diamonds the
+ * daemon was started with are served. Write routes build an unsigned
+ * transaction, preview it against the live state and return both. The
+ * caller's approval workflow collects signatures and then posts the signed
+ * transaction to `/submit`. That route previews and lints it again and
+ * sends it only when nothing blocks.
+ */
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use borsh::BorshDeserialize;
+use diamond_cli::{
+    admin,
+    http::{self, Request},
+    lint,
+    plan::MAX_TRANSACTION_SIZE,
+    rpc::{unsigned_transaction, RpcClient, RpcResult},
+    simulate::{self, Preview},
+};
+use diamond_router_native::{
+    diamond_cut::FacetCut,
+    diamond_proposal::CutProposal,
+    diamond_state::{fixed_str, DiamondState},
+};
+use serde_json::{json, Value};
+use solana_program::{instruction::Instruction, message::Message, pubkey::Pubkey};
+use std::time::Duration;
+
+use crate::auth::{Role, Tokens};
+
+/// How long `/submit` waits for confirmation
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, PartialEq)]
+pub struct Response {
+    pub status: &'static str,
+    pub body: Value,
+}
+
+impl Response {
+    pub fn ok(body: Value) -> Self {
+        Self { status: "200 OK", body }
+    }
+    
+    pub fn error(status: &'static str, message: impl Into<String>) -> Self {
+        Self { status, body: json!({ "error": message.into() }) }
+    }
+}
+
+impl From<Response> for http::Response {
+    fn from(response: Response) -> Self {
+        http::Response {
+            status: response.status,
+            headers: vec![("Cache-Control", "no-store".to_string())],
+            body: response.body.to_string(),
+        }
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn bad_request(message: impl Into<String>) -> Response {
+    Response::error("400 Bad Request", message)
+}
+
+fn pubkey_field(body: &Value, name: &str) -> Result<Pubkey, Response> {
+    body[name]
+        .as_str()
+        .ok_or_else(|| bad_request(format!("`{}` is required", name)))?
+        .parse()
+        .map_err(|_| bad_request(format!("`{}`: invalid pubkey", name)))
+}
+
+fn parse_cuts(body: &Value) -> Result<Vec<FacetCut>, Response> {
+    let cuts = body["cuts"].as_array().ok_or_else(|| bad_request("`cuts` must be an array"))?;
+    if cuts.is_empty() || cuts.len() > CutProposal::MAX_CUTS {
+        return Err(bad_request(format!("`cuts` must hold 1..={} cuts", CutProposal::MAX_CUTS)));
+    }
+    cuts.iter().map(admin::parse_cut).collect::<Result<_, _>>().map_err(bad_request)
+}
+
+fn preview_json(preview: &Preview) -> Value {
+    json!({
+        "succeeds": preview.succeeds(),
+        "error": preview.error.as_ref().map(|e| e.to_string()),
+        "units_consumed": preview.units_consumed,
+        "summary": preview.to_string(),
+        "logs": preview.logs,
+    })
+}
+
+pub struct Api {
+    pub rpc: RpcClient,
+    pub router: Pubkey,
+    pub diamonds: Vec<Pubkey>,
+    pub tokens: Tokens,
+}
+
+impl Api {
+    pub fn handle(&self, request: &Request) -> Response {
+        let Some(client) = self.tokens.authenticate(request.header("authorization")) else {
+            return Response::error("401 Unauthorized", "missing or unknown bearer token");
+        };
+        let required = if request.method == "GET" { Role::Read } else { Role::Admin };
+        let response = if client.role < required {
+            Response::error("403 Forbidden", "this token is read-only")
+        } else {
+            self.route(request).unwrap_or_else(|response| response)
+        };
+        println!("{} {} {} -> {}", client.name, request.method, request.path, response.status);
+        response
+    }
+    
+    fn route(&self, request: &Request) -> Result<Response, Response> {
+        let path = request.path.split('?').next().unwrap_or_default().trim_end_matches('/');
+        let segments: Vec<&str> = path.split('/').skip(1).collect();
+        let body = || -> Result<Value, Response> {
+            serde_json::from_slice(&request.body).map_err(|e| bad_request(format!("invalid JSON body: {}", e)))
+        };
+        match (request.method.as_str(), &segments[..]) {
+            ("GET", ["v1", "diamonds"]) => {
+                Ok(Response::ok(json!(self.diamonds.iter().map(Pubkey::to_string).collect::<Vec<_>>())))
+            }
+            (method, ["v1", "diamonds", diamond, rest @ ..]) => {
+                let diamond: Pubkey = diamond.parse().map_err(|_| bad_request("invalid diamond address"))?;
+                if !self.diamonds.contains(&diamond) {
+                    return Err(Response::error("404 Not Found", "diamond not served by this daemon"));
+                }
+                match (method, rest) {
+                    ("GET", []) => self.loupe(&diamond),
+                    ("GET", ["proposals", id]) => self.proposal(&diamond, id),
+                    ("POST", ["cuts"]) => self.cut(&diamond, &body()?),
+                    ("POST", ["pause"]) => self.pause(&diamond, &body()?),
+                    ("POST", ["proposals"]) => self.propose(&diamond, &body()?),
+                    ("POST", ["proposals", id, action @ ("approve" | "execute")]) => {
+                        self.decide(&diamond, id, action, &body()?)
+                    }
+                    ("POST", ["submit"]) => self.submit(&diamond, &body()?),
+                    _ => Err(Response::error("404 Not Found", "unknown route")),
+                }
+            }
+            _ => Err(Response::error("404 Not Found", "unknown route")),
+        }
+    }
+    
+    fn state(&self, diamond: &Pubkey) -> Result<DiamondState, Response> {
+        let upstream = |e: String| Response::error("502 Bad Gateway", e);
+        let account = self
+            .rpc
+            .get_account(diamond)
+            .map_err(upstream)?
+            .ok_or_else(|| Response::error("404 Not Found", "diamond state account not found"))?;
+        if account.owner != self.router {
+            return Err(upstream(format!("{} is not owned by router {}", diamond, self.router)));
+        }
+        DiamondState::deserialize(&mut &account.data[..]).map_err(|e| upstream(e.to_string()))
+    }
+    
+    fn loupe(&self, diamond: &Pubkey) -> Result<Response, Response> {
+        let state = self.state(diamond)?;
+        let width = state.selector_width;
+        Ok(Response::ok(json!({
+            "owner": state.owner.to_string(),
+            "pause_authority": state.pause_authority.to_string(),
+            "dispatch_paused": state.dispatch_paused,
+            "cut_paused": state.cut_paused,
+            "config_version": state.config_version,
+            "next_proposal_id": state.next_proposal_id,
+            "selectors": state.selectors.iter().map(|m| json!({
+                "selector": hex(m.selector.as_bytes(width)),
+                "name": m.qualified_name(),
+                "module": m.module.to_string(),
+                "immutable": m.is_immutable,
+            })).collect::<Vec<Value>>(),
+            "facets": state.active_modules.iter().map(|m| json!({
+                "name": fixed_str(&m.name),
+                "address": m.address.to_string(),
+                "version": m.version.to_string(),
+                "active": m.is_active,
+            })).collect::<Vec<Value>>(),
+        })))
+    }
+    
+    fn proposal(&self, diamond: &Pubkey, id: &str) -> Result<Response, Response> {
+        let id: u64 = id.parse().map_err(|_| bad_request("invalid proposal id"))?;
+        let address = CutProposal::find_address(&self.router, diamond, id).0;
+        let account = self
+            .rpc
+            .get_account(&address)
+            .map_err(|e| Response::error("502 Bad Gateway", e))?
+            .ok_or_else(|| Response::error("404 Not Found", "proposal not found"))?;
+        let proposal = CutProposal::deserialize(&mut &account.data[..])
+            .map_err(|e| Response::error("502 Bad Gateway", e.to_string()))?;
+        Ok(Response::ok(json!({
+            "address": address.to_string(),
+            "id": proposal.id,
+            "proposer": proposal.proposer.to_string(),
+            "executed": proposal.executed,
+            "eta_slot": proposal.eta_slot,
+            "tip_lamports": proposal.tip_lamports,
+            "cuts": proposal.cuts.iter().map(|cut| json!({
+                "action": format!("{:?}", cut.action).to_lowercase(),
+                "selector": hex(&cut.selector.0),
+                "module": cut.module.to_string(),
+                "module_name": cut.module_name,
+                "namespace": cut.namespace,
+                "function_name": cut.function_name,
+            })).collect::<Vec<Value>>(),
+        })))
+    }
+    
+    /// Unsigned transaction for `instructions` with its preview
+    ///
+    /// The fee payer defaults to `authority`. Returns 413 when the transaction
+    /// is too large to send and 422 when the preview fails.
+    fn build(
+        &self,
+        diamond: &Pubkey,
+        body: &Value,
+        authority: &Pubkey,
+        instructions: &[Instruction],
+        extra: Value,
+    ) -> Result<Response, Response> {
+        let fee_payer = match body.get("fee_payer") {
+            Some(_) => pubkey_field(body, "fee_payer")?,
+            None => *authority,
+        };
+        let upstream = |e: String| Response::error("502 Bad Gateway", e);
+        let blockhash = self.rpc.latest_blockhash().map_err(upstream)?;
+        let message = Message::new_with_blockhash(instructions, Some(&fee_payer), &blockhash);
+        let size = unsigned_transaction(&message).len();
+        if size > MAX_TRANSACTION_SIZE {
+            return Err(Response::error(
+                "413 Payload Too Large",
+                format!("transaction is {} bytes (limit {}); split it with `diamond plan-cuts`", size, MAX_TRANSACTION_SIZE),
+            ));
+        }
+        let preview = simulate::preview(&self.rpc, &self.router, diamond, &message).map_err(upstream)?;
+        let signers = &message.account_keys[..message.header.num_required_signatures as usize];
+        let mut response = json!({
+            "transaction": STANDARD.encode(unsigned_transaction(&message)),
+            "signers": signers.iter().map(Pubkey::to_string).collect::<Vec<_>>(),
+            "preview": preview_json(&preview),
+        });
+        if let (Value::Object(response), Value::Object(extra)) = (&mut response, extra) {
+            response.extend(extra);
+        }
+        let status = if preview.succeeds() { "200 OK" } else { "422 Unprocessable Entity" };
+        Ok(Response { status, body: response })
+    }
+    
+    fn cut(&self, diamond: &Pubkey, body: &Value) -> Result<Response, Response> {
+        let authority = pubkey_field(body, "authority")?;
+        let cuts = parse_cuts(body)?;
+        let state = self.state(diamond)?;
+        let instructions =
+            admin::cut_instructions(&self.router, diamond, &state, &authority, &cuts).map_err(bad_request)?;
+        self.build(diamond, body, &authority, &instructions, json!({}))
+    }
+    
+    fn pause(&self, diamond: &Pubkey, body: &Value) -> Result<Response, Response> {
+        let authority = pubkey_field(body, "authority")?;
+        let paused = body["paused"].as_bool().ok_or_else(|| bad_request("`paused` must be a boolean"))?;
+        let expiry_slot = match &body["expiry_slot"] {
+            Value::Null => None,
+            slot => Some(slot.as_u64().ok_or_else(|| bad_request("`expiry_slot` must be a slot"))?),
+        };
+        let approvers = match &body["approvers"] {
+            Value::Null => Vec::new(),
+            Value::Array(keys) => keys
+                .iter()
+                .map(|key| key.as_str().and_then(|key| key.parse().ok()).ok_or_else(|| bad_request("`approvers` must be pubkeys")))
+                .collect::<Result<Vec<Pubkey>, Response>>()?,
+            _ => return Err(bad_request("`approvers` must be an array")),
+        };
+        let ix = admin::pause_instruction(&self.router, diamond, &authority, &approvers, paused, expiry_slot);
+        self.build(diamond, body, &authority, &[ix], json!({}))
+    }
+    
+    fn propose(&self, diamond: &Pubkey, body: &Value) -> Result<Response, Response> {
+        let authority = pubkey_field(body, "authority")?;
+        let cuts = parse_cuts(body)?;
+        let state = self.state(diamond)?;
+        let payer = match body.get("fee_payer") {
+            Some(_) => pubkey_field(body, "fee_payer")?,
+            None => authority,
+        };
+        let (ix, id) = admin::propose_instruction(&self.router, diamond, &state, &authority, &payer, &cuts);
+        self.build(diamond, body, &authority, &[ix], json!({ "proposal_id": id }))
+    }
+    
+    fn decide(&self, diamond: &Pubkey, id: &str, action: &str, body: &Value) -> Result<Response, Response> {
+        let id: u64 = id.parse().map_err(|_| bad_request("invalid proposal id"))?;
+        let authority = pubkey_field(body, "authority")?;
+        let state = self.state(diamond)?;
+        let ix = match action {
+            "approve" => match admin::approver(&state) {
+                admin::Approver::Council => {
+                    return Err(Response::error("409 Conflict", "council diamonds apply proposals by motion"))
+                }
+                approver if approver.key() != Some(authority) => {
+                    return Err(bad_request(format!("proposals on this diamond are approved by its {}", approver)))
+                }
+                _ => admin::approve_instruction(&self.router, diamond, &state, &authority, id),
+            },
+            _ => admin::execute_instruction(&self.router, diamond, &state, &authority, id),
+        };
+        self.build(diamond, body, &authority, &[ix], json!({ "proposal_id": id }))
+    }
+    
+    /// Preview, lint and (with `"send": true`) send a signed transaction
+    ///
+    /// Body: `{"transaction": <base64>, "allow": "<lint,...>", "send": bool}`
+    fn submit(&self, diamond: &Pubkey, body: &Value) -> Result<Response, Response> {
+        let wire = STANDARD
+            .decode(body["transaction"].as_str().ok_or_else(|| bad_request("`transaction` is required"))?)
+            .map_err(|e| bad_request(format!("`transaction`: {}", e)))?;
+        let allowed = lint::parse_allowed(body["allow"].as_str().unwrap_or_default()).map_err(bad_request)?;
+        let upstream = |e: String| Response::error("502 Bad Gateway", e);
+        
+        let preview = simulate::preview_transaction(&self.rpc, &self.router, diamond, &wire).map_err(upstream)?;
+        if !preview.succeeds() {
+            return Err(Response { status: "422 Unprocessable Entity", body: json!({ "preview": preview_json(&preview) }) });
+        }
+        let findings = lint::run(&self.rpc, &preview, lint::DEFAULT_RECENT_SLOTS).map_err(upstream)?;
+        let blocking = findings.iter().filter(|f| !allowed.contains(&f.lint)).count();
+        let mut response = json!({
+            "preview": preview_json(&preview),
+            "findings": findings.iter().map(|f| json!({
+                "lint": f.lint.name(),
+                "message": f.message,
+                "allowed": allowed.contains(&f.lint),
+            })).collect::<Vec<Value>>(),
+            "sent": false,
+        });
+        if blocking > 0 {
+            return Err(Response { status: "409 Conflict", body: response });
+        }
+        if body["send"].as_bool() == Some(true) {
+            let signature = self.send(&wire).map_err(upstream)?;
+            response["sent"] = json!(true);
+            response["signature"] = json!(signature);
+        }
+        Ok(Response::ok(response))
+    }
+    
+    fn send(&self, wire: &[u8]) -> RpcResult<String> {
+        let signature = self.rpc.send_wire(wire)?;
+        self.rpc.confirm(&signature, CONFIRM_TIMEOUT)?;
+        Ok(signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_tokens_gate_routes() {
+        let api = Api {
+            rpc: RpcClient::new("http://127.0.0.1:1").unwrap(),
+            router: Pubkey::new_unique(),
+            diamonds: vec![Pubkey::new_unique()],
+            tokens: Tokens::parse("dash read 0123456789abcdef\nops admin fedcba9876543210").unwrap(),
+        };
+        let request = |method: &str, path: String, token: Option<&str>| Request {
+            method: method.to_string(),
+            path,
+            headers: token.map(|t| ("Authorization".to_string(), format!("Bearer {}", t))).into_iter().collect(),
+            body: b"{}".to_vec(),
+        };
+        let diamond = api.diamonds[0];
+        
+        let listed = api.handle(&request("GET", "/v1/diamonds".to_string(), Some("0123456789abcdef")));
+        assert_eq!(listed, Response::ok(json!([diamond.to_string()])));
+        assert_eq!(api.handle(&request("GET", "/v1/diamonds".to_string(), None)).status, "401 Unauthorized");
+        let pause = format!("/v1/diamonds/{}/pause", diamond);
+        assert_eq!(api.handle(&request("POST", pause.clone(), Some("0123456789abcdef"))).status, "403 Forbidden");
+        // Admins get past auth; the empty body fails validation before any RPC
+        assert_eq!(api.handle(&request("POST", pause, Some("fedcba9876543210"))).status, "400 Bad Request");
+        let other = format!("/v1/diamonds/{}", Pubkey::new_unique());
+        assert_eq!(api.handle(&request("GET", other, Some("0123456789abcdef"))).status, "404 Not Found");
+    }
+}
@@ -0,0 +1,95 @@
+/*!
+ * Bearer-token authentication
+ *
+ * Tokens come from a file with one client per line, `<name> <role> <token>`,
+ * plus `#` comments. `read` clients may only GET. `admin` clients may also
+ * build and submit transactions. The daemon holds no signing keys, so an
+ * admin token can only relay transactions that the authorities signed.
+ */
+
+/// Shortest token accepted in the tokens file
+pub const MIN_TOKEN_LEN: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    Read,
+    Admin,
+}
+
+#[derive(Debug)]
+pub struct Client {
+    pub name: String,
+    pub role: Role,
+    token: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct Tokens(Vec<Client>);
+
+/// Compare without an early exit so timing doesn't leak a matching prefix
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+impl Tokens {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut clients: Vec<Client> = Vec::new();
+        for (number, line) in text.lines().enumerate().map(|(i, line)| (i + 1, line.trim())) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [name, role, token] = fields[..] else {
+                return Err(format!("tokens line {}: expected `<name> <role> <token>`", number));
+            };
+            let role = match role {
+                "read" => Role::Read,
+                "admin" => Role::Admin,
+                other => return Err(format!("tokens line {}: unknown role {}", number, other)),
+            };
+            if token.len() < MIN_TOKEN_LEN {
+                return Err(format!("tokens line {}: token shorter than {} characters", number, MIN_TOKEN_LEN));
+            }
+            if clients.iter().any(|c| c.name == name || c.token == token.as_bytes()) {
+                return Err(format!("tokens line {}: duplicate client or token", number));
+            }
+            clients.push(Client { name: name.to_string(), role, token: token.as_bytes().to_vec() });
+        }
+        if clients.is_empty() {
+            return Err("tokens file lists no clients".to_string());
+        }
+        Ok(Self(clients))
+    }
+    
+    /// Client presenting `Authorization: Bearer <token>`
+    pub fn authenticate(&self, authorization: Option<&str>) -> Option<&Client> {
+        let token = authorization?.strip_prefix("Bearer ")?.trim().as_bytes();
+        self.0.iter().find(|client| constant_time_eq(&client.token, token))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_tokens() {
+        let tokens = Tokens::parse(
+            "# ops tooling\n\
+             dashboard read  0123456789abcdef\n\
+             approvals admin fedcba9876543210\n",
+        )
+        .unwrap();
+        let client = tokens.authenticate(Some("Bearer fedcba9876543210")).unwrap();
+        assert_eq!((client.name.as_str(), client.role), ("approvals", Role::Admin));
+        assert_eq!(tokens.authenticate(Some("Bearer 0123456789abcdef")).unwrap().role, Role::Read);
+        assert!(tokens.authenticate(Some("Bearer 0123456789abcde")).is_none());
+        assert!(tokens.authenticate(Some("0123456789abcdef")).is_none());
+        assert!(tokens.authenticate(None).is_none());
+        
+        assert!(Tokens::parse("ci admin short").is_err());
+        assert!(Tokens::parse("ci root 0123456789abcdef").is_err());
+        assert!(Tokens::parse("a read 0123456789abcdef\nb admin 0123456789abcdef").is_err());
+        assert!(Tokens::parse("# nobody\n").is_err());
+    }
+}
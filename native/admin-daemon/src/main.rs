@@ -0,0 +1,63 @@
+/*!
+ * Diamond Admin Daemon
+ *
+ * Serves diamond administration over authenticated REST/JSON, so internal
+ * tooling and approval workflows can drive cuts, pauses and proposals
+ * without embedding Rust. It wraps the `diamond-cli` library. Write routes
+ * return an unsigned transaction with its preview, and signed transactions
+ * go back through `/submit`, which runs the same lints as `diamond submit`.
+ *
+ * Usage:
+ *   diamond-admin --program <router_id> --state <diamond_state>[,<diamond_state>...]
+ *                 --tokens <file> [--url <http_rpc>] [--listen <addr>]
+ *
+ * `--listen` defaults to 127.0.0.1:8990. Put a TLS-terminating proxy in
+ * front before exposing it beyond localhost.
+ */
+
+use diamond_cli::{http, parse_flags, rpc::RpcClient};
+use solana_program::pubkey::Pubkey;
+use std::process::ExitCode;
+
+mod api;
+mod auth;
+
+use api::Api;
+use auth::Tokens;
+
+const DEFAULT_URL: &str = "http://127.0.0.1:8899";
+const DEFAULT_LISTEN: &str = "127.0.0.1:8990";
+
+fn run(args: &[String]) -> Result<(), String> {
+    let flags = parse_flags(args)?;
+    let rpc = RpcClient::new(flags.get("url").map(String::as_str).unwrap_or(DEFAULT_URL))?;
+    let router: Pubkey = flags
+        .get("program")
+        .ok_or("--program is required")?
+        .parse()
+        .map_err(|_| "--program: invalid pubkey")?;
+    let diamonds = flags
+        .get("state")
+        .ok_or("--state is required")?
+        .split(',')
+        .map(|s| s.trim().parse().map_err(|_| format!("--state: invalid pubkey {}", s)))
+        .collect::<Result<Vec<Pubkey>, String>>()?;
+    let path = flags.get("tokens").ok_or("--tokens is required")?;
+    let tokens = Tokens::parse(&std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?)?;
+    let listen = flags.get("listen").map(String::as_str).unwrap_or(DEFAULT_LISTEN);
+    
+    let api = Api { rpc, router, diamonds, tokens };
+    println!("Serving diamond admin API on http://{}", listen);
+    http::serve(listen, move |request| api.handle(request).into())
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,33 @@
+#!/usr/bin/env bash
+#
+# Native vs Anchor size and compute report
+# Builds the native router, the counter facet and the Anchor program, then
+# runs the localnet check with both routers and fails if the native router
+# is over budget.toml. `./bench.sh --record` instead writes budget.toml from
+# the run. Needs the Solana CLI (cargo-build-sbf and solana-test-validator).
+#
+
+set -e
+
+cd "$(dirname "$0")"
+
+budget_flag=--budget
+if [ "$1" = "--record" ]; then
+    budget_flag=--record-budget
+    shift
+fi
+
+echo "🔨 Building programs..."
+cargo build-sbf --manifest-path router/Cargo.toml
+cargo build-sbf --manifest-path facet/Cargo.toml
+cargo build-sbf --manifest-path ../programs/sol_diamond/Cargo.toml
+
+# The Anchor program must declare its deploy key (`anchor keys sync` once)
+anchor_id=$(solana address -k ../target/deploy/sol_diamond_mvp-keypair.json)
+
+echo ""
+echo "📏 Measuring..."
+cargo run --release -p diamond-cli --bin diamond-validator -- \
+    --anchor-so ../target/deploy/sol_diamond_mvp.so \
+    --anchor-id "$anchor_id" \
+    "$budget_flag" budget.toml "$@"
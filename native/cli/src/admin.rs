@@ -0,0 +1,357 @@
+/*!
+ * Admin instruction builders
+ *
+ * Cut, pause and proposal instructions for a diamond, with the trailing
+ * accounts its state asks for (routing digest, selector index, audit
+ * tree) already
+ * appended. Builders take the decoded state so callers fetch it once.
+ * Cuts can also be read from the JSON form the admin daemon and
+ * `diamond plan-cuts` accept.
+ */
+
+use borsh::BorshSerialize;
+use diamond_router_native::{
+    diamond_audit::{audit_authority, SPL_ACCOUNT_COMPRESSION_ID, SPL_NOOP_ID},
+    diamond_cut::{FacetCut, FacetCutAction},
+    diamond_digest::RoutingDigest,
+    diamond_index::SelectorIndex,
+    diamond_proposal::CutProposal,
+    diamond_state::DiamondState,
+    diamond_vault::rent_vault,
+    selector::Selector,
+    ADD_MODULE_DISCRIMINATOR, APPROVE_CUT_DISCRIMINATOR, EXECUTE_CUT_DISCRIMINATOR, PAUSE_DISCRIMINATOR,
+    PROPOSE_CUT_DISCRIMINATOR, REMOVE_MODULE_DISCRIMINATOR,
+};
+use serde_json::{json, Value};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program,
+};
+use std::fmt;
+
+use crate::rpc::RpcResult;
+
+fn instruction(router: &Pubkey, discriminator: [u8; 8], args: impl BorshSerialize, accounts: Vec<AccountMeta>) -> Instruction {
+    let mut data = discriminator.to_vec();
+    args.serialize(&mut data).expect("writing to a Vec cannot fail");
+    Instruction { program_id: *router, accounts, data }
+}
+
+/// `"01020304"` into a 2, 4 or 8 byte selector
+pub fn parse_selector(text: &str) -> Result<Selector, String> {
+    let bytes = (0..text.len())
+        .step_by(2)
+        .map(|i| text.get(i..i + 2).and_then(|pair| u8::from_str_radix(pair, 16).ok()))
+        .collect::<Option<Vec<u8>>>()
+        .ok_or_else(|| format!("invalid selector hex {}", text))?;
+    match bytes.len() {
+        2 | 4 | 8 => Selector::from_bytes(&bytes).ok_or_else(|| format!("invalid selector {}", text)),
+        _ => Err(format!("selector {} is not 2, 4 or 8 bytes", text)),
+    }
+}
+
+/// One JSON cut:
+/// `{"action", "selector", "module", "module_name", "namespace", "function_name", "immutable", "flags"}`
+///
+/// Only `action` and `selector` are needed for a removal.
+pub fn parse_cut(value: &Value) -> Result<FacetCut, String> {
+    let text = |name: &str| value[name].as_str().unwrap_or_default().to_string();
+    let action = match value["action"].as_str() {
+        Some("add") => FacetCutAction::Add,
+        Some("replace") => FacetCutAction::Replace,
+        Some("remove") => FacetCutAction::Remove,
+        other => return Err(format!("unknown cut action {:?}", other)),
+    };
+    let selector = parse_selector(value["selector"].as_str().ok_or("cut selector is required")?)?;
+    let module = match action {
+        FacetCutAction::Remove => Pubkey::default(),
+        _ => value["module"]
+            .as_str()
+            .and_then(|s| s.parse().ok())
+            .ok_or("cut module is required")?,
+    };
+    let standard_accounts = match value["flags"].as_u64() {
+        Some(flags) => u8::try_from(flags).map_err(|_| format!("cut flags {} out of range", flags))?,
+        None => 0,
+    };
+    let cut = FacetCut {
+        action,
+        selector,
+        module,
+        module_name: text("module_name"),
+        namespace: text("namespace"),
+        function_name: text("function_name"),
+        is_immutable: value["immutable"].as_bool().unwrap_or(false),
+        standard_accounts,
+    };
+    cut.validate().map_err(|e| format!("{:?}: {}", selector, e))?;
+    Ok(cut)
+}
+
+/// JSON form of `cut` that `parse_cut` reads back, selector at `width` bytes
+pub fn cut_json(cut: &FacetCut, width: u8) -> Value {
+    let action = match cut.action {
+        FacetCutAction::Add => "add",
+        FacetCutAction::Replace => "replace",
+        FacetCutAction::Remove => "remove",
+    };
+    let selector: String = cut.selector.as_bytes(width).iter().map(|b| format!("{:02x}", b)).collect();
+    if cut.action == FacetCutAction::Remove {
+        return json!({ "action": action, "selector": selector });
+    }
+    json!({
+        "action": action,
+        "selector": selector,
+        "module": cut.module.to_string(),
+        "module_name": cut.module_name,
+        "namespace": cut.namespace,
+        "function_name": cut.function_name,
+        "immutable": cut.is_immutable,
+        "flags": cut.standard_accounts,
+    })
+}
+
+/// Accounts every cut-applying instruction appends: the routing digest and
+/// selector index (if enabled) and the audit accounts (if the diamond has a
+/// tree)
+pub fn cut_tail(router: &Pubkey, diamond: &Pubkey, state: &DiamondState) -> Vec<AccountMeta> {
+    let mut tail = Vec::new();
+    if state.routing_digest {
+        tail.push(AccountMeta::new(RoutingDigest::find_address(router, diamond).0, false));
+    }
+    if state.selector_index {
+        tail.push(AccountMeta::new(SelectorIndex::find_address(router, diamond).0, false));
+    }
+    if let Some(tree) = state.audit_tree {
+        tail.extend([
+            AccountMeta::new(tree, false),
+            AccountMeta::new_readonly(audit_authority(router, diamond).0, false),
+            AccountMeta::new_readonly(SPL_ACCOUNT_COMPRESSION_ID, false),
+            AccountMeta::new_readonly(SPL_NOOP_ID, false),
+        ]);
+    }
+    tail
+}
+
+/// One `add_module`/`remove_module` instruction per cut
+///
+/// Replacements only apply through proposals.
+pub fn cut_instructions(
+    router: &Pubkey,
+    diamond: &Pubkey,
+    state: &DiamondState,
+    authority: &Pubkey,
+    cuts: &[FacetCut],
+) -> RpcResult<Vec<Instruction>> {
+    let mut accounts = vec![AccountMeta::new(*diamond, false), AccountMeta::new_readonly(*authority, true)];
+    accounts.extend(cut_tail(router, diamond, state));
+    cuts.iter()
+        .map(|cut| {
+            cut.validate().map_err(|e| format!("{:?}: {}", cut.selector, e))?;
+            match cut.action {
+                FacetCutAction::Add => Ok(instruction(
+                    router,
+                    ADD_MODULE_DISCRIMINATOR,
+                    (
+                        &cut.module_name,
+                        cut.module,
+                        cut.selector,
+                        &cut.function_name,
+                        cut.is_immutable,
+                        &cut.namespace,
+                        cut.standard_accounts,
+                    ),
+                    accounts.clone(),
+                )),
+                FacetCutAction::Remove => {
+                    Ok(instruction(router, REMOVE_MODULE_DISCRIMINATOR, cut.selector, accounts.clone()))
+                }
+                FacetCutAction::Replace => Err(format!("{:?}: replacements need a proposal", cut.selector)),
+            }
+        })
+        .collect()
+}
+
+/// Pause or unpause dispatch and cuts together
+///
+/// `approvers` co-sign a call that lifts or shortens a pause, for diamonds
+/// with an unpause quorum.
+pub fn pause_instruction(
+    router: &Pubkey,
+    diamond: &Pubkey,
+    authority: &Pubkey,
+    approvers: &[Pubkey],
+    paused: bool,
+    expiry_slot: Option<u64>,
+) -> Instruction {
+    let mut accounts = vec![AccountMeta::new(*diamond, false), AccountMeta::new_readonly(*authority, true)];
+    accounts.extend(approvers.iter().map(|approver| AccountMeta::new_readonly(*approver, true)));
+    instruction(router, PAUSE_DISCRIMINATOR, (paused, expiry_slot), accounts)
+}
+
+/// Propose `cuts` as the diamond's next proposal; returns the instruction
+/// and the proposal id it will get
+///
+/// `payer` may be the diamond's rent vault, which the router signs for (for
+/// authorities that can't pay rent, like a governance PDA).
+pub fn propose_instruction(
+    router: &Pubkey,
+    diamond: &Pubkey,
+    state: &DiamondState,
+    authority: &Pubkey,
+    payer: &Pubkey,
+    cuts: &[FacetCut],
+) -> (Instruction, u64) {
+    let id = state.next_proposal_id;
+    let ix = instruction(
+        router,
+        PROPOSE_CUT_DISCRIMINATOR,
+        cuts,
+        vec![
+            AccountMeta::new(*diamond, false),
+            AccountMeta::new(CutProposal::find_address(router, diamond, id).0, false),
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(*payer, payer != &rent_vault(router, diamond).0),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    (ix, id)
+}
+
+/// Who applies a proposal on a diamond
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Approver {
+    Owner(Pubkey),
+    /// The governance key of a governance-only diamond
+    Governance(Pubkey),
+    /// Council diamonds apply proposals by motion, not `approve_cut`
+    Council,
+}
+
+impl Approver {
+    /// The key that signs `approve_cut`, if the diamond takes one
+    pub fn key(&self) -> Option<Pubkey> {
+        match self {
+            Approver::Owner(key) | Approver::Governance(key) => Some(*key),
+            Approver::Council => None,
+        }
+    }
+}
+
+impl fmt::Display for Approver {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Approver::Owner(_) => write!(f, "owner"),
+            Approver::Governance(_) => write!(f, "governance"),
+            Approver::Council => write!(f, "council motion"),
+        }
+    }
+}
+
+/// Work out who applies proposals on the diamond `state` describes
+pub fn approver(state: &DiamondState) -> Approver {
+    if state.has_council {
+        return Approver::Council;
+    }
+    match state.governance {
+        Some(governance) if state.governance_only => Approver::Governance(governance),
+        _ => Approver::Owner(state.owner),
+    }
+}
+
+/// Approve and apply proposal `id` (owner, or governance once governance-only)
+pub fn approve_instruction(router: &Pubkey, diamond: &Pubkey, state: &DiamondState, approver: &Pubkey, id: u64) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*diamond, false),
+        AccountMeta::new(CutProposal::find_address(router, diamond, id).0, false),
+        AccountMeta::new(*approver, true),
+    ];
+    accounts.extend(cut_tail(router, diamond, state));
+    instruction(router, APPROVE_CUT_DISCRIMINATOR, (), accounts)
+}
+
+/// Crank scheduled proposal `id` once due (anyone; takes any escrowed tip)
+pub fn execute_instruction(
+    router: &Pubkey,
+    diamond: &Pubkey,
+    state: &DiamondState,
+    executor: &Pubkey,
+    id: u64,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*diamond, false),
+        AccountMeta::new(CutProposal::find_address(router, diamond, id).0, false),
+        AccountMeta::new(*executor, true),
+    ];
+    accounts.extend(cut_tail(router, diamond, state));
+    instruction(router, EXECUTE_CUT_DISCRIMINATOR, (), accounts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use borsh::BorshDeserialize;
+    use serde_json::json;
+    
+    #[test]
+    fn test_cut_instructions_follow_the_state() {
+        let (router, diamond, authority) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut state = DiamondState::new(authority, 255);
+        let cuts = [FacetCut::add([1, 2, 3, 4], Pubkey::new_unique(), "counter", "increment"), FacetCut::remove([9, 9, 9, 9])];
+        
+        let ixs = cut_instructions(&router, &diamond, &state, &authority, &cuts).unwrap();
+        assert_eq!(ixs.len(), 2);
+        assert_eq!(ixs[0].accounts.len(), 2);
+        assert_eq!(&ixs[1].data[..8], &REMOVE_MODULE_DISCRIMINATOR);
+        assert_eq!(Selector::try_from_slice(&ixs[1].data[8..]).unwrap(), Selector::from([9, 9, 9, 9]));
+        
+        state.routing_digest = true;
+        state.selector_index = true;
+        state.audit_tree = Some(Pubkey::new_unique());
+        let ixs = cut_instructions(&router, &diamond, &state, &authority, &cuts[..1]).unwrap();
+        assert_eq!(ixs[0].accounts.len(), 2 + 2 + 4);
+        assert_eq!(ixs[0].accounts[2].pubkey, RoutingDigest::find_address(&router, &diamond).0);
+        assert_eq!(ixs[0].accounts[3].pubkey, SelectorIndex::find_address(&router, &diamond).0);
+        assert_eq!(ixs[0].accounts[4].pubkey, state.audit_tree.unwrap());
+        
+        let replace = FacetCut::replace([1, 2, 3, 4], Pubkey::new_unique(), "counter");
+        assert!(cut_instructions(&router, &diamond, &state, &authority, &[replace]).is_err());
+    }
+    
+    #[test]
+    fn test_propose_paid_by_the_rent_vault() {
+        let (router, diamond, governance) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let state = DiamondState::new(Pubkey::new_unique(), 255);
+        let cuts = [FacetCut::add([1, 2, 3, 4], Pubkey::new_unique(), "counter", "increment")];
+        
+        let (ix, _) = propose_instruction(&router, &diamond, &state, &governance, &governance, &cuts);
+        assert!(ix.accounts[3].is_signer);
+        let vault = rent_vault(&router, &diamond).0;
+        let (ix, _) = propose_instruction(&router, &diamond, &state, &governance, &vault, &cuts);
+        assert_eq!((ix.accounts[3].pubkey, ix.accounts[3].is_signer, ix.accounts[3].is_writable), (vault, false, true));
+    }
+    
+    #[test]
+    fn test_parse_cut() {
+        let module = Pubkey::new_unique();
+        let cut = parse_cut(&json!({
+            "action": "add",
+            "selector": "01020304",
+            "module": module.to_string(),
+            "module_name": "counter",
+            "function_name": "increment",
+            "flags": 2,
+        }))
+        .unwrap();
+        assert_eq!(cut, FacetCut { standard_accounts: 2, ..FacetCut::add([1, 2, 3, 4], module, "counter", "increment") });
+        assert_eq!(parse_cut(&json!({ "action": "remove", "selector": "0102" })).unwrap(), FacetCut::remove([1, 2]));
+        assert_eq!(parse_cut(&cut_json(&cut, 4)).unwrap(), cut);
+        assert_eq!(parse_cut(&cut_json(&FacetCut::remove([1, 2]), 2)).unwrap(), FacetCut::remove([1, 2]));
+        
+        assert!(parse_cut(&json!({ "action": "add", "selector": "01020304" })).is_err());
+        assert!(parse_cut(&json!({ "action": "remove", "selector": "010203" })).is_err());
+        assert!(parse_cut(&json!({ "action": "remove", "selector": "zz020304" })).is_err());
+        assert!(parse_cut(&json!({ "action": "move", "selector": "01020304" })).is_err());
+    }
+}
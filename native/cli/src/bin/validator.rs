@@ -0,0 +1,418 @@
+/*!
+ * Portability Validator
+ * End-to-end proof that the native router and counter facet work together
+ *
+ * Starts `solana-test-validator` with both programs preloaded, initializes a
+ * diamond, cuts the counter's selectors in, dispatches increments through
+ * the router, adds a co-authority that increments once more, hands the
+ * counter over to it and reads the counter back. Exits nonzero on any
+ * failure or mismatch.
+ *
+ * With `--anchor-so`, the Anchor program is preloaded too at `--anchor-id`
+ * (the program id it was built to declare), routes the same counter, and
+ * both routers' binary sizes and `increment` dispatch compute units are
+ * reported side by side. `--budget` then fails the run if the
+ * native router exceeds the ceilings in that file (see `native/budget.toml`;
+ * `native/bench.sh` builds everything and runs this), and `--record-budget`
+ * writes ceilings just above this run's measurements to a file.
+ *
+ * Usage:
+ *   diamond-validator [--router-so <path>] [--facet-so <path>] [--rpc-port <port>] [--increments <n>]
+ *                     [--anchor-so <path> --anchor-id <pubkey>] [--budget <path>] [--record-budget <path>]
+ *
+ * Build the programs first with `cargo build-sbf`; the .so paths default to
+ * target/deploy.
+ */
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use diamond_cli::{
+    budget::{anchor_discriminator, Budget, BudgetReport, Measurement},
+    keys::Keypair,
+    parse_flags,
+    rpc::RpcClient,
+};
+use diamond_router_native::{
+    diamond_state::{diamond_address, DiamondState},
+    selector::{encode_call, Selector},
+    ADD_MODULE_DISCRIMINATOR, DISPATCH_DISCRIMINATOR, INITIALIZE_DISCRIMINATOR,
+};
+use serde_json::json;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    message::Message,
+    pubkey::Pubkey,
+    system_program,
+};
+use std::path::PathBuf;
+use std::process::{Child, Command, ExitCode, Stdio};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+// Counter facet (native/facet). Mirrored rather than linked: both programs
+// export an `entrypoint` symbol, so they can't share one host binary.
+const FACET_ID: Pubkey = solana_program::pubkey!("FacetNativeExamp1e1111111111111111111111111");
+const INCREMENT_SELECTOR: [u8; 4] = [0x01, 0x02, 0x03, 0x04];
+const SET_AUTHORITY_SELECTOR: [u8; 4] = [0x15, 0x16, 0x17, 0x18];
+const SET_CO_AUTHORITIES_SELECTOR: [u8; 4] = [0x19, 0x1A, 0x1B, 0x1C];
+const COUNTER_SPACE: usize = 8 + 8 + 32 + 1 + 4 + 4 * 32;
+
+#[derive(BorshSerialize, BorshDeserialize)]
+struct Counter {
+    value: u64,
+    authority: Pubkey,
+    bump: u8,
+    co_authorities: Vec<Pubkey>,
+}
+
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Test validator that is killed when dropped
+struct Localnet {
+    child: Child,
+    ledger: PathBuf,
+}
+
+impl Drop for Localnet {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = std::fs::remove_dir_all(&self.ledger);
+    }
+}
+
+fn start_localnet(
+    router_so: &str,
+    facet_so: &str,
+    anchor: Option<(&Pubkey, &str)>,
+    rpc_port: u16,
+    counter: &Pubkey,
+    counter_data: &[u8],
+) -> Result<Localnet, String> {
+    let ledger = std::env::temp_dir().join(format!("diamond-validator-{}", std::process::id()));
+    std::fs::create_dir_all(&ledger).map_err(|e| e.to_string())?;
+    
+    // The counter facet has no init instruction, so its account is preloaded
+    let counter_file = ledger.join("counter.json");
+    let account = json!({
+        "pubkey": counter.to_string(),
+        "account": {
+            "lamports": 1_000_000_000u64,
+            "data": [base64_encode(counter_data), "base64"],
+            "owner": FACET_ID.to_string(),
+            "executable": false,
+            "rentEpoch": 0,
+            "space": counter_data.len(),
+        }
+    });
+    std::fs::write(&counter_file, account.to_string()).map_err(|e| e.to_string())?;
+    
+    let mut command = Command::new("solana-test-validator");
+    command
+        .args(["--reset", "--quiet", "--rpc-port", &rpc_port.to_string()])
+        .arg("--ledger")
+        .arg(ledger.join("ledger"))
+        .args(["--bpf-program", &diamond_router_native::id().to_string(), router_so])
+        .args(["--bpf-program", &FACET_ID.to_string(), facet_so])
+        .args(["--account", &counter.to_string()])
+        .arg(&counter_file);
+    if let Some((anchor_id, anchor_so)) = anchor {
+        command.args(["--bpf-program", &anchor_id.to_string(), anchor_so]);
+    }
+    let child = command
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("solana-test-validator: {}", e))?;
+    Ok(Localnet { child, ledger })
+}
+
+fn base64_encode(bytes: &[u8]) -> String {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    STANDARD.encode(bytes)
+}
+
+fn initialize_ix(owner: &Pubkey, state: &Pubkey, bump: u8) -> Instruction {
+    let mut data = INITIALIZE_DISCRIMINATOR.to_vec();
+    // InitData { owner, bump, initial_cuts: None }
+    (*owner, bump, None::<Vec<u8>>).serialize(&mut data).expect("borsh encode");
+    Instruction {
+        program_id: diamond_router_native::id(),
+        accounts: vec![
+            AccountMeta::new(*state, false),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(*owner, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data,
+    }
+}
+
+fn add_selector_ix(owner: &Pubkey, state: &Pubkey, selector: [u8; 4], name: &str) -> Instruction {
+    let mut data = ADD_MODULE_DISCRIMINATOR.to_vec();
+    // AddModuleData
+    (
+        "counter".to_string(),
+        FACET_ID,
+        Selector::from(selector),
+        name.to_string(),
+        false,
+        "counter".to_string(),
+        0u8,
+    )
+        .serialize(&mut data)
+        .expect("borsh encode");
+    Instruction {
+        program_id: diamond_router_native::id(),
+        accounts: vec![AccountMeta::new(*state, false), AccountMeta::new_readonly(*owner, true)],
+        data,
+    }
+}
+
+/// Dispatch a counter call signed by `signer`
+fn counter_ix(signer: &Pubkey, state: &Pubkey, counter: &Pubkey, selector: [u8; 4], args: &[u8]) -> Instruction {
+    let payload = encode_call(selector, 4, args);
+    let mut data = DISPATCH_DISCRIMINATOR.to_vec();
+    payload.serialize(&mut data).expect("borsh encode");
+    Instruction {
+        program_id: diamond_router_native::id(),
+        accounts: vec![
+            AccountMeta::new(*state, false),
+            AccountMeta::new_readonly(FACET_ID, false),
+            AccountMeta::new(*counter, false),
+            AccountMeta::new_readonly(*signer, true),
+        ],
+        data,
+    }
+}
+
+/// Anchor `initialize` of the diamond at `[b"diamond_state", owner]`
+fn anchor_initialize_ix(anchor_id: &Pubkey, owner: &Pubkey, state: &Pubkey) -> Instruction {
+    let mut data = anchor_discriminator("initialize").to_vec();
+    None::<Vec<u8>>.serialize(&mut data).expect("borsh encode");
+    Instruction {
+        program_id: *anchor_id,
+        accounts: vec![
+            AccountMeta::new(*state, false),
+            AccountMeta::new_readonly(*owner, false),
+            AccountMeta::new(*owner, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data,
+    }
+}
+
+fn anchor_add_facet_ix(anchor_id: &Pubkey, owner: &Pubkey, state: &Pubkey, selector: [u8; 4], name: &str) -> Instruction {
+    let mut data = anchor_discriminator("add_facet").to_vec();
+    (selector, FACET_ID, name.to_string(), false).serialize(&mut data).expect("borsh encode");
+    Instruction {
+        program_id: *anchor_id,
+        accounts: vec![AccountMeta::new(*state, false), AccountMeta::new_readonly(*owner, true)],
+        data,
+    }
+}
+
+/// Anchor `dispatch` of a counter call signed by `signer`
+fn anchor_counter_ix(
+    anchor_id: &Pubkey,
+    signer: &Pubkey,
+    state: &Pubkey,
+    counter: &Pubkey,
+    selector: [u8; 4],
+    args: &[u8],
+) -> Instruction {
+    let mut instruction = counter_ix(signer, state, counter, selector, args);
+    instruction.program_id = *anchor_id;
+    instruction.data[..8].copy_from_slice(&anchor_discriminator("dispatch"));
+    instruction
+}
+
+/// Compute units of `instruction`, paid for by `payer`
+fn dispatch_units(rpc: &RpcClient, payer: &Pubkey, instruction: Instruction) -> Result<u64, String> {
+    let simulation = rpc.simulate(&Message::new(&[instruction], Some(payer)))?;
+    if let Some(err) = simulation.err {
+        return Err(format!("dispatch failed in simulation: {} ({})", err, simulation.logs.join(" | ")));
+    }
+    simulation.units_consumed.ok_or_else(|| "simulation reported no compute units".to_string())
+}
+
+fn binary_bytes(path: &str) -> Option<u64> {
+    std::fs::metadata(path).map(|m| m.len()).ok()
+}
+
+/// Route the counter through the Anchor program too, then measure both
+/// routers dispatching the same `increment`
+fn measure(
+    rpc: &RpcClient,
+    owner: &Keypair,
+    signer: &Pubkey,
+    native_state: &Pubkey,
+    counter: &Pubkey,
+    router_so: &str,
+    (anchor_id, anchor_so): (&Pubkey, &str),
+) -> Result<BudgetReport, String> {
+    let (anchor_state, _) = Pubkey::find_program_address(&[b"diamond_state", owner.pubkey().as_ref()], anchor_id);
+    rpc.send_and_confirm(
+        &[
+            anchor_initialize_ix(anchor_id, &owner.pubkey(), &anchor_state),
+            anchor_add_facet_ix(anchor_id, &owner.pubkey(), &anchor_state, INCREMENT_SELECTOR, "increment"),
+        ],
+        &[owner],
+        CONFIRM_TIMEOUT,
+    )?;
+    
+    let amount = 1u64.to_le_bytes();
+    let native = counter_ix(signer, native_state, counter, INCREMENT_SELECTOR, &amount);
+    let anchor = anchor_counter_ix(anchor_id, signer, &anchor_state, counter, INCREMENT_SELECTOR, &amount);
+    Ok(BudgetReport {
+        anchor: Measurement {
+            binary_bytes: binary_bytes(anchor_so),
+            dispatch_cu: Some(dispatch_units(rpc, &owner.pubkey(), anchor)?),
+        },
+        native: Measurement {
+            binary_bytes: binary_bytes(router_so),
+            dispatch_cu: Some(dispatch_units(rpc, &owner.pubkey(), native)?),
+        },
+    })
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let flags = parse_flags(args)?;
+    let flag = |name: &str, default: &str| flags.get(name).cloned().unwrap_or_else(|| default.to_string());
+    let router_so = flag("router-so", "target/deploy/diamond_router_native.so");
+    let facet_so = flag("facet-so", "target/deploy/example_facet_native.so");
+    let rpc_port: u16 = flag("rpc-port", "8899").parse().map_err(|_| "--rpc-port: invalid port")?;
+    let increments: u64 = flag("increments", "3").parse().map_err(|_| "--increments: invalid count")?;
+    let anchor_so = flags.get("anchor-so");
+    let anchor_id: Option<Pubkey> = match (flags.get("anchor-id"), anchor_so) {
+        (Some(id), _) => Some(id.parse().map_err(|_| format!("--anchor-id: invalid pubkey {}", id))?),
+        (None, Some(_)) => return Err("--anchor-so needs --anchor-id".to_string()),
+        (None, None) => None,
+    };
+    let anchor = anchor_id.as_ref().zip(anchor_so.map(String::as_str));
+    let budget = match flags.get("budget") {
+        Some(path) => Some(Budget::parse(&std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?)?),
+        None => None,
+    };
+    let record = flags.get("record-budget");
+    if (budget.is_some() || record.is_some()) && anchor_so.is_none() {
+        return Err("--budget and --record-budget need --anchor-so".to_string());
+    }
+    
+    let owner = Keypair::generate()?;
+    let counter = Pubkey::create_with_seed(&owner.pubkey(), "counter", &FACET_ID).map_err(|e| e.to_string())?;
+    let mut counter_data = Counter { value: 0, authority: owner.pubkey(), bump: 0, co_authorities: Vec::new() }
+        .try_to_vec()
+        .map_err(|e| e.to_string())?;
+    counter_data.resize(COUNTER_SPACE, 0);
+    
+    println!("Starting solana-test-validator on port {}", rpc_port);
+    let _localnet = start_localnet(&router_so, &facet_so, anchor, rpc_port, &counter, &counter_data)?;
+    let rpc = RpcClient::new(&format!("http://127.0.0.1:{}", rpc_port))?;
+    let started = Instant::now();
+    while !rpc.is_healthy() {
+        if started.elapsed() > Duration::from_secs(60) {
+            return Err("validator did not become healthy".to_string());
+        }
+        sleep(Duration::from_millis(500));
+    }
+    
+    rpc.airdrop(&owner.pubkey(), 10_000_000_000, CONFIRM_TIMEOUT)?;
+    let (state, bump) = diamond_address(&diamond_router_native::id(), &owner.pubkey(), None);
+    
+    rpc.send_and_confirm(&[initialize_ix(&owner.pubkey(), &state, bump)], &[&owner], CONFIRM_TIMEOUT)?;
+    println!("✅ Diamond initialized: {}", state);
+    
+    let selectors = [
+        (INCREMENT_SELECTOR, "increment"),
+        (SET_AUTHORITY_SELECTOR, "set_authority"),
+        (SET_CO_AUTHORITIES_SELECTOR, "set_co_authorities"),
+    ];
+    for (selector, name) in selectors {
+        rpc.send_and_confirm(&[add_selector_ix(&owner.pubkey(), &state, selector, name)], &[&owner], CONFIRM_TIMEOUT)?;
+    }
+    let account = rpc.get_account(&state)?.ok_or("diamond state missing")?;
+    let diamond = DiamondState::deserialize(&mut &account.data[..]).map_err(|e| e.to_string())?;
+    if selectors.iter().any(|(selector, _)| diamond.get_module_by_selector(*selector) != Some(FACET_ID)) {
+        return Err("cuts did not register the counter selectors".to_string());
+    }
+    println!("✅ Cuts applied: {} counter selectors -> {}", selectors.len(), FACET_ID);
+    
+    let mut expected = 0u64;
+    for amount in 1..=increments {
+        rpc.send_and_confirm(
+            &[counter_ix(&owner.pubkey(), &state, &counter, INCREMENT_SELECTOR, &amount.to_le_bytes())],
+            &[&owner],
+            CONFIRM_TIMEOUT,
+        )?;
+        expected += amount;
+    }
+    let read_counter = || -> Result<Counter, String> {
+        let account = rpc.get_account(&counter)?.ok_or("counter account missing")?;
+        Counter::deserialize(&mut &account.data[..]).map_err(|e| e.to_string())
+    };
+    let value = read_counter()?.value;
+    if value != expected {
+        return Err(format!("counter is {}, expected {}", value, expected));
+    }
+    println!("✅ {} dispatches through the router: counter = {}", increments, value);
+    
+    // The owner pays; the co-authority only signs its own dispatch
+    let co_authority = Keypair::generate()?;
+    let co_authorities = vec![co_authority.pubkey()].try_to_vec().map_err(|e| e.to_string())?;
+    rpc.send_and_confirm(
+        &[counter_ix(&owner.pubkey(), &state, &counter, SET_CO_AUTHORITIES_SELECTOR, &co_authorities)],
+        &[&owner],
+        CONFIRM_TIMEOUT,
+    )?;
+    rpc.send_and_confirm(
+        &[counter_ix(&co_authority.pubkey(), &state, &counter, INCREMENT_SELECTOR, &1u64.to_le_bytes())],
+        &[&owner, &co_authority],
+        CONFIRM_TIMEOUT,
+    )?;
+    rpc.send_and_confirm(
+        &[counter_ix(&owner.pubkey(), &state, &counter, SET_AUTHORITY_SELECTOR, co_authority.pubkey().as_ref())],
+        &[&owner],
+        CONFIRM_TIMEOUT,
+    )?;
+    let handed_over = read_counter()?;
+    if handed_over.value != expected + 1 || handed_over.authority != co_authority.pubkey() {
+        return Err(format!(
+            "counter is {} under {}, expected {} under the co-authority",
+            handed_over.value,
+            handed_over.authority,
+            expected + 1
+        ));
+    }
+    println!("✅ Co-authority incremented and took over: counter = {}", handed_over.value);
+    
+    if let Some(anchor) = anchor {
+        let report = measure(&rpc, &owner, &co_authority.pubkey(), &state, &counter, &router_so, anchor)?;
+        println!("\n{}", report);
+        if let Some(path) = record {
+            let recorded = Budget::recorded(&report.native).ok_or("nothing to record: the native router wasn't fully measured")?;
+            std::fs::write(path, recorded.to_toml(&report)).map_err(|e| format!("{}: {}", path, e))?;
+            println!("📝 Recorded budget in {} ({} bytes, {} CU)", path, recorded.router_max_bytes, recorded.dispatch_max_cu);
+        }
+        if let Some(budget) = budget {
+            let overruns = report.overruns(&budget);
+            if !overruns.is_empty() {
+                return Err(format!("over budget: {}", overruns.join("; ")));
+            }
+            println!("✅ Within budget ({} bytes, {} CU)", budget.router_max_bytes, budget.dispatch_max_cu);
+        }
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match run(&args) {
+        Ok(()) => {
+            println!("\n💎 Native router and facet validated end to end");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
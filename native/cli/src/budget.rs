@@ -0,0 +1,161 @@
+/*!
+ * Size and compute budget
+ *
+ * Side-by-side binary size and dispatch compute units of the Anchor program
+ * and the native router, as measured by `diamond-validator --anchor-so`,
+ * and the ceilings the native router must stay under (`native/budget.toml`).
+ */
+
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+/// Ceilings for the native router
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Budget {
+    /// Deployed router binary
+    pub router_max_bytes: u64,
+    /// One counter `increment` through `dispatch`, facet CPI included
+    pub dispatch_max_cu: u64,
+}
+
+impl Budget {
+    /// Room a recorded budget leaves above the measurement, in percent
+    pub const HEADROOM_PERCENT: u64 = 5;
+    
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let value: Value = toml::from_str(text).map_err(|e| e.to_string())?;
+        let field = |name: &str| value[name].as_u64().ok_or_else(|| format!("budget: missing {}", name));
+        Ok(Self { router_max_bytes: field("router_max_bytes")?, dispatch_max_cu: field("dispatch_max_cu")? })
+    }
+    
+    /// Ceilings just above what `native` measured, if it was fully measured
+    pub fn recorded(native: &Measurement) -> Option<Self> {
+        let ceiling = |value: u64| value + (value * Self::HEADROOM_PERCENT).div_ceil(100);
+        Some(Self {
+            router_max_bytes: ceiling(native.binary_bytes?),
+            dispatch_max_cu: ceiling(native.dispatch_cu?),
+        })
+    }
+    
+    /// `budget.toml` holding these ceilings, recorded from `report`
+    pub fn to_toml(&self, report: &BudgetReport) -> String {
+        let measured = |value: Option<u64>| value.map_or("-".to_string(), |v| v.to_string());
+        format!(
+            "# Ceilings for the native router, checked by `bench.sh`\n\
+             # (`diamond-validator --anchor-so ... --budget budget.toml`).\n\
+             #\n\
+             # Recorded by `bench.sh --record`: {}% above the last measured run.\n\
+             # Re-record after an intended size or compute change.\n\
+             \n\
+             # Deployed router binary, target/deploy/diamond_router_native.so\n\
+             # (measured {})\n\
+             router_max_bytes = {}\n\
+             \n\
+             # One counter `increment` through `dispatch`, facet CPI included\n\
+             # (measured {})\n\
+             dispatch_max_cu = {}\n",
+            Self::HEADROOM_PERCENT,
+            measured(report.native.binary_bytes),
+            self.router_max_bytes,
+            measured(report.native.dispatch_cu),
+            self.dispatch_max_cu
+        )
+    }
+}
+
+/// What was measured of one program (`None` when it couldn't be)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Measurement {
+    pub binary_bytes: Option<u64>,
+    pub dispatch_cu: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BudgetReport {
+    pub anchor: Measurement,
+    pub native: Measurement,
+}
+
+impl BudgetReport {
+    /// Ways the native router misses `budget`; an unmeasured value misses it
+    pub fn overruns(&self, budget: &Budget) -> Vec<String> {
+        let check = |what: &str, measured: Option<u64>, max: u64| match measured {
+            None => Some(format!("{} not measured", what)),
+            Some(value) if value > max => Some(format!("{} is {}, budget {}", what, value, max)),
+            Some(_) => None,
+        };
+        [
+            check("router binary (bytes)", self.native.binary_bytes, budget.router_max_bytes),
+            check("dispatch (CU)", self.native.dispatch_cu, budget.dispatch_max_cu),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+}
+
+impl fmt::Display for BudgetReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cell = |value: Option<u64>| value.map_or("-".to_string(), |v| v.to_string());
+        writeln!(f, "{:<8} {:>12} {:>12}", "program", "binary (B)", "dispatch CU")?;
+        for (name, measurement) in [("anchor", &self.anchor), ("native", &self.native)] {
+            writeln!(f, "{:<8} {:>12} {:>12}", name, cell(measurement.binary_bytes), cell(measurement.dispatch_cu))?;
+        }
+        if let (Some(anchor), Some(native)) = (self.anchor.binary_bytes, self.native.binary_bytes) {
+            writeln!(f, "native binary is {:.2}x the Anchor one", native as f64 / anchor as f64)?;
+        }
+        Ok(())
+    }
+}
+
+/// Anchor instruction discriminator of `name`
+pub fn anchor_discriminator(name: &str) -> [u8; 8] {
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&Sha256::digest(format!("global:{}", name).as_bytes())[..8]);
+    discriminator
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_overruns() {
+        let budget = Budget::parse("router_max_bytes = 1000\ndispatch_max_cu = 50\n").unwrap();
+        assert_eq!(budget, Budget { router_max_bytes: 1000, dispatch_max_cu: 50 });
+        assert!(Budget::parse("router_max_bytes = 1000\n").is_err());
+        
+        let mut report = BudgetReport {
+            anchor: Measurement { binary_bytes: Some(2000), dispatch_cu: None },
+            native: Measurement { binary_bytes: Some(1000), dispatch_cu: Some(50) },
+        };
+        assert!(report.overruns(&budget).is_empty());
+        assert!(report.to_string().contains("native binary is 0.50x"));
+        
+        report.native = Measurement { binary_bytes: Some(1001), dispatch_cu: None };
+        assert_eq!(
+            report.overruns(&budget),
+            vec!["router binary (bytes) is 1001, budget 1000".to_string(), "dispatch (CU) not measured".to_string()]
+        );
+    }
+    
+    #[test]
+    fn test_recorded_budget_round_trips() {
+        let report = BudgetReport {
+            anchor: Measurement::default(),
+            native: Measurement { binary_bytes: Some(100_000), dispatch_cu: Some(2_001) },
+        };
+        let budget = Budget::recorded(&report.native).unwrap();
+        assert_eq!(budget, Budget { router_max_bytes: 105_000, dispatch_max_cu: 2_102 });
+        assert_eq!(Budget::parse(&budget.to_toml(&report)), Ok(budget));
+        assert!(report.overruns(&budget).is_empty());
+        assert_eq!(Budget::recorded(&Measurement { binary_bytes: Some(1), dispatch_cu: None }), None);
+    }
+    
+    #[test]
+    fn test_anchor_discriminator() {
+        // sha256("global:initialize")[..8], as Anchor derives it
+        assert_eq!(anchor_discriminator("initialize"), [0xaf, 0xaf, 0x6d, 0x1f, 0x0d, 0x98, 0x9b, 0xed]);
+    }
+}
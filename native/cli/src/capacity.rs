@@ -0,0 +1,99 @@
+/*!
+ * `diamond capacity`
+ *
+ * Asks the router for the state account's capacity (via simulation of the
+ * `get_capacity` built-in) and flags tables close to full, so a cut doesn't
+ * fail on a capacity limit halfway through an upgrade.
+ */
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use diamond_router_native::{
+    builtins::CAPACITY_SELECTOR,
+    diamond_loupe::{Capacity, Usage},
+    DISPATCH_DISCRIMINATOR,
+};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    message::Message,
+    pubkey::Pubkey,
+};
+
+use crate::errors;
+use crate::rpc::{RpcClient, RpcResult};
+
+/// Percent used at which a table is reported as nearly full
+pub const WARN_PERCENT: u8 = 80;
+
+/// Router `dispatch` instruction calling `get_capacity()`
+pub fn capacity_instruction(router: &Pubkey, diamond_state: &Pubkey) -> Instruction {
+    let mut data = DISPATCH_DISCRIMINATOR.to_vec();
+    // Writing into a Vec cannot fail
+    CAPACITY_SELECTOR.to_vec().serialize(&mut data).expect("borsh encode");
+    
+    Instruction {
+        program_id: *router,
+        accounts: vec![
+            AccountMeta::new(*diamond_state, false),
+            AccountMeta::new_readonly(*router, false),
+        ],
+        data,
+    }
+}
+
+/// Tables at or over `WARN_PERCENT`
+pub fn warnings(capacity: &Capacity) -> Vec<&'static str> {
+    [("selectors", capacity.selectors), ("modules", capacity.modules), ("admins", capacity.admins)]
+        .into_iter()
+        .filter(|(_, usage)| usage.percent() >= WARN_PERCENT)
+        .map(|(name, _)| name)
+        .collect()
+}
+
+fn row(name: &str, usage: Usage) -> String {
+    format!("  {:<10} {:>3}/{:<3} {:>3}%", name, usage.used, usage.max, usage.percent())
+}
+
+/// Print the diamond's capacity; returns false if any table is nearly full
+pub fn run(rpc: &RpcClient, router: &Pubkey, diamond_state: &Pubkey, payer: &Pubkey) -> RpcResult<bool> {
+    let ix = capacity_instruction(router, diamond_state);
+    let simulation = rpc.simulate(&Message::new(&[ix], Some(payer)))?;
+    if let Some(err) = &simulation.err {
+        return Err(format!("get_capacity failed: {}", errors::decode(router, err, &simulation.logs)));
+    }
+    let mut bytes = simulation.return_data.as_deref().ok_or("get_capacity returned no data")?;
+    let capacity = Capacity::deserialize(&mut bytes).map_err(|e| e.to_string())?;
+    
+    println!("Diamond {}", diamond_state);
+    println!("{}", row("selectors", capacity.selectors));
+    println!("{}", row("modules", capacity.modules));
+    println!("{}", row("admins", capacity.admins));
+    println!("  {} bytes free", capacity.bytes_free);
+    
+    let full = warnings(&capacity);
+    for name in &full {
+        println!("⚠️  {} at {}% or more; free entries before the next cut", name, WARN_PERCENT);
+    }
+    Ok(full.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_capacity_instruction_and_warnings() {
+        let (router, state) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let ix = capacity_instruction(&router, &state);
+        assert_eq!(&ix.data[..8], &DISPATCH_DISCRIMINATOR);
+        assert_eq!(Vec::<u8>::try_from_slice(&ix.data[8..]).unwrap(), CAPACITY_SELECTOR);
+        assert!(ix.accounts[0].is_writable);
+        
+        let capacity = Capacity {
+            selectors: Usage { used: 40, max: 50 },
+            modules: Usage { used: 3, max: 20 },
+            admins: Usage { used: 9, max: 10 },
+            bytes_free: 100,
+        };
+        assert_eq!(warnings(&capacity), ["selectors", "admins"]);
+    }
+}
@@ -0,0 +1,557 @@
+/*!
+ * `diamond decode`
+ *
+ * Turns a transaction (a confirmed signature or a base64 wire transaction)
+ * into readable router instructions: the discriminator's name, the decoded
+ * payload, every cut with its selector and target, and the built-in or
+ * facet function a dispatch calls. Confirmed transactions also get their
+ * router events and error. Signers checking a queued transaction see what
+ * it does instead of its bytes.
+ *
+ * Account lookups (the diamond state for selector names, a proposal's cuts
+ * for approvals) are optional; without them the decode falls back to raw
+ * selectors.
+ */
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use borsh::BorshDeserialize;
+use diamond_router_native::{
+    builtins::{BUILTIN_SELECTORS, RESERVED_PREFIX},
+    diamond_config::schema::ROLE_EXTRA,
+    diamond_council::CouncilAction,
+    diamond_cut::FacetCut,
+    diamond_proposal::{registration_cuts, CutProposal, RegistrationManifest},
+    diamond_state::{BreakerMetric, DiamondState, HealthRegistry, OracleGuard, PauseWindow, SelectorMapping},
+    events::{DiamondEvent, EVENT_PREFIX},
+    selector::Selector,
+    *,
+};
+use serde_json::json;
+use diamond_router_native::compress;
+use solana_program::{
+    message::VersionedMessage, pubkey::Pubkey, syscalls::MAX_CPI_INSTRUCTION_DATA_LEN,
+    system_instruction::SystemInstruction, system_program,
+};
+use std::fmt;
+
+use crate::jito::decode_short_u16;
+use crate::rpc::{RpcClient, RpcResult};
+
+/// Account data by address, when it can be fetched
+pub type Lookup<'a> = &'a dyn Fn(&Pubkey) -> Option<Vec<u8>>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedInstruction {
+    pub program: Pubkey,
+    /// `router::<instruction>`, or what the other program's instruction is
+    pub name: String,
+    pub details: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedTransaction {
+    /// First signature, when the transaction is signed
+    pub signature: Option<String>,
+    pub signers: Vec<Pubkey>,
+    pub instructions: Vec<DecodedInstruction>,
+    /// Router events from the logs of a confirmed transaction
+    pub events: Vec<DiamondEvent>,
+    pub error: Option<String>,
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Selector at the diamond's width, or trimmed to the narrowest width it fits
+fn selector_hex(selector: &Selector, width: Option<u8>) -> String {
+    let width = width.unwrap_or_else(|| [2, 4].into_iter().find(|w| selector.fits(*w)).unwrap_or(8));
+    hex(selector.as_bytes(width))
+}
+
+/// `namespace::function`, or just `function` in the global namespace
+fn qualified(namespace: &str, function: &str) -> String {
+    match namespace {
+        "" => function.to_string(),
+        namespace => format!("{}::{}", namespace, function),
+    }
+}
+
+fn cut_line(cut: &FacetCut, width: Option<u8>) -> String {
+    format!(
+        "{:?} {} {} -> {} ({}){}",
+        cut.action,
+        selector_hex(&cut.selector, width),
+        qualified(&cut.namespace, &cut.function_name),
+        cut.module_name,
+        cut.module,
+        if cut.is_immutable { " immutable" } else { "" }
+    )
+}
+
+fn mapping_line(mapping: &SelectorMapping) -> String {
+    format!("{} -> {}", mapping.qualified_name(), mapping.module)
+}
+
+/// A Borsh value followed by an optional `expected_version` (cut instructions)
+fn versioned<T: BorshDeserialize>(data: &[u8]) -> Option<(T, Option<u64>)> {
+    let mut rest = data;
+    let value = T::deserialize(&mut rest).ok()?;
+    match rest.len() {
+        0 => Some((value, None)),
+        8 => Some((value, Some(u64::from_le_bytes(rest.try_into().ok()?)))),
+        _ => None,
+    }
+}
+
+fn state_of(lookup: Lookup, address: Option<&Pubkey>) -> Option<DiamondState> {
+    DiamondState::deserialize(&mut &lookup(address?)?[..]).ok()
+}
+
+/// What a dispatched payload calls: a built-in (with its arguments) or a facet function
+fn describe_call(ix_data: &[u8], state: Option<&DiamondState>) -> Vec<String> {
+    if ix_data.starts_with(&RESERVED_PREFIX) {
+        if let Some((_, name)) = BUILTIN_SELECTORS.iter().find(|(selector, _)| ix_data.starts_with(selector)) {
+            let short = name.strip_prefix("diamond::").unwrap_or(name);
+            let mut lines = vec![format!("built-in {}", name)];
+            lines.extend(arguments(short, &ix_data[4..]).unwrap_or_else(|| raw(&ix_data[4..])));
+            return lines;
+        }
+    }
+    let Some(state) = state else {
+        return vec![format!("facet call {} ({} bytes; diamond state unavailable)", hex(ix_data), ix_data.len())];
+    };
+    let width = state.selector_width;
+    match Selector::read(ix_data, width) {
+        Some(selector) => match state.resolve_mapping(selector) {
+            Some(mapping) => vec![
+                format!("facet call {} {}", selector_hex(&selector, Some(width)), mapping_line(mapping)),
+                format!("args ({} bytes): {}", ix_data.len() - width as usize, hex(&ix_data[width as usize..])),
+            ],
+            None => vec![format!("facet call {} (unrouted selector)", selector_hex(&selector, Some(width)))],
+        },
+        None => vec![format!("payload shorter than the {}-byte selector", width)],
+    }
+}
+
+fn raw(data: &[u8]) -> Vec<String> {
+    match data {
+        [] => Vec::new(),
+        data => vec![format!("data ({} bytes): {}", data.len(), hex(data))],
+    }
+}
+
+fn pubkeys(keys: &[Pubkey]) -> String {
+    keys.iter().map(Pubkey::to_string).collect::<Vec<_>>().join(", ")
+}
+
+fn optional(key: Option<Pubkey>) -> String {
+    key.map_or_else(|| "none".to_string(), |key| key.to_string())
+}
+
+/// Payload of an instruction (or built-in) that takes plain Borsh arguments
+fn arguments(name: &str, data: &[u8]) -> Option<Vec<String>> {
+    let line = match name {
+        "add_admin" | "set_pause_authority" => Pubkey::try_from_slice(data).ok()?.to_string(),
+        "set_admins" | "remove_admins" => pubkeys(&Vec::<Pubkey>::try_from_slice(data).ok()?),
+        "set_governance" | "transfer_ownership" => optional(Option::<Pubkey>::try_from_slice(data).ok()?),
+        "pause" => {
+            let (paused, expiry) = <(bool, Option<u64>)>::try_from_slice(data).ok()?;
+            format!("paused: {}, expiry slot: {:?}", paused, expiry)
+        }
+        "set_pause_scopes" => {
+            let (dispatch, cut, expiry) = <(bool, bool, Option<u64>)>::try_from_slice(data).ok()?;
+            format!("dispatch paused: {}, cuts paused: {}, expiry slot: {:?}", dispatch, cut, expiry)
+        }
+        "set_unpause_quorum" => match u8::try_from_slice(data).ok()? {
+            0 => "any pause key may unpause".to_string(),
+            quorum => format!("lifting a pause needs {} approvers", quorum),
+        },
+        "schedule_pause" | "cancel_pause_window" => {
+            let window = PauseWindow::try_from_slice(data).ok()?;
+            format!("maintenance window: slots {}..{}", window.start_slot, window.end_slot)
+        }
+        "schedule_cut" => {
+            let (eta, tip) = <(u64, u64)>::try_from_slice(data).ok()?;
+            format!("eta slot: {}, tip: {} lamports", eta, tip)
+        }
+        "set_tenant" => {
+            let (namespace, tenant) = <(String, Option<Pubkey>)>::try_from_slice(data).ok()?;
+            format!("namespace {:?} -> {}", namespace, optional(tenant))
+        }
+        "fund_vault" | "withdraw_vault" => format!("{} lamports", u64::try_from_slice(data).ok()?),
+        "sweep_token" => format!("creator: {}", Pubkey::try_from_slice(data).ok()?),
+        "migrate_from_anchor" => format!("close old account: {}", bool::try_from_slice(data).ok()?),
+        "propose_motion" => format!("{:?}", CouncilAction::try_from_slice(data).ok()?),
+        "resolve_selector" => selector_hex(&Selector::try_from_slice(data).ok()?, None),
+        "tags::set" => {
+            let (selector, tags) = <(Selector, Vec<String>)>::try_from_slice(data).ok()?;
+            format!("{} tags: {:?}", selector_hex(&selector, None), tags)
+        }
+        "selectors_by_tag" => format!("tag {:?}", String::try_from_slice(data).ok()?),
+        "tags::set_paused" => {
+            let (tag, paused) = <(String, bool)>::try_from_slice(data).ok()?;
+            format!("tag {:?} paused: {}", tag, paused)
+        }
+        "tags::remove_all" => {
+            let (tag, version) = versioned::<String>(data)?;
+            let line = format!("remove every selector tagged {:?}", tag);
+            return Some(std::iter::once(line).chain(version_line(version)).collect());
+        }
+        "health::set_registry" => match Option::<HealthRegistry>::try_from_slice(data).ok()? {
+            Some(registry) => format!("honor health registry {} from severity {}", registry.program, registry.min_severity),
+            None => "stop honoring the health registry".to_string(),
+        },
+        "oracle::set_guard" => {
+            let guard = OracleGuard::try_from_slice(data).ok()?;
+            match guard.feed == Pubkey::default() {
+                true => format!("{} oracle guard removed", selector_hex(&guard.selector, None)),
+                false => format!(
+                    "{} gated on feed {} (max age {}s, max confidence {} bps)",
+                    selector_hex(&guard.selector, None),
+                    guard.feed,
+                    guard.max_age_secs,
+                    guard.max_confidence_bps
+                ),
+            }
+        }
+        "breaker::set" => {
+            let (selector, metric, threshold, window) = <(Selector, BreakerMetric, u32, u64)>::try_from_slice(data).ok()?;
+            match threshold {
+                0 => format!("{} {:?} breaker removed", selector_hex(&selector, None), metric),
+                _ => format!("pause {} after {} {:?} within {} slots", selector_hex(&selector, None), threshold, metric, window),
+            }
+        }
+        "break_glass::schedule" => match <(Selector, Option<Pubkey>)>::try_from_slice(data).ok()? {
+            (selector, Some(module)) => format!("BREAK GLASS: re-point {} to {}", selector_hex(&selector, None), module),
+            (selector, None) => format!("BREAK GLASS: remove {}", selector_hex(&selector, None)),
+        },
+        "accept_ownership" | "enable_governance_only" | "maintain" | "approve_motion" | "execute_motion"
+        | "sweep_lamports" | "break_glass::execute" | "break_glass::cancel" => {
+            return data.is_empty().then(Vec::new);
+        }
+        _ => return None,
+    };
+    Some(vec![line])
+}
+
+fn version_line(version: Option<u64>) -> Option<String> {
+    version.map(|v| format!("expects state version {}", v))
+}
+
+/// Decode one router instruction; `accounts` are its account keys in order
+pub fn decode_router_instruction(accounts: &[Pubkey], data: &[u8], lookup: Lookup) -> (String, Vec<String>) {
+    let Some((_, name)) = data.get(..8).and_then(|d| INSTRUCTIONS.iter().find(|(disc, _)| disc[..] == *d)) else {
+        return ("unknown instruction".to_string(), raw(data));
+    };
+    let args = &data[8..];
+    let state = || state_of(lookup, accounts.first());
+    let width = || state().map(|s| s.selector_width);
+    let details = match *name {
+        "dispatch" | "try_dispatch" | "dispatch_fast" | "dispatch_indexed" => {
+            Vec::<u8>::try_from_slice(args).ok().map(|ix| describe_call(&ix, state().as_ref()))
+        }
+        "dispatch_traced" => <([u8; 16], Vec<u8>)>::try_from_slice(args).ok().map(|(trace, ix)| {
+            let mut lines = vec![format!("trace id {}", hex(&trace))];
+            lines.extend(describe_call(&ix, state().as_ref()));
+            lines
+        }),
+        "dispatch_signed" => <(Vec<u8>, Vec<u8>)>::try_from_slice(args).ok().map(|(seed, ix)| {
+            let mut lines = vec![format!("facet signer seed {}", hex(&seed))];
+            lines.extend(describe_call(&ix, state().as_ref()));
+            lines
+        }),
+        "dispatch_compressed" => Vec::<u8>::try_from_slice(args).ok().map(|frame| {
+            match compress::decompress(&frame, MAX_CPI_INSTRUCTION_DATA_LEN as usize) {
+                Some(ix) => {
+                    let mut lines = vec![format!("compressed payload ({} -> {} bytes)", frame.len(), ix.len())];
+                    lines.extend(describe_call(&ix, state().as_ref()));
+                    lines
+                }
+                None => vec![format!("malformed compressed payload: {}", hex(&frame))],
+            }
+        }),
+        "dispatch_checked" => <([u8; 8], Vec<u8>)>::try_from_slice(args).ok().map(|(schema, ix)| {
+            let mut lines = vec![format!("schema hash {}", hex(&schema))];
+            lines.extend(describe_call(&ix, state().as_ref()));
+            lines
+        }),
+        "dispatch_mapped" => <(Vec<u8>, Vec<u8>)>::try_from_slice(args).ok().map(|(roles, ix)| {
+            let roles: Vec<String> = roles
+                .iter()
+                .map(|&r| if r == ROLE_EXTRA { "extra".to_string() } else { r.to_string() })
+                .collect();
+            let mut lines = vec![format!("account roles [{}]", roles.join(", "))];
+            lines.extend(describe_call(&ix, state().as_ref()));
+            lines
+        }),
+        "dispatch_proven" => {
+            <(SelectorMapping, u16, Vec<[u8; 32]>, Vec<u8>)>::try_from_slice(args).ok().map(|(mapping, index, proof, ix)| {
+                vec![
+                    format!("proven mapping #{} {} ({} proof nodes)", index, mapping_line(&mapping), proof.len()),
+                    format!("payload ({} bytes): {}", ix.len(), hex(&ix)),
+                ]
+            })
+        }
+        "initialize" => {
+            let mut rest = args;
+            <(Pubkey, u8, Option<Vec<FacetCut>>)>::deserialize(&mut rest).ok().map(|(owner, bump, cuts)| {
+                let mut lines = vec![format!("owner {} (bump {})", owner, bump)];
+                if let Some(width) = rest.first() {
+                    lines.push(format!("selector width {}", width));
+                }
+                let width = rest.first().copied();
+                lines.extend(cuts.unwrap_or_default().iter().map(|cut| cut_line(cut, width)));
+                lines
+            })
+        }
+        "add_module" => {
+            versioned::<(String, Pubkey, Selector, String, bool, String, u8)>(args).map(|(add, version)| {
+                let (module_name, module, selector, function_name, is_immutable, namespace, _) = add;
+                let mut lines = vec![format!(
+                    "Add {} {} -> {} ({}){}",
+                    selector_hex(&selector, width()),
+                    qualified(&namespace, &function_name),
+                    module_name,
+                    module,
+                    if is_immutable { " immutable" } else { "" }
+                )];
+                lines.extend(version_line(version));
+                lines
+            })
+        }
+        "remove_module" => versioned::<Selector>(args).map(|(selector, version)| {
+            let state = state();
+            let target = state.as_ref().and_then(|s| s.get_mapping(selector)).map(mapping_line);
+            let mut lines = vec![format!(
+                "Remove {}{}",
+                selector_hex(&selector, state.as_ref().map(|s| s.selector_width)),
+                target.map(|t| format!(" ({})", t)).unwrap_or_default()
+            )];
+            lines.extend(version_line(version));
+            lines
+        }),
+        "tenant_cut" => versioned::<(String, Vec<FacetCut>)>(args).map(|((namespace, cuts), version)| {
+            let width = width();
+            let mut lines = vec![format!("tenant namespace {:?}", namespace)];
+            lines.extend(cuts.iter().map(|cut| cut_line(cut, width)));
+            lines.extend(version_line(version));
+            lines
+        }),
+        "allocate_selector" => versioned::<(String, String, String, Pubkey, bool, u8)>(args).map(|(alloc, version)| {
+            let (namespace, signature, module_name, module, is_immutable, _) = alloc;
+            let mut lines = vec![format!(
+                "Allocate {} -> {} ({}){}",
+                qualified(&namespace, &signature),
+                module_name,
+                module,
+                if is_immutable { " immutable" } else { "" }
+            )];
+            lines.extend(version_line(version));
+            lines
+        }),
+        "propose_cut" => Vec::<FacetCut>::try_from_slice(args).ok().map(|cuts| {
+            let width = width();
+            cuts.iter().map(|cut| cut_line(cut, width)).collect()
+        }),
+        "request_registration" => RegistrationManifest::try_from_slice(args).ok().map(|manifest| {
+            let width = width();
+            let facet = accounts.get(2).copied().unwrap_or_default();
+            let mut lines: Vec<String> = registration_cuts(&facet, &manifest).iter().map(|cut| cut_line(cut, width)).collect();
+            lines.extend(manifest.functions.iter().filter(|f| f.return_schema != [0; 8]).map(|f| {
+                format!("{} returns schema {:02x?}", f.function_name, f.return_schema)
+            }));
+            lines
+        }),
+        "approve_cut" | "execute_cut" | "schedule_cut" | "describe_proposal" => {
+            let proposal = accounts
+                .get(1)
+                .and_then(lookup)
+                .and_then(|data| CutProposal::deserialize(&mut &data[..]).ok());
+            let mut lines = arguments(name, args).unwrap_or_default();
+            match proposal {
+                Some(proposal) => {
+                    let width = width();
+                    lines.push(format!(
+                        "proposal {} by {}{}",
+                        proposal.id,
+                        proposal.proposer,
+                        if proposal.executed { " (already executed)" } else { "" }
+                    ));
+                    lines.extend(proposal.cuts.iter().map(|cut| cut_line(cut, width)));
+                }
+                None => lines.push(format!("proposal {} (not fetched)", optional(accounts.get(1).copied()))),
+            }
+            Some(lines)
+        }
+        other => arguments(other, args),
+    };
+    (format!("router::{}", name), details.unwrap_or_else(|| {
+        let mut lines = vec!["(payload doesn't decode)".to_string()];
+        lines.extend(raw(args));
+        lines
+    }))
+}
+
+/// Decode every instruction of `message`
+pub fn decode_message(router: &Pubkey, message: &VersionedMessage, lookup: Lookup) -> Vec<DecodedInstruction> {
+    let keys = message.static_account_keys();
+    message
+        .instructions()
+        .iter()
+        .map(|ix| {
+            let program = keys.get(ix.program_id_index as usize).copied().unwrap_or_default();
+            let accounts: Vec<Pubkey> = ix.accounts.iter().filter_map(|i| keys.get(*i as usize).copied()).collect();
+            let (name, details) = if program == *router {
+                decode_router_instruction(&accounts, &ix.data, lookup)
+            } else if program == system_program::id() {
+                match bincode::deserialize::<SystemInstruction>(&ix.data) {
+                    Ok(instruction) => (format!("system::{:?}", instruction), Vec::new()),
+                    Err(_) => ("system (undecoded)".to_string(), raw(&ix.data)),
+                }
+            } else {
+                (format!("program {}", program), raw(&ix.data))
+            };
+            DecodedInstruction { program, name, details }
+        })
+        .collect()
+}
+
+/// Router events in `Program data:` log lines
+pub fn events(logs: &[String]) -> Vec<DiamondEvent> {
+    logs.iter()
+        .filter_map(|log| {
+            let mut fields = log.strip_prefix("Program data: ")?.split(' ');
+            if STANDARD.decode(fields.next()?).ok()? != EVENT_PREFIX {
+                return None;
+            }
+            DiamondEvent::try_from_slice(&STANDARD.decode(fields.next()?).ok()?).ok()
+        })
+        .collect()
+}
+
+/// Decode a wire transaction
+pub fn decode_wire(router: &Pubkey, wire: &[u8], lookup: Lookup) -> RpcResult<DecodedTransaction> {
+    let (count, offset) = decode_short_u16(wire).ok_or("truncated signature count")?;
+    let message_start = offset + count as usize * 64;
+    let message: VersionedMessage = bincode::deserialize(wire.get(message_start..).ok_or("truncated signatures")?)
+        .map_err(|e| format!("bad message: {}", e))?;
+    let first = wire.get(offset..offset + 64).filter(|sig| sig.iter().any(|b| *b != 0));
+    let required = message.header().num_required_signatures as usize;
+    Ok(DecodedTransaction {
+        signature: first.map(|sig| bs58::encode(sig).into_string()),
+        signers: message.static_account_keys().iter().take(required).copied().collect(),
+        instructions: decode_message(router, &message, lookup),
+        events: Vec::new(),
+        error: None,
+    })
+}
+
+/// Fetch and decode a confirmed transaction with its events and error
+pub fn decode_signature(rpc: &RpcClient, router: &Pubkey, signature: &str, lookup: Lookup) -> RpcResult<DecodedTransaction> {
+    let result = rpc.call(
+        "getTransaction",
+        json!([signature, { "encoding": "base64", "commitment": "confirmed", "maxSupportedTransactionVersion": 0 }]),
+    )?;
+    if result.is_null() {
+        return Err(format!("transaction {} not found", signature));
+    }
+    let encoded = result["transaction"][0].as_str().ok_or("transaction data missing")?;
+    let wire = STANDARD.decode(encoded).map_err(|e| e.to_string())?;
+    let mut decoded = decode_wire(router, &wire, lookup)?;
+    let logs: Vec<String> = result["meta"]["logMessages"]
+        .as_array()
+        .map(|logs| logs.iter().filter_map(|l| l.as_str().map(String::from)).collect())
+        .unwrap_or_default();
+    decoded.events = events(&logs);
+    decoded.error = result["meta"]["err"].as_object().map(|err| serde_json::Value::Object(err.clone()).to_string());
+    Ok(decoded)
+}
+
+impl fmt::Display for DecodedTransaction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.signature {
+            Some(signature) => writeln!(f, "Transaction {}", signature)?,
+            None => writeln!(f, "Transaction (unsigned)")?,
+        }
+        writeln!(f, "  signers: {}", pubkeys(&self.signers))?;
+        for (i, ix) in self.instructions.iter().enumerate() {
+            writeln!(f, "  {}. {}", i + 1, ix.name)?;
+            for line in &ix.details {
+                writeln!(f, "       {}", line)?;
+            }
+        }
+        for event in &self.events {
+            writeln!(f, "  event: {:?}", event)?;
+        }
+        if let Some(error) = &self.error {
+            writeln!(f, "  ❌ failed: {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::admin;
+    use crate::rpc::unsigned_transaction;
+    use diamond_router_native::builtins::PAUSE_SELECTOR;
+    use solana_program::{instruction::Instruction, message::Message};
+    
+    #[test]
+    fn test_decode_cuts_and_builtins() {
+        let (router, diamond, owner) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let module = Pubkey::new_unique();
+        let mut state = DiamondState::new(owner, 255);
+        state.selectors.push(SelectorMapping::new([1, 0, 0, 9], module, "counter::increment", false));
+        let cuts = [FacetCut::add([1, 0, 0, 7], module, "counter", "reset"), FacetCut::remove([1, 0, 0, 9])];
+        let mut instructions = admin::cut_instructions(&router, &diamond, &state, &owner, &cuts).unwrap();
+        let mut call = PAUSE_SELECTOR.to_vec();
+        call.extend(borsh::to_vec(&(true, Some(500u64))).unwrap());
+        let mut data = DISPATCH_DISCRIMINATOR.to_vec();
+        data.extend(borsh::to_vec(&call).unwrap());
+        instructions.push(Instruction::new_with_bytes(router, &data, vec![]));
+        let wire = unsigned_transaction(&Message::new(&instructions, Some(&owner)));
+        
+        let encoded = borsh::to_vec(&state).unwrap();
+        let lookup = |address: &Pubkey| (*address == diamond).then(|| encoded.clone());
+        let decoded = decode_wire(&router, &wire, &lookup).unwrap();
+        assert_eq!((decoded.signature, decoded.signers), (None, vec![owner]));
+        let names: Vec<&str> = decoded.instructions.iter().map(|ix| ix.name.as_str()).collect();
+        assert_eq!(names, ["router::add_module", "router::remove_module", "router::dispatch"]);
+        assert!(decoded.instructions[0].details[0].starts_with("Add 01000007 reset -> counter"));
+        assert_eq!(
+            decoded.instructions[1].details[0],
+            format!("Remove 01000009 (counter::increment -> {})", module)
+        );
+        assert_eq!(decoded.instructions[2].details, ["built-in diamond::pause", "paused: true, expiry slot: Some(500)"]);
+        
+        // Without the state, selectors are trimmed and facet calls stay raw
+        let offline = decode_wire(&router, &wire, &|_| None).unwrap();
+        assert_eq!(offline.instructions[1].details[0], "Remove 01000009");
+    }
+    
+    #[test]
+    fn test_decode_compressed_dispatch() {
+        let (router, diamond, facet) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut call = vec![1, 0, 0, 9];
+        call.extend([0xAB; 600]);
+        let ix = crate::resolve::dispatch_compressed(&router, &diamond, &facet, vec![], &call);
+        assert!(ix.data.len() < 100);
+        let keys: Vec<Pubkey> = ix.accounts.iter().map(|m| m.pubkey).collect();
+        let (name, details) = decode_router_instruction(&keys, &ix.data, &|_| None);
+        assert_eq!(name, "router::dispatch_compressed");
+        assert!(details[0].ends_with("-> 604 bytes)"), "{:?}", details);
+    }
+    
+    #[test]
+    fn test_events_from_logs() {
+        let event = DiamondEvent::ProposalExecuted { id: 3, approver: Pubkey::new_unique() };
+        let logs = [
+            "Program log: Instruction: ApproveCut".to_string(),
+            format!("Program data: {} {}", STANDARD.encode(EVENT_PREFIX), STANDARD.encode(borsh::to_vec(&event).unwrap())),
+        ];
+        assert_eq!(events(&logs), [event]);
+    }
+}
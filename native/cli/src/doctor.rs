@@ -0,0 +1,127 @@
+/*!
+ * `diamond doctor`
+ *
+ * Probes every registered facet through the router's `probe_facet` built-in
+ * (via simulation) and reports which are dead or undeployed.
+ */
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use diamond_router_native::{
+    builtins::PROBE_FACET_SELECTOR,
+    diamond_health::{HealthReport, HealthStatus},
+    diamond_state::{DiamondState, SelectorMapping},
+    selector::Selector,
+    DISPATCH_DISCRIMINATOR,
+};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    message::Message,
+    pubkey::Pubkey,
+};
+
+use crate::errors;
+use crate::rpc::{RpcClient, RpcResult};
+
+/// Router `dispatch` instruction calling `probe_facet(selector)`
+pub fn probe_instruction(
+    router: &Pubkey,
+    diamond_state: &Pubkey,
+    facet: &Pubkey,
+    selector: Selector,
+) -> Instruction {
+    let mut ix_data = PROBE_FACET_SELECTOR.to_vec();
+    // Writing into a Vec cannot fail
+    selector.serialize(&mut ix_data).expect("borsh encode");
+    
+    let mut data = DISPATCH_DISCRIMINATOR.to_vec();
+    // Writing into a Vec cannot fail
+    ix_data.serialize(&mut data).expect("borsh encode");
+    
+    Instruction {
+        program_id: *router,
+        accounts: vec![
+            AccountMeta::new(*diamond_state, false),
+            AccountMeta::new_readonly(*router, false),
+            AccountMeta::new_readonly(*facet, false),
+        ],
+        data,
+    }
+}
+
+/// Probe the facet behind one selector: `"healthy"`, or what is wrong
+pub fn probe(
+    rpc: &RpcClient,
+    router: &Pubkey,
+    diamond_state: &Pubkey,
+    payer: &Pubkey,
+    mapping: &SelectorMapping,
+) -> RpcResult<String> {
+    let ix = probe_instruction(router, diamond_state, &mapping.module, mapping.selector);
+    let simulation = rpc.simulate(&Message::new(&[ix], Some(payer)))?;
+    
+    Ok(match (&simulation.err, simulation.return_data.as_deref()) {
+        (None, Some(mut bytes)) => match HealthReport::deserialize(&mut bytes) {
+            Ok(report) if report.status == HealthStatus::Healthy => "healthy".to_string(),
+            Ok(_) => "NOT DEPLOYED".to_string(),
+            Err(e) => format!("BAD REPORT: {}", e),
+        },
+        (None, None) => "NO REPORT".to_string(),
+        (Some(err), _) => format!("FAILED: {}", errors::decode(router, err, &simulation.logs)),
+    })
+}
+
+/// Probe all facets; returns the number of unhealthy selectors
+pub fn run(
+    rpc: &RpcClient,
+    router: &Pubkey,
+    diamond_state: &Pubkey,
+    payer: Option<Pubkey>,
+) -> RpcResult<usize> {
+    let account = rpc
+        .get_account(diamond_state)?
+        .ok_or_else(|| format!("diamond state {} not found", diamond_state))?;
+    if &account.owner != router {
+        return Err(format!("{} is not owned by router {}", diamond_state, router));
+    }
+    let state = DiamondState::deserialize(&mut &account.data[..]).map_err(|e| e.to_string())?;
+    let payer = payer.unwrap_or(state.owner);
+    
+    println!("Diamond {} ({} selectors)", diamond_state, state.selectors.len());
+    let mut unhealthy = 0;
+    for mapping in &state.selectors {
+        let verdict = probe(rpc, router, diamond_state, &payer, mapping)?;
+        if verdict != "healthy" {
+            unhealthy += 1;
+        }
+        println!(
+            "  {:<40} {} -> {}  {}",
+            mapping.qualified_name(),
+            hex(mapping.selector.as_bytes(state.selector_width)),
+            mapping.module,
+            verdict
+        );
+    }
+    Ok(unhealthy)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_probe_instruction_encoding() {
+        let (router, state, facet) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let ix = probe_instruction(&router, &state, &facet, [1, 2, 3, 4].into());
+        
+        assert_eq!(&ix.data[..8], &DISPATCH_DISCRIMINATOR);
+        let inner = Vec::<u8>::try_from_slice(&ix.data[8..]).unwrap();
+        assert_eq!(&inner[..4], &PROBE_FACET_SELECTOR);
+        assert_eq!(&inner[4..], &[1, 2, 3, 4, 0, 0, 0, 0]);
+        assert_eq!(ix.accounts[1].pubkey, router);
+        assert_eq!(ix.accounts[2].pubkey, facet);
+    }
+}
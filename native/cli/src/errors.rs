@@ -0,0 +1,143 @@
+/*!
+ * Error decoding
+ *
+ * Maps a failed transaction or simulation back to the program that raised
+ * the error: router codes (6000+) become `DiamondError` variants, and codes
+ * raised by a facet behind `dispatch` are passed through with the facet's id
+ * (and its Anchor error message, when the facet logs one).
+ */
+
+use diamond_router_native::error::DiamondError;
+use serde_json::Value;
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
+use std::fmt;
+use std::str::FromStr;
+
+/// Where a failure came from
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodedError {
+    /// Custom error raised by the router itself
+    Router(DiamondError),
+    /// Custom error raised by another program (a facet, usually)
+    Program {
+        program: Option<Pubkey>,
+        code: u32,
+        message: Option<String>,
+    },
+    /// Anything that isn't a custom program error
+    Other(String),
+}
+
+impl fmt::Display for DecodedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Router(error) => write!(f, "DiamondError::{:?} ({}): {}", error, *error as u32, error),
+            Self::Program { program, code, message } => {
+                match program {
+                    Some(program) => write!(f, "facet {} error {} (0x{:x})", program, code, code)?,
+                    None => write!(f, "custom program error {} (0x{:x})", code, code)?,
+                }
+                match message {
+                    Some(message) => write!(f, ": {}", message),
+                    None => Ok(()),
+                }
+            }
+            Self::Other(error) => f.write_str(error),
+        }
+    }
+}
+
+/// `Custom(code)` from a `TransactionError` JSON value
+pub fn custom_code(err: &Value) -> Option<u32> {
+    err["InstructionError"][1]["Custom"].as_u64().and_then(|code| u32::try_from(code).ok())
+}
+
+/// Program whose failure the runtime logged first (the innermost one)
+fn failing_program(logs: &[String]) -> Option<Pubkey> {
+    logs.iter().find_map(|log| {
+        let rest = log.strip_prefix("Program ")?;
+        let (id, outcome) = rest.split_once(' ')?;
+        if outcome.starts_with("failed") {
+            Pubkey::from_str(id).ok()
+        } else {
+            None
+        }
+    })
+}
+
+/// `Error Message: ...` from an Anchor facet's error log
+fn anchor_message(logs: &[String]) -> Option<String> {
+    logs.iter().find_map(|log| {
+        let rest = log.strip_prefix("Program log: AnchorError")?;
+        rest.split_once("Error Message: ").map(|(_, msg)| msg.trim_end_matches('.').to_string())
+    })
+}
+
+/// Decode a transaction error given the program logs that came with it
+pub fn decode(router: &Pubkey, err: &Value, logs: &[String]) -> DecodedError {
+    let code = match custom_code(err) {
+        Some(code) => code,
+        None => return DecodedError::Other(err.to_string()),
+    };
+    let program = failing_program(logs);
+    match program {
+        Some(program) if &program == router => match DiamondError::from_code(code) {
+            Some(error) => DecodedError::Router(error),
+            None => DecodedError::Program { program: Some(program), code, message: None },
+        },
+        Some(_) => DecodedError::Program { program, code, message: anchor_message(logs) },
+        // Without logs, router codes are the likelier reading
+        None => match DiamondError::from_code(code) {
+            Some(error) => DecodedError::Router(error),
+            None => DecodedError::Program { program: None, code, message: None },
+        },
+    }
+}
+
+/// Decode the `error_code` of a `DispatchFailed` event (a `ProgramError` as u64)
+pub fn decode_dispatch_failure(facet: &Pubkey, error_code: u64) -> DecodedError {
+    match ProgramError::from(error_code) {
+        ProgramError::Custom(code) => DecodedError::Program { program: Some(*facet), code, message: None },
+        other => DecodedError::Other(other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    
+    #[test]
+    fn test_decode_router_and_facet_errors() {
+        let router = diamond_router_native::id();
+        let facet = Pubkey::new_unique();
+        let err = |code: u32| json!({ "InstructionError": [0, { "Custom": code }] });
+        
+        let logs = vec![
+            format!("Program {} invoke [1]", router),
+            format!("Program {} failed: custom program error: 0x1770", router),
+        ];
+        assert_eq!(decode(&router, &err(6000), &logs), DecodedError::Router(DiamondError::ModuleNotFound));
+        
+        let logs = vec![
+            format!("Program {} invoke [1]", router),
+            format!("Program {} invoke [2]", facet),
+            "Program log: AnchorError occurred. Error Code: Overflow. Error Number: 6000. Error Message: Counter overflow.".to_string(),
+            format!("Program {} failed: custom program error: 0x1770", facet),
+            format!("Program {} failed: custom program error: 0x1770", router),
+        ];
+        assert_eq!(
+            decode(&router, &err(6000), &logs),
+            DecodedError::Program { program: Some(facet), code: 6000, message: Some("Counter overflow".to_string()) }
+        );
+        
+        assert_eq!(
+            decode(&router, &json!("AccountNotFound"), &[]),
+            DecodedError::Other("\"AccountNotFound\"".to_string())
+        );
+        assert_eq!(
+            decode_dispatch_failure(&facet, u64::from(ProgramError::Custom(7))),
+            DecodedError::Program { program: Some(facet), code: 7, message: None }
+        );
+    }
+}
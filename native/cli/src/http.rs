@@ -0,0 +1,156 @@
+/*!
+ * HTTP/1.1 server shared by the loupe cache and the admin daemon
+ *
+ * A thread-per-connection server on `std::net`. Each connection carries one
+ * request with an optional `Content-Length` body and is answered with a JSON
+ * body and `Connection: close`. Reads and writes time out, so a client that
+ * stalls doesn't hold its thread forever.
+ */
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Largest request head read from a client
+const MAX_HEAD_LEN: usize = 8192;
+
+/// Largest request body (a full proposal is ~2 KB of JSON)
+const MAX_BODY_LEN: usize = 64 * 1024;
+
+/// How long a client may take to send its request or read the response
+pub const IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    /// Header names as sent; look them up with `header`
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// Value of header `name` (case-insensitive)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// A JSON response, with any headers beyond the ones every response carries
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: &'static str,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: String,
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+pub fn read_request(reader: &mut impl BufRead) -> io::Result<Request> {
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = match (parts.next(), parts.next()) {
+        (Some(method), Some(path)) => (method.to_string(), path.to_string()),
+        _ => return Err(invalid("malformed request line")),
+    };
+    
+    let mut headers = Vec::new();
+    let mut head_len = request_line.len();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line == "\r\n" || line == "\n" {
+            break;
+        }
+        head_len += line.len();
+        if head_len > MAX_HEAD_LEN {
+            return Err(invalid("request head too large"));
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    let mut request = Request { method, path, headers, body: Vec::new() };
+    let content_length = match request.header("content-length") {
+        Some(value) => value.parse().map_err(|_| invalid("invalid Content-Length"))?,
+        None => 0,
+    };
+    if content_length > MAX_BODY_LEN {
+        return Err(invalid("request body too large"));
+    }
+    request.body = vec![0u8; content_length];
+    reader.read_exact(&mut request.body)?;
+    Ok(request)
+}
+
+fn write_response(mut stream: &TcpStream, response: &Response) -> io::Result<()> {
+    let mut out = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        response.body.len()
+    );
+    for (name, value) in &response.headers {
+        out.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    out.push_str("\r\n");
+    out.push_str(&response.body);
+    stream.write_all(out.as_bytes())
+}
+
+fn handle(stream: TcpStream, handler: &dyn Fn(&Request) -> Response) -> io::Result<()> {
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    let response = match read_request(&mut BufReader::new(stream.try_clone()?)) {
+        Ok(request) => handler(&request),
+        Err(e) => Response {
+            status: "400 Bad Request",
+            headers: Vec::new(),
+            body: serde_json::json!({ "error": e.to_string() }).to_string(),
+        },
+    };
+    write_response(&stream, &response)
+}
+
+/// Serve `handler` on `listen` until the process exits
+pub fn serve<H>(listen: &str, handler: H) -> Result<(), String>
+where
+    H: Fn(&Request) -> Response + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind(listen).map_err(|e| format!("{}: {}", listen, e))?;
+    let handler = Arc::new(handler);
+    for stream in listener.incoming().flatten() {
+        let handler = handler.clone();
+        thread::spawn(move || {
+            if let Err(e) = handle(stream, &*handler) {
+                eprintln!("http: {}", e);
+            }
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_read_request() {
+        let raw = "POST /v1/diamonds HTTP/1.1\r\nauthorization: Bearer abc\r\nContent-Length: 2\r\n\r\n{}trailing";
+        let request = read_request(&mut raw.as_bytes()).unwrap();
+        assert_eq!((request.method.as_str(), request.path.as_str()), ("POST", "/v1/diamonds"));
+        assert_eq!(request.header("Authorization"), Some("Bearer abc"));
+        assert_eq!(request.header("if-none-match"), None);
+        assert_eq!(request.body, b"{}");
+        
+        assert!(read_request(&mut "GET /\r\nContent-Length: 9\r\n\r\n{}".as_bytes()).is_err());
+        assert!(read_request(&mut "GET / HTTP/1.1\r\nContent-Length: 999999\r\n\r\n".as_bytes()).is_err());
+        assert!(read_request(&mut "\r\n".as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,186 @@
+/*!
+ * `diamond bundle`
+ *
+ * Submits an upgrade sequence (final facet deploy, cut, init call) as a Jito
+ * bundle so it lands atomically in one block or not at all. The CLI holds no
+ * keys: transactions arrive signed, one base64 wire transaction per line, and
+ * are checked against the block engine's rules before `sendBundle`.
+ *
+ * Block engines are served over HTTPS; point `--engine` at a local relay or
+ * TLS-terminating proxy since the RPC client speaks plain HTTP.
+ */
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde_json::json;
+use solana_program::{
+    message::VersionedMessage,
+    pubkey::Pubkey,
+    system_instruction::SystemInstruction,
+    system_program,
+};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use crate::rpc::{RpcClient, RpcResult};
+
+/// Block engine limit on transactions per bundle
+pub const MAX_BUNDLE_LEN: usize = 5;
+
+/// Mainnet tip accounts; a bundle must pay one of them to be considered
+pub const TIP_ACCOUNTS: [Pubkey; 8] = [
+    solana_program::pubkey!("96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5"),
+    solana_program::pubkey!("HFqU5x63VTqvQss8hp11i4wVV8bD44PvwucfZ2bU7gRe"),
+    solana_program::pubkey!("Cw8CFyM9FkoMi7K7Crf6HNQqf4uEMzpKw6QNghXLvLkY"),
+    solana_program::pubkey!("ADaUMid9yfUytqMBgopwjb2DTLSokTSzL1zt6iGPaS49"),
+    solana_program::pubkey!("DfXygSm4jCyNCybVYYK6DwvWqjKee8pbDmJGcLWNDXjh"),
+    solana_program::pubkey!("ADuUkR4vqLUMWXxW9gh6D6L8pMSawimctcNZ5pGwDcEt"),
+    solana_program::pubkey!("DttWaMuVvTiduZRnguLF7jNxTgiMBZ1hyAumKUiL2KRL"),
+    solana_program::pubkey!("3AVi9Tg9Uo68tJfuvoKvqKNWKkC5wPdSSdeBnizKZ6jT"),
+];
+
+/// A signed wire transaction and its decoded message
+#[derive(Debug, Clone)]
+pub struct BundleTransaction {
+    pub wire: Vec<u8>,
+    pub message: VersionedMessage,
+}
+
+impl BundleTransaction {
+    pub fn decode(wire: Vec<u8>) -> RpcResult<Self> {
+        let (signatures, offset) = decode_short_u16(&wire).ok_or("truncated signature count")?;
+        let message_start = offset + signatures as usize * 64;
+        let message: VersionedMessage = bincode::deserialize(
+            wire.get(message_start..).ok_or("truncated signatures")?,
+        )
+        .map_err(|e| format!("bad message: {}", e))?;
+        if message.header().num_required_signatures as u16 != signatures {
+            return Err(format!(
+                "{} signatures for {} required signers",
+                signatures,
+                message.header().num_required_signatures
+            ));
+        }
+        if wire[offset..message_start].iter().all(|b| *b == 0) {
+            return Err("transaction is unsigned".to_string());
+        }
+        Ok(Self { wire, message })
+    }
+    
+    /// Lamports this transaction pays to Jito tip accounts (static keys only)
+    pub fn tip_lamports(&self) -> u64 {
+        let keys = self.message.static_account_keys();
+        self.message
+            .instructions()
+            .iter()
+            .filter(|ix| keys.get(ix.program_id_index as usize) == Some(&system_program::id()))
+            .filter_map(|ix| {
+                let to = keys.get(*ix.accounts.get(1)? as usize)?;
+                match bincode::deserialize(&ix.data).ok()? {
+                    SystemInstruction::Transfer { lamports } if TIP_ACCOUNTS.contains(to) => Some(lamports),
+                    _ => None,
+                }
+            })
+            .sum()
+    }
+}
+
+/// compact-u16 (shortvec) length prefix
+pub(crate) fn decode_short_u16(bytes: &[u8]) -> Option<(u16, usize)> {
+    let mut value = 0u32;
+    for (i, byte) in bytes.iter().take(3).enumerate() {
+        value |= u32::from(byte & 0x7F) << (7 * i);
+        if byte & 0x80 == 0 {
+            return u16::try_from(value).ok().map(|v| (v, i + 1));
+        }
+    }
+    None
+}
+
+/// Parse and validate a bundle from base64 lines (blank lines and `#` comments skipped)
+pub fn parse_bundle(text: &str) -> RpcResult<Vec<BundleTransaction>> {
+    let bundle = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .enumerate()
+        .map(|(i, line)| {
+            let wire = STANDARD.decode(line).map_err(|e| format!("tx {}: {}", i, e))?;
+            BundleTransaction::decode(wire).map_err(|e| format!("tx {}: {}", i, e))
+        })
+        .collect::<RpcResult<Vec<_>>>()?;
+    
+    if bundle.is_empty() || bundle.len() > MAX_BUNDLE_LEN {
+        return Err(format!("bundle must hold 1..={} transactions, got {}", MAX_BUNDLE_LEN, bundle.len()));
+    }
+    // Tipping last means the tip is only paid if every earlier step landed
+    if bundle.last().map(BundleTransaction::tip_lamports).unwrap_or(0) == 0 {
+        return Err("last transaction must transfer a tip to a Jito tip account".to_string());
+    }
+    Ok(bundle)
+}
+
+/// Submit a bundle and wait for it to land; returns the bundle id
+pub fn submit(engine: &RpcClient, bundle: &[BundleTransaction], timeout: Duration) -> RpcResult<String> {
+    let encoded: Vec<String> = bundle.iter().map(|tx| STANDARD.encode(&tx.wire)).collect();
+    let bundle_id = engine
+        .call("sendBundle", json!([encoded, { "encoding": "base64" }]))?
+        .as_str()
+        .ok_or("sendBundle returned no bundle id")?
+        .to_string();
+    println!("Bundle {} submitted ({} transactions)", bundle_id, bundle.len());
+    
+    let started = Instant::now();
+    loop {
+        let result = engine.call("getInflightBundleStatuses", json!([[bundle_id]]))?;
+        let status = result["value"][0]["status"].as_str().unwrap_or("Pending").to_string();
+        match status.as_str() {
+            "Landed" => {
+                println!("Bundle landed in slot {}", result["value"][0]["landed_slot"]);
+                return Ok(bundle_id);
+            }
+            "Failed" | "Invalid" => return Err(format!("bundle {} {}", bundle_id, status.to_lowercase())),
+            _ if started.elapsed() >= timeout => {
+                return Err(format!("bundle {} still {} after {:?}", bundle_id, status, timeout));
+            }
+            _ => sleep(Duration::from_secs(2)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_program::{hash::Hash, message::Message, system_instruction};
+    
+    fn signed(message: &Message) -> String {
+        let mut tx = crate::rpc::unsigned_transaction(message);
+        tx[1] = 1; // non-zero placeholder signature
+        STANDARD.encode(tx)
+    }
+    
+    #[test]
+    fn test_bundle_requires_final_tip() {
+        let payer = Pubkey::new_unique();
+        let cut = Message::new_with_blockhash(
+            &[system_instruction::transfer(&payer, &Pubkey::new_unique(), 5)],
+            Some(&payer),
+            &Hash::default(),
+        );
+        let tip = Message::new_with_blockhash(
+            &[system_instruction::transfer(&payer, &TIP_ACCOUNTS[3], 10_000)],
+            Some(&payer),
+            &Hash::default(),
+        );
+        
+        let bundle = parse_bundle(&format!("# cut\n{}\n\n{}\n", signed(&cut), signed(&tip))).unwrap();
+        assert_eq!(bundle.len(), 2);
+        assert_eq!(bundle[0].tip_lamports(), 0);
+        assert_eq!(bundle[1].tip_lamports(), 10_000);
+        
+        assert!(parse_bundle(&format!("{}\n{}", signed(&tip), signed(&cut))).is_err());
+        assert!(parse_bundle(&vec![signed(&tip); MAX_BUNDLE_LEN + 1].join("\n")).is_err());
+        
+        let unsigned = STANDARD.encode(crate::rpc::unsigned_transaction(&tip));
+        assert!(parse_bundle(&unsigned).is_err());
+    }
+}
@@ -0,0 +1,124 @@
+/*!
+ * Ed25519 keypairs
+ *
+ * Just enough signing for throwaway localnet keys and `solana-keygen` files:
+ * RFC 8032 Ed25519 over the curve25519-dalek and sha2 crates solana-program
+ * already pulls in. Keys held elsewhere sign through `Signer` (see `signer`).
+ */
+
+use curve25519_dalek::{constants::ED25519_BASEPOINT_TABLE, scalar::Scalar};
+use sha2::{Digest, Sha512};
+use solana_program::pubkey::Pubkey;
+use std::fs::File;
+use std::io::Read;
+
+/// Signs as one of a transaction's required signers
+pub trait Signer {
+    fn pubkey(&self) -> Pubkey;
+    
+    /// Ed25519 signature over `message` (may prompt, or fail on a device)
+    fn try_sign(&self, message: &[u8]) -> Result<[u8; 64], String>;
+}
+
+pub struct Keypair {
+    expanded: [u8; 64],
+    secret: Scalar,
+    public: [u8; 32],
+}
+
+impl Keypair {
+    /// Derive from a 32-byte secret seed
+    pub fn from_seed(seed: &[u8; 32]) -> Self {
+        let mut expanded = [0u8; 64];
+        expanded.copy_from_slice(&Sha512::digest(seed));
+        let mut scalar = [0u8; 32];
+        scalar.copy_from_slice(&expanded[..32]);
+        scalar[0] &= 248;
+        scalar[31] &= 127;
+        scalar[31] |= 64;
+        let secret = Scalar::from_bits(scalar);
+        let public = (&secret * &ED25519_BASEPOINT_TABLE).compress().to_bytes();
+        Self { expanded, secret, public }
+    }
+
+    /// Fresh keypair seeded from the OS
+    pub fn generate() -> Result<Self, String> {
+        let mut seed = [0u8; 32];
+        File::open("/dev/urandom")
+            .and_then(|mut f| f.read_exact(&mut seed))
+            .map_err(|e| format!("/dev/urandom: {}", e))?;
+        Ok(Self::from_seed(&seed))
+    }
+
+    /// Read a `solana-keygen` JSON keypair file (64 bytes: seed, then public key)
+    pub fn read_file(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let bytes: Vec<u8> = serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))?;
+        let (seed, public) = match bytes.len() {
+            64 => bytes.split_at(32),
+            n => return Err(format!("{}: expected 64 bytes, found {}", path, n)),
+        };
+        let keypair = Self::from_seed(seed.try_into().unwrap_or(&[0; 32]));
+        if keypair.public != public {
+            return Err(format!("{}: public key doesn't match the seed", path));
+        }
+        Ok(keypair)
+    }
+    
+    pub fn pubkey(&self) -> Pubkey {
+        Pubkey::new_from_array(self.public)
+    }
+
+    pub fn sign(&self, message: &[u8]) -> [u8; 64] {
+        let r = Scalar::from_hash(Sha512::new().chain(&self.expanded[32..]).chain(message));
+        let big_r = (&r * &ED25519_BASEPOINT_TABLE).compress();
+        let k = Scalar::from_hash(
+            Sha512::new().chain(big_r.as_bytes()).chain(self.public).chain(message),
+        );
+        let s = k * self.secret + r;
+
+        let mut signature = [0u8; 64];
+        signature[..32].copy_from_slice(big_r.as_bytes());
+        signature[32..].copy_from_slice(s.as_bytes());
+        signature
+    }
+}
+
+impl Signer for Keypair {
+    fn pubkey(&self) -> Pubkey {
+        Keypair::pubkey(self)
+    }
+    
+    fn try_sign(&self, message: &[u8]) -> Result<[u8; 64], String> {
+        Ok(self.sign(message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unhex(s: &str) -> Vec<u8> {
+        (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_rfc8032_vector_1() {
+        let mut seed = [0u8; 32];
+        seed.copy_from_slice(&unhex("9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60"));
+        let keypair = Keypair::from_seed(&seed);
+
+        assert_eq!(
+            keypair.pubkey().to_bytes().to_vec(),
+            unhex("d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a")
+        );
+        assert_eq!(
+            keypair.sign(b"").to_vec(),
+            unhex(
+                "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555\
+                 fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b"
+            )
+        );
+    }
+
+}
@@ -0,0 +1,51 @@
+/*!
+ * Diamond CLI library
+ *
+ * Shared by the `diamond` operator CLI and the `diamond-validator` localnet check.
+ */
+
+pub mod admin;
+pub mod budget;
+pub mod capacity;
+pub mod decode;
+pub mod doctor;
+pub mod errors;
+pub mod http;
+pub mod jito;
+pub mod keys;
+pub mod lint;
+pub mod loupe;
+pub mod nonce;
+pub mod plan;
+pub mod reconcile;
+#[cfg(any(test, feature = "replay"))]
+pub mod replay;
+pub mod resolve;
+pub mod responses;
+pub mod rpc;
+pub mod sender;
+pub mod signer;
+pub mod simulate;
+pub mod upgrade;
+pub mod vanity;
+pub mod verify;
+
+use std::collections::HashMap;
+
+/// `--flag value` pairs following the subcommand; a flag with no value
+/// (`--yes`) is recorded as `"true"`
+pub fn parse_flags(args: &[String]) -> Result<HashMap<String, String>, String> {
+    let mut flags = HashMap::new();
+    let mut iter = args.iter().peekable();
+    while let Some(arg) = iter.next() {
+        let name = arg
+            .strip_prefix("--")
+            .ok_or_else(|| format!("unexpected argument: {}", arg))?;
+        let value = match iter.peek() {
+            Some(next) if !next.starts_with("--") => iter.next().cloned().unwrap_or_default(),
+            _ => "true".to_string(),
+        };
+        flags.insert(name.to_string(), value);
+    }
+    Ok(flags)
+}
@@ -0,0 +1,243 @@
+/*!
+ * Cut linting
+ *
+ * Checks a previewed admin transaction for changes that succeed on-chain but
+ * are probably mistakes: removing a selector that is still being called,
+ * routing to an address that isn't a deployed program, shadowing a router
+ * built-in by name, or filling a table to the brim. `diamond submit` refuses
+ * to send while any finding is not overridden with `--allow <lint,...>`.
+ */
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use borsh::BorshDeserialize;
+use diamond_router_native::{
+    builtins::{is_reserved, BUILTIN_SELECTORS},
+    events::{DiamondEvent, EVENT_PREFIX},
+    selector::Selector,
+};
+use solana_program::pubkey::Pubkey;
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::capacity;
+use crate::rpc::{RpcClient, RpcResult};
+use crate::simulate::{Preview, Route};
+
+/// Recent-call window when `--recent-slots` isn't given (about an hour)
+pub const DEFAULT_RECENT_SLOTS: u64 = 9_000;
+
+/// Signatures per `getSignaturesForAddress` page
+const SIGNATURE_PAGE: usize = 1000;
+
+/// Kinds of risky change (the name is the `--allow` override)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lint {
+    /// A removed selector was dispatched within the recent-call window
+    RecentCall,
+    /// A selector would route to a missing or non-executable account
+    NotExecutable,
+    /// A selector is reserved or named like a router built-in
+    Shadowing,
+    /// A table would be at or over `capacity::WARN_PERCENT`
+    Capacity,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 4] = [Lint::RecentCall, Lint::NotExecutable, Lint::Shadowing, Lint::Capacity];
+    
+    pub fn name(&self) -> &'static str {
+        match self {
+            Lint::RecentCall => "recent-call",
+            Lint::NotExecutable => "not-executable",
+            Lint::Shadowing => "shadowing",
+            Lint::Capacity => "capacity",
+        }
+    }
+}
+
+/// Parse a comma-separated `--allow` list
+pub fn parse_allowed(list: &str) -> Result<HashSet<Lint>, String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            Lint::ALL
+                .into_iter()
+                .find(|lint| lint.name() == name)
+                .ok_or_else(|| format!("--allow: unknown lint {}", name))
+        })
+        .collect()
+}
+
+/// One risky change found in a preview
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub lint: Lint,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}] {}", self.lint.name(), self.message)
+    }
+}
+
+/// Selector of a router `Dispatched` event in a `Program data:` log line
+pub fn dispatched_selector(log: &str) -> Option<Selector> {
+    let mut fields = log.strip_prefix("Program data: ")?.split(' ');
+    if STANDARD.decode(fields.next()?).ok()? != EVENT_PREFIX {
+        return None;
+    }
+    match DiamondEvent::try_from_slice(&STANDARD.decode(fields.next()?).ok()?).ok()? {
+        DiamondEvent::Dispatched { selector, .. } => Some(selector),
+        _ => None,
+    }
+}
+
+/// Selectors dispatched through `diamond_state` within the last `slots` slots
+///
+/// Pages back through the state's history with `before` until it reaches
+/// a transaction older than the window, so no call in it is missed.
+pub fn recent_selectors(rpc: &RpcClient, diamond_state: &Pubkey, slots: u64) -> RpcResult<HashSet<Selector>> {
+    let since = rpc.slot()?.saturating_sub(slots);
+    let mut selectors = HashSet::new();
+    let mut before: Option<String> = None;
+    loop {
+        let page = rpc.signatures_for(diamond_state, before.as_deref(), SIGNATURE_PAGE)?;
+        for (signature, slot) in &page {
+            if *slot < since {
+                return Ok(selectors);
+            }
+            selectors.extend(rpc.transaction_logs(signature)?.iter().filter_map(|log| dispatched_selector(log)));
+        }
+        if page.len() < SIGNATURE_PAGE {
+            return Ok(selectors);
+        }
+        before = page.last().map(|(signature, _)| signature.clone());
+    }
+}
+
+/// Lint a successful preview
+///
+/// `recent` holds the selectors called lately; `executable` says whether a
+/// module address is a deployed program.
+pub fn check(preview: &Preview, recent: &HashSet<Selector>, executable: impl Fn(&Pubkey) -> bool) -> Vec<Finding> {
+    let show = |route: &Route| {
+        let hex: String = route.selector.as_bytes(preview.selector_width).iter().map(|b| format!("{:02x}", b)).collect();
+        format!("{} ({})", hex, route.name)
+    };
+    let delta = &preview.delta;
+    let mut findings = Vec::new();
+    
+    for route in delta.removed.iter().filter(|route| recent.contains(&route.selector)) {
+        findings.push(Finding {
+            lint: Lint::RecentCall,
+            message: format!("removes {}, which was called recently", show(route)),
+        });
+    }
+    let routed = delta.added.iter().chain(delta.rerouted.iter().map(|(_, new)| new));
+    let mut checked = HashSet::new();
+    for route in routed.clone().filter(|route| checked.insert(route.module) && !executable(&route.module)) {
+        findings.push(Finding {
+            lint: Lint::NotExecutable,
+            message: format!("routes {} to {}, which is not an executable program", show(route), route.module),
+        });
+    }
+    for route in routed {
+        let builtin = BUILTIN_SELECTORS.iter().find(|(_, name)| *name == route.name);
+        if is_reserved(route.selector) || builtin.is_some() {
+            findings.push(Finding {
+                lint: Lint::Shadowing,
+                message: format!("{} shadows a router built-in", show(route)),
+            });
+        }
+    }
+    if let Some(capacity) = &preview.capacity {
+        for table in capacity::warnings(capacity) {
+            findings.push(Finding {
+                lint: Lint::Capacity,
+                message: format!("leaves {} at {}% or more of capacity", table, capacity::WARN_PERCENT),
+            });
+        }
+    }
+    findings
+}
+
+/// Lint a preview against the live cluster
+pub fn run(rpc: &RpcClient, preview: &Preview, recent_slots: u64) -> RpcResult<Vec<Finding>> {
+    let recent = if preview.delta.removed.is_empty() {
+        HashSet::new()
+    } else {
+        recent_selectors(rpc, &preview.diamond_state, recent_slots)?
+    };
+    let mut executable = HashSet::new();
+    for route in preview.delta.added.iter().chain(preview.delta.rerouted.iter().map(|(_, new)| new)) {
+        if rpc.get_account(&route.module)?.is_some_and(|account| account.executable) {
+            executable.insert(route.module);
+        }
+    }
+    Ok(check(preview, &recent, |module| executable.contains(module)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulate::StateDelta;
+    use diamond_router_native::diamond_loupe::{Capacity, Usage};
+    
+    fn route(selector: [u8; 4], name: &str, module: Pubkey) -> Route {
+        Route { selector: selector.into(), name: name.to_string(), module }
+    }
+    
+    #[test]
+    fn test_check_flags_risky_cuts() {
+        let (live, undeployed) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut preview = Preview {
+            diamond_state: Pubkey::new_unique(),
+            selector_width: 4,
+            error: None,
+            delta: StateDelta {
+                added: vec![
+                    route([1, 0, 0, 3], "token::mint", undeployed),
+                    route([1, 0, 0, 4], "diamond::pause", live),
+                ],
+                removed: vec![route([1, 0, 0, 1], "token::burn", live), route([1, 0, 0, 2], "token::old", live)],
+                ..StateDelta::default()
+            },
+            capacity: Some(Capacity {
+                selectors: Usage { used: 45, max: 50 },
+                modules: Usage { used: 2, max: 20 },
+                admins: Usage { used: 1, max: 10 },
+                bytes_free: 1000,
+            }),
+            units_consumed: None,
+            logs: vec![],
+        };
+        let recent = HashSet::from([Selector::from([1, 0, 0, 1])]);
+        let lints: Vec<Lint> = check(&preview, &recent, |m| m == &live).iter().map(|f| f.lint).collect();
+        assert_eq!(lints, [Lint::RecentCall, Lint::NotExecutable, Lint::Shadowing, Lint::Capacity]);
+        
+        preview.delta = StateDelta::default();
+        preview.capacity = None;
+        assert!(check(&preview, &recent, |_| false).is_empty());
+    }
+    
+    #[test]
+    fn test_dispatched_selector_and_allow_list() {
+        let event = DiamondEvent::Dispatched {
+            trace_id: None,
+            selector: [1, 2, 3, 4].into(),
+            facet: Pubkey::new_unique(),
+        };
+        let log = format!(
+            "Program data: {} {}",
+            STANDARD.encode(EVENT_PREFIX),
+            STANDARD.encode(borsh::to_vec(&event).unwrap())
+        );
+        assert_eq!(dispatched_selector(&log), Some([1, 2, 3, 4].into()));
+        assert_eq!(dispatched_selector("Program log: hello"), None);
+        
+        assert_eq!(parse_allowed("capacity, recent-call").unwrap(), HashSet::from([Lint::Capacity, Lint::RecentCall]));
+        assert!(parse_allowed("everything").is_err());
+    }
+}
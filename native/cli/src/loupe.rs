@@ -0,0 +1,205 @@
+/*!
+ * `diamond loupe`
+ *
+ * Exports a diamond's routing table for tooling outside this repo:
+ *
+ * - `json`: the full table (facets, selectors with names, flags and tags,
+ *   aliases), in the field names the loupe cache serves.
+ * - `csv`: one row per selector, for spreadsheets and dashboards.
+ * - `louper`: the shape of EIP-2535's `facets()` loupe output that Louper
+ *   and other EVM diamond tooling read (`facetAddress` plus
+ *   `functionSelectors` per facet), with base58 addresses and `0x`-prefixed
+ *   selectors at the diamond's width. Each facet also carries its name and
+ *   each function's name.
+ */
+
+use borsh::BorshDeserialize;
+use diamond_router_native::diamond_state::{fixed_str, DiamondState, SelectorMapping};
+use serde_json::{json, Value};
+use solana_program::pubkey::Pubkey;
+
+use crate::rpc::{RpcClient, RpcResult};
+
+/// Export formats of `diamond loupe --format`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Csv,
+    Louper,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            "louper" => Ok(Self::Louper),
+            other => Err(format!("--format: expected json, csv or louper, got {}", other)),
+        }
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn tags(state: &DiamondState, mapping: &SelectorMapping) -> Vec<String> {
+    state.tags_of(mapping.selector).iter().map(|t| fixed_str(t).to_string()).collect()
+}
+
+/// `namespace::function`, or the bare function in the global namespace
+fn qualified_name(mapping: &SelectorMapping) -> String {
+    match fixed_str(&mapping.namespace) {
+        "" | "global" => fixed_str(&mapping.function_name).to_string(),
+        namespace => format!("{}::{}", namespace, fixed_str(&mapping.function_name)),
+    }
+}
+
+fn json_table(address: &Pubkey, state: &DiamondState) -> Value {
+    let width = state.selector_width;
+    json!({
+        "address": address.to_string(),
+        "selector_width": width,
+        "facets": state.active_modules.iter().map(|m| json!({
+            "name": fixed_str(&m.name),
+            "address": m.address.to_string(),
+            "version": m.version.to_string(),
+            "active": m.is_active,
+        })).collect::<Vec<Value>>(),
+        "selectors": state.selectors.iter().map(|m| json!({
+            "selector": hex(m.selector.as_bytes(width)),
+            "name": fixed_str(&m.function_name),
+            "namespace": fixed_str(&m.namespace),
+            "module": m.module.to_string(),
+            "immutable": m.is_immutable,
+            "flags": m.standard_accounts,
+            "tags": tags(state, m),
+        })).collect::<Vec<Value>>(),
+        "aliases": state.aliases.iter().map(|a| json!({
+            "alias": hex(a.alias.as_bytes(width)),
+            "canonical": hex(a.canonical.as_bytes(width)),
+        })).collect::<Vec<Value>>(),
+    })
+}
+
+fn louper_table(address: &Pubkey, state: &DiamondState) -> Value {
+    let width = state.selector_width;
+    let facets: Vec<Value> = state
+        .active_modules
+        .iter()
+        .map(|module| {
+            let mappings: Vec<&SelectorMapping> = state.selectors.iter().filter(|m| m.module == module.address).collect();
+            json!({
+                "facetAddress": module.address.to_string(),
+                "name": fixed_str(&module.name),
+                "functionSelectors": mappings.iter().map(|m| format!("0x{}", hex(m.selector.as_bytes(width)))).collect::<Vec<_>>(),
+                "functions": mappings.iter().map(|m| json!({
+                    "name": qualified_name(m),
+                    "selector": format!("0x{}", hex(m.selector.as_bytes(width))),
+                })).collect::<Vec<Value>>(),
+            })
+        })
+        .collect();
+    json!({ "address": address.to_string(), "network": "solana", "facets": facets })
+}
+
+/// Quote a CSV field when it needs it
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn csv_table(state: &DiamondState) -> String {
+    let width = state.selector_width;
+    let mut out = String::from("selector,namespace,function,module,module_name,immutable,flags,tags\n");
+    for mapping in &state.selectors {
+        let module_name = state
+            .active_modules
+            .iter()
+            .find(|m| m.address == mapping.module)
+            .map(|m| fixed_str(&m.name))
+            .unwrap_or("");
+        let row = [
+            hex(mapping.selector.as_bytes(width)),
+            fixed_str(&mapping.namespace).to_string(),
+            fixed_str(&mapping.function_name).to_string(),
+            mapping.module.to_string(),
+            module_name.to_string(),
+            mapping.is_immutable.to_string(),
+            mapping.standard_accounts.to_string(),
+            tags(state, mapping).join(";"),
+        ];
+        out.push_str(&row.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(","));
+        out.push('\n');
+    }
+    out
+}
+
+/// The routing table of the diamond at `address` in `format`
+pub fn export(address: &Pubkey, state: &DiamondState, format: Format) -> String {
+    match format {
+        Format::Json => serde_json::to_string_pretty(&json_table(address, state)).expect("JSON values serialize") + "\n",
+        Format::Csv => csv_table(state),
+        Format::Louper => serde_json::to_string_pretty(&louper_table(address, state)).expect("JSON values serialize") + "\n",
+    }
+}
+
+/// Fetch the diamond state and export its routing table
+pub fn run(rpc: &RpcClient, diamond_state: &Pubkey, format: Format) -> RpcResult<String> {
+    let account = rpc
+        .get_account(diamond_state)?
+        .ok_or_else(|| format!("diamond state {} not found", diamond_state))?;
+    let state = DiamondState::deserialize(&mut &account.data[..]).map_err(|e| format!("{}: {}", diamond_state, e))?;
+    Ok(export(diamond_state, &state, format))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diamond_router_native::diamond_cut::{apply_cuts, FacetCut};
+    
+    fn state() -> (Pubkey, Pubkey, DiamondState) {
+        let facet = Pubkey::new_unique();
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        apply_cuts(&mut state, &[
+            FacetCut { namespace: "vault".into(), ..FacetCut::add([1, 0, 0, 1], facet, "vault", "deposit") },
+            FacetCut::add([1, 0, 0, 2], facet, "vault", "get, \"total\""),
+        ])
+        .unwrap();
+        state.set_tags([1, 0, 0, 1].into(), &[*b"defi\0\0\0\0", *b"user\0\0\0\0"]).unwrap();
+        (Pubkey::new_unique(), facet, state)
+    }
+    
+    #[test]
+    fn test_louper_shape() {
+        let (address, facet, state) = state();
+        let louper: Value = serde_json::from_str(&export(&address, &state, Format::Louper)).unwrap();
+        assert_eq!(louper["address"], address.to_string());
+        let facets = louper["facets"].as_array().unwrap();
+        assert_eq!(facets.len(), 1);
+        assert_eq!(facets[0]["facetAddress"], facet.to_string());
+        assert_eq!(facets[0]["functionSelectors"], json!(["0x01000001", "0x01000002"]));
+        assert_eq!(facets[0]["functions"][0]["name"], "vault::deposit");
+    }
+    
+    #[test]
+    fn test_json_and_csv() {
+        let (address, _, state) = state();
+        let table: Value = serde_json::from_str(&export(&address, &state, Format::Json)).unwrap();
+        assert_eq!(table["selectors"][0]["tags"], json!(["defi", "user"]));
+        assert_eq!(table["facets"][0]["name"], "vault");
+        
+        let csv = export(&address, &state, Format::Csv);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].starts_with("01000001,vault,deposit,"));
+        assert!(lines[1].ends_with(",vault,false,0,defi;user"));
+        assert!(lines[2].contains(",\"get, \"\"total\"\"\","));
+        
+        assert_eq!(Format::parse("louper"), Ok(Format::Louper));
+        assert!(Format::parse("xml").is_err());
+    }
+}
@@ -0,0 +1,467 @@
+/*!
+ * Diamond CLI
+ *
+ * Usage:
+ *   diamond doctor --program <router_id> --state <diamond_state> [--url <http_rpc>] [--payer <pubkey>]
+ *   diamond capacity --program <router_id> --state <diamond_state> --payer <pubkey> [--url <http_rpc>]
+ *   diamond bundle --engine <http_block_engine> --txs <file> [--timeout <secs>]
+ *   diamond submit --program <router_id> --state <diamond_state> --tx <file> [--url <http_rpc>]
+ *                  [--allow <lint,...>] [--recent-slots <n>] [--yes]
+ *   diamond vanity --program <router_id> --owner <pubkey> --prefix <base58> [--start <n>] [--attempts <n>]
+ *   diamond verify-build --program <router_id> --state <diamond_state> --module <facet> [--so <file>] [--url <http_rpc>]
+ *   diamond plan-cuts --program <router_id> --state <diamond_state> --authority <pubkey> --cuts <file.json>
+ *                     [--fee-payer <pubkey>] [--atomic] [--out <prefix>] [--url <http_rpc>]
+ *                     [--nonce <account>[,<account>...] --nonce-authority <pubkey>]
+ *   diamond build-pause --program <router_id> --state <diamond_state> --authority <pubkey> --paused <true|false>
+ *                       --out <file> [--expiry-slot <slot>] [--fee-payer <pubkey>] [--url <http_rpc>]
+ *                       [--approvers <pubkey>[,<pubkey>...]] [--nonce <account> --nonce-authority <pubkey>]
+ *   diamond nonce-show --account <nonce_account> [--url <http_rpc>]
+ *   diamond nonce-create --account <nonce_account> --authority <pubkey> --payer <pubkey> --out <file> [--url <http_rpc>]
+ *   diamond nonce-authorize --account <nonce_account> --authority <pubkey> --new-authority <pubkey> --out <file>
+ *                           [--url <http_rpc>]
+ *   diamond sign --tx <file> --signer <uri>[,<uri>...] [--out <file>]
+ *   diamond decode <signature|base64_tx|file> --program <router_id> [--url <http_rpc>]
+ *   diamond apply --program <router_id> --state <diamond_state> --spec <file.toml|file.json> [--signer <uri>]
+ *                 [--watch] [--interval <secs>] [--out <cuts.json>] [--url <http_rpc>]
+ *   diamond loupe --state <diamond_state> [--format <json|csv|louper>] [--out <file>] [--url <http_rpc>]
+ *   diamond replay --program <router_id> --state <diamond_state> [--url <http_rpc>]
+ *   diamond upgrade <facet> --program <router_id> --state <diamond_state> --so <file> --signer <uri>
+ *                   [--new-program <keypair_file>] [--url <http_rpc>]
+ *
+ * `submit` previews a signed admin transaction (one base64 wire transaction)
+ * and only sends it when `--yes` is given and every lint finding
+ * (recent-call, not-executable, shadowing, capacity) is overridden with
+ * `--allow`. `vanity` searches offline for an `initialize` nonce giving the
+ * diamond address a prefix. `verify-build` compares a facet's deployed
+ * program with its attested build hash. `plan-cuts` splits a JSON batch of
+ * cuts into the fewest transactions that fit (or one proposal with
+ * `--atomic`), prints the plan and writes each unsigned transaction to
+ * `<prefix>-<n>.b64` for signing and `submit`. `build-pause` writes an
+ * unsigned pause transaction the same way; `--approvers` adds the co-signers
+ * a diamond with an unpause quorum needs to lift a pause. With `--nonce`, transactions are
+ * signed against durable nonces instead of a blockhash (one nonce account
+ * per transaction), so signers can take days; the `nonce-*` commands
+ * inspect nonce accounts and build their setup transactions. `sign` adds
+ * signatures to any of these transactions in place (or into `--out`), from
+ * keypair files, external signer programs (`exec:<program>`) or a Ledger
+ * (`usb://ledger`, see `diamond_cli::signer`), and exits non-zero while
+ * signatures are still missing. `decode` prints what a transaction does
+ * (instructions, cuts, dispatched functions and, once confirmed, events),
+ * for incident response and for checking a queued transaction before
+ * signing it. `apply` reconciles the routing table with a declared spec,
+ * proposing (or, in direct mode, applying) the cuts that correct any drift;
+ * with `--watch` it keeps doing so every `--interval` seconds (default 30).
+ * Without `--signer` it only reports drift, and `--out` writes the
+ * correcting cuts for `plan-cuts`. `loupe` exports the routing table as
+ * JSON (the default), CSV or the facet list Louper and other EIP-2535
+ * tooling read, to stdout or `--out`. `replay` re-runs every transaction
+ * since the diamond's `initialize` against the router built into the CLI
+ * and exits non-zero when the live account differs from the result (see
+ * `diamond_cli::replay`; built with the `replay` feature).
+ * `upgrade` writes `--so` to a buffer, upgrades the facet from it (or
+ * deploys it as `--new-program` and proposes moving the facet's selectors
+ * there), waits for finalization, checks the deployed hash and probes the
+ * selectors, in one run (see `diamond_cli::upgrade`).
+ */
+
+use diamond_cli::nonce::{self, DurableNonce};
+use diamond_cli::reconcile::{Pass, Reconciler, Spec};
+use diamond_cli::{
+    admin, capacity, decode, doctor, jito, lint, loupe, parse_flags, plan, rpc, sender, signer, simulate, upgrade, vanity,
+    verify,
+};
+#[cfg(feature = "replay")]
+use diamond_cli::replay;
+use solana_program::{hash::Hash, message::Message, pubkey::Pubkey};
+use std::collections::HashMap;
+use std::process::ExitCode;
+use std::time::Duration;
+
+const DEFAULT_URL: &str = "http://127.0.0.1:8899";
+
+fn pubkey_flag(flags: &HashMap<String, String>, name: &str) -> Result<Option<Pubkey>, String> {
+    flags
+        .get(name)
+        .map(|s| s.parse().map_err(|_| format!("--{}: invalid pubkey {}", name, s)))
+        .transpose()
+}
+
+fn required_pubkey(flags: &HashMap<String, String>, name: &str) -> Result<Pubkey, String> {
+    pubkey_flag(flags, name)?.ok_or_else(|| format!("--{} is required", name))
+}
+
+/// `--nonce` accounts with their current values, all under `--nonce-authority`
+fn nonce_flags(rpc: &rpc::RpcClient, flags: &HashMap<String, String>) -> Result<Option<Vec<(DurableNonce, Hash)>>, String> {
+    let Some(accounts) = flags.get("nonce") else {
+        return Ok(None);
+    };
+    let authority = required_pubkey(flags, "nonce-authority")?;
+    accounts
+        .split(',')
+        .map(|account| {
+            let account = account.trim().parse().map_err(|_| format!("--nonce: invalid pubkey {}", account))?;
+            let nonce = DurableNonce { account, authority };
+            Ok((nonce, nonce::fetch(rpc, &nonce)?))
+        })
+        .collect::<Result<Vec<_>, String>>()
+        .map(Some)
+}
+
+/// Write `message` as an unsigned base64 wire transaction
+fn write_transaction(path: &str, message: &Message) -> Result<(), String> {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    let wire = STANDARD.encode(rpc::unsigned_transaction(message));
+    std::fs::write(path, wire + "\n").map_err(|e| format!("{}: {}", path, e))?;
+    let signers = &message.account_keys[..message.header.num_required_signatures as usize];
+    let signers: Vec<String> = signers.iter().map(Pubkey::to_string).collect();
+    println!("Wrote {} (signers: {})", path, signers.join(", "));
+    Ok(())
+}
+
+fn count_flag(flags: &HashMap<String, String>, name: &str, default: u64) -> Result<u64, String> {
+    match flags.get(name) {
+        Some(n) => n.parse().map_err(|_| format!("--{}: invalid number {}", name, n)),
+        None => Ok(default),
+    }
+}
+
+fn run(args: &[String]) -> Result<bool, String> {
+    let (command, rest) = args.split_first().ok_or("missing subcommand")?;
+    // `decode` takes the transaction, `upgrade` the facet, as its one positional argument
+    let (target, rest) = match (command.as_str(), rest.split_first()) {
+        ("decode" | "upgrade", Some((target, rest))) if !target.starts_with("--") => (Some(target.as_str()), rest),
+        _ => (None, rest),
+    };
+    let flags = parse_flags(rest)?;
+    let rpc = rpc::RpcClient::new(flags.get("url").map(String::as_str).unwrap_or(DEFAULT_URL))?;
+    
+    match command.as_str() {
+        "doctor" => {
+            let unhealthy = doctor::run(
+                &rpc,
+                &required_pubkey(&flags, "program")?,
+                &required_pubkey(&flags, "state")?,
+                pubkey_flag(&flags, "payer")?,
+            )?;
+            if unhealthy > 0 {
+                eprintln!("{} unhealthy selector(s)", unhealthy);
+            }
+            Ok(unhealthy == 0)
+        }
+        "capacity" => capacity::run(
+            &rpc,
+            &required_pubkey(&flags, "program")?,
+            &required_pubkey(&flags, "state")?,
+            &required_pubkey(&flags, "payer")?,
+        ),
+        "bundle" => {
+            let engine = rpc::RpcClient::new(flags.get("engine").ok_or("--engine is required")?)?;
+            let path = flags.get("txs").ok_or("--txs is required")?;
+            let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+            let timeout = match flags.get("timeout") {
+                Some(secs) => secs.parse().map_err(|_| format!("--timeout: invalid seconds {}", secs))?,
+                None => 60,
+            };
+            let bundle = jito::parse_bundle(&text)?;
+            jito::submit(&engine, &bundle, Duration::from_secs(timeout))?;
+            Ok(true)
+        }
+        "submit" => {
+            use base64::{engine::general_purpose::STANDARD, Engine};
+            let path = flags.get("tx").ok_or("--tx is required")?;
+            let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+            let wire = STANDARD.decode(text.trim()).map_err(|e| format!("{}: {}", path, e))?;
+            let preview = simulate::preview_transaction(
+                &rpc,
+                &required_pubkey(&flags, "program")?,
+                &required_pubkey(&flags, "state")?,
+                &wire,
+            )?;
+            print!("{}", preview);
+            if !preview.succeeds() {
+                return Ok(false);
+            }
+            let allowed = lint::parse_allowed(flags.get("allow").map(String::as_str).unwrap_or(""))?;
+            let recent_slots = count_flag(&flags, "recent-slots", lint::DEFAULT_RECENT_SLOTS)?;
+            let findings = lint::run(&rpc, &preview, recent_slots)?;
+            for finding in &findings {
+                let mark = if allowed.contains(&finding.lint) { "allowed" } else { "⚠️" };
+                println!("  {} {}", mark, finding);
+            }
+            let blocking: Vec<&str> = findings
+                .iter()
+                .filter(|f| !allowed.contains(&f.lint))
+                .map(|f| f.lint.name())
+                .collect();
+            if !blocking.is_empty() {
+                println!("Not sent; review the findings or re-run with --allow {}", blocking.join(","));
+                return Ok(false);
+            }
+            if !flags.contains_key("yes") {
+                println!("Not sent; re-run with --yes to submit");
+                return Ok(true);
+            }
+            let signature = rpc.send_wire(&wire)?;
+            rpc.confirm(&signature, Duration::from_secs(60))?;
+            println!("Sent {}", signature);
+            Ok(true)
+        }
+        "vanity" => {
+            let prefix = flags.get("prefix").ok_or("--prefix is required")?;
+            let start = count_flag(&flags, "start", 0)?;
+            let attempts = count_flag(&flags, "attempts", 100_000_000)?;
+            let found = vanity::grind(
+                &required_pubkey(&flags, "program")?,
+                &required_pubkey(&flags, "owner")?,
+                prefix,
+                start,
+                attempts,
+            )?;
+            match found {
+                Some(found) => {
+                    println!("nonce {} -> {} (bump {})", found.nonce, found.address, found.bump);
+                    Ok(true)
+                }
+                None => {
+                    println!("No match in nonces {}..{}", start, start.saturating_add(attempts));
+                    Ok(false)
+                }
+            }
+        }
+        "verify-build" => {
+            let local_build = match flags.get("so") {
+                Some(path) => Some(std::fs::read(path).map_err(|e| format!("{}: {}", path, e))?),
+                None => None,
+            };
+            verify::run(
+                &rpc,
+                &required_pubkey(&flags, "program")?,
+                &required_pubkey(&flags, "state")?,
+                &required_pubkey(&flags, "module")?,
+                local_build.as_deref(),
+            )
+        }
+        "plan-cuts" => {
+            use borsh::BorshDeserialize;
+            let router = required_pubkey(&flags, "program")?;
+            let diamond = required_pubkey(&flags, "state")?;
+            let authority = required_pubkey(&flags, "authority")?;
+            let fee_payer = pubkey_flag(&flags, "fee-payer")?.unwrap_or(authority);
+            let path = flags.get("cuts").ok_or("--cuts is required")?;
+            let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+            let json: serde_json::Value = serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))?;
+            let cuts = json
+                .as_array()
+                .ok_or_else(|| format!("{}: expected a JSON array of cuts", path))?
+                .iter()
+                .map(admin::parse_cut)
+                .collect::<Result<Vec<_>, _>>()?;
+            let account = rpc.get_account(&diamond)?.ok_or_else(|| format!("diamond state {} not found", diamond))?;
+            let state = diamond_router_native::diamond_state::DiamondState::deserialize(&mut &account.data[..])
+                .map_err(|e| e.to_string())?;
+            let lifetime = match nonce_flags(&rpc, &flags)? {
+                Some(nonces) => plan::Lifetime::Nonces(nonces),
+                None => plan::Lifetime::Blockhash(rpc.latest_blockhash()?),
+            };
+            let plan = plan::plan_cuts(
+                &router,
+                &diamond,
+                &state,
+                &authority,
+                &fee_payer,
+                &cuts,
+                flags.contains_key("atomic"),
+                &lifetime,
+            )?;
+            print!("{}", plan);
+            if let Some(prefix) = flags.get("out") {
+                for (i, tx) in plan.transactions.iter().enumerate() {
+                    write_transaction(&format!("{}-{}.b64", prefix, i + 1), &tx.message)?;
+                }
+            }
+            Ok(plan.gaps.is_empty())
+        }
+        "build-pause" => {
+            let router = required_pubkey(&flags, "program")?;
+            let diamond = required_pubkey(&flags, "state")?;
+            let authority = required_pubkey(&flags, "authority")?;
+            let fee_payer = pubkey_flag(&flags, "fee-payer")?.unwrap_or(authority);
+            let paused = match flags.get("paused").map(String::as_str) {
+                Some("true") => true,
+                Some("false") => false,
+                _ => return Err("--paused must be true or false".to_string()),
+            };
+            let expiry_slot = flags.get("expiry-slot").map(|_| count_flag(&flags, "expiry-slot", 0)).transpose()?;
+            let approvers = flags
+                .get("approvers")
+                .map(|list| {
+                    list.split(',')
+                        .map(|key| key.trim().parse().map_err(|_| format!("--approvers: invalid pubkey {}", key)))
+                        .collect::<Result<Vec<Pubkey>, String>>()
+                })
+                .transpose()?
+                .unwrap_or_default();
+            let ix = admin::pause_instruction(&router, &diamond, &authority, &approvers, paused, expiry_slot);
+            let message = match nonce_flags(&rpc, &flags)?.as_deref() {
+                Some([(nonce, value)]) => nonce.message(&[ix], &fee_payer, value),
+                Some(_) => return Err("--nonce: a pause is one transaction; pass one nonce account".to_string()),
+                None => Message::new_with_blockhash(&[ix], Some(&fee_payer), &rpc.latest_blockhash()?),
+            };
+            write_transaction(flags.get("out").ok_or("--out is required")?, &message)?;
+            Ok(true)
+        }
+        "nonce-show" => {
+            let account = required_pubkey(&flags, "account")?;
+            let data = rpc.get_account(&account)?.ok_or_else(|| format!("{} not found", account))?.data;
+            let (value, authority) = nonce::parse(&data)?;
+            println!("Nonce {}\n  value:     {}\n  authority: {}", account, value, authority);
+            Ok(true)
+        }
+        "nonce-create" => {
+            let nonce = DurableNonce { account: required_pubkey(&flags, "account")?, authority: required_pubkey(&flags, "authority")? };
+            let payer = required_pubkey(&flags, "payer")?;
+            let instructions = nonce::create_instructions(&rpc, &payer, &nonce)?;
+            let message = Message::new_with_blockhash(&instructions, Some(&payer), &rpc.latest_blockhash()?);
+            write_transaction(flags.get("out").ok_or("--out is required")?, &message)?;
+            Ok(true)
+        }
+        "nonce-authorize" => {
+            let nonce = DurableNonce { account: required_pubkey(&flags, "account")?, authority: required_pubkey(&flags, "authority")? };
+            let ix = nonce::authorize_instruction(&nonce, &required_pubkey(&flags, "new-authority")?);
+            let message = Message::new_with_blockhash(&[ix], Some(&nonce.authority), &rpc.latest_blockhash()?);
+            write_transaction(flags.get("out").ok_or("--out is required")?, &message)?;
+            Ok(true)
+        }
+        "sign" => {
+            use base64::{engine::general_purpose::STANDARD, Engine};
+            let path = flags.get("tx").ok_or("--tx is required")?;
+            let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+            let mut wire = STANDARD.decode(text.trim()).map_err(|e| format!("{}: {}", path, e))?;
+            let signers = flags
+                .get("signer")
+                .ok_or("--signer is required")?
+                .split(',')
+                .map(|uri| signer::resolve(uri.trim()))
+                .collect::<Result<Vec<_>, _>>()?;
+            let signers: Vec<&dyn diamond_cli::keys::Signer> = signers.iter().map(|s| s.as_ref()).collect();
+            let missing = signer::sign_wire(&mut wire, &signers)?;
+            let out = flags.get("out").unwrap_or(path);
+            std::fs::write(out, STANDARD.encode(&wire) + "\n").map_err(|e| format!("{}: {}", out, e))?;
+            println!("Signed {} as {}", out, signers.iter().map(|s| s.pubkey().to_string()).collect::<Vec<_>>().join(", "));
+            if !missing.is_empty() {
+                let missing: Vec<String> = missing.iter().map(Pubkey::to_string).collect();
+                println!("Still needs: {}", missing.join(", "));
+            }
+            Ok(missing.is_empty())
+        }
+        "decode" => {
+            use base64::{engine::general_purpose::STANDARD, Engine};
+            let target = target.ok_or("decode needs a signature, base64 transaction or file")?;
+            let router = required_pubkey(&flags, "program")?;
+            let text = match std::fs::read_to_string(target) {
+                Ok(text) => text.trim().to_string(),
+                Err(_) => target.to_string(),
+            };
+            let lookup = |address: &Pubkey| rpc.get_account(address).ok().flatten().map(|account| account.data);
+            let decoded = match bs58::decode(&text).into_vec() {
+                Ok(signature) if signature.len() == 64 => decode::decode_signature(&rpc, &router, &text, &lookup)?,
+                _ => {
+                    let wire = STANDARD.decode(&text).map_err(|_| format!("{}: not a signature or base64 transaction", target))?;
+                    decode::decode_wire(&router, &wire, &lookup)?
+                }
+            };
+            print!("{}", decoded);
+            Ok(decoded.error.is_none())
+        }
+        "apply" => {
+            let path = flags.get("spec").ok_or("--spec is required")?;
+            let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+            let spec = Spec::parse(&text, path.ends_with(".toml")).map_err(|e| format!("{}: {}", path, e))?;
+            let signer = flags.get("signer").map(|uri| signer::resolve(uri)).transpose()?;
+            let mut reconciler = Reconciler {
+                rpc: &rpc,
+                router: required_pubkey(&flags, "program")?,
+                diamond: required_pubkey(&flags, "state")?,
+                spec,
+                signer: signer.as_deref(),
+                config: sender::SendConfig::default(),
+                pending: None,
+            };
+            if !flags.contains_key("watch") {
+                let pass = reconciler.pass()?;
+                print!("{}", pass);
+                if let (Pass::Drifted(drift), Some(out)) = (&pass, flags.get("out")) {
+                    let width = reconciler.state()?.selector_width;
+                    let cuts: Vec<_> = drift.cuts.iter().map(|cut| admin::cut_json(cut, width)).collect();
+                    let json = serde_json::to_string_pretty(&cuts).map_err(|e| e.to_string())?;
+                    std::fs::write(out, json + "\n").map_err(|e| format!("{}: {}", out, e))?;
+                    println!("Wrote {}", out);
+                }
+                return Ok(matches!(pass, Pass::InSync | Pass::Applied(..)));
+            }
+            let interval = Duration::from_secs(count_flag(&flags, "interval", 30)?);
+            let mut last = None;
+            loop {
+                match reconciler.pass() {
+                    Ok(pass) if last.as_ref() != Some(&pass) => {
+                        print!("{}", pass);
+                        last = Some(pass);
+                    }
+                    Ok(_) => {}
+                    Err(e) => eprintln!("apply: {}; retrying in {}s", e, interval.as_secs()),
+                }
+                std::thread::sleep(interval);
+            }
+        }
+        "loupe" => {
+            let format = loupe::Format::parse(flags.get("format").map(String::as_str).unwrap_or("json"))?;
+            let table = loupe::run(&rpc, &required_pubkey(&flags, "state")?, format)?;
+            match flags.get("out") {
+                Some(out) => {
+                    std::fs::write(out, table).map_err(|e| format!("{}: {}", out, e))?;
+                    println!("Wrote {}", out);
+                }
+                None => print!("{}", table),
+            }
+            Ok(true)
+        }
+        #[cfg(not(feature = "replay"))]
+        "replay" => Err("replay is not in this build; rebuild with --features replay".to_string()),
+        #[cfg(feature = "replay")]
+        "replay" => {
+            let report = replay::run(&rpc, &required_pubkey(&flags, "program")?, &required_pubkey(&flags, "state")?)?;
+            print!("{}", report);
+            Ok(report.is_consistent())
+        }
+        "upgrade" => {
+            let facet = target.ok_or("upgrade needs the facet program id")?;
+            let facet = facet.parse().map_err(|_| format!("invalid facet program id {}", facet))?;
+            let path = flags.get("so").ok_or("--so is required")?;
+            let elf = std::fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+            let signer = signer::resolve(flags.get("signer").ok_or("--signer is required")?)?;
+            let new_program = flags.get("new-program").map(|uri| signer::resolve(uri)).transpose()?;
+            upgrade::run(
+                &rpc,
+                &required_pubkey(&flags, "program")?,
+                &required_pubkey(&flags, "state")?,
+                &facet,
+                &elf,
+                signer.as_ref(),
+                new_program.as_deref(),
+            )
+        }
+        other => Err(format!("unknown subcommand: {}", other)),
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match run(&args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(2)
+        }
+    }
+}
@@ -0,0 +1,111 @@
+/*!
+ * Durable nonces
+ *
+ * A transaction signed against a blockhash expires after about a minute,
+ * which is too short for multisig signers working offline over days. A
+ * transaction whose first instruction advances a nonce account, and whose
+ * blockhash is that account's stored nonce, stays valid until the nonce
+ * moves. Each nonce serves one transaction, so a multi-transaction plan
+ * needs one nonce account per transaction.
+ */
+
+use serde_json::json;
+use solana_program::{
+    hash::Hash,
+    instruction::Instruction,
+    message::Message,
+    nonce::state::{State, Versions},
+    pubkey::Pubkey,
+    system_instruction,
+};
+
+use crate::rpc::{RpcClient, RpcResult};
+
+/// Size of a nonce account
+pub const NONCE_ACCOUNT_SPACE: usize = 80;
+
+/// Nonce account whose stored blockhash a transaction is signed against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DurableNonce {
+    pub account: Pubkey,
+    /// Must sign the transaction
+    pub authority: Pubkey,
+}
+
+impl DurableNonce {
+    /// `advance_nonce_account` followed by `instructions`
+    pub fn wrap(&self, instructions: &[Instruction]) -> Vec<Instruction> {
+        let mut wrapped = vec![system_instruction::advance_nonce_account(&self.account, &self.authority)];
+        wrapped.extend_from_slice(instructions);
+        wrapped
+    }
+    
+    /// Message for `instructions` signed against `nonce_value`
+    pub fn message(&self, instructions: &[Instruction], fee_payer: &Pubkey, nonce_value: &Hash) -> Message {
+        Message::new_with_blockhash(&self.wrap(instructions), Some(fee_payer), nonce_value)
+    }
+}
+
+/// Stored nonce value and authority of a nonce account's data
+pub fn parse(data: &[u8]) -> RpcResult<(Hash, Pubkey)> {
+    let versions: Versions = bincode::deserialize(data).map_err(|e| format!("not a nonce account: {}", e))?;
+    match versions.state() {
+        State::Initialized(nonce) => Ok((nonce.blockhash(), nonce.authority)),
+        State::Uninitialized => Err("nonce account is not initialized".to_string()),
+    }
+}
+
+/// Current nonce value of `nonce`, checking its authority
+pub fn fetch(rpc: &RpcClient, nonce: &DurableNonce) -> RpcResult<Hash> {
+    let account = rpc
+        .get_account(&nonce.account)?
+        .ok_or_else(|| format!("nonce account {} not found", nonce.account))?;
+    if account.owner != solana_program::system_program::id() {
+        return Err(format!("{} is not a system account", nonce.account));
+    }
+    let (value, authority) = parse(&account.data)?;
+    if authority != nonce.authority {
+        return Err(format!("nonce {} authority is {}, not {}", nonce.account, authority, nonce.authority));
+    }
+    Ok(value)
+}
+
+/// Instructions creating and initializing a rent-exempt nonce account
+/// (the new account must sign too)
+pub fn create_instructions(rpc: &RpcClient, payer: &Pubkey, nonce: &DurableNonce) -> RpcResult<Vec<Instruction>> {
+    let lamports = rpc
+        .call("getMinimumBalanceForRentExemption", json!([NONCE_ACCOUNT_SPACE]))?
+        .as_u64()
+        .ok_or("getMinimumBalanceForRentExemption returned no amount")?;
+    Ok(system_instruction::create_nonce_account(payer, &nonce.account, &nonce.authority, lamports))
+}
+
+/// Hand a nonce account to another authority (e.g. the multisig's fee payer)
+pub fn authorize_instruction(nonce: &DurableNonce, new_authority: &Pubkey) -> Instruction {
+    system_instruction::authorize_nonce_account(&nonce.account, &nonce.authority, new_authority)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_program::nonce::state::{Data, DurableNonce as NonceValue};
+    
+    #[test]
+    fn test_parse_and_wrap() {
+        let authority = Pubkey::new_unique();
+        let value = NonceValue::from_blockhash(&Hash::new_unique());
+        let data = bincode::serialize(&Versions::new(State::Initialized(Data::new(authority, value, 5000)))).unwrap();
+        assert_eq!(data.len(), NONCE_ACCOUNT_SPACE);
+        assert_eq!(parse(&data).unwrap(), (*value.as_hash(), authority));
+        let empty = bincode::serialize(&Versions::new(State::Uninitialized)).unwrap();
+        assert!(parse(&empty).is_err());
+        assert!(parse(&[1, 2]).is_err());
+        
+        let nonce = DurableNonce { account: Pubkey::new_unique(), authority };
+        let payload = Instruction::new_with_bytes(Pubkey::new_unique(), &[7], vec![]);
+        let message = nonce.message(&[payload], &authority, value.as_hash());
+        assert_eq!(message.recent_blockhash, *value.as_hash());
+        assert_eq!(message.account_keys[message.instructions[0].program_id_index as usize], solana_program::system_program::id());
+        assert_eq!(message.instructions.len(), 2);
+    }
+}
@@ -0,0 +1,273 @@
+/*!
+ * Transaction size budgeting for batch cuts
+ *
+ * A batch of cuts that doesn't fit one transaction fails at send time.
+ * `plan_cuts` splits it into the fewest transactions that fit, keeping the
+ * cuts in order. Each transaction takes the longest run of cuts that still
+ * fits, which is optimal when order is preserved. When a selector is
+ * removed in one transaction and added back in a later one, the plan
+ * reports the gap. An atomic plan instead puts the whole batch in one
+ * proposal and fails if that can't fit a single transaction. Its approval is
+ * signed by whoever the state says applies proposals; a council diamond gets
+ * no approval transaction, since the council applies it by motion.
+ */
+
+use diamond_router_native::{
+    diamond_cut::{FacetCut, FacetCutAction},
+    diamond_proposal::CutProposal,
+    diamond_state::DiamondState,
+    selector::Selector,
+};
+use solana_program::{hash::Hash, instruction::Instruction, message::Message, pubkey::Pubkey};
+use std::fmt;
+use std::ops::Range;
+
+use crate::admin;
+use crate::nonce::DurableNonce;
+use crate::rpc::{unsigned_transaction, RpcResult};
+
+/// Largest wire transaction a validator accepts (`PACKET_DATA_SIZE`)
+pub const MAX_TRANSACTION_SIZE: usize = 1232;
+
+/// One transaction of a plan
+#[derive(Debug, Clone)]
+pub struct PlannedTransaction {
+    /// Indices into the batch (empty for a proposal's approval)
+    pub cuts: Range<usize>,
+    /// Payload without the nonce advance
+    pub instructions: Vec<Instruction>,
+    pub message: Message,
+    /// Wire size in bytes, signatures included
+    pub size: usize,
+}
+
+/// How a batch of cuts will be sent
+#[derive(Debug, Clone)]
+pub struct CutPlan {
+    /// Proposal id when the batch goes through a proposal (all-or-nothing)
+    pub proposal_id: Option<u64>,
+    /// Who applies the proposal, for proposal plans
+    pub approver: Option<admin::Approver>,
+    pub transactions: Vec<PlannedTransaction>,
+    /// Selectors removed in one transaction and added back in a later one,
+    /// with the transaction indices (they are unrouted in between)
+    pub gaps: Vec<(Selector, usize, usize)>,
+}
+
+/// What keeps the planned transactions valid
+#[derive(Debug, Clone)]
+pub enum Lifetime {
+    /// A recent blockhash (valid for about a minute)
+    Blockhash(Hash),
+    /// One durable nonce per transaction, with its current value, for
+    /// signing offline over days
+    Nonces(Vec<(DurableNonce, Hash)>),
+}
+
+impl Lifetime {
+    /// Instructions as sent, for sizing (every nonce wrap is the same size)
+    fn sized(&self, instructions: &[Instruction]) -> Vec<Instruction> {
+        match self {
+            Lifetime::Nonces(nonces) if !nonces.is_empty() => nonces[0].0.wrap(instructions),
+            _ => instructions.to_vec(),
+        }
+    }
+}
+
+/// Wire size of a transaction carrying `instructions`
+pub fn transaction_size(instructions: &[Instruction], fee_payer: &Pubkey) -> usize {
+    unsigned_transaction(&Message::new(instructions, Some(fee_payer))).len()
+}
+
+/// Transaction `index` of a plan; `instructions` excludes any nonce advance
+fn planned(
+    instructions: &[Instruction],
+    fee_payer: &Pubkey,
+    lifetime: &Lifetime,
+    index: usize,
+    cuts: Range<usize>,
+) -> RpcResult<PlannedTransaction> {
+    let message = match lifetime {
+        Lifetime::Blockhash(blockhash) => Message::new_with_blockhash(instructions, Some(fee_payer), blockhash),
+        Lifetime::Nonces(nonces) => {
+            let (nonce, value) = nonces
+                .get(index)
+                .ok_or_else(|| format!("the plan needs a nonce account for transaction {}", index + 1))?;
+            nonce.message(instructions, fee_payer, value)
+        }
+    };
+    let size = unsigned_transaction(&message).len();
+    Ok(PlannedTransaction { cuts, instructions: instructions.to_vec(), message, size })
+}
+
+/// Split `cuts` into the fewest in-order transactions that fit, or with
+/// `atomic`, one proposal plus its approval (when the diamond takes one)
+///
+/// With nonces, transaction `i` uses the `i`th nonce; fewer nonces than
+/// transactions is an error.
+#[allow(clippy::too_many_arguments)]
+pub fn plan_cuts(
+    router: &Pubkey,
+    diamond: &Pubkey,
+    state: &DiamondState,
+    authority: &Pubkey,
+    fee_payer: &Pubkey,
+    cuts: &[FacetCut],
+    atomic: bool,
+    lifetime: &Lifetime,
+) -> RpcResult<CutPlan> {
+    if cuts.is_empty() {
+        return Err("no cuts to plan".to_string());
+    }
+    if atomic {
+        if cuts.len() > CutProposal::MAX_CUTS {
+            return Err(format!(
+                "{} cuts can't be all-or-nothing: a proposal holds at most {}",
+                cuts.len(),
+                CutProposal::MAX_CUTS
+            ));
+        }
+        let (propose, id) = admin::propose_instruction(router, diamond, state, authority, fee_payer, cuts);
+        let propose = planned(&[propose], fee_payer, lifetime, 0, 0..cuts.len())?;
+        if propose.size > MAX_TRANSACTION_SIZE {
+            return Err(format!(
+                "the proposal transaction is {} bytes (limit {}); shorten names or split the batch without --atomic",
+                propose.size, MAX_TRANSACTION_SIZE
+            ));
+        }
+        let approver = admin::approver(state);
+        let mut transactions = vec![propose];
+        if let Some(key) = approver.key() {
+            let approve = admin::approve_instruction(router, diamond, state, &key, id);
+            transactions.push(planned(&[approve], &key, lifetime, 1, cuts.len()..cuts.len())?);
+        }
+        return Ok(CutPlan { proposal_id: Some(id), approver: Some(approver), transactions, gaps: Vec::new() });
+    }
+    
+    let instructions = admin::cut_instructions(router, diamond, state, authority, cuts)?;
+    let fits = |run: &[Instruction]| transaction_size(&lifetime.sized(run), fee_payer) <= MAX_TRANSACTION_SIZE;
+    let mut transactions = Vec::new();
+    let mut start = 0;
+    while start < instructions.len() {
+        let mut end = start + 1;
+        if !fits(&instructions[start..end]) {
+            return Err(format!("cut {} ({:?}) alone exceeds {} bytes", start + 1, cuts[start].selector, MAX_TRANSACTION_SIZE));
+        }
+        while end < instructions.len() && fits(&instructions[start..end + 1]) {
+            end += 1;
+        }
+        transactions.push(planned(&instructions[start..end], fee_payer, lifetime, transactions.len(), start..end)?);
+        start = end;
+    }
+    
+    let tx_of = |index: usize| transactions.iter().position(|tx| tx.cuts.contains(&index)).unwrap_or_default();
+    let mut gaps = Vec::new();
+    for (removed_at, removal) in cuts.iter().enumerate().filter(|(_, c)| c.action == FacetCutAction::Remove) {
+        let readded = cuts
+            .iter()
+            .enumerate()
+            .skip(removed_at + 1)
+            .find(|(_, c)| c.selector == removal.selector && c.action == FacetCutAction::Add);
+        if let Some((added_at, _)) = readded {
+            let (from, to) = (tx_of(removed_at), tx_of(added_at));
+            if from != to {
+                gaps.push((removal.selector, from, to));
+            }
+        }
+    }
+    Ok(CutPlan { proposal_id: None, approver: None, transactions, gaps })
+}
+
+impl fmt::Display for CutPlan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let cut_count = self.transactions.iter().map(|tx| tx.cuts.len()).sum::<usize>();
+        match self.proposal_id {
+            Some(id) => writeln!(f, "Plan: proposal {} with {} cuts (all-or-nothing)", id, cut_count)?,
+            None => writeln!(f, "Plan: {} transaction(s) for {} cuts", self.transactions.len(), cut_count)?,
+        }
+        for (i, tx) in self.transactions.iter().enumerate() {
+            let what = if tx.cuts.is_empty() {
+                format!("approve ({})", self.approver.unwrap_or(admin::Approver::Council))
+            } else if self.proposal_id.is_some() {
+                format!("propose cuts {}-{}", tx.cuts.start + 1, tx.cuts.end)
+            } else {
+                format!("cuts {}-{}", tx.cuts.start + 1, tx.cuts.end)
+            };
+            writeln!(f, "  {}. {:<24} {:>5} bytes", i + 1, what, tx.size)?;
+        }
+        if let (Some(id), Some(admin::Approver::Council)) = (self.proposal_id, self.approver) {
+            writeln!(f, "  then a council motion applies proposal {}", id)?;
+        }
+        for (selector, from, to) in &self.gaps {
+            writeln!(
+                f,
+                "  ⚠️ {:?} is removed in transaction {} and re-added in {}; it is unrouted in between (use --atomic)",
+                selector,
+                from + 1,
+                to + 1
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_plan_splits_in_order() {
+        let (router, diamond, owner) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let state = DiamondState::new(owner, 255);
+        let module = Pubkey::new_unique();
+        let name = "x".repeat(60);
+        let mut cuts: Vec<FacetCut> =
+            (0..12u8).map(|i| FacetCut::add([1, 0, 0, i], module, "counter", &name)).collect();
+        cuts.insert(2, FacetCut::remove([9, 9, 9, 9]));
+        cuts.push(FacetCut::add([9, 9, 9, 9], module, "counter", "restored"));
+        
+        let recent = Lifetime::Blockhash(Hash::default());
+        let plan = plan_cuts(&router, &diamond, &state, &owner, &owner, &cuts, false, &recent).unwrap();
+        assert!(plan.transactions.len() > 1);
+        assert!(plan.transactions.iter().all(|tx| tx.size <= MAX_TRANSACTION_SIZE));
+        // Contiguous, in order, covering every cut
+        let mut next = 0;
+        for tx in &plan.transactions {
+            assert_eq!(tx.cuts.start, next);
+            next = tx.cuts.end;
+        }
+        assert_eq!(next, cuts.len());
+        // Greedy: no transaction could have taken the next cut too
+        let instructions = admin::cut_instructions(&router, &diamond, &state, &owner, &cuts).unwrap();
+        for tx in &plan.transactions[..plan.transactions.len() - 1] {
+            assert!(transaction_size(&instructions[tx.cuts.start..tx.cuts.end + 1], &owner) > MAX_TRANSACTION_SIZE);
+        }
+        assert_eq!(plan.gaps, [(Selector::from([9, 9, 9, 9]), 0, plan.transactions.len() - 1)]);
+        
+        let small = &cuts[..3];
+        let atomic = plan_cuts(&router, &diamond, &state, &owner, &owner, small, true, &recent).unwrap();
+        assert_eq!((atomic.proposal_id, atomic.transactions.len()), (Some(0), 2));
+        assert!(plan_cuts(&router, &diamond, &state, &owner, &owner, &cuts, true, &recent).is_err());
+        
+        // The approval is signed by whoever the state says applies proposals
+        let mut governed = state.clone();
+        let governance = Pubkey::new_unique();
+        governed.governance = Some(governance);
+        governed.governance_only = true;
+        let plan = plan_cuts(&router, &diamond, &governed, &governance, &governance, small, true, &recent).unwrap();
+        assert_eq!(plan.approver, Some(admin::Approver::Governance(governance)));
+        assert_eq!(plan.transactions[1].instructions[0].accounts[2].pubkey, governance);
+        governed.has_council = true;
+        let plan = plan_cuts(&router, &diamond, &governed, &governance, &governance, small, true, &recent).unwrap();
+        assert_eq!((plan.approver, plan.transactions.len()), (Some(admin::Approver::Council), 1));
+        
+        // One nonce per transaction, each advanced first
+        let nonce = |_| (DurableNonce { account: Pubkey::new_unique(), authority: owner }, Hash::new_unique());
+        let nonces = Lifetime::Nonces((0..2).map(nonce).collect());
+        let nonced = plan_cuts(&router, &diamond, &state, &owner, &owner, small, true, &nonces).unwrap();
+        assert_eq!(nonced.transactions[1].message.instructions.len(), 2);
+        assert!(nonced.transactions[0].size > atomic.transactions[0].size);
+        let one = Lifetime::Nonces(vec![nonce(0)]);
+        assert!(plan_cuts(&router, &diamond, &state, &owner, &owner, &cuts, false, &one).is_err());
+    }
+}
@@ -0,0 +1,311 @@
+/*!
+ * `diamond apply`
+ *
+ * Reconciles a diamond's routing table with a declared spec (TOML or JSON),
+ * so routing is administered from a reviewed file rather than by hand. The
+ * spec lists every selector the diamond should route; anything on chain
+ * that differs is drift:
+ *
+ * ```toml
+ * mode = "proposal"   # or "direct"
+ *
+ * [[selectors]]
+ * selector = "01000001"
+ * module = "<facet program id>"
+ * module_name = "counter"
+ * function_name = "increment"
+ * ```
+ *
+ * Entries take the keys of a JSON cut (`admin::parse_cut`) without
+ * `action`. In `proposal` mode, the default, drift is corrected by opening a
+ * cut proposal (at most `CutProposal::MAX_CUTS` cuts at a time) for the
+ * owner or council to approve; while it is open, later passes wait instead
+ * of proposing again. In `direct` mode the cuts are applied with
+ * `plan::plan_cuts` and `sender::send_plan`. Immutable selectors can't be
+ * corrected and are reported instead.
+ */
+
+use borsh::BorshDeserialize;
+use diamond_router_native::{
+    diamond_cut::{FacetCut, FacetCutAction},
+    diamond_proposal::CutProposal,
+    diamond_state::{DiamondState, SelectorMapping},
+};
+use serde_json::Value;
+use solana_program::pubkey::Pubkey;
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::admin;
+use crate::keys::Signer;
+use crate::plan::{self, Lifetime};
+use crate::rpc::{RpcClient, RpcResult};
+use crate::sender::{self, Outcome, SendConfig};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApplyMode {
+    /// Open a cut proposal for the owner or council to approve
+    Proposal,
+    /// Apply the cuts with the signer's own authority
+    Direct,
+}
+
+/// Declared routing table
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spec {
+    pub mode: ApplyMode,
+    /// One `Add` cut per routed selector
+    pub selectors: Vec<FacetCut>,
+}
+
+impl Spec {
+    /// Parse a spec; `toml` picks the format
+    pub fn parse(text: &str, toml: bool) -> Result<Self, String> {
+        let value: Value = if toml {
+            toml::from_str(text).map_err(|e| e.to_string())?
+        } else {
+            serde_json::from_str(text).map_err(|e| e.to_string())?
+        };
+        let mode = match value["mode"].as_str() {
+            None | Some("proposal") => ApplyMode::Proposal,
+            Some("direct") => ApplyMode::Direct,
+            Some(other) => return Err(format!("unknown mode {:?} (expected proposal or direct)", other)),
+        };
+        let mut seen = HashSet::new();
+        let selectors = value["selectors"]
+            .as_array()
+            .ok_or("spec needs a selectors list")?
+            .iter()
+            .map(|entry| {
+                let mut entry = entry.clone();
+                entry["action"] = Value::from("add");
+                let cut = admin::parse_cut(&entry)?;
+                if !seen.insert(cut.selector) {
+                    return Err(format!("{:?} is declared twice", cut.selector));
+                }
+                Ok(cut)
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(Self { mode, selectors })
+    }
+}
+
+/// Difference between the chain and a spec
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Drift {
+    /// Cuts that bring the chain to the spec, in order
+    pub cuts: Vec<FacetCut>,
+    /// Differences no cut can fix (immutable selectors)
+    pub blocked: Vec<String>,
+}
+
+impl Drift {
+    pub fn is_empty(&self) -> bool {
+        self.cuts.is_empty() && self.blocked.is_empty()
+    }
+}
+
+fn matches(mapping: &SelectorMapping, cut: &FacetCut) -> bool {
+    mapping.module == cut.module
+        && mapping.namespace_as_str() == cut.namespace
+        && mapping.function_name_as_str() == cut.function_name
+        && mapping.is_immutable == cut.is_immutable
+        && mapping.standard_accounts == cut.standard_accounts
+}
+
+/// Cuts taking `state` to `spec`
+///
+/// A changed selector becomes a `Replace` in proposal mode. Direct cuts
+/// can't replace, and `Replace` keeps the namespace, so otherwise it is a
+/// `Remove` followed by an `Add`.
+pub fn drift(state: &DiamondState, spec: &Spec) -> Drift {
+    let mut drift = Drift::default();
+    for declared in &spec.selectors {
+        match state.get_mapping(declared.selector) {
+            None => drift.cuts.push(declared.clone()),
+            Some(mapping) if matches(mapping, declared) => {}
+            Some(mapping) if mapping.is_immutable => drift
+                .blocked
+                .push(format!("{:?} ({}) is immutable and differs from the spec", declared.selector, mapping.qualified_name())),
+            Some(mapping) if spec.mode == ApplyMode::Proposal && mapping.namespace_as_str() == declared.namespace => {
+                drift.cuts.push(FacetCut { action: FacetCutAction::Replace, ..declared.clone() })
+            }
+            Some(_) => {
+                drift.cuts.push(FacetCut::remove(declared.selector));
+                drift.cuts.push(declared.clone());
+            }
+        }
+    }
+    for mapping in &state.selectors {
+        if spec.selectors.iter().any(|declared| declared.selector == mapping.selector) {
+            continue;
+        }
+        if mapping.is_immutable {
+            drift
+                .blocked
+                .push(format!("{:?} ({}) is immutable and not in the spec", mapping.selector, mapping.qualified_name()));
+        } else {
+            drift.cuts.push(FacetCut::remove(mapping.selector));
+        }
+    }
+    drift
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for cut in &self.cuts {
+            match cut.action {
+                FacetCutAction::Remove => writeln!(f, "  - Remove {:?}", cut.selector)?,
+                action => writeln!(f, "  - {:?} {:?} -> {} ({})", action, cut.selector, cut.function_name, cut.module)?,
+            }
+        }
+        for reason in &self.blocked {
+            writeln!(f, "  ⚠️ {}", reason)?;
+        }
+        Ok(())
+    }
+}
+
+/// Result of one reconcile pass
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pass {
+    InSync,
+    /// Drift found and not acted on (no signer, or only blocked differences)
+    Drifted(Drift),
+    /// The proposal opened by an earlier pass is still waiting for approval
+    AwaitingApproval(u64),
+    /// Opened proposal `id` for the first cuts of the drift
+    Proposed(u64, Drift),
+    /// Applied the drift directly in this many transactions
+    Applied(usize, Drift),
+}
+
+impl fmt::Display for Pass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Pass::InSync => writeln!(f, "✅ In sync with the spec"),
+            Pass::Drifted(drift) => write!(f, "Drift ({} cuts):\n{}", drift.cuts.len(), drift),
+            Pass::AwaitingApproval(id) => writeln!(f, "⏳ Waiting for proposal {} to be approved", id),
+            Pass::Proposed(id, drift) => write!(f, "Proposed {} ({} cuts):\n{}", id, drift.cuts.len(), drift),
+            Pass::Applied(count, drift) => write!(f, "Applied {} cuts in {} transaction(s):\n{}", drift.cuts.len(), count, drift),
+        }
+    }
+}
+
+/// Keeps one diamond at its spec, pass after pass
+pub struct Reconciler<'a> {
+    pub rpc: &'a RpcClient,
+    pub router: Pubkey,
+    pub diamond: Pubkey,
+    pub spec: Spec,
+    /// Authority and fee payer; without one, passes only report drift
+    pub signer: Option<&'a dyn Signer>,
+    pub config: SendConfig,
+    /// Proposal opened by an earlier pass and not yet applied
+    pub pending: Option<u64>,
+}
+
+impl Reconciler<'_> {
+    pub fn state(&self) -> RpcResult<DiamondState> {
+        let account = self
+            .rpc
+            .get_account(&self.diamond)?
+            .ok_or_else(|| format!("diamond state {} not found", self.diamond))?;
+        if account.owner != self.router {
+            return Err(format!("{} is not owned by router {}", self.diamond, self.router));
+        }
+        DiamondState::deserialize(&mut &account.data[..]).map_err(|e| e.to_string())
+    }
+    
+    /// Compare the chain with the spec and correct what can be corrected
+    pub fn pass(&mut self) -> RpcResult<Pass> {
+        if let Some(id) = self.pending {
+            if !sender::proposal_executed(self.router, self.diamond, id)(self.rpc)? {
+                return Ok(Pass::AwaitingApproval(id));
+            }
+            self.pending = None;
+        }
+        let state = self.state()?;
+        let mut drift = drift(&state, &self.spec);
+        if drift.is_empty() {
+            return Ok(Pass::InSync);
+        }
+        let signer = match self.signer {
+            Some(signer) if !drift.cuts.is_empty() => signer,
+            _ => return Ok(Pass::Drifted(drift)),
+        };
+        let authority = signer.pubkey();
+        
+        match self.spec.mode {
+            ApplyMode::Proposal => {
+                drift.cuts.truncate(CutProposal::MAX_CUTS);
+                let (propose, id) =
+                    admin::propose_instruction(&self.router, &self.diamond, &state, &authority, &authority, &drift.cuts);
+                let created = sender::proposal_exists(self.router, self.diamond, id);
+                sender::send(self.rpc, &[propose], &[signer], &self.config, Some(&created))?;
+                self.pending = Some(id);
+                Ok(Pass::Proposed(id, drift))
+            }
+            ApplyMode::Direct => {
+                let lifetime = Lifetime::Blockhash(self.rpc.latest_blockhash()?);
+                let plan =
+                    plan::plan_cuts(&self.router, &self.diamond, &state, &authority, &authority, &drift.cuts, false, &lifetime)?;
+                let outcomes = sender::send_plan(self.rpc, &self.router, &self.diamond, &plan, &[signer], &self.config)
+                    .map_err(|(done, e)| format!("stopped after {} of {} transactions: {}", done, plan.transactions.len(), e))?;
+                let sent = outcomes.iter().filter(|o| matches!(o, Outcome::Sent(_))).count();
+                Ok(Pass::Applied(sent, drift))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const SPEC: &str = r#"
+        [[selectors]]
+        selector = "01000001"
+        module = "11111111111111111111111111111112"
+        module_name = "counter"
+        function_name = "increment"
+        
+        [[selectors]]
+        selector = "01000002"
+        module = "11111111111111111111111111111112"
+        module_name = "counter"
+        function_name = "reset"
+    "#;
+    
+    #[test]
+    fn test_spec_formats() {
+        let spec = Spec::parse(SPEC, true).unwrap();
+        assert_eq!((spec.mode, spec.selectors.len()), (ApplyMode::Proposal, 2));
+        let json = r#"{"mode": "direct", "selectors": [{"selector": "0102", "module": "11111111111111111111111111111112"}]}"#;
+        assert_eq!(Spec::parse(json, false).unwrap().mode, ApplyMode::Direct);
+        assert!(Spec::parse(&SPEC.replace("01000002", "01000001"), true).is_err());
+        assert!(Spec::parse("mode = \"yolo\"\nselectors = []", true).is_err());
+    }
+    
+    #[test]
+    fn test_drift() {
+        let mut spec = Spec::parse(SPEC, true).unwrap();
+        let module = spec.selectors[0].module;
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        assert_eq!(drift(&state, &spec).cuts, spec.selectors);
+        
+        // Matching, changed, extra and immutable extra selectors
+        state.selectors.push(SelectorMapping::new([1, 0, 0, 1], module, "increment", false));
+        state.selectors.push(SelectorMapping::new([1, 0, 0, 2], Pubkey::new_unique(), "reset", false));
+        state.selectors.push(SelectorMapping::new([1, 0, 0, 3], module, "stale", false));
+        state.selectors.push(SelectorMapping::new([1, 0, 0, 4], module, "frozen", true));
+        let found = drift(&state, &spec);
+        let replace = FacetCut { action: FacetCutAction::Replace, ..spec.selectors[1].clone() };
+        assert_eq!(found.cuts, [replace, FacetCut::remove([1, 0, 0, 3])]);
+        assert_eq!(found.blocked.len(), 1);
+        
+        spec.mode = ApplyMode::Direct;
+        let found = drift(&state, &spec);
+        assert_eq!(found.cuts[..2], [FacetCut::remove([1, 0, 0, 2]), spec.selectors[1].clone()]);
+    }
+}
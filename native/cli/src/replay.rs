@@ -0,0 +1,384 @@
+/*!
+ * `diamond replay`
+ *
+ * Re-runs a diamond's transaction history against current code and
+ * compares the state it produces byte for byte with the live account. Every
+ * successful transaction naming the state (`getSignaturesForAddress`, paged
+ * back to genesis) is fetched and its top-level router instructions are run,
+ * oldest first, through the router linked into this CLI in a `Sandbox`, with
+ * the clock at the transaction's slot and block time.
+ *
+ * This is not a historical re-execution. The router is the one the CLI was
+ * built with, not the program deployed at each slot, so history from before
+ * an upgrade that changed behaviour or the state layout diverges. Accounts
+ * other than the router's are their present-day selves (oracle feeds,
+ * health statuses and the like), not their state at the time. Facets are
+ * stood in by no-ops: they can't write router-owned accounts, but their
+ * return data and CPIs back into the router are lost. What the replay
+ * answers is whether today's router, given the same instructions, arrives
+ * at the live state. On a diamond whose router hasn't changed since
+ * `initialize`, a difference is a write the router's own instructions don't
+ * account for, or corruption; an instruction that succeeded on chain but
+ * fails in the replay is reported too, as the point where the histories part.
+ *
+ * The sandbox starts from nothing: accounts that don't exist any more or
+ * belong to the router are reserved (as large as the diamond state, the
+ * router's largest account) so the router can create them, and every other
+ * account (signers included) is its live self with lamports to spare.
+ * Lamports aren't compared. Router calls made by CPI from another program
+ * (a governance program executing a proposal, say) aren't replayed, and
+ * their writes show up as differences; so does a history the node has
+ * pruned, which no longer reaches the diamond's `initialize`.
+ *
+ * The sandbox comes from the test utilities, so the module is only built
+ * with the CLI's `replay` feature.
+ */
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use diamond_router_native::diamond_state::DiamondState;
+use diamond_test_utils::sandbox::{changed_ranges, Sandbox, SandboxAccount};
+use serde_json::json;
+use solana_program::{
+    account_info::AccountInfo,
+    clock::Clock,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    message::VersionedMessage,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    system_program,
+};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::ops::Range;
+
+use crate::jito::decode_short_u16;
+use crate::rpc::{RpcAccount, RpcClient, RpcResult};
+
+/// Signatures per `getSignaturesForAddress` page
+const PAGE: usize = 1000;
+
+/// Lamports every replayed payer starts with
+const FUNDED_LAMPORTS: u64 = u64::MAX / 4;
+
+/// A confirmed transaction's router instructions
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoricalTransaction {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub instructions: Vec<Instruction>,
+}
+
+/// An instruction that succeeded on chain but not in the replay
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    pub signature: String,
+    pub index: usize,
+    pub error: ProgramError,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayReport {
+    pub transactions: usize,
+    pub instructions: usize,
+    pub divergences: Vec<Divergence>,
+    /// State data the history produces (empty if it never created the state)
+    pub expected: Vec<u8>,
+    pub live: Vec<u8>,
+}
+
+impl ReplayReport {
+    /// Byte ranges where the live state differs from the replayed one
+    pub fn mismatches(&self) -> Vec<Range<usize>> {
+        changed_ranges(&self.expected, &self.live)
+    }
+    
+    pub fn is_consistent(&self) -> bool {
+        self.divergences.is_empty() && self.expected == self.live
+    }
+}
+
+impl fmt::Display for ReplayReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Replayed {} router instructions from {} transactions", self.instructions, self.transactions)?;
+        for divergence in &self.divergences {
+            writeln!(
+                f,
+                "  {} instruction {} succeeded on chain but fails in the replay: {}",
+                divergence.signature, divergence.index, divergence.error
+            )?;
+        }
+        if self.expected.is_empty() {
+            return writeln!(f, "The history never creates the state (pruned by the node?)");
+        }
+        let mismatches = self.mismatches();
+        if mismatches.is_empty() {
+            return writeln!(f, "Live state matches the replay ({} bytes)", self.live.len());
+        }
+        writeln!(f, "Live state differs from the replay in {} ranges:", mismatches.len())?;
+        let excerpt = |data: &[u8], range: &Range<usize>| -> String {
+            let bytes = data.get(range.start..range.end.min(data.len())).unwrap_or_default();
+            let shown: String = bytes.iter().take(16).map(|b| format!("{:02x}", b)).collect();
+            if bytes.len() > 16 { format!("{}..", shown) } else { shown }
+        };
+        for range in &mismatches {
+            writeln!(
+                f,
+                "  bytes {}..{}: expected {} live {}",
+                range.start,
+                range.end,
+                excerpt(&self.expected, range),
+                excerpt(&self.live, range)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Top-level `router` instructions of a transaction, with the accounts its
+/// address lookups loaded (writable ones first)
+pub fn router_instructions(router: &Pubkey, message: &VersionedMessage, loaded: &[Pubkey]) -> Vec<Instruction> {
+    let keys: Vec<Pubkey> = message.static_account_keys().iter().chain(loaded).copied().collect();
+    message
+        .instructions()
+        .iter()
+        .filter(|ix| keys.get(ix.program_id_index as usize) == Some(router))
+        .filter_map(|ix| {
+            let accounts = ix
+                .accounts
+                .iter()
+                .map(|&i| {
+                    let i = i as usize;
+                    Some(AccountMeta {
+                        pubkey: *keys.get(i)?,
+                        is_signer: message.is_signer(i),
+                        is_writable: message.is_maybe_writable(i),
+                    })
+                })
+                .collect::<Option<Vec<_>>>()?;
+            Some(Instruction { program_id: *router, accounts, data: ix.data.clone() })
+        })
+        .collect()
+}
+
+/// Successful transactions naming `diamond`, oldest first
+pub fn fetch_history(rpc: &RpcClient, router: &Pubkey, diamond: &Pubkey) -> RpcResult<Vec<HistoricalTransaction>> {
+    let mut signatures = Vec::new();
+    let mut before: Option<String> = None;
+    loop {
+        let mut config = json!({ "limit": PAGE, "commitment": "confirmed" });
+        if let Some(before) = &before {
+            config["before"] = json!(before);
+        }
+        let page = rpc.call("getSignaturesForAddress", json!([diamond.to_string(), config]))?;
+        let entries = page.as_array().cloned().unwrap_or_default();
+        before = entries.last().and_then(|e| e["signature"].as_str()).map(String::from);
+        signatures.extend(
+            entries
+                .iter()
+                .filter(|e| e["err"].is_null())
+                .filter_map(|e| e["signature"].as_str().map(String::from)),
+        );
+        if entries.len() < PAGE {
+            break;
+        }
+    }
+    
+    signatures
+        .iter()
+        .rev()
+        .map(|signature| {
+            let result = rpc.call(
+                "getTransaction",
+                json!([signature, { "encoding": "base64", "commitment": "confirmed", "maxSupportedTransactionVersion": 0 }]),
+            )?;
+            if result.is_null() {
+                return Err(format!("transaction {} not found", signature));
+            }
+            let encoded = result["transaction"][0].as_str().ok_or("transaction data missing")?;
+            let wire = STANDARD.decode(encoded).map_err(|e| e.to_string())?;
+            let (count, offset) = decode_short_u16(&wire).ok_or("truncated signature count")?;
+            let message: VersionedMessage = bincode::deserialize(wire.get(offset + count as usize * 64..).ok_or("truncated signatures")?)
+                .map_err(|e| format!("{}: bad message: {}", signature, e))?;
+            let addresses = |kind: &str| -> Vec<Pubkey> {
+                result["meta"]["loadedAddresses"][kind]
+                    .as_array()
+                    .map(|keys| keys.iter().filter_map(|k| k.as_str()?.parse().ok()).collect())
+                    .unwrap_or_default()
+            };
+            let loaded: Vec<Pubkey> = addresses("writable").into_iter().chain(addresses("readonly")).collect();
+            Ok(HistoricalTransaction {
+                signature: signature.clone(),
+                slot: result["slot"].as_u64().unwrap_or_default(),
+                block_time: result["blockTime"].as_i64(),
+                instructions: router_instructions(router, &message, &loaded),
+            })
+        })
+        .collect()
+}
+
+/// Stand-in for the facets and other programs the router calls
+fn stand_in(_program_id: &Pubkey, _accounts: &[AccountInfo], _data: &[u8]) -> ProgramResult {
+    Ok(())
+}
+
+/// Replay `history` and compare the state it produces with `live`, the
+/// accounts as they are now (by address, absent when closed)
+pub fn replay(
+    router: &Pubkey,
+    diamond: &Pubkey,
+    history: &[HistoricalTransaction],
+    live: &HashMap<Pubkey, RpcAccount>,
+) -> ReplayReport {
+    let mut sandbox = Sandbox::new();
+    sandbox
+        .add_program(*router, diamond_router_native::process_instruction)
+        .add_program(system_program::id(), diamond_test_utils::sandbox::process_system_instruction);
+    let mut seeded: HashSet<Pubkey> = [*router, system_program::id()].into();
+    let mut report = ReplayReport {
+        transactions: history.len(),
+        instructions: 0,
+        divergences: Vec::new(),
+        expected: Vec::new(),
+        live: live.get(diamond).map(|account| account.data.clone()).unwrap_or_default(),
+    };
+    
+    for transaction in history {
+        sandbox.set_clock(Clock {
+            slot: transaction.slot,
+            unix_timestamp: transaction.block_time.unwrap_or_default(),
+            ..Clock::default()
+        });
+        let metas: Vec<&AccountMeta> = transaction.instructions.iter().flat_map(|ix| &ix.accounts).collect();
+        let keys: HashSet<Pubkey> = metas.iter().map(|meta| meta.pubkey).collect();
+        for meta in &metas {
+            if !seeded.insert(meta.pubkey) {
+                continue;
+            }
+            let key = &meta.pubkey;
+            match live.get(key) {
+                Some(account) if account.executable => {
+                    sandbox.add_program(*key, stand_in);
+                }
+                // Signers pay, whether or not they still exist
+                account if meta.is_signer || account.is_some_and(|account| &account.owner != router) => {
+                    sandbox.add_account(*key, SandboxAccount {
+                        lamports: FUNDED_LAMPORTS,
+                        data: account.map(|account| account.data.clone()).unwrap_or_default(),
+                        owner: account.map_or(system_program::id(), |account| account.owner),
+                        executable: false,
+                    });
+                }
+                _ => {
+                    sandbox.add_account(*key, SandboxAccount::reserved(DiamondState::SPACE));
+                }
+            }
+        }
+        
+        for (index, ix) in transaction.instructions.iter().enumerate() {
+            report.instructions += 1;
+            if let Err(error) = sandbox.process(ix).result {
+                report.divergences.push(Divergence { signature: transaction.signature.clone(), index, error });
+            }
+        }
+        // The runtime drops accounts left without lamports, so they can be created again
+        for key in &keys {
+            if sandbox.account(key).is_some_and(|account| account.lamports == 0 && &account.owner == router) {
+                sandbox.add_account(*key, SandboxAccount::reserved(DiamondState::SPACE));
+            }
+        }
+    }
+    
+    report.expected = sandbox
+        .account(diamond)
+        .filter(|account| &account.owner == router)
+        .map(|account| account.data.clone())
+        .unwrap_or_default();
+    report
+}
+
+/// Fetch the history of `diamond` and replay it against the live accounts
+pub fn run(rpc: &RpcClient, router: &Pubkey, diamond: &Pubkey) -> RpcResult<ReplayReport> {
+    let history = fetch_history(rpc, router, diamond)?;
+    let mut keys: Vec<Pubkey> = history
+        .iter()
+        .flat_map(|transaction| transaction.instructions.iter().flat_map(|ix| ix.accounts.iter().map(|m| m.pubkey)))
+        .chain([*diamond])
+        .collect();
+    keys.sort();
+    keys.dedup();
+    let mut live = HashMap::new();
+    for key in keys {
+        if let Some(account) = rpc.get_account(&key)? {
+            live.insert(key, account);
+        }
+    }
+    Ok(replay(router, diamond, &history, &live))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use borsh::BorshSerialize;
+    use diamond_router_native::{
+        diamond_cut::FacetCut,
+        diamond_state::diamond_address,
+        INITIALIZE_DISCRIMINATOR,
+    };
+    use solana_program::message::Message;
+    
+    fn transaction(slot: u64, instructions: Vec<Instruction>) -> HistoricalTransaction {
+        HistoricalTransaction { signature: format!("sig{}", slot), slot, block_time: Some(slot as i64), instructions }
+    }
+    
+    #[test]
+    fn test_replay_flags_unexplained_writes() {
+        let router = diamond_router_native::id();
+        let (owner, facet) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (diamond, bump) = diamond_address(&router, &owner, None);
+        let mut data = INITIALIZE_DISCRIMINATOR.to_vec();
+        (owner, bump, None::<Vec<FacetCut>>).serialize(&mut data).unwrap();
+        let initialize = Instruction {
+            program_id: router,
+            accounts: vec![
+                AccountMeta::new(diamond, false),
+                AccountMeta::new_readonly(owner, true),
+                AccountMeta::new(owner, true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+            data,
+        };
+        let mut state = DiamondState::new(owner, bump);
+        let cuts = crate::admin::cut_instructions(&router, &diamond, &state, &owner, &[
+            FacetCut::add([1, 0, 0, 1], facet, "counter", "increment"),
+        ])
+        .unwrap();
+        
+        // As the transactions arrive from the node
+        let message = Message::new(&[initialize.clone(), cuts[0].clone()], Some(&owner));
+        let history = vec![transaction(10, router_instructions(&router, &VersionedMessage::Legacy(message), &[]))];
+        // The owner pays, so the message makes it writable wherever it appears
+        let keys = |ix: &Instruction| (ix.data.clone(), ix.accounts.iter().map(|m| (m.pubkey, m.is_signer)).collect::<Vec<_>>());
+        assert_eq!(history[0].instructions.iter().map(keys).collect::<Vec<_>>(), vec![keys(&initialize), keys(&cuts[0])]);
+        
+        diamond_router_native::diamond_cut::apply_cuts(&mut state, &[FacetCut::add([1, 0, 0, 1], facet, "counter", "increment")])
+            .unwrap();
+        let mut expected = borsh::to_vec(&state).unwrap();
+        expected.resize(DiamondState::SPACE, 0);
+        let mut live = HashMap::from([(diamond, RpcAccount { owner: router, data: expected, executable: false })]);
+        let report = replay(&router, &diamond, &history, &live);
+        assert!(report.is_consistent(), "{}", report);
+        assert_eq!(report.instructions, 2);
+        
+        // A byte no instruction wrote
+        live.get_mut(&diamond).unwrap().data[200] ^= 1;
+        let report = replay(&router, &diamond, &history, &live);
+        assert_eq!(report.mismatches(), vec![200..201]);
+        assert!(report.to_string().contains("bytes 200..201"));
+        
+        // A pruned history never creates the state
+        let report = replay(&router, &diamond, &[transaction(11, cuts)], &live);
+        assert!(report.expected.is_empty() && !report.divergences.is_empty());
+    }
+}
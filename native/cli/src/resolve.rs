@@ -0,0 +1,454 @@
+/*!
+ * Account resolution
+ *
+ * Builds a facet call from its logical inputs: the selector's account schema
+ * (a config entry, see `diamond_config::schema`) says which accounts to pass
+ * in which order, PDAs are derived here, and the standard accounts the
+ * selector's flags ask for are appended.
+ */
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use diamond_router_native::{
+    diamond_config::{
+        key_bytes,
+        schema::{account_schema_key, AccountSchema, AccountSource, PdaProgram, SeedSource, ACCOUNT_SCHEMA_NAMESPACE},
+        ConfigEntry,
+    },
+    diamond_index::SelectorIndex,
+    diamond_relay::{accounts_hash, fee_vault, RelayNonce, RelayPayload},
+    diamond_session::{Session, SessionGrant},
+    diamond_signing,
+    diamond_state::{namespace_bytes, standard_account_ids, DiamondState, SelectorMapping, SignedVeto},
+    builtins::VETO_SIGNED_SELECTOR,
+    selector::Selector,
+    DISPATCH_COMPRESSED_DISCRIMINATOR, DISPATCH_DISCRIMINATOR, DISPATCH_INDEXED_DISCRIMINATOR,
+    DISPATCH_MAPPED_DISCRIMINATOR, DISPATCH_RELAYED_DISCRIMINATOR, DISPATCH_SESSION_DISCRIMINATOR,
+    OPEN_SESSION_DISCRIMINATOR,
+};
+use diamond_router_native::compress;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program, sysvar,
+};
+
+use crate::rpc::{RpcClient, RpcResult};
+
+/// Logical inputs of a call
+#[derive(Debug, Clone, Default)]
+pub struct CallInputs<'a> {
+    /// Accounts referenced by `AccountSource::Input`
+    pub accounts: &'a [Pubkey],
+    /// Seed bytes referenced by `SeedSource::Arg`
+    pub seeds: &'a [&'a [u8]],
+}
+
+/// Accounts after the module account for a call to `mapping`, in order
+pub fn resolve_accounts(
+    router: &Pubkey,
+    diamond_state: &Pubkey,
+    mapping: &SelectorMapping,
+    schema: &AccountSchema,
+    inputs: &CallInputs,
+) -> Result<Vec<AccountMeta>, String> {
+    let facet = mapping.module;
+    let mut metas: Vec<AccountMeta> = Vec::with_capacity(schema.accounts.len());
+    for (i, slot) in schema.accounts.iter().enumerate() {
+        let pubkey = match &slot.source {
+            AccountSource::Input(n) => *inputs
+                .accounts
+                .get(*n as usize)
+                .ok_or_else(|| format!("slot {} needs account input {}", i, n))?,
+            AccountSource::Fixed(key) => *key,
+            AccountSource::DiamondState => *diamond_state,
+            AccountSource::Pda { program, seeds } => {
+                let mut parts: Vec<Vec<u8>> = Vec::with_capacity(seeds.len());
+                for seed in seeds {
+                    parts.push(match seed {
+                        SeedSource::Literal(bytes) => bytes.clone(),
+                        SeedSource::DiamondState => diamond_state.to_bytes().to_vec(),
+                        SeedSource::Facet => facet.to_bytes().to_vec(),
+                        SeedSource::Slot(n) => metas
+                            .get(*n as usize)
+                            .ok_or_else(|| format!("slot {} seeds on later slot {}", i, n))?
+                            .pubkey
+                            .to_bytes()
+                            .to_vec(),
+                        SeedSource::Arg(n) => inputs
+                            .seeds
+                            .get(*n as usize)
+                            .ok_or_else(|| format!("slot {} needs seed input {}", i, n))?
+                            .to_vec(),
+                    });
+                }
+                let program = match program {
+                    PdaProgram::Facet => facet,
+                    PdaProgram::Router => *router,
+                    PdaProgram::Program(id) => *id,
+                };
+                let seeds: Vec<&[u8]> = parts.iter().map(Vec::as_slice).collect();
+                Pubkey::try_find_program_address(&seeds, &program)
+                    .ok_or_else(|| format!("slot {} has no valid PDA", i))?
+                    .0
+            }
+        };
+        metas.push(AccountMeta { pubkey, is_signer: slot.is_signer, is_writable: slot.is_writable });
+    }
+    for id in standard_account_ids(mapping.standard_accounts) {
+        if !metas.iter().any(|meta| meta.pubkey == id) {
+            metas.push(AccountMeta::new_readonly(id, false));
+        }
+    }
+    Ok(metas)
+}
+
+/// Published account schema for `selector`, if any
+pub fn fetch_account_schema(
+    rpc: &RpcClient,
+    router: &Pubkey,
+    diamond_state: &Pubkey,
+    state: &DiamondState,
+    selector: Selector,
+) -> RpcResult<Option<AccountSchema>> {
+    let namespace = namespace_bytes(ACCOUNT_SCHEMA_NAMESPACE).ok_or("account schema namespace too long")?;
+    let key = key_bytes(&account_schema_key(selector, state.selector_width)).map_err(|e| e.to_string())?;
+    let (address, _) = ConfigEntry::find_address(router, diamond_state, &namespace, &key);
+    let account = match rpc.get_account(&address)? {
+        Some(account) => account,
+        None => return Ok(None),
+    };
+    let entry = ConfigEntry::deserialize(&mut &account.data[..]).map_err(|e| e.to_string())?;
+    AccountSchema::try_from_slice(&entry.value)
+        .map(Some)
+        .map_err(|e| format!("bad account schema for {:?}: {}", selector, e))
+}
+
+/// `dispatch` instruction for `selector` with every account resolved
+///
+/// `args` are the facet's Borsh-encoded arguments (after the selector).
+pub fn dispatch_instruction(
+    rpc: &RpcClient,
+    router: &Pubkey,
+    diamond_state: &Pubkey,
+    selector: Selector,
+    args: &[u8],
+    inputs: &CallInputs,
+) -> RpcResult<Instruction> {
+    let account = rpc
+        .get_account(diamond_state)?
+        .ok_or_else(|| format!("diamond state {} not found", diamond_state))?;
+    let state = DiamondState::deserialize(&mut &account.data[..]).map_err(|e| e.to_string())?;
+    let mapping = state
+        .resolve_mapping(selector)
+        .ok_or_else(|| format!("selector {:?} is not registered", selector))?;
+    let schema = fetch_account_schema(rpc, router, diamond_state, &state, mapping.selector)?
+        .ok_or_else(|| format!("no account schema published for {}", mapping.qualified_name()))?;
+    let accounts = resolve_accounts(router, diamond_state, mapping, &schema, inputs)?;
+    
+    let mut ix_data = selector.as_bytes(state.selector_width).to_vec();
+    ix_data.extend_from_slice(args);
+    Ok(dispatch(router, diamond_state, &mapping.module, accounts, &ix_data))
+}
+
+/// Router `dispatch` of `ix_data` to `facet` with the given trailing accounts
+pub fn dispatch(
+    router: &Pubkey,
+    diamond_state: &Pubkey,
+    facet: &Pubkey,
+    accounts: Vec<AccountMeta>,
+    ix_data: &[u8],
+) -> Instruction {
+    let mut data = DISPATCH_DISCRIMINATOR.to_vec();
+    // Writing into a Vec cannot fail
+    ix_data.to_vec().serialize(&mut data).expect("borsh encode");
+    
+    let mut metas = vec![AccountMeta::new(*diamond_state, false), AccountMeta::new_readonly(*facet, false)];
+    metas.extend(accounts);
+    Instruction { program_id: *router, accounts: metas, data }
+}
+
+/// Router `dispatch_compressed` of `ix_data`, for payloads that compress well
+///
+/// Same accounts as `dispatch`. Worth it once the payload is a few hundred
+/// bytes of repetitive data (proofs, batches); the router pays for inflating.
+pub fn dispatch_compressed(
+    router: &Pubkey,
+    diamond_state: &Pubkey,
+    facet: &Pubkey,
+    accounts: Vec<AccountMeta>,
+    ix_data: &[u8],
+) -> Instruction {
+    let mut instruction = dispatch(router, diamond_state, facet, accounts, &[]);
+    instruction.data = DISPATCH_COMPRESSED_DISCRIMINATOR.to_vec();
+    compress::compress(ix_data).serialize(&mut instruction.data).expect("borsh encode");
+    instruction
+}
+
+/// Router `dispatch_mapped` of `ix_data`, with each account labeled by the
+/// schema slot it fills (`ROLE_EXTRA` for accounts after the schema's)
+///
+/// `schema_entry` is the selector's config entry in the `accounts`
+/// namespace. The labeled accounts may come in any order.
+pub fn dispatch_mapped(
+    router: &Pubkey,
+    diamond_state: &Pubkey,
+    facet: &Pubkey,
+    schema_entry: &Pubkey,
+    labeled: Vec<(u8, AccountMeta)>,
+    ix_data: &[u8],
+) -> Instruction {
+    let (roles, accounts): (Vec<u8>, Vec<AccountMeta>) = labeled.into_iter().unzip();
+    let mut metas = vec![AccountMeta::new_readonly(*schema_entry, false)];
+    metas.extend(accounts);
+    let mut instruction = dispatch(router, diamond_state, facet, metas, &[]);
+    instruction.data = DISPATCH_MAPPED_DISCRIMINATOR.to_vec();
+    (roles, ix_data.to_vec()).serialize(&mut instruction.data).expect("borsh encode");
+    instruction
+}
+
+/// Router `dispatch_indexed` of `ix_data`, for diamonds with a selector index
+///
+/// Same accounts as `dispatch`; the router resolves the selector through the
+/// index instead of the diamond state's selector table.
+pub fn dispatch_indexed(
+    router: &Pubkey,
+    diamond_state: &Pubkey,
+    facet: &Pubkey,
+    accounts: Vec<AccountMeta>,
+    ix_data: &[u8],
+) -> Instruction {
+    let mut instruction = dispatch(router, diamond_state, facet, accounts, ix_data);
+    instruction.data[..8].copy_from_slice(&DISPATCH_INDEXED_DISCRIMINATOR);
+    let (index, _) = SelectorIndex::find_address(router, diamond_state);
+    instruction.accounts.insert(1, AccountMeta::new_readonly(index, false));
+    instruction
+}
+
+/// `RelayPayload::accounts_hash` of the facet accounts a relayed call passes
+pub fn relayed_accounts_hash(accounts: &[AccountMeta]) -> [u8; 32] {
+    accounts_hash(accounts.iter().map(|m| (&m.pubkey, m.is_signer, m.is_writable)))
+}
+
+/// The Ed25519 verification and `dispatch_relayed` of a payload the user
+/// signed (`signature` over `payload.message()`), paid for by `relayer`
+///
+/// `accounts` must be the ones the payload's `accounts_hash` was made from.
+pub fn dispatch_relayed(
+    router: &Pubkey,
+    relayer: &Pubkey,
+    payload: &RelayPayload,
+    signature: &[u8; 64],
+    accounts: Vec<AccountMeta>,
+) -> [Instruction; 2] {
+    let (nonce, _) = RelayNonce::find_address(router, &payload.diamond, &payload.user);
+    let mut metas = vec![
+        AccountMeta::new(payload.diamond, false),
+        AccountMeta::new(nonce, false),
+        AccountMeta::new(*relayer, true),
+        AccountMeta::new(fee_vault(router, &payload.diamond, &payload.user).0, false),
+        AccountMeta::new_readonly(sysvar::instructions::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(payload.facet, false),
+    ];
+    metas.extend(accounts);
+    let mut data = DISPATCH_RELAYED_DISCRIMINATOR.to_vec();
+    payload.serialize(&mut data).expect("borsh encode");
+    [
+        diamond_signing::verify_instruction(&payload.user, signature, &payload.message()),
+        Instruction { program_id: *router, accounts: metas, data },
+    ]
+}
+
+/// The Ed25519 verification and `open_session` of a grant the user signed
+/// (`signature` over `grant.message()`), paid for by `payer`
+pub fn open_session(router: &Pubkey, payer: &Pubkey, grant: &SessionGrant, signature: &[u8; 64]) -> [Instruction; 2] {
+    let (nonce, _) = RelayNonce::find_address(router, &grant.diamond, &grant.user);
+    let (session, _) = Session::find_address(router, &grant.diamond, &grant.user, &grant.session_key);
+    let mut data = OPEN_SESSION_DISCRIMINATOR.to_vec();
+    grant.serialize(&mut data).expect("borsh encode");
+    [
+        diamond_signing::verify_instruction(&grant.user, signature, &grant.message()),
+        Instruction {
+            program_id: *router,
+            accounts: vec![
+                AccountMeta::new_readonly(grant.diamond, false),
+                AccountMeta::new(nonce, false),
+                AccountMeta::new(session, false),
+                AccountMeta::new(*payer, true),
+                AccountMeta::new_readonly(sysvar::instructions::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+            data,
+        },
+    ]
+}
+
+/// `dispatch_session` of a facet call signed by `session_key` for `user`
+///
+/// `accounts` must include the user's relay signer (see `relay_signer`).
+pub fn dispatch_session(
+    router: &Pubkey,
+    diamond: &Pubkey,
+    user: &Pubkey,
+    session_key: &Pubkey,
+    facet: &Pubkey,
+    ix_data: &[u8],
+    accounts: Vec<AccountMeta>,
+) -> Instruction {
+    let (session, _) = Session::find_address(router, diamond, user, session_key);
+    let mut metas = vec![
+        AccountMeta::new_readonly(*diamond, false),
+        AccountMeta::new_readonly(session, false),
+        AccountMeta::new_readonly(*session_key, true),
+        AccountMeta::new_readonly(*facet, false),
+    ];
+    metas.extend(accounts);
+    let mut data = DISPATCH_SESSION_DISCRIMINATOR.to_vec();
+    ix_data.to_vec().serialize(&mut data).expect("borsh encode");
+    Instruction { program_id: *router, accounts: metas, data }
+}
+
+/// The Ed25519 verification and `diamond::veto_signed` of a veto the
+/// guardian signed (`signature` over `veto.message()`)
+pub fn veto_signed(router: &Pubkey, veto: &SignedVeto, signature: &[u8; 64]) -> [Instruction; 2] {
+    let mut call = VETO_SIGNED_SELECTOR.to_vec();
+    veto.serialize(&mut call).expect("borsh encode");
+    let mut data = DISPATCH_DISCRIMINATOR.to_vec();
+    call.serialize(&mut data).expect("borsh encode");
+    [
+        diamond_signing::verify_instruction(&veto.guardian, signature, &veto.message()),
+        Instruction {
+            program_id: *router,
+            accounts: vec![
+                AccountMeta::new(veto.diamond, false),
+                AccountMeta::new_readonly(*router, false),
+                AccountMeta::new_readonly(sysvar::instructions::id(), false),
+            ],
+            data,
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diamond_router_native::diamond_config::schema::AccountSlot;
+    use diamond_router_native::diamond_relay::relay_signer;
+    use diamond_router_native::diamond_router::FACET_SIGNER_SEED;
+    use diamond_router_native::diamond_state::STD_SYSTEM_PROGRAM;
+    
+    #[test]
+    fn test_resolves_escrow_style_accounts() {
+        let (router, state, facet) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let depositor = Pubkey::new_unique();
+        let escrow_id = 7u64.to_le_bytes();
+        
+        // [depositor, escrow PDA of the facet, router-signed vault of the escrow]
+        let slot = |source, is_signer, is_writable| AccountSlot { source, is_signer, is_writable };
+        let schema = AccountSchema {
+            accounts: vec![
+                slot(AccountSource::Input(0), true, true),
+                slot(
+                    AccountSource::Pda {
+                        program: PdaProgram::Facet,
+                        seeds: vec![
+                            SeedSource::Literal(b"escrow".to_vec()),
+                            SeedSource::DiamondState,
+                            SeedSource::Slot(0),
+                            SeedSource::Arg(0),
+                        ],
+                    },
+                    false,
+                    true,
+                ),
+                slot(
+                    AccountSource::Pda {
+                        program: PdaProgram::Router,
+                        seeds: vec![
+                            SeedSource::Literal(FACET_SIGNER_SEED.to_vec()),
+                            SeedSource::DiamondState,
+                            SeedSource::Facet,
+                            SeedSource::Slot(1),
+                        ],
+                    },
+                    false,
+                    true,
+                ),
+            ],
+        };
+        let mapping = SelectorMapping::new([1, 2, 3, 4], facet, "create", false).with_standard_accounts(STD_SYSTEM_PROGRAM);
+        let inputs = CallInputs { accounts: &[depositor], seeds: &[&escrow_id] };
+        
+        let metas = resolve_accounts(&router, &state, &mapping, &schema, &inputs).unwrap();
+        let (escrow, _) =
+            Pubkey::find_program_address(&[b"escrow", state.as_ref(), depositor.as_ref(), &escrow_id], &facet);
+        let (vault, _) = Pubkey::find_program_address(
+            &[FACET_SIGNER_SEED, state.as_ref(), facet.as_ref(), escrow.as_ref()],
+            &router,
+        );
+        let keys: Vec<Pubkey> = metas.iter().map(|m| m.pubkey).collect();
+        assert_eq!(keys, vec![depositor, escrow, vault, system_program::id()]);
+        assert!(metas[0].is_signer && !metas[1].is_signer);
+        
+        // Missing inputs are reported, not guessed
+        let err = resolve_accounts(&router, &state, &mapping, &schema, &CallInputs::default()).unwrap_err();
+        assert!(err.contains("account input 0"));
+        
+        // Schemas fit a config entry
+        assert!(borsh::to_vec(&schema).unwrap().len() <= ConfigEntry::MAX_VALUE_LEN);
+    }
+    
+    #[test]
+    fn test_dispatch_relayed_verifies_the_signed_payload() {
+        let user = crate::keys::Keypair::from_seed(&[3; 32]);
+        let (router, relayer) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let accounts = vec![AccountMeta::new(Pubkey::new_unique(), false)];
+        let payload = RelayPayload {
+            domain: "app.example.com".into(),
+            diamond: Pubkey::new_unique(),
+            facet: Pubkey::new_unique(),
+            user: user.pubkey(),
+            nonce: 0,
+            expiry_slot: 500,
+            ix_data: vec![1, 0, 0, 1],
+            accounts_hash: relayed_accounts_hash(&accounts),
+            max_reimbursement: 5_000,
+        };
+        let signature = user.sign(&payload.message());
+        let [verify, relayed] = dispatch_relayed(&router, &relayer, &payload, &signature, accounts.clone());
+        
+        assert!(diamond_signing::verifies_signature(&verify, &user.pubkey(), &payload.message()));
+        assert_eq!(verify.data[16 + 32..16 + 96], signature);
+        assert_eq!(relayed.data[..8], DISPATCH_RELAYED_DISCRIMINATOR);
+        assert_eq!(RelayPayload::try_from_slice(&relayed.data[8..]).unwrap(), payload);
+        assert_eq!(relayed.accounts[6].pubkey, payload.facet);
+        assert_eq!(relayed_accounts_hash(&relayed.accounts[7..]), payload.accounts_hash);
+    }
+    
+    #[test]
+    fn test_session_grant_and_dispatch() {
+        let user = crate::keys::Keypair::from_seed(&[4; 32]);
+        let (router, diamond, session_key, facet) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let grant = SessionGrant {
+            domain: "game.example.com".into(),
+            diamond,
+            user: user.pubkey(),
+            session_key,
+            facet,
+            nonce: 0,
+            expiry_slot: 9_000,
+        };
+        let signature = user.sign(&grant.message());
+        let [verify, open] = open_session(&router, &session_key, &grant, &signature);
+        assert!(diamond_signing::verifies_signature(&verify, &user.pubkey(), &grant.message()));
+        assert_eq!(open.data[..8], OPEN_SESSION_DISCRIMINATOR);
+        assert_eq!(SessionGrant::try_from_slice(&open.data[8..]).unwrap(), grant);
+        
+        let signer = AccountMeta::new_readonly(relay_signer(&router, &diamond, &user.pubkey()).0, false);
+        let call = dispatch_session(&router, &diamond, &user.pubkey(), &session_key, &facet, &[1, 0, 0, 1], vec![signer]);
+        assert_eq!(call.accounts[1].pubkey, open.accounts[2].pubkey);
+        assert!(call.accounts[2].is_signer);
+        assert_eq!(Vec::<u8>::try_from_slice(&call.data[8..]).unwrap(), [1, 0, 0, 1]);
+    }
+}
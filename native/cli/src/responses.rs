@@ -0,0 +1,207 @@
+/*!
+ * Facet responses
+ *
+ * Registration manifests may declare, per function, the `schema_hash` of
+ * the Borsh schema its return data follows, and the router keeps those in
+ * each facet's record. `ResponseSchemas` collects the declarations, from
+ * chain or from manifests at hand, along with the Rust types the client
+ * knows, so the return data of a view call through the router decodes
+ * straight into its struct, and data under another schema than the one
+ * declared is refused instead of misread.
+ */
+
+use borsh::BorshDeserialize;
+use diamond_router_native::{
+    diamond_proposal::RegistrationManifest,
+    diamond_record::FacetRecord,
+    diamond_state::{schema_hash, DiamondState},
+    selector::Selector,
+};
+use solana_program::{instruction::Instruction, message::Message, pubkey::Pubkey};
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::errors;
+use crate::rpc::{RpcClient, RpcResult};
+use crate::verify;
+
+/// A facet response type and the schema description its hash is taken of
+pub trait Response: BorshDeserialize + fmt::Debug {
+    /// Schema description, e.g. `"(u64,Pubkey)"`; must match what the
+    /// facet's manifest hashed
+    const SCHEMA: &'static str;
+}
+
+/// Return data in a registered type's `Debug` form
+type Describe = fn(&[u8]) -> Option<String>;
+
+/// Selector -> declared return schema, and schema -> known decoder
+#[derive(Default)]
+pub struct ResponseSchemas {
+    declared: HashMap<Selector, [u8; 8]>,
+    decoders: HashMap<[u8; 8], Describe>,
+}
+
+fn describe_as<T: Response>(data: &[u8]) -> Option<String> {
+    T::try_from_slice(data).ok().map(|value| format!("{:?}", value))
+}
+
+impl ResponseSchemas {
+    pub fn from_manifests<'a>(manifests: impl IntoIterator<Item = &'a RegistrationManifest>) -> Self {
+        let mut schemas = Self::default();
+        for manifest in manifests {
+            schemas.add_manifest(manifest);
+        }
+        schemas
+    }
+    
+    /// Schemas kept in the facet records of `state`'s active modules
+    pub fn from_chain(rpc: &RpcClient, router: &Pubkey, diamond_state: &Pubkey, state: &DiamondState) -> RpcResult<Self> {
+        let mut schemas = Self::default();
+        for module in state.active_modules.iter().filter(|m| m.is_active) {
+            if let Some(record) = verify::record(rpc, router, diamond_state, &module.address)? {
+                schemas.add_record(state, &record);
+            }
+        }
+        Ok(schemas)
+    }
+    
+    /// Record the return schemas kept in `record`, for the selectors `state`
+    /// still routes to its facet
+    pub fn add_record(&mut self, state: &DiamondState, record: &FacetRecord) -> &mut Self {
+        for (selector, schema) in &record.return_schemas {
+            if state.get_mapping(*selector).is_some_and(|m| m.module == record.module) {
+                self.declared.insert(*selector, *schema);
+            }
+        }
+        self
+    }
+    
+    /// Record the return schemas `manifest` declares (undeclared ones are skipped)
+    pub fn add_manifest(&mut self, manifest: &RegistrationManifest) -> &mut Self {
+        for function in manifest.functions.iter().filter(|f| f.return_schema != [0; 8]) {
+            self.declared.insert(function.selector, function.return_schema);
+        }
+        self
+    }
+    
+    /// Teach the registry to decode responses following `T::SCHEMA`
+    pub fn register<T: Response>(&mut self) -> &mut Self {
+        self.decoders.insert(schema_hash(T::SCHEMA), describe_as::<T>);
+        self
+    }
+    
+    /// Return schema declared for `selector`
+    pub fn declared(&self, selector: &Selector) -> Option<[u8; 8]> {
+        self.declared.get(selector).copied()
+    }
+    
+    /// `data` returned by `selector`, as `T`
+    pub fn decode<T: Response>(&self, selector: &Selector, data: &[u8]) -> Result<T, String> {
+        let declared = self.declared(selector).ok_or_else(|| format!("{:?} declares no return schema", selector))?;
+        if declared != schema_hash(T::SCHEMA) {
+            return Err(format!("{:?} returns schema {:02x?}, not {}", selector, declared, T::SCHEMA));
+        }
+        T::try_from_slice(data).map_err(|e| format!("{:?} returned malformed data: {}", selector, e))
+    }
+    
+    /// `data` returned by `selector` in its registered type's `Debug` form,
+    /// if both the schema and a type for it are known
+    pub fn describe(&self, selector: &Selector, data: &[u8]) -> Option<String> {
+        let decoder = self.decoders.get(&self.declared(selector)?)?;
+        decoder(data)
+    }
+}
+
+/// Simulate `dispatch` (a router dispatch calling `selector`, see
+/// `resolve::dispatch`) and decode what the facet returned
+pub fn view<T: Response>(
+    rpc: &RpcClient,
+    schemas: &ResponseSchemas,
+    payer: &Pubkey,
+    selector: &Selector,
+    dispatch: Instruction,
+) -> RpcResult<T> {
+    let router = dispatch.program_id;
+    let simulation = rpc.simulate(&Message::new(&[dispatch], Some(payer)))?;
+    if let Some(err) = &simulation.err {
+        return Err(format!("{:?} failed: {}", selector, errors::decode(&router, err, &simulation.logs)));
+    }
+    let data = simulation.return_data.ok_or_else(|| format!("{:?} returned no data", selector))?;
+    schemas.decode(selector, &data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use borsh::BorshSerialize;
+    use diamond_router_native::{diamond_proposal::ManifestFunction, diamond_state::SelectorMapping};
+    
+    #[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+    struct Balance {
+        owner: Pubkey,
+        amount: u64,
+    }
+    
+    impl Response for Balance {
+        const SCHEMA: &'static str = "(Pubkey,u64)";
+    }
+    
+    impl Response for u64 {
+        const SCHEMA: &'static str = "u64";
+    }
+    
+    #[test]
+    fn test_known_selectors_decode_into_their_type() {
+        let function = |n: u8, name: &str, schema: Option<&str>| ManifestFunction {
+            selector: [1, 0, 0, n].into(),
+            function_name: name.to_string(),
+            standard_accounts: 0,
+            return_schema: schema.map(schema_hash).unwrap_or_default(),
+        };
+        let manifest = RegistrationManifest {
+            module_name: "vault".to_string(),
+            namespace: "vault".to_string(),
+            functions: vec![
+                function(1, "deposit", None),
+                function(2, "balance", Some(Balance::SCHEMA)),
+                function(3, "total", Some("u64")),
+            ],
+        };
+        let mut schemas = ResponseSchemas::from_manifests([&manifest]);
+        schemas.register::<Balance>();
+        let (deposit, balance, total) = ([1, 0, 0, 1].into(), [1, 0, 0, 2].into(), [1, 0, 0, 3].into());
+        
+        let returned = Balance { owner: Pubkey::new_unique(), amount: 7 };
+        let data = borsh::to_vec(&returned).unwrap();
+        assert_eq!(schemas.decode::<Balance>(&balance, &data), Ok(returned));
+        assert!(schemas.describe(&balance, &data).unwrap().starts_with("Balance { owner:"));
+        
+        // Wrong type, trailing bytes, undeclared schema, unregistered type
+        assert!(schemas.decode::<u64>(&balance, &data).is_err());
+        assert!(schemas.decode::<Balance>(&balance, &[data.as_slice(), &[0]].concat()).is_err());
+        assert!(schemas.decode::<u64>(&deposit, &7u64.to_le_bytes()).is_err());
+        assert_eq!(schemas.decode::<u64>(&total, &7u64.to_le_bytes()), Ok(7));
+        assert_eq!(schemas.describe(&total, &7u64.to_le_bytes()), None);
+    }
+    
+    #[test]
+    fn test_records_only_declare_routed_selectors() {
+        let (vault, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        state.selectors.push(SelectorMapping::new([1, 0, 0, 1], vault, "total", false));
+        state.selectors.push(SelectorMapping::new([1, 0, 0, 2], other, "total", false));
+        let record = FacetRecord {
+            diamond: Pubkey::new_unique(),
+            module: vault,
+            attestation: None,
+            versions: Vec::new(),
+            return_schemas: vec![([1, 0, 0, 1].into(), schema_hash("u64")), ([1, 0, 0, 2].into(), schema_hash("u64"))],
+            bump: 255,
+        };
+        let mut schemas = ResponseSchemas::default();
+        schemas.add_record(&state, &record);
+        assert_eq!(schemas.decode::<u64>(&[1, 0, 0, 1].into(), &7u64.to_le_bytes()), Ok(7));
+        assert_eq!(schemas.declared(&[1, 0, 0, 2].into()), None);
+    }
+}
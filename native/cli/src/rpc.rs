@@ -0,0 +1,314 @@
+/*!
+ * Minimal JSON-RPC client
+ *
+ * Plain `http://` endpoints only (local validators, RPC proxies); requests are
+ * sent as HTTP/1.0 so responses arrive unchunked and close the connection.
+ */
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde_json::{json, Value};
+use solana_program::{hash::Hash, instruction::Instruction, message::Message, pubkey::Pubkey};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use crate::keys::Signer;
+
+pub type RpcResult<T> = Result<T, String>;
+
+/// Account fields the CLI cares about
+#[derive(Debug, Clone)]
+pub struct RpcAccount {
+    pub owner: Pubkey,
+    pub data: Vec<u8>,
+    pub executable: bool,
+}
+
+/// Outcome of `simulateTransaction`
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    pub err: Option<Value>,
+    pub logs: Vec<String>,
+    pub return_data: Option<Vec<u8>>,
+    pub units_consumed: Option<u64>,
+    /// Post-simulation state of the requested accounts, in request order
+    pub accounts: Vec<Option<RpcAccount>>,
+}
+
+pub struct RpcClient {
+    host: String,
+    path: String,
+}
+
+impl RpcClient {
+    pub fn new(url: &str) -> RpcResult<Self> {
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| format!("only http:// endpoints are supported: {}", url))?;
+        let (host, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+        let host = if host.contains(':') { host.to_string() } else { format!("{}:80", host) };
+        Ok(Self { host, path: path.to_string() })
+    }
+    
+    pub fn call(&self, method: &str, params: Value) -> RpcResult<Value> {
+        let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }).to_string();
+        let request = format!(
+            "POST {} HTTP/1.0\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            self.path,
+            self.host,
+            body.len(),
+            body
+        );
+        
+        let mut stream = TcpStream::connect(&self.host).map_err(|e| format!("{}: {}", self.host, e))?;
+        stream.write_all(request.as_bytes()).map_err(|e| e.to_string())?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).map_err(|e| e.to_string())?;
+        
+        let payload = response
+            .split_once("\r\n\r\n")
+            .map(|(_, body)| body)
+            .ok_or("malformed HTTP response")?;
+        let mut value: Value = serde_json::from_str(payload).map_err(|e| e.to_string())?;
+        if let Some(err) = value.get("error") {
+            return Err(format!("{} failed: {}", method, err));
+        }
+        Ok(value["result"].take())
+    }
+    
+    pub fn get_account(&self, pubkey: &Pubkey) -> RpcResult<Option<RpcAccount>> {
+        let result = self.call(
+            "getAccountInfo",
+            json!([pubkey.to_string(), { "encoding": "base64" }]),
+        )?;
+        parse_account(&result["value"])
+    }
+    
+    /// Simulate without signatures, letting the node supply a fresh blockhash
+    pub fn simulate(&self, message: &Message) -> RpcResult<Simulation> {
+        self.simulate_wire(&unsigned_transaction(message), &[])
+    }
+    
+    /// Simulate a wire transaction (signatures unchecked, blockhash replaced),
+    /// returning the post-simulation state of `addresses`
+    pub fn simulate_wire(&self, wire: &[u8], addresses: &[Pubkey]) -> RpcResult<Simulation> {
+        let mut config = json!({ "encoding": "base64", "sigVerify": false, "replaceRecentBlockhash": true });
+        if !addresses.is_empty() {
+            let addresses: Vec<String> = addresses.iter().map(Pubkey::to_string).collect();
+            config["accounts"] = json!({ "addresses": addresses, "encoding": "base64" });
+        }
+        let result = self.call("simulateTransaction", json!([STANDARD.encode(wire), config]))?;
+        let value = &result["value"];
+        let return_data = match value.get("returnData") {
+            Some(data) if !data.is_null() => Some(decode_base64_pair(&data["data"])?),
+            _ => None,
+        };
+        let accounts = match value["accounts"].as_array() {
+            Some(accounts) => accounts.iter().map(parse_account).collect::<RpcResult<_>>()?,
+            None => Vec::new(),
+        };
+        Ok(Simulation {
+            err: value.get("err").filter(|e| !e.is_null()).cloned(),
+            logs: value["logs"]
+                .as_array()
+                .map(|logs| logs.iter().filter_map(|l| l.as_str().map(String::from)).collect())
+                .unwrap_or_default(),
+            return_data,
+            units_consumed: value["unitsConsumed"].as_u64(),
+            accounts,
+        })
+    }
+    
+    pub fn slot(&self) -> RpcResult<u64> {
+        self.call("getSlot", json!([{ "commitment": "confirmed" }]))?
+            .as_u64()
+            .ok_or_else(|| "getSlot returned no slot".to_string())
+    }
+    
+    /// Newest-first (signature, slot) pairs of up to `limit` transactions
+    /// touching `address`, older than signature `before` if given
+    pub fn signatures_for(&self, address: &Pubkey, before: Option<&str>, limit: usize) -> RpcResult<Vec<(String, u64)>> {
+        let mut config = json!({ "limit": limit, "commitment": "confirmed" });
+        if let Some(before) = before {
+            config["before"] = json!(before);
+        }
+        let result = self.call("getSignaturesForAddress", json!([address.to_string(), config]))?;
+        Ok(result
+            .as_array()
+            .map(|entries| {
+                entries
+                    .iter()
+                    .filter_map(|e| Some((e["signature"].as_str()?.to_string(), e["slot"].as_u64()?)))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+    
+    /// Log messages of a confirmed transaction (empty if the node has pruned it)
+    pub fn transaction_logs(&self, signature: &str) -> RpcResult<Vec<String>> {
+        let result = self.call(
+            "getTransaction",
+            json!([signature, { "encoding": "json", "commitment": "confirmed", "maxSupportedTransactionVersion": 0 }]),
+        )?;
+        Ok(result["meta"]["logMessages"]
+            .as_array()
+            .map(|logs| logs.iter().filter_map(|l| l.as_str().map(String::from)).collect())
+            .unwrap_or_default())
+    }
+    
+    /// Lamports an account of `len` bytes needs to be rent exempt
+    pub fn rent_exempt(&self, len: usize) -> RpcResult<u64> {
+        self.call("getMinimumBalanceForRentExemption", json!([len]))?
+            .as_u64()
+            .ok_or_else(|| "getMinimumBalanceForRentExemption returned no balance".to_string())
+    }
+    
+    pub fn is_healthy(&self) -> bool {
+        matches!(self.call("getHealth", json!([])), Ok(Value::String(s)) if s == "ok")
+    }
+    
+    pub fn latest_blockhash(&self) -> RpcResult<Hash> {
+        self.call("getLatestBlockhash", json!([{ "commitment": "confirmed" }]))?["value"]["blockhash"]
+            .as_str()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| "blockhash missing".to_string())
+    }
+    
+    /// Airdrop and wait for it to confirm (test validators and devnet only)
+    pub fn airdrop(&self, to: &Pubkey, lamports: u64, timeout: Duration) -> RpcResult<()> {
+        let signature = self.call("requestAirdrop", json!([to.to_string(), lamports]))?;
+        let signature = signature.as_str().ok_or("requestAirdrop returned no signature")?;
+        self.confirm(signature, timeout)
+    }
+    
+    /// Sign with the fee payer first, submit, and wait for confirmation
+    pub fn send_and_confirm(
+        &self,
+        instructions: &[Instruction],
+        signers: &[&dyn Signer],
+        timeout: Duration,
+    ) -> RpcResult<String> {
+        let payer = signers.first().ok_or("no signers")?.pubkey();
+        let message = Message::new_with_blockhash(instructions, Some(&payer), &self.latest_blockhash()?);
+        let (wire, signature) = signed_transaction(&message, signers)?;
+        self.send_wire(&wire)?;
+        self.confirm(&signature, timeout)?;
+        Ok(signature)
+    }
+    
+    /// Submit an already signed wire transaction; returns its signature
+    pub fn send_wire(&self, wire: &[u8]) -> RpcResult<String> {
+        self.call(
+            "sendTransaction",
+            json!([STANDARD.encode(wire), { "encoding": "base64", "preflightCommitment": "confirmed" }]),
+        )?
+        .as_str()
+        .map(String::from)
+        .ok_or_else(|| "sendTransaction returned no signature".to_string())
+    }
+    
+    pub fn confirm(&self, signature: &str, timeout: Duration) -> RpcResult<()> {
+        let started = Instant::now();
+        loop {
+            let result = self.call("getSignatureStatuses", json!([[signature]]))?;
+            let status = &result["value"][0];
+            if !status.is_null() {
+                if !status["err"].is_null() {
+                    return Err(format!("{} failed: {}", signature, status["err"]));
+                }
+                if matches!(status["confirmationStatus"].as_str(), Some("confirmed" | "finalized")) {
+                    return Ok(());
+                }
+            }
+            if started.elapsed() >= timeout {
+                return Err(format!("{} not confirmed after {:?}", signature, timeout));
+            }
+            sleep(Duration::from_millis(400));
+        }
+    }
+}
+
+/// Account JSON (base64 encoding) as returned by `getAccountInfo`,
+/// simulations and `accountNotification`
+pub fn parse_account(value: &Value) -> RpcResult<Option<RpcAccount>> {
+    if value.is_null() {
+        return Ok(None);
+    }
+    let owner = value["owner"]
+        .as_str()
+        .and_then(|s| s.parse().ok())
+        .ok_or("account owner missing")?;
+    Ok(Some(RpcAccount {
+        owner,
+        data: decode_base64_pair(&value["data"])?,
+        executable: value["executable"].as_bool().unwrap_or(false),
+    }))
+}
+
+/// Decode the RPC's `["<base64>", "base64"]` pair
+fn decode_base64_pair(value: &Value) -> RpcResult<Vec<u8>> {
+    let encoded = value[0].as_str().ok_or("expected base64 data")?;
+    STANDARD.decode(encoded).map_err(|e| e.to_string())
+}
+
+/// Wire-format transaction with zeroed signatures
+pub fn unsigned_transaction(message: &Message) -> Vec<u8> {
+    let signatures = message.header.num_required_signatures as usize;
+    // compact-u16 length prefix (signature counts are always < 128)
+    let mut tx = vec![signatures as u8];
+    tx.resize(1 + signatures * 64, 0);
+    tx.extend_from_slice(&message.serialize());
+    tx
+}
+
+/// Wire-format transaction signed by `signers` (in the message's signer order)
+///
+/// Returns the transaction and its base58 signature.
+pub fn signed_transaction(message: &Message, signers: &[&dyn Signer]) -> RpcResult<(Vec<u8>, String)> {
+    let mut tx = unsigned_transaction(message);
+    let payload = message.serialize();
+    let required = message.header.num_required_signatures as usize;
+    for (i, key) in message.account_keys.iter().take(required).enumerate() {
+        let signer = signers
+            .iter()
+            .find(|k| &k.pubkey() == key)
+            .ok_or_else(|| format!("missing signer {}", key))?;
+        tx[1 + i * 64..1 + (i + 1) * 64].copy_from_slice(&signer.try_sign(&payload)?);
+    }
+    let signature = bs58::encode(&tx[1..65]).into_string();
+    Ok((tx, signature))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_program::instruction::{AccountMeta, Instruction};
+    
+    #[test]
+    fn test_url_parsing() {
+        let client = RpcClient::new("http://127.0.0.1:8899").unwrap();
+        assert_eq!((client.host.as_str(), client.path.as_str()), ("127.0.0.1:8899", "/"));
+        let client = RpcClient::new("http://rpc.local/v1").unwrap();
+        assert_eq!((client.host.as_str(), client.path.as_str()), ("rpc.local:80", "/v1"));
+        assert!(RpcClient::new("https://api.devnet.solana.com").is_err());
+    }
+    
+    #[test]
+    fn test_unsigned_transaction_layout() {
+        let payer = Pubkey::new_unique();
+        let ix = Instruction::new_with_bytes(
+            Pubkey::new_unique(),
+            &[1, 2, 3],
+            vec![AccountMeta::new(payer, true)],
+        );
+        let message = Message::new(&[ix], Some(&payer));
+        let tx = unsigned_transaction(&message);
+        assert_eq!(tx[0], 1);
+        assert!(tx[1..65].iter().all(|b| *b == 0));
+        assert_eq!(&tx[65..], &message.serialize()[..]);
+    }
+}
@@ -0,0 +1,280 @@
+/*!
+ * Retrying transaction sender
+ *
+ * `RpcClient::send_and_confirm` gives up when a transaction expires, which
+ * can leave a multi-transaction cut pipeline half-applied. `send` re-signs
+ * with a fresh blockhash and retries with exponential backoff. It waits
+ * until the blockhash is really expired, or it has seen the signature land,
+ * before deciding an attempt failed. A durable nonce replaces the blockhash
+ * altogether, so a retry is byte-identical until the nonce moves.
+ *
+ * Before each retry, an optional `AlreadyApplied` check asks the chain
+ * whether the effect already happened. For proposals, that is "proposal
+ * `id` exists" or "proposal `id` executed", so a retry can't open a
+ * duplicate proposal or fail on an executed one. `send_plan` sends a
+ * `plan::CutPlan` this way and reports where it stopped.
+ */
+
+use borsh::BorshDeserialize;
+use diamond_router_native::diamond_proposal::CutProposal;
+use serde_json::json;
+use solana_program::{
+    hash::Hash,
+    instruction::Instruction,
+    message::Message,
+    pubkey::Pubkey,
+};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use crate::keys::Signer;
+use crate::nonce::{self, DurableNonce};
+use crate::plan::CutPlan;
+use crate::rpc::{signed_transaction, RpcClient, RpcResult};
+
+/// How often signature status and block height are polled
+const POLL_INTERVAL: Duration = Duration::from_millis(400);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Commitment {
+    Processed,
+    Confirmed,
+    Finalized,
+}
+
+impl Commitment {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Commitment::Processed => "processed",
+            Commitment::Confirmed => "confirmed",
+            Commitment::Finalized => "finalized",
+        }
+    }
+    
+    pub fn parse(name: &str) -> Option<Self> {
+        [Commitment::Processed, Commitment::Confirmed, Commitment::Finalized]
+            .into_iter()
+            .find(|c| c.as_str() == name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SendConfig {
+    pub commitment: Commitment,
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// With a durable nonce there is no expiry, so each attempt waits this long
+    pub nonce_timeout: Duration,
+    pub nonce: Option<DurableNonce>,
+}
+
+impl Default for SendConfig {
+    fn default() -> Self {
+        Self {
+            commitment: Commitment::Confirmed,
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(8),
+            nonce_timeout: Duration::from_secs(60),
+            nonce: None,
+        }
+    }
+}
+
+impl SendConfig {
+    /// Wait before attempt `attempt` (0 is the first and doesn't wait)
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        match attempt {
+            0 => Duration::ZERO,
+            n => self.initial_backoff.saturating_mul(1 << (n - 1).min(16)).min(self.max_backoff),
+        }
+    }
+}
+
+/// Whether a transaction's effect is already on chain
+pub type AlreadyApplied<'a> = &'a dyn Fn(&RpcClient) -> RpcResult<bool>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// Landed at the configured commitment
+    Sent(String),
+    /// The idempotency check found the effect already applied
+    AlreadyApplied,
+}
+
+/// Proposal `id` of `diamond` has been created
+pub fn proposal_exists(router: Pubkey, diamond: Pubkey, id: u64) -> impl Fn(&RpcClient) -> RpcResult<bool> {
+    move |rpc| Ok(rpc.get_account(&CutProposal::find_address(&router, &diamond, id).0)?.is_some())
+}
+
+/// Proposal `id` of `diamond` has been applied
+pub fn proposal_executed(router: Pubkey, diamond: Pubkey, id: u64) -> impl Fn(&RpcClient) -> RpcResult<bool> {
+    move |rpc| match rpc.get_account(&CutProposal::find_address(&router, &diamond, id).0)? {
+        Some(account) => Ok(CutProposal::deserialize(&mut &account.data[..]).is_ok_and(|p| p.executed)),
+        None => Ok(false),
+    }
+}
+
+/// Preflight rejections (simulation failed, bad signature) won't change on
+/// retry, except a blockhash the node hasn't seen yet
+fn is_final(error: &str) -> bool {
+    let preflight = error.contains("\"code\":-32002") || error.contains("\"code\":-32003");
+    preflight && !error.contains("BlockhashNotFound")
+}
+
+/// Whether `signature` reached `commitment`; errors if it failed on chain
+fn landed(rpc: &RpcClient, signature: &str, commitment: Commitment) -> RpcResult<bool> {
+    let result = rpc.call("getSignatureStatuses", json!([[signature]]))?;
+    let status = &result["value"][0];
+    if status.is_null() {
+        return Ok(false);
+    }
+    if !status["err"].is_null() {
+        return Err(format!("{} failed: {}", signature, status["err"]));
+    }
+    let reached = status["confirmationStatus"].as_str().and_then(Commitment::parse);
+    Ok(reached.is_some_and(|reached| reached >= commitment))
+}
+
+fn block_height(rpc: &RpcClient, commitment: Commitment) -> RpcResult<u64> {
+    rpc.call("getBlockHeight", json!([{ "commitment": commitment.as_str() }]))?
+        .as_u64()
+        .ok_or_else(|| "getBlockHeight returned no height".to_string())
+}
+
+/// Fresh blockhash and the last block height it is valid for
+fn latest_blockhash(rpc: &RpcClient, commitment: Commitment) -> RpcResult<(Hash, u64)> {
+    let value = &rpc.call("getLatestBlockhash", json!([{ "commitment": commitment.as_str() }]))?["value"];
+    let blockhash = value["blockhash"].as_str().and_then(|s| s.parse().ok()).ok_or("blockhash missing")?;
+    let last_valid = value["lastValidBlockHeight"].as_u64().ok_or("lastValidBlockHeight missing")?;
+    Ok((blockhash, last_valid))
+}
+
+/// Sign and send `instructions` until they land, fail on chain, or
+/// `max_attempts` run out. The first signer pays.
+pub fn send(
+    rpc: &RpcClient,
+    instructions: &[Instruction],
+    signers: &[&dyn Signer],
+    config: &SendConfig,
+    already_applied: Option<AlreadyApplied>,
+) -> RpcResult<Outcome> {
+    let payer = signers.first().ok_or("no signers")?.pubkey();
+    // Processed is too weak to judge expiry by; blockhashes come from confirmed
+    let fetch_commitment = config.commitment.max(Commitment::Confirmed);
+    let mut previous: Option<String> = None;
+    let mut last_error = String::new();
+    for attempt in 0..config.max_attempts.max(1) {
+        sleep(config.backoff(attempt));
+        if let Some(signature) = &previous {
+            if landed(rpc, signature, config.commitment)? {
+                return Ok(Outcome::Sent(signature.clone()));
+            }
+        }
+        if attempt > 0 {
+            if let Some(check) = already_applied {
+                if check(rpc)? {
+                    return Ok(Outcome::AlreadyApplied);
+                }
+            }
+        }
+        
+        let (message, expiry) = match &config.nonce {
+            Some(nonce) => (nonce.message(instructions, &payer, &nonce::fetch(rpc, nonce)?), None),
+            None => {
+                let (blockhash, last_valid) = latest_blockhash(rpc, fetch_commitment)?;
+                (Message::new_with_blockhash(instructions, Some(&payer), &blockhash), Some(last_valid))
+            }
+        };
+        let (wire, signature) = signed_transaction(&message, signers)?;
+        previous = Some(signature.clone());
+        if let Err(e) = rpc.send_wire(&wire) {
+            if is_final(&e) {
+                return Err(e);
+            }
+            eprintln!("attempt {}: {}; retrying", attempt + 1, e);
+            last_error = e;
+            continue;
+        }
+        
+        let started = Instant::now();
+        loop {
+            if landed(rpc, &signature, config.commitment)? {
+                return Ok(Outcome::Sent(signature));
+            }
+            let expired = match expiry {
+                Some(last_valid) => block_height(rpc, fetch_commitment)? > last_valid,
+                None => started.elapsed() >= config.nonce_timeout,
+            };
+            if expired {
+                last_error = format!("{} expired before reaching {}", signature, config.commitment.as_str());
+                eprintln!("attempt {}: {}; retrying", attempt + 1, last_error);
+                break;
+            }
+            sleep(POLL_INTERVAL);
+        }
+    }
+    // The last attempt may still have landed in its final blocks
+    if let Some(signature) = previous {
+        if landed(rpc, &signature, config.commitment)? {
+            return Ok(Outcome::Sent(signature));
+        }
+    }
+    Err(format!("gave up after {} attempts: {}", config.max_attempts.max(1), last_error))
+}
+
+/// Send every transaction of `plan` in order, stopping at the first that
+/// can't be sent
+///
+/// On failure returns how many transactions completed, so the caller can
+/// resume from there. Proposal plans are idempotent on the proposal id.
+pub fn send_plan(
+    rpc: &RpcClient,
+    router: &Pubkey,
+    diamond: &Pubkey,
+    plan: &CutPlan,
+    signers: &[&dyn Signer],
+    config: &SendConfig,
+) -> Result<Vec<Outcome>, (usize, String)> {
+    let mut outcomes = Vec::new();
+    for (i, tx) in plan.transactions.iter().enumerate() {
+        let outcome = match plan.proposal_id {
+            Some(id) if tx.cuts.is_empty() => {
+                send(rpc, &tx.instructions, signers, config, Some(&proposal_executed(*router, *diamond, id)))
+            }
+            Some(id) => send(rpc, &tx.instructions, signers, config, Some(&proposal_exists(*router, *diamond, id))),
+            None => send(rpc, &tx.instructions, signers, config, None),
+        };
+        outcomes.push(outcome.map_err(|e| (i, e))?);
+    }
+    Ok(outcomes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_backoff_doubles_up_to_the_cap() {
+        let config = SendConfig::default();
+        let waits: Vec<u64> = (0..7).map(|n| config.backoff(n).as_millis() as u64).collect();
+        assert_eq!(waits, [0, 500, 1000, 2000, 4000, 8000, 8000]);
+        assert_eq!(config.backoff(u32::MAX), config.max_backoff);
+    }
+    
+    #[test]
+    fn test_final_send_errors() {
+        let preflight = |err: &str| format!("sendTransaction failed: {{\"code\":-32002,\"message\":\"{}\"}}", err);
+        assert!(is_final(&preflight("Transaction simulation failed: custom program error: 0x1771")));
+        assert!(!is_final(&preflight("Transaction simulation failed: BlockhashNotFound")));
+        assert!(!is_final("127.0.0.1:8899: Connection refused"));
+    }
+    
+    #[test]
+    fn test_commitment_order() {
+        assert!(Commitment::Finalized > Commitment::Confirmed);
+        assert_eq!(Commitment::parse("processed"), Some(Commitment::Processed));
+        assert_eq!(Commitment::parse("max"), None);
+    }
+}
@@ -0,0 +1,155 @@
+/*!
+ * Owner and admin signers
+ *
+ * Production owner keys live on hardware wallets, not in keypair files. A
+ * signer is named by a URI, as in the Solana CLI:
+ *
+ * - a path to a `solana-keygen` JSON keypair file
+ * - `exec:<program> [args...]`, an external signer process
+ * - `usb://ledger[?key=<n>]`, a Ledger through the remote-wallet bridge
+ *   named by `DIAMOND_REMOTE_SIGNER`
+ *
+ * An external signer is run as `<program> [args...] pubkey`, printing its
+ * base58 public key, and as `<program> [args...] sign`, reading the base64
+ * message on stdin and printing the base58 signature. Signatures aren't
+ * checked here: the cluster verifies a transaction's signatures, and
+ * off-chain approvals go through the Ed25519 precompile the router reads
+ * back from the instructions sysvar (see `diamond_signing`). The USB stack (`solana-remote-wallet` over hidapi) lives in
+ * the bridge so this crate keeps its small dependency set; the bridge gets
+ * the `usb://` URI as its first argument.
+ */
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use solana_program::{message::VersionedMessage, pubkey::Pubkey};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use crate::jito::decode_short_u16;
+use crate::keys::{Keypair, Signer};
+use crate::rpc::RpcResult;
+
+/// Environment variable naming the remote-wallet bridge for `usb://` URIs
+pub const REMOTE_SIGNER_ENV: &str = "DIAMOND_REMOTE_SIGNER";
+
+/// A signer process speaking the `pubkey`/`sign` protocol
+#[derive(Debug, Clone)]
+pub struct ExternalSigner {
+    command: Vec<String>,
+    pubkey: Pubkey,
+}
+
+impl ExternalSigner {
+    /// Start `command` once to learn its public key
+    pub fn new(command: Vec<String>) -> RpcResult<Self> {
+        if command.is_empty() {
+            return Err("external signer: empty command".to_string());
+        }
+        let mut signer = Self { command, pubkey: Pubkey::default() };
+        let output = signer.run("pubkey", None)?;
+        signer.pubkey = output.parse().map_err(|_| format!("{}: invalid pubkey {:?}", signer.name(), output))?;
+        Ok(signer)
+    }
+    
+    fn name(&self) -> &str {
+        &self.command[0]
+    }
+    
+    /// Run `<command> <action>`, feeding `input` on stdin; returns trimmed stdout
+    fn run(&self, action: &str, input: Option<&str>) -> RpcResult<String> {
+        let mut child = Command::new(&self.command[0])
+            .args(&self.command[1..])
+            .arg(action)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|e| format!("{}: {}", self.name(), e))?;
+        if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+            writeln!(stdin, "{}", input).map_err(|e| format!("{}: {}", self.name(), e))?;
+        }
+        let output = child.wait_with_output().map_err(|e| format!("{}: {}", self.name(), e))?;
+        if !output.status.success() {
+            return Err(format!("{} {} failed ({})", self.name(), action, output.status));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+}
+
+impl Signer for ExternalSigner {
+    fn pubkey(&self) -> Pubkey {
+        self.pubkey
+    }
+    
+    fn try_sign(&self, message: &[u8]) -> Result<[u8; 64], String> {
+        let output = self.run("sign", Some(&STANDARD.encode(message)))?;
+        let bytes = bs58::decode(&output).into_vec().map_err(|e| format!("{}: {}", self.name(), e))?;
+        let signature: [u8; 64] = bytes
+            .try_into()
+            .map_err(|_| format!("{}: signature is not 64 bytes", self.name()))?;
+        Ok(signature)
+    }
+}
+
+/// Open the signer named by `uri`
+pub fn resolve(uri: &str) -> RpcResult<Box<dyn Signer>> {
+    if let Some(command) = uri.strip_prefix("exec:") {
+        return Ok(Box::new(ExternalSigner::new(command.split_whitespace().map(String::from).collect())?));
+    }
+    if uri.starts_with("usb://") {
+        let bridge = std::env::var(REMOTE_SIGNER_ENV)
+            .map_err(|_| format!("{}: set {} to a remote-wallet bridge program", uri, REMOTE_SIGNER_ENV))?;
+        return Ok(Box::new(ExternalSigner::new(vec![bridge, uri.to_string()])?));
+    }
+    Ok(Box::new(Keypair::read_file(uri)?))
+}
+
+/// Add `signers`' signatures to a wire transaction in place
+///
+/// Every signer must be one of the transaction's required signers. Returns
+/// the required signers whose signature slot is still empty.
+pub fn sign_wire(wire: &mut [u8], signers: &[&dyn Signer]) -> RpcResult<Vec<Pubkey>> {
+    let (count, offset) = decode_short_u16(wire).ok_or("truncated signature count")?;
+    let message_start = offset + count as usize * 64;
+    let payload = wire.get(message_start..).ok_or("truncated signatures")?.to_vec();
+    let message: VersionedMessage = bincode::deserialize(&payload).map_err(|e| format!("bad message: {}", e))?;
+    let required = &message.static_account_keys()[..count as usize];
+    for signer in signers {
+        let pubkey = signer.pubkey();
+        let slot = required
+            .iter()
+            .position(|key| *key == pubkey)
+            .ok_or_else(|| format!("{} is not a signer of this transaction", pubkey))?;
+        let at = offset + slot * 64;
+        wire[at..at + 64].copy_from_slice(&signer.try_sign(&payload)?);
+    }
+    Ok(required
+        .iter()
+        .enumerate()
+        .filter(|(slot, _)| wire[offset + slot * 64..offset + (slot + 1) * 64].iter().all(|b| *b == 0))
+        .map(|(_, key)| *key)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::unsigned_transaction;
+    use solana_program::{
+        instruction::{AccountMeta, Instruction},
+        message::Message,
+    };
+    
+    #[test]
+    fn test_sign_wire() {
+        let (payer, owner) = (Keypair::from_seed(&[1; 32]), Keypair::from_seed(&[2; 32]));
+        let ix = Instruction::new_with_bytes(Pubkey::new_unique(), &[1], vec![AccountMeta::new_readonly(owner.pubkey(), true)]);
+        let message = Message::new(&[ix], Some(&payer.pubkey()));
+        let mut wire = unsigned_transaction(&message);
+        
+        assert_eq!(sign_wire(&mut wire, &[&owner]).unwrap(), [payer.pubkey()]);
+        assert_eq!(wire[65..129], owner.sign(&message.serialize()));
+        assert!(sign_wire(&mut wire, &[&payer]).unwrap().is_empty());
+        let stranger = Keypair::from_seed(&[3; 32]);
+        assert!(sign_wire(&mut wire, &[&stranger]).is_err());
+    }
+}
@@ -0,0 +1,254 @@
+/*!
+ * Admin action previews
+ *
+ * Runs a cut/pause/ownership transaction through `simulateTransaction`,
+ * asking the node for the diamond state account as it would be afterwards,
+ * and diffs it against the live account: selectors added, removed or
+ * rerouted, plus owner, admin and pause changes. Nothing is sent.
+ */
+
+use borsh::BorshDeserialize;
+use diamond_router_native::{
+    diamond_loupe::{self, Capacity},
+    diamond_state::{DiamondState, PendingAuthorityChange, SelectorMapping},
+    selector::Selector,
+};
+use solana_program::{message::Message, pubkey::Pubkey};
+use std::fmt;
+
+use crate::errors::{self, DecodedError};
+use crate::rpc::{unsigned_transaction, RpcClient, RpcResult};
+
+/// A selector as it appears in a preview
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    pub selector: Selector,
+    pub name: String,
+    pub module: Pubkey,
+}
+
+impl Route {
+    fn new(mapping: &SelectorMapping) -> Self {
+        Self { selector: mapping.selector, name: mapping.qualified_name(), module: mapping.module }
+    }
+}
+
+/// What a simulated transaction would change in the diamond state
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StateDelta {
+    pub added: Vec<Route>,
+    pub removed: Vec<Route>,
+    /// (before, after) for selectors whose module changed
+    pub rerouted: Vec<(Route, Route)>,
+    pub owner: Option<(Pubkey, Pubkey)>,
+    pub pending_owner: Option<(Option<Pubkey>, Option<Pubkey>)>,
+    /// Governance or delay change announced, applied or vetoed
+    pub pending_change: Option<(Option<PendingAuthorityChange>, Option<PendingAuthorityChange>)>,
+    pub admins_added: Vec<Pubkey>,
+    pub admins_removed: Vec<Pubkey>,
+    pub dispatch_paused: Option<(bool, bool)>,
+    pub cut_paused: Option<(bool, bool)>,
+}
+
+impl StateDelta {
+    pub fn between(before: &DiamondState, after: &DiamondState) -> Self {
+        let find = |state: &DiamondState, selector: Selector| {
+            state.selectors.iter().find(|m| m.selector == selector).map(Route::new)
+        };
+        let mut delta = Self::default();
+        for mapping in &after.selectors {
+            match find(before, mapping.selector) {
+                None => delta.added.push(Route::new(mapping)),
+                Some(old) if old.module != mapping.module => delta.rerouted.push((old, Route::new(mapping))),
+                Some(_) => {}
+            }
+        }
+        delta.removed = before
+            .selectors
+            .iter()
+            .filter(|m| find(after, m.selector).is_none())
+            .map(Route::new)
+            .collect();
+        delta.owner = (before.owner != after.owner).then_some((before.owner, after.owner));
+        delta.pending_owner =
+            (before.pending_owner != after.pending_owner).then_some((before.pending_owner, after.pending_owner));
+        delta.pending_change =
+            (before.pending_change != after.pending_change).then_some((before.pending_change, after.pending_change));
+        delta.admins_added = after.admins.iter().filter(|a| !before.admins.contains(a)).copied().collect();
+        delta.admins_removed = before.admins.iter().filter(|a| !after.admins.contains(a)).copied().collect();
+        delta.dispatch_paused = (before.dispatch_paused != after.dispatch_paused)
+            .then_some((before.dispatch_paused, after.dispatch_paused));
+        delta.cut_paused = (before.cut_paused != after.cut_paused).then_some((before.cut_paused, after.cut_paused));
+        delta
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+/// Structured outcome of a simulated admin transaction
+#[derive(Debug, Clone)]
+pub struct Preview {
+    pub diamond_state: Pubkey,
+    pub selector_width: u8,
+    /// Why the transaction would fail, if it would
+    pub error: Option<DecodedError>,
+    /// Empty when the transaction would fail
+    pub delta: StateDelta,
+    /// Capacity of the state account afterwards (`None` when the transaction would fail)
+    pub capacity: Option<Capacity>,
+    pub units_consumed: Option<u64>,
+    pub logs: Vec<String>,
+}
+
+impl Preview {
+    pub fn succeeds(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+impl fmt::Display for Preview {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Preview for diamond {}", self.diamond_state)?;
+        if let Some(error) = &self.error {
+            return writeln!(f, "  ❌ would fail: {}", error);
+        }
+        if let Some(units) = self.units_consumed {
+            writeln!(f, "  compute units: {}", units)?;
+        }
+        if self.delta.is_empty() {
+            return writeln!(f, "  no routing or authority changes");
+        }
+        let selector = |route: &Route| hex(route.selector.as_bytes(self.selector_width));
+        for route in &self.delta.added {
+            writeln!(f, "  + {} {:<40} -> {}", selector(route), route.name, route.module)?;
+        }
+        for (old, new) in &self.delta.rerouted {
+            writeln!(f, "  ~ {} {:<40} {} -> {}", selector(new), new.name, old.module, new.module)?;
+        }
+        for route in &self.delta.removed {
+            writeln!(f, "  - {} {:<40} (was {})", selector(route), route.name, route.module)?;
+        }
+        if let Some((old, new)) = self.delta.owner {
+            writeln!(f, "  owner: {} -> {}", old, new)?;
+        }
+        if let Some((old, new)) = self.delta.pending_owner {
+            let show = |key: Option<Pubkey>| key.map(|k| k.to_string()).unwrap_or_else(|| "none".to_string());
+            writeln!(f, "  pending owner: {} -> {}", show(old), show(new))?;
+        }
+        if let Some((old, new)) = &self.delta.pending_change {
+            let show = |pending: &Option<PendingAuthorityChange>| match pending {
+                Some(p) => format!("{:?} at slot {}", p.change, p.effective_slot),
+                None => "none".to_string(),
+            };
+            writeln!(f, "  pending authority change: {} -> {}", show(old), show(new))?;
+        }
+        for admin in &self.delta.admins_added {
+            writeln!(f, "  + admin {}", admin)?;
+        }
+        for admin in &self.delta.admins_removed {
+            writeln!(f, "  - admin {}", admin)?;
+        }
+        if let Some((old, new)) = self.delta.dispatch_paused {
+            writeln!(f, "  dispatch paused: {} -> {}", old, new)?;
+        }
+        if let Some((old, new)) = self.delta.cut_paused {
+            writeln!(f, "  cuts paused: {} -> {}", old, new)?;
+        }
+        Ok(())
+    }
+}
+
+/// Preview a wire transaction (signed or not) against the live diamond state
+pub fn preview_transaction(
+    rpc: &RpcClient,
+    router: &Pubkey,
+    diamond_state: &Pubkey,
+    wire: &[u8],
+) -> RpcResult<Preview> {
+    let account = rpc
+        .get_account(diamond_state)?
+        .ok_or_else(|| format!("diamond state {} not found", diamond_state))?;
+    if &account.owner != router {
+        return Err(format!("{} is not owned by router {}", diamond_state, router));
+    }
+    let before = DiamondState::deserialize(&mut &account.data[..]).map_err(|e| e.to_string())?;
+    
+    let simulation = rpc.simulate_wire(wire, &[*diamond_state])?;
+    let mut preview = Preview {
+        diamond_state: *diamond_state,
+        selector_width: before.selector_width,
+        error: None,
+        delta: StateDelta::default(),
+        capacity: None,
+        units_consumed: simulation.units_consumed,
+        logs: simulation.logs,
+    };
+    if let Some(err) = &simulation.err {
+        preview.error = Some(errors::decode(router, err, &preview.logs));
+        return Ok(preview);
+    }
+    let (after, account_len) = match simulation.accounts.first() {
+        Some(Some(account)) => (
+            DiamondState::deserialize(&mut &account.data[..]).map_err(|e| e.to_string())?,
+            account.data.len(),
+        ),
+        _ => return Err("simulation returned no diamond state".to_string()),
+    };
+    preview.delta = StateDelta::between(&before, &after);
+    preview.capacity = Some(diamond_loupe::capacity(&after, account_len).map_err(|e| e.to_string())?);
+    Ok(preview)
+}
+
+/// Preview an unsigned message
+pub fn preview(rpc: &RpcClient, router: &Pubkey, diamond_state: &Pubkey, message: &Message) -> RpcResult<Preview> {
+    preview_transaction(rpc, router, diamond_state, &unsigned_transaction(message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diamond_router_native::diamond_cut::{apply_cuts, FacetCut};
+    
+    #[test]
+    fn test_state_delta() {
+        let owner = Pubkey::new_unique();
+        let (facet_a, facet_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut before = DiamondState::new(owner, 255);
+        apply_cuts(
+            &mut before,
+            &[FacetCut::add([1, 0, 0, 1], facet_a, "a", "one"), FacetCut::add([1, 0, 0, 2], facet_a, "a", "two")],
+        )
+        .unwrap();
+        
+        let mut after = before.clone();
+        apply_cuts(
+            &mut after,
+            &[
+                FacetCut::remove([1, 0, 0, 1]),
+                FacetCut::replace([1, 0, 0, 2], facet_b, "b"),
+                FacetCut::add([1, 0, 0, 3], facet_b, "b", "three"),
+            ],
+        )
+        .unwrap();
+        after.cut_paused = true;
+        
+        let delta = StateDelta::between(&before, &after);
+        assert_eq!(delta.removed.len(), 1);
+        assert_eq!(delta.removed[0].name, "one");
+        assert_eq!(delta.rerouted.len(), 1);
+        assert_eq!((delta.rerouted[0].0.module, delta.rerouted[0].1.module), (facet_a, facet_b));
+        assert_eq!(delta.added.len(), 1);
+        assert_eq!(delta.added[0].selector, Selector::from([1, 0, 0, 3]));
+        assert_eq!(delta.cut_paused, Some((false, true)));
+        assert_eq!(delta.dispatch_paused, None);
+        assert_eq!(delta.owner, None);
+        
+        assert!(StateDelta::between(&after, &after).is_empty());
+    }
+}
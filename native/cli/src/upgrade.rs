@@ -0,0 +1,258 @@
+/*!
+ * `diamond upgrade`
+ *
+ * The whole facet upgrade ceremony in one command, stopping at the first
+ * step that fails:
+ *
+ * 1. write the new build into a fresh loader buffer;
+ * 2. upgrade the facet program in place from it (or, with a new program
+ *    keypair, deploy it as a new program) and wait until that is finalized;
+ * 3. check the deployed executable hash against the local build;
+ * 4. for a new program, move every selector of the old facet to it in one
+ *    proposal, approved right away when the signer is the owner of a diamond
+ *    without a council and otherwise left queued for approval;
+ * 5. probe the selectors the new build serves through `probe_facet`.
+ *
+ * The signer pays, holds the facet's upgrade authority and proposes the
+ * cut. If a write or the deploy fails, the buffer is closed and its rent
+ * returned to the signer. Every step prints what it did with its
+ * signatures, so the output is the upgrade's record.
+ */
+
+use borsh::BorshDeserialize;
+use diamond_router_native::{
+    diamond_build::{executable_hash, programdata_address},
+    diamond_cut::FacetCut,
+    diamond_state::DiamondState,
+};
+use solana_program::{
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    instruction::Instruction,
+    pubkey::Pubkey,
+};
+
+use crate::doctor;
+use crate::keys::{Keypair, Signer};
+use crate::plan::{self, Lifetime};
+use crate::rpc::{RpcClient, RpcResult};
+use crate::sender::{self, Commitment, Outcome, SendConfig};
+use crate::verify;
+
+/// Program bytes per buffer write, leaving room for one signature and the
+/// loader's accounts in a 1232-byte transaction
+pub const WRITE_CHUNK: usize = 900;
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn signature(outcome: &Outcome) -> &str {
+    match outcome {
+        Outcome::Sent(signature) => signature,
+        Outcome::AlreadyApplied => "already applied",
+    }
+}
+
+/// Buffer writes of `elf`, one chunk each
+pub fn write_instructions(buffer: &Pubkey, authority: &Pubkey, elf: &[u8]) -> Vec<Instruction> {
+    elf.chunks(WRITE_CHUNK)
+        .enumerate()
+        .map(|(i, chunk)| bpf_loader_upgradeable::write(buffer, authority, (i * WRITE_CHUNK) as u32, chunk.to_vec()))
+        .collect()
+}
+
+/// `Replace` cuts moving every selector of `old` to `new`
+pub fn replace_cuts(state: &DiamondState, old: &Pubkey, new: &Pubkey) -> Result<Vec<FacetCut>, String> {
+    let name = state.active_modules.iter().find(|m| &m.address == old).map(|m| m.name_as_str().to_string());
+    let cuts = state
+        .selectors
+        .iter()
+        .filter(|mapping| &mapping.module == old)
+        .map(|mapping| {
+            if mapping.is_immutable {
+                return Err(format!("{} is immutable and can't move to a new program", mapping.qualified_name()));
+            }
+            Ok(FacetCut {
+                standard_accounts: mapping.standard_accounts,
+                ..FacetCut::replace(mapping.selector, *new, name.as_deref().unwrap_or_default())
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if cuts.is_empty() {
+        return Err(format!("{} serves no selectors of this diamond", old));
+    }
+    Ok(cuts)
+}
+
+fn load_state(rpc: &RpcClient, router: &Pubkey, diamond: &Pubkey) -> RpcResult<DiamondState> {
+    let account = rpc.get_account(diamond)?.ok_or_else(|| format!("diamond state {} not found", diamond))?;
+    if &account.owner != router {
+        return Err(format!("{} is not owned by router {}", diamond, router));
+    }
+    DiamondState::deserialize(&mut &account.data[..]).map_err(|e| e.to_string())
+}
+
+/// Bytes of program the deployed `program`'s data account can hold
+fn programdata_capacity(rpc: &RpcClient, program: &Pubkey) -> RpcResult<usize> {
+    let account = rpc.get_account(program)?.ok_or_else(|| format!("{} not found", program))?;
+    let programdata = programdata_address(&account.data).ok_or_else(|| format!("{} is not an upgradeable program", program))?;
+    let data = rpc.get_account(&programdata)?.ok_or_else(|| format!("program data {} not found", programdata))?.data;
+    Ok(data.len().saturating_sub(UpgradeableLoaderState::size_of_programdata_metadata()))
+}
+
+/// Run the ceremony; returns true when the new build is live and healthy
+/// (or, when the cut waits for approval, deployed and verified)
+pub fn run(
+    rpc: &RpcClient,
+    router: &Pubkey,
+    diamond: &Pubkey,
+    facet: &Pubkey,
+    elf: &[u8],
+    signer: &dyn Signer,
+    new_program: Option<&dyn Signer>,
+) -> RpcResult<bool> {
+    let authority = signer.pubkey();
+    let target = new_program.map_or(*facet, |program| program.pubkey());
+    let state = load_state(rpc, router, diamond)?;
+    // Refuse before deploying anything if the routes can't follow
+    match new_program {
+        Some(_) => {
+            replace_cuts(&state, facet, &target)?;
+        }
+        None if !state.selectors.iter().any(|m| &m.module == facet) => {
+            return Err(format!("{} serves no selectors of this diamond", facet));
+        }
+        None => {}
+    }
+    let config = SendConfig::default();
+    println!("Upgrade of facet {} ({} bytes) on diamond {}", facet, elf.len(), diamond);
+    
+    let buffer = Keypair::generate()?;
+    let buffer_address = buffer.pubkey();
+    let lamports = rpc.rent_exempt(UpgradeableLoaderState::size_of_buffer(elf.len()))?;
+    let create = bpf_loader_upgradeable::create_buffer(&authority, &buffer_address, &authority, lamports, elf.len())
+        .map_err(|e| e.to_string())?;
+    let created = |rpc: &RpcClient| Ok(rpc.get_account(&buffer_address)?.is_some());
+    sender::send(rpc, &create, &[signer, &buffer], &config, Some(&created))?;
+    let finalized = SendConfig { commitment: Commitment::Finalized, ..SendConfig::default() };
+    let write_and_deploy = || -> RpcResult<Outcome> {
+        let writes = write_instructions(&buffer_address, &authority, elf);
+        for write in &writes {
+            sender::send(rpc, std::slice::from_ref(write), &[signer], &config, None)?;
+        }
+        println!("  1. wrote buffer {} in {} transactions", buffer_address, writes.len() + 1);
+        
+        Ok(match new_program {
+            Some(program) => {
+                let program_lamports = rpc.rent_exempt(UpgradeableLoaderState::size_of_program())?;
+                let deploy = bpf_loader_upgradeable::deploy_with_max_program_len(
+                    &authority,
+                    &target,
+                    &buffer_address,
+                    &authority,
+                    program_lamports,
+                    elf.len() * 2,
+                )
+                .map_err(|e| e.to_string())?;
+                sender::send(rpc, &deploy, &[signer, program], &finalized, None)?
+            }
+            None => {
+                let capacity = programdata_capacity(rpc, facet)?;
+                if elf.len() > capacity {
+                    let extend = bpf_loader_upgradeable::extend_program(facet, Some(&authority), (elf.len() - capacity) as u32);
+                    let extended = sender::send(rpc, &[extend], &[signer], &config, None)?;
+                    println!("     extended the program data by {} bytes: {}", elf.len() - capacity, signature(&extended));
+                }
+                let upgrade = bpf_loader_upgradeable::upgrade(facet, &buffer_address, &authority, &authority);
+                sender::send(rpc, &[upgrade], &[signer], &finalized, None)?
+            }
+        })
+    };
+    let deployed = write_and_deploy().map_err(|e| {
+        // The loader only consumes the buffer on success; get its rent back
+        let close = bpf_loader_upgradeable::close(&buffer_address, &authority, &authority);
+        match sender::send(rpc, &[close], &[signer], &config, None) {
+            Ok(_) => format!("{} (closed buffer {})", e, buffer_address),
+            Err(close_err) => format!("{} (buffer {} left open: {})", e, buffer_address, close_err),
+        }
+    })?;
+    let verb = if new_program.is_some() { "deployed" } else { "upgraded" };
+    println!("  2. {} {} (finalized): {}", verb, target, signature(&deployed));
+    
+    let (deploy_slot, hash) = verify::deployed_hash(rpc, &target)?;
+    let local = executable_hash(elf);
+    if hash != local {
+        println!("  3. ❌ deployed hash {} does not match the local build {}", hex(&hash), hex(&local));
+        return Ok(false);
+    }
+    println!("  3. deployed hash {} (slot {}) matches the local build", hex(&hash), deploy_slot);
+    if verify::attestation(rpc, router, diamond, &target)?.is_some_and(|a| a.hash != local) {
+        println!("     ⚠️ the attested build hash differs; attest the new one with build::attest");
+    }
+    
+    let mut serving = *facet;
+    if new_program.is_some() {
+        // The ceremony can take minutes; propose against the current state
+        let state = load_state(rpc, router, diamond)?;
+        let cuts = replace_cuts(&state, facet, &target)?;
+        let lifetime = Lifetime::Blockhash(rpc.latest_blockhash()?);
+        let plan = plan::plan_cuts(router, diamond, &state, &authority, &authority, &cuts, true, &lifetime)?;
+        let id = plan.proposal_id.unwrap_or_default();
+        if plan.approver.and_then(|approver| approver.key()) == Some(authority) {
+            let outcomes = sender::send_plan(rpc, router, diamond, &plan, &[signer], &config)
+                .map_err(|(done, e)| format!("cut stopped after {} of {} transactions: {}", done, plan.transactions.len(), e))?;
+            let signatures: Vec<&str> = outcomes.iter().map(signature).collect();
+            println!("  4. moved {} selectors to {} (proposal {}): {}", cuts.len(), target, id, signatures.join(", "));
+            serving = target;
+        } else {
+            let created = sender::proposal_exists(*router, *diamond, id);
+            let proposed = sender::send(rpc, &plan.transactions[0].instructions, &[signer], &config, Some(&created))?;
+            println!("  4. proposal {} to move {} selectors queued: {}", id, cuts.len(), signature(&proposed));
+            println!("     the new build serves nothing until the owner or council approves it; probes skipped");
+            return Ok(true);
+        }
+    }
+    
+    let state = load_state(rpc, router, diamond)?;
+    let mut healthy = true;
+    for mapping in state.selectors.iter().filter(|m| m.module == serving) {
+        let verdict = doctor::probe(rpc, router, diamond, &authority, mapping)?;
+        healthy &= verdict == "healthy";
+        println!("  5. {:<40} {}", mapping.qualified_name(), verdict);
+    }
+    println!("{}", if healthy { "✅ upgrade complete" } else { "❌ upgraded, but probes failed" });
+    Ok(healthy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diamond_router_native::diamond_cut::{apply_cuts, FacetCutAction};
+    
+    #[test]
+    fn test_replace_cuts_and_buffer_writes() {
+        let (old, new) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        apply_cuts(&mut state, &[
+            FacetCut { standard_accounts: 3, ..FacetCut::add([1, 0, 0, 1], old, "vault", "deposit") },
+            FacetCut::add([1, 0, 0, 2], Pubkey::new_unique(), "other", "noop"),
+        ])
+        .unwrap();
+        let cuts = replace_cuts(&state, &old, &new).unwrap();
+        assert_eq!(cuts.len(), 1);
+        assert_eq!((cuts[0].action, cuts[0].module, cuts[0].standard_accounts), (FacetCutAction::Replace, new, 3));
+        assert_eq!(cuts[0].module_name, "vault");
+        assert!(replace_cuts(&state, &new, &old).is_err());
+        
+        state.selectors[0].is_immutable = true;
+        assert!(replace_cuts(&state, &old, &new).is_err());
+        
+        let (buffer, authority) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let elf = vec![7u8; WRITE_CHUNK * 2 + 1];
+        let writes = write_instructions(&buffer, &authority, &elf);
+        assert_eq!(writes.len(), 3);
+        assert!(writes.iter().all(|ix| ix.program_id == bpf_loader_upgradeable::id() && ix.accounts[0].pubkey == buffer));
+        let message = solana_program::message::Message::new(&writes[..1], Some(&authority));
+        assert!(crate::rpc::unsigned_transaction(&message).len() <= plan::MAX_TRANSACTION_SIZE);
+    }
+}
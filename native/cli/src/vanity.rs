@@ -0,0 +1,64 @@
+/*!
+ * Vanity diamond addresses
+ *
+ * Grinds the extra nonce seed `initialize` accepts, so a diamond PDA starts
+ * with a chosen base58 prefix. Each extra character multiplies the expected
+ * attempts by 58.
+ */
+
+use diamond_router_native::diamond_state::{diamond_address, MAX_VANITY_PREFIX};
+use solana_program::pubkey::Pubkey;
+
+/// Base58 alphabet (no 0, O, I or l)
+const BASE58: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// A nonce whose diamond address starts with the requested prefix
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VanityMatch {
+    pub nonce: u64,
+    pub address: Pubkey,
+    pub bump: u8,
+}
+
+/// Reject prefixes no address can have (or the router won't check)
+pub fn validate_prefix(prefix: &str) -> Result<(), String> {
+    if prefix.is_empty() || prefix.len() > MAX_VANITY_PREFIX {
+        return Err(format!("prefix must be 1 to {} characters", MAX_VANITY_PREFIX));
+    }
+    match prefix.chars().find(|c| !BASE58.contains(*c)) {
+        Some(c) => Err(format!("{:?} is not a base58 character", c)),
+        None => Ok(()),
+    }
+}
+
+/// Try nonces `start..start + attempts` for `owner`'s diamond
+pub fn grind(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    prefix: &str,
+    start: u64,
+    attempts: u64,
+) -> Result<Option<VanityMatch>, String> {
+    validate_prefix(prefix)?;
+    Ok((start..start.saturating_add(attempts)).find_map(|nonce| {
+        let (address, bump) = diamond_address(program_id, owner, Some(nonce));
+        address.to_string().starts_with(prefix).then_some(VanityMatch { nonce, address, bump })
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_grind_finds_prefix() {
+        let (program_id, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let found = grind(&program_id, &owner, "A", 0, 10_000).unwrap().unwrap();
+        assert!(found.address.to_string().starts_with('A'));
+        assert_eq!(diamond_address(&program_id, &owner, Some(found.nonce)), (found.address, found.bump));
+        
+        assert!(validate_prefix("0x").is_err());
+        assert!(validate_prefix("").is_err());
+        assert!(validate_prefix("ABCDEFGHJ").is_err());
+    }
+}
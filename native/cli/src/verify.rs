@@ -0,0 +1,86 @@
+/*!
+ * `diamond verify-build`
+ *
+ * Hashes a facet's deployed program data the way `solana-verify` does and
+ * compares it with the hash attested in the facet's record (and, with
+ * `--so`, with a locally reproduced build). Read-only: to record the result
+ * on-chain, send `build::verify_facet_build`.
+ */
+
+use borsh::BorshDeserialize;
+use diamond_router_native::{
+    diamond_build::{build_status, deployed_executable, executable_hash, programdata_address},
+    diamond_record::FacetRecord,
+    diamond_state::BuildAttestation,
+};
+use solana_program::pubkey::Pubkey;
+
+use crate::rpc::{RpcClient, RpcResult};
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Deploy slot and executable hash of an upgradeable program
+pub fn deployed_hash(rpc: &RpcClient, module: &Pubkey) -> RpcResult<(u64, [u8; 32])> {
+    let program = rpc.get_account(module)?.ok_or_else(|| format!("{} not found", module))?;
+    let programdata = programdata_address(&program.data)
+        .ok_or_else(|| format!("{} is not an upgradeable program", module))?;
+    let account = rpc
+        .get_account(&programdata)?
+        .ok_or_else(|| format!("program data {} not found", programdata))?;
+    let (slot, elf) = deployed_executable(&account.data)
+        .ok_or_else(|| format!("{} is not a program data account", programdata))?;
+    Ok((slot, executable_hash(elf)))
+}
+
+/// Facet record of `module`, if it has one
+pub fn record(rpc: &RpcClient, router: &Pubkey, diamond_state: &Pubkey, module: &Pubkey) -> RpcResult<Option<FacetRecord>> {
+    let (address, _) = FacetRecord::find_address(router, diamond_state, module);
+    let Some(account) = rpc.get_account(&address)? else {
+        return Ok(None);
+    };
+    if &account.owner != router {
+        return Err(format!("{} is not owned by router {}", address, router));
+    }
+    FacetRecord::deserialize(&mut &account.data[..]).map(Some).map_err(|e| e.to_string())
+}
+
+/// Build hash attested for `module` in its facet record, if any
+pub fn attestation(rpc: &RpcClient, router: &Pubkey, diamond_state: &Pubkey, module: &Pubkey) -> RpcResult<Option<BuildAttestation>> {
+    Ok(record(rpc, router, diamond_state, module)?.and_then(|record| record.attestation))
+}
+
+/// Print a facet's build verification; returns true when the deployed
+/// program matches the attested hash (and the local build, if given)
+pub fn run(
+    rpc: &RpcClient,
+    router: &Pubkey,
+    diamond_state: &Pubkey,
+    module: &Pubkey,
+    local_build: Option<&[u8]>,
+) -> RpcResult<bool> {
+    let attestation = attestation(rpc, router, diamond_state, module)?;
+    let (deploy_slot, deployed) = deployed_hash(rpc, module)?;
+    
+    println!("Facet {}", module);
+    println!("  deployed   {} (slot {})", hex(&deployed), deploy_slot);
+    let mut ok = match &attestation {
+        Some(attestation) => {
+            println!("  attested   {}", hex(&attestation.hash));
+            attestation.hash == deployed
+        }
+        None => {
+            println!("  attested   none");
+            false
+        }
+    };
+    if let Some(elf) = local_build {
+        let local = executable_hash(elf);
+        println!("  local      {}", hex(&local));
+        ok &= local == deployed;
+    }
+    println!("  on-chain   {:?}", build_status(attestation.as_ref(), deploy_slot));
+    println!("{}", if ok { "✅ build verified" } else { "❌ build does not match" });
+    Ok(ok)
+}
@@ -0,0 +1,35 @@
+//! Table capacities (the account sizes are computed from these)
+
+// Diamond state tables
+pub const MAX_ADMINS: usize = 10;
+pub const MAX_MODULES: usize = 20;
+pub const MAX_SELECTORS: usize = 50;
+pub const MAX_ALIASES: usize = 7;
+pub const MAX_PAYLOAD_SPECS: usize = 8;
+pub const MAX_TENANTS: usize = 4;
+pub const MAX_DEPENDENCIES: usize = 8;
+pub const MAX_SELECTOR_TAGS: usize = 16;
+pub const MAX_PAUSED_TAGS: usize = 4;
+pub const MAX_DENYLIST: usize = 4;
+pub const MAX_GUARDED_SELECTORS: usize = 8;
+pub const MAX_PROTECTED_SELECTORS: usize = 8;
+pub const MAX_ORACLE_GUARDS: usize = 4;
+pub const MAX_PAUSE_WINDOWS: usize = 4;
+pub const MAX_CIRCUIT_BREAKERS: usize = 4;
+
+/// Members of a council account
+pub const MAX_COUNCIL_MEMBERS: usize = 8;
+/// Version declarations kept in one facet record
+pub const MAX_VERSION_RECORDS: usize = 16;
+/// Return schemas kept in one facet record
+pub const MAX_RETURN_SCHEMAS: usize = 16;
+/// Cuts in one proposal
+pub const MAX_PROPOSAL_CUTS: usize = 8;
+/// Entries of the fast path account
+pub const MAX_FAST_PATH_ENTRIES: usize = 4;
+/// Bytes of a facet config blob
+pub const MAX_FACET_CONFIG_LEN: usize = 64;
+/// Tags on one selector
+pub const MAX_TAGS_PER_SELECTOR: usize = 4;
+/// Base58 characters of a vanity prefix
+pub const MAX_VANITY_PREFIX: usize = 8;
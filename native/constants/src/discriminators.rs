@@ -0,0 +1,118 @@
+//! Top-level router instruction discriminators (8 bytes, Anchor-compatible
+//! format: the instruction number, then zeros)
+
+pub const INITIALIZE_DISCRIMINATOR: [u8; 8] = [0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const DISPATCH_DISCRIMINATOR: [u8; 8] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const ADD_MODULE_DISCRIMINATOR: [u8; 8] = [0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const REMOVE_MODULE_DISCRIMINATOR: [u8; 8] = [0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const ADD_ADMIN_DISCRIMINATOR: [u8; 8] = [0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const PAUSE_DISCRIMINATOR: [u8; 8] = [0x06, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const LOOKUP_FUNCTION_DISCRIMINATOR: [u8; 8] = [0x07, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const SET_ADMINS_DISCRIMINATOR: [u8; 8] = [0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const REMOVE_ADMINS_DISCRIMINATOR: [u8; 8] = [0x09, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const SET_PAUSE_AUTHORITY_DISCRIMINATOR: [u8; 8] = [0x0A, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const PROPOSE_CUT_DISCRIMINATOR: [u8; 8] = [0x0B, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const APPROVE_CUT_DISCRIMINATOR: [u8; 8] = [0x0C, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const DESCRIBE_PROPOSAL_DISCRIMINATOR: [u8; 8] = [0x0D, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const SET_GOVERNANCE_DISCRIMINATOR: [u8; 8] = [0x0E, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const ENABLE_GOVERNANCE_ONLY_DISCRIMINATOR: [u8; 8] = [0x0F, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const MIGRATE_FROM_ANCHOR_DISCRIMINATOR: [u8; 8] = [0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const TRY_DISPATCH_DISCRIMINATOR: [u8; 8] = [0x11, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const SCHEDULE_CUT_DISCRIMINATOR: [u8; 8] = [0x12, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const EXECUTE_CUT_DISCRIMINATOR: [u8; 8] = [0x13, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const MAINTAIN_DISCRIMINATOR: [u8; 8] = [0x14, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const SET_TENANT_DISCRIMINATOR: [u8; 8] = [0x15, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const TENANT_CUT_DISCRIMINATOR: [u8; 8] = [0x16, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const TRANSFER_OWNERSHIP_DISCRIMINATOR: [u8; 8] = [0x17, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const ACCEPT_OWNERSHIP_DISCRIMINATOR: [u8; 8] = [0x18, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const DISPATCH_TRACED_DISCRIMINATOR: [u8; 8] = [0x19, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const DISPATCH_AUDITED_DISCRIMINATOR: [u8; 8] = [0x1A, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const ALLOCATE_SELECTOR_DISCRIMINATOR: [u8; 8] = [0x1B, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const RESOLVE_SELECTOR_DISCRIMINATOR: [u8; 8] = [0x1C, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const DISPATCH_SIGNED_DISCRIMINATOR: [u8; 8] = [0x1D, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const DISPATCH_PROVEN_DISCRIMINATOR: [u8; 8] = [0x1E, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const SET_PAUSE_SCOPES_DISCRIMINATOR: [u8; 8] = [0x1F, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const DISPATCH_CHECKED_DISCRIMINATOR: [u8; 8] = [0x20, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const DISPATCH_RELAYED_DISCRIMINATOR: [u8; 8] = [0x21, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const DISPATCH_FAST_DISCRIMINATOR: [u8; 8] = [0x22, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const FUND_VAULT_DISCRIMINATOR: [u8; 8] = [0x23, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const WITHDRAW_VAULT_DISCRIMINATOR: [u8; 8] = [0x24, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const PROPOSE_MOTION_DISCRIMINATOR: [u8; 8] = [0x25, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const APPROVE_MOTION_DISCRIMINATOR: [u8; 8] = [0x26, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const EXECUTE_MOTION_DISCRIMINATOR: [u8; 8] = [0x27, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const SWEEP_LAMPORTS_DISCRIMINATOR: [u8; 8] = [0x28, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const SWEEP_TOKEN_DISCRIMINATOR: [u8; 8] = [0x29, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const DISPATCH_COMPRESSED_DISCRIMINATOR: [u8; 8] = [0x2A, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const REQUEST_REGISTRATION_DISCRIMINATOR: [u8; 8] = [0x2B, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const DISPATCH_MAPPED_DISCRIMINATOR: [u8; 8] = [0x2C, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const DISPATCH_INDEXED_DISCRIMINATOR: [u8; 8] = [0x2D, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const MIGRATE_STATE_DISCRIMINATOR: [u8; 8] = [0x2E, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const CLOSE_PROPOSAL_DISCRIMINATOR: [u8; 8] = [0x2F, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const OPEN_SESSION_DISCRIMINATOR: [u8; 8] = [0x30, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub const DISPATCH_SESSION_DISCRIMINATOR: [u8; 8] = [0x31, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+
+/// Every discriminator with its instruction name, in order
+pub const INSTRUCTIONS: [([u8; 8], &str); 49] = [
+    (INITIALIZE_DISCRIMINATOR, "initialize"),
+    (DISPATCH_DISCRIMINATOR, "dispatch"),
+    (ADD_MODULE_DISCRIMINATOR, "add_module"),
+    (REMOVE_MODULE_DISCRIMINATOR, "remove_module"),
+    (ADD_ADMIN_DISCRIMINATOR, "add_admin"),
+    (PAUSE_DISCRIMINATOR, "pause"),
+    (LOOKUP_FUNCTION_DISCRIMINATOR, "lookup_function"),
+    (SET_ADMINS_DISCRIMINATOR, "set_admins"),
+    (REMOVE_ADMINS_DISCRIMINATOR, "remove_admins"),
+    (SET_PAUSE_AUTHORITY_DISCRIMINATOR, "set_pause_authority"),
+    (PROPOSE_CUT_DISCRIMINATOR, "propose_cut"),
+    (APPROVE_CUT_DISCRIMINATOR, "approve_cut"),
+    (DESCRIBE_PROPOSAL_DISCRIMINATOR, "describe_proposal"),
+    (SET_GOVERNANCE_DISCRIMINATOR, "set_governance"),
+    (ENABLE_GOVERNANCE_ONLY_DISCRIMINATOR, "enable_governance_only"),
+    (MIGRATE_FROM_ANCHOR_DISCRIMINATOR, "migrate_from_anchor"),
+    (TRY_DISPATCH_DISCRIMINATOR, "try_dispatch"),
+    (SCHEDULE_CUT_DISCRIMINATOR, "schedule_cut"),
+    (EXECUTE_CUT_DISCRIMINATOR, "execute_cut"),
+    (MAINTAIN_DISCRIMINATOR, "maintain"),
+    (SET_TENANT_DISCRIMINATOR, "set_tenant"),
+    (TENANT_CUT_DISCRIMINATOR, "tenant_cut"),
+    (TRANSFER_OWNERSHIP_DISCRIMINATOR, "transfer_ownership"),
+    (ACCEPT_OWNERSHIP_DISCRIMINATOR, "accept_ownership"),
+    (DISPATCH_TRACED_DISCRIMINATOR, "dispatch_traced"),
+    (DISPATCH_AUDITED_DISCRIMINATOR, "dispatch_audited"),
+    (ALLOCATE_SELECTOR_DISCRIMINATOR, "allocate_selector"),
+    (RESOLVE_SELECTOR_DISCRIMINATOR, "resolve_selector"),
+    (DISPATCH_SIGNED_DISCRIMINATOR, "dispatch_signed"),
+    (DISPATCH_PROVEN_DISCRIMINATOR, "dispatch_proven"),
+    (SET_PAUSE_SCOPES_DISCRIMINATOR, "set_pause_scopes"),
+    (DISPATCH_CHECKED_DISCRIMINATOR, "dispatch_checked"),
+    (DISPATCH_RELAYED_DISCRIMINATOR, "dispatch_relayed"),
+    (DISPATCH_FAST_DISCRIMINATOR, "dispatch_fast"),
+    (FUND_VAULT_DISCRIMINATOR, "fund_vault"),
+    (WITHDRAW_VAULT_DISCRIMINATOR, "withdraw_vault"),
+    (PROPOSE_MOTION_DISCRIMINATOR, "propose_motion"),
+    (APPROVE_MOTION_DISCRIMINATOR, "approve_motion"),
+    (EXECUTE_MOTION_DISCRIMINATOR, "execute_motion"),
+    (SWEEP_LAMPORTS_DISCRIMINATOR, "sweep_lamports"),
+    (SWEEP_TOKEN_DISCRIMINATOR, "sweep_token"),
+    (DISPATCH_COMPRESSED_DISCRIMINATOR, "dispatch_compressed"),
+    (REQUEST_REGISTRATION_DISCRIMINATOR, "request_registration"),
+    (DISPATCH_MAPPED_DISCRIMINATOR, "dispatch_mapped"),
+    (DISPATCH_INDEXED_DISCRIMINATOR, "dispatch_indexed"),
+    (MIGRATE_STATE_DISCRIMINATOR, "migrate_state"),
+    (CLOSE_PROPOSAL_DISCRIMINATOR, "close_proposal"),
+    (OPEN_SESSION_DISCRIMINATOR, "open_session"),
+    (DISPATCH_SESSION_DISCRIMINATOR, "dispatch_session"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_numbered_in_order() {
+        for (i, (discriminator, name)) in INSTRUCTIONS.iter().enumerate() {
+            assert_eq!(discriminator[0] as usize, i + 1, "{} is out of order", name);
+            assert!(discriminator[1..].iter().all(|b| *b == 0), "{} has trailing bytes", name);
+        }
+    }
+}
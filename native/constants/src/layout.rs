@@ -0,0 +1,117 @@
+//! Borsh-encoded sizes of diamond state entries, and offsets within them
+
+pub const PUBKEY_LEN: usize = 32;
+pub const OWNER_LEN: usize = PUBKEY_LEN;
+pub const ADMIN_LEN: usize = PUBKEY_LEN;
+
+/// Selectors are stored left-aligned in 8 bytes at every width
+pub const SELECTOR_LEN: usize = 8;
+pub const NAMESPACE_LEN: usize = 8;
+pub const TAG_LEN: usize = 8;
+pub const MODULE_NAME_LEN: usize = 32;
+pub const FUNCTION_NAME_LEN: usize = 64;
+
+/// `major.minor.patch`, u16 each
+pub const SEMVER_LEN: usize = 6;
+
+/// name, address, version, is_active
+pub const MODULE_META_LEN: usize = MODULE_NAME_LEN + PUBKEY_LEN + SEMVER_LEN + 1;
+
+/// namespace, selector, module, function_name, is_immutable,
+/// standard_accounts, salt
+pub const SELECTOR_MAPPING_LEN: usize = NAMESPACE_LEN + SELECTOR_LEN + PUBKEY_LEN + FUNCTION_NAME_LEN + 3;
+
+/// Offsets within one selector mapping
+pub const MAPPING_SELECTOR_OFFSET: usize = NAMESPACE_LEN;
+pub const MAPPING_MODULE_OFFSET: usize = MAPPING_SELECTOR_OFFSET + SELECTOR_LEN;
+
+/// Fixed offsets at the start of the diamond state: owner, then the layout
+/// version, the dispatch pause flag and selector width, then the selector index
+pub const STATE_VERSION_OFFSET: usize = OWNER_LEN;
+pub const STATE_PAUSED_OFFSET: usize = STATE_VERSION_OFFSET + 1;
+pub const STATE_WIDTH_OFFSET: usize = STATE_PAUSED_OFFSET + 1;
+pub const STATE_INDEX_OFFSET: usize = STATE_WIDTH_OFFSET + 1;
+
+/// Diamond state layout this build reads and writes
+///
+/// Versions count up from 0x80: an account in the original, unversioned
+/// layout holds its admin count (at most `MAX_ADMINS`) at
+/// `STATE_VERSION_OFFSET`, so it never reads as versioned.
+pub const STATE_LAYOUT_VERSION: u8 = 0x80;
+
+/// selector, position in the selector table
+pub const SELECTOR_INDEX_ENTRY_LEN: usize = SELECTOR_LEN + 1;
+
+/// selector, module, flags (one selector index account entry)
+pub const INDEXED_ROUTE_LEN: usize = SELECTOR_LEN + PUBKEY_LEN + 1;
+
+/// alias, canonical
+pub const SELECTOR_ALIAS_LEN: usize = 2 * SELECTOR_LEN;
+
+/// tag, selector
+pub const SELECTOR_TAG_LEN: usize = TAG_LEN + SELECTOR_LEN;
+
+/// namespace, authority
+pub const TENANT_LEN: usize = NAMESPACE_LEN + PUBKEY_LEN;
+
+/// facet, reentrancy, stack_height
+pub const ACTIVE_CALL_LEN: usize = PUBKEY_LEN + 2;
+
+/// max_accounts (u16), max_payload_len (u32), max_depth (u8)
+pub const DISPATCH_LIMITS_LEN: usize = 2 + 4 + 1;
+
+/// selector, min_len, max_len, schema_hash
+pub const PAYLOAD_SPEC_LEN: usize = SELECTOR_LEN + 2 + 2 + 8;
+
+/// module, hash, last_check (Option: deploy_slot, matched)
+pub const BUILD_ATTESTATION_LEN: usize = PUBKEY_LEN + 32 + 1 + 8 + 1;
+
+/// module, namespace, min_version
+pub const FACET_DEPENDENCY_LEN: usize = PUBKEY_LEN + NAMESPACE_LEN + SEMVER_LEN;
+
+/// module, version, hash, slot
+pub const VERSION_RECORD_LEN: usize = PUBKEY_LEN + SEMVER_LEN + 32 + 8;
+
+/// selector, feed, max_age_secs (u32), max_confidence_bps (u16)
+pub const ORACLE_GUARD_LEN: usize = SELECTOR_LEN + PUBKEY_LEN + 4 + 2;
+
+/// start_slot, end_slot
+pub const PAUSE_WINDOW_LEN: usize = 8 + 8;
+
+/// program, min_severity
+pub const HEALTH_REGISTRY_LEN: usize = PUBKEY_LEN + 1;
+
+/// facet, killed, severity, advisory, updated_at, bump (a registry's status account)
+pub const FACET_STATUS_LEN: usize = PUBKEY_LEN + 1 + 1 + ADVISORY_LEN + 8 + 1;
+
+/// selector, module (Option<Pubkey>), effective_slot
+pub const BREAK_GLASS_LEN: usize = SELECTOR_LEN + 1 + PUBKEY_LEN + 8;
+
+/// selector, metric, threshold, window_slots, window_start, count, tripped_slot (Option<u64>)
+pub const CIRCUIT_BREAKER_LEN: usize = SELECTOR_LEN + 1 + 4 + 8 + 8 + 4 + 1 + 8;
+
+/// diamond, facet, selector, payer, signer, slot, stack_height, bump (the call context account)
+pub const CALL_CONTEXT_LEN: usize = PUBKEY_LEN * 2 + SELECTOR_LEN + PUBKEY_LEN * 2 + 8 + 1 + 1;
+
+/// Advisory reference of a facet status (zero padded)
+pub const ADVISORY_LEN: usize = 64;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_entry_sizes() {
+        assert_eq!(MODULE_META_LEN, 71);
+        assert_eq!(SELECTOR_MAPPING_LEN, 115);
+        assert_eq!((MAPPING_SELECTOR_OFFSET, MAPPING_MODULE_OFFSET), (8, 16));
+        assert_eq!((SELECTOR_ALIAS_LEN, SELECTOR_TAG_LEN, TENANT_LEN, ACTIVE_CALL_LEN), (16, 16, 40, 34));
+        assert_eq!((DISPATCH_LIMITS_LEN, PAYLOAD_SPEC_LEN), (7, 20));
+        assert_eq!((BUILD_ATTESTATION_LEN, FACET_DEPENDENCY_LEN, VERSION_RECORD_LEN), (74, 46, 78));
+        assert_eq!((ORACLE_GUARD_LEN, PAUSE_WINDOW_LEN), (46, 16));
+        assert_eq!((HEALTH_REGISTRY_LEN, FACET_STATUS_LEN), (33, 107));
+        assert_eq!((STATE_VERSION_OFFSET, STATE_PAUSED_OFFSET, STATE_WIDTH_OFFSET, STATE_INDEX_OFFSET), (32, 33, 34, 35));
+        assert_eq!(SELECTOR_INDEX_ENTRY_LEN, 9);
+        assert_eq!((INDEXED_ROUTE_LEN, BREAK_GLASS_LEN, CALL_CONTEXT_LEN, CIRCUIT_BREAKER_LEN), (41, 49, 146, 42));
+    }
+}
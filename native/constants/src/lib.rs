@@ -0,0 +1,19 @@
+/*!
+ * Diamond Constants
+ * Byte-level agreements between the router, facets and clients
+ *
+ * Instruction discriminators, PDA seeds, reserved selectors, selector widths,
+ * table capacities and the encoded sizes of state entries, with no
+ * dependencies at all. The router, `diamond-core`, the example facets and
+ * every client read them from here rather than repeating literals, so none
+ * of them can disagree about an offset or a seed. The router's layout tests
+ * check the encoded sizes against its Borsh types.
+ */
+
+#![no_std]
+
+pub mod capacity;
+pub mod discriminators;
+pub mod layout;
+pub mod seeds;
+pub mod selectors;
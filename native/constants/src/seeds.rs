@@ -0,0 +1,83 @@
+//! PDA seed prefixes of router-derived accounts
+
+/// `[DIAMOND_STATE_SEED, creator, (vanity nonce)]` under the router
+pub const DIAMOND_STATE_SEED: &[u8] = b"diamond_state";
+/// `[PROPOSAL_SEED, diamond, id]`
+pub const PROPOSAL_SEED: &[u8] = b"proposal";
+/// `[MOTION_SEED, diamond, id]`
+pub const MOTION_SEED: &[u8] = b"motion";
+/// `[RENT_VAULT_SEED, diamond]`
+pub const RENT_VAULT_SEED: &[u8] = b"rent_vault";
+/// `[FAST_PATH_SEED, diamond]`
+pub const FAST_PATH_SEED: &[u8] = b"fast_path";
+/// `[ROUTING_DIGEST_SEED, diamond]`
+pub const ROUTING_DIGEST_SEED: &[u8] = b"routing_digest";
+/// `[AUDIT_SEED, diamond]`, authority of the audit tree
+pub const AUDIT_SEED: &[u8] = b"audit";
+/// `[CONFIG_SEED, diamond, namespace, key]`
+pub const CONFIG_SEED: &[u8] = b"config";
+/// `[RELAY_NONCE_SEED, diamond, user]`
+pub const RELAY_NONCE_SEED: &[u8] = b"relay_nonce";
+/// `[RELAY_SIGNER_SEED, diamond, user]`, signed for on relayed calls
+pub const RELAY_SIGNER_SEED: &[u8] = b"relayed";
+/// `[FEE_VAULT_SEED, diamond, user]`
+pub const FEE_VAULT_SEED: &[u8] = b"fee_vault";
+/// `[FACET_SIGNER_SEED, diamond, facet, seed]`, signed for on `dispatch_signed`
+pub const FACET_SIGNER_SEED: &[u8] = b"signer";
+/// `[RETURN_SCRATCH_SEED, diamond]` under the facet program
+pub const RETURN_SCRATCH_SEED: &[u8] = b"return_scratch";
+/// `[REGISTRAR_SEED, diamond]` under the facet program, signed for on `request_registration`
+pub const REGISTRAR_SEED: &[u8] = b"registrar";
+/// `[FACET_STATUS_SEED, facet]` under a health registry program
+pub const FACET_STATUS_SEED: &[u8] = b"facet_status";
+/// `[SELECTOR_INDEX_SEED, diamond]`
+pub const SELECTOR_INDEX_SEED: &[u8] = b"selector_index";
+/// `[CALL_CONTEXT_SEED, diamond]`
+pub const CALL_CONTEXT_SEED: &[u8] = b"call_context";
+/// `[FACET_CONFIG_SEED, diamond, facet]`
+pub const FACET_CONFIG_SEED: &[u8] = b"facet_config";
+/// `[COUNCIL_SEED, diamond]`
+pub const COUNCIL_SEED: &[u8] = b"council";
+/// `[FACET_RECORD_SEED, diamond, facet]`
+pub const FACET_RECORD_SEED: &[u8] = b"facet_record";
+/// `[SESSION_SEED, diamond, user, session_key]`
+pub const SESSION_SEED: &[u8] = b"session";
+
+/// Every router seed prefix
+pub const ALL: [&[u8]; 21] = [
+    DIAMOND_STATE_SEED,
+    PROPOSAL_SEED,
+    MOTION_SEED,
+    RENT_VAULT_SEED,
+    FAST_PATH_SEED,
+    ROUTING_DIGEST_SEED,
+    AUDIT_SEED,
+    CONFIG_SEED,
+    RELAY_NONCE_SEED,
+    RELAY_SIGNER_SEED,
+    FEE_VAULT_SEED,
+    FACET_SIGNER_SEED,
+    RETURN_SCRATCH_SEED,
+    REGISTRAR_SEED,
+    FACET_STATUS_SEED,
+    SELECTOR_INDEX_SEED,
+    CALL_CONTEXT_SEED,
+    FACET_CONFIG_SEED,
+    COUNCIL_SEED,
+    FACET_RECORD_SEED,
+    SESSION_SEED,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_seeds_distinct_and_valid() {
+        for (i, seed) in ALL.iter().enumerate() {
+            // Solana's MAX_SEED_LEN
+            assert!(seed.len() <= 32);
+            assert!(ALL[i + 1..].iter().all(|other| other != seed));
+        }
+    }
+}
@@ -0,0 +1,32 @@
+//! Selector widths and the selectors reserved for the router
+
+/// Widths a diamond may be initialized with
+pub const SELECTOR_WIDTHS: [u8; 3] = [2, 4, 8];
+
+/// Width of diamonds initialized without choosing one
+pub const DEFAULT_SELECTOR_WIDTH: u8 = 4;
+
+/// Leading bytes of every reserved selector ("diamond")
+pub const RESERVED_PREFIX: [u8; 2] = [0xD1, 0xA0];
+
+/// Answered by every facet (not the router); reserved so it can't be routed
+pub const HEALTH_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0xFF, 0x01];
+
+/// Answered by a diamond's authorizer program; reserved likewise
+pub const AUTHORIZE_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0xFF, 0x02];
+
+/// Leading bytes of an overflow pointer (in the reserved selector range)
+pub const OVERFLOW_MAGIC: [u8; 4] = [0xD1, 0xA0, 0xEE, 0x01];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_reserved_selectors() {
+        assert!(SELECTOR_WIDTHS.contains(&DEFAULT_SELECTOR_WIDTH));
+        for selector in [HEALTH_SELECTOR, AUTHORIZE_SELECTOR, OVERFLOW_MAGIC] {
+            assert_eq!(selector[..2], RESERVED_PREFIX);
+        }
+    }
+}
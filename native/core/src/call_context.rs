@@ -0,0 +1,92 @@
+/*!
+ * Call context
+ * Who called a facet through the router
+ *
+ * A diamond can keep one router-owned account at `[CALL_CONTEXT_SEED,
+ * diamond]`. Whenever it is among a dispatch's accounts, the router writes
+ * the call's context there right before the facet CPI, forwards it
+ * read-only, and restores what it held before once the CPI returns. Outside
+ * a router call the account is therefore idle (no facet), and during one it
+ * names the facet being called, so a facet that finds itself named in the
+ * context (and checks the account's owner and address) one level above the
+ * router's stack height knows the call came through the router and from
+ * whom. The height check matters: while the router calls one facet, that
+ * facet may call the same facet directly, which then finds itself named
+ * but two levels up.
+ */
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use crate::selector::Selector;
+
+pub use diamond_constants::{layout::CALL_CONTEXT_LEN, seeds::CALL_CONTEXT_SEED};
+
+/// The router call in progress
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CallContext {
+    pub diamond: [u8; 32],
+    /// Facet being called; zero while no call is in progress
+    pub facet: [u8; 32],
+    /// Canonical selector of the call (aliases already resolved)
+    pub selector: Selector,
+    /// First writable signer among the dispatch's accounts. Not necessarily
+    /// the fee payer, which the runtime doesn't show programs: don't use it
+    /// to attribute payment.
+    pub first_writable_signer: [u8; 32],
+    /// The relayed user on relayed calls, else the first signer
+    pub signer: [u8; 32],
+    pub slot: u64,
+    /// The router's stack height; the facet runs one above it
+    pub stack_height: u8,
+    pub bump: u8,
+}
+
+impl CallContext {
+    pub const ENCODED_LEN: usize = CALL_CONTEXT_LEN;
+    
+    /// Context held by a call context account's data
+    pub fn read(data: &[u8]) -> Option<Self> {
+        Self::deserialize(&mut &data[..]).ok()
+    }
+    
+    /// Context of a call to `facet` through `diamond`, if the facet, running
+    /// at `stack_height` (its `get_stack_height()`), was invoked by it
+    pub fn of_call(data: &[u8], diamond: &[u8; 32], facet: &[u8; 32], stack_height: usize) -> Option<Self> {
+        Self::read(data).filter(|context| {
+            &context.diamond == diamond
+                && &context.facet == facet
+                && facet != &[0; 32]
+                && context.stack_height as usize + 1 == stack_height
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_of_call_names_the_facet() {
+        let context = CallContext {
+            diamond: [1; 32],
+            facet: [2; 32],
+            selector: Selector([3, 0, 0, 0, 0, 0, 0, 0]),
+            first_writable_signer: [4; 32],
+            signer: [5; 32],
+            slot: 6,
+            stack_height: 1,
+            bump: 254,
+        };
+        let encoded = borsh::to_vec(&context).unwrap();
+        assert_eq!(encoded.len(), CallContext::ENCODED_LEN);
+        assert_eq!(CallContext::of_call(&encoded, &[1; 32], &[2; 32], 2), Some(context));
+        assert_eq!(CallContext::of_call(&encoded, &[1; 32], &[9; 32], 2), None);
+        assert_eq!(CallContext::of_call(&encoded, &[9; 32], &[2; 32], 2), None);
+        // The facet called directly from within the router's call
+        assert_eq!(CallContext::of_call(&encoded, &[1; 32], &[2; 32], 3), None);
+        
+        // Between calls only the diamond and bump are set
+        let idle = borsh::to_vec(&CallContext { diamond: [1; 32], bump: 254, ..CallContext::default() }).unwrap();
+        assert_eq!(CallContext::of_call(&idle, &[1; 32], &[0; 32], 1), None);
+    }
+}
@@ -0,0 +1,165 @@
+/*!
+ * Payload compression
+ * A small LZ77 codec for large dispatch payloads
+ *
+ * Facets taking large argument blobs (merkle proofs, batched orders) run
+ * into the transaction size limit long before the compute budget, so the
+ * router accepts compressed payloads and inflates them before the CPI. The
+ * codec is deliberately simple, byte-aligned LZ77 with a 64 KiB window: it
+ * decodes in a single pass with no tables, which keeps the on-chain side
+ * cheap and easy to audit.
+ *
+ * A frame is `codec: u8 | raw_len: u32 | body`. `CODEC_STORED` bodies are
+ * the raw bytes. `CODEC_LZ` bodies are a token stream: a control byte below
+ * 0x80 is followed by `control + 1` literal bytes; otherwise it copies
+ * `(control & 0x7F) + MIN_MATCH` bytes from a u16 distance back in the
+ * output.
+ */
+
+use alloc::vec::Vec;
+
+/// Body is the payload as-is
+pub const CODEC_STORED: u8 = 0;
+/// Body is an LZ token stream
+pub const CODEC_LZ: u8 = 1;
+
+/// codec, raw_len
+pub const FRAME_HEADER_LEN: usize = 5;
+
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 0x7F + MIN_MATCH;
+const MAX_LITERALS: usize = 0x80;
+const MAX_DISTANCE: usize = u16::MAX as usize;
+const HASH_BITS: u32 = 12;
+
+fn hash(bytes: &[u8]) -> usize {
+    let word = u32::from(bytes[0]) | u32::from(bytes[1]) << 8 | u32::from(bytes[2]) << 16;
+    (word.wrapping_mul(2_654_435_761) >> (32 - HASH_BITS)) as usize
+}
+
+fn flush_literals(out: &mut Vec<u8>, literals: &[u8]) {
+    for run in literals.chunks(MAX_LITERALS) {
+        out.push(run.len() as u8 - 1);
+        out.extend_from_slice(run);
+    }
+}
+
+fn lz_body(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    // Last position seen for each 3-byte hash (offset by one; 0 is empty)
+    let mut table = alloc::vec![0usize; 1 << HASH_BITS];
+    let (mut pos, mut literal_start) = (0, 0);
+    while pos + MIN_MATCH <= data.len() {
+        let slot = hash(&data[pos..]);
+        let candidate = table[slot].checked_sub(1);
+        table[slot] = pos + 1;
+        let len = candidate
+            .filter(|c| pos - c <= MAX_DISTANCE)
+            .map(|c| data[c..].iter().zip(&data[pos..]).take(MAX_MATCH).take_while(|(a, b)| a == b).count())
+            .unwrap_or(0);
+        if len < MIN_MATCH {
+            pos += 1;
+            continue;
+        }
+        flush_literals(&mut out, &data[literal_start..pos]);
+        out.push(0x80 | (len - MIN_MATCH) as u8);
+        out.extend_from_slice(&((pos - candidate.unwrap_or(0)) as u16).to_le_bytes());
+        pos += len;
+        literal_start = pos;
+    }
+    flush_literals(&mut out, &data[literal_start..]);
+    out
+}
+
+/// Frame `data`, LZ-compressed unless that would not make it smaller
+///
+/// Payloads over 4 GiB are not supported.
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let body = lz_body(data);
+    let (codec, body) = if body.len() < data.len() { (CODEC_LZ, &body[..]) } else { (CODEC_STORED, data) };
+    let mut frame = Vec::with_capacity(FRAME_HEADER_LEN + body.len());
+    frame.push(codec);
+    frame.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    frame.extend_from_slice(body);
+    frame
+}
+
+/// Declared payload length of a frame
+pub fn raw_len(frame: &[u8]) -> Option<usize> {
+    Some(u32::from_le_bytes(frame.get(1..FRAME_HEADER_LEN)?.try_into().ok()?) as usize)
+}
+
+/// Inflate a frame, refusing any that declares more than `max_len` bytes
+///
+/// `None` for unknown codecs, truncated or inconsistent bodies, and matches
+/// reaching before the start of the output.
+pub fn decompress(frame: &[u8], max_len: usize) -> Option<Vec<u8>> {
+    let len = raw_len(frame).filter(|len| *len <= max_len)?;
+    let body = &frame[FRAME_HEADER_LEN..];
+    match frame[0] {
+        CODEC_STORED => (body.len() == len).then(|| body.to_vec()),
+        CODEC_LZ => {
+            let mut out = Vec::with_capacity(len);
+            let mut rest = body;
+            while let Some((&control, tail)) = rest.split_first() {
+                if control < 0x80 {
+                    let (literals, tail) = (tail.len() > control as usize).then(|| tail.split_at(control as usize + 1))?;
+                    out.extend_from_slice(literals);
+                    rest = tail;
+                } else {
+                    let distance = u16::from_le_bytes(tail.get(..2)?.try_into().ok()?) as usize;
+                    let start = out.len().checked_sub(distance).filter(|_| distance > 0)?;
+                    // Overlapping copies repeat the pattern, so go byte by byte
+                    for i in 0..(control & 0x7F) as usize + MIN_MATCH {
+                        out.push(out[start + i]);
+                    }
+                    rest = &tail[2..];
+                }
+                if out.len() > len {
+                    return None;
+                }
+            }
+            (out.len() == len).then_some(out)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_round_trip() {
+        let mut proof = Vec::new();
+        for i in 0..40u8 {
+            proof.extend_from_slice(&[i % 4; 32]);
+            proof.extend_from_slice(b"order:limit:buy:");
+        }
+        let frame = compress(&proof);
+        assert_eq!(frame[0], CODEC_LZ);
+        assert!(frame.len() < proof.len() / 4);
+        assert_eq!(raw_len(&frame), Some(proof.len()));
+        assert_eq!(decompress(&frame, proof.len()).unwrap(), proof);
+        assert_eq!(decompress(&frame, proof.len() - 1), None);
+        
+        // Incompressible input is stored as-is
+        let noise: Vec<u8> = (0..200u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8).collect();
+        let frame = compress(&noise);
+        assert_eq!(frame[0], CODEC_STORED);
+        assert_eq!(decompress(&frame, 1024).unwrap(), noise);
+        assert_eq!(decompress(&compress(&[]), 0).unwrap(), Vec::<u8>::new());
+    }
+    
+    #[test]
+    fn test_malformed_frames() {
+        let frame = compress(&[7u8; 300]);
+        assert_eq!(decompress(&frame[..frame.len() - 1], 300), None);
+        assert_eq!(decompress(&[9, 1, 0, 0, 0, 0], 1), None);
+        assert_eq!(decompress(&[CODEC_STORED, 2, 0, 0, 0, 1], 2), None);
+        // A match before the start of the output
+        assert_eq!(decompress(&[CODEC_LZ, 3, 0, 0, 0, 0x80, 1, 0], 3), None);
+        // More output than declared
+        assert_eq!(decompress(&[CODEC_LZ, 1, 0, 0, 0, 0, 1, 0x80, 1, 0], 1), None);
+    }
+}
@@ -0,0 +1,144 @@
+/*!
+ * Routing digest
+ * Merkle commitment to a diamond's selector table
+ *
+ * Leaves are the Borsh-encoded selector mappings, hashed with a domain
+ * prefix and ordered by selector bytes; the leaf list is padded with zero
+ * leaves to a power of two, so a proof is exactly `depth(count)` siblings and
+ * a leaf's index alone says which side each sibling is on. The empty table's
+ * root is all zeros.
+ *
+ * Every function takes the hash as a parameter so on-chain callers can use
+ * the sha256 syscall; `sha256` is the host equivalent.
+ */
+
+use alloc::vec::Vec;
+use diamond_constants::layout::{MAPPING_SELECTOR_OFFSET, SELECTOR_LEN};
+use sha2::{Digest, Sha256};
+
+use crate::routing::{selector_table, SELECTOR_MAPPING_LEN};
+
+/// Hash over the concatenation of `parts`
+pub type Hasher = fn(&[&[u8]]) -> [u8; 32];
+
+pub const LEAF_PREFIX: &[u8] = b"diamond-route";
+pub const NODE_PREFIX: &[u8] = b"diamond-node";
+
+/// Padding leaf (no real leaf hashes to zero)
+pub const EMPTY_LEAF: [u8; 32] = [0; 32];
+
+pub fn sha256(parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+/// Leaf for one Borsh-encoded selector mapping
+pub fn leaf(hash: Hasher, mapping: &[u8]) -> [u8; 32] {
+    hash(&[LEAF_PREFIX, mapping])
+}
+
+fn node(hash: Hasher, left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    hash(&[NODE_PREFIX, left, right])
+}
+
+/// Proof length for a table of `count` selectors
+pub fn depth(count: usize) -> usize {
+    count.next_power_of_two().trailing_zeros() as usize
+}
+
+fn padded(leaves: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    let mut level = leaves.to_vec();
+    level.resize(leaves.len().next_power_of_two(), EMPTY_LEAF);
+    level
+}
+
+fn parent_level(hash: Hasher, level: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    level.chunks(2).map(|pair| node(hash, &pair[0], &pair[1])).collect()
+}
+
+/// Root over leaves already in canonical (selector) order
+pub fn root(hash: Hasher, leaves: &[[u8; 32]]) -> [u8; 32] {
+    if leaves.is_empty() {
+        return EMPTY_LEAF;
+    }
+    let mut level = padded(leaves);
+    while level.len() > 1 {
+        level = parent_level(hash, &level);
+    }
+    level[0]
+}
+
+/// Sibling path for the leaf at `index`, bottom up
+pub fn proof(hash: Hasher, leaves: &[[u8; 32]], index: usize) -> Option<Vec<[u8; 32]>> {
+    if index >= leaves.len() {
+        return None;
+    }
+    let mut level = padded(leaves);
+    let mut position = index;
+    let mut path = Vec::with_capacity(depth(leaves.len()));
+    while level.len() > 1 {
+        path.push(level[position ^ 1]);
+        level = parent_level(hash, &level);
+        position /= 2;
+    }
+    Some(path)
+}
+
+/// Whether `leaf` sits at `index` of a `count`-selector table committed to by `root`
+pub fn verify(hash: Hasher, leaf: [u8; 32], index: usize, count: usize, path: &[[u8; 32]], root: &[u8; 32]) -> bool {
+    if index >= count || path.len() != depth(count) {
+        return false;
+    }
+    let mut current = leaf;
+    let mut position = index;
+    for sibling in path {
+        current = if position & 1 == 0 {
+            node(hash, &current, sibling)
+        } else {
+            node(hash, sibling, &current)
+        };
+        position /= 2;
+    }
+    &current == root
+}
+
+/// Leaves of a diamond state account's selector table, in canonical order
+pub fn state_leaves(hash: Hasher, state_data: &[u8]) -> Option<Vec<[u8; 32]>> {
+    let (start, count) = selector_table(state_data)?;
+    let mut entries = (0..count)
+        .map(|i| state_data.get(start + i * SELECTOR_MAPPING_LEN..start + (i + 1) * SELECTOR_MAPPING_LEN))
+        .collect::<Option<Vec<_>>>()?;
+    entries.sort_by_key(|entry| &entry[MAPPING_SELECTOR_OFFSET..MAPPING_SELECTOR_OFFSET + SELECTOR_LEN]);
+    Some(entries.into_iter().map(|entry| leaf(hash, entry)).collect())
+}
+
+/// Routing root recomputed from diamond state account bytes
+pub fn state_root(hash: Hasher, state_data: &[u8]) -> Option<[u8; 32]> {
+    state_leaves(hash, state_data).map(|leaves| root(hash, &leaves))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_proofs_verify_at_every_size() {
+        for count in 1..=9usize {
+            let leaves: Vec<[u8; 32]> = (0..count).map(|i| leaf(sha256, &[i as u8])).collect();
+            let root = root(sha256, &leaves);
+            for (index, leaf) in leaves.iter().enumerate() {
+                let path = proof(sha256, &leaves, index).unwrap();
+                assert_eq!(path.len(), depth(count));
+                assert!(verify(sha256, *leaf, index, count, &path, &root));
+                if count > 1 {
+                    assert!(!verify(sha256, *leaf, (index + 1) % count, count, &path, &root));
+                }
+            }
+            assert!(!verify(sha256, EMPTY_LEAF, count, count + 1, &[], &root));
+        }
+        assert_eq!(root(sha256, &[]), EMPTY_LEAF);
+    }
+}
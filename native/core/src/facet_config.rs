@@ -0,0 +1,59 @@
+/*!
+ * Facet config
+ * Per-diamond parameters a facet reads from a router-owned account
+ *
+ * The owner attaches a small immutable blob to a facet once. The router
+ * keeps it at `[FACET_CONFIG_SEED, diamond, facet]` and never touches the
+ * call data, so a facet's wire format is the same with or without a config
+ * and configuring a live facet breaks none of its callers. A generic facet
+ * takes the account among its inputs and reads its parameters from there
+ * instead of keeping its own setup instruction and state.
+ */
+
+use alloc::vec::Vec;
+use borsh::{BorshDeserialize, BorshSerialize};
+
+pub use diamond_constants::{capacity::MAX_FACET_CONFIG_LEN, seeds::FACET_CONFIG_SEED};
+
+/// Config blob one diamond gave one facet
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct FacetConfig {
+    pub diamond: [u8; 32],
+    pub facet: [u8; 32],
+    pub data: Vec<u8>,
+    pub bump: u8,
+}
+
+impl FacetConfig {
+    pub const MAX_ENCODED_LEN: usize = 32 + 32 + 4 + MAX_FACET_CONFIG_LEN + 1;
+    
+    /// Config held by a config account's data
+    pub fn read(data: &[u8]) -> Option<Self> {
+        Self::deserialize(&mut &data[..]).ok()
+    }
+    
+    /// Config `diamond` gave `facet`, if the data holds it
+    pub fn of_facet(data: &[u8], diamond: &[u8; 32], facet: &[u8; 32]) -> Option<Self> {
+        Self::read(data).filter(|config| &config.diamond == diamond && &config.facet == facet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_config_round_trip() {
+        let config = FacetConfig { diamond: [1; 32], facet: [2; 32], data: b"fee=30".to_vec(), bump: 254 };
+        let mut encoded = borsh::to_vec(&config).unwrap();
+        assert_eq!(FacetConfig::of_facet(&encoded, &[1; 32], &[2; 32]), Some(config.clone()));
+        assert_eq!(FacetConfig::of_facet(&encoded, &[1; 32], &[3; 32]), None);
+        assert_eq!(FacetConfig::of_facet(&encoded, &[3; 32], &[2; 32]), None);
+        
+        // Accounts are allocated at the maximum size
+        encoded.resize(FacetConfig::MAX_ENCODED_LEN, 0);
+        assert_eq!(FacetConfig::read(&encoded), Some(config));
+        let full = FacetConfig { data: alloc::vec![0xFF; MAX_FACET_CONFIG_LEN], ..FacetConfig::read(&encoded).unwrap() };
+        assert_eq!(borsh::to_vec(&full).unwrap().len(), FacetConfig::MAX_ENCODED_LEN);
+    }
+}
@@ -0,0 +1,82 @@
+/*!
+ * Facet status
+ * Shared advisory account a health registry keeps per facet program
+ *
+ * A registry program publishes one status per facet program at
+ * `[FACET_STATUS_SEED, facet]`: whether the facet is killed, the severity
+ * of its open advisory and a short advisory reference. Every router that
+ * opts in to the registry reads the status on calls to the facet, so one
+ * publication reaches every diamond routing to it.
+ */
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+pub use diamond_constants::{
+    layout::{ADVISORY_LEN, FACET_STATUS_LEN},
+    seeds::FACET_STATUS_SEED,
+};
+
+/// Advisory severities, in increasing order
+pub mod severity {
+    pub const NONE: u8 = 0;
+    pub const LOW: u8 = 1;
+    pub const MEDIUM: u8 = 2;
+    pub const HIGH: u8 = 3;
+    pub const CRITICAL: u8 = 4;
+}
+
+/// Published status of one facet program
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FacetStatus {
+    pub facet: [u8; 32],
+    /// Calls to the facet must stop, whatever their severity threshold
+    pub killed: bool,
+    pub severity: u8,
+    /// Advisory id or short link (zero padded)
+    pub advisory: [u8; ADVISORY_LEN],
+    /// Unix time of the last publication
+    pub updated_at: i64,
+    pub bump: u8,
+}
+
+impl FacetStatus {
+    pub const ENCODED_LEN: usize = FACET_STATUS_LEN;
+    
+    /// Status held by a status account's data
+    pub fn read(data: &[u8]) -> Option<Self> {
+        Self::deserialize(&mut &data[..]).ok()
+    }
+    
+    /// Whether a router blocking from `min_severity` (0 = only the kill
+    /// flag) refuses calls to the facet
+    pub fn blocks(&self, min_severity: u8) -> bool {
+        self.killed || (min_severity != severity::NONE && self.severity >= min_severity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_blocks_from_threshold() {
+        let status = FacetStatus {
+            facet: [1; 32],
+            killed: false,
+            severity: severity::HIGH,
+            advisory: [0; ADVISORY_LEN],
+            updated_at: 0,
+            bump: 255,
+        };
+        assert!(!status.blocks(severity::NONE));
+        assert!(status.blocks(severity::MEDIUM));
+        assert!(status.blocks(severity::HIGH));
+        assert!(!status.blocks(severity::CRITICAL));
+        assert!(FacetStatus { killed: true, ..status }.blocks(severity::NONE));
+        
+        let mut encoded = borsh::to_vec(&status).unwrap();
+        assert_eq!(encoded.len(), FacetStatus::ENCODED_LEN);
+        encoded.truncate(40);
+        assert_eq!(FacetStatus::read(&encoded), None);
+    }
+}
@@ -0,0 +1,56 @@
+/*!
+ * Selector flags
+ * The per-selector flags byte: standard accounts, call options, reentrancy
+ */
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+/// Standard accounts a selector can ask the router to append (bit flags)
+pub const STD_SYSTEM_PROGRAM: u8 = 1 << 0;
+pub const STD_RENT: u8 = 1 << 1;
+pub const STD_CLOCK: u8 = 1 << 2;
+pub const STD_TOKEN_PROGRAM: u8 = 1 << 3;
+
+/// Call option sharing the flags byte: the facet wants the 16-byte trace id
+/// of traced dispatches inserted right after the selector
+pub const CALL_TRACE: u8 = 1 << 7;
+
+/// Call option: on `dispatch_signed`, the router forwards the diamond's rent
+/// vault as a signer so the facet can pay rent for accounts it creates
+pub const RENT_VAULT: u8 = 1 << 4;
+
+/// Reentrancy policy bits in the flags byte (see `Reentrancy`)
+pub const REENTRANCY_SAME_FACET: u8 = 1 << 5;
+pub const REENTRANCY_ALLOWED: u8 = 1 << 6;
+pub const REENTRANCY_MASK: u8 = REENTRANCY_SAME_FACET | REENTRANCY_ALLOWED;
+
+/// Whether a selector's facet may call back into the router while it runs,
+/// and whether it may itself be reached from such a call
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reentrancy {
+    /// Never on the stack twice (the default)
+    Forbidden,
+    /// Nested dispatches may only target the same facet
+    SameFacetOnly,
+    /// Nested dispatches may target any facet that isn't `Forbidden`
+    Allowed,
+}
+
+impl Reentrancy {
+    /// Decode from a flags byte; the unused combination fails closed
+    pub fn from_flags(flags: u8) -> Self {
+        match flags & REENTRANCY_MASK {
+            REENTRANCY_SAME_FACET => Self::SameFacetOnly,
+            REENTRANCY_ALLOWED => Self::Allowed,
+            _ => Self::Forbidden,
+        }
+    }
+    
+    pub fn to_flags(self) -> u8 {
+        match self {
+            Self::Forbidden => 0,
+            Self::SameFacetOnly => REENTRANCY_SAME_FACET,
+            Self::Allowed => REENTRANCY_ALLOWED,
+        }
+    }
+}
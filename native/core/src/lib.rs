@@ -0,0 +1,30 @@
+/*!
+ * Diamond Core
+ * Framework-free diamond logic (no_std + alloc)
+ *
+ * Selector encoding, the per-selector flags byte, fixed-width name fields,
+ * payload specs, facet config accounts, self-registration manifests, shared
+ * facet status accounts, the per-call caller context, the off-chain signing
+ * payload, the routing digest, payload compression and the chunked return
+ * data convention, with no dependency on solana-program. The native router re-exports these, so clients, SVM
+ * rollups and WASM builds can share the exact byte layout without pulling in
+ * the Solana SDK.
+ */
+
+#![no_std]
+
+extern crate alloc;
+
+pub mod call_context;
+pub mod compress;
+pub mod digest;
+pub mod facet_config;
+pub mod facet_status;
+pub mod flags;
+pub mod names;
+pub mod payload;
+pub mod registration;
+pub mod return_data;
+pub mod routing;
+pub mod selector;
+pub mod signing;
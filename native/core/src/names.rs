@@ -0,0 +1,37 @@
+/*!
+ * Fixed-width name fields
+ * Zero-padded UTF-8 labels stored inline in accounts
+ */
+
+/// Pack a label into `N` bytes (zero padded, truncated)
+pub fn fixed_bytes<const N: usize>(label: &str) -> [u8; N] {
+    let mut out = [0u8; N];
+    let bytes = label.as_bytes();
+    let len = bytes.len().min(N);
+    out[..len].copy_from_slice(&bytes[..len]);
+    out
+}
+
+/// Fixed 8-byte form of a namespace label (zero padded), if the label fits
+pub fn namespace_bytes(namespace: &str) -> Option<[u8; 8]> {
+    (namespace.len() <= 8).then(|| fixed_bytes(namespace))
+}
+
+/// Decode a zero-padded fixed-size name field
+pub fn fixed_str(bytes: &[u8]) -> &str {
+    let end = bytes.iter().position(|&c| c == 0).unwrap_or(bytes.len());
+    core::str::from_utf8(&bytes[..end]).unwrap_or("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_fixed_round_trip() {
+        assert_eq!(fixed_str(&namespace_bytes("token").unwrap()), "token");
+        assert_eq!(fixed_str(&namespace_bytes("").unwrap()), "");
+        assert_eq!(namespace_bytes("overlylongname"), None);
+        assert_eq!(fixed_str(&fixed_bytes::<64>("")), "");
+    }
+}
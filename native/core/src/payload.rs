@@ -0,0 +1,48 @@
+/*!
+ * Payload specs
+ * Per-selector argument size and schema declarations
+ *
+ * A spec bounds the argument bytes after the selector and may name the
+ * Borsh schema they follow by an 8-byte hash. The router checks lengths on
+ * every dispatch and the schema hash when the caller states which schema it
+ * encoded against, so malformed calls fail before the facet runs.
+ */
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use sha2::{Digest, Sha256};
+
+use crate::selector::Selector;
+
+pub const SCHEMA_HASH_PREFIX: &[u8] = b"diamond-schema:";
+
+/// Declared shape of one selector's arguments
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PayloadSpec {
+    pub selector: Selector,
+    pub min_len: u16,
+    /// 0 = no upper bound
+    pub max_len: u16,
+    /// `schema_hash` of the argument schema (zero when not declared)
+    pub schema_hash: [u8; 8],
+}
+
+impl PayloadSpec {
+    pub const ENCODED_LEN: usize = diamond_constants::layout::PAYLOAD_SPEC_LEN;
+    
+    pub fn accepts_len(&self, args_len: usize) -> bool {
+        args_len >= self.min_len as usize && (self.max_len == 0 || args_len <= self.max_len as usize)
+    }
+    
+    /// Whether a caller encoding against `claimed` matches the declared schema
+    pub fn accepts_schema(&self, claimed: &[u8; 8]) -> bool {
+        self.schema_hash == [0; 8] || &self.schema_hash == claimed
+    }
+}
+
+/// Hash of a schema description, e.g. `"(u64,Pubkey,Option<u8>)"`
+pub fn schema_hash(schema: &str) -> [u8; 8] {
+    let digest = Sha256::new().chain_update(SCHEMA_HASH_PREFIX).chain_update(schema.as_bytes()).finalize();
+    let mut hash = [0u8; 8];
+    hash.copy_from_slice(&digest[..8]);
+    hash
+}
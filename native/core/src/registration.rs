@@ -0,0 +1,80 @@
+/*!
+ * Self-registration
+ * Manifest a facet submits to route its own selectors
+ *
+ * A facet CPIs `request_registration` into the router, signing with its
+ * registrar PDA (`[REGISTRAR_SEED, diamond]` under the facet's program id).
+ * The router turns the manifest into a cut proposal adding every function to
+ * that facet, and nothing routes until the owner approves it. Declared return
+ * schemas go to the facet's record (`[FACET_RECORD_SEED, diamond, facet]`
+ * under the router) right away, so clients can decode its views from chain.
+ */
+
+use alloc::{string::String, vec::Vec};
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use crate::selector::Selector;
+
+pub use diamond_constants::{
+    discriminators::REQUEST_REGISTRATION_DISCRIMINATOR,
+    seeds::{FACET_RECORD_SEED, REGISTRAR_SEED},
+};
+
+/// One function a facet asks to have routed to it
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ManifestFunction {
+    pub selector: Selector,
+    pub function_name: String,
+    /// Flags byte of the mapping (see `flags`)
+    pub standard_accounts: u8,
+    /// `payload::schema_hash` of the Borsh schema the function returns
+    /// (zero when it returns nothing or doesn't say)
+    pub return_schema: [u8; 8],
+}
+
+/// Functions a facet asks to register, under one module name and namespace
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct RegistrationManifest {
+    pub module_name: String,
+    pub namespace: String,
+    pub functions: Vec<ManifestFunction>,
+}
+
+impl RegistrationManifest {
+    /// `request_registration` instruction data
+    pub fn instruction_data(&self) -> Vec<u8> {
+        let mut data = REQUEST_REGISTRATION_DISCRIMINATOR.to_vec();
+        self.serialize(&mut data).expect("writing to a Vec cannot fail");
+        data
+    }
+    
+    /// Return schemas the manifest declares (zero ones are skipped)
+    pub fn return_schemas(&self) -> impl Iterator<Item = (Selector, [u8; 8])> + '_ {
+        self.functions
+            .iter()
+            .filter(|f| f.return_schema != [0; 8])
+            .map(|f| (f.selector, f.return_schema))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_instruction_data_round_trip() {
+        let manifest = RegistrationManifest {
+            module_name: "vault".into(),
+            namespace: "vault".into(),
+            functions: alloc::vec![ManifestFunction {
+                selector: [1, 0, 0, 1].into(),
+                function_name: "deposit".into(),
+                standard_accounts: 0,
+                return_schema: crate::payload::schema_hash("u64"),
+            }],
+        };
+        let data = manifest.instruction_data();
+        assert_eq!(data[..8], REQUEST_REGISTRATION_DISCRIMINATOR);
+        assert_eq!(RegistrationManifest::try_from_slice(&data[8..]).unwrap(), manifest);
+    }
+}
@@ -0,0 +1,105 @@
+/*!
+ * Chunked return data
+ * Convention for facet results larger than the runtime's return data cap
+ *
+ * A facet whose result does not fit writes it to its scratch PDA
+ * (`[RETURN_SCRATCH_SEED, diamond]` under the facet program) as a u32
+ * length followed by the bytes, and sets return data to an
+ * `OverflowPointer` instead. The router checks the pointer after the CPI and
+ * serves the scratch account back in `MAX_RETURN_DATA`-sized chunks, or
+ * rejects the call, per the diamond's `ReturnOverflowPolicy`.
+ */
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+/// Solana's return data cap (`solana_program::program::MAX_RETURN_DATA`)
+pub const MAX_RETURN_DATA: usize = 1024;
+
+pub use diamond_constants::{seeds::RETURN_SCRATCH_SEED, selectors::OVERFLOW_MAGIC};
+
+/// Length prefix ahead of the payload in a scratch account
+pub const SCRATCH_HEADER_LEN: usize = 4;
+
+/// What the router does when a facet returns an `OverflowPointer`
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReturnOverflowPolicy {
+    /// Check the scratch account and pass the pointer through (the default)
+    #[default]
+    Chunked,
+    /// Fail the dispatch: results must fit in return data
+    Reject,
+}
+
+/// Return data standing in for a result that lives in a scratch account
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OverflowPointer {
+    pub scratch: [u8; 32],
+    /// Payload bytes (without the scratch header)
+    pub len: u32,
+}
+
+impl OverflowPointer {
+    pub const ENCODED_LEN: usize = OVERFLOW_MAGIC.len() + 32 + 4;
+    
+    pub fn encode(&self) -> [u8; Self::ENCODED_LEN] {
+        let mut data = [0u8; Self::ENCODED_LEN];
+        data[..4].copy_from_slice(&OVERFLOW_MAGIC);
+        data[4..36].copy_from_slice(&self.scratch);
+        data[36..].copy_from_slice(&self.len.to_le_bytes());
+        data
+    }
+    
+    /// Pointer in `return_data`, or `None` for an ordinary result
+    pub fn decode(return_data: &[u8]) -> Option<Self> {
+        if return_data.len() != Self::ENCODED_LEN || return_data[..4] != OVERFLOW_MAGIC {
+            return None;
+        }
+        Self::try_from_slice(&return_data[4..]).ok()
+    }
+    
+    pub fn chunk_count(&self) -> usize {
+        (self.len as usize).div_ceil(MAX_RETURN_DATA)
+    }
+}
+
+/// Payload stored in a scratch account's data, if the header fits
+pub fn scratch_payload(scratch_data: &[u8]) -> Option<&[u8]> {
+    let header = scratch_data.get(..SCRATCH_HEADER_LEN)?;
+    let len = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    scratch_data.get(SCRATCH_HEADER_LEN..SCRATCH_HEADER_LEN.checked_add(len)?)
+}
+
+/// `index`-th `MAX_RETURN_DATA`-sized chunk of `payload`
+pub fn chunk(payload: &[u8], index: u32) -> Option<&[u8]> {
+    let start = (index as usize).checked_mul(MAX_RETURN_DATA)?;
+    if start >= payload.len() {
+        return None;
+    }
+    Some(&payload[start..payload.len().min(start + MAX_RETURN_DATA)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+    
+    #[test]
+    fn test_pointer_and_chunks() {
+        let pointer = OverflowPointer { scratch: [7; 32], len: 2500 };
+        assert_eq!(OverflowPointer::decode(&pointer.encode()), Some(pointer));
+        assert_eq!(OverflowPointer::decode(&pointer.encode()[..39]), None);
+        assert_eq!(OverflowPointer::decode(&[0u8; OverflowPointer::ENCODED_LEN]), None);
+        assert_eq!(pointer.chunk_count(), 3);
+        
+        let mut scratch = 2500u32.to_le_bytes().to_vec();
+        scratch.extend((0..2500u32).map(|i| i as u8));
+        scratch.resize(3000, 0);
+        let payload = scratch_payload(&scratch).unwrap();
+        assert_eq!(payload.len(), 2500);
+        
+        let chunks: Vec<&[u8]> = (0..).map_while(|i| chunk(payload, i)).collect();
+        assert_eq!(chunks.iter().map(|c| c.len()).collect::<Vec<_>>(), [1024, 1024, 452]);
+        assert_eq!(chunks.concat(), payload);
+        assert_eq!(scratch_payload(&scratch[..100]), None);
+    }
+}
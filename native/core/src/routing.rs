@@ -0,0 +1,310 @@
+/*!
+ * Routing table reads
+ * Resolve a selector straight from diamond state account bytes
+ *
+ * The state starts with a fixed header: the owner, the layout version, the
+ * dispatch pause flag, the selector width and the selector index, a table of (selector,
+ * position) pairs sorted by selector. A lookup binary-searches the index and
+ * reads the one mapping it points at in the selector table that follows, so
+ * it never allocates or decodes the rest of the state. Aliases (stored right
+ * after the selector table) cost one extra scan. `dispatch_header` walks on
+ * to the other fields a dispatch checks (pause expiry, limits, payload
+ * specs, return overflow policy, maintenance windows) the same way.
+ */
+
+use alloc::vec::Vec;
+use borsh::{BorshDeserialize, BorshSerialize};
+use core::cmp::Ordering;
+
+use crate::payload::PayloadSpec;
+use crate::return_data::ReturnOverflowPolicy;
+use crate::selector::Selector;
+
+/// Encoded sizes of the state fields ahead of and inside the selector table
+pub use diamond_constants::layout::{
+    ADMIN_LEN, MODULE_META_LEN, OWNER_LEN, SELECTOR_ALIAS_LEN, SELECTOR_INDEX_ENTRY_LEN, SELECTOR_MAPPING_LEN,
+    STATE_INDEX_OFFSET, STATE_LAYOUT_VERSION, STATE_PAUSED_OFFSET, STATE_VERSION_OFFSET, STATE_WIDTH_OFFSET,
+};
+use diamond_constants::layout::{
+    ACTIVE_CALL_LEN, DISPATCH_LIMITS_LEN, FACET_DEPENDENCY_LEN, MAPPING_MODULE_OFFSET, MAPPING_SELECTOR_OFFSET,
+    ORACLE_GUARD_LEN, PAUSE_WINDOW_LEN, PUBKEY_LEN, SELECTOR_LEN, SELECTOR_TAG_LEN, TAG_LEN, TENANT_LEN,
+};
+
+/// One selector index entry
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IndexEntry {
+    pub selector: Selector,
+    /// Position of the selector's mapping in the selector table
+    pub position: u8,
+}
+
+/// Index of a selector table, given its selectors in table order
+pub fn build_index(selectors: impl IntoIterator<Item = Selector>) -> Vec<IndexEntry> {
+    let mut index: Vec<IndexEntry> = selectors
+        .into_iter()
+        .enumerate()
+        .map(|(position, selector)| IndexEntry { selector, position: position as u8 })
+        .collect();
+    index.sort_unstable_by_key(|entry| entry.selector.0);
+    index
+}
+
+/// Fixed-offset fields at the start of the state
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StateHeader {
+    pub owner: [u8; 32],
+    /// Dispatch pause flag (the pause may have lapsed; see `DispatchHeader`)
+    pub dispatch_paused: bool,
+    pub selector_width: u8,
+}
+
+/// Read the fixed-offset header; `None` unless the state is in the current layout
+pub fn state_header(state_data: &[u8]) -> Option<StateHeader> {
+    if read_u8(state_data, STATE_VERSION_OFFSET)? != STATE_LAYOUT_VERSION {
+        return None;
+    }
+    let mut owner = [0u8; 32];
+    owner.copy_from_slice(state_data.get(..OWNER_LEN)?);
+    Some(StateHeader {
+        owner,
+        dispatch_paused: read_u8(state_data, STATE_PAUSED_OFFSET)? != 0,
+        selector_width: read_u8(state_data, STATE_WIDTH_OFFSET)?,
+    })
+}
+
+fn read_len(data: &[u8], offset: usize) -> Option<usize> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+}
+
+/// Byte range of the selector table entries
+pub(crate) fn selector_table(data: &[u8]) -> Option<(usize, usize)> {
+    let index_count = read_len(data, STATE_INDEX_OFFSET)?;
+    let offset = (STATE_INDEX_OFFSET + 4).checked_add(index_count.checked_mul(SELECTOR_INDEX_ENTRY_LEN)?)?;
+    let count = read_len(data, offset)?;
+    Some((offset + 4, count))
+}
+
+/// Selector table position of `selector`, by binary search of the index
+fn index_position(state_data: &[u8], selector: &Selector) -> Option<usize> {
+    let count = read_len(state_data, STATE_INDEX_OFFSET)?;
+    let entry = |i: usize| {
+        let start = STATE_INDEX_OFFSET + 4 + i * SELECTOR_INDEX_ENTRY_LEN;
+        state_data.get(start..start + SELECTOR_INDEX_ENTRY_LEN)
+    };
+    let (mut low, mut high) = (0, count);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        let entry = entry(mid)?;
+        match entry[..SELECTOR_LEN].cmp(&selector.0) {
+            Ordering::Less => low = mid + 1,
+            Ordering::Greater => high = mid,
+            Ordering::Equal => return Some(entry[SELECTOR_LEN] as usize),
+        }
+    }
+    None
+}
+
+/// Canonical selector an alias routes to
+fn resolve_alias(state_data: &[u8], start: usize, count: usize, alias: &Selector) -> Option<Selector> {
+    let offset = start.checked_add(count.checked_mul(SELECTOR_MAPPING_LEN)?)?;
+    let alias_count = read_len(state_data, offset)?;
+    (0..alias_count)
+        .map(|i| offset + 4 + i * SELECTOR_ALIAS_LEN)
+        .map_while(|entry| state_data.get(entry..entry + SELECTOR_ALIAS_LEN))
+        .find(|entry| entry[..8] == alias.0)
+        .map(|entry| {
+            let mut canonical = [0u8; 8];
+            canonical.copy_from_slice(&entry[8..]);
+            Selector(canonical)
+        })
+}
+
+/// Program serving `selector` (directly or through an alias), or `None` if
+/// it is unregistered or the data is not a diamond state
+pub fn resolve_selector(state_data: &[u8], selector: &Selector) -> Option<[u8; 32]> {
+    let mapping = resolve_mapping(state_data, selector)?;
+    let mut module = [0u8; 32];
+    module.copy_from_slice(&mapping[MAPPING_MODULE_OFFSET..MAPPING_MODULE_OFFSET + 32]);
+    Some(module)
+}
+
+/// Raw encoded mapping serving `selector`, following an alias one hop
+pub fn resolve_mapping<'a>(state_data: &'a [u8], selector: &Selector) -> Option<&'a [u8]> {
+    find_mapping(state_data, selector).or_else(|| {
+        let (start, count) = selector_table(state_data)?;
+        find_mapping(state_data, &resolve_alias(state_data, start, count, selector)?)
+    })
+}
+
+/// Raw encoded mapping for `selector` (canonical selectors only)
+pub fn find_mapping<'a>(state_data: &'a [u8], selector: &Selector) -> Option<&'a [u8]> {
+    let position = index_position(state_data, selector)?;
+    let (start, count) = selector_table(state_data)?;
+    if position >= count {
+        return None;
+    }
+    let entry = start + position * SELECTOR_MAPPING_LEN;
+    state_data
+        .get(entry..entry + SELECTOR_MAPPING_LEN)
+        .filter(|mapping| mapping[MAPPING_SELECTOR_OFFSET..MAPPING_MODULE_OFFSET] == selector.0)
+}
+
+/// State fields a facet dispatch checks, read in place
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DispatchHeader {
+    pub dispatch_paused: bool,
+    pub pause_expiry_slot: Option<u64>,
+    pub selector_width: u8,
+    /// A re-entrant call currently holds the lock
+    pub call_active: bool,
+    pub max_accounts: u16,
+    pub max_payload_len: u32,
+    pub max_depth: u8,
+    pub return_overflow: ReturnOverflowPolicy,
+    /// Offset and count of the encoded payload specs
+    payload_specs: (usize, usize),
+    /// Offset and count of the encoded maintenance windows
+    pause_windows: (usize, usize),
+}
+
+impl DispatchHeader {
+    /// Whether facet dispatch is paused at `slot`, honoring any pause expiry
+    pub fn is_dispatch_paused_at(&self, slot: u64) -> bool {
+        match self.pause_expiry_slot {
+            Some(expiry) => self.dispatch_paused && slot < expiry,
+            None => self.dispatch_paused,
+        }
+    }
+    
+    /// Whether a maintenance window covers `slot`
+    pub fn in_pause_window(&self, state_data: &[u8], slot: u64) -> bool {
+        let (start, count) = self.pause_windows;
+        (0..count)
+            .map(|i| start + i * PAUSE_WINDOW_LEN)
+            .any(|entry| match (read_u64(state_data, entry), read_u64(state_data, entry + 8)) {
+                (Some(start_slot), Some(end_slot)) => (start_slot..end_slot).contains(&slot),
+                // A truncated window fails closed
+                _ => true,
+            })
+    }
+    
+    /// Declared payload spec of `selector`
+    pub fn payload_spec(&self, state_data: &[u8], selector: &Selector) -> Option<PayloadSpec> {
+        let (start, count) = self.payload_specs;
+        (0..count)
+            .map(|i| start + i * PayloadSpec::ENCODED_LEN)
+            .map_while(|entry| state_data.get(entry..entry + PayloadSpec::ENCODED_LEN))
+            .find(|entry| entry[..8] == selector.0)
+            .and_then(|entry| PayloadSpec::try_from_slice(entry).ok())
+    }
+}
+
+fn read_u8(data: &[u8], offset: usize) -> Option<u8> {
+    data.get(offset).copied()
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    let bytes = data.get(offset..offset + 8)?;
+    let mut le = [0u8; 8];
+    le.copy_from_slice(bytes);
+    Some(u64::from_le_bytes(le))
+}
+
+/// Offset past a Borsh `Option` whose `Some` payload is `len` bytes
+fn skip_option(data: &[u8], offset: usize, len: usize) -> Option<usize> {
+    match read_u8(data, offset)? {
+        0 => Some(offset + 1),
+        1 => Some(offset + 1 + len),
+        _ => None,
+    }
+}
+
+/// Offset of the first field after the routing tables (selectors, aliases,
+/// admins and modules), which dispatch never decodes
+pub fn tables_end(state_data: &[u8]) -> Option<usize> {
+    let (start, count) = selector_table(state_data)?;
+    let mut offset = start.checked_add(count.checked_mul(SELECTOR_MAPPING_LEN)?)?;
+    offset += 4 + read_len(state_data, offset)?.checked_mul(SELECTOR_ALIAS_LEN)?;
+    offset += 4 + read_len(state_data, offset)?.checked_mul(ADMIN_LEN)?;
+    offset += 4 + read_len(state_data, offset)?.checked_mul(MODULE_META_LEN)?;
+    Some(offset)
+}
+
+/// Read the dispatch-relevant fields without decoding the state
+pub fn dispatch_header(state_data: &[u8]) -> Option<DispatchHeader> {
+    let StateHeader { dispatch_paused, selector_width, .. } = state_header(state_data)?;
+    let mut offset = tables_end(state_data)?;
+    
+    offset += 1 + 1 + 32; // bump, cut_paused, pause_authority
+    let pause_expiry_slot = match read_u8(state_data, offset)? {
+        0 => None,
+        _ => Some(read_u64(state_data, offset + 1)?),
+    };
+    offset = skip_option(state_data, offset, 8)?;
+    offset += 8; // next_proposal_id
+    offset = skip_option(state_data, offset, 32)?; // governance
+    offset += 1; // governance_only
+    offset += 4 + read_len(state_data, offset)?.checked_mul(TENANT_LEN)?;
+    offset = skip_option(state_data, offset, 32)?; // pending_owner
+    let call_active = read_u8(state_data, offset)? != 0;
+    offset = skip_option(state_data, offset, ACTIVE_CALL_LEN)?;
+    offset = skip_option(state_data, offset, 32)?; // audit_tree
+    let limits = state_data.get(offset..offset + DISPATCH_LIMITS_LEN)?;
+    let max_accounts = u16::from_le_bytes([limits[0], limits[1]]);
+    let max_payload_len = u32::from_le_bytes([limits[2], limits[3], limits[4], limits[5]]);
+    let max_depth = limits[6];
+    offset += DISPATCH_LIMITS_LEN;
+    offset += 1 + 8 + 8; // routing_digest, authority_delay_slots, owner_effective_slot
+    
+    // pending_change: Option<(AuthorityChange, effective_slot)>
+    if read_u8(state_data, offset)? == 1 {
+        offset += 1;
+        offset = match read_u8(state_data, offset)? {
+            0 => offset + 1 + 32,                         // Owner(Pubkey)
+            1 => skip_option(state_data, offset + 1, 32)?, // Governance(Option<Pubkey>)
+            2 => offset + 1 + 8,                          // Delay(u64)
+            3 => offset + 1 + 32,                         // PauseAuthority(Pubkey)
+            _ => return None,
+        };
+        offset += 8;
+    } else {
+        offset += 1;
+    }
+    let spec_count = read_len(state_data, offset)?;
+    let specs_start = offset + 4;
+    offset = specs_start + spec_count.checked_mul(PayloadSpec::ENCODED_LEN)?;
+    offset += 8 + 8; // config_version, relay_reimbursement
+    let return_overflow = ReturnOverflowPolicy::try_from_slice(state_data.get(offset..offset + 1)?).ok()?;
+    offset += 1;
+    offset = skip_option(state_data, offset, 8)?; // vanity_nonce
+    offset += 1; // has_council
+    offset += 4 + read_len(state_data, offset)?.checked_mul(PUBKEY_LEN)?; // tx_guard denylist
+    offset += 4 + read_len(state_data, offset)?.checked_mul(SELECTOR_LEN)?; // tx_guard selectors
+    offset += 4 + read_len(state_data, offset)?.checked_mul(FACET_DEPENDENCY_LEN)?;
+    // authorizer: Option<(program, Vec<Selector>)>
+    if read_u8(state_data, offset)? == 1 {
+        offset += 1 + PUBKEY_LEN;
+        offset += 4 + read_len(state_data, offset)?.checked_mul(SELECTOR_LEN)?;
+    } else {
+        offset += 1;
+    }
+    offset += 4 + read_len(state_data, offset)?.checked_mul(SELECTOR_TAG_LEN)?; // tags
+    offset += 4 + read_len(state_data, offset)?.checked_mul(TAG_LEN)?; // paused_tags
+    offset += 4 + read_len(state_data, offset)?.checked_mul(ORACLE_GUARD_LEN)?;
+    offset += 1; // unpause_quorum
+    let window_count = read_len(state_data, offset)?;
+    
+    Some(DispatchHeader {
+        dispatch_paused,
+        pause_expiry_slot,
+        selector_width,
+        call_active,
+        max_accounts,
+        max_payload_len,
+        max_depth,
+        return_overflow,
+        payload_specs: (specs_start, spec_count),
+        pause_windows: (offset + 4, window_count),
+    })
+}
@@ -0,0 +1,158 @@
+/*!
+ * Selectors
+ * Per-diamond selector width (2, 4 or 8 bytes)
+ *
+ * A diamond picks its width at initialize. Selectors are stored left-aligned
+ * in a fixed 8-byte key with zero padding, so one state layout serves every
+ * width and Anchor's 8-byte discriminators fit unchanged. Built-ins keep
+ * their 4-byte selectors on every diamond.
+ */
+
+use alloc::{format, vec::Vec};
+use borsh::{BorshDeserialize, BorshSerialize};
+use sha2::{Digest, Sha256};
+
+pub use diamond_constants::selectors::{DEFAULT_SELECTOR_WIDTH, SELECTOR_WIDTHS};
+
+pub fn is_valid_width(width: u8) -> bool {
+    SELECTOR_WIDTHS.contains(&width)
+}
+
+/// Routed selector, left-aligned and zero padded to 8 bytes
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Selector(pub [u8; 8]);
+
+impl Selector {
+    /// Left-align up to 8 bytes
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() > 8 {
+            return None;
+        }
+        let mut key = [0u8; 8];
+        key[..bytes.len()].copy_from_slice(bytes);
+        Some(Self(key))
+    }
+    
+    /// Leading `width` bytes of a facet payload
+    pub fn read(ix_data: &[u8], width: u8) -> Option<Self> {
+        ix_data.get(..width as usize).and_then(Self::from_bytes)
+    }
+    
+    /// `sha256("<namespace>:<function_name>")[..width]`, which for width 8
+    /// and namespace "global" is Anchor's instruction discriminator
+    pub fn sighash(namespace: &str, function_name: &str, width: u8) -> Self {
+        let digest = Sha256::digest(format!("{}:{}", namespace, function_name).as_bytes());
+        Self::read(&digest, width.min(8)).unwrap_or_default()
+    }
+    
+    /// Whether the selector is representable at `width` (padding is zero)
+    pub fn fits(&self, width: u8) -> bool {
+        self.0.iter().skip(width as usize).all(|b| *b == 0)
+    }
+    
+    /// Wire form at `width`
+    pub fn as_bytes(&self, width: u8) -> &[u8] {
+        &self.0[..(width as usize).min(8)]
+    }
+    
+    /// Leading 4 bytes (the Anchor router's fixed width)
+    pub fn prefix4(&self) -> [u8; 4] {
+        [self.0[0], self.0[1], self.0[2], self.0[3]]
+    }
+}
+
+impl From<[u8; 2]> for Selector {
+    fn from(bytes: [u8; 2]) -> Self {
+        Self([bytes[0], bytes[1], 0, 0, 0, 0, 0, 0])
+    }
+}
+
+impl From<[u8; 4]> for Selector {
+    fn from(bytes: [u8; 4]) -> Self {
+        Self([bytes[0], bytes[1], bytes[2], bytes[3], 0, 0, 0, 0])
+    }
+}
+
+impl From<[u8; 8]> for Selector {
+    fn from(bytes: [u8; 8]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl<const N: usize> PartialEq<[u8; N]> for Selector {
+    fn eq(&self, other: &[u8; N]) -> bool {
+        Self::from_bytes(other).as_ref() == Some(self)
+    }
+}
+
+/// Selector derived for `namespace:function_signature` at rehash `salt`
+///
+/// Allocation rule: salt 0 hashes `"<namespace>:<signature>"`, salt n > 0
+/// hashes `"<namespace>:<signature>#<n>"`, and the first `width` bytes of
+/// the sha256 digest are the selector. An empty namespace hashes as
+/// "global", so an unsalted 8-byte allocation is the Anchor discriminator.
+pub fn allocated(namespace: &str, function_signature: &str, width: u8, salt: u8) -> Selector {
+    let namespace = if namespace.is_empty() { "global" } else { namespace };
+    match salt {
+        0 => Selector::sighash(namespace, function_signature, width),
+        n => Selector::sighash(namespace, &format!("{}#{}", function_signature, n), width),
+    }
+}
+
+/// First free selector under the allocation rule, with the salt that produced it
+///
+/// Salts are tried in order from 0, so anyone replaying allocations against
+/// the same table gets the same result. `None` once every salt is taken.
+pub fn allocate(
+    namespace: &str,
+    function_signature: &str,
+    width: u8,
+    taken: impl Fn(&Selector) -> bool,
+) -> Option<(Selector, u8)> {
+    (0..=u8::MAX)
+        .map(|salt| (allocated(namespace, function_signature, width, salt), salt))
+        .find(|(selector, _)| !taken(selector))
+}
+
+/// Facet payload for a routed call: selector at the diamond's width, then args
+pub fn encode_call(selector: impl Into<Selector>, width: u8, args: &[u8]) -> Vec<u8> {
+    let selector = selector.into();
+    let mut data = Vec::with_capacity(width as usize + args.len());
+    data.extend_from_slice(selector.as_bytes(width));
+    data.extend_from_slice(args);
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    
+    #[test]
+    fn test_widths_round_trip() {
+        let data = encode_call([0xAB, 0xCD], 2, &[9, 9]);
+        assert_eq!(data, vec![0xAB, 0xCD, 9, 9]);
+        assert_eq!(Selector::read(&data, 2), Some(Selector::from([0xAB, 0xCD])));
+        assert_eq!(Selector::read(&data, 8), None);
+        
+        let anchor = Selector::sighash("global", "increment", 8);
+        assert!(anchor.fits(8) && !anchor.fits(4));
+        assert_eq!(Selector::read(&encode_call(anchor, 8, &[]), 8), Some(anchor));
+        
+        assert!(Selector::from([1, 2, 3, 4]).fits(4));
+        assert!(!Selector::from([1, 2, 3, 4]).fits(2));
+        assert_eq!(Selector::from([1, 2, 3, 4]), [1, 2, 3, 4]);
+    }
+    
+    #[test]
+    fn test_allocate_rehashes_on_collision() {
+        assert_eq!(allocated("", "increment", 8, 0), Selector::sighash("global", "increment", 8));
+        
+        let first = allocated("counter", "increment(u64)", 2, 0);
+        let second = allocated("counter", "increment(u64)", 2, 1);
+        assert_ne!(first, second);
+        assert_eq!(allocate("counter", "increment(u64)", 2, |_| false), Some((first, 0)));
+        assert_eq!(allocate("counter", "increment(u64)", 2, |s| *s == first), Some((second, 1)));
+        assert_eq!(allocate("counter", "increment(u64)", 2, |_| true), None);
+    }
+}
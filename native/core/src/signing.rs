@@ -0,0 +1,154 @@
+/*!
+ * Signing payloads
+ * The one message format users sign off-chain for the diamond
+ *
+ * Anything the router or a facet accepts on the strength of an off-chain
+ * signature (relayed calls, session keys, guardian approvals) has the user
+ * sign a `SigningPayload`. The signed bytes are Sign-In-With-Solana style
+ * text, which wallet-standard `signMessage` shows the user as is:
+ *
+ * ```text
+ * app.example.com wants you to approve a diamond action with your Solana account:
+ * <signer>
+ *
+ * Action: relay
+ * Diamond: <diamond>
+ * Payload: <sha256 of the action's data, hex>
+ * Nonce: 3
+ * Expiration Slot: 1000
+ * ```
+ *
+ * Keys are base58. The verifier rebuilds the text from the structured
+ * payload and compares it with the bytes the Ed25519 program checked, so
+ * nothing on chain parses text.
+ */
+
+use alloc::{format, string::String, vec::Vec};
+use borsh::{BorshDeserialize, BorshSerialize};
+use sha2::{Digest, Sha256};
+
+/// Longest domain a payload may name
+pub const MAX_DOMAIN_LEN: usize = 64;
+
+/// Actions the diamond verifies signatures for
+pub mod action {
+    /// `dispatch_relayed`; the data is the Borsh `(facet, ix_data, accounts_hash, max_reimbursement)`
+    pub const RELAY: &str = "relay";
+    /// `open_session`; the data is the Borsh `(session_key, facet)`
+    pub const SESSION: &str = "session";
+    /// `diamond::veto_signed`; the data is the Borsh `(target, change, effective_slot)`
+    pub const GUARDIAN_VETO: &str = "guardian-veto";
+}
+
+/// One off-chain approval
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SigningPayload {
+    /// Origin of the requesting application (wallets check it against the page)
+    pub domain: String,
+    pub signer: [u8; 32],
+    pub diamond: [u8; 32],
+    /// What is approved (see `action`)
+    pub action: String,
+    /// Replay protection, scoped by the verifying feature
+    pub nonce: u64,
+    /// Last slot the approval may be used at
+    pub expiry_slot: u64,
+    /// `data_hash` of the action's data
+    pub data_hash: [u8; 32],
+}
+
+impl SigningPayload {
+    /// Whether the domain and action can be signed unambiguously (one line
+    /// each, domain within `MAX_DOMAIN_LEN`)
+    pub fn is_well_formed(&self) -> bool {
+        let single_line = |s: &str| !s.is_empty() && !s.contains(['\n', '\r']);
+        single_line(&self.domain) && self.domain.len() <= MAX_DOMAIN_LEN && single_line(&self.action)
+    }
+    
+    /// Bytes the signer signs
+    pub fn message(&self) -> Vec<u8> {
+        format!(
+            "{} wants you to approve a diamond action with your Solana account:\n{}\n\n\
+             Action: {}\nDiamond: {}\nPayload: {}\nNonce: {}\nExpiration Slot: {}",
+            self.domain,
+            base58(&self.signer),
+            self.action,
+            base58(&self.diamond),
+            hex(&self.data_hash),
+            self.nonce,
+            self.expiry_slot,
+        )
+        .into_bytes()
+    }
+    
+    /// Whether the approval is for `diamond` and `action` and still usable at `slot`
+    pub fn applies(&self, diamond: &[u8; 32], action: &str, slot: u64) -> bool {
+        &self.diamond == diamond && self.action == action && slot <= self.expiry_slot
+    }
+}
+
+/// Hash binding an action's data into a payload
+pub fn data_hash(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn base58(bytes: &[u8; 32]) -> String {
+    const ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+    // Little-endian base-58 digits
+    let mut digits: Vec<u8> = Vec::with_capacity(44);
+    for &byte in bytes {
+        let mut carry = byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    let zeros = bytes.iter().take_while(|&&b| b == 0).count();
+    core::iter::repeat_n(b'1', zeros)
+        .chain(digits.iter().rev().map(|&d| ALPHABET[d as usize]))
+        .map(char::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_message_text() {
+        let payload = SigningPayload {
+            domain: "app.example.com".into(),
+            signer: [0; 32],
+            diamond: [0xD1; 32],
+            action: action::RELAY.into(),
+            nonce: 3,
+            expiry_slot: 1000,
+            data_hash: [0xab; 32],
+        };
+        let message = String::from_utf8(payload.message()).unwrap();
+        let expected = format!(
+            "app.example.com wants you to approve a diamond action with your Solana account:\n\
+             11111111111111111111111111111111\n\n\
+             Action: relay\nDiamond: F83muwL8bL5M9vH5ASB2oxJS2By4mHVNnHJ9BSND9dQk\nPayload: {}\nNonce: 3\nExpiration Slot: 1000",
+            "ab".repeat(32)
+        );
+        assert_eq!(message, expected);
+        assert!(payload.is_well_formed());
+        assert!(payload.applies(&[0xD1; 32], action::RELAY, 1000));
+        assert!(!payload.applies(&[0xD1; 32], action::RELAY, 1001));
+        assert!(!payload.applies(&[0xD1; 32], "session", 0));
+        
+        // A second line in the domain could spoof the signer line
+        let spoofed = SigningPayload { domain: "a.com wants you\nevil".into(), ..payload };
+        assert!(!spoofed.is_well_formed());
+    }
+}
@@ -0,0 +1,358 @@
+/*!
+ * Example Native Facet - Escrow
+ *
+ * Lamport escrow whose vault is a router PDA: only the diamond router can
+ * sign for it, and only through `dispatch_signed` to this facet. Shows:
+ * - Facet-owned state PDAs created through `dispatch`
+ * - Funds held by a PDA the router signs for (`dispatch_signed`)
+ * - Multi-account release and cancel flows through the diamond
+ *
+ * The router signs for the vault on any `dispatch_signed` call that reaches
+ * this facet, so every path that spends it checks a signature against the
+ * stored escrow: release takes the depositor or the arbiter, cancel only the
+ * depositor.
+ */
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    sysvar::Sysvar,
+};
+
+// Program ID (placeholder - replace with actual deployed program ID)
+solana_program::declare_id!("EscrowFacet111111111111111111111111111111111");
+
+// Function selectors
+pub const CREATE_ESCROW_SELECTOR: [u8; 4] = [0xE5, 0xC0, 0x00, 0x01];
+pub const FUND_SELECTOR: [u8; 4] = [0xE5, 0xC0, 0x00, 0x02];
+pub const RELEASE_SELECTOR: [u8; 4] = [0xE5, 0xC0, 0x00, 0x03];
+pub const CANCEL_SELECTOR: [u8; 4] = [0xE5, 0xC0, 0x00, 0x04];
+
+/// Liveness probe (the router's reserved `HEALTH_SELECTOR`)
+pub use diamond_constants::selectors::HEALTH_SELECTOR;
+
+/// Seed of escrow state PDAs: [ESCROW_SEED, diamond_state, depositor, id]
+pub const ESCROW_SEED: &[u8] = b"escrow";
+
+/// The router's `FACET_SIGNER_SEED`, shared through the constants crate (the
+/// router itself can't be linked into a program with its own entrypoint)
+pub use diamond_constants::seeds::FACET_SIGNER_SEED;
+
+/// Escrow state
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct Escrow {
+    pub diamond_state: Pubkey,
+    pub router: Pubkey,
+    pub depositor: Pubkey,
+    pub beneficiary: Pubkey,
+    /// May release in the depositor's place
+    pub arbiter: Option<Pubkey>,
+    pub amount: u64,
+    pub funded: bool,
+    pub bump: u8,
+}
+
+impl Escrow {
+    pub const SPACE: usize = 32 + 32 + 32 + 32 + (1 + 32) + 8 + 1 + 1;
+    
+    /// Whether `key` may release the escrow to the beneficiary
+    pub fn may_release(&self, key: &Pubkey) -> bool {
+        key == &self.depositor || self.arbiter.as_ref() == Some(key)
+    }
+}
+
+pub fn escrow_address(diamond_state: &Pubkey, depositor: &Pubkey, escrow_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[ESCROW_SEED, diamond_state.as_ref(), depositor.as_ref(), &escrow_id.to_le_bytes()],
+        &id(),
+    )
+}
+
+/// Vault of an escrow: the router's facet signer PDA seeded by the escrow key
+pub fn vault_address(router: &Pubkey, diamond_state: &Pubkey, escrow: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[FACET_SIGNER_SEED, diamond_state.as_ref(), id().as_ref(), escrow.as_ref()],
+        router,
+    )
+}
+
+// Program entrypoint
+#[cfg(not(feature = "no-entrypoint"))]
+solana_program::entrypoint!(process_instruction);
+
+/// Main instruction processor
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    msg!("Escrow Facet: Processing instruction");
+    
+    if instruction_data.len() < 4 {
+        msg!("Error: Instruction data too short");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let (selector, data) = instruction_data.split_at(4);
+    
+    match [selector[0], selector[1], selector[2], selector[3]] {
+        CREATE_ESCROW_SELECTOR => {
+            msg!("Function: CreateEscrow");
+            create_escrow(program_id, accounts, data)
+        }
+        FUND_SELECTOR => {
+            msg!("Function: Fund");
+            fund(program_id, accounts)
+        }
+        RELEASE_SELECTOR => {
+            msg!("Function: Release");
+            release(program_id, accounts)
+        }
+        CANCEL_SELECTOR => {
+            msg!("Function: Cancel");
+            cancel(program_id, accounts)
+        }
+        HEALTH_SELECTOR => {
+            msg!("Function: Health");
+            Ok(())
+        }
+        _ => {
+            msg!("Error: Unknown selector: {:?}", selector);
+            Err(ProgramError::InvalidInstructionData)
+        }
+    }
+}
+
+fn load(program_id: &Pubkey, escrow_account: &AccountInfo) -> Result<Escrow, ProgramError> {
+    if escrow_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    Escrow::try_from_slice(&escrow_account.try_borrow_data()?).map_err(|_| ProgramError::InvalidAccountData)
+}
+
+/// The depositor is the stored one and the vault is the one derived for this escrow
+fn check_parties(escrow: &Escrow, escrow_key: &Pubkey, depositor: &AccountInfo, vault: &AccountInfo) -> ProgramResult {
+    if depositor.key != &escrow.depositor {
+        msg!("Error: Invalid depositor");
+        return Err(ProgramError::IllegalOwner);
+    }
+    if vault.key != &vault_address(&escrow.router, &escrow.diamond_state, escrow_key).0 {
+        msg!("Error: Vault does not belong to this escrow");
+        return Err(ProgramError::InvalidSeeds);
+    }
+    Ok(())
+}
+
+/// `check_parties`, with the depositor's signature
+fn check_depositor(escrow: &Escrow, escrow_key: &Pubkey, depositor: &AccountInfo, vault: &AccountInfo) -> ProgramResult {
+    if !depositor.is_signer {
+        msg!("Error: Depositor must sign");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    check_parties(escrow, escrow_key, depositor, vault)
+}
+
+/// Return an escrow account's rent to `destination` and wipe it
+fn close(account: &AccountInfo, destination: &AccountInfo) -> ProgramResult {
+    let lamports = account.lamports();
+    **account.try_borrow_mut_lamports()? = 0;
+    **destination.try_borrow_mut_lamports()? = destination
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    account.try_borrow_mut_data()?.fill(0);
+    Ok(())
+}
+
+/// Create an escrow (via `dispatch`)
+///
+/// Accounts: [escrow (w), depositor (s, w), diamond_state, system_program]
+/// Data: id (u64), beneficiary (Pubkey), arbiter (Option<Pubkey>), amount (u64)
+fn create_escrow(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let escrow_account = next_account_info(account_iter)?;
+    let depositor = next_account_info(account_iter)?;
+    let diamond_state = next_account_info(account_iter)?;
+    let system_program_account = next_account_info(account_iter)?;
+    
+    if !depositor.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    let (escrow_id, beneficiary, arbiter, amount) = <(u64, Pubkey, Option<Pubkey>, u64)>::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    // An empty vault must still be rent exempt once funded
+    let rent = Rent::get()?;
+    if amount < rent.minimum_balance(0) {
+        msg!("Error: Amount must cover the vault's rent exemption ({})", rent.minimum_balance(0));
+        return Err(ProgramError::InsufficientFunds);
+    }
+    let (expected, bump) = escrow_address(diamond_state.key, depositor.key, escrow_id);
+    if escrow_account.key != &expected {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    
+    invoke_signed(
+        &system_instruction::create_account(
+            depositor.key,
+            escrow_account.key,
+            rent.minimum_balance(Escrow::SPACE),
+            Escrow::SPACE as u64,
+            program_id,
+        ),
+        &[depositor.clone(), escrow_account.clone(), system_program_account.clone()],
+        &[&[ESCROW_SEED, diamond_state.key.as_ref(), depositor.key.as_ref(), &escrow_id.to_le_bytes(), &[bump]]],
+    )?;
+    
+    let escrow = Escrow {
+        diamond_state: *diamond_state.key,
+        // The router owns its diamond state, so the vault follows whichever router serves it
+        router: *diamond_state.owner,
+        depositor: *depositor.key,
+        beneficiary,
+        arbiter,
+        amount,
+        funded: false,
+        bump,
+    };
+    escrow.serialize(&mut &mut escrow_account.try_borrow_mut_data()?[..])?;
+    
+    msg!("Escrow {} created: {} lamports for {}", escrow_account.key, amount, beneficiary);
+    Ok(())
+}
+
+/// Move the escrowed amount into the vault (via `dispatch`)
+///
+/// Accounts: [escrow (w), depositor (s, w), vault (w), system_program]
+fn fund(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let escrow_account = next_account_info(account_iter)?;
+    let depositor = next_account_info(account_iter)?;
+    let vault = next_account_info(account_iter)?;
+    let system_program_account = next_account_info(account_iter)?;
+    
+    let mut escrow = load(program_id, escrow_account)?;
+    check_depositor(&escrow, escrow_account.key, depositor, vault)?;
+    if escrow.funded {
+        msg!("Error: Escrow already funded");
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    
+    invoke(
+        &system_instruction::transfer(depositor.key, vault.key, escrow.amount),
+        &[depositor.clone(), vault.clone(), system_program_account.clone()],
+    )?;
+    escrow.funded = true;
+    escrow.serialize(&mut &mut escrow_account.try_borrow_mut_data()?[..])?;
+    
+    msg!("Escrow funded: {} lamports in {}", escrow.amount, vault.key);
+    Ok(())
+}
+
+/// Pay the beneficiary from the vault and close (via `dispatch_signed`, seed = escrow key)
+///
+/// Accounts: [escrow (w), depositor (w), releaser (s), vault (router-signed, w), beneficiary (w), system_program]
+///
+/// The releaser is the depositor or the escrow's arbiter; the escrow's rent
+/// goes back to the depositor either way.
+fn release(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let escrow_account = next_account_info(account_iter)?;
+    let depositor = next_account_info(account_iter)?;
+    let releaser = next_account_info(account_iter)?;
+    let vault = next_account_info(account_iter)?;
+    let beneficiary = next_account_info(account_iter)?;
+    let system_program_account = next_account_info(account_iter)?;
+    
+    let escrow = load(program_id, escrow_account)?;
+    check_parties(&escrow, escrow_account.key, depositor, vault)?;
+    if !releaser.is_signer {
+        msg!("Error: Releaser must sign");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if !escrow.may_release(releaser.key) {
+        msg!("Error: Only the depositor or the arbiter can release");
+        return Err(ProgramError::IllegalOwner);
+    }
+    if !escrow.funded {
+        msg!("Error: Escrow not funded");
+        return Err(ProgramError::UninitializedAccount);
+    }
+    if beneficiary.key != &escrow.beneficiary {
+        msg!("Error: Invalid beneficiary");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    // The vault's signature comes from the router through `dispatch_signed`
+    invoke(
+        &system_instruction::transfer(vault.key, beneficiary.key, escrow.amount),
+        &[vault.clone(), beneficiary.clone(), system_program_account.clone()],
+    )?;
+    close(escrow_account, depositor)?;
+    
+    msg!("Escrow released: {} lamports to {}", escrow.amount, beneficiary.key);
+    Ok(())
+}
+
+/// Refund the depositor and close (via `dispatch_signed`, seed = escrow key)
+///
+/// Accounts: [escrow (w), depositor (s, w), vault (router-signed, w), system_program]
+fn cancel(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let escrow_account = next_account_info(account_iter)?;
+    let depositor = next_account_info(account_iter)?;
+    let vault = next_account_info(account_iter)?;
+    let system_program_account = next_account_info(account_iter)?;
+    
+    let escrow = load(program_id, escrow_account)?;
+    check_depositor(&escrow, escrow_account.key, depositor, vault)?;
+    
+    if escrow.funded {
+        invoke(
+            &system_instruction::transfer(vault.key, depositor.key, escrow.amount),
+            &[vault.clone(), depositor.clone(), system_program_account.clone()],
+        )?;
+    }
+    close(escrow_account, depositor)?;
+    
+    msg!("Escrow cancelled");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_escrow_space() {
+        let escrow = Escrow {
+            diamond_state: Pubkey::new_unique(),
+            router: Pubkey::new_unique(),
+            depositor: Pubkey::new_unique(),
+            beneficiary: Pubkey::new_unique(),
+            arbiter: Some(Pubkey::new_unique()),
+            amount: u64::MAX,
+            funded: true,
+            bump: 255,
+        };
+        assert_eq!(borsh::to_vec(&escrow).unwrap().len(), Escrow::SPACE);
+    }
+    
+    #[test]
+    fn test_vault_is_router_facet_signer() {
+        let router = diamond_router_native::id();
+        let diamond_state = Pubkey::new_unique();
+        let escrow = Pubkey::new_unique();
+        
+        assert_eq!(
+            vault_address(&router, &diamond_state, &escrow),
+            diamond_router_native::diamond_router::facet_signer(&router, &diamond_state, &id(), escrow.as_ref())
+        );
+    }
+}
@@ -0,0 +1,36 @@
+/*!
+ * Facet config
+ * Facet side of the per-diamond config blob the router keeps for a facet
+ *
+ * Once the owner attaches a config to the facet (`config::set_facet`), it
+ * lives in a router-owned account at `[FACET_CONFIG_SEED, diamond, facet]`.
+ * Callers pass that account like any other input; the call data is left
+ * alone. Facets written for a config should fail calls that arrive without
+ * the account.
+ */
+
+use solana_program::{account_info::AccountInfo, msg, program_error::ProgramError, pubkey::Pubkey};
+
+pub use diamond_core::facet_config::{FacetConfig, FACET_CONFIG_SEED, MAX_FACET_CONFIG_LEN};
+
+/// Config `diamond` (routed by `router`) gave `facet`, read from `account`
+///
+/// The account must be the router's config account for this diamond and
+/// facet; anything else is refused rather than read.
+pub fn read_config(router: &Pubkey, diamond: &Pubkey, facet: &Pubkey, account: &AccountInfo) -> Result<Vec<u8>, ProgramError> {
+    if account.owner != router {
+        msg!("Error: Facet config {} is not owned by the router", account.key);
+        return Err(ProgramError::IllegalOwner);
+    }
+    let config = FacetConfig::of_facet(&account.try_borrow_data()?, &diamond.to_bytes(), &facet.to_bytes())
+        .ok_or_else(|| {
+            msg!("Error: {} holds no config of this facet", account.key);
+            ProgramError::InvalidAccountData
+        })?;
+    let seeds: &[&[u8]] = &[FACET_CONFIG_SEED, diamond.as_ref(), facet.as_ref(), &[config.bump]];
+    if Pubkey::create_program_address(seeds, router).ok().as_ref() != Some(account.key) {
+        msg!("Error: {} is not the facet config address", account.key);
+        return Err(ProgramError::InvalidSeeds);
+    }
+    Ok(config.data)
+}
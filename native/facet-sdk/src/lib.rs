@@ -0,0 +1,11 @@
+/*!
+ * Diamond Facet SDK
+ *
+ * Helpers shared by native facets. A plain library: link it into a facet
+ * program, it has no entrypoint of its own.
+ */
+
+pub mod config;
+pub mod registration;
+pub mod return_data;
+pub mod token;
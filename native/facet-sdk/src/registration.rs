@@ -0,0 +1,99 @@
+/*!
+ * Self-registration
+ * Facet side of the router's `request_registration`
+ *
+ * A facet proposes routes for its own functions by CPI, signing with its
+ * registrar PDA for the diamond. The router files the manifest as a cut
+ * proposal from the facet; nothing routes until the owner approves it. The
+ * client passes the diamond's next proposal PDA (`next_proposal_id` of the
+ * diamond state), a payer for its rent and, if the diamond has an
+ * authorizer, the authorizer program, which is asked with the facet as actor.
+ * A manifest declaring return schemas also passes the facet's record under
+ * the router, where they're kept for clients to decode views with.
+ */
+
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    program::invoke_signed,
+    pubkey::Pubkey,
+    system_program,
+};
+
+pub use diamond_core::registration::{ManifestFunction, RegistrationManifest, FACET_RECORD_SEED, REGISTRAR_SEED};
+
+/// Registrar PDA of `program_id` for `diamond`
+pub fn registrar_address(program_id: &Pubkey, diamond: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[REGISTRAR_SEED, diamond.as_ref()], program_id)
+}
+
+/// Record the router keeps about `program_id` as a facet of `diamond`
+pub fn facet_record_address(router: &Pubkey, diamond: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[FACET_RECORD_SEED, diamond.as_ref(), program_id.as_ref()], router)
+}
+
+/// `request_registration` for the facet `program_id`
+pub fn request_registration_ix(
+    router: &Pubkey,
+    diamond: &Pubkey,
+    proposal: &Pubkey,
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    authorizer: Option<&Pubkey>,
+    manifest: &RegistrationManifest,
+) -> Instruction {
+    let (registrar, _) = registrar_address(program_id, diamond);
+    let mut accounts = vec![
+        AccountMeta::new(*diamond, false),
+        AccountMeta::new(*proposal, false),
+        AccountMeta::new_readonly(*program_id, false),
+        AccountMeta::new_readonly(registrar, true),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    if manifest.return_schemas().next().is_some() {
+        accounts.push(AccountMeta::new(facet_record_address(router, diamond, program_id).0, false));
+    }
+    accounts.extend(authorizer.map(|a| AccountMeta::new_readonly(*a, false)));
+    Instruction { program_id: *router, accounts, data: manifest.instruction_data() }
+}
+
+/// Ask `router` to route `manifest` to this facet, pending the owner's approval
+#[allow(clippy::too_many_arguments)]
+pub fn request_registration<'a>(
+    program_id: &Pubkey,
+    router: &AccountInfo<'a>,
+    diamond: &AccountInfo<'a>,
+    proposal: &AccountInfo<'a>,
+    facet_program: &AccountInfo<'a>,
+    registrar: &AccountInfo<'a>,
+    payer: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    facet_record: Option<&AccountInfo<'a>>,
+    authorizer: Option<&AccountInfo<'a>>,
+    manifest: &RegistrationManifest,
+) -> ProgramResult {
+    let (_, bump) = registrar_address(program_id, diamond.key);
+    let ix = request_registration_ix(
+        router.key,
+        diamond.key,
+        proposal.key,
+        program_id,
+        payer.key,
+        authorizer.map(|a| a.key),
+        manifest,
+    );
+    let mut infos = vec![
+        diamond.clone(),
+        proposal.clone(),
+        facet_program.clone(),
+        registrar.clone(),
+        payer.clone(),
+        system_program.clone(),
+        router.clone(),
+    ];
+    infos.extend(facet_record.cloned());
+    infos.extend(authorizer.cloned());
+    invoke_signed(&ix, &infos, &[&[REGISTRAR_SEED, diamond.key.as_ref(), &[bump]]])
+}
@@ -0,0 +1,62 @@
+/*!
+ * Large results
+ * Facet side of the chunked return data convention
+ *
+ * `set_return` sets small results directly. Larger ones are written to the
+ * facet's scratch PDA for the calling diamond and replaced by an
+ * `OverflowPointer`, which the router checks and serves back through
+ * `return::read_chunk`. The scratch account must be passed to the facet,
+ * owned by it and allocated big enough for the largest result.
+ */
+
+use diamond_core::return_data::{OverflowPointer, SCRATCH_HEADER_LEN};
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    msg,
+    program::{set_return_data, MAX_RETURN_DATA},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+pub use diamond_core::return_data::RETURN_SCRATCH_SEED;
+
+/// Scratch PDA of `program_id` for results returned to `diamond`
+pub fn scratch_address(program_id: &Pubkey, diamond: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[RETURN_SCRATCH_SEED, diamond.as_ref()], program_id)
+}
+
+/// Account size holding results of up to `max_len` bytes
+pub const fn scratch_space(max_len: usize) -> usize {
+    SCRATCH_HEADER_LEN + max_len
+}
+
+/// Set `result` as return data, spilling to `scratch` if it is too large
+pub fn set_return(
+    program_id: &Pubkey,
+    diamond: &Pubkey,
+    scratch: &AccountInfo,
+    result: &[u8],
+) -> ProgramResult {
+    if result.len() <= MAX_RETURN_DATA {
+        set_return_data(result);
+        return Ok(());
+    }
+    
+    let (expected, _) = scratch_address(program_id, diamond);
+    if scratch.key != &expected || scratch.owner != program_id || !scratch.is_writable {
+        msg!("Error: Invalid return scratch. Expected: {}", expected);
+        return Err(ProgramError::InvalidSeeds);
+    }
+    let len = u32::try_from(result.len()).map_err(|_| ProgramError::InvalidArgument)?;
+    let mut data = scratch.try_borrow_mut_data()?;
+    if data.len() < scratch_space(result.len()) {
+        msg!("Error: Return scratch holds {} bytes, result is {}", data.len(), result.len());
+        return Err(ProgramError::AccountDataTooSmall);
+    }
+    data[..SCRATCH_HEADER_LEN].copy_from_slice(&len.to_le_bytes());
+    data[SCRATCH_HEADER_LEN..SCRATCH_HEADER_LEN + result.len()].copy_from_slice(result);
+    
+    set_return_data(&OverflowPointer { scratch: expected.to_bytes(), len }.encode());
+    Ok(())
+}
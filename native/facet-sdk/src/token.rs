@@ -0,0 +1,423 @@
+/*!
+ * SPL Token and Token-2022 helpers
+ *
+ * Hand-encoded so facets don't pull in spl-token. Everything here accepts
+ * accounts from either token program; for Token-2022 mints it reads the
+ * extensions that change how a transfer must be built:
+ * - TransferFeeConfig: transfers go out as `TransferCheckedWithFee` with the
+ *   fee for the current epoch, and the recipient is credited the net amount
+ * - TransferHook: the hook program, its `extra-account-metas` account and the
+ *   extra accounts it lists must ride along on the transfer
+ * - NonTransferable: transfers are refused up front
+ *
+ * Hook extra accounts are resolved by the client (spl-transfer-hook-interface
+ * offline helpers) and passed to the facet after its own accounts, in the
+ * order the token program expects: the extras, then the hook program, then
+ * the validation account.
+ */
+
+use solana_program::{
+    account_info::AccountInfo,
+    clock::Clock,
+    instruction::{AccountMeta, Instruction},
+    msg,
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    sysvar::Sysvar,
+};
+
+pub const SPL_TOKEN_ID: Pubkey = solana_program::pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+pub const TOKEN_2022_ID: Pubkey = solana_program::pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+
+/// Seed of a transfer hook's validation account: [EXTRA_ACCOUNT_METAS_SEED, mint]
+pub const EXTRA_ACCOUNT_METAS_SEED: &[u8] = b"extra-account-metas";
+
+/// `TokenInstruction::TransferChecked` tag
+const TRANSFER_CHECKED: u8 = 12;
+/// `TokenInstruction::TransferFeeExtension` tag and its `TransferCheckedWithFee` sub-tag
+const TRANSFER_FEE_EXTENSION: u8 = 26;
+const TRANSFER_CHECKED_WITH_FEE: u8 = 1;
+
+/// Base lengths; Token-2022 accounts with extensions are longer
+const MINT_LEN: usize = 82;
+const TOKEN_ACCOUNT_LEN: usize = 165;
+
+/// Offsets into the base mint (after the mint authority option and supply)
+const MINT_DECIMALS_OFFSET: usize = 44;
+const MINT_INITIALIZED_OFFSET: usize = 45;
+
+/// Token-2022 extension TLVs start after the account-type byte that follows
+/// the (padded) base account
+const EXTENSIONS_OFFSET: usize = TOKEN_ACCOUNT_LEN + 1;
+
+/// Token-2022 `ExtensionType` values this module reads
+const EXT_TRANSFER_FEE_CONFIG: u16 = 1;
+const EXT_NON_TRANSFERABLE: u16 = 9;
+const EXT_TRANSFER_HOOK: u16 = 14;
+
+const MAX_FEE_BASIS_POINTS: u128 = 10_000;
+
+/// The fields of a token account facets read
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenAccount {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+}
+
+/// One epoch's transfer fee schedule
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransferFee {
+    pub epoch: u64,
+    pub maximum_fee: u64,
+    pub basis_points: u16,
+}
+
+impl TransferFee {
+    /// Fee withheld from a transfer of `amount` (rounded up, capped at `maximum_fee`)
+    pub fn fee(&self, amount: u64) -> u64 {
+        if self.basis_points == 0 || amount == 0 {
+            return 0;
+        }
+        let fee = (amount as u128 * self.basis_points as u128).div_ceil(MAX_FEE_BASIS_POINTS);
+        u64::try_from(fee).unwrap_or(u64::MAX).min(self.maximum_fee)
+    }
+}
+
+/// A Token-2022 `TransferFeeConfig`: the schedule in force switches from
+/// `older` to `newer` at `newer.epoch`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransferFeeConfig {
+    pub older: TransferFee,
+    pub newer: TransferFee,
+}
+
+impl TransferFeeConfig {
+    pub fn epoch_fee(&self, epoch: u64) -> &TransferFee {
+        if epoch >= self.newer.epoch {
+            &self.newer
+        } else {
+            &self.older
+        }
+    }
+}
+
+/// The parts of a mint that decide how to transfer it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mint {
+    pub decimals: u8,
+    pub transfer_fee: Option<TransferFeeConfig>,
+    /// Transfer hook program, if one is set
+    pub transfer_hook: Option<Pubkey>,
+    pub non_transferable: bool,
+}
+
+impl Mint {
+    /// Fee on a transfer of `amount` in `epoch`
+    pub fn fee(&self, amount: u64, epoch: u64) -> u64 {
+        self.transfer_fee.map(|config| config.epoch_fee(epoch).fee(amount)).unwrap_or(0)
+    }
+}
+
+pub fn is_token_program(program: &Pubkey) -> bool {
+    program == &SPL_TOKEN_ID || program == &TOKEN_2022_ID
+}
+
+pub fn check_token_program(program: &AccountInfo) -> Result<(), ProgramError> {
+    if !is_token_program(program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    Ok(())
+}
+
+fn check_token_program_owner(account: &AccountInfo) -> Result<(), ProgramError> {
+    if !is_token_program(account.owner) {
+        return Err(ProgramError::IllegalOwner);
+    }
+    Ok(())
+}
+
+fn pubkey_at(data: &[u8], offset: usize) -> Pubkey {
+    Pubkey::new_from_array(data[offset..offset + 32].try_into().unwrap())
+}
+
+fn u64_at(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+/// Token-2022 extension TLVs of an account or mint: (type, value)
+fn extensions(data: &[u8]) -> impl Iterator<Item = (u16, &[u8])> {
+    let mut offset = EXTENSIONS_OFFSET;
+    std::iter::from_fn(move || {
+        let header = data.get(offset..offset + 4)?;
+        let kind = u16::from_le_bytes([header[0], header[1]]);
+        let len = u16::from_le_bytes([header[2], header[3]]) as usize;
+        // Type 0 is zeroed padding after the last extension
+        if kind == 0 {
+            return None;
+        }
+        let value = data.get(offset + 4..offset + 4 + len)?;
+        offset += 4 + len;
+        Some((kind, value))
+    })
+}
+
+fn parse_transfer_fee(value: &[u8], offset: usize) -> TransferFee {
+    TransferFee {
+        epoch: u64_at(value, offset),
+        maximum_fee: u64_at(value, offset + 8),
+        basis_points: u16::from_le_bytes([value[offset + 16], value[offset + 17]]),
+    }
+}
+
+/// Parse a mint owned by `owner` (extensions are only read for Token-2022)
+pub fn parse_mint(owner: &Pubkey, data: &[u8]) -> Result<Mint, ProgramError> {
+    if data.len() < MINT_LEN || data[MINT_INITIALIZED_OFFSET] != 1 {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let mut mint = Mint {
+        decimals: data[MINT_DECIMALS_OFFSET],
+        transfer_fee: None,
+        transfer_hook: None,
+        non_transferable: false,
+    };
+    if owner != &TOKEN_2022_ID {
+        return Ok(mint);
+    }
+    for (kind, value) in extensions(data) {
+        match kind {
+            // Two authorities and the withheld amount precede the schedules
+            EXT_TRANSFER_FEE_CONFIG if value.len() >= 108 => {
+                mint.transfer_fee = Some(TransferFeeConfig {
+                    older: parse_transfer_fee(value, 72),
+                    newer: parse_transfer_fee(value, 90),
+                });
+            }
+            // Authority, then the hook program (all zeros when unset)
+            EXT_TRANSFER_HOOK if value.len() >= 64 => {
+                let program = pubkey_at(value, 32);
+                mint.transfer_hook = (program != Pubkey::default()).then_some(program);
+            }
+            EXT_NON_TRANSFERABLE => mint.non_transferable = true,
+            _ => {}
+        }
+    }
+    Ok(mint)
+}
+
+/// Parse the base fields of a token account
+pub fn parse_account(data: &[u8]) -> Result<TokenAccount, ProgramError> {
+    if data.len() < TOKEN_ACCOUNT_LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(TokenAccount { mint: pubkey_at(data, 0), owner: pubkey_at(data, 32), amount: u64_at(data, 64) })
+}
+
+pub fn unpack_mint(mint: &AccountInfo) -> Result<Mint, ProgramError> {
+    check_token_program_owner(mint)?;
+    parse_mint(mint.owner, &mint.try_borrow_data()?)
+}
+
+pub fn unpack_account(account: &AccountInfo) -> Result<TokenAccount, ProgramError> {
+    check_token_program_owner(account)?;
+    parse_account(&account.try_borrow_data()?)
+}
+
+/// Validation account a transfer hook program keeps for `mint`
+pub fn extra_account_metas_address(mint: &Pubkey, hook_program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[EXTRA_ACCOUNT_METAS_SEED, mint.as_ref()], hook_program).0
+}
+
+fn transfer_metas(source: &Pubkey, mint: &Pubkey, destination: &Pubkey, authority: &Pubkey) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new(*source, false),
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new(*destination, false),
+        AccountMeta::new_readonly(*authority, true),
+    ]
+}
+
+/// `TransferChecked` of `amount` from `source` to `destination`
+pub fn transfer_checked_ix(
+    token_program: &Pubkey,
+    source: &Pubkey,
+    mint: &Pubkey,
+    destination: &Pubkey,
+    authority: &Pubkey,
+    amount: u64,
+    decimals: u8,
+) -> Instruction {
+    let mut data = Vec::with_capacity(10);
+    data.push(TRANSFER_CHECKED);
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.push(decimals);
+    Instruction { program_id: *token_program, accounts: transfer_metas(source, mint, destination, authority), data }
+}
+
+/// Token-2022 `TransferCheckedWithFee`; fails unless `fee` matches the mint's current fee
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_checked_with_fee_ix(
+    token_program: &Pubkey,
+    source: &Pubkey,
+    mint: &Pubkey,
+    destination: &Pubkey,
+    authority: &Pubkey,
+    amount: u64,
+    decimals: u8,
+    fee: u64,
+) -> Instruction {
+    let mut data = Vec::with_capacity(19);
+    data.extend_from_slice(&[TRANSFER_FEE_EXTENSION, TRANSFER_CHECKED_WITH_FEE]);
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.push(decimals);
+    data.extend_from_slice(&fee.to_le_bytes());
+    Instruction { program_id: *token_program, accounts: transfer_metas(source, mint, destination, authority), data }
+}
+
+/// Transfer `amount` with `authority` signing itself or through `signer_seeds`
+///
+/// `hook_accounts` are forwarded as-is when the mint has a transfer hook
+/// (and ignored otherwise). Returns the amount `destination` is credited,
+/// which is less than `amount` on mints with a transfer fee.
+#[allow(clippy::too_many_arguments)]
+pub fn transfer<'a>(
+    token_program: &AccountInfo<'a>,
+    source: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
+    destination: &AccountInfo<'a>,
+    authority: &AccountInfo<'a>,
+    amount: u64,
+    hook_accounts: &[AccountInfo<'a>],
+    signer_seeds: &[&[&[u8]]],
+) -> Result<u64, ProgramError> {
+    check_token_program(token_program)?;
+    if mint.owner != token_program.key {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let mint_state = unpack_mint(mint)?;
+    if mint_state.non_transferable {
+        msg!("Error: Mint {} is non-transferable", mint.key);
+        return Err(ProgramError::InvalidAccountData);
+    }
+    
+    let (mut ix, fee) = match mint_state.transfer_fee {
+        Some(_) => {
+            let fee = mint_state.fee(amount, Clock::get()?.epoch);
+            let ix = transfer_checked_with_fee_ix(
+                token_program.key,
+                source.key,
+                mint.key,
+                destination.key,
+                authority.key,
+                amount,
+                mint_state.decimals,
+                fee,
+            );
+            (ix, fee)
+        }
+        None => {
+            let ix = transfer_checked_ix(
+                token_program.key,
+                source.key,
+                mint.key,
+                destination.key,
+                authority.key,
+                amount,
+                mint_state.decimals,
+            );
+            (ix, 0)
+        }
+    };
+    
+    let mut infos = vec![source.clone(), mint.clone(), destination.clone(), authority.clone()];
+    if let Some(hook_program) = mint_state.transfer_hook {
+        let validation = extra_account_metas_address(mint.key, &hook_program);
+        let provided = |key: &Pubkey| hook_accounts.iter().any(|account| account.key == key);
+        if !provided(&hook_program) || !provided(&validation) {
+            msg!("Error: Mint {} has transfer hook {}; pass its accounts", mint.key, hook_program);
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+        for account in hook_accounts {
+            ix.accounts.push(AccountMeta {
+                pubkey: *account.key,
+                is_signer: account.is_signer,
+                is_writable: account.is_writable,
+            });
+            infos.push(account.clone());
+        }
+    }
+    infos.push(token_program.clone());
+    
+    invoke_signed(&ix, &infos, signer_seeds)?;
+    Ok(amount - fee)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// A Token-2022 mint with a fee config and a transfer hook
+    fn mint_with_extensions(hook: &Pubkey) -> Vec<u8> {
+        let mut data = vec![0u8; EXTENSIONS_OFFSET];
+        data[MINT_DECIMALS_OFFSET] = 6;
+        data[MINT_INITIALIZED_OFFSET] = 1;
+        data[TOKEN_ACCOUNT_LEN] = 1; // AccountType::Mint
+        
+        let mut fee_config = vec![0u8; 72];
+        for (epoch, maximum_fee, basis_points) in [(0u64, 5_000u64, 50u16), (10, 1_000, 100)] {
+            fee_config.extend_from_slice(&epoch.to_le_bytes());
+            fee_config.extend_from_slice(&maximum_fee.to_le_bytes());
+            fee_config.extend_from_slice(&basis_points.to_le_bytes());
+        }
+        let mut hook_config = vec![0u8; 32];
+        hook_config.extend_from_slice(hook.as_ref());
+        
+        for (kind, value) in [(EXT_TRANSFER_FEE_CONFIG, fee_config), (EXT_TRANSFER_HOOK, hook_config)] {
+            data.extend_from_slice(&kind.to_le_bytes());
+            data.extend_from_slice(&(value.len() as u16).to_le_bytes());
+            data.extend_from_slice(&value);
+        }
+        data.extend_from_slice(&[0u8; 8]);
+        data
+    }
+    
+    #[test]
+    fn test_parse_token_2022_mint() {
+        let hook = Pubkey::new_unique();
+        let data = mint_with_extensions(&hook);
+        
+        let mint = parse_mint(&TOKEN_2022_ID, &data).unwrap();
+        assert_eq!(mint.decimals, 6);
+        assert_eq!(mint.transfer_hook, Some(hook));
+        assert!(!mint.non_transferable);
+        
+        // 0.5% before epoch 10 (capped at 5000), 1% from epoch 10 (capped at 1000)
+        assert_eq!(mint.fee(1_001, 9), 6);
+        assert_eq!(mint.fee(10_000_000, 9), 5_000);
+        assert_eq!(mint.fee(50_000, 10), 500);
+        assert_eq!(mint.fee(10_000_000, 10), 1_000);
+        assert_eq!(mint.fee(0, 10), 0);
+        
+        // SPL Token mints carry no extensions, whatever trails the base layout
+        let legacy = parse_mint(&SPL_TOKEN_ID, &data).unwrap();
+        assert_eq!(legacy.transfer_fee, None);
+        assert_eq!(legacy.transfer_hook, None);
+        
+        assert!(parse_mint(&SPL_TOKEN_ID, &data[..MINT_LEN - 1]).is_err());
+    }
+    
+    #[test]
+    fn test_transfer_ix_encoding() {
+        let keys: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+        let ix = transfer_checked_with_fee_ix(&TOKEN_2022_ID, &keys[0], &keys[1], &keys[2], &keys[3], 500, 6, 5);
+        assert_eq!(ix.data[..2], [26, 1]);
+        assert_eq!(u64::from_le_bytes(ix.data[2..10].try_into().unwrap()), 500);
+        assert_eq!(ix.data[10], 6);
+        assert_eq!(u64::from_le_bytes(ix.data[11..19].try_into().unwrap()), 5);
+        assert!(ix.accounts[3].is_signer && ix.accounts[2].is_writable);
+        
+        let ix = transfer_checked_ix(&SPL_TOKEN_ID, &keys[0], &keys[1], &keys[2], &keys[3], 500, 6);
+        assert_eq!(ix.data, [&[12u8][..], &500u64.to_le_bytes(), &[6]].concat());
+    }
+}
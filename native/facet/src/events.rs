@@ -0,0 +1,47 @@
+/*!
+ * Counter Events
+ * Structured events emitted via `sol_log_data`, following the router's pattern
+ *
+ * Every mutation logs the old and new value, who made it and through which
+ * selector, so an indexer never has to diff account snapshots. Authority
+ * changes are logged the same way.
+ */
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{log::sol_log_data, pubkey::Pubkey};
+
+/// Prefix for every event payload so indexers can filter facet logs
+pub const EVENT_PREFIX: &[u8; 8] = b"counter:";
+
+/// Events emitted by the counter facet (variant order is part of the wire format)
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub enum CounterEvent {
+    /// Value changed by increment, decrement or reset
+    Changed {
+        counter: Pubkey,
+        old: u64,
+        new: u64,
+        actor: Pubkey,
+        selector: [u8; 4],
+    },
+    /// Counter handed to a new authority
+    AuthorityChanged {
+        counter: Pubkey,
+        old: Pubkey,
+        new: Pubkey,
+    },
+    /// Co-authorities replaced by the authority
+    CoAuthoritiesChanged {
+        counter: Pubkey,
+        co_authorities: Vec<Pubkey>,
+    },
+}
+
+impl CounterEvent {
+    /// Emit the event as `Program data: <prefix> <borsh(event)>`
+    pub fn emit(&self) {
+        if let Ok(encoded) = borsh::to_vec(self) {
+            sol_log_data(&[EVENT_PREFIX, &encoded]);
+        }
+    }
+}
@@ -0,0 +1,103 @@
+/*!
+ * Counter History
+ * Optional ring buffer of recent changes in a per-counter PDA
+ *
+ * Events are the primary record; the history account is for programs and
+ * clients that need recent changes on-chain without an indexer. Mutations
+ * append to it only when it is passed after the authority account.
+ */
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+/// Seed of the history PDA: [HISTORY_SEED, counter]
+pub const HISTORY_SEED: &[u8] = b"history";
+
+/// One recorded change
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct HistoryEntry {
+    pub slot: u64,
+    pub old: u64,
+    pub new: u64,
+    pub actor: Pubkey,
+    pub selector: [u8; 4],
+}
+
+/// Most recent changes to one counter, oldest first
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct CounterHistory {
+    pub counter: Pubkey,
+    pub bump: u8,
+    pub entries: Vec<HistoryEntry>,
+}
+
+impl CounterHistory {
+    pub const CAPACITY: usize = 16;
+    
+    pub const SPACE: usize =
+        32 + // counter
+        1 +  // bump
+        4 + (Self::CAPACITY * 60); // entries (8 slot + 8 old + 8 new + 32 actor + 4 selector)
+    
+    pub fn new(counter: Pubkey, bump: u8) -> Self {
+        Self { counter, bump, entries: Vec::new() }
+    }
+    
+    /// Append, dropping the oldest entry once full
+    pub fn push(&mut self, entry: HistoryEntry) {
+        if self.entries.len() >= Self::CAPACITY {
+            self.entries.remove(0);
+        }
+        self.entries.push(entry);
+    }
+}
+
+pub fn history_address(program_id: &Pubkey, counter: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[HISTORY_SEED, counter.as_ref()], program_id)
+}
+
+/// Append `entry` to the counter's history account
+pub fn record(
+    program_id: &Pubkey,
+    history_account: &AccountInfo,
+    counter: &Pubkey,
+    entry: HistoryEntry,
+) -> ProgramResult {
+    if history_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let mut data = history_account.try_borrow_mut_data()?;
+    // Allocated at SPACE, so trailing bytes are expected
+    let mut history = CounterHistory::deserialize(&mut &data[..])
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    if &history.counter != counter {
+        msg!("Error: History belongs to {}, not {}", history.counter, counter);
+        return Err(ProgramError::InvalidSeeds);
+    }
+    history.push(entry);
+    history
+        .serialize(&mut &mut data[..])
+        .map_err(|_| ProgramError::AccountDataTooSmall)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_history_ring_buffer() {
+        let mut history = CounterHistory::new(Pubkey::new_unique(), 255);
+        for i in 0..CounterHistory::CAPACITY as u64 + 3 {
+            history.push(HistoryEntry { slot: i, old: i, new: i + 1, actor: Pubkey::new_unique(), selector: [1, 2, 3, 4] });
+        }
+        assert_eq!(history.entries.len(), CounterHistory::CAPACITY);
+        assert_eq!(history.entries[0].slot, 3);
+        assert_eq!(borsh::to_vec(&history).unwrap().len(), CounterHistory::SPACE);
+    }
+}
@@ -0,0 +1,80 @@
+/*!
+ * Counter invariants checked after every instruction in audit builds
+ *
+ * With the `audit` feature the facet snapshots the counter (the first
+ * account, when it holds one) before each instruction and, once the
+ * instruction succeeds, checks that the counter still fits `Counter::SPACE`,
+ * has at most `MAX_CO_AUTHORITIES` co-authorities and kept its bump.
+ */
+
+use borsh::BorshDeserialize;
+use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, msg, program_error::ProgramError, pubkey::Pubkey};
+
+use crate::Counter;
+
+/// Broken counter invariant
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Violation {
+    /// Too many co-authorities, or the encoding outgrew `Counter::SPACE`
+    Oversized,
+    /// The bump changed
+    BumpChanged,
+}
+
+/// Check `after` against the counter invariants and, if given, against the
+/// counter `before` the instruction
+pub fn check(before: Option<&Counter>, after: &Counter) -> Result<(), Violation> {
+    let encoded = borsh::to_vec(after).map_err(|_| Violation::Oversized)?.len();
+    if after.co_authorities.len() > Counter::MAX_CO_AUTHORITIES || encoded > Counter::SPACE {
+        return Err(Violation::Oversized);
+    }
+    if before.is_some_and(|counter| counter.bump != after.bump) {
+        return Err(Violation::BumpChanged);
+    }
+    Ok(())
+}
+
+fn load(program_id: &Pubkey, account: &AccountInfo) -> Option<Counter> {
+    if account.owner != program_id || account.data_len() != Counter::SPACE {
+        return None;
+    }
+    Counter::deserialize(&mut &account.try_borrow_data().ok()?[..]).ok()
+}
+
+/// Run `instruction`, then check the invariants of the counter it was given
+/// (if any)
+pub fn checked(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction: impl FnOnce() -> ProgramResult,
+) -> ProgramResult {
+    let Some(counter_account) = accounts.first() else {
+        return instruction();
+    };
+    let before = load(program_id, counter_account);
+    instruction()?;
+    
+    let Some(after) = load(program_id, counter_account) else {
+        return Ok(());
+    };
+    check(before.as_ref(), &after).map_err(|violation| {
+        msg!("Error: Invariant violated: {:?}", violation);
+        ProgramError::InvalidAccountData
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_check() {
+        let counter = Counter::new(Pubkey::new_unique(), 254);
+        assert_eq!(check(None, &counter), Ok(()));
+        
+        let crowded = Counter { co_authorities: vec![Pubkey::new_unique(); 5], ..counter.clone() };
+        assert_eq!(check(Some(&counter), &crowded), Err(Violation::Oversized));
+        let rebumped = Counter { bump: 253, ..counter.clone() };
+        assert_eq!(check(Some(&counter), &rebumped), Err(Violation::BumpChanged));
+    }
+}
@@ -5,19 +5,32 @@
  * This is a complete, working example showing:
  * - Selector-based routing
  * - State management
+ * - Facet-level access control (authority transfer, co-authorities)
+ * - Structured events and an optional on-chain history
  * - Pure Rust implementation
  */
 
 use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
-    entrypoint,
+    clock::Clock,
     entrypoint::ProgramResult,
     msg,
+    program::invoke_signed,
     program_error::ProgramError,
     pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    sysvar::Sysvar,
 };
 
+pub mod events;
+pub mod history;
+pub mod invariants;
+
+use events::CounterEvent;
+use history::{history_address, CounterHistory, HistoryEntry, HISTORY_SEED};
+
 // Program ID (placeholder - replace with actual deployed program ID)
 solana_program::declare_id!("FacetNativeExamp1e1111111111111111111111111");
 
@@ -26,6 +39,12 @@ pub const INCREMENT_SELECTOR: [u8; 4] = [0x01, 0x02, 0x03, 0x04];
 pub const DECREMENT_SELECTOR: [u8; 4] = [0x05, 0x06, 0x07, 0x08];
 pub const GET_VALUE_SELECTOR: [u8; 4] = [0x09, 0x0A, 0x0B, 0x0C];
 pub const RESET_SELECTOR: [u8; 4] = [0x0D, 0x0E, 0x0F, 0x10];
+pub const INIT_HISTORY_SELECTOR: [u8; 4] = [0x11, 0x12, 0x13, 0x14];
+pub const SET_AUTHORITY_SELECTOR: [u8; 4] = [0x15, 0x16, 0x17, 0x18];
+pub const SET_CO_AUTHORITIES_SELECTOR: [u8; 4] = [0x19, 0x1A, 0x1B, 0x1C];
+
+/// Liveness probe (the router's reserved `HEALTH_SELECTOR`)
+pub use diamond_constants::selectors::HEALTH_SELECTOR;
 
 /// Counter state
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
@@ -33,26 +52,38 @@ pub struct Counter {
     pub value: u64,
     pub authority: Pubkey,
     pub bump: u8,
+    /// Keys that may change the value but not the authorities
+    pub co_authorities: Vec<Pubkey>,
 }
 
 impl Counter {
-    pub const SPACE: usize = 8 + 8 + 32 + 1; // discriminator + value + authority + bump
+    pub const MAX_CO_AUTHORITIES: usize = 4;
+    
+    // discriminator + value + authority + bump + co_authorities vec
+    pub const SPACE: usize = 8 + 8 + 32 + 1 + 4 + Self::MAX_CO_AUTHORITIES * 32;
     
     pub fn new(authority: Pubkey, bump: u8) -> Self {
         Self {
             value: 0,
             authority,
             bump,
+            co_authorities: Vec::new(),
         }
     }
+    
+    /// Whether `key` may increment, decrement or reset the counter
+    pub fn can_write(&self, key: &Pubkey) -> bool {
+        &self.authority == key || self.co_authorities.contains(key)
+    }
 }
 
 // Program entrypoint
-entrypoint!(process_instruction);
+#[cfg(not(feature = "no-entrypoint"))]
+solana_program::entrypoint!(process_instruction);
 
 /// Main instruction processor
 pub fn process_instruction(
-    _program_id: &Pubkey,
+    program_id: &Pubkey,
     accounts: &[AccountInfo],
     instruction_data: &[u8],
 ) -> ProgramResult {
@@ -71,15 +102,23 @@ pub fn process_instruction(
     
     let data = &instruction_data[4..];
     
-    // Route to handler
+    if cfg!(feature = "audit") {
+        invariants::checked(program_id, accounts, || route_function(program_id, accounts, selector, data))
+    } else {
+        route_function(program_id, accounts, selector, data)
+    }
+}
+
+/// Route to the handler of `selector`
+fn route_function(program_id: &Pubkey, accounts: &[AccountInfo], selector: [u8; 4], data: &[u8]) -> ProgramResult {
     match selector {
         INCREMENT_SELECTOR => {
             msg!("Function: Increment");
-            increment(accounts, data)
+            increment(program_id, accounts, data)
         }
         DECREMENT_SELECTOR => {
             msg!("Function: Decrement");
-            decrement(accounts, data)
+            decrement(program_id, accounts, data)
         }
         GET_VALUE_SELECTOR => {
             msg!("Function: GetValue");
@@ -87,7 +126,23 @@ pub fn process_instruction(
         }
         RESET_SELECTOR => {
             msg!("Function: Reset");
-            reset(accounts)
+            reset(program_id, accounts)
+        }
+        INIT_HISTORY_SELECTOR => {
+            msg!("Function: InitHistory");
+            init_history(program_id, accounts)
+        }
+        SET_AUTHORITY_SELECTOR => {
+            msg!("Function: SetAuthority");
+            set_authority(program_id, accounts, data)
+        }
+        SET_CO_AUTHORITIES_SELECTOR => {
+            msg!("Function: SetCoAuthorities");
+            set_co_authorities(program_id, accounts, data)
+        }
+        HEALTH_SELECTOR => {
+            msg!("Function: Health");
+            Ok(())
         }
         _ => {
             msg!("Error: Unknown selector: {:?}", selector);
@@ -96,8 +151,31 @@ pub fn process_instruction(
     }
 }
 
+/// Emit the change event and, if the history account follows, record it
+fn record_change(
+    program_id: &Pubkey,
+    account_iter: &mut std::slice::Iter<AccountInfo>,
+    counter: &Pubkey,
+    (old, new): (u64, u64),
+    actor: &Pubkey,
+    selector: [u8; 4],
+) -> ProgramResult {
+    CounterEvent::Changed { counter: *counter, old, new, actor: *actor, selector }.emit();
+    match account_iter.next() {
+        Some(history_account) => history::record(
+            program_id,
+            history_account,
+            counter,
+            HistoryEntry { slot: Clock::get()?.slot, old, new, actor: *actor, selector },
+        ),
+        None => Ok(()),
+    }
+}
+
 /// Increment the counter
-fn increment(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+///
+/// Accounts: [counter (w), authority (s), (history (w))]
+fn increment(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
     let account_iter = &mut accounts.iter();
     let counter_account = next_account_info(account_iter)?;
     let authority = next_account_info(account_iter)?;
@@ -110,11 +188,11 @@ fn increment(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
     
     // Load counter
     let mut counter_data = counter_account.try_borrow_mut_data()?;
-    let mut counter = Counter::try_from_slice(&counter_data)
+    let mut counter = Counter::deserialize(&mut &counter_data[..])
         .map_err(|_| ProgramError::InvalidAccountData)?;
     
-    // Verify authority
-    if counter.authority != *authority.key {
+    // Verify authority (or a co-authority)
+    if !counter.can_write(authority.key) {
         msg!("Error: Invalid authority");
         return Err(ProgramError::IllegalOwner);
     }
@@ -133,6 +211,7 @@ fn increment(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
     };
     
     // Increment
+    let old = counter.value;
     counter.value = counter
         .value
         .checked_add(amount)
@@ -142,11 +221,20 @@ fn increment(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
     counter.serialize(&mut &mut counter_data[..])?;
     
     msg!("Counter incremented by {} to {}", amount, counter.value);
-    Ok(())
+    record_change(
+        program_id,
+        account_iter,
+        counter_account.key,
+        (old, counter.value),
+        authority.key,
+        INCREMENT_SELECTOR,
+    )
 }
 
 /// Decrement the counter
-fn decrement(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+///
+/// Accounts: [counter (w), authority (s), (history (w))]
+fn decrement(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
     let account_iter = &mut accounts.iter();
     let counter_account = next_account_info(account_iter)?;
     let authority = next_account_info(account_iter)?;
@@ -159,11 +247,11 @@ fn decrement(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
     
     // Load counter
     let mut counter_data = counter_account.try_borrow_mut_data()?;
-    let mut counter = Counter::try_from_slice(&counter_data)
+    let mut counter = Counter::deserialize(&mut &counter_data[..])
         .map_err(|_| ProgramError::InvalidAccountData)?;
     
-    // Verify authority
-    if counter.authority != *authority.key {
+    // Verify authority (or a co-authority)
+    if !counter.can_write(authority.key) {
         msg!("Error: Invalid authority");
         return Err(ProgramError::IllegalOwner);
     }
@@ -182,6 +270,7 @@ fn decrement(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
     };
     
     // Decrement
+    let old = counter.value;
     counter.value = counter
         .value
         .checked_sub(amount)
@@ -191,7 +280,14 @@ fn decrement(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
     counter.serialize(&mut &mut counter_data[..])?;
     
     msg!("Counter decremented by {} to {}", amount, counter.value);
-    Ok(())
+    record_change(
+        program_id,
+        account_iter,
+        counter_account.key,
+        (old, counter.value),
+        authority.key,
+        DECREMENT_SELECTOR,
+    )
 }
 
 /// Get counter value (read-only)
@@ -201,7 +297,7 @@ fn get_value(accounts: &[AccountInfo]) -> ProgramResult {
     
     // Load counter
     let counter_data = counter_account.try_borrow_data()?;
-    let counter = Counter::try_from_slice(&counter_data)
+    let counter = Counter::deserialize(&mut &counter_data[..])
         .map_err(|_| ProgramError::InvalidAccountData)?;
     
     msg!("Counter value: {}", counter.value);
@@ -211,7 +307,9 @@ fn get_value(accounts: &[AccountInfo]) -> ProgramResult {
 }
 
 /// Reset counter to zero
-fn reset(accounts: &[AccountInfo]) -> ProgramResult {
+///
+/// Accounts: [counter (w), authority (s), (history (w))]
+fn reset(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
     let account_iter = &mut accounts.iter();
     let counter_account = next_account_info(account_iter)?;
     let authority = next_account_info(account_iter)?;
@@ -224,34 +322,155 @@ fn reset(accounts: &[AccountInfo]) -> ProgramResult {
     
     // Load counter
     let mut counter_data = counter_account.try_borrow_mut_data()?;
-    let mut counter = Counter::try_from_slice(&counter_data)
+    let mut counter = Counter::deserialize(&mut &counter_data[..])
         .map_err(|_| ProgramError::InvalidAccountData)?;
     
-    // Verify authority
-    if counter.authority != *authority.key {
+    // Verify authority (or a co-authority)
+    if !counter.can_write(authority.key) {
         msg!("Error: Invalid authority");
         return Err(ProgramError::IllegalOwner);
     }
     
     // Reset
+    let old = counter.value;
     counter.value = 0;
     
     // Save
     counter.serialize(&mut &mut counter_data[..])?;
     
     msg!("Counter reset to 0");
+    record_change(program_id, account_iter, counter_account.key, (old, 0), authority.key, RESET_SELECTOR)
+}
+
+/// Create the counter's history PDA
+///
+/// Accounts: [counter, history (w), payer (s, w), system_program]
+fn init_history(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let counter_account = next_account_info(account_iter)?;
+    let history_account = next_account_info(account_iter)?;
+    let payer = next_account_info(account_iter)?;
+    let system_program_account = next_account_info(account_iter)?;
+    
+    if !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if counter_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let (expected, bump) = history_address(program_id, counter_account.key);
+    if history_account.key != &expected {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            history_account.key,
+            Rent::get()?.minimum_balance(CounterHistory::SPACE),
+            CounterHistory::SPACE as u64,
+            program_id,
+        ),
+        &[payer.clone(), history_account.clone(), system_program_account.clone()],
+        &[&[HISTORY_SEED, counter_account.key.as_ref(), &[bump]]],
+    )?;
+    
+    let history = CounterHistory::new(*counter_account.key, bump);
+    history
+        .serialize(&mut &mut history_account.try_borrow_mut_data()?[..])
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    msg!("History initialized for {}", counter_account.key);
+    Ok(())
+}
+
+/// Load a counter for a change only its primary authority may make
+fn load_for_authority(
+    program_id: &Pubkey,
+    counter_account: &AccountInfo,
+    authority: &AccountInfo,
+) -> Result<Counter, ProgramError> {
+    if !authority.is_signer {
+        msg!("Error: Authority must sign");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if counter_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let counter = Counter::deserialize(&mut &counter_account.try_borrow_data()?[..])
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    if counter.authority != *authority.key {
+        msg!("Error: Only the authority can change authorities");
+        return Err(ProgramError::IllegalOwner);
+    }
+    Ok(counter)
+}
+
+/// Hand the counter to a new authority
+///
+/// Accounts: [counter (w), authority (s)]
+/// Data: new_authority (Pubkey)
+///
+/// Called through the diamond, the authority signs the dispatch and the
+/// router forwards the signature. Setting the authority to the diamond's
+/// facet signer PDA puts the counter under the diamond's own control, so
+/// later changes need a router-signed dispatch.
+fn set_authority(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let counter_account = next_account_info(account_iter)?;
+    let authority = next_account_info(account_iter)?;
+    
+    let new_authority = Pubkey::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    let mut counter = load_for_authority(program_id, counter_account, authority)?;
+    
+    let old = counter.authority;
+    counter.authority = new_authority;
+    // The new authority can't be its own co-authority
+    counter.co_authorities.retain(|key| key != &new_authority);
+    counter.serialize(&mut &mut counter_account.try_borrow_mut_data()?[..])?;
+    
+    msg!("Counter authority: {} -> {}", old, new_authority);
+    CounterEvent::AuthorityChanged { counter: *counter_account.key, old, new: new_authority }.emit();
+    Ok(())
+}
+
+/// Replace the co-authorities (empty clears them)
+///
+/// Accounts: [counter (w), authority (s)]
+/// Data: co_authorities (Vec<Pubkey>, at most `Counter::MAX_CO_AUTHORITIES`)
+fn set_co_authorities(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let counter_account = next_account_info(account_iter)?;
+    let authority = next_account_info(account_iter)?;
+    
+    let co_authorities = Vec::<Pubkey>::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    if co_authorities.len() > Counter::MAX_CO_AUTHORITIES {
+        msg!("Error: At most {} co-authorities", Counter::MAX_CO_AUTHORITIES);
+        return Err(ProgramError::InvalidArgument);
+    }
+    let mut counter = load_for_authority(program_id, counter_account, authority)?;
+    
+    counter.co_authorities = co_authorities.clone();
+    counter.serialize(&mut &mut counter_account.try_borrow_mut_data()?[..])?;
+    
+    msg!("Counter co-authorities: {}", co_authorities.len());
+    CounterEvent::CoAuthoritiesChanged { counter: *counter_account.key, co_authorities }.emit();
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-
+    
     #[test]
     fn test_selectors_unique() {
         assert_ne!(INCREMENT_SELECTOR, DECREMENT_SELECTOR);
         assert_ne!(INCREMENT_SELECTOR, GET_VALUE_SELECTOR);
         assert_ne!(DECREMENT_SELECTOR, GET_VALUE_SELECTOR);
+        assert_ne!(RESET_SELECTOR, INIT_HISTORY_SELECTOR);
+        assert_ne!(SET_AUTHORITY_SELECTOR, SET_CO_AUTHORITIES_SELECTOR);
     }
     
     #[test]
@@ -275,5 +494,34 @@ mod tests {
         let deserialized = Counter::try_from_slice(&buffer).unwrap();
         assert_eq!(deserialized.value, counter.value);
         assert_eq!(deserialized.authority, counter.authority);
+        
+        // Fully loaded counter fits its account
+        let mut full = counter;
+        full.co_authorities = vec![Pubkey::new_unique(); Counter::MAX_CO_AUTHORITIES];
+        assert!(borsh::to_vec(&full).unwrap().len() <= Counter::SPACE);
+    }
+    
+    #[test]
+    fn test_co_authorities_can_write() {
+        let (authority, co_authority) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut counter = Counter::new(authority, 255);
+        assert!(counter.can_write(&authority));
+        assert!(!counter.can_write(&co_authority));
+        
+        counter.co_authorities.push(co_authority);
+        assert!(counter.can_write(&co_authority));
+    }
+    
+    #[test]
+    fn test_change_event_round_trip() {
+        let event = CounterEvent::Changed {
+            counter: Pubkey::new_unique(),
+            old: 4,
+            new: 5,
+            actor: Pubkey::new_unique(),
+            selector: INCREMENT_SELECTOR,
+        };
+        let encoded = borsh::to_vec(&event).unwrap();
+        assert_eq!(CounterEvent::try_from_slice(&encoded).unwrap(), event);
     }
 }
@@ -0,0 +1,312 @@
+/*!
+ * Diamond Health Registry
+ *
+ * Shared kill flags and advisories for facet programs that many diamonds
+ * route to. The registry authority publishes one `FacetStatus` per facet
+ * program at `[FACET_STATUS_SEED, facet]`; routers that opt in with
+ * `health::set_registry` read it on every call to the facet and refuse the
+ * call once the facet is killed or flagged at their severity threshold.
+ *
+ * The registry program's upgrade authority names the registry authority
+ * once, with `Initialize`; the authority can then hand itself over (to a
+ * multisig, say) with `SetAuthority`. Publishing again overwrites a facet's
+ * status, so lifting a kill is a publish with `killed: false`.
+ */
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    bpf_loader_upgradeable,
+    clock::Clock,
+    entrypoint,
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    system_program,
+    sysvar::Sysvar,
+};
+
+pub use diamond_core::facet_status::{severity, FacetStatus, ADVISORY_LEN, FACET_STATUS_SEED};
+
+// Program ID (placeholder - replace with actual deployed program ID)
+solana_program::declare_id!("Hea1thRegistry111111111111111111111111111111");
+
+/// Seed of the registry config PDA: [REGISTRY_SEED]
+pub const REGISTRY_SEED: &[u8] = b"registry";
+
+/// `UpgradeableLoaderState::ProgramData` tag, then slot and the authority option
+const PROGRAM_DATA_TAG: [u8; 4] = [3, 0, 0, 0];
+const PROGRAM_DATA_AUTHORITY_OFFSET: usize = 12;
+
+/// Registry config
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct Registry {
+    /// Key allowed to publish facet statuses
+    pub authority: Pubkey,
+    pub bump: u8,
+}
+
+impl Registry {
+    pub const SPACE: usize = 32 + 1;
+}
+
+/// Registry instructions (Borsh, variant index first)
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub enum RegistryInstruction {
+    /// Name the registry authority (the program's upgrade authority, once)
+    ///
+    /// Accounts: [registry (w), program_data, upgrade_authority (s), payer (s, w), system_program]
+    Initialize { authority: Pubkey },
+    /// Hand the registry to a new authority
+    ///
+    /// Accounts: [registry (w), authority (s)]
+    SetAuthority { authority: Pubkey },
+    /// Create or overwrite a facet's status
+    ///
+    /// Accounts: [registry, status (w), authority (s), payer (s, w), system_program]
+    Publish { facet: Pubkey, killed: bool, severity: u8, advisory: String },
+}
+
+pub fn registry_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[REGISTRY_SEED], &id())
+}
+
+/// Status account of `facet` (the router derives the same address)
+pub fn status_address(facet: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[FACET_STATUS_SEED, facet.as_ref()], &id())
+}
+
+/// Upgrade authority recorded in a program data account's data
+pub fn upgrade_authority(programdata: &[u8]) -> Option<Pubkey> {
+    if programdata.get(..4)? != PROGRAM_DATA_TAG || *programdata.get(PROGRAM_DATA_AUTHORITY_OFFSET)? != 1 {
+        return None;
+    }
+    let start = PROGRAM_DATA_AUTHORITY_OFFSET + 1;
+    Pubkey::try_from(programdata.get(start..start + 32)?).ok()
+}
+
+// Program entrypoint
+entrypoint!(process_instruction);
+
+/// Main instruction processor
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let instruction = RegistryInstruction::try_from_slice(instruction_data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    match instruction {
+        RegistryInstruction::Initialize { authority } => {
+            msg!("Instruction: Initialize");
+            initialize(program_id, accounts, authority)
+        }
+        RegistryInstruction::SetAuthority { authority } => {
+            msg!("Instruction: SetAuthority");
+            set_authority(program_id, accounts, authority)
+        }
+        RegistryInstruction::Publish { facet, killed, severity, advisory } => {
+            msg!("Instruction: Publish");
+            publish(program_id, accounts, facet, killed, severity, &advisory)
+        }
+    }
+}
+
+/// Create `account` at a PDA of this program, even if someone already sent
+/// it lamports (which would fail a plain `create_account`)
+fn create_pda<'a>(
+    program_id: &Pubkey,
+    account: &AccountInfo<'a>,
+    payer: &AccountInfo<'a>,
+    system_program_account: &AccountInfo<'a>,
+    space: usize,
+    seeds: &[&[u8]],
+) -> ProgramResult {
+    let required = Rent::get()?.minimum_balance(space);
+    let shortfall = required.saturating_sub(account.lamports());
+    if shortfall > 0 {
+        invoke(
+            &system_instruction::transfer(payer.key, account.key, shortfall),
+            &[payer.clone(), account.clone(), system_program_account.clone()],
+        )?;
+    }
+    invoke_signed(
+        &system_instruction::allocate(account.key, space as u64),
+        &[account.clone(), system_program_account.clone()],
+        &[seeds],
+    )?;
+    invoke_signed(
+        &system_instruction::assign(account.key, program_id),
+        &[account.clone(), system_program_account.clone()],
+        &[seeds],
+    )
+}
+
+fn load_registry(program_id: &Pubkey, registry_account: &AccountInfo) -> Result<Registry, ProgramError> {
+    if registry_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    Registry::try_from_slice(&registry_account.try_borrow_data()?).map_err(|_| ProgramError::InvalidAccountData)
+}
+
+fn check_authority(registry: &Registry, authority: &AccountInfo) -> ProgramResult {
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if authority.key != &registry.authority {
+        msg!("Error: {} is not the registry authority", authority.key);
+        return Err(ProgramError::IllegalOwner);
+    }
+    Ok(())
+}
+
+fn initialize(program_id: &Pubkey, accounts: &[AccountInfo], authority: Pubkey) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let registry_account = next_account_info(account_iter)?;
+    let program_data = next_account_info(account_iter)?;
+    let upgrade_authority_account = next_account_info(account_iter)?;
+    let payer = next_account_info(account_iter)?;
+    let system_program_account = next_account_info(account_iter)?;
+    
+    if !upgrade_authority_account.is_signer || !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if system_program_account.key != &system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let (expected_data, _) = Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id());
+    if program_data.key != &expected_data || program_data.owner != &bpf_loader_upgradeable::id() {
+        msg!("Error: Invalid program data account. Expected: {}", expected_data);
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if upgrade_authority(&program_data.try_borrow_data()?) != Some(*upgrade_authority_account.key) {
+        msg!("Error: Only the registry's upgrade authority can initialize it");
+        return Err(ProgramError::IllegalOwner);
+    }
+    
+    let (expected, bump) = registry_address();
+    if registry_account.key != &expected {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if registry_account.owner == program_id {
+        msg!("Error: Registry already initialized");
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    create_pda(program_id, registry_account, payer, system_program_account, Registry::SPACE, &[REGISTRY_SEED, &[bump]])?;
+    
+    let registry = Registry { authority, bump };
+    registry.serialize(&mut &mut registry_account.try_borrow_mut_data()?[..])?;
+    
+    msg!("Registry authority: {}", authority);
+    Ok(())
+}
+
+fn set_authority(program_id: &Pubkey, accounts: &[AccountInfo], authority: Pubkey) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let registry_account = next_account_info(account_iter)?;
+    let current = next_account_info(account_iter)?;
+    
+    let mut registry = load_registry(program_id, registry_account)?;
+    check_authority(&registry, current)?;
+    
+    registry.authority = authority;
+    registry.serialize(&mut &mut registry_account.try_borrow_mut_data()?[..])?;
+    
+    msg!("Registry authority: {} -> {}", current.key, authority);
+    Ok(())
+}
+
+fn publish(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    facet: Pubkey,
+    killed: bool,
+    severity: u8,
+    advisory: &str,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let registry_account = next_account_info(account_iter)?;
+    let status_account = next_account_info(account_iter)?;
+    let authority = next_account_info(account_iter)?;
+    let payer = next_account_info(account_iter)?;
+    let system_program_account = next_account_info(account_iter)?;
+    
+    let registry = load_registry(program_id, registry_account)?;
+    check_authority(&registry, authority)?;
+    if severity > severity::CRITICAL || advisory.len() > ADVISORY_LEN {
+        msg!("Error: Severity must be at most {} and the advisory at most {} bytes", severity::CRITICAL, ADVISORY_LEN);
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    
+    let (expected, bump) = status_address(&facet);
+    if status_account.key != &expected {
+        msg!("Error: Invalid status account for {}. Expected: {}", facet, expected);
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if status_account.owner != program_id {
+        if !payer.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if system_program_account.key != &system_program::id() {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let seeds: &[&[u8]] = &[FACET_STATUS_SEED, facet.as_ref(), &[bump]];
+        create_pda(program_id, status_account, payer, system_program_account, FacetStatus::ENCODED_LEN, seeds)?;
+    }
+    
+    let mut reference = [0u8; ADVISORY_LEN];
+    reference[..advisory.len()].copy_from_slice(advisory.as_bytes());
+    let status = FacetStatus {
+        facet: facet.to_bytes(),
+        killed,
+        severity,
+        advisory: reference,
+        updated_at: Clock::get()?.unix_timestamp,
+        bump,
+    };
+    status.serialize(&mut &mut status_account.try_borrow_mut_data()?[..])?;
+    
+    msg!("Facet {} status: killed {}, severity {}, advisory {:?}", facet, killed, severity, advisory);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_registry_space() {
+        let registry = Registry { authority: Pubkey::new_unique(), bump: 255 };
+        assert_eq!(borsh::to_vec(&registry).unwrap().len(), Registry::SPACE);
+    }
+    
+    #[test]
+    fn test_status_address_is_the_routers() {
+        let facet = Pubkey::new_unique();
+        assert_eq!(
+            status_address(&facet),
+            diamond_router_native::diamond_health::facet_status_address(&id(), &facet)
+        );
+    }
+    
+    #[test]
+    fn test_upgrade_authority() {
+        let authority = Pubkey::new_unique();
+        let mut programdata = PROGRAM_DATA_TAG.to_vec();
+        programdata.extend_from_slice(&7u64.to_le_bytes());
+        programdata.push(1);
+        programdata.extend_from_slice(authority.as_ref());
+        programdata.extend_from_slice(b"\x7fELF");
+        assert_eq!(upgrade_authority(&programdata), Some(authority));
+        
+        // Immutable programs have no upgrade authority
+        programdata[PROGRAM_DATA_AUTHORITY_OFFSET] = 0;
+        assert_eq!(upgrade_authority(&programdata), None);
+        assert_eq!(upgrade_authority(&[2, 0, 0, 0]), None);
+    }
+}
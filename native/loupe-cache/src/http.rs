@@ -0,0 +1,108 @@
+/*!
+ * HTTP/JSON front
+ *
+ * Runs on the shared `diamond_cli::http` server, answering GETs only.
+ * Responses carry the snapshot's ETag and
+ * `Cache-Control: no-cache`, so browsers and CDNs revalidate with
+ * `If-None-Match` and get a bodiless 304 while the table is unchanged.
+ */
+
+use diamond_cli::http;
+use solana_program::pubkey::Pubkey;
+
+use crate::Cache;
+
+#[derive(Debug, PartialEq)]
+pub struct Response {
+    pub status: &'static str,
+    pub etag: Option<String>,
+    pub body: String,
+}
+
+impl Response {
+    fn error(status: &'static str, message: &str) -> Self {
+        Self { status, etag: None, body: serde_json::json!({ "error": message }).to_string() }
+    }
+}
+
+impl From<Response> for http::Response {
+    fn from(response: Response) -> Self {
+        let mut headers = vec![
+            ("Cache-Control", "no-cache".to_string()),
+            ("Access-Control-Allow-Origin", "*".to_string()),
+        ];
+        headers.extend(response.etag.map(|etag| ("ETag", etag)));
+        http::Response { status: response.status, headers, body: response.body }
+    }
+}
+
+/// Answer `GET <path>` from the cache
+///
+/// Routes: `/health`, `/diamonds`, `/diamonds/<state>[/<view>]` with the
+/// views of `snapshot::VIEWS`.
+pub fn respond(cache: &Cache, method: &str, path: &str, if_none_match: Option<&str>) -> Response {
+    if method != "GET" {
+        return Response::error("405 Method Not Allowed", "only GET is served");
+    }
+    let path = path.split('?').next().unwrap_or_default().trim_end_matches('/');
+    let snapshots = cache.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let mut segments = path.split('/').skip(1);
+    match (segments.next(), segments.next(), segments.next(), segments.next()) {
+        (Some("health"), None, ..) => Response { status: "200 OK", etag: None, body: "{\"ok\":true}".to_string() },
+        (Some("diamonds"), None, ..) => {
+            let diamonds: Vec<String> = snapshots.keys().map(Pubkey::to_string).collect();
+            Response { status: "200 OK", etag: None, body: serde_json::json!(diamonds).to_string() }
+        }
+        (Some("diamonds"), Some(address), view, None) => {
+            let Some(Some(snapshot)) = address.parse::<Pubkey>().ok().and_then(|a| snapshots.get(&a)) else {
+                return Response::error("404 Not Found", "diamond not cached");
+            };
+            let Some(body) = snapshot.view(view.unwrap_or_default()) else {
+                return Response::error("404 Not Found", "unknown view");
+            };
+            if if_none_match.is_some_and(|tags| tags.split(',').any(|tag| tag.trim() == snapshot.etag)) {
+                return Response { status: "304 Not Modified", etag: Some(snapshot.etag.clone()), body: String::new() };
+            }
+            Response { status: "200 OK", etag: Some(snapshot.etag.clone()), body: body.to_string() }
+        }
+        _ => Response::error("404 Not Found", "unknown route"),
+    }
+}
+
+/// Serve the cache on `listen` until the process exits
+pub fn serve(cache: Cache, listen: &str) -> Result<(), String> {
+    println!("Serving loupe data on http://{}", listen);
+    http::serve(listen, move |request| {
+        respond(&cache, &request.method, &request.path, request.header("if-none-match")).into()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::Snapshot;
+    use borsh::BorshSerialize;
+    use diamond_router_native::diamond_state::DiamondState;
+    use std::collections::HashMap;
+    use std::sync::{Arc, RwLock};
+    
+    #[test]
+    fn test_routes_and_revalidation() {
+        let (address, pending) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let data = DiamondState::new(Pubkey::new_unique(), 255).try_to_vec().unwrap();
+        let snapshot = Snapshot::decode(&address, &data, 7).unwrap();
+        let etag = snapshot.etag.clone();
+        let cache: Cache = Arc::new(RwLock::new(HashMap::from([(address, Some(snapshot)), (pending, None)])));
+        
+        let path = format!("/diamonds/{}/selectors", address);
+        let fresh = respond(&cache, "GET", &path, None);
+        assert_eq!((fresh.status, fresh.etag.as_deref()), ("200 OK", Some(etag.as_str())));
+        let revalidated = respond(&cache, "GET", &path, Some(&format!("\"other\", {}", etag)));
+        assert_eq!((revalidated.status, revalidated.body.as_str()), ("304 Not Modified", ""));
+        
+        assert_eq!(respond(&cache, "GET", &format!("/diamonds/{}", pending), None).status, "404 Not Found");
+        assert_eq!(respond(&cache, "GET", &format!("/diamonds/{}/nope", address), None).status, "404 Not Found");
+        assert_eq!(respond(&cache, "POST", "/health", None).status, "405 Method Not Allowed");
+        assert_eq!(respond(&cache, "GET", "/health", None).status, "200 OK");
+    }
+}
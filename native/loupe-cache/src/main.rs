@@ -0,0 +1,176 @@
+/*!
+ * Diamond Loupe Cache
+ *
+ * Serves decoded diamond routing tables over HTTP/JSON so front-ends don't
+ * fetch and decode the full state account on every page load. Each diamond
+ * is fetched once over JSON-RPC, then kept current through an
+ * `accountSubscribe` WebSocket subscription. A dropped subscription
+ * refetches everything before resubscribing.
+ *
+ * Usage:
+ *   diamond-loupe-cache --program <router_id> --state <diamond_state>[,<diamond_state>...]
+ *                       [--url <http_rpc>] [--ws <ws_rpc>] [--listen <addr>]
+ *
+ * `--ws` defaults to the RPC URL's host on the next port (the validator
+ * convention); `--listen` defaults to 127.0.0.1:8980.
+ */
+
+use diamond_cli::{parse_flags, rpc::parse_account, rpc::RpcClient};
+use serde_json::{json, Value};
+use solana_program::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::process::ExitCode;
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::Duration;
+
+mod http;
+mod snapshot;
+mod ws;
+
+use snapshot::Snapshot;
+use ws::WebSocket;
+
+const DEFAULT_URL: &str = "http://127.0.0.1:8899";
+const DEFAULT_LISTEN: &str = "127.0.0.1:8980";
+
+/// Wait before resubscribing after the WebSocket drops
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+/// Latest snapshot of every watched diamond (`None` until fetched, or while
+/// the account is missing or undecodable)
+pub type Cache = Arc<RwLock<HashMap<Pubkey, Option<Snapshot>>>>;
+
+/// Pubsub URL next to an `http://host:port` RPC URL
+fn default_ws_url(rpc_url: &str) -> Result<String, String> {
+    let rest = rpc_url
+        .strip_prefix("http://")
+        .ok_or_else(|| format!("only http:// endpoints are supported: {}", rpc_url))?;
+    let host = rest.split('/').next().unwrap_or_default();
+    match host.rsplit_once(':') {
+        Some((name, port)) => {
+            let port: u16 = port.parse().map_err(|_| format!("invalid port in {}", rpc_url))?;
+            Ok(format!("ws://{}:{}", name, port + 1))
+        }
+        None => Ok(format!("ws://{}", host)),
+    }
+}
+
+/// Decode and store one account version
+fn store(cache: &Cache, router: &Pubkey, diamond: &Pubkey, account: Option<(Pubkey, Vec<u8>)>, slot: u64) {
+    let snapshot = match account {
+        Some((owner, _)) if &owner != router => {
+            eprintln!("{} is not owned by router {}", diamond, router);
+            None
+        }
+        Some((_, data)) => Snapshot::decode(diamond, &data, slot).map_err(|e| eprintln!("{}", e)).ok(),
+        None => None,
+    };
+    let mut snapshots = cache.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+    // Notifications can race the initial fetch; keep the newest
+    if snapshots.get(diamond).and_then(Option::as_ref).is_some_and(|current| current.slot > slot) {
+        return;
+    }
+    snapshots.insert(*diamond, snapshot);
+}
+
+fn refetch(rpc: &RpcClient, cache: &Cache, router: &Pubkey, diamonds: &[Pubkey]) -> Result<(), String> {
+    let slot = rpc.slot()?;
+    for diamond in diamonds {
+        let account = rpc.get_account(diamond)?.map(|a| (a.owner, a.data));
+        store(cache, router, diamond, account, slot);
+    }
+    Ok(())
+}
+
+/// Subscribe to every diamond and apply notifications until the socket drops
+fn follow(ws_url: &str, cache: &Cache, router: &Pubkey, diamonds: &[Pubkey]) -> Result<(), String> {
+    let mut socket = WebSocket::connect(ws_url)?;
+    for (id, diamond) in diamonds.iter().enumerate() {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "accountSubscribe",
+            "params": [diamond.to_string(), { "encoding": "base64", "commitment": "confirmed" }],
+        });
+        socket.send_text(&request.to_string())?;
+    }
+    let mut subscriptions = HashMap::new();
+    loop {
+        let message: Value = serde_json::from_str(&socket.read_text()?).map_err(|e| e.to_string())?;
+        if let (Some(id), Some(subscription)) = (message["id"].as_u64(), message["result"].as_u64()) {
+            let diamond = diamonds.get(id as usize).ok_or("subscription reply for an unknown request")?;
+            subscriptions.insert(subscription, *diamond);
+            continue;
+        }
+        if message["method"] != "accountNotification" {
+            continue;
+        }
+        let params = &message["params"];
+        let Some(diamond) = params["subscription"].as_u64().and_then(|s| subscriptions.get(&s)) else {
+            continue;
+        };
+        let slot = params["result"]["context"]["slot"].as_u64().unwrap_or_default();
+        let account = parse_account(&params["result"]["value"])?.map(|a| (a.owner, a.data));
+        store(cache, router, diamond, account, slot);
+        println!("Updated {} at slot {}", diamond, slot);
+    }
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let flags = parse_flags(args)?;
+    let url = flags.get("url").map(String::as_str).unwrap_or(DEFAULT_URL);
+    let rpc = RpcClient::new(url)?;
+    let ws_url = match flags.get("ws") {
+        Some(ws) => ws.clone(),
+        None => default_ws_url(url)?,
+    };
+    let router: Pubkey = flags
+        .get("program")
+        .ok_or("--program is required")?
+        .parse()
+        .map_err(|_| "--program: invalid pubkey")?;
+    let diamonds = flags
+        .get("state")
+        .ok_or("--state is required")?
+        .split(',')
+        .map(|s| s.trim().parse().map_err(|_| format!("--state: invalid pubkey {}", s)))
+        .collect::<Result<Vec<Pubkey>, String>>()?;
+    let listen = flags.get("listen").map(String::as_str).unwrap_or(DEFAULT_LISTEN);
+    
+    let cache: Cache = Arc::new(RwLock::new(diamonds.iter().map(|d| (*d, None)).collect()));
+    let follower = cache.clone();
+    thread::spawn(move || loop {
+        // Refetch first so nothing missed while disconnected stays stale
+        let result = refetch(&rpc, &follower, &router, &diamonds)
+            .and_then(|()| follow(&ws_url, &follower, &router, &diamonds));
+        if let Err(e) = result {
+            eprintln!("subscription: {}; reconnecting", e);
+        }
+        thread::sleep(RECONNECT_DELAY);
+    });
+    http::serve(cache, listen)
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_default_ws_url() {
+        assert_eq!(default_ws_url("http://127.0.0.1:8899").unwrap(), "ws://127.0.0.1:8900");
+        assert_eq!(default_ws_url("http://rpc.local/path").unwrap(), "ws://rpc.local");
+        assert!(default_ws_url("https://api.mainnet-beta.solana.com").is_err());
+    }
+}
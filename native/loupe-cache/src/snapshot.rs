@@ -0,0 +1,126 @@
+/*!
+ * Decoded diamond snapshots
+ *
+ * Each account update is decoded once into the JSON views the server hands
+ * out, all tagged with one ETag (a hash of the account bytes), so repeat
+ * requests for an unchanged table cost a header comparison.
+ */
+
+use borsh::BorshDeserialize;
+use diamond_router_native::{
+    diamond_loupe,
+    diamond_state::{fixed_str, DiamondState},
+};
+use serde_json::{json, Value};
+use solana_program::{hash::hashv, pubkey::Pubkey};
+use std::collections::HashMap;
+
+/// Views served under `/diamonds/<state>/<view>` (`""` is the summary)
+pub const VIEWS: [&str; 4] = ["", "selectors", "facets", "capacity"];
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// One decoded version of a diamond state account
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub slot: u64,
+    /// Quoted strong ETag shared by every view
+    pub etag: String,
+    views: HashMap<&'static str, String>,
+}
+
+impl Snapshot {
+    /// Decode a diamond state account's data as of `slot`
+    pub fn decode(address: &Pubkey, data: &[u8], slot: u64) -> Result<Self, String> {
+        let state = DiamondState::deserialize(&mut &data[..]).map_err(|e| format!("{}: {}", address, e))?;
+        let capacity = diamond_loupe::capacity(&state, data.len()).map_err(|e| e.to_string())?;
+        let width = state.selector_width;
+        
+        let summary = json!({
+            "address": address.to_string(),
+            "slot": slot,
+            "owner": state.owner.to_string(),
+            "pause_authority": state.pause_authority.to_string(),
+            "dispatch_paused": state.dispatch_paused,
+            "cut_paused": state.cut_paused,
+            "pause_expiry_slot": state.pause_expiry_slot,
+            "governance": state.governance.map(|g| g.to_string()),
+            "council": state.has_council,
+            "selector_width": width,
+            "config_version": state.config_version,
+            "selectors": state.selectors.len(),
+            "facets": state.active_modules.len(),
+        });
+        let selectors = json!({
+            "selectors": state.selectors.iter().map(|m| json!({
+                "selector": hex(m.selector.as_bytes(width)),
+                "name": fixed_str(&m.function_name),
+                "namespace": fixed_str(&m.namespace),
+                "module": m.module.to_string(),
+                "immutable": m.is_immutable,
+                "flags": m.standard_accounts,
+            })).collect::<Vec<Value>>(),
+            "aliases": state.aliases.iter().map(|a| json!({
+                "alias": hex(a.alias.as_bytes(width)),
+                "canonical": hex(a.canonical.as_bytes(width)),
+            })).collect::<Vec<Value>>(),
+        });
+        let facets = json!(state.active_modules.iter().map(|m| json!({
+            "name": fixed_str(&m.name),
+            "address": m.address.to_string(),
+            "version": m.version.to_string(),
+            "active": m.is_active,
+        })).collect::<Vec<Value>>());
+        let usage = |u: diamond_loupe::Usage| json!({ "used": u.used, "max": u.max });
+        let capacity = json!({
+            "selectors": usage(capacity.selectors),
+            "modules": usage(capacity.modules),
+            "admins": usage(capacity.admins),
+            "bytes_free": capacity.bytes_free,
+        });
+        
+        let views = VIEWS.into_iter().zip([summary, selectors, facets, capacity]);
+        Ok(Self {
+            slot,
+            etag: format!("\"{}\"", &hex(&hashv(&[data]).to_bytes())[..16]),
+            views: views.map(|(name, value)| (name, value.to_string())).collect(),
+        })
+    }
+    
+    /// JSON body of `view`
+    pub fn view(&self, view: &str) -> Option<&str> {
+        self.views.get(view).map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diamond_router_native::diamond_cut::{apply_cuts, FacetCut};
+    
+    #[test]
+    fn test_views_and_etag_follow_the_data() {
+        let address = Pubkey::new_unique();
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        let mut data = vec![0; DiamondState::SPACE];
+        borsh::to_writer(&mut data[..], &state).unwrap();
+        let empty = Snapshot::decode(&address, &data, 1).unwrap();
+        
+        let module = Pubkey::new_unique();
+        apply_cuts(&mut state, &[FacetCut::add([1, 2, 3, 4], module, "counter", "increment")]).unwrap();
+        borsh::to_writer(&mut data[..], &state).unwrap();
+        let cut = Snapshot::decode(&address, &data, 2).unwrap();
+        
+        assert_ne!(empty.etag, cut.etag);
+        assert_eq!(Snapshot::decode(&address, &data, 3).unwrap().etag, cut.etag);
+        let selectors: Value = serde_json::from_str(cut.view("selectors").unwrap()).unwrap();
+        assert_eq!(selectors["selectors"][0]["name"], "increment");
+        assert_eq!(selectors["selectors"][0]["module"], module.to_string());
+        let summary: Value = serde_json::from_str(cut.view("").unwrap()).unwrap();
+        assert_eq!(summary["slot"], 2);
+        assert_eq!(cut.view("admins"), None);
+        assert!(Snapshot::decode(&address, &[1, 2], 1).is_err());
+    }
+}
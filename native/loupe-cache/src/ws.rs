@@ -0,0 +1,193 @@
+/*!
+ * Minimal WebSocket client
+ *
+ * Just enough of RFC 6455 for Solana's pubsub endpoint: a plain `ws://`
+ * handshake, masked text frames out, and text, ping and close frames in
+ * (fragmented messages are reassembled). Secure endpoints need a local
+ * proxy, as with the CLI's JSON-RPC client.
+ */
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use solana_program::hash::hashv;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const OP_CONTINUATION: u8 = 0x0;
+const OP_TEXT: u8 = 0x1;
+const OP_CLOSE: u8 = 0x8;
+const OP_PING: u8 = 0x9;
+const OP_PONG: u8 = 0xA;
+
+/// Largest incoming message accepted (account notifications are ~16 KB)
+const MAX_MESSAGE_LEN: usize = 1 << 20;
+
+/// One frame off the wire
+#[derive(Debug, PartialEq)]
+pub struct Frame {
+    pub fin: bool,
+    pub opcode: u8,
+    pub payload: Vec<u8>,
+}
+
+/// Encode a single-frame message, masked with `mask` (client frames must be)
+pub fn encode_frame(opcode: u8, payload: &[u8], mask: [u8; 4]) -> Vec<u8> {
+    let mut frame = vec![0x80 | opcode];
+    match payload.len() {
+        len if len < 126 => frame.push(0x80 | len as u8),
+        len if len <= u16::MAX as usize => {
+            frame.push(0x80 | 126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(0x80 | 127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(&mask);
+    frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+    frame
+}
+
+/// Read one frame (masked or not)
+pub fn read_frame(reader: &mut impl Read) -> io::Result<Frame> {
+    let mut head = [0u8; 2];
+    reader.read_exact(&mut head)?;
+    let len = match head[1] & 0x7F {
+        126 => {
+            let mut len = [0u8; 2];
+            reader.read_exact(&mut len)?;
+            u16::from_be_bytes(len) as usize
+        }
+        127 => {
+            let mut len = [0u8; 8];
+            reader.read_exact(&mut len)?;
+            u64::from_be_bytes(len) as usize
+        }
+        len => len as usize,
+    };
+    if len > MAX_MESSAGE_LEN {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "websocket frame too large"));
+    }
+    let mask = if head[1] & 0x80 != 0 {
+        let mut mask = [0u8; 4];
+        reader.read_exact(&mut mask)?;
+        Some(mask)
+    } else {
+        None
+    };
+    let mut payload = vec![0u8; len];
+    reader.read_exact(&mut payload)?;
+    if let Some(mask) = mask {
+        payload.iter_mut().enumerate().for_each(|(i, b)| *b ^= mask[i % 4]);
+    }
+    Ok(Frame { fin: head[0] & 0x80 != 0, opcode: head[0] & 0x0F, payload })
+}
+
+/// Fresh bytes for the handshake key and frame masks (not cryptographic;
+/// masking only has to be unpredictable to intermediaries)
+fn nonce(salt: &[u8]) -> [u8; 32] {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or_default();
+    hashv(&[&nanos.to_le_bytes(), &std::process::id().to_le_bytes(), salt]).to_bytes()
+}
+
+pub struct WebSocket {
+    stream: TcpStream,
+    sent: u64,
+}
+
+impl WebSocket {
+    /// Connect and complete the opening handshake
+    pub fn connect(url: &str) -> Result<Self, String> {
+        let rest = url
+            .strip_prefix("ws://")
+            .ok_or_else(|| format!("only ws:// endpoints are supported: {}", url))?;
+        let (host, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+        let host = if host.contains(':') { host.to_string() } else { format!("{}:80", host) };
+        let mut stream = TcpStream::connect(&host).map_err(|e| format!("{}: {}", host, e))?;
+        
+        let key = STANDARD.encode(&nonce(b"key")[..16]);
+        let request = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Key: {}\r\nSec-WebSocket-Version: 13\r\n\r\n",
+            path, host, key
+        );
+        stream.write_all(request.as_bytes()).map_err(|e| e.to_string())?;
+        
+        // Read the response head byte by byte so no frame data is consumed
+        let mut head = Vec::new();
+        let mut byte = [0u8; 1];
+        while !head.ends_with(b"\r\n\r\n") {
+            stream.read_exact(&mut byte).map_err(|e| format!("websocket handshake: {}", e))?;
+            head.push(byte[0]);
+            if head.len() > 8192 {
+                return Err("websocket handshake: response head too large".to_string());
+            }
+        }
+        let status = String::from_utf8_lossy(&head);
+        if !status.starts_with("HTTP/1.1 101") {
+            return Err(format!("websocket handshake refused: {}", status.lines().next().unwrap_or_default()));
+        }
+        Ok(Self { stream, sent: 0 })
+    }
+    
+    fn send(&mut self, opcode: u8, payload: &[u8]) -> Result<(), String> {
+        self.sent += 1;
+        let mut mask = [0u8; 4];
+        mask.copy_from_slice(&nonce(&self.sent.to_le_bytes())[..4]);
+        self.stream.write_all(&encode_frame(opcode, payload, mask)).map_err(|e| e.to_string())
+    }
+    
+    pub fn send_text(&mut self, text: &str) -> Result<(), String> {
+        self.send(OP_TEXT, text.as_bytes())
+    }
+    
+    /// Next text message, answering pings on the way; errors once the
+    /// server closes the connection
+    pub fn read_text(&mut self) -> Result<String, String> {
+        let mut message = Vec::new();
+        loop {
+            let frame = read_frame(&mut self.stream).map_err(|e| e.to_string())?;
+            match frame.opcode {
+                OP_PING => self.send(OP_PONG, &frame.payload)?,
+                OP_PONG => {}
+                OP_CLOSE => return Err("websocket closed by server".to_string()),
+                OP_TEXT | OP_CONTINUATION => {
+                    message.extend_from_slice(&frame.payload);
+                    if message.len() > MAX_MESSAGE_LEN {
+                        return Err("websocket message too large".to_string());
+                    }
+                    if frame.fin {
+                        return String::from_utf8(message).map_err(|e| e.to_string());
+                    }
+                }
+                opcode => return Err(format!("unexpected websocket opcode {:#x}", opcode)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_frames_round_trip() {
+        for len in [5, 200, 70_000] {
+            let payload = vec![7u8; len];
+            let encoded = encode_frame(OP_TEXT, &payload, [1, 2, 3, 4]);
+            // Masked on the wire
+            assert_ne!(&encoded[encoded.len() - len..], &payload[..]);
+            let frame = read_frame(&mut &encoded[..]).unwrap();
+            assert_eq!(frame, Frame { fin: true, opcode: OP_TEXT, payload });
+        }
+        
+        // Unmasked server frame
+        let frame = read_frame(&mut &[0x89, 2, b'h', b'i'][..]).unwrap();
+        assert_eq!(frame, Frame { fin: true, opcode: OP_PING, payload: b"hi".to_vec() });
+        assert!(read_frame(&mut &[0x81, 5, b'h'][..]).is_err());
+    }
+}
@@ -0,0 +1,350 @@
+/*!
+ * Anchor Compatibility
+ * Borsh mirror of the Anchor router's `DiamondState` account
+ *
+ * Anchor accounts are an 8-byte discriminator (`sha256("account:DiamondState")[..8]`)
+ * followed by the Borsh encoding of the struct, so the native router can read
+ * and write them without depending on anchor-lang.
+ */
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    hash::hash,
+    instruction::{AccountMeta, Instruction},
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    system_program,
+    sysvar::Sysvar,
+};
+
+use crate::builtins::is_reserved;
+use crate::diamond_state::{
+    check_vanity, diamond_address, diamond_seeds, DiamondState, ModuleMeta, SelectorMapping, Semver, VanitySeed,
+};
+use crate::error::DiamondError;
+
+/// Anchor instruction discriminator (`sha256("global:<name>")[..8]`)
+fn anchor_ix_discriminator(name: &str) -> [u8; 8] {
+    let mut out = [0u8; 8];
+    out.copy_from_slice(&hash(format!("global:{}", name).as_bytes()).to_bytes()[..8]);
+    out
+}
+
+/// Approver list capacity of the Anchor `DiamondState` (the owner approves
+/// without being listed)
+pub const ANCHOR_MAX_UNPAUSE_APPROVERS: usize = 5;
+
+/// Anchor `SelectorMapping`
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct AnchorSelectorMapping {
+    pub selector: [u8; 4],
+    pub module: Pubkey,
+    pub function_name: String,
+    pub is_immutable: bool,
+}
+
+/// Anchor `ModuleMeta`
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct AnchorModuleMeta {
+    pub name: String,
+    pub address: Pubkey,
+    pub version: u16,
+}
+
+/// Anchor `DiamondState` (field order must match programs/sol_diamond)
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct AnchorDiamondState {
+    pub owner: Pubkey,
+    pub selectors: Vec<AnchorSelectorMapping>,
+    pub modules: Vec<AnchorModuleMeta>,
+    pub bump: u8,
+    pub is_paused: bool,
+    pub pause_expiry_slot: Option<u64>,
+    pub pause_authority: Pubkey,
+    pub unpause_quorum: u8,
+    pub unpause_approvers: Vec<Pubkey>,
+}
+
+impl AnchorDiamondState {
+    /// Anchor account discriminator for `DiamondState`
+    pub fn discriminator() -> [u8; 8] {
+        let mut out = [0u8; 8];
+        out.copy_from_slice(&hash(b"account:DiamondState").to_bytes()[..8]);
+        out
+    }
+    
+    /// Decode Anchor account data (discriminator + Borsh, trailing padding allowed)
+    pub fn decode(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() < 8 || data[..8] != Self::discriminator() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Self::deserialize(&mut &data[8..]).map_err(|_| ProgramError::InvalidAccountData)
+    }
+    
+    /// Encode as Anchor account data
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = Self::discriminator().to_vec();
+        // Writing into a Vec cannot fail
+        self.serialize(&mut data).expect("borsh encode");
+        data
+    }
+    
+    /// Upgrade into native state; native-only fields take their defaults
+    ///
+    /// Module names longer than the native 32-byte field are truncated, and
+    /// the diamond keeps the Anchor router's 4-byte selector width. Native
+    /// unpause approvers besides the owner are the admins, and Anchor
+    /// approvers aren't promoted to admins (admins may also cut), so the
+    /// unpause quorum starts at 0; set it again once the admins are in.
+    pub fn to_native(&self) -> DiamondState {
+        let mut state = DiamondState::new(self.owner, self.bump);
+        state.selectors = self
+            .selectors
+            .iter()
+            .map(|s| SelectorMapping::new(s.selector, s.module, &s.function_name, s.is_immutable))
+            .collect();
+        state.active_modules = self
+            .modules
+            .iter()
+            .map(|m| ModuleMeta::new(&m.name, m.address, Semver::new(m.version, 0, 0)))
+            .collect();
+        state.dispatch_paused = self.is_paused;
+        state.pause_expiry_slot = self.pause_expiry_slot;
+        state.pause_authority = self.pause_authority;
+        state
+    }
+    
+    /// Project native state onto the Anchor layout
+    ///
+    /// Admins, governance, namespaces, standard-account flags, a cut-only
+    /// pause and minor/patch versions have no Anchor equivalent and are
+    /// dropped. An unpause quorum keeps the first admins as its approver
+    /// list (and at most as many approvals as the owner and that list hold).
+    /// Selectors keep their first 4 bytes, so only 4-byte diamonds project
+    /// losslessly.
+    pub fn from_native(state: &DiamondState) -> Self {
+        let approvers: Vec<Pubkey> = match state.unpause_quorum {
+            0 => Vec::new(),
+            _ => state
+                .unpause_approvers()
+                .into_iter()
+                .filter(|approver| approver != &state.owner)
+                .take(ANCHOR_MAX_UNPAUSE_APPROVERS)
+                .collect(),
+        };
+        Self {
+            owner: state.owner,
+            selectors: state
+                .selectors
+                .iter()
+                .map(|s| AnchorSelectorMapping {
+                    selector: s.selector.prefix4(),
+                    module: s.module,
+                    function_name: s.function_name_as_str().to_string(),
+                    is_immutable: s.is_immutable,
+                })
+                .collect(),
+            modules: state
+                .active_modules
+                .iter()
+                .map(|m| AnchorModuleMeta {
+                    name: m.name_as_str().to_string(),
+                    address: m.address,
+                    version: m.version.major,
+                })
+                .collect(),
+            bump: state.bump,
+            is_paused: state.dispatch_paused,
+            pause_expiry_slot: state.pause_expiry_slot,
+            pause_authority: state.pause_authority,
+            unpause_quorum: state.unpause_quorum.min(1 + approvers.len() as u8),
+            unpause_approvers: approvers,
+        }
+    }
+}
+
+/// Build native state for `program_id` from an Anchor account
+///
+/// Rejects routing tables the native router would refuse to dispatch.
+pub fn upgrade(
+    anchor: &AnchorDiamondState,
+    program_id: &Pubkey,
+    bump: u8,
+) -> Result<DiamondState, DiamondError> {
+    for mapping in &anchor.selectors {
+        if &mapping.module == program_id {
+            return Err(DiamondError::SelfDispatchForbidden);
+        }
+        if is_reserved(mapping.selector) {
+            return Err(DiamondError::ReservedSelector);
+        }
+    }
+    
+    let mut state = anchor.to_native();
+    state.bump = bump;
+    Ok(state)
+}
+
+/// Copy an Anchor-router diamond into a fresh native-router PDA
+///
+/// Accounts: [anchor_state, native_state, owner, payer, system_program, anchor_program, (recipient)]
+/// With `close_old`, the Anchor program's `close_diamond` is invoked so the
+/// old account's rent goes to `recipient`. Data is `close_old`, optionally
+/// followed by a `VanitySeed` for the new address (as in `initialize`).
+pub fn migrate_from_anchor(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let anchor_state_account = next_account_info(account_iter)?;
+    let native_state_account = next_account_info(account_iter)?;
+    let owner = next_account_info(account_iter)?;
+    let payer = next_account_info(account_iter)?;
+    let system_program_account = next_account_info(account_iter)?;
+    let anchor_program = next_account_info(account_iter)?;
+    
+    if !owner.is_signer || !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if system_program_account.key != &system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    #[derive(BorshDeserialize)]
+    struct MigrateData {
+        close_old: bool,
+    }
+    
+    let mut rest = data;
+    let migrate_data = MigrateData::deserialize(&mut rest)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    let vanity = match rest {
+        [] => None,
+        _ => Some(VanitySeed::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)?),
+    };
+    
+    // The old account must be the Anchor program's diamond PDA for this owner
+    if anchor_state_account.owner != anchor_program.key {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let (anchor_pda, _) = diamond_address(anchor_program.key, owner.key, None);
+    if anchor_state_account.key != &anchor_pda {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    
+    let anchor = AnchorDiamondState::decode(&anchor_state_account.try_borrow_data()?)?;
+    if &anchor.owner != owner.key {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    
+    // Re-derive under the native program
+    let vanity_nonce = vanity.as_ref().map(|vanity| vanity.nonce);
+    let (native_pda, bump) = diamond_address(program_id, owner.key, vanity_nonce);
+    if native_state_account.key != &native_pda {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if let Some(vanity) = &vanity {
+        check_vanity(&native_pda, &vanity.prefix)?;
+    }
+    if !native_state_account.data_is_empty() {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    
+    let mut state = upgrade(&anchor, program_id, bump)?;
+    state.vanity_nonce = vanity_nonce;
+    
+    let nonce = vanity_nonce.map(u64::to_le_bytes);
+    let bump = [bump];
+    let mut signer_seeds = diamond_seeds(owner.key, nonce.as_ref());
+    signer_seeds.push(&bump);
+    let rent = Rent::get()?;
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            native_state_account.key,
+            rent.minimum_balance(DiamondState::SPACE),
+            DiamondState::SPACE as u64,
+            program_id,
+        ),
+        &[payer.clone(), native_state_account.clone(), system_program_account.clone()],
+        &[&signer_seeds],
+    )?;
+    state.save(native_state_account)?;
+    
+    if migrate_data.close_old {
+        let recipient = next_account_info(account_iter)?;
+        invoke(
+            &Instruction {
+                program_id: *anchor_program.key,
+                accounts: vec![
+                    AccountMeta::new(*anchor_state_account.key, false),
+                    AccountMeta::new_readonly(*owner.key, true),
+                    AccountMeta::new(*recipient.key, false),
+                ],
+                data: anchor_ix_discriminator("close_diamond").to_vec(),
+            },
+            &[anchor_state_account.clone(), owner.clone(), recipient.clone()],
+        )?;
+    }
+    
+    msg!(
+        "Migrated diamond for {} from {} ({} selectors, old account {})",
+        owner.key,
+        anchor_program.key,
+        state.selectors.len(),
+        if migrate_data.close_old { "closed" } else { "kept" }
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builtins::LOOKUP_FUNCTION_SELECTOR;
+    
+    fn anchor_with(selector: [u8; 4], module: Pubkey) -> AnchorDiamondState {
+        let owner = Pubkey::new_unique();
+        AnchorDiamondState {
+            owner,
+            selectors: vec![AnchorSelectorMapping {
+                selector,
+                module,
+                function_name: "increment".to_string(),
+                is_immutable: false,
+            }],
+            modules: vec![],
+            bump: 255,
+            is_paused: false,
+            pause_expiry_slot: None,
+            pause_authority: owner,
+            unpause_quorum: 0,
+            unpause_approvers: vec![],
+        }
+    }
+    
+    #[test]
+    fn test_upgrade_rebinds_bump_and_validates() {
+        let program_id = Pubkey::new_unique();
+        let facet = Pubkey::new_unique();
+        
+        let state = upgrade(&anchor_with([1, 2, 3, 4], facet), &program_id, 7).unwrap();
+        assert_eq!(state.bump, 7);
+        assert_eq!(state.get_module_by_selector([1, 2, 3, 4]), Some(facet));
+        
+        assert_eq!(
+            upgrade(&anchor_with([1, 2, 3, 4], program_id), &program_id, 7).unwrap_err(),
+            DiamondError::SelfDispatchForbidden
+        );
+        assert_eq!(
+            upgrade(&anchor_with(LOOKUP_FUNCTION_SELECTOR, facet), &program_id, 7).unwrap_err(),
+            DiamondError::ReservedSelector
+        );
+    }
+}
@@ -0,0 +1,285 @@
+/*!
+ * Built-in Router Functions
+ * Compile-time selector table for operations the router serves itself
+ *
+ * Built-ins are reachable through `dispatch` exactly like facet selectors,
+ * and their selectors are reserved so no cut can register a facet over them.
+ */
+
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    pubkey::Pubkey,
+};
+
+use crate::selector::Selector;
+use crate::{
+    diamond_authorizer, diamond_breaker, diamond_build, diamond_config, diamond_context, diamond_cut, diamond_dependency,
+    diamond_digest, diamond_fast_path, diamond_guard, diamond_health, diamond_index, diamond_loupe, diamond_relay,
+    diamond_return, diamond_session, diamond_state,
+    diamond_tags, diamond_version,
+};
+#[cfg(feature = "audit-log")]
+use crate::diamond_audit;
+#[cfg(feature = "governance")]
+use crate::{diamond_break_glass, diamond_council, diamond_proposal};
+#[cfg(feature = "oracle-policy")]
+use crate::diamond_oracle;
+
+/// Handler signature shared with the top-level instruction processors
+pub type BuiltinHandler = fn(&Pubkey, &[AccountInfo], &[u8]) -> ProgramResult;
+
+pub use diamond_constants::selectors::{AUTHORIZE_SELECTOR, HEALTH_SELECTOR, RESERVED_PREFIX};
+
+// Built-in selectors (4 bytes at every selector width)
+pub const LOOKUP_FUNCTION_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x00, 0x01];
+pub const DESCRIBE_PROPOSAL_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x00, 0x02];
+pub const PROBE_FACET_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x00, 0x03];
+pub const RESOLVE_SELECTOR_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x00, 0x04];
+pub const PERMISSIONS_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x00, 0x05];
+pub const CAPACITY_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x00, 0x06];
+pub const BUILD_STATUS_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x00, 0x07];
+pub const VERSION_HISTORY_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x00, 0x08];
+pub const SELECTORS_BY_TAG_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x00, 0x09];
+pub const PAUSE_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x01, 0x01];
+pub const SET_PAUSE_AUTHORITY_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x01, 0x02];
+pub const SET_DISPATCH_LIMITS_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x01, 0x03];
+pub const SET_PAYLOAD_SPEC_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x01, 0x04];
+pub const SET_UNPAUSE_QUORUM_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x01, 0x05];
+pub const SCHEDULE_PAUSE_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x01, 0x06];
+pub const CANCEL_PAUSE_WINDOW_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x01, 0x07];
+pub const SET_ADMINS_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x02, 0x01];
+pub const REMOVE_ADMINS_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x02, 0x02];
+pub const SET_GOVERNANCE_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x02, 0x03];
+pub const TRANSFER_OWNERSHIP_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x02, 0x04];
+pub const ACCEPT_OWNERSHIP_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x02, 0x05];
+pub const SET_AUTHORITY_DELAY_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x02, 0x06];
+pub const APPLY_AUTHORITY_CHANGE_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x02, 0x07];
+pub const VETO_AUTHORITY_CHANGE_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x02, 0x08];
+pub const VETO_SIGNED_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x02, 0x09];
+pub const CONFIG_SET_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x03, 0x01];
+pub const CONFIG_DELETE_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x03, 0x02];
+pub const CONFIG_GET_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x03, 0x03];
+pub const SET_FACET_CONFIG_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x03, 0x04];
+pub const SET_AUDIT_TREE_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x04, 0x01];
+pub const ENABLE_ROUTING_DIGEST_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x05, 0x01];
+pub const ENABLE_SELECTOR_INDEX_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x05, 0x02];
+pub const ADD_ALIAS_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x06, 0x01];
+pub const REMOVE_ALIAS_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x06, 0x02];
+pub const SET_RELAY_REIMBURSEMENT_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x07, 0x01];
+pub const CLOSE_SESSION_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x07, 0x02];
+pub const FAST_PATH_PIN_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x08, 0x01];
+pub const FAST_PATH_UNPIN_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x08, 0x02];
+pub const READ_RETURN_CHUNK_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x09, 0x01];
+pub const SET_RETURN_OVERFLOW_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x09, 0x02];
+pub const ATTEST_BUILD_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x0A, 0x01];
+pub const VERIFY_BUILD_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x0A, 0x02];
+pub const ESTABLISH_COUNCIL_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x0B, 0x01];
+pub const SET_TX_DENYLIST_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x0C, 0x01];
+pub const SET_SELECTOR_GUARD_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x0C, 0x02];
+pub const SET_DEPENDENCIES_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x0D, 0x01];
+pub const SET_VERSION_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x0D, 0x02];
+pub const UPGRADE_FACET_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x0D, 0x03];
+pub const SET_AUTHORIZER_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x0E, 0x01];
+pub const SET_TAGS_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x0F, 0x01];
+pub const SET_TAG_PAUSED_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x0F, 0x02];
+pub const REMOVE_BY_TAG_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x0F, 0x03];
+pub const SET_ORACLE_GUARD_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x10, 0x01];
+pub const SET_HEALTH_REGISTRY_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x11, 0x01];
+pub const BREAK_GLASS_SCHEDULE_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x12, 0x01];
+pub const BREAK_GLASS_EXECUTE_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x12, 0x02];
+pub const BREAK_GLASS_CANCEL_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x12, 0x03];
+pub const ENABLE_CALL_CONTEXT_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x13, 0x01];
+pub const SET_CIRCUIT_BREAKER_SELECTOR: [u8; 4] = [0xD1, 0xA0, 0x14, 0x01];
+
+/// Every reserved selector with its human-readable name
+///
+/// Selectors of subsystems compiled out of this build stay listed (and
+/// reserved) but have no handler.
+pub const BUILTIN_SELECTORS: [([u8; 4], &str); 59] = [
+    (LOOKUP_FUNCTION_SELECTOR, "diamond::lookup_function"),
+    (DESCRIBE_PROPOSAL_SELECTOR, "diamond::describe_proposal"),
+    (PROBE_FACET_SELECTOR, "diamond::probe_facet"),
+    (RESOLVE_SELECTOR_SELECTOR, "diamond::resolve_selector"),
+    (PERMISSIONS_SELECTOR, "diamond::permissions"),
+    (CAPACITY_SELECTOR, "diamond::get_capacity"),
+    (BUILD_STATUS_SELECTOR, "diamond::get_build_status"),
+    (VERSION_HISTORY_SELECTOR, "diamond::version_history"),
+    (SELECTORS_BY_TAG_SELECTOR, "diamond::selectors_by_tag"),
+    (PAUSE_SELECTOR, "diamond::pause"),
+    (SET_PAUSE_AUTHORITY_SELECTOR, "diamond::set_pause_authority"),
+    (SET_DISPATCH_LIMITS_SELECTOR, "diamond::set_dispatch_limits"),
+    (SET_PAYLOAD_SPEC_SELECTOR, "diamond::set_payload_spec"),
+    (SET_UNPAUSE_QUORUM_SELECTOR, "diamond::set_unpause_quorum"),
+    (SCHEDULE_PAUSE_SELECTOR, "diamond::schedule_pause"),
+    (CANCEL_PAUSE_WINDOW_SELECTOR, "diamond::cancel_pause_window"),
+    (SET_ADMINS_SELECTOR, "diamond::set_admins"),
+    (REMOVE_ADMINS_SELECTOR, "diamond::remove_admins"),
+    (SET_GOVERNANCE_SELECTOR, "diamond::set_governance"),
+    (TRANSFER_OWNERSHIP_SELECTOR, "diamond::transfer_ownership"),
+    (ACCEPT_OWNERSHIP_SELECTOR, "diamond::accept_ownership"),
+    (SET_AUTHORITY_DELAY_SELECTOR, "diamond::set_authority_delay"),
+    (APPLY_AUTHORITY_CHANGE_SELECTOR, "diamond::apply_authority_change"),
+    (VETO_AUTHORITY_CHANGE_SELECTOR, "diamond::veto_authority_change"),
+    (VETO_SIGNED_SELECTOR, "diamond::veto_signed"),
+    (CONFIG_SET_SELECTOR, "config::set"),
+    (CONFIG_DELETE_SELECTOR, "config::delete"),
+    (CONFIG_GET_SELECTOR, "config::get"),
+    (SET_FACET_CONFIG_SELECTOR, "config::set_facet"),
+    (SET_AUDIT_TREE_SELECTOR, "audit::set_tree"),
+    (ENABLE_ROUTING_DIGEST_SELECTOR, "digest::enable"),
+    (ENABLE_SELECTOR_INDEX_SELECTOR, "index::enable"),
+    (ADD_ALIAS_SELECTOR, "alias::add"),
+    (REMOVE_ALIAS_SELECTOR, "alias::remove"),
+    (SET_RELAY_REIMBURSEMENT_SELECTOR, "relay::set_reimbursement"),
+    (CLOSE_SESSION_SELECTOR, "relay::close_session"),
+    (FAST_PATH_PIN_SELECTOR, "fast_path::pin"),
+    (FAST_PATH_UNPIN_SELECTOR, "fast_path::unpin"),
+    (READ_RETURN_CHUNK_SELECTOR, "return::read_chunk"),
+    (SET_RETURN_OVERFLOW_SELECTOR, "return::set_overflow_policy"),
+    (ATTEST_BUILD_SELECTOR, "build::attest"),
+    (VERIFY_BUILD_SELECTOR, "build::verify_facet_build"),
+    (ESTABLISH_COUNCIL_SELECTOR, "council::establish"),
+    (SET_TX_DENYLIST_SELECTOR, "guard::set_denylist"),
+    (SET_SELECTOR_GUARD_SELECTOR, "guard::set_selector"),
+    (SET_DEPENDENCIES_SELECTOR, "facet::set_dependencies"),
+    (SET_VERSION_SELECTOR, "facet::set_version"),
+    (UPGRADE_FACET_SELECTOR, "facet::upgrade"),
+    (SET_AUTHORIZER_SELECTOR, "authorizer::set"),
+    (SET_TAGS_SELECTOR, "tags::set"),
+    (SET_TAG_PAUSED_SELECTOR, "tags::set_paused"),
+    (REMOVE_BY_TAG_SELECTOR, "tags::remove_all"),
+    (SET_ORACLE_GUARD_SELECTOR, "oracle::set_guard"),
+    (SET_HEALTH_REGISTRY_SELECTOR, "health::set_registry"),
+    (BREAK_GLASS_SCHEDULE_SELECTOR, "break_glass::schedule"),
+    (BREAK_GLASS_EXECUTE_SELECTOR, "break_glass::execute"),
+    (BREAK_GLASS_CANCEL_SELECTOR, "break_glass::cancel"),
+    (ENABLE_CALL_CONTEXT_SELECTOR, "context::enable"),
+    (SET_CIRCUIT_BREAKER_SELECTOR, "breaker::set"),
+];
+
+/// Resolve a built-in selector to its handler
+pub fn builtin_handler(selector: [u8; 4]) -> Option<BuiltinHandler> {
+    match selector {
+        LOOKUP_FUNCTION_SELECTOR => Some(diamond_loupe::lookup_function),
+        #[cfg(feature = "governance")]
+        DESCRIBE_PROPOSAL_SELECTOR => Some(diamond_proposal::describe_proposal),
+        PROBE_FACET_SELECTOR => Some(diamond_health::probe_facet),
+        RESOLVE_SELECTOR_SELECTOR => Some(diamond_loupe::resolve_selector),
+        PERMISSIONS_SELECTOR => Some(diamond_loupe::list_permissions),
+        CAPACITY_SELECTOR => Some(diamond_loupe::get_capacity),
+        BUILD_STATUS_SELECTOR => Some(diamond_loupe::get_build_status),
+        VERSION_HISTORY_SELECTOR => Some(diamond_loupe::version_history),
+        SELECTORS_BY_TAG_SELECTOR => Some(diamond_tags::selectors_by_tag),
+        PAUSE_SELECTOR => Some(diamond_state::pause),
+        SET_PAUSE_AUTHORITY_SELECTOR => Some(diamond_state::set_pause_authority),
+        SET_DISPATCH_LIMITS_SELECTOR => Some(diamond_state::set_dispatch_limits),
+        SET_PAYLOAD_SPEC_SELECTOR => Some(diamond_state::set_payload_spec),
+        SET_UNPAUSE_QUORUM_SELECTOR => Some(diamond_state::set_unpause_quorum),
+        SCHEDULE_PAUSE_SELECTOR => Some(diamond_state::schedule_pause),
+        CANCEL_PAUSE_WINDOW_SELECTOR => Some(diamond_state::cancel_pause_window),
+        SET_ADMINS_SELECTOR => Some(diamond_state::set_admins),
+        REMOVE_ADMINS_SELECTOR => Some(diamond_state::remove_admins),
+        #[cfg(feature = "governance")]
+        SET_GOVERNANCE_SELECTOR => Some(diamond_state::set_governance),
+        TRANSFER_OWNERSHIP_SELECTOR => Some(diamond_state::transfer_ownership),
+        ACCEPT_OWNERSHIP_SELECTOR => Some(diamond_state::accept_ownership),
+        SET_AUTHORITY_DELAY_SELECTOR => Some(diamond_state::set_authority_delay),
+        APPLY_AUTHORITY_CHANGE_SELECTOR => Some(diamond_state::apply_authority_change),
+        VETO_AUTHORITY_CHANGE_SELECTOR => Some(diamond_state::veto_authority_change),
+        VETO_SIGNED_SELECTOR => Some(diamond_state::veto_signed),
+        CONFIG_SET_SELECTOR => Some(diamond_config::set_config),
+        CONFIG_DELETE_SELECTOR => Some(diamond_config::delete_config),
+        CONFIG_GET_SELECTOR => Some(diamond_config::get_config),
+        SET_FACET_CONFIG_SELECTOR => Some(diamond_config::set_facet_config),
+        #[cfg(feature = "audit-log")]
+        SET_AUDIT_TREE_SELECTOR => Some(diamond_audit::set_audit_tree),
+        ENABLE_ROUTING_DIGEST_SELECTOR => Some(diamond_digest::enable_routing_digest),
+        ENABLE_SELECTOR_INDEX_SELECTOR => Some(diamond_index::enable_selector_index),
+        ADD_ALIAS_SELECTOR => Some(diamond_cut::add_alias),
+        REMOVE_ALIAS_SELECTOR => Some(diamond_cut::remove_alias),
+        SET_RELAY_REIMBURSEMENT_SELECTOR => Some(diamond_relay::set_relay_reimbursement),
+        CLOSE_SESSION_SELECTOR => Some(diamond_session::close_session),
+        FAST_PATH_PIN_SELECTOR => Some(diamond_fast_path::pin),
+        FAST_PATH_UNPIN_SELECTOR => Some(diamond_fast_path::unpin),
+        READ_RETURN_CHUNK_SELECTOR => Some(diamond_return::read_chunk),
+        SET_RETURN_OVERFLOW_SELECTOR => Some(diamond_return::set_return_overflow),
+        ATTEST_BUILD_SELECTOR => Some(diamond_build::attest_facet_build),
+        VERIFY_BUILD_SELECTOR => Some(diamond_build::verify_facet_build),
+        #[cfg(feature = "governance")]
+        ESTABLISH_COUNCIL_SELECTOR => Some(diamond_council::establish_council),
+        SET_TX_DENYLIST_SELECTOR => Some(diamond_guard::set_tx_denylist),
+        SET_SELECTOR_GUARD_SELECTOR => Some(diamond_guard::set_selector_guard),
+        SET_DEPENDENCIES_SELECTOR => Some(diamond_dependency::set_dependencies),
+        SET_VERSION_SELECTOR => Some(diamond_version::set_version),
+        UPGRADE_FACET_SELECTOR => Some(diamond_version::upgrade_facet),
+        SET_AUTHORIZER_SELECTOR => Some(diamond_authorizer::set_authorizer),
+        SET_TAGS_SELECTOR => Some(diamond_tags::set_tags),
+        SET_TAG_PAUSED_SELECTOR => Some(diamond_tags::set_tag_paused),
+        REMOVE_BY_TAG_SELECTOR => Some(diamond_tags::remove_by_tag),
+        #[cfg(feature = "oracle-policy")]
+        SET_ORACLE_GUARD_SELECTOR => Some(diamond_oracle::set_oracle_guard),
+        SET_HEALTH_REGISTRY_SELECTOR => Some(diamond_health::set_health_registry),
+        #[cfg(feature = "governance")]
+        BREAK_GLASS_SCHEDULE_SELECTOR => Some(diamond_break_glass::schedule_break_glass),
+        #[cfg(feature = "governance")]
+        BREAK_GLASS_EXECUTE_SELECTOR => Some(diamond_break_glass::execute_break_glass),
+        #[cfg(feature = "governance")]
+        BREAK_GLASS_CANCEL_SELECTOR => Some(diamond_break_glass::cancel_break_glass),
+        ENABLE_CALL_CONTEXT_SELECTOR => Some(diamond_context::enable_call_context),
+        SET_CIRCUIT_BREAKER_SELECTOR => Some(diamond_breaker::set_circuit_breaker),
+        _ => None,
+    }
+}
+
+/// Whether a selector is reserved for the router
+///
+/// The whole `0xD1 0xA0` prefix is reserved at every width, so a built-in's
+/// 4-byte selector can never be the prefix of a routed one.
+pub fn is_reserved(selector: impl Into<Selector>) -> bool {
+    selector.into().0[..2] == RESERVED_PREFIX
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_builtin_table_consistent() {
+        // Built-ins of subsystems left out of this build
+        let compiled_out = [
+            (cfg!(feature = "governance"), DESCRIBE_PROPOSAL_SELECTOR),
+            (cfg!(feature = "governance"), SET_GOVERNANCE_SELECTOR),
+            (cfg!(feature = "audit-log"), SET_AUDIT_TREE_SELECTOR),
+            (cfg!(feature = "governance"), ESTABLISH_COUNCIL_SELECTOR),
+            (cfg!(feature = "oracle-policy"), SET_ORACLE_GUARD_SELECTOR),
+            (cfg!(feature = "governance"), BREAK_GLASS_SCHEDULE_SELECTOR),
+            (cfg!(feature = "governance"), BREAK_GLASS_EXECUTE_SELECTOR),
+            (cfg!(feature = "governance"), BREAK_GLASS_CANCEL_SELECTOR),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, selector)| (!enabled).then_some(selector))
+        .collect::<Vec<_>>();
+        for (i, (selector, name)) in BUILTIN_SELECTORS.iter().enumerate() {
+            assert!(is_reserved(*selector), "{} is outside the reserved prefix", name);
+            assert_eq!(
+                builtin_handler(*selector).is_some(),
+                !compiled_out.contains(selector),
+                "{} has no handler in this build",
+                name
+            );
+            assert!(
+                BUILTIN_SELECTORS[i + 1..].iter().all(|(other, _)| other != selector),
+                "{} is listed twice",
+                name
+            );
+        }
+        assert!(is_reserved(HEALTH_SELECTOR));
+        assert!(builtin_handler(HEALTH_SELECTOR).is_none());
+        assert!(is_reserved(AUTHORIZE_SELECTOR));
+        assert!(builtin_handler(AUTHORIZE_SELECTOR).is_none());
+        assert!(!is_reserved([0x01, 0x02, 0x03, 0x04]));
+        assert!(is_reserved([0xD1, 0xA0]));
+        assert!(is_reserved([0xD1, 0xA0, 0x09, 0x09, 0, 0, 0, 1]));
+    }
+}
@@ -0,0 +1,368 @@
+/*!
+ * Diamond Audit Module
+ * Append-only history committed to an spl-account-compression merkle tree
+ *
+ * Each cut and audited dispatch is hashed into a 32-byte leaf and appended
+ * to a concurrent merkle tree whose authority is a router PDA. The tree
+ * account holds only the root and a changelog buffer, so history size is
+ * bounded by tree depth rather than rent. The full `AuditRecord` is emitted
+ * as a `DiamondEvent::AuditRecorded` next to the compression program's own
+ * changelog, which lets an indexer rebuild every leaf and its proof.
+ *
+ * spl-account-compression is an Anchor program; its two instructions are
+ * encoded here directly rather than through the crate.
+ *
+ * Without the `audit-log` feature a tree can't be attached and audited
+ * dispatch isn't compiled; cuts on a diamond that already has a tree are
+ * still recorded.
+ */
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    hash::{hash, hashv},
+    instruction::{AccountMeta, Instruction},
+    msg,
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    sysvar::Sysvar,
+};
+
+use crate::diamond_cut::FacetCut;
+use crate::diamond_state::DiamondState;
+use crate::error::DiamondError;
+use crate::events::DiamondEvent;
+use crate::selector::Selector;
+#[cfg(feature = "audit-log")]
+use crate::diamond_router::{route, RouteOptions};
+
+pub use diamond_constants::seeds::AUDIT_SEED;
+
+/// spl-account-compression program
+pub const SPL_ACCOUNT_COMPRESSION_ID: Pubkey =
+    solana_program::pubkey!("cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK");
+
+/// spl-noop program (carries the compression changelog into the logs)
+pub const SPL_NOOP_ID: Pubkey = solana_program::pubkey!("noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV");
+
+/// What a leaf records
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub enum AuditKind {
+    /// Cuts applied to the routing table (hash of the Borsh-encoded batch)
+    Cut { cut_count: u8, cuts_hash: [u8; 32] },
+    /// Facet call routed through `dispatch_audited`
+    Dispatch { facet: Pubkey, payload_hash: [u8; 32] },
+    /// Alias added (`canonical` set) or removed
+    Alias { alias: Selector, canonical: Option<Selector> },
+}
+
+/// Preimage of one audit leaf
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct AuditRecord {
+    pub diamond: Pubkey,
+    pub slot: u64,
+    pub actor: Pubkey,
+    pub kind: AuditKind,
+}
+
+impl AuditRecord {
+    pub fn cut(diamond: Pubkey, slot: u64, actor: Pubkey, cuts: &[FacetCut]) -> Self {
+        let encoded = borsh::to_vec(cuts).unwrap_or_default();
+        Self {
+            diamond,
+            slot,
+            actor,
+            kind: AuditKind::Cut {
+                cut_count: cuts.len() as u8,
+                cuts_hash: hash(&encoded).to_bytes(),
+            },
+        }
+    }
+    
+    pub fn alias(diamond: Pubkey, slot: u64, actor: Pubkey, alias: Selector, canonical: Option<Selector>) -> Self {
+        Self { diamond, slot, actor, kind: AuditKind::Alias { alias, canonical } }
+    }
+    
+    /// Leaf committed to the tree
+    pub fn leaf(&self) -> [u8; 32] {
+        let encoded = borsh::to_vec(self).unwrap_or_default();
+        hashv(&[b"diamond-audit", &encoded]).to_bytes()
+    }
+}
+
+pub fn audit_authority(program_id: &Pubkey, diamond: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[AUDIT_SEED, diamond.as_ref()], program_id)
+}
+
+/// Anchor instruction discriminator (`sha256("global:<name>")[..8]`)
+fn compression_ix_data(name: &str, args: &[u8]) -> Vec<u8> {
+    let mut data = hash(format!("global:{}", name).as_bytes()).to_bytes()[..8].to_vec();
+    data.extend_from_slice(args);
+    data
+}
+
+/// `init_empty_merkle_tree(max_depth, max_buffer_size)`
+pub fn init_tree_instruction(
+    merkle_tree: &Pubkey,
+    authority: &Pubkey,
+    max_depth: u32,
+    max_buffer_size: u32,
+) -> Instruction {
+    let mut args = max_depth.to_le_bytes().to_vec();
+    args.extend_from_slice(&max_buffer_size.to_le_bytes());
+    Instruction {
+        program_id: SPL_ACCOUNT_COMPRESSION_ID,
+        accounts: vec![
+            AccountMeta::new(*merkle_tree, false),
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new_readonly(SPL_NOOP_ID, false),
+        ],
+        data: compression_ix_data("init_empty_merkle_tree", &args),
+    }
+}
+
+/// `append(leaf)`
+pub fn append_instruction(merkle_tree: &Pubkey, authority: &Pubkey, leaf: [u8; 32]) -> Instruction {
+    Instruction {
+        program_id: SPL_ACCOUNT_COMPRESSION_ID,
+        accounts: vec![
+            AccountMeta::new(*merkle_tree, false),
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new_readonly(SPL_NOOP_ID, false),
+        ],
+        data: compression_ix_data("append", &leaf),
+    }
+}
+
+/// Accounts every audited instruction appends: [merkle_tree, audit_authority, compression_program, noop_program]
+pub const AUDIT_ACCOUNT_COUNT: usize = 4;
+
+/// Validated audit accounts for `diamond`
+struct AuditAccounts<'a, 'info> {
+    merkle_tree: &'a AccountInfo<'info>,
+    authority: &'a AccountInfo<'info>,
+    compression_program: &'a AccountInfo<'info>,
+    noop_program: &'a AccountInfo<'info>,
+    bump: u8,
+}
+
+impl<'a, 'info> AuditAccounts<'a, 'info> {
+    fn parse(
+        program_id: &Pubkey,
+        diamond: &Pubkey,
+        accounts: &'a [AccountInfo<'info>],
+    ) -> Result<Self, ProgramError> {
+        let account_iter = &mut accounts.iter();
+        let merkle_tree = next_account_info(account_iter)?;
+        let authority = next_account_info(account_iter)?;
+        let compression_program = next_account_info(account_iter)?;
+        let noop_program = next_account_info(account_iter)?;
+        
+        if compression_program.key != &SPL_ACCOUNT_COMPRESSION_ID || noop_program.key != &SPL_NOOP_ID {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let (expected, bump) = audit_authority(program_id, diamond);
+        if authority.key != &expected {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        Ok(Self { merkle_tree, authority, compression_program, noop_program, bump })
+    }
+    
+    fn invoke(&self, diamond: &Pubkey, ix: &Instruction) -> ProgramResult {
+        invoke_signed(
+            ix,
+            &[
+                self.merkle_tree.clone(),
+                self.authority.clone(),
+                self.noop_program.clone(),
+                self.compression_program.clone(),
+            ],
+            &[&[AUDIT_SEED, diamond.as_ref(), &[self.bump]]],
+        )
+    }
+}
+
+/// Append a record if the diamond has an audit tree
+///
+/// `accounts` must start with the audit accounts whenever a tree is set, so
+/// no audited operation can run without leaving a leaf behind.
+pub fn commit(
+    program_id: &Pubkey,
+    diamond: &Pubkey,
+    state: &DiamondState,
+    accounts: &[AccountInfo],
+    record: AuditRecord,
+) -> ProgramResult {
+    let tree = match state.audit_tree {
+        Some(tree) => tree,
+        None => return Ok(()),
+    };
+    let audit = match AuditAccounts::parse(program_id, diamond, accounts) {
+        Ok(audit) => audit,
+        Err(err) => {
+            msg!("Error: Audit accounts required (tree {})", tree);
+            return Err(err);
+        }
+    };
+    if audit.merkle_tree.key != &tree {
+        msg!("Error: Audit tree mismatch. Expected: {}, Got: {}", tree, audit.merkle_tree.key);
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    
+    audit.invoke(diamond, &append_instruction(&tree, audit.authority.key, record.leaf()))?;
+    DiamondEvent::AuditRecorded { record }.emit();
+    Ok(())
+}
+
+/// Record a batch of applied cuts (no-op without an audit tree)
+pub fn commit_cuts(
+    program_id: &Pubkey,
+    state_account: &AccountInfo,
+    state: &DiamondState,
+    audit_accounts: &[AccountInfo],
+    actor: &Pubkey,
+    cuts: &[FacetCut],
+) -> ProgramResult {
+    if state.audit_tree.is_none() {
+        return Ok(());
+    }
+    let record = AuditRecord::cut(*state_account.key, Clock::get()?.slot, *actor, cuts);
+    commit(program_id, state_account.key, state, audit_accounts, record)
+}
+
+/// Record an alias change (no-op without an audit tree)
+pub fn commit_alias(
+    program_id: &Pubkey,
+    state_account: &AccountInfo,
+    state: &DiamondState,
+    audit_accounts: &[AccountInfo],
+    actor: &Pubkey,
+    alias: Selector,
+    canonical: Option<Selector>,
+) -> ProgramResult {
+    if state.audit_tree.is_none() {
+        return Ok(());
+    }
+    let record = AuditRecord::alias(*state_account.key, Clock::get()?.slot, *actor, alias, canonical);
+    commit(program_id, state_account.key, state, audit_accounts, record)
+}
+
+/// Attach (or detach) the diamond's audit tree (owner only)
+///
+/// Accounts: [diamond_state, owner, (merkle_tree, audit_authority, compression_program, noop_program)]
+/// Data: Option<(max_depth: u32, max_buffer_size: u32)>
+///
+/// The tree account must already be allocated for the chosen depth and
+/// buffer size and owned by spl-account-compression; the router initializes
+/// it with its audit PDA as authority. `None` stops auditing.
+#[cfg(feature = "audit-log")]
+pub fn set_audit_tree(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let owner = next_account_info(account_iter)?;
+    
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let config = Option::<(u32, u32)>::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    
+    if !state.is_owner(owner.key) {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    
+    let old = state.audit_tree;
+    state.audit_tree = match config {
+        Some((max_depth, max_buffer_size)) => {
+            let audit = AuditAccounts::parse(program_id, state_account.key, account_iter.as_slice())?;
+            audit.invoke(
+                state_account.key,
+                &init_tree_instruction(audit.merkle_tree.key, audit.authority.key, max_depth, max_buffer_size),
+            )?;
+            Some(*audit.merkle_tree.key)
+        }
+        None => None,
+    };
+    state.save(state_account)?;
+    
+    msg!("Audit tree: {:?} -> {:?}", old, state.audit_tree);
+    Ok(())
+}
+
+/// `dispatch` that commits an audit leaf for the call
+///
+/// Accounts: [diamond_state, module, merkle_tree, audit_authority, compression_program, noop_program, ...facet accounts]
+/// Data: same as `dispatch`
+#[cfg(feature = "audit-log")]
+pub fn dispatch_audited(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let ix_data = Vec::<u8>::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    if accounts.len() < 2 + AUDIT_ACCOUNT_COUNT {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    let (head, rest) = accounts.split_at(2);
+    let (audit_accounts, facet_accounts) = rest.split_at(AUDIT_ACCOUNT_COUNT);
+    
+    let state = DiamondState::load(program_id, &head[0])?;
+    if state.audit_tree.is_none() {
+        msg!("Error: Diamond has no audit tree");
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    let record = AuditRecord {
+        diamond: *head[0].key,
+        slot: Clock::get()?.slot,
+        actor: facet_accounts.iter().find(|a| a.is_signer).map(|a| *a.key).unwrap_or_default(),
+        kind: AuditKind::Dispatch {
+            facet: *head[1].key,
+            payload_hash: hash(&ix_data).to_bytes(),
+        },
+    };
+    
+    let mut routed = head.to_vec();
+    routed.extend_from_slice(facet_accounts);
+    route(program_id, &routed, ix_data, RouteOptions::default())?;
+    commit(program_id, head[0].key, &state, audit_accounts, record)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_leaf_binds_record() {
+        let diamond = Pubkey::new_unique();
+        let actor = Pubkey::new_unique();
+        let cuts = vec![FacetCut::add([1, 0, 0, 0], Pubkey::new_unique(), "counter", "increment")];
+        
+        let record = AuditRecord::cut(diamond, 7, actor, &cuts);
+        assert_eq!(record.leaf(), AuditRecord::cut(diamond, 7, actor, &cuts).leaf());
+        assert_ne!(record.leaf(), AuditRecord::cut(diamond, 8, actor, &cuts).leaf());
+        assert_ne!(record.leaf(), AuditRecord::cut(diamond, 7, actor, &[]).leaf());
+        
+        let (alias, canonical) = (Selector::from([9, 0, 0, 0]), Selector::from([1, 0, 0, 0]));
+        let added = AuditRecord::alias(diamond, 7, actor, alias, Some(canonical));
+        assert_ne!(added.leaf(), AuditRecord::alias(diamond, 7, actor, alias, None).leaf());
+        assert_ne!(added.leaf(), record.leaf());
+        
+        let tree = Pubkey::new_unique();
+        let (authority, _) = audit_authority(&Pubkey::new_unique(), &diamond);
+        let ix = append_instruction(&tree, &authority, record.leaf());
+        assert_eq!(ix.data.len(), 8 + 32);
+        assert_eq!(&ix.data[8..], &record.leaf());
+        assert!(ix.accounts[1].is_signer && ix.accounts[0].is_writable);
+    }
+}
@@ -0,0 +1,247 @@
+/*!
+ * Diamond Authorizer Module
+ * External policy program consulted before cuts and protected calls
+ *
+ * The owner may register an authorizer program. Before every cut, and
+ * before dispatching any selector it protects, the router calls it with
+ * `AUTHORIZE_SELECTOR` followed by a borsh `AuthorizeRequest` (who, doing
+ * what, to which selector), passing only the diamond state, read-only. The
+ * authorizer approves by setting return data to borsh `true`; anything else
+ * (no return data, `false`, a failed CPI) refuses. Policy engines of any
+ * shape plug in this way without forking the router.
+ *
+ * Callers pass the authorizer program among the trailing accounts. Cut
+ * proposals are authorized when proposed, with the proposer as actor; a
+ * dispatch's actor is the relayed user, else its first signer.
+ */
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    msg,
+    program::{get_return_data, invoke},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::builtins::AUTHORIZE_SELECTOR;
+use crate::diamond_cut::{FacetCut, FacetCutAction};
+use crate::diamond_state::{Authorizer, DiamondState};
+use crate::error::DiamondError;
+use crate::events::DiamondEvent;
+use crate::selector::Selector;
+
+/// What the actor is about to do with the selector
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuthAction {
+    Dispatch,
+    Add,
+    Replace,
+    Remove,
+}
+
+impl From<FacetCutAction> for AuthAction {
+    fn from(action: FacetCutAction) -> Self {
+        match action {
+            FacetCutAction::Add => AuthAction::Add,
+            FacetCutAction::Replace => AuthAction::Replace,
+            FacetCutAction::Remove => AuthAction::Remove,
+        }
+    }
+}
+
+/// Arguments of the authorizer call (after `AUTHORIZE_SELECTOR`)
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AuthorizeRequest {
+    pub diamond: Pubkey,
+    pub actor: Pubkey,
+    pub action: AuthAction,
+    pub selector: Selector,
+}
+
+impl AuthorizeRequest {
+    /// Instruction data of the authorizer CPI
+    pub fn instruction_data(&self) -> Vec<u8> {
+        let mut data = AUTHORIZE_SELECTOR.to_vec();
+        data.extend_from_slice(&borsh::to_vec(self).expect("fixed-size request"));
+        data
+    }
+}
+
+/// Whether return data left by a call to `authorizer` approves
+pub fn approved(authorizer: &Pubkey, return_data: Option<(Pubkey, Vec<u8>)>) -> bool {
+    match return_data {
+        Some((program, data)) if &program == authorizer => bool::try_from_slice(&data).unwrap_or(false),
+        _ => false,
+    }
+}
+
+/// Actor of a dispatch: the relayed user, else the first signer
+pub fn dispatch_actor(relayed_user: Option<&Pubkey>, accounts: &[AccountInfo]) -> Pubkey {
+    relayed_user
+        .copied()
+        .or_else(|| accounts.iter().find(|a| a.is_signer).map(|a| *a.key))
+        .unwrap_or_default()
+}
+
+/// Ask the authorizer whether `actor` may perform `action` on `selector`
+///
+/// `accounts` must include the authorizer program.
+pub fn authorize<'info>(
+    state_account: &AccountInfo<'info>,
+    authorizer: &Authorizer,
+    accounts: &[AccountInfo<'info>],
+    actor: &Pubkey,
+    action: AuthAction,
+    selector: Selector,
+) -> ProgramResult {
+    let program = accounts.iter().find(|a| a.key == &authorizer.program).ok_or_else(|| {
+        msg!("Error: Authorizer {} not among the accounts", authorizer.program);
+        ProgramError::NotEnoughAccountKeys
+    })?;
+    let request = AuthorizeRequest { diamond: *state_account.key, actor: *actor, action, selector };
+    invoke(
+        &Instruction {
+            program_id: authorizer.program,
+            accounts: vec![AccountMeta::new_readonly(*state_account.key, false)],
+            data: request.instruction_data(),
+        },
+        &[state_account.clone(), program.clone()],
+    )?;
+    if !approved(&authorizer.program, get_return_data()) {
+        msg!("Error: Authorizer refused {:?} {:?} by {}", action, selector, actor);
+        return Err(DiamondError::AuthorizerRejected.into());
+    }
+    Ok(())
+}
+
+/// Authorize every cut of a batch, when the diamond has an authorizer
+pub fn authorize_cuts<'info>(
+    state_account: &AccountInfo<'info>,
+    state: &DiamondState,
+    accounts: &[AccountInfo<'info>],
+    actor: &Pubkey,
+    cuts: &[FacetCut],
+) -> ProgramResult {
+    if let Some(authorizer) = &state.authorizer {
+        for cut in cuts {
+            authorize(state_account, authorizer, accounts, actor, cut.action.into(), cut.selector)?;
+        }
+    }
+    Ok(())
+}
+
+/// Set, change or clear the authorizer (owner only)
+///
+/// Accounts: [diamond_state, owner]
+/// Data: Option<Authorizer> (`None` removes it)
+pub fn set_authorizer(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let owner = next_account_info(account_iter)?;
+    
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let authorizer = Option::<Authorizer>::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    if !state.is_owner(owner.key) {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    if let Some(authorizer) = &authorizer {
+        if &authorizer.program == program_id {
+            msg!("Error: The router cannot authorize itself");
+            return Err(DiamondError::SelfDispatchForbidden.into());
+        }
+        if authorizer.protected.len() > Authorizer::MAX_PROTECTED {
+            msg!("Error: At most {} selectors can be protected", Authorizer::MAX_PROTECTED);
+            return Err(DiamondError::SelectorCapacityExceeded.into());
+        }
+        if let Some(selector) = authorizer.protected.iter().find(|s| state.get_mapping(**s).is_none()) {
+            msg!("Error: Selector {:?} not found", selector);
+            return Err(DiamondError::ModuleNotFound.into());
+        }
+    }
+    state.authorizer = authorizer.clone();
+    state.save(state_account)?;
+    
+    match &authorizer {
+        Some(authorizer) => msg!("Authorizer: {} ({} protected selectors)", authorizer.program, authorizer.protected.len()),
+        None => msg!("Authorizer removed"),
+    }
+    DiamondEvent::AuthorizerChanged { authorizer }.emit();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_program::clock::Epoch;
+    
+    #[test]
+    fn test_request_encoding() {
+        let request = AuthorizeRequest {
+            diamond: Pubkey::new_unique(),
+            actor: Pubkey::new_unique(),
+            action: AuthAction::Replace,
+            selector: Selector::from([1, 0, 0, 1]),
+        };
+        let data = request.instruction_data();
+        assert_eq!(data[..4], AUTHORIZE_SELECTOR);
+        assert_eq!(AuthorizeRequest::try_from_slice(&data[4..]).unwrap(), request);
+        assert_eq!(AuthAction::from(FacetCutAction::Remove), AuthAction::Remove);
+    }
+    
+    #[test]
+    fn test_only_the_authorizer_approves() {
+        let authorizer = Pubkey::new_unique();
+        assert!(approved(&authorizer, Some((authorizer, vec![1]))));
+        assert!(!approved(&authorizer, Some((authorizer, vec![0]))));
+        assert!(!approved(&authorizer, Some((authorizer, vec![]))));
+        assert!(!approved(&authorizer, Some((authorizer, vec![1, 0]))));
+        // Return data left behind by another program doesn't count
+        assert!(!approved(&authorizer, Some((Pubkey::new_unique(), vec![1]))));
+        assert!(!approved(&authorizer, None));
+    }
+    
+    #[test]
+    fn test_dispatch_actor() {
+        let (user, signer, other) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let owner = Pubkey::default();
+        let (mut other_lamports, mut signer_lamports) = (0, 0);
+        let (mut other_data, mut signer_data) = (vec![], vec![]);
+        let accounts = [
+            AccountInfo::new(&other, false, true, &mut other_lamports, &mut other_data, &owner, false, Epoch::default()),
+            AccountInfo::new(&signer, true, true, &mut signer_lamports, &mut signer_data, &owner, false, Epoch::default()),
+        ];
+        assert_eq!(dispatch_actor(None, &accounts), signer);
+        assert_eq!(dispatch_actor(Some(&user), &accounts), user);
+        assert_eq!(dispatch_actor(None, &accounts[..1]), Pubkey::default());
+    }
+    
+    #[test]
+    fn test_no_authorizer_no_call() {
+        let key = Pubkey::new_unique();
+        let (mut lamports, mut data) = (0, vec![]);
+        let state_account = AccountInfo::new(&key, false, true, &mut lamports, &mut data, &key, false, Epoch::default());
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        let cuts = [FacetCut::remove([1, 0, 0, 1])];
+        assert_eq!(authorize_cuts(&state_account, &state, &[], &key, &cuts), Ok(()));
+        
+        // With one set, its program account must be passed
+        state.authorizer = Some(Authorizer { program: Pubkey::new_unique(), protected: vec![] });
+        assert_eq!(
+            authorize_cuts(&state_account, &state, &[], &key, &cuts),
+            Err(ProgramError::NotEnoughAccountKeys)
+        );
+    }
+}
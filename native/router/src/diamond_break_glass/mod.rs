@@ -0,0 +1,229 @@
+/*!
+ * Diamond Break-Glass Module
+ * Timelocked governance override of a single route
+ *
+ * A mapping flagged immutable by mistake, or pointing at a facet that can no
+ * longer be fixed, would otherwise stay routed for good. The governance key
+ * (typically a multisig) can schedule an override that re-points one
+ * selector to another facet or removes it, immutable or not. It may be
+ * executed, again by governance only, once `BREAK_GLASS_DELAY_SLOTS` (or the
+ * diamond's authority delay, if longer) have passed; until then the owner,
+ * the guardian or governance can cancel it. Scheduling replaces any pending
+ * override and restarts the clock. Every step logs a `BREAK GLASS` line and
+ * emits an event, so monitors can page whoever should object.
+ *
+ * Overrides go through while cuts are paused, since they are meant for
+ * outages, and leave a re-pointed mapping mutable so the routing can be
+ * settled with ordinary cuts afterwards.
+ */
+
+use borsh::BorshDeserialize;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    sysvar::Sysvar,
+};
+
+use crate::diamond_audit;
+use crate::diamond_cut::{apply_cut, FacetCut};
+use crate::diamond_digest;
+use crate::diamond_index;
+use crate::diamond_state::{BreakGlass, DiamondState};
+use crate::error::DiamondError;
+use crate::events::DiamondEvent;
+use crate::selector::Selector;
+
+/// Shortest timelock of an override (~7 days of slots)
+pub const BREAK_GLASS_DELAY_SLOTS: u64 = 1_512_000;
+
+/// Refuse anyone but the configured governance key
+fn check_governance(state: &DiamondState, authority: &AccountInfo) -> ProgramResult {
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    let governance = state.governance.ok_or(DiamondError::GovernanceNotConfigured)?;
+    if authority.key != &governance {
+        msg!("Error: Only governance {} may break glass", governance);
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    Ok(())
+}
+
+/// Override of `selector` scheduled at `slot`
+pub fn schedule(state: &DiamondState, selector: Selector, module: Option<Pubkey>, slot: u64) -> BreakGlass {
+    let delay = BREAK_GLASS_DELAY_SLOTS.max(state.authority_delay_slots);
+    BreakGlass { selector, module, effective_slot: slot.saturating_add(delay) }
+}
+
+/// Apply `pending` to the routing table, returning the module it displaced
+/// and the cut that was applied
+pub fn apply_break_glass(state: &mut DiamondState, pending: &BreakGlass) -> Result<(Pubkey, FacetCut), DiamondError> {
+    let mut next = state.clone();
+    let mapping = next.selectors.iter_mut().find(|m| m.selector == pending.selector).ok_or_else(|| {
+        msg!("Error: Selector {:?} not found", pending.selector);
+        DiamondError::ModuleNotFound
+    })?;
+    let displaced = mapping.module;
+    mapping.is_immutable = false;
+    let cut = match pending.module {
+        Some(module) => {
+            // A facet new to the diamond takes over the displaced one's name
+            let name = next.active_modules.iter().find(|m| m.address == displaced).map(|m| m.name_as_str().to_string());
+            FacetCut {
+                standard_accounts: mapping.standard_accounts,
+                ..FacetCut::replace(pending.selector, module, name.as_deref().unwrap_or_default())
+            }
+        }
+        None => FacetCut::remove(pending.selector),
+    };
+    apply_cut(&mut next, &cut)?;
+    next.break_glass = None;
+    *state = next;
+    Ok((displaced, cut))
+}
+
+/// Schedule an override of one route (governance only)
+///
+/// Accounts: [diamond_state, governance]
+/// Data: selector (Selector), module (Option<Pubkey>; `None` removes the selector)
+pub fn schedule_break_glass(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let authority = next_account_info(account_iter)?;
+    
+    let (selector, module) = <(Selector, Option<Pubkey>)>::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    check_governance(&state, authority)?;
+    if module.as_ref() == Some(program_id) {
+        return Err(DiamondError::SelfDispatchForbidden.into());
+    }
+    if state.get_mapping(selector).is_none() {
+        msg!("Error: Selector {:?} not found", selector);
+        return Err(DiamondError::ModuleNotFound.into());
+    }
+    
+    let pending = schedule(&state, selector, module, Clock::get()?.slot);
+    if let Some(replaced) = state.break_glass.replace(pending) {
+        msg!("BREAK GLASS: replacing the override of {:?} scheduled for slot {}", replaced.selector, replaced.effective_slot);
+    }
+    state.save(state_account)?;
+    
+    msg!(
+        "BREAK GLASS: {:?} -> {:?} scheduled by governance, executable from slot {}",
+        selector,
+        module,
+        pending.effective_slot
+    );
+    DiamondEvent::BreakGlassScheduled { selector, module, effective_slot: pending.effective_slot }.emit();
+    Ok(())
+}
+
+/// Carry out the scheduled override once its timelock has passed (governance only)
+///
+/// Accounts: [diamond_state, governance, (routing digest), (selector index), (audit accounts)]
+pub fn execute_break_glass(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let authority = next_account_info(account_iter)?;
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    check_governance(&state, authority)?;
+    let pending = match state.break_glass {
+        Some(pending) if Clock::get()?.slot >= pending.effective_slot => pending,
+        Some(pending) => {
+            msg!("Error: Override of {:?} executable from slot {}", pending.selector, pending.effective_slot);
+            return Err(DiamondError::AuthorityChangeNotReady.into());
+        }
+        None => return Err(DiamondError::AuthorityChangeNotReady.into()),
+    };
+    let (displaced, cut) = apply_break_glass(&mut state, &pending)?;
+    
+    state.save(state_account)?;
+    let index_accounts = diamond_digest::refresh(program_id, state_account.key, &state, account_iter.as_slice())?;
+    let audit_accounts = diamond_index::refresh(program_id, state_account.key, &state, index_accounts)?;
+    diamond_audit::commit_cuts(program_id, state_account, &state, audit_accounts, authority.key, &[cut])?;
+    
+    msg!("BREAK GLASS: {:?} moved from {} to {:?}", pending.selector, displaced, pending.module);
+    DiamondEvent::BreakGlassExecuted { selector: pending.selector, old: displaced, new: pending.module }.emit();
+    Ok(())
+}
+
+/// Drop the scheduled override
+///
+/// Accounts: [diamond_state, authority]
+/// The guardian (pause authority), governance or owner may cancel.
+pub fn cancel_break_glass(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let authority = next_account_info(account_iter)?;
+    
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    if !state.can_veto(authority.key) {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    let pending = state.break_glass.take().ok_or(DiamondError::AuthorityChangeNotReady)?;
+    state.save(state_account)?;
+    
+    msg!("BREAK GLASS: override of {:?} cancelled by {}", pending.selector, authority.key);
+    DiamondEvent::BreakGlassCancelled { selector: pending.selector, by: *authority.key }.emit();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diamond_cut::apply_cuts;
+    use crate::diamond_state::DEFAULT_AUTHORITY_DELAY_SLOTS;
+    
+    #[test]
+    fn test_override_reaches_immutable_routes() {
+        let (broken, fixed) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        apply_cuts(&mut state, &[
+            FacetCut { is_immutable: true, ..FacetCut::add([1, 0, 0, 1], broken, "vault", "withdraw") },
+            FacetCut { is_immutable: true, ..FacetCut::add([1, 0, 0, 2], broken, "vault", "deposit") },
+        ])
+        .unwrap();
+        
+        let pending = schedule(&state, [1, 0, 0, 1].into(), Some(fixed), 100);
+        assert_eq!(pending.effective_slot, 100 + BREAK_GLASS_DELAY_SLOTS);
+        state.authority_delay_slots = BREAK_GLASS_DELAY_SLOTS * 2;
+        assert_eq!(schedule(&state, [1, 0, 0, 1].into(), None, 0).effective_slot, BREAK_GLASS_DELAY_SLOTS * 2);
+        state.authority_delay_slots = DEFAULT_AUTHORITY_DELAY_SLOTS;
+        
+        state.break_glass = Some(pending);
+        let version = state.config_version;
+        assert_eq!(apply_break_glass(&mut state, &pending).unwrap().0, broken);
+        let mapping = state.get_mapping([1, 0, 0, 1]).unwrap();
+        assert_eq!((mapping.module, mapping.is_immutable), (fixed, false));
+        assert_eq!(state.active_modules.last().unwrap().name_as_str(), "vault");
+        assert_eq!((state.break_glass, state.config_version), (None, version + 1));
+        
+        let removal = schedule(&state, [1, 0, 0, 2].into(), None, 0);
+        apply_break_glass(&mut state, &removal).unwrap();
+        assert!(state.get_mapping([1, 0, 0, 2]).is_none());
+        assert_eq!(apply_break_glass(&mut state, &removal), Err(DiamondError::ModuleNotFound));
+    }
+}
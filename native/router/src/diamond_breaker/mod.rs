@@ -0,0 +1,223 @@
+/*!
+ * Diamond Breaker Module
+ * Circuit breakers pausing a selector on anomalous traffic
+ *
+ * The owner can give a selector a breaker per metric: more than `threshold`
+ * events within `window_slots` trips it. A tripped breaker stops the
+ * selector from dispatching (built-ins and other selectors keep working),
+ * logs an `ALERT` line and emits `CircuitBreakerTripped`, so an exploit
+ * draining a facet is cut off without waiting for someone to pause by hand.
+ * The owner re-arms a breaker by setting it again.
+ *
+ * Counts only survive in transactions that succeed. `Calls` counts every
+ * successful facet call; `Refusals` counts the calls `try_dispatch` skipped
+ * because the router refused them, which commit like any other success. A
+ * facet that fails aborts its transaction, counter update included, so its
+ * failures can't be counted.
+ */
+
+use borsh::BorshDeserialize;
+use diamond_core::routing;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::diamond_state::{BreakerMetric, CircuitBreaker, DiamondState, DispatchState, SelectorMapping};
+use crate::error::DiamondError;
+use crate::events::DiamondEvent;
+use crate::selector::Selector;
+
+/// Count a `metric` event of `selector` at `slot`, saving the state when a
+/// breaker counted it
+pub fn record(
+    state: &mut DispatchState,
+    state_account: &AccountInfo,
+    selector: Selector,
+    metric: BreakerMetric,
+    slot: u64,
+) -> ProgramResult {
+    let Some(breaker) = state.circuit_breakers.iter_mut().find(|b| b.selector == selector && b.metric == metric) else {
+        return Ok(());
+    };
+    let tripped = breaker.record(slot);
+    let (count, window_slots) = (breaker.count, breaker.window_slots);
+    state.save(state_account)?;
+    
+    if tripped {
+        msg!("ALERT: {:?} paused by its circuit breaker ({} {:?} within {} slots)", selector, count, metric, window_slots);
+        DiamondEvent::CircuitBreakerTripped { selector, metric, count, window_slots }.emit();
+    }
+    Ok(())
+}
+
+/// Count a call `try_dispatch` skipped at `slot` as a `Refusals` event of
+/// its selector
+///
+/// Calls that don't resolve to a routed selector, or that re-enter with the
+/// state read-only, aren't counted.
+pub fn record_refusal(program_id: &Pubkey, accounts: &[AccountInfo], ix_data: &[u8], slot: u64) -> ProgramResult {
+    let Some(state_account) = accounts.first().filter(|a| a.is_writable && a.owner == program_id) else {
+        return Ok(());
+    };
+    let selector = {
+        let data = state_account.try_borrow_data()?;
+        routing::state_header(&data)
+            .and_then(|header| Selector::read(ix_data, header.selector_width))
+            .and_then(|selector| routing::resolve_mapping(&data, &selector))
+            .and_then(|raw| SelectorMapping::try_from_slice(raw).ok())
+            .map(|mapping| mapping.selector)
+    };
+    let Some(selector) = selector else {
+        return Ok(());
+    };
+    let Ok(mut state) = DispatchState::load(program_id, state_account) else {
+        return Ok(());
+    };
+    if state.circuit_breakers.is_empty() {
+        return Ok(());
+    }
+    record(&mut state, state_account, selector, BreakerMetric::Refusals, slot)
+}
+
+/// Set, re-arm or remove a selector's circuit breaker for one metric (owner only)
+///
+/// Accounts: [diamond_state, owner, approvers...]
+/// Data: selector (Selector), metric (BreakerMetric), threshold (u32),
+/// window_slots (u64); a zero threshold removes the breaker
+///
+/// Re-arming or removing a tripped breaker lifts its pause, so it needs the
+/// unpause quorum.
+pub fn set_circuit_breaker(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let owner = next_account_info(account_iter)?;
+    
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let (selector, metric, threshold, window_slots) = <(Selector, BreakerMetric, u32, u64)>::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    if !state.is_owner(owner.key) {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    if state.get_mapping(selector).is_none() {
+        msg!("Error: Selector {:?} not found", selector);
+        return Err(DiamondError::ModuleNotFound.into());
+    }
+    let lifts_pause = state
+        .circuit_breakers
+        .iter()
+        .any(|b| b.selector == selector && b.metric == metric && b.tripped_slot.is_some());
+    if lifts_pause {
+        state.check_unpause_quorum(accounts.iter().filter(|a| a.is_signer).map(|a| a.key))?;
+    }
+    state.circuit_breakers.retain(|b| b.selector != selector || b.metric != metric);
+    if threshold > 0 {
+        if window_slots == 0 {
+            msg!("Error: A circuit breaker needs a window of at least one slot");
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        if state.circuit_breakers.len() >= DiamondState::MAX_CIRCUIT_BREAKERS {
+            msg!("Error: At most {} circuit breakers can be set", DiamondState::MAX_CIRCUIT_BREAKERS);
+            return Err(DiamondError::SelectorCapacityExceeded.into());
+        }
+        state.circuit_breakers.push(CircuitBreaker {
+            selector,
+            metric,
+            threshold,
+            window_slots,
+            window_start: 0,
+            count: 0,
+            tripped_slot: None,
+        });
+    }
+    state.save(state_account)?;
+    
+    match threshold {
+        0 => msg!("Selector {:?} {:?} breaker removed", selector, metric),
+        _ => msg!("Selector {:?} pauses after {} {:?} within {} slots", selector, threshold, metric, window_slots),
+    }
+    DiamondEvent::CircuitBreakerChanged { selector, metric, threshold, window_slots }.emit();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_breaker_trips_past_threshold_within_window() {
+        let mut breaker = CircuitBreaker {
+            selector: [1, 0, 0, 1].into(),
+            metric: BreakerMetric::Calls,
+            threshold: 2,
+            window_slots: 10,
+            window_start: 0,
+            count: 0,
+            tripped_slot: None,
+        };
+        assert!(!breaker.record(100));
+        assert!(!breaker.record(105));
+        // A new window starts the count over
+        assert!(!breaker.record(110));
+        assert_eq!((breaker.window_start, breaker.count), (110, 1));
+        assert!(!breaker.record(111));
+        assert!(breaker.record(119));
+        assert_eq!(breaker.tripped_slot, Some(119));
+        
+        // Tripped breakers stay tripped until re-armed
+        assert!(!breaker.record(500));
+        assert_eq!(breaker.count, 3);
+        
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        state.circuit_breakers.push(breaker);
+        assert_eq!(state.tripped_breaker(&[1, 0, 0, 1].into()), Some(&breaker));
+        assert_eq!(state.tripped_breaker(&[1, 0, 0, 2].into()), None);
+    }
+    
+    #[test]
+    fn test_refusals_count_against_the_routed_selector() {
+        use crate::diamond_cut::{apply_cuts, FacetCut};
+        use crate::diamond_state::diamond_address;
+        use solana_program::clock::Epoch;
+        
+        let (program_id, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (address, bump) = diamond_address(&program_id, &owner, None);
+        let mut state = DiamondState::new(owner, bump);
+        apply_cuts(&mut state, &[FacetCut::add([1, 0, 0, 1], Pubkey::new_unique(), "vault", "withdraw")]).unwrap();
+        state.circuit_breakers.push(CircuitBreaker {
+            selector: [1, 0, 0, 1].into(),
+            metric: BreakerMetric::Refusals,
+            threshold: 1,
+            window_slots: 10,
+            window_start: 0,
+            count: 0,
+            tripped_slot: None,
+        });
+        let (mut lamports, mut data) = (0, borsh::to_vec(&state).unwrap());
+        data.resize(DiamondState::SPACE, 0);
+        let account = AccountInfo::new(&address, false, true, &mut lamports, &mut data, &program_id, false, Epoch::default());
+        let accounts = [account];
+        let refuse = |ix_data: &[u8]| record_refusal(&program_id, &accounts, ix_data, 100).unwrap();
+        let breaker = |accounts: &[AccountInfo]| DispatchState::load(&program_id, &accounts[0]).unwrap().circuit_breakers[0];
+        
+        // Unrouted selectors aren't counted
+        refuse(&[9, 9, 9, 9]);
+        assert_eq!(breaker(&accounts).count, 0);
+        refuse(&[1, 0, 0, 1, 7]);
+        assert_eq!(breaker(&accounts).tripped_slot, None);
+        refuse(&[1, 0, 0, 1]);
+        assert_eq!(breaker(&accounts).tripped_slot, Some(100));
+    }
+}
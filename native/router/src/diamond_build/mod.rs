@@ -0,0 +1,226 @@
+/*!
+ * Diamond Build Module
+ * Attested build hashes for facets
+ *
+ * The owner attests the hash of a facet's verifiable build, as printed by
+ * `solana-verify get-executable-hash` (SHA-256 of the program ELF without
+ * trailing zero padding). `build::verify_facet_build` is permissionless: it
+ * hashes the facet's deployed program data and records whether it matched
+ * and at which deploy slot, so a later upgrade leaves the result stale.
+ * Hashing costs roughly one compute unit per two bytes of program, so
+ * large facets need a raised compute budget. Attestations are kept in the
+ * facet's record (`diamond_record`).
+ */
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    entrypoint::ProgramResult,
+    hash::hashv,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::diamond_loupe::return_borsh;
+use crate::diamond_record::FacetRecord;
+use crate::diamond_state::{BuildAttestation, BuildCheck, DiamondState};
+use crate::error::DiamondError;
+use crate::events::DiamondEvent;
+
+/// Loader state tags (`UpgradeableLoaderState` variant index, bincode u32)
+const PROGRAM_TAG: [u8; 4] = [2, 0, 0, 0];
+const PROGRAM_DATA_TAG: [u8; 4] = [3, 0, 0, 0];
+
+/// Where a facet's build stands against its attestation
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BuildStatus {
+    /// No hash attested for the facet
+    Unattested,
+    /// Attested but never verified
+    Unchecked,
+    /// Deployed program matched the attested hash
+    Verified,
+    /// Deployed program did not match the attested hash
+    Mismatch,
+    /// Program upgraded since the last verification
+    Stale,
+}
+
+/// Status of a facet currently deployed at `deploy_slot`
+pub fn build_status(attestation: Option<&BuildAttestation>, deploy_slot: u64) -> BuildStatus {
+    match attestation.map(|a| a.last_check) {
+        None => BuildStatus::Unattested,
+        Some(None) => BuildStatus::Unchecked,
+        Some(Some(check)) if check.deploy_slot != deploy_slot => BuildStatus::Stale,
+        Some(Some(check)) if check.matched => BuildStatus::Verified,
+        Some(Some(_)) => BuildStatus::Mismatch,
+    }
+}
+
+/// Hash of a program ELF as `solana-verify` computes it
+pub fn executable_hash(elf: &[u8]) -> [u8; 32] {
+    let end = elf.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+    hashv(&[&elf[..end]]).to_bytes()
+}
+
+/// Program data address named by an upgradeable program account's data
+pub fn programdata_address(program_data: &[u8]) -> Option<Pubkey> {
+    if program_data.get(..4)? != PROGRAM_TAG {
+        return None;
+    }
+    Pubkey::try_from(program_data.get(4..36)?).ok()
+}
+
+/// Deploy slot and ELF held by a program data account's data
+pub fn deployed_executable(programdata: &[u8]) -> Option<(u64, &[u8])> {
+    if programdata.get(..4)? != PROGRAM_DATA_TAG {
+        return None;
+    }
+    let slot = u64::from_le_bytes(programdata.get(4..12)?.try_into().ok()?);
+    Some((slot, programdata.get(UpgradeableLoaderState::size_of_programdata_metadata()..)?))
+}
+
+/// Check `programdata` belongs to the upgradeable program `module`; returns
+/// the deploy slot
+pub(crate) fn deploy_slot(module: &AccountInfo, programdata: &AccountInfo) -> Result<u64, ProgramError> {
+    if module.owner != &bpf_loader_upgradeable::id() || programdata.owner != &bpf_loader_upgradeable::id() {
+        msg!("Error: {} is not an upgradeable program", module.key);
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if programdata_address(&module.try_borrow_data()?) != Some(*programdata.key) {
+        msg!("Error: {} is not the program data of {}", programdata.key, module.key);
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let data = programdata.try_borrow_data()?;
+    deployed_executable(&data).map(|(slot, _)| slot).ok_or(ProgramError::InvalidAccountData)
+}
+
+/// Attest (or, with `None`, clear) a facet's build hash (owner only)
+///
+/// Accounts: [diamond_state, owner, facet_record, payer, system_program]
+/// Data: module (Pubkey), hash (Option<[u8; 32]>)
+///
+/// A new hash resets the facet to `Unchecked`. The facet needn't be routed
+/// yet: its version is declared against a verified attestation before its
+/// first cut.
+pub fn attest_facet_build(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let owner = next_account_info(account_iter)?;
+    let record_account = next_account_info(account_iter)?;
+    let payer = next_account_info(account_iter)?;
+    let system_program_account = next_account_info(account_iter)?;
+    
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let (module, hash) = <(Pubkey, Option<[u8; 32]>)>::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let state = DiamondState::load(program_id, state_account)?;
+    if !state.is_owner(owner.key) {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    let mut record = FacetRecord::open(program_id, record_account, payer, system_program_account, state_account.key, &module)?;
+    record.attestation = hash.map(|hash| BuildAttestation { module, hash, last_check: None });
+    record.save(record_account)?;
+    
+    msg!("Build hash for {} {}", module, if hash.is_some() { "attested" } else { "cleared" });
+    DiamondEvent::FacetBuildAttested { module, hash }.emit();
+    Ok(())
+}
+
+/// Hash a facet's deployed program and record the result (anyone)
+///
+/// Accounts: [diamond_state, module, module_program_data, facet_record]
+/// Returns the new `BuildStatus` via return data.
+///
+/// A mismatch is recorded rather than failing the call, so it stays visible
+/// in the loupe until the facet is redeployed or re-attested.
+pub fn verify_facet_build(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let module = next_account_info(account_iter)?;
+    let programdata = next_account_info(account_iter)?;
+    let record_account = next_account_info(account_iter)?;
+    
+    if state_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let deploy_slot = deploy_slot(module, programdata)?;
+    let hash = {
+        let data = programdata.try_borrow_data()?;
+        let (_, elf) = deployed_executable(&data).ok_or(ProgramError::InvalidAccountData)?;
+        executable_hash(elf)
+    };
+    
+    let mut record = FacetRecord::load(program_id, record_account, state_account.key, module.key)?
+        .filter(|r| r.attestation.is_some())
+        .ok_or_else(|| {
+            msg!("Error: No build hash attested for {}", module.key);
+            DiamondError::BuildNotAttested
+        })?;
+    let attestation = record.attestation.as_mut().ok_or(DiamondError::BuildNotAttested)?;
+    let matched = attestation.hash == hash;
+    attestation.last_check = Some(BuildCheck { deploy_slot, matched });
+    let status = build_status(Some(attestation), deploy_slot);
+    record.save(record_account)?;
+    
+    msg!("Build of {} (deployed at slot {}): {:?}", module.key, deploy_slot, status);
+    DiamondEvent::FacetBuildChecked { module: *module.key, deploy_slot, matched }.emit();
+    return_borsh(&status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_build_status() {
+        let mut attestation = BuildAttestation { module: Pubkey::new_unique(), hash: [1; 32], last_check: None };
+        assert_eq!(build_status(None, 10), BuildStatus::Unattested);
+        assert_eq!(build_status(Some(&attestation), 10), BuildStatus::Unchecked);
+        
+        attestation.last_check = Some(BuildCheck { deploy_slot: 10, matched: true });
+        assert_eq!(build_status(Some(&attestation), 10), BuildStatus::Verified);
+        // Upgraded since
+        assert_eq!(build_status(Some(&attestation), 11), BuildStatus::Stale);
+        
+        attestation.last_check = Some(BuildCheck { deploy_slot: 10, matched: false });
+        assert_eq!(build_status(Some(&attestation), 10), BuildStatus::Mismatch);
+    }
+    
+    #[test]
+    fn test_loader_accounts_and_hash() {
+        let programdata_key = Pubkey::new_unique();
+        let mut program = PROGRAM_TAG.to_vec();
+        program.extend_from_slice(programdata_key.as_ref());
+        assert_eq!(programdata_address(&program), Some(programdata_key));
+        assert_eq!(programdata_address(&program[..20]), None);
+        
+        let elf = b"\x7fELF program".to_vec();
+        let mut programdata = PROGRAM_DATA_TAG.to_vec();
+        programdata.extend_from_slice(&42u64.to_le_bytes());
+        programdata.extend_from_slice(&[1; 33]); // Some(upgrade authority)
+        programdata.extend_from_slice(&elf);
+        programdata.resize(programdata.len() + 100, 0);
+        
+        let (slot, deployed) = deployed_executable(&programdata).unwrap();
+        assert_eq!(slot, 42);
+        // Zero padding past the ELF doesn't change the hash
+        assert_eq!(executable_hash(deployed), executable_hash(&elf));
+        assert_ne!(executable_hash(deployed), executable_hash(b"\x7fELF other"));
+        assert_eq!(deployed_executable(&program), None);
+    }
+}
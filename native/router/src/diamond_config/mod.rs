@@ -0,0 +1,416 @@
+/*!
+ * Diamond Config Module
+ * First-party key/value config store served as router built-ins
+ *
+ * Entries live in router-owned PDAs seeded by diamond, namespace and key, so
+ * any facet can read parameters (fees, limits) with a single account load.
+ * Writes are gated on owner/admin authority. Facet account schemas (see
+ * `schema`) are published the same way, and each facet's immutable config
+ * blob sits in a PDA of its own.
+ */
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    system_program,
+    sysvar::Sysvar,
+};
+
+use crate::diamond_loupe::return_borsh;
+use crate::diamond_state::{fixed_str, namespace_bytes, DiamondState};
+use crate::error::DiamondError;
+use crate::events::DiamondEvent;
+
+pub mod schema;
+
+pub use diamond_constants::seeds::CONFIG_SEED;
+pub use diamond_core::facet_config::{FacetConfig, FACET_CONFIG_SEED, MAX_FACET_CONFIG_LEN};
+
+/// Single namespaced config value
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct ConfigEntry {
+    pub diamond: Pubkey,
+    pub namespace: [u8; 8],
+    pub key: [u8; 32],
+    pub value: Vec<u8>,
+    pub updated_by: Pubkey,
+    pub bump: u8,
+}
+
+impl ConfigEntry {
+    pub const MAX_VALUE_LEN: usize = 256;
+    
+    pub const SPACE: usize =
+        32 + // diamond
+        8 +  // namespace
+        32 + // key
+        4 + Self::MAX_VALUE_LEN + // value
+        32 + // updated_by
+        1;   // bump
+    
+    pub fn find_address(
+        program_id: &Pubkey,
+        diamond: &Pubkey,
+        namespace: &[u8; 8],
+        key: &[u8; 32],
+    ) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[CONFIG_SEED, diamond.as_ref(), namespace, key],
+            program_id,
+        )
+    }
+    
+    pub fn key_as_str(&self) -> &str {
+        fixed_str(&self.key)
+    }
+}
+
+/// Load a config entry of `diamond`, checking its owner and its address
+/// against the stored seeds and bump
+pub fn load(program_id: &Pubkey, diamond: &Pubkey, account: &AccountInfo) -> Result<ConfigEntry, ProgramError> {
+    if account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let entry = ConfigEntry::deserialize(&mut &account.try_borrow_data()?[..])
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    let expected = Pubkey::create_program_address(
+        &[CONFIG_SEED, diamond.as_ref(), &entry.namespace, &entry.key, &[entry.bump]],
+        program_id,
+    );
+    if expected.as_ref() != Ok(account.key) || &entry.diamond != diamond {
+        msg!("Error: {} is not a config entry of {}", account.key, diamond);
+        return Err(ProgramError::InvalidSeeds);
+    }
+    Ok(entry)
+}
+
+/// Pack a config key into its fixed 32-byte form
+pub fn key_bytes(key: &str) -> Result<[u8; 32], DiamondError> {
+    let bytes = key.as_bytes();
+    if bytes.is_empty() || bytes.len() > 32 {
+        return Err(DiamondError::InvalidConfigEntry);
+    }
+    let mut out = [0u8; 32];
+    out[..bytes.len()].copy_from_slice(bytes);
+    Ok(out)
+}
+
+/// Namespace and key as sent in instruction data
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug)]
+pub struct ConfigKey {
+    pub namespace: String,
+    pub key: String,
+}
+
+/// Resolve and verify the entry PDA for a key
+fn entry_address(
+    program_id: &Pubkey,
+    diamond: &Pubkey,
+    entry_account: &AccountInfo,
+    config_key: &ConfigKey,
+) -> Result<([u8; 8], [u8; 32], u8), ProgramError> {
+    let namespace = namespace_bytes(&config_key.namespace).ok_or(DiamondError::InvalidConfigEntry)?;
+    let key = key_bytes(&config_key.key)?;
+    let (expected, bump) = ConfigEntry::find_address(program_id, diamond, &namespace, &key);
+    if entry_account.key != &expected {
+        msg!("Error: Invalid config PDA for {}::{}", config_key.namespace, config_key.key);
+        return Err(ProgramError::InvalidSeeds);
+    }
+    Ok((namespace, key, bump))
+}
+
+/// Create or overwrite a config entry (owner or admin)
+///
+/// Accounts: [diamond_state, entry, authority, payer, system_program]
+pub fn set_config(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let entry_account = next_account_info(account_iter)?;
+    let authority = next_account_info(account_iter)?;
+    let payer = next_account_info(account_iter)?;
+    let system_program_account = next_account_info(account_iter)?;
+    
+    if !authority.is_signer || !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if system_program_account.key != &system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    #[derive(BorshDeserialize)]
+    struct SetConfigData {
+        config_key: ConfigKey,
+        value: Vec<u8>,
+    }
+    
+    let set_data = SetConfigData::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let state = DiamondState::load(program_id, state_account)?;
+    if !state.has_authority(authority.key) {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    if set_data.value.len() > ConfigEntry::MAX_VALUE_LEN {
+        msg!("Error: Config value exceeds {} bytes", ConfigEntry::MAX_VALUE_LEN);
+        return Err(DiamondError::InvalidConfigEntry.into());
+    }
+    
+    let (namespace, key, bump) =
+        entry_address(program_id, state_account.key, entry_account, &set_data.config_key)?;
+    
+    // First write allocates the PDA
+    if entry_account.data_is_empty() {
+        let rent = Rent::get()?;
+        invoke_signed(
+            &system_instruction::create_account(
+                payer.key,
+                entry_account.key,
+                rent.minimum_balance(ConfigEntry::SPACE),
+                ConfigEntry::SPACE as u64,
+                program_id,
+            ),
+            &[payer.clone(), entry_account.clone(), system_program_account.clone()],
+            &[&[CONFIG_SEED, state_account.key.as_ref(), &namespace, &key, &[bump]]],
+        )?;
+    } else if entry_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    let entry = ConfigEntry {
+        diamond: *state_account.key,
+        namespace,
+        key,
+        value: set_data.value,
+        updated_by: *authority.key,
+        bump,
+    };
+    let mut entry_data = entry_account.try_borrow_mut_data()?;
+    entry.serialize(&mut &mut entry_data[..])
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    msg!(
+        "Config set: {}::{} ({} bytes)",
+        set_data.config_key.namespace,
+        set_data.config_key.key,
+        entry.value.len()
+    );
+    Ok(())
+}
+
+/// Delete a config entry, returning its rent (owner or admin)
+///
+/// Accounts: [diamond_state, entry, authority, recipient]
+pub fn delete_config(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let entry_account = next_account_info(account_iter)?;
+    let authority = next_account_info(account_iter)?;
+    let recipient = next_account_info(account_iter)?;
+    
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let config_key = ConfigKey::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let state = DiamondState::load(program_id, state_account)?;
+    if !state.has_authority(authority.key) {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    if entry_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    entry_address(program_id, state_account.key, entry_account, &config_key)?;
+    
+    // Close: drain lamports and zero the data
+    let lamports = entry_account.lamports();
+    **recipient.try_borrow_mut_lamports()? = recipient
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    **entry_account.try_borrow_mut_lamports()? = 0;
+    entry_account.try_borrow_mut_data()?.fill(0);
+    
+    msg!("Config deleted: {}::{}", config_key.namespace, config_key.key);
+    Ok(())
+}
+
+/// Read a config value
+///
+/// Accounts: [diamond_state, entry]
+/// Returns the value bytes (Borsh `Vec<u8>`) via return data.
+pub fn get_config(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let entry_account = next_account_info(account_iter)?;
+    
+    let entry = load(program_id, state_account.key, entry_account)?;
+    
+    msg!("Config get: {} ({} bytes)", entry.key_as_str(), entry.value.len());
+    return_borsh(&entry.value)
+}
+
+/// Address of the config account `diamond` keeps for `facet`
+pub fn facet_config_address(program_id: &Pubkey, diamond: &Pubkey, facet: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[FACET_CONFIG_SEED, diamond.as_ref(), facet.as_ref()], program_id)
+}
+
+/// Config blob for an active facet of `state`
+pub fn facet_config(state: &DiamondState, diamond: &Pubkey, facet: Pubkey, data: Vec<u8>, bump: u8) -> Result<FacetConfig, DiamondError> {
+    if !state.active_modules.iter().any(|m| m.address == facet) {
+        return Err(DiamondError::ModuleNotFound);
+    }
+    if data.len() > MAX_FACET_CONFIG_LEN {
+        return Err(DiamondError::InvalidConfigEntry);
+    }
+    Ok(FacetConfig { diamond: diamond.to_bytes(), facet: facet.to_bytes(), data, bump })
+}
+
+/// Attach a facet's immutable config blob (owner only)
+///
+/// Accounts: [diamond_state, owner, facet_config, payer, system_program]
+/// Data: module (Pubkey), config (Vec<u8>, at most `MAX_FACET_CONFIG_LEN`)
+///
+/// The blob goes into its own account (see `diamond_core::facet_config`),
+/// which the facet reads when callers pass it; the call data is untouched,
+/// so a live facet can be configured without breaking its callers. A
+/// facet's config is set once.
+pub fn set_facet_config(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let owner = next_account_info(account_iter)?;
+    let config_account = next_account_info(account_iter)?;
+    let payer = next_account_info(account_iter)?;
+    let system_program_account = next_account_info(account_iter)?;
+    
+    if !owner.is_signer || !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if system_program_account.key != &system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    let (module, config) = <(Pubkey, Vec<u8>)>::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let state = DiamondState::load(program_id, state_account)?;
+    if !state.is_owner(owner.key) {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    let (expected, bump) = facet_config_address(program_id, state_account.key, &module);
+    if config_account.key != &expected {
+        msg!("Error: Invalid facet config PDA. Expected: {}", expected);
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if !config_account.data_is_empty() {
+        msg!("Error: {} already has a config", module);
+        return Err(DiamondError::FacetConfigImmutable.into());
+    }
+    let facet_config = facet_config(&state, state_account.key, module, config, bump).inspect_err(|e| {
+        msg!("Error: Cannot set the config of {}: {}", module, e);
+    })?;
+    
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            config_account.key,
+            Rent::get()?.minimum_balance(FacetConfig::MAX_ENCODED_LEN),
+            FacetConfig::MAX_ENCODED_LEN as u64,
+            program_id,
+        ),
+        &[payer.clone(), config_account.clone(), system_program_account.clone()],
+        &[&[FACET_CONFIG_SEED, state_account.key.as_ref(), module.as_ref(), &[bump]]],
+    )?;
+    let mut config_data = config_account.try_borrow_mut_data()?;
+    facet_config.serialize(&mut &mut config_data[..])
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    msg!("Facet config for {}: {} bytes", module, facet_config.data.len());
+    DiamondEvent::FacetConfigSet { module, config: facet_config.data }.emit();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_key_bytes_bounds() {
+        assert_eq!(fixed_str(&key_bytes("fee_bps").unwrap()), "fee_bps");
+        assert!(key_bytes("").is_err());
+        assert!(key_bytes(&"k".repeat(33)).is_err());
+    }
+    
+    #[test]
+    fn test_load_checks_address_with_stored_bump() {
+        use solana_program::clock::Epoch;
+        
+        let (program_id, diamond) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (namespace, key) = (namespace_bytes("fees").unwrap(), key_bytes("protocol_fee").unwrap());
+        let (address, bump) = ConfigEntry::find_address(&program_id, &diamond, &namespace, &key);
+        let entry = ConfigEntry { diamond, namespace, key, value: vec![5], updated_by: Pubkey::new_unique(), bump };
+        let load_at = |key: &Pubkey, entry: &ConfigEntry| {
+            let (mut lamports, mut data) = (0, borsh::to_vec(entry).unwrap());
+            let account = AccountInfo::new(key, false, false, &mut lamports, &mut data, &program_id, false, Epoch::default());
+            load(&program_id, &diamond, &account)
+        };
+        assert_eq!(load_at(&address, &entry), Ok(entry.clone()));
+        assert_eq!(load_at(&Pubkey::new_unique(), &entry), Err(ProgramError::InvalidSeeds));
+        
+        // An entry claiming another key doesn't pass at this address
+        let renamed = ConfigEntry { key: key_bytes("admin_fee").unwrap(), ..entry.clone() };
+        assert_eq!(load_at(&address, &renamed), Err(ProgramError::InvalidSeeds));
+    }
+    
+    #[test]
+    fn test_max_entry_fits_space() {
+        let entry = ConfigEntry {
+            diamond: Pubkey::new_unique(),
+            namespace: namespace_bytes("fees").unwrap(),
+            key: key_bytes("protocol_fee").unwrap(),
+            value: vec![0xFF; ConfigEntry::MAX_VALUE_LEN],
+            updated_by: Pubkey::new_unique(),
+            bump: 255,
+        };
+        assert_eq!(borsh::to_vec(&entry).unwrap().len(), ConfigEntry::SPACE);
+    }
+    
+    #[test]
+    fn test_facet_config_needs_an_active_facet() {
+        use crate::diamond_cut::{apply_cuts, FacetCut};
+        
+        let (diamond, module) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        assert_eq!(facet_config(&state, &diamond, module, vec![1], 255), Err(DiamondError::ModuleNotFound));
+        
+        apply_cuts(&mut state, &[FacetCut::add([1, 0, 0, 1], module, "m", "f")]).unwrap();
+        let oversized = vec![0; MAX_FACET_CONFIG_LEN + 1];
+        assert_eq!(facet_config(&state, &diamond, module, oversized, 255), Err(DiamondError::InvalidConfigEntry));
+        let config = facet_config(&state, &diamond, module, vec![1, 2], 255).unwrap();
+        assert_eq!(FacetConfig::of_facet(&borsh::to_vec(&config).unwrap(), &diamond.to_bytes(), &module.to_bytes()), Some(config));
+    }
+}
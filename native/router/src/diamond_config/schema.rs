@@ -0,0 +1,120 @@
+/*!
+ * Account schemas
+ * Per-selector account lists published as config entries
+ *
+ * A facet's accounts are stored under the `accounts` config namespace, keyed
+ * by the selector's hex bytes, as a Borsh `AccountSchema`. Clients use them
+ * to derive PDAs and order the accounts of a call so callers only supply the
+ * logical inputs.
+ *
+ * The router reads them for `dispatch_mapped`: the caller labels each
+ * account with its role, the index of its slot in the schema, and the router
+ * forwards the accounts in slot order. Accounts in the wrong order then stop
+ * being a failure mode; a missing, repeated or unknown role is refused
+ * before the facet runs.
+ */
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::pubkey::Pubkey;
+
+use crate::error::DiamondError;
+use crate::selector::Selector;
+
+/// Config namespace holding account schemas
+pub const ACCOUNT_SCHEMA_NAMESPACE: &str = "accounts";
+
+/// Config key of a selector's schema: its bytes at the diamond's width, in hex
+pub fn account_schema_key(selector: Selector, width: u8) -> String {
+    selector.as_bytes(width).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Program a PDA slot is derived under
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub enum PdaProgram {
+    /// The facet serving the selector
+    Facet,
+    /// The router (e.g. `dispatch_signed` facet signers)
+    Router,
+    Program(Pubkey),
+}
+
+/// One seed of a PDA slot
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub enum SeedSource {
+    Literal(Vec<u8>),
+    DiamondState,
+    Facet,
+    /// Address of an earlier slot
+    Slot(u8),
+    /// Caller-supplied seed bytes (e.g. an id's little-endian bytes)
+    Arg(u8),
+}
+
+/// Where a slot's address comes from
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub enum AccountSource {
+    /// Caller-supplied account, by position
+    Input(u8),
+    Fixed(Pubkey),
+    DiamondState,
+    Pda { program: PdaProgram, seeds: Vec<SeedSource> },
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct AccountSlot {
+    pub source: AccountSource,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+/// Accounts a selector expects after the module account, in order
+///
+/// Standard accounts requested by the selector's flags are not listed; the
+/// resolver appends them.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, Default, PartialEq)]
+pub struct AccountSchema {
+    pub accounts: Vec<AccountSlot>,
+}
+
+/// Role of an account forwarded after the schema's slots, in the order given
+/// (standard accounts, oracle feeds, transfer hook extras)
+pub const ROLE_EXTRA: u8 = u8::MAX;
+
+/// `accounts` in schema order, where `roles[i]` is the slot of `accounts[i]`
+/// (or `ROLE_EXTRA`); every one of the schema's `slots` must be labeled once
+pub fn order_by_role<T: Clone>(slots: usize, roles: &[u8], accounts: &[T]) -> Result<Vec<T>, DiamondError> {
+    if roles.len() != accounts.len() || slots >= ROLE_EXTRA as usize {
+        return Err(DiamondError::AccountRoleMismatch);
+    }
+    let mut ordered: Vec<Option<&T>> = vec![None; slots];
+    let mut extras = Vec::new();
+    for (&role, account) in roles.iter().zip(accounts) {
+        match ordered.get_mut(role as usize) {
+            _ if role == ROLE_EXTRA => extras.push(account),
+            Some(slot @ None) => *slot = Some(account),
+            _ => return Err(DiamondError::AccountRoleMismatch),
+        }
+    }
+    ordered
+        .into_iter()
+        .chain(extras.into_iter().map(Some))
+        .map(|account| account.cloned().ok_or(DiamondError::AccountRoleMismatch))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_order_by_role() {
+        // Slots 0..3 given as 2, extra, 0, 1
+        assert_eq!(order_by_role(3, &[2, ROLE_EXTRA, 0, 1], &['c', 'x', 'a', 'b']), Ok(vec!['a', 'b', 'c', 'x']));
+        assert_eq!(order_by_role(0, &[ROLE_EXTRA], &['x']), Ok(vec!['x']));
+        // Missing, repeated and unknown roles, or a label count that doesn't match
+        assert_eq!(order_by_role(2, &[0], &['a']), Err(DiamondError::AccountRoleMismatch));
+        assert_eq!(order_by_role(2, &[0, 0], &['a', 'b']), Err(DiamondError::AccountRoleMismatch));
+        assert_eq!(order_by_role(2, &[0, 1, 2], &['a', 'b', 'c']), Err(DiamondError::AccountRoleMismatch));
+        assert_eq!(order_by_role(1, &[0], &['a', 'b']), Err(DiamondError::AccountRoleMismatch));
+    }
+}
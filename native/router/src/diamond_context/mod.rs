@@ -0,0 +1,146 @@
+/*!
+ * Diamond Context Module
+ * Per-call caller context PDA written around every facet CPI
+ *
+ * Facets only see the accounts they are handed, so "who called me through
+ * the router" can't be read off them: a facet can be invoked directly, or by
+ * another program with any accounts it likes. Once `context::enable` has
+ * created the diamond's call context account (see
+ * `diamond_core::call_context`), any dispatch that includes it, writable,
+ * gets the call's first writable signer, signer, canonical selector, slot
+ * and stack height written there before the facet CPI. Only the router can write it, and it
+ * restores the previous contents after the CPI, so nested dispatches leave
+ * their caller's context intact and the account is idle between calls. A
+ * facet that dispatches again passes the account on, writable, like any
+ * other.
+ *
+ * Calls carrying the context bypass the fast path. The first writable signer
+ * is just that, whatever the client listed first; the runtime doesn't show
+ * programs the fee payer, so facets must not read payment into it.
+ */
+
+use borsh::BorshSerialize;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    system_program,
+};
+
+use crate::diamond_state::DiamondState;
+use crate::diamond_vault;
+use crate::error::DiamondError;
+
+pub use diamond_core::call_context::{CallContext, CALL_CONTEXT_SEED};
+
+pub fn find_address(program_id: &Pubkey, diamond: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[CALL_CONTEXT_SEED, diamond.as_ref()], program_id)
+}
+
+/// The call context account of `diamond` among `accounts`, with what it holds
+pub fn find<'a, 'info>(
+    program_id: &Pubkey,
+    diamond: &Pubkey,
+    accounts: &'a [AccountInfo<'info>],
+) -> Option<(&'a AccountInfo<'info>, CallContext)> {
+    accounts.iter().find_map(|account| {
+        if account.owner != program_id || account.data_len() != CallContext::ENCODED_LEN {
+            return None;
+        }
+        let context = CallContext::read(&account.try_borrow_data().ok()?)?;
+        let seeds: &[&[u8]] = &[CALL_CONTEXT_SEED, diamond.as_ref(), &[context.bump]];
+        let address = Pubkey::create_program_address(seeds, program_id).ok()?;
+        (context.diamond == diamond.to_bytes() && &address == account.key).then_some((account, context))
+    })
+}
+
+pub fn write(account: &AccountInfo, context: &CallContext) -> ProgramResult {
+    if !account.is_writable {
+        msg!("Error: Call context {} must be writable", account.key);
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let mut data = account.try_borrow_mut_data()?;
+    context.serialize(&mut &mut data[..])
+        .map_err(|_| ProgramError::AccountDataTooSmall)
+}
+
+/// Create the diamond's call context account (owner only)
+///
+/// Accounts: [diamond_state, owner, call_context, payer, system_program]
+pub fn enable_call_context(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let owner = next_account_info(account_iter)?;
+    let context_account = next_account_info(account_iter)?;
+    let payer = next_account_info(account_iter)?;
+    let system_program_account = next_account_info(account_iter)?;
+    
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if system_program_account.key != &system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    let state = DiamondState::load(program_id, state_account)?;
+    if !state.is_owner(owner.key) {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    let (expected, bump) = find_address(program_id, state_account.key);
+    if context_account.key != &expected {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    
+    diamond_vault::create_pda(
+        program_id,
+        state_account.key,
+        state.may_spend_rent_vault(owner.key),
+        payer,
+        context_account,
+        system_program_account,
+        CallContext::ENCODED_LEN,
+        &[CALL_CONTEXT_SEED, state_account.key.as_ref(), &[bump]],
+    )?;
+    let idle = CallContext { diamond: state_account.key.to_bytes(), bump, ..CallContext::default() };
+    write(context_account, &idle)?;
+    
+    msg!("Call context enabled: {}", context_account.key);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_find_checks_owner_and_address() {
+        let program_id = Pubkey::new_unique();
+        let diamond = Pubkey::new_unique();
+        let (address, bump) = find_address(&program_id, &diamond);
+        let idle = CallContext { diamond: diamond.to_bytes(), bump, ..CallContext::default() };
+        let (mut l0, mut l1, mut l2) = (0u64, 0u64, 0u64);
+        let (mut d0, mut d1, mut d2) = (borsh::to_vec(&idle).unwrap(), borsh::to_vec(&idle).unwrap(), vec![]);
+        let (other, stranger) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let accounts = vec![
+            // Right contents at the wrong address, then under the wrong owner
+            AccountInfo::new(&other, false, true, &mut l0, &mut d0, &program_id, false, 0),
+            AccountInfo::new(&address, false, true, &mut l1, &mut d1, &stranger, false, 0),
+            AccountInfo::new(&stranger, false, true, &mut l2, &mut d2, &program_id, false, 0),
+        ];
+        assert!(find(&program_id, &diamond, &accounts).is_none());
+        
+        let mut data = borsh::to_vec(&idle).unwrap();
+        let mut lamports = 0;
+        let context = AccountInfo::new(&address, false, false, &mut lamports, &mut data, &program_id, false, 0);
+        let accounts = [accounts[0].clone(), context];
+        let (found, held) = find(&program_id, &diamond, &accounts).unwrap();
+        assert_eq!((found.key, held), (&address, idle));
+        assert_eq!(write(found, &idle), Err(ProgramError::InvalidAccountData));
+    }
+}
@@ -0,0 +1,536 @@
+/*!
+ * Diamond Council Module
+ * Weighted multi-owner approval of cuts, pauses and membership
+ *
+ * The owner establishes a council once; from then on cuts, pauses, council
+ * changes and authority changes (admins, pause authority, governance,
+ * ownership, tenants) only happen through motions. A member opens a motion
+ * (counting their own approval), other members approve it, and once the
+ * combined weight of current members reaches the threshold anyone can
+ * execute it. Approvals are weighed against the council at execution time,
+ * so members removed in the meantime no longer count.
+ *
+ * Membership lives in its own account at `[COUNCIL_SEED, diamond]`; the
+ * diamond state only records that one governs it. Handlers take that account
+ * right after the diamond state.
+ */
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    system_program,
+    sysvar::Sysvar,
+};
+
+use crate::diamond_proposal::{self, CutProposal};
+use crate::diamond_state::{apply_authority_action, AuthorityAction, Council, DiamondState};
+use crate::error::DiamondError;
+use crate::events::DiamondEvent;
+
+pub use diamond_constants::seeds::{COUNCIL_SEED, MOTION_SEED};
+
+/// Council of one diamond
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct CouncilAccount {
+    pub diamond: Pubkey,
+    pub council: Council,
+    pub bump: u8,
+}
+
+impl CouncilAccount {
+    pub const SPACE: usize =
+        32 + // diamond
+        Council::MAX_ENCODED_LEN + // council
+        1;   // bump
+    
+    pub fn find_address(program_id: &Pubkey, diamond: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[COUNCIL_SEED, diamond.as_ref()], program_id)
+    }
+    
+    /// Load the council account of `diamond`
+    pub fn load(program_id: &Pubkey, account: &AccountInfo, diamond: &Pubkey) -> Result<Self, ProgramError> {
+        if account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let data = account.try_borrow_data()?;
+        let loaded = Self::deserialize(&mut &data[..]).map_err(|_| ProgramError::InvalidAccountData)?;
+        let seeds: &[&[u8]] = &[COUNCIL_SEED, diamond.as_ref(), &[loaded.bump]];
+        if &loaded.diamond != diamond || Pubkey::create_program_address(seeds, program_id).ok().as_ref() != Some(account.key) {
+            msg!("Error: {} is not the council account of {}", account.key, diamond);
+            return Err(ProgramError::InvalidSeeds);
+        }
+        Ok(loaded)
+    }
+    
+    pub fn save(&self, account: &AccountInfo) -> ProgramResult {
+        let mut data = account.try_borrow_mut_data()?;
+        self.serialize(&mut &mut data[..]).map_err(|_| ProgramError::AccountDataTooSmall)
+    }
+}
+
+/// Council governing `diamond`, read from its account
+pub fn current_council(
+    program_id: &Pubkey,
+    state: &DiamondState,
+    diamond: &Pubkey,
+    account: &AccountInfo,
+) -> Result<Council, ProgramError> {
+    if !state.has_council {
+        msg!("Error: Diamond has no council");
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    Ok(CouncilAccount::load(program_id, account, diamond)?.council)
+}
+
+/// Whether `key` sits on the council of `diamond`, whose account must be among `accounts`
+pub fn is_member(program_id: &Pubkey, state: &DiamondState, diamond: &Pubkey, accounts: &[AccountInfo], key: &Pubkey) -> bool {
+    let (address, _) = CouncilAccount::find_address(program_id, diamond);
+    accounts
+        .iter()
+        .find(|account| account.key == &address)
+        .and_then(|account| current_council(program_id, state, diamond, account).ok())
+        .is_some_and(|council| council.weight_of(key).is_some())
+}
+
+/// What a motion does once passed
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub enum CouncilAction {
+    /// Apply cut proposal `id` (its PDA follows the executor in the accounts)
+    ApplyProposal(u64),
+    /// Set both pause scopes, as `set_pause_scopes` does
+    Pause {
+        dispatch_paused: bool,
+        cut_paused: bool,
+        expiry_slot: Option<u64>,
+    },
+    /// Replace the council, or dissolve it (`None`) and hand control back to the owner
+    SetCouncil(Option<Council>),
+    /// Make an authority change the owner makes directly without a council
+    Authority(AuthorityAction),
+}
+
+/// Motion awaiting approval weight
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct Motion {
+    pub diamond: Pubkey,
+    pub id: u64,
+    pub proposer: Pubkey,
+    pub action: CouncilAction,
+    /// Members who approved, in order
+    pub approvals: Vec<Pubkey>,
+    pub executed: bool,
+    pub bump: u8,
+}
+
+impl Motion {
+    pub const SPACE: usize =
+        32 + // diamond
+        8 +  // id
+        32 + // proposer
+        1 + 1 + Council::MAX_ENCODED_LEN + // action (largest: SetCouncil(Some))
+        4 + (Council::MAX_MEMBERS * 32) + // approvals vec
+        1 +  // executed
+        1;   // bump
+    
+    pub fn find_address(program_id: &Pubkey, diamond: &Pubkey, id: u64) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[MOTION_SEED, diamond.as_ref(), &id.to_le_bytes()], program_id)
+    }
+    
+    /// Load a motion belonging to `diamond`
+    pub fn load(program_id: &Pubkey, account: &AccountInfo, diamond: &Pubkey) -> Result<Self, ProgramError> {
+        if account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let data = account.try_borrow_data()?;
+        let motion = Self::deserialize(&mut &data[..]).map_err(|_| ProgramError::InvalidAccountData)?;
+        if &motion.diamond != diamond {
+            msg!("Error: Motion {} belongs to another diamond", motion.id);
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(motion)
+    }
+    
+    pub fn save(&self, account: &AccountInfo) -> ProgramResult {
+        let mut data = account.try_borrow_mut_data()?;
+        self.serialize(&mut &mut data[..]).map_err(|_| ProgramError::AccountDataTooSmall)
+    }
+    
+    /// Record `member`'s approval, returning the running weight
+    pub fn approve(&mut self, council: &Council, member: &Pubkey) -> Result<u32, DiamondError> {
+        if council.weight_of(member).is_none() {
+            return Err(DiamondError::UnauthorizedAccess);
+        }
+        if self.executed {
+            return Err(DiamondError::ProposalAlreadyExecuted);
+        }
+        if !self.approvals.contains(member) {
+            // Stale approvals from former members make room for current ones
+            if self.approvals.len() >= Council::MAX_MEMBERS {
+                self.approvals.retain(|key| council.weight_of(key).is_some());
+            }
+            self.approvals.push(*member);
+        }
+        Ok(council.approved_weight(&self.approvals))
+    }
+    
+    pub fn passes(&self, council: &Council) -> bool {
+        council.approved_weight(&self.approvals) >= council.threshold
+    }
+}
+
+/// Install a council (owner only, once)
+///
+/// Accounts: [diamond_state, owner, council, payer, system_program]
+/// Data: Council
+///
+/// The council account is created on first use; after a dissolution the
+/// existing account is overwritten.
+pub fn establish_council(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let owner = next_account_info(account_iter)?;
+    let council_account = next_account_info(account_iter)?;
+    let payer = next_account_info(account_iter)?;
+    let system_program_account = next_account_info(account_iter)?;
+    
+    if !owner.is_signer || !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if system_program_account.key != &system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    let council = Council::try_from_slice(data).map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    if !state.is_owner(owner.key) {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    if state.has_council {
+        msg!("Error: Council membership changes by motion");
+        return Err(DiamondError::CouncilApprovalRequired.into());
+    }
+    council.validate()?;
+    
+    let (expected_pda, bump) = CouncilAccount::find_address(program_id, state_account.key);
+    if council_account.key != &expected_pda {
+        msg!("Error: Invalid council PDA");
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if council_account.data_is_empty() {
+        invoke_signed(
+            &system_instruction::create_account(
+                payer.key,
+                council_account.key,
+                Rent::get()?.minimum_balance(CouncilAccount::SPACE),
+                CouncilAccount::SPACE as u64,
+                program_id,
+            ),
+            &[payer.clone(), council_account.clone(), system_program_account.clone()],
+            &[&[COUNCIL_SEED, state_account.key.as_ref(), &[bump]]],
+        )?;
+    } else if council_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    CouncilAccount { diamond: *state_account.key, council: council.clone(), bump }.save(council_account)?;
+    state.has_council = true;
+    state.save(state_account)?;
+    
+    msg!("Council established: {} members, threshold {}", council.members.len(), council.threshold);
+    DiamondEvent::CouncilChanged { council: Some(council) }.emit();
+    Ok(())
+}
+
+/// Open a motion (council member)
+///
+/// Accounts: [diamond_state, council, motion, member, payer, system_program]
+/// Data: CouncilAction
+pub fn propose_motion(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let council_account = next_account_info(account_iter)?;
+    let motion_account = next_account_info(account_iter)?;
+    let member = next_account_info(account_iter)?;
+    let payer = next_account_info(account_iter)?;
+    let system_program_account = next_account_info(account_iter)?;
+    
+    if !member.is_signer || !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if system_program_account.key != &system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    let action = CouncilAction::try_from_slice(data).map_err(|_| ProgramError::InvalidInstructionData)?;
+    if let CouncilAction::SetCouncil(Some(council)) = &action {
+        council.validate()?;
+    }
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    let council = current_council(program_id, &state, state_account.key, council_account)?;
+    
+    let id = state.next_proposal_id;
+    let (expected_pda, bump) = Motion::find_address(program_id, state_account.key, id);
+    if motion_account.key != &expected_pda {
+        msg!("Error: Invalid motion PDA for id {}", id);
+        return Err(ProgramError::InvalidSeeds);
+    }
+    
+    let mut motion = Motion {
+        diamond: *state_account.key,
+        id,
+        proposer: *member.key,
+        action,
+        approvals: Vec::new(),
+        executed: false,
+        bump,
+    };
+    let weight = motion.approve(&council, member.key)?;
+    
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            motion_account.key,
+            Rent::get()?.minimum_balance(Motion::SPACE),
+            Motion::SPACE as u64,
+            program_id,
+        ),
+        &[payer.clone(), motion_account.clone(), system_program_account.clone()],
+        &[&[MOTION_SEED, state_account.key.as_ref(), &id.to_le_bytes(), &[bump]]],
+    )?;
+    motion.save(motion_account)?;
+    
+    state.next_proposal_id = id.checked_add(1).ok_or(ProgramError::ArithmeticOverflow)?;
+    state.save(state_account)?;
+    
+    msg!("Motion {} opened by {} ({}/{})", id, member.key, weight, council.threshold);
+    DiamondEvent::MotionCreated { id, proposer: *member.key }.emit();
+    DiamondEvent::MotionApproved { id, member: *member.key, weight }.emit();
+    Ok(())
+}
+
+/// Add a member's approval to a motion (council member)
+///
+/// Accounts: [diamond_state, council, motion, member]
+pub fn approve_motion(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let council_account = next_account_info(account_iter)?;
+    let motion_account = next_account_info(account_iter)?;
+    let member = next_account_info(account_iter)?;
+    
+    if !member.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let state = DiamondState::load(program_id, state_account)?;
+    let council = current_council(program_id, &state, state_account.key, council_account)?;
+    let mut motion = Motion::load(program_id, motion_account, state_account.key)?;
+    let weight = motion.approve(&council, member.key)?;
+    motion.save(motion_account)?;
+    
+    msg!("Motion {} approved by {} ({}/{})", motion.id, member.key, weight, council.threshold);
+    DiamondEvent::MotionApproved { id: motion.id, member: *member.key, weight }.emit();
+    Ok(())
+}
+
+/// Carry out a motion that has reached the threshold (anyone)
+///
+/// Accounts: [diamond_state, council, motion, executor, (proposal), (routing digest), (selector index), (audit accounts)]
+/// `ApplyProposal` motions take the proposal PDA (and what `approve_cut`
+/// takes after it); an escrowed tip goes to the executor.
+pub fn execute_motion(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let council_account = next_account_info(account_iter)?;
+    let motion_account = next_account_info(account_iter)?;
+    let executor = next_account_info(account_iter)?;
+    
+    if !executor.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    let mut motion = Motion::load(program_id, motion_account, state_account.key)?;
+    if motion.executed {
+        return Err(DiamondError::ProposalAlreadyExecuted.into());
+    }
+    let council = current_council(program_id, &state, state_account.key, council_account)?;
+    if !motion.passes(&council) {
+        msg!(
+            "Error: Motion {} has {} of {} approval weight",
+            motion.id,
+            council.approved_weight(&motion.approvals),
+            council.threshold
+        );
+        return Err(DiamondError::InsufficientApprovals.into());
+    }
+    motion.executed = true;
+    motion.save(motion_account)?;
+    
+    match &motion.action {
+        CouncilAction::ApplyProposal(id) => {
+            let proposal_account = next_account_info(account_iter)?;
+            let mut proposal = CutProposal::load(program_id, proposal_account, state_account.key)?;
+            if proposal.id != *id {
+                msg!("Error: Motion {} applies proposal {}, not {}", motion.id, id, proposal.id);
+                return Err(ProgramError::InvalidArgument);
+            }
+            diamond_proposal::execute(
+                program_id,
+                state_account,
+                proposal_account,
+                executor,
+                account_iter.as_slice(),
+                &mut state,
+                &mut proposal,
+            )?;
+        }
+        CouncilAction::Pause { dispatch_paused, cut_paused, expiry_slot } => {
+            state.set_pause_scopes(*dispatch_paused, *cut_paused, *expiry_slot);
+            state.save(state_account)?;
+            msg!("Dispatch paused: {}, cuts paused: {} (expiry slot: {:?})", dispatch_paused, cut_paused, expiry_slot);
+        }
+        CouncilAction::SetCouncil(council) => {
+            // A dissolved council's account stays behind for a later `establish`
+            if let Some(council) = council {
+                let mut stored = CouncilAccount::load(program_id, council_account, state_account.key)?;
+                stored.council = council.clone();
+                stored.save(council_account)?;
+            }
+            state.has_council = council.is_some();
+            state.save(state_account)?;
+            msg!("Council {}", if council.is_some() { "replaced" } else { "dissolved" });
+            DiamondEvent::CouncilChanged { council: council.clone() }.emit();
+        }
+        CouncilAction::Authority(action) => {
+            apply_authority_action(&mut state, *action, Clock::get()?.slot)?;
+            state.save(state_account)?;
+        }
+    }
+    
+    DiamondEvent::MotionExecuted { id: motion.id, executor: *executor.key }.emit();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diamond_state::{CouncilMember, CutRole};
+    
+    fn council(weights: &[u16], threshold: u32) -> Council {
+        Council {
+            members: weights.iter().map(|&weight| CouncilMember { key: Pubkey::new_unique(), weight }).collect(),
+            threshold,
+        }
+    }
+    
+    #[test]
+    fn test_council_validation() {
+        assert_eq!(council(&[2, 1, 1], 3).validate(), Ok(()));
+        assert_eq!(council(&[2, 1, 1], 5).validate(), Err(DiamondError::InvalidCouncil));
+        assert_eq!(council(&[2, 0], 1).validate(), Err(DiamondError::InvalidCouncil));
+        assert_eq!(council(&[], 0).validate(), Err(DiamondError::InvalidCouncil));
+        
+        let mut duplicate = council(&[1, 1], 1);
+        duplicate.members[1].key = duplicate.members[0].key;
+        assert_eq!(duplicate.validate(), Err(DiamondError::InvalidCouncil));
+    }
+    
+    #[test]
+    fn test_motion_weighs_current_members() {
+        let mut council = council(&[2, 1, 1], 3);
+        let [a, b, c] = [0, 1, 2].map(|i| council.members[i].key);
+        let mut motion = Motion {
+            diamond: Pubkey::new_unique(),
+            id: 0,
+            proposer: a,
+            action: CouncilAction::Pause { dispatch_paused: true, cut_paused: true, expiry_slot: None },
+            approvals: vec![],
+            executed: false,
+            bump: 255,
+        };
+        
+        assert_eq!(motion.approve(&council, &b), Ok(1));
+        assert_eq!(motion.approve(&council, &b), Ok(1));
+        assert!(!motion.passes(&council));
+        assert_eq!(motion.approve(&council, &a), Ok(3));
+        assert!(motion.passes(&council));
+        assert_eq!(motion.approve(&council, &Pubkey::new_unique()), Err(DiamondError::UnauthorizedAccess));
+        
+        // A removed member's approval stops counting
+        council.members.remove(0);
+        council.threshold = 2;
+        assert!(!motion.passes(&council));
+        assert_eq!(motion.approve(&council, &c), Ok(2));
+    }
+    
+    #[test]
+    fn test_council_replaces_owner_cut_rights() {
+        let owner = Pubkey::new_unique();
+        let mut state = DiamondState::new(owner, 255);
+        state.pause_authority = Pubkey::new_unique();
+        assert_eq!(state.cut_role(&owner, &[0; 8]), Some(CutRole::Owner));
+        
+        state.has_council = true;
+        assert_eq!(state.cut_role(&owner, &[0; 8]), None);
+        assert!(!state.can_pause(&owner));
+        assert!(state.can_pause(&state.pause_authority.clone()));
+        assert_eq!(state.check_authority_change(&owner), Err(DiamondError::CouncilApprovalRequired));
+        
+        // Authority changes come through motions instead
+        let admin = Pubkey::new_unique();
+        apply_authority_action(&mut state, AuthorityAction::AddAdmin(admin), 0).unwrap();
+        apply_authority_action(&mut state, AuthorityAction::PauseAuthority(admin), 0).unwrap();
+        state.apply_pending_change(u64::MAX).unwrap();
+        assert_eq!((state.admins.as_slice(), state.pause_authority), (&[admin][..], admin));
+    }
+    
+    #[test]
+    fn test_max_council_fits_space() {
+        let account = CouncilAccount {
+            diamond: Pubkey::new_unique(),
+            council: council(&[u16::MAX; Council::MAX_MEMBERS], u32::MAX),
+            bump: 255,
+        };
+        assert_eq!(borsh::to_vec(&account).unwrap().len(), CouncilAccount::SPACE);
+    }
+    
+    #[test]
+    fn test_max_motion_fits_space() {
+        let motion = Motion {
+            diamond: Pubkey::new_unique(),
+            id: u64::MAX,
+            proposer: Pubkey::new_unique(),
+            action: CouncilAction::SetCouncil(Some(council(&[u16::MAX; Council::MAX_MEMBERS], u32::MAX))),
+            approvals: vec![Pubkey::new_unique(); Council::MAX_MEMBERS],
+            executed: true,
+            bump: 255,
+        };
+        assert_eq!(borsh::to_vec(&motion).unwrap().len(), Motion::SPACE);
+    }
+}
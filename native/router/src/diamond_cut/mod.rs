@@ -1,23 +1,497 @@
 /*!
  * Diamond Cut Module
- * Module (facet) management - add/remove facets
+ * Module (facet) management - add/replace/remove facets
  */
 
 use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
+    clock::Clock,
     entrypoint::ProgramResult,
     msg,
     program_error::ProgramError,
     pubkey::Pubkey,
+    sysvar::Sysvar,
 };
 
-use crate::diamond_state::{DiamondState, ModuleMeta, SelectorMapping};
+use crate::builtins::is_reserved;
+use crate::diamond_audit;
+use crate::diamond_authorizer::{self, AuthAction};
+use crate::diamond_digest;
+use crate::diamond_index;
+use crate::diamond_loupe::return_borsh;
+use crate::diamond_state::{
+    fixed_str, namespace_bytes, CutRole, DiamondState, ModuleMeta, Reentrancy, SelectorAlias, SelectorMapping, Semver,
+    REENTRANCY_MASK,
+};
 use crate::error::DiamondError;
+use crate::events::DiamondEvent;
+use crate::selector::{allocate, Selector};
+
+/// EIP-2535 cut action
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FacetCutAction {
+    Add,
+    Replace,
+    Remove,
+}
+
+/// A single routing table change
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct FacetCut {
+    pub action: FacetCutAction,
+    pub selector: Selector,
+    pub module: Pubkey,
+    pub module_name: String,
+    pub namespace: String,
+    pub function_name: String,
+    pub is_immutable: bool,
+    pub standard_accounts: u8,
+}
+
+impl FacetCut {
+    /// Largest Borsh encoding of a valid cut
+    pub const MAX_SIZE: usize =
+        1 +        // action
+        8 +        // selector
+        32 +       // module
+        4 + 32 +   // module_name
+        4 + 8 +    // namespace
+        4 + 64 +   // function_name
+        1 +        // is_immutable
+        1;         // standard_accounts
+    
+    pub fn add(
+        selector: impl Into<Selector>,
+        module: Pubkey,
+        module_name: &str,
+        function_name: &str,
+    ) -> Self {
+        Self {
+            action: FacetCutAction::Add,
+            selector: selector.into(),
+            module,
+            module_name: module_name.to_string(),
+            namespace: String::new(),
+            function_name: function_name.to_string(),
+            is_immutable: false,
+            standard_accounts: 0,
+        }
+    }
+    
+    pub fn replace(selector: impl Into<Selector>, module: Pubkey, module_name: &str) -> Self {
+        Self {
+            action: FacetCutAction::Replace,
+            module_name: module_name.to_string(),
+            ..Self::add(selector, module, "", "")
+        }
+    }
+    
+    /// Declare the selector's reentrancy policy
+    pub fn with_reentrancy(mut self, policy: Reentrancy) -> Self {
+        self.standard_accounts = (self.standard_accounts & !REENTRANCY_MASK) | policy.to_flags();
+        self
+    }
+    
+    pub fn remove(selector: impl Into<Selector>) -> Self {
+        Self {
+            action: FacetCutAction::Remove,
+            ..Self::add(selector, Pubkey::default(), "", "")
+        }
+    }
+    
+    /// Reject names that would not fit the fixed-size state fields
+    pub fn validate(&self) -> Result<(), DiamondError> {
+        if self.module_name.len() > 32 || namespace_bytes(&self.namespace).is_none() || self.function_name.len() > 64 {
+            msg!("Error: Cut for selector {:?} has an oversized name", self.selector);
+            return Err(DiamondError::InvalidCut);
+        }
+        if self.standard_accounts & REENTRANCY_MASK == REENTRANCY_MASK {
+            msg!("Error: Cut for selector {:?} sets both reentrancy bits", self.selector);
+            return Err(DiamondError::InvalidCut);
+        }
+        Ok(())
+    }
+}
+
+/// Apply a single cut to the routing table
+pub fn apply_cut(state: &mut DiamondState, cut: &FacetCut) -> Result<(), DiamondError> {
+    cut.validate()?;
+    
+    let namespace = match cut.action {
+        FacetCutAction::Add => {
+            if state.selectors.len() >= DiamondState::MAX_SELECTORS {
+                return Err(DiamondError::SelectorCapacityExceeded);
+            }
+            if !cut.selector.fits(state.selector_width) {
+                msg!("Error: Selector {:?} is wider than {} bytes", cut.selector, state.selector_width);
+                return Err(DiamondError::InvalidSelectorWidth);
+            }
+            if is_reserved(cut.selector) {
+                msg!("Error: Selector {:?} is reserved for the router", cut.selector);
+                return Err(DiamondError::ReservedSelector);
+            }
+            if state.is_registered(cut.selector) {
+                msg!("Error: Selector {:?} already registered", cut.selector);
+                return Err(DiamondError::SelectorCollision);
+            }
+            let namespace = namespace_bytes(&cut.namespace).ok_or(DiamondError::InvalidCut)?;
+            check_name(state, &namespace, &cut.function_name, cut.selector)?;
+            register_module(state, cut)?;
+            state.selectors.push(
+                SelectorMapping::new_with_namespace(
+                    namespace,
+                    cut.selector,
+                    cut.module,
+                    &cut.function_name,
+                    cut.is_immutable,
+                )
+                .with_standard_accounts(cut.standard_accounts),
+            );
+            namespace
+        }
+        FacetCutAction::Replace => {
+            let index = mutable_mapping_index(state, cut.selector)?;
+            let namespace = state.selectors[index].namespace;
+            check_name(state, &namespace, &cut.function_name, cut.selector)?;
+            register_module(state, cut)?;
+            let mapping = &mut state.selectors[index];
+            mapping.module = cut.module;
+            mapping.is_immutable = cut.is_immutable;
+            mapping.standard_accounts = cut.standard_accounts;
+            if !cut.function_name.is_empty() {
+                let name = cut.function_name.as_bytes();
+                mapping.function_name = [0u8; 64];
+                mapping.function_name[..name.len()].copy_from_slice(name);
+            }
+            namespace
+        }
+        FacetCutAction::Remove => {
+            let index = mutable_mapping_index(state, cut.selector)?;
+            let removed = state.selectors.remove(index);
+            state.aliases.retain(|a| a.canonical != cut.selector);
+            state.payload_specs.retain(|s| s.selector != cut.selector);
+            state.tx_guard.selectors.retain(|s| s != &cut.selector);
+            state.tags.retain(|t| t.selector != cut.selector);
+            state.oracle_guards.retain(|g| g.selector != cut.selector);
+            state.circuit_breakers.retain(|b| b.selector != cut.selector);
+            if let Some(authorizer) = &mut state.authorizer {
+                authorizer.protected.retain(|s| s != &cut.selector);
+            }
+            removed.namespace
+        }
+    };
+    check_dependencies(state, cut, &namespace)?;
+    state.config_version += 1;
+    Ok(())
+}
+
+/// Apply a batch of cuts atomically: either all succeed or state is untouched
+pub fn apply_cuts(state: &mut DiamondState, cuts: &[FacetCut]) -> Result<(), DiamondError> {
+    let mut next = state.clone();
+    for cut in cuts {
+        apply_cut(&mut next, cut)?;
+    }
+    *state = next;
+    Ok(())
+}
+
+/// `apply_cuts` for cuts outside a proposal: a `Replace` must keep the
+/// facet's major version (`DiamondState::check_upgrade`)
+pub fn apply_direct_cuts(state: &mut DiamondState, cuts: &[FacetCut]) -> Result<(), DiamondError> {
+    let mut next = state.clone();
+    for cut in cuts {
+        if cut.action == FacetCutAction::Replace {
+            if let Some(mapping) = next.get_mapping(cut.selector) {
+                next.check_upgrade(&mapping.module, &cut.module)?;
+            }
+        }
+        apply_cut(&mut next, cut)?;
+    }
+    *state = next;
+    Ok(())
+}
+
+/// Add `cut` under the first free selector the allocation rule yields
+///
+/// The cut's `selector` is ignored; its namespace and `function_name` (the
+/// function signature) are hashed as described in `selector::allocated`.
+/// Reserved and registered selectors count as collisions, and the salt that
+/// resolved them is recorded in the new mapping.
+pub fn apply_allocation(state: &mut DiamondState, cut: &FacetCut) -> Result<(Selector, u8), DiamondError> {
+    let (selector, salt) = allocate(&cut.namespace, &cut.function_name, state.selector_width, |s| {
+        is_reserved(*s) || state.is_registered(*s)
+    })
+    .ok_or(DiamondError::SelectorCollision)?;
+    
+    apply_cut(state, &FacetCut { action: FacetCutAction::Add, selector, ..cut.clone() })?;
+    if let Some(mapping) = state.selectors.last_mut() {
+        mapping.salt = salt;
+    }
+    Ok((selector, salt))
+}
+
+/// Route `alias` to the existing mapping of `canonical`
+///
+/// Aliases resolve in one hop: `canonical` must be a mapping, not another
+/// alias. Removing the canonical selector drops its aliases.
+pub fn apply_alias(state: &mut DiamondState, alias: Selector, canonical: Selector) -> Result<(), DiamondError> {
+    if !alias.fits(state.selector_width) {
+        msg!("Error: Alias {:?} is wider than {} bytes", alias, state.selector_width);
+        return Err(DiamondError::InvalidSelectorWidth);
+    }
+    if is_reserved(alias) {
+        msg!("Error: Alias {:?} is reserved for the router", alias);
+        return Err(DiamondError::ReservedSelector);
+    }
+    if state.is_registered(alias) {
+        msg!("Error: Selector {:?} already registered", alias);
+        return Err(DiamondError::SelectorCollision);
+    }
+    if state.get_mapping(canonical).is_none() {
+        msg!("Error: Canonical selector {:?} not found", canonical);
+        return Err(DiamondError::ModuleNotFound);
+    }
+    if state.aliases.len() >= DiamondState::MAX_ALIASES {
+        return Err(DiamondError::SelectorCapacityExceeded);
+    }
+    state.aliases.push(SelectorAlias { alias, canonical });
+    state.config_version += 1;
+    Ok(())
+}
+
+/// Drop an alias, returning the selector it routed to
+pub fn drop_alias(state: &mut DiamondState, alias: Selector) -> Result<Selector, DiamondError> {
+    let index = state.aliases.iter().position(|a| a.alias == alias).ok_or_else(|| {
+        msg!("Error: Alias {:?} not found", alias);
+        DiamondError::ModuleNotFound
+    })?;
+    state.config_version += 1;
+    Ok(state.aliases.remove(index).canonical)
+}
+
+/// Decode instruction data followed by an optional `expected_version: u64`
+///
+/// Cut instructions accept the diamond's `config_version` as an optional
+/// trailing field; callers that omit it keep the old encoding.
+pub fn decode_with_version<T: BorshDeserialize>(data: &[u8]) -> Result<(T, Option<u64>), ProgramError> {
+    let mut rest = data;
+    let value = T::deserialize(&mut rest).map_err(|_| ProgramError::InvalidInstructionData)?;
+    let expected_version = match rest.len() {
+        0 => None,
+        8 => Some(u64::from_le_bytes(rest.try_into().map_err(|_| ProgramError::InvalidInstructionData)?)),
+        _ => return Err(ProgramError::InvalidInstructionData),
+    };
+    Ok((value, expected_version))
+}
+
+/// Track module metadata for a cut's target, once per module
+///
+/// A module whose version was declared ahead of the cut is activated at
+/// that version.
+fn register_module(state: &mut DiamondState, cut: &FacetCut) -> Result<(), DiamondError> {
+    let full = state.active_modules.len() >= DiamondState::MAX_MODULES;
+    match state.active_modules.iter_mut().find(|m| m.address == cut.module) {
+        Some(meta) if meta.is_active => {}
+        Some(meta) => *meta = ModuleMeta::new(&cut.module_name, cut.module, meta.version),
+        None if full => return Err(DiamondError::ModuleCapacityExceeded),
+        None => state.active_modules.push(ModuleMeta::new(&cut.module_name, cut.module, Semver::INITIAL)),
+    }
+    Ok(())
+}
+
+/// Refuse a name already settled by an immutable mapping
+/// (`DiamondState::immutable_name_holder`)
+fn check_name(state: &DiamondState, namespace: &[u8; 8], function_name: &str, selector: Selector) -> Result<(), DiamondError> {
+    if let Some(holder) = state.immutable_name_holder(namespace, function_name, selector) {
+        msg!("Error: {} is held by immutable selector {:?}", holder.qualified_name(), holder.selector);
+        return Err(DiamondError::ImmutableNameConflict);
+    }
+    Ok(())
+}
+
+/// Keep routed facets' dependencies routed (see `diamond_dependency`)
+///
+/// Runs after each cut of a batch, so a facet must come after the
+/// namespaces it depends on, and a namespace can't go while a facet still
+/// needs it. Only what the cut touched is checked: the dependencies of the
+/// module it routes, and those on the `namespace` of its selector. A
+/// dependency left unmet elsewhere doesn't block unrelated cuts, including
+/// the one that meets it.
+fn check_dependencies(state: &DiamondState, cut: &FacetCut, namespace: &[u8; 8]) -> Result<(), DiamondError> {
+    let routes_module = cut.action != FacetCutAction::Remove;
+    let Some(unmet) = state
+        .unmet_dependencies()
+        .find(|d| (routes_module && d.module == cut.module) || &d.namespace == namespace)
+    else {
+        return Ok(());
+    };
+    let namespace = unmet.namespace_as_str();
+    if unmet.module == cut.module && cut.action != FacetCutAction::Remove {
+        msg!("Error: {} needs {} at version {} routed first", unmet.module, namespace, unmet.min_version);
+        return Err(DiamondError::DependencyMissing);
+    }
+    msg!("Error: {} still depends on {} at version {}", unmet.module, namespace, unmet.min_version);
+    Err(DiamondError::DependentsRemain)
+}
+
+/// Index of an existing, mutable mapping
+fn mutable_mapping_index(state: &DiamondState, selector: Selector) -> Result<usize, DiamondError> {
+    let index = state
+        .selectors
+        .iter()
+        .position(|s| s.selector == selector)
+        .ok_or_else(|| {
+            msg!("Error: Selector {:?} not found", selector);
+            DiamondError::ModuleNotFound
+        })?;
+    if state.selectors[index].is_immutable {
+        msg!("Error: Selector {:?} is immutable", selector);
+        return Err(DiamondError::ImmutableSelector);
+    }
+    Ok(index)
+}
+
+/// Check `authority` may change a mapping in `namespace`
+///
+/// `None` stands for a selector that isn't mapped yet: any cutter passes and
+/// the missing mapping fails later in `apply_cuts`.
+pub fn authorize_namespace(
+    state: &DiamondState,
+    authority: &Pubkey,
+    namespace: Option<&[u8; 8]>,
+) -> Result<CutRole, DiamondError> {
+    let is_tenant = state.tenants.iter().any(|t| &t.authority == authority);
+    let role = match namespace {
+        Some(namespace) => state.cut_role(authority, namespace),
+        None => state.cut_role(authority, &[0u8; 8]).or(is_tenant.then_some(CutRole::Tenant)),
+    };
+    role.ok_or_else(|| {
+        if is_tenant {
+            msg!("Error: {} is not the tenant of {}", authority, fixed_str(namespace.unwrap_or(&[0u8; 8])));
+            DiamondError::TenantScopeViolation
+        } else {
+            msg!("Error: Unauthorized - {} holds no cut rights", authority);
+            DiamondError::UnauthorizedAccess
+        }
+    })
+}
+
+/// Check `authority` may apply every cut, per `DiamondState::cut_role`
+///
+/// Adds are judged by their declared namespace; replaces and removes by the
+/// namespace of the live mapping.
+pub fn authorize_cuts(
+    state: &DiamondState,
+    authority: &Pubkey,
+    cuts: &[FacetCut],
+) -> Result<(), DiamondError> {
+    for cut in cuts {
+        let namespace = match cut.action {
+            FacetCutAction::Add => Some(namespace_bytes(&cut.namespace).ok_or(DiamondError::InvalidCut)?),
+            FacetCutAction::Replace | FacetCutAction::Remove => {
+                state.get_mapping(cut.selector).map(|mapping| mapping.namespace)
+            }
+        };
+        authorize_namespace(state, authority, namespace.as_ref())?;
+    }
+    Ok(())
+}
+
+/// Namespace of the mapping an alias (or canonical selector) routes to
+fn alias_namespace(state: &DiamondState, selector: Selector) -> Option<[u8; 8]> {
+    state.resolve_mapping(selector).map(|mapping| mapping.namespace)
+}
+
+/// Check that every cut stays inside a tenant's namespace
+///
+/// Added selectors must be declared in `namespace`, and replaced or removed
+/// selectors must already live there.
+pub fn check_tenant_scope(
+    state: &DiamondState,
+    namespace: &[u8; 8],
+    cuts: &[FacetCut],
+) -> Result<(), DiamondError> {
+    for cut in cuts {
+        let in_scope = match cut.action {
+            FacetCutAction::Add => namespace_bytes(&cut.namespace).as_ref() == Some(namespace),
+            // Unknown selectors fail later in `apply_cuts`
+            FacetCutAction::Replace | FacetCutAction::Remove => match state.get_mapping(cut.selector) {
+                Some(mapping) => &mapping.namespace == namespace,
+                None => true,
+            },
+        };
+        if !in_scope {
+            msg!("Error: Selector {:?} is outside the tenant namespace", cut.selector);
+            return Err(DiamondError::TenantScopeViolation);
+        }
+    }
+    Ok(())
+}
+
+/// Apply cuts within a delegated namespace (tenant only)
+///
+/// Accounts: [diamond_state, tenant, (routing digest), (selector index), (audit accounts), (authorizer)]
+/// Data: namespace (String), cuts (Vec<FacetCut>), optional expected_version (u64)
+pub fn tenant_cut(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let diamond_state_account = next_account_info(account_iter)?;
+    let tenant = next_account_info(account_iter)?;
+    
+    if !tenant.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    #[derive(BorshDeserialize)]
+    struct TenantCutData {
+        namespace: String,
+        cuts: Vec<FacetCut>,
+    }
+    
+    let (cut_data, expected_version) = decode_with_version::<TenantCutData>(data)?;
+    let namespace = namespace_bytes(&cut_data.namespace).ok_or(DiamondError::InvalidCut)?;
+    
+    let mut state = DiamondState::load(program_id, diamond_state_account)?;
+    state.check_cuts_allowed(Clock::get()?.slot)?;
+    state.check_config_version(expected_version)?;
+    
+    if state.tenant_authority(&namespace) != Some(tenant.key) {
+        msg!("Error: {} is not the tenant of {}", tenant.key, cut_data.namespace);
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    if cut_data.cuts.iter().any(|cut| &cut.module == program_id) {
+        return Err(DiamondError::SelfDispatchForbidden.into());
+    }
+    check_tenant_scope(&state, &namespace, &cut_data.cuts)?;
+    diamond_authorizer::authorize_cuts(diamond_state_account, &state, accounts, tenant.key, &cut_data.cuts)?;
+    apply_direct_cuts(&mut state, &cut_data.cuts)?;
+    
+    state.save(diamond_state_account)?;
+    let index_accounts =
+        diamond_digest::refresh(program_id, diamond_state_account.key, &state, account_iter.as_slice())?;
+    let audit_accounts = diamond_index::refresh(program_id, diamond_state_account.key, &state, index_accounts)?;
+    diamond_audit::commit_cuts(
+        program_id,
+        diamond_state_account,
+        &state,
+        audit_accounts,
+        tenant.key,
+        &cut_data.cuts,
+    )?;
+    
+    msg!("Tenant {} applied {} cuts to {}", tenant.key, cut_data.cuts.len(), cut_data.namespace);
+    Ok(())
+}
 
 /// Add a new module (facet) to the diamond
+///
+/// Accounts: [diamond_state, authority, (routing digest), (selector index), (audit accounts), (authorizer)]
 pub fn add_module(
-    _program_id: &Pubkey,
+    program_id: &Pubkey,
     accounts: &[AccountInfo],
     data: &[u8],
 ) -> ProgramResult {
@@ -37,72 +511,68 @@ pub fn add_module(
     struct AddModuleData {
         module_name: String,
         module_address: Pubkey,
-        selector: [u8; 4],
+        selector: Selector,
         function_name: String,
         is_immutable: bool,
+        namespace: String,
+        standard_accounts: u8,
     }
     
-    let add_data = AddModuleData::try_from_slice(data)
-        .map_err(|_| ProgramError::InvalidInstructionData)?;
-    
-    // Load and modify state
-    let mut state_data = diamond_state_account.try_borrow_mut_data()?;
-    let mut state = DiamondState::try_from_slice(&state_data)
-        .map_err(|_| ProgramError::InvalidAccountData)?;
+    let (add_data, expected_version) = decode_with_version::<AddModuleData>(data)?;
     
-    // Check authority
-    if !state.has_authority(authority.key) {
-        msg!("Error: Unauthorized - only owner or admin can add modules");
-        return Err(DiamondError::UnauthorizedAccess.into());
-    }
-    
-    // Check capacities
-    if state.active_modules.len() >= DiamondState::MAX_MODULES {
-        return Err(DiamondError::ModuleCapacityExceeded.into());
-    }
-    if state.selectors.len() >= DiamondState::MAX_SELECTORS {
-        return Err(DiamondError::SelectorCapacityExceeded.into());
+    // The router (which also owns the diamond state) can never be a facet
+    if &add_data.module_address == program_id {
+        msg!("Error: Cannot register the router itself as a module");
+        return Err(DiamondError::SelfDispatchForbidden.into());
     }
     
-    // Check for selector collision
-    if state.get_module_by_selector(add_data.selector).is_some() {
-        msg!("Error: Selector {:?} already registered", add_data.selector);
-        return Err(DiamondError::SelectorCollision.into());
-    }
+    // Load and modify state
+    let mut state = DiamondState::load(program_id, diamond_state_account)?;
+    state.check_cuts_allowed(Clock::get()?.slot)?;
+    state.check_config_version(expected_version)?;
     
-    // Add module metadata
-    let module_meta = ModuleMeta::new(
-        &add_data.module_name,
-        add_data.module_address,
-        1,
-    );
-    state.active_modules.push(module_meta);
-    
-    // Add selector mapping
-    let selector_mapping = SelectorMapping::new(
-        add_data.selector,
-        add_data.module_address,
-        &add_data.function_name,
-        add_data.is_immutable,
-    );
-    state.selectors.push(selector_mapping);
+    let cut = FacetCut {
+        action: FacetCutAction::Add,
+        selector: add_data.selector,
+        module: add_data.module_address,
+        module_name: add_data.module_name,
+        namespace: add_data.namespace,
+        function_name: add_data.function_name,
+        is_immutable: add_data.is_immutable,
+        standard_accounts: add_data.standard_accounts,
+    };
+    authorize_cuts(&state, authority.key, std::slice::from_ref(&cut))?;
+    diamond_authorizer::authorize_cuts(diamond_state_account, &state, accounts, authority.key, std::slice::from_ref(&cut))?;
+    apply_cut(&mut state, &cut)?;
     
     // Serialize back
-    state.serialize(&mut &mut state_data[..])
-        .map_err(|_| ProgramError::InvalidAccountData)?;
+    state.save(diamond_state_account)?;
+    let index_accounts =
+        diamond_digest::refresh(program_id, diamond_state_account.key, &state, account_iter.as_slice())?;
+    let audit_accounts = diamond_index::refresh(program_id, diamond_state_account.key, &state, index_accounts)?;
+    diamond_audit::commit_cuts(
+        program_id,
+        diamond_state_account,
+        &state,
+        audit_accounts,
+        authority.key,
+        std::slice::from_ref(&cut),
+    )?;
     
     msg!(
         "Module added: {} ({}) with selector {:?}",
-        add_data.module_name,
-        add_data.module_address,
-        add_data.selector
+        cut.module_name,
+        cut.module,
+        cut.selector
     );
     Ok(())
 }
 
 /// Remove a module from the diamond
+///
+/// Accounts: [diamond_state, authority, (routing digest), (selector index), (audit accounts), (authorizer)]
 pub fn remove_module(
-    _program_id: &Pubkey,
+    program_id: &Pubkey,
     accounts: &[AccountInfo],
     data: &[u8],
 ) -> ProgramResult {
@@ -120,48 +590,191 @@ pub fn remove_module(
     // Parse instruction data (selector to remove)
     #[derive(BorshDeserialize)]
     struct RemoveModuleData {
-        selector: [u8; 4],
+        selector: Selector,
     }
     
-    let remove_data = RemoveModuleData::try_from_slice(data)
-        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    let (remove_data, expected_version) = decode_with_version::<RemoveModuleData>(data)?;
     
     // Load and modify state
-    let mut state_data = diamond_state_account.try_borrow_mut_data()?;
-    let mut state = DiamondState::try_from_slice(&state_data)
-        .map_err(|_| ProgramError::InvalidAccountData)?;
+    let mut state = DiamondState::load(program_id, diamond_state_account)?;
+    state.check_cuts_allowed(Clock::get()?.slot)?;
+    state.check_config_version(expected_version)?;
     
-    // Check authority
-    if !state.has_authority(authority.key) {
-        msg!("Error: Unauthorized - only owner or admin can remove modules");
-        return Err(DiamondError::UnauthorizedAccess.into());
+    let cut = FacetCut::remove(remove_data.selector);
+    authorize_cuts(&state, authority.key, std::slice::from_ref(&cut))?;
+    diamond_authorizer::authorize_cuts(diamond_state_account, &state, accounts, authority.key, std::slice::from_ref(&cut))?;
+    apply_cut(&mut state, &cut)?;
+    
+    // Serialize back
+    state.save(diamond_state_account)?;
+    let index_accounts =
+        diamond_digest::refresh(program_id, diamond_state_account.key, &state, account_iter.as_slice())?;
+    let audit_accounts = diamond_index::refresh(program_id, diamond_state_account.key, &state, index_accounts)?;
+    diamond_audit::commit_cuts(
+        program_id,
+        diamond_state_account,
+        &state,
+        audit_accounts,
+        authority.key,
+        std::slice::from_ref(&cut),
+    )?;
+    
+    msg!("Module removed for selector: {:?}", remove_data.selector);
+    Ok(())
+}
+
+/// Add a function under a deterministically allocated selector
+///
+/// Accounts: [diamond_state, authority, (routing digest), (selector index), (audit accounts), (authorizer)]
+/// Data: namespace, function_signature, module_name (Strings), module (Pubkey),
+///       is_immutable (bool), standard_accounts (u8), optional expected_version (u64)
+/// Returns: (selector, salt) as return data
+pub fn allocate_selector(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let diamond_state_account = next_account_info(account_iter)?;
+    let authority = next_account_info(account_iter)?;
+    
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
     }
     
-    // Check if selector exists and is mutable
-    let selector_info = state.selectors
-        .iter()
-        .find(|s| s.selector == remove_data.selector);
+    #[derive(BorshDeserialize)]
+    struct AllocateSelectorData {
+        namespace: String,
+        function_signature: String,
+        module_name: String,
+        module: Pubkey,
+        is_immutable: bool,
+        standard_accounts: u8,
+    }
     
-    match selector_info {
-        None => {
-            msg!("Error: Selector {:?} not found", remove_data.selector);
-            return Err(DiamondError::ModuleNotFound.into());
-        }
-        Some(mapping) if mapping.is_immutable => {
-            msg!("Error: Cannot remove immutable selector {:?}", remove_data.selector);
-            return Err(DiamondError::ImmutableSelector.into());
-        }
-        _ => {}
+    let (alloc_data, expected_version) = decode_with_version::<AllocateSelectorData>(data)?;
+    if &alloc_data.module == program_id {
+        msg!("Error: Cannot register the router itself as a module");
+        return Err(DiamondError::SelfDispatchForbidden.into());
     }
     
-    // Remove selector
-    state.selectors.retain(|s| s.selector != remove_data.selector);
+    let mut state = DiamondState::load(program_id, diamond_state_account)?;
+    state.check_cuts_allowed(Clock::get()?.slot)?;
+    state.check_config_version(expected_version)?;
     
-    // Serialize back
-    state.serialize(&mut &mut state_data[..])
-        .map_err(|_| ProgramError::InvalidAccountData)?;
+    let mut cut = FacetCut {
+        action: FacetCutAction::Add,
+        selector: Selector::default(),
+        module: alloc_data.module,
+        module_name: alloc_data.module_name,
+        namespace: alloc_data.namespace,
+        function_name: alloc_data.function_signature,
+        is_immutable: alloc_data.is_immutable,
+        standard_accounts: alloc_data.standard_accounts,
+    };
+    authorize_cuts(&state, authority.key, std::slice::from_ref(&cut))?;
+    let (selector, salt) = apply_allocation(&mut state, &cut)?;
+    cut.selector = selector;
+    diamond_authorizer::authorize_cuts(diamond_state_account, &state, accounts, authority.key, std::slice::from_ref(&cut))?;
     
-    msg!("Module removed for selector: {:?}", remove_data.selector);
+    state.save(diamond_state_account)?;
+    let index_accounts =
+        diamond_digest::refresh(program_id, diamond_state_account.key, &state, account_iter.as_slice())?;
+    let audit_accounts = diamond_index::refresh(program_id, diamond_state_account.key, &state, index_accounts)?;
+    diamond_audit::commit_cuts(
+        program_id,
+        diamond_state_account,
+        &state,
+        audit_accounts,
+        authority.key,
+        std::slice::from_ref(&cut),
+    )?;
+    
+    msg!("Selector allocated: {} -> {:?} (salt {})", cut.function_name, selector, salt);
+    return_borsh(&(selector, salt))
+}
+
+/// Route an extra selector to an existing mapping
+///
+/// Accounts: [diamond_state, authority, (routing digest), (selector index), (audit accounts), (authorizer)]
+/// Data: alias (Selector), canonical (Selector), optional expected_version (u64)
+///
+/// Authorized like an `Add` of `alias` in the canonical mapping's namespace.
+pub fn add_alias(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let diamond_state_account = next_account_info(account_iter)?;
+    let authority = next_account_info(account_iter)?;
+    
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let ((alias, canonical), expected_version) = decode_with_version::<(Selector, Selector)>(data)?;
+    
+    let mut state = DiamondState::load(program_id, diamond_state_account)?;
+    state.check_cuts_allowed(Clock::get()?.slot)?;
+    state.check_config_version(expected_version)?;
+    
+    authorize_namespace(&state, authority.key, alias_namespace(&state, canonical).as_ref())?;
+    if let Some(authorizer) = &state.authorizer {
+        diamond_authorizer::authorize(diamond_state_account, authorizer, accounts, authority.key, AuthAction::Add, alias)?;
+    }
+    
+    apply_alias(&mut state, alias, canonical)?;
+    state.save(diamond_state_account)?;
+    let index_accounts =
+        diamond_digest::refresh(program_id, diamond_state_account.key, &state, account_iter.as_slice())?;
+    let audit_accounts = diamond_index::refresh(program_id, diamond_state_account.key, &state, index_accounts)?;
+    diamond_audit::commit_alias(program_id, diamond_state_account, &state, audit_accounts, authority.key, alias, Some(canonical))?;
+    
+    msg!("Alias added: {:?} -> {:?}", alias, canonical);
+    DiamondEvent::AliasChanged { alias, canonical: Some(canonical) }.emit();
+    Ok(())
+}
+
+/// Remove a selector alias
+///
+/// Accounts: [diamond_state, authority, (routing digest), (selector index), (audit accounts), (authorizer)]
+/// Data: alias (Selector), optional expected_version (u64)
+///
+/// Authorized like a `Remove` of `alias` in the canonical mapping's namespace.
+pub fn remove_alias(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let diamond_state_account = next_account_info(account_iter)?;
+    let authority = next_account_info(account_iter)?;
+    
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let (alias, expected_version) = decode_with_version::<Selector>(data)?;
+    
+    let mut state = DiamondState::load(program_id, diamond_state_account)?;
+    state.check_cuts_allowed(Clock::get()?.slot)?;
+    state.check_config_version(expected_version)?;
+    
+    authorize_namespace(&state, authority.key, alias_namespace(&state, alias).as_ref())?;
+    if let Some(authorizer) = &state.authorizer {
+        diamond_authorizer::authorize(diamond_state_account, authorizer, accounts, authority.key, AuthAction::Remove, alias)?;
+    }
+    
+    let canonical = drop_alias(&mut state, alias)?;
+    state.save(diamond_state_account)?;
+    let index_accounts =
+        diamond_digest::refresh(program_id, diamond_state_account.key, &state, account_iter.as_slice())?;
+    let audit_accounts = diamond_index::refresh(program_id, diamond_state_account.key, &state, index_accounts)?;
+    diamond_audit::commit_alias(program_id, diamond_state_account, &state, audit_accounts, authority.key, alias, None)?;
+    
+    msg!("Alias removed: {:?} (was {:?})", alias, canonical);
+    DiamondEvent::AliasChanged { alias, canonical: None }.emit();
     Ok(())
 }
 
@@ -180,6 +793,7 @@ mod tests {
             selector: [u8; 4],
             function_name: String,
             is_immutable: bool,
+            namespace: String,
         }
         
         let data = TestData {
@@ -188,6 +802,7 @@ mod tests {
             selector: [0x01, 0x02, 0x03, 0x04],
             function_name: "test_fn".to_string(),
             is_immutable: false,
+            namespace: "counter".to_string(),
         };
         
         let mut buffer = Vec::new();
@@ -195,5 +810,307 @@ mod tests {
         
         let deserialized = TestData::try_from_slice(&buffer).unwrap();
         assert_eq!(deserialized.module_name, "test_module");
+        assert_eq!(deserialized.namespace, "counter");
+    }
+    
+    #[test]
+    fn test_router_cannot_be_added_as_module() {
+        let program_id = Pubkey::new_unique();
+        let (state_key, authority) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (mut l0, mut l1) = (0u64, 0u64);
+        let (mut d0, mut d1) = (vec![], vec![]);
+        let accounts = vec![
+            AccountInfo::new(&state_key, false, true, &mut l0, &mut d0, &program_id, false, 0),
+            AccountInfo::new(&authority, true, false, &mut l1, &mut d1, &program_id, false, 0),
+        ];
+        let data = borsh::to_vec(&(
+            "router".to_string(),
+            program_id,
+            Selector::from([1, 0, 0, 0]),
+            "loop".to_string(),
+            false,
+            String::new(),
+            0u8,
+        ))
+        .unwrap();
+        
+        assert_eq!(add_module(&program_id, &accounts, &data), Err(DiamondError::SelfDispatchForbidden.into()));
+    }
+    
+    #[test]
+    fn test_apply_cuts_add_replace_remove() {
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        let (v1, v2) = (Pubkey::new_unique(), Pubkey::new_unique());
+        
+        apply_cuts(&mut state, &[
+            FacetCut::add([1, 0, 0, 0], v1, "counter", "increment"),
+            FacetCut::add([2, 0, 0, 0], v1, "counter", "decrement"),
+        ]).unwrap();
+        assert_eq!(state.active_modules.len(), 1);
+        
+        apply_cut(&mut state, &FacetCut::replace([1, 0, 0, 0], v2, "counter_v2")).unwrap();
+        assert_eq!(state.get_module_by_selector([1, 0, 0, 0]), Some(v2));
+        assert_eq!(state.get_mapping([1, 0, 0, 0]).unwrap().function_name_as_str(), "increment");
+        assert_eq!(state.active_modules.len(), 2);
+        
+        apply_cut(&mut state, &FacetCut::remove([2, 0, 0, 0])).unwrap();
+        assert_eq!(state.get_module_by_selector([2, 0, 0, 0]), None);
+    }
+    
+    #[test]
+    fn test_config_version_tracks_routing_changes() {
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        let module = Pubkey::new_unique();
+        
+        apply_cut(&mut state, &FacetCut::add([1, 0, 0, 0], module, "counter", "increment")).unwrap();
+        apply_alias(&mut state, [9, 0, 0, 0].into(), [1, 0, 0, 0].into()).unwrap();
+        assert_eq!(state.config_version, 2);
+        drop_alias(&mut state, [9, 0, 0, 0].into()).unwrap();
+        assert_eq!(state.config_version, 3);
+        
+        assert!(state.check_config_version(None).is_ok());
+        assert!(state.check_config_version(Some(3)).is_ok());
+        assert_eq!(state.check_config_version(Some(2)), Err(DiamondError::StaleConfig));
+        
+        let data = borsh::to_vec(&Selector::from([9, 0, 0, 0])).unwrap();
+        assert_eq!(decode_with_version::<Selector>(&data).unwrap().1, None);
+        let mut versioned = data.clone();
+        versioned.extend_from_slice(&3u64.to_le_bytes());
+        assert_eq!(decode_with_version::<Selector>(&versioned).unwrap().1, Some(3));
+        versioned.push(0);
+        assert!(decode_with_version::<Selector>(&versioned).is_err());
+    }
+    
+    #[test]
+    fn test_authorize_cuts_follows_permission_matrix() {
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        let (admin, tenant, stranger) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let module = Pubkey::new_unique();
+        state.admins.push(admin);
+        state.set_tenant(namespace_bytes("counter").unwrap(), Some(tenant)).unwrap();
+        let in_counter = |cut: FacetCut| FacetCut { namespace: "counter".to_string(), ..cut };
+        apply_cuts(&mut state, &[
+            in_counter(FacetCut::add([1, 0, 0, 0], module, "counter", "increment")),
+            FacetCut::add([2, 0, 0, 0], module, "core", "owner_only"),
+        ]).unwrap();
+        
+        let tenant_add = in_counter(FacetCut::add([3, 0, 0, 0], module, "counter", "decrement"));
+        let global_add = FacetCut::add([4, 0, 0, 0], module, "counter", "reset");
+        
+        for authority in [state.owner, admin] {
+            assert!(authorize_cuts(&state, &authority, &[tenant_add.clone(), global_add.clone()]).is_ok());
+        }
+        assert!(authorize_cuts(&state, &tenant, &[tenant_add, FacetCut::remove([1, 0, 0, 0])]).is_ok());
+        assert_eq!(authorize_cuts(&state, &tenant, &[global_add]), Err(DiamondError::TenantScopeViolation));
+        assert_eq!(
+            authorize_cuts(&state, &tenant, &[FacetCut::remove([2, 0, 0, 0])]),
+            Err(DiamondError::TenantScopeViolation)
+        );
+        assert_eq!(
+            authorize_cuts(&state, &stranger, &[FacetCut::remove([9, 0, 0, 0])]),
+            Err(DiamondError::UnauthorizedAccess)
+        );
+        assert_eq!(authorize_namespace(&state, &tenant, None), Ok(CutRole::Tenant));
+    }
+    
+    #[test]
+    fn test_apply_cuts_is_atomic() {
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        let module = Pubkey::new_unique();
+        
+        let result = apply_cuts(&mut state, &[
+            FacetCut::add([1, 0, 0, 0], module, "counter", "increment"),
+            FacetCut::add([1, 0, 0, 0], module, "counter", "increment"),
+        ]);
+        assert_eq!(result, Err(DiamondError::SelectorCollision));
+        assert!(state.selectors.is_empty());
+    }
+    
+    #[test]
+    fn test_reserved_selector_rejected() {
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        let cut = FacetCut::add(crate::builtins::PAUSE_SELECTOR, Pubkey::new_unique(), "evil", "pause");
+        
+        assert_eq!(apply_cut(&mut state, &cut), Err(DiamondError::ReservedSelector));
+    }
+    
+    #[test]
+    fn test_long_namespace_rejected() {
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        let mut cut = FacetCut::add([1, 0, 0, 0], Pubkey::new_unique(), "counter", "increment");
+        cut.namespace = "counter_v2".to_string();
+        
+        assert_eq!(apply_cut(&mut state, &cut), Err(DiamondError::InvalidCut));
+        assert_eq!(authorize_cuts(&state, &state.owner, &[cut]), Err(DiamondError::InvalidCut));
+        assert!(state.selectors.is_empty());
+    }
+    
+    #[test]
+    fn test_immutable_selector_cannot_be_replaced() {
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        let mut cut = FacetCut::add([1, 0, 0, 0], Pubkey::new_unique(), "core", "owner");
+        cut.is_immutable = true;
+        apply_cut(&mut state, &cut).unwrap();
+        
+        assert_eq!(
+            apply_cut(&mut state, &FacetCut::replace([1, 0, 0, 0], Pubkey::new_unique(), "x")),
+            Err(DiamondError::ImmutableSelector)
+        );
+        assert_eq!(
+            apply_cut(&mut state, &FacetCut::remove([1, 0, 0, 0])),
+            Err(DiamondError::ImmutableSelector)
+        );
+    }
+    
+    #[test]
+    fn test_selector_width_enforced() {
+        let module = Pubkey::new_unique();
+        let mut short = DiamondState::new(Pubkey::new_unique(), 255);
+        short.selector_width = 2;
+        
+        apply_cut(&mut short, &FacetCut::add([1, 2], module, "counter", "increment")).unwrap();
+        assert_eq!(
+            apply_cut(&mut short, &FacetCut::add([1, 3, 0, 4], module, "counter", "decrement")),
+            Err(DiamondError::InvalidSelectorWidth)
+        );
+        // Padding makes [1, 2] and [1, 2, 0, 0] the same selector
+        assert_eq!(
+            apply_cut(&mut short, &FacetCut::add([1, 2, 0, 0], module, "counter", "reset")),
+            Err(DiamondError::SelectorCollision)
+        );
+        
+        let mut wide = DiamondState::new(Pubkey::new_unique(), 255);
+        wide.selector_width = 8;
+        let anchor = Selector::sighash("global", "increment", 8);
+        apply_cut(&mut wide, &FacetCut::add(anchor, module, "counter", "increment")).unwrap();
+        assert_eq!(wide.get_module_by_selector(anchor), Some(module));
+        assert_eq!(wide.get_module_by_selector(anchor.prefix4()), None);
+    }
+    
+    #[test]
+    fn test_tenant_scope() {
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        let facet = Pubkey::new_unique();
+        let ours = namespace_bytes("amm").unwrap();
+        
+        let mut in_ns = FacetCut::add([1, 0, 0, 0], facet, "amm", "swap");
+        in_ns.namespace = "amm".to_string();
+        let mut other_ns = FacetCut::add([2, 0, 0, 0], facet, "lend", "borrow");
+        other_ns.namespace = "lend".to_string();
+        apply_cuts(&mut state, &[in_ns.clone(), other_ns.clone()]).unwrap();
+        
+        assert!(check_tenant_scope(&state, &ours, &[FacetCut::remove([1, 0, 0, 0])]).is_ok());
+        assert_eq!(
+            check_tenant_scope(&state, &ours, &[FacetCut::remove([2, 0, 0, 0])]),
+            Err(DiamondError::TenantScopeViolation)
+        );
+        assert_eq!(
+            check_tenant_scope(&state, &ours, &[FacetCut::add([3, 0, 0, 0], facet, "amm", "quote")]),
+            Err(DiamondError::TenantScopeViolation)
+        );
+    }
+    
+    #[test]
+    fn test_allocation_is_deterministic() {
+        let module = Pubkey::new_unique();
+        let mut cut = FacetCut::add(Selector::default(), module, "counter", "increment(u64)");
+        cut.namespace = "counter".to_string();
+        
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        state.selector_width = 2;
+        let mut replay = state.clone();
+        
+        let first = apply_allocation(&mut state, &cut).unwrap();
+        assert_eq!(first, apply_allocation(&mut replay, &cut).unwrap());
+        assert_eq!(first.1, 0);
+        
+        // Same signature again collides and moves to the next salt
+        let (selector, salt) = apply_allocation(&mut state, &cut).unwrap();
+        assert_eq!(salt, 1);
+        assert_eq!(selector, crate::selector::allocated("counter", "increment(u64)", 2, 1));
+        assert_eq!(state.get_mapping(selector).unwrap().salt, 1);
+        assert_eq!(state.get_mapping(first.0).unwrap().salt, 0);
+    }
+    
+    #[test]
+    fn test_aliases() {
+        let module = Pubkey::new_unique();
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        apply_cuts(&mut state, &[FacetCut::add([1, 0, 0, 1], module, "m", "transfer_v2")]).unwrap();
+        let (canonical, alias) = (Selector::from([1, 0, 0, 1]), Selector::from([0xA9, 0x05, 0x9C, 0xBB]));
+        
+        apply_alias(&mut state, alias, canonical).unwrap();
+        assert_eq!(state.resolve_mapping(alias).unwrap().selector, canonical);
+        assert_eq!(state.aliases_of(canonical), vec![alias]);
+        assert_eq!(state.get_mapping(alias), None);
+        
+        // One hop only, and aliases share the selector namespace
+        assert_eq!(apply_alias(&mut state, [7, 7, 7, 7].into(), alias), Err(DiamondError::ModuleNotFound));
+        assert_eq!(apply_alias(&mut state, alias, canonical), Err(DiamondError::SelectorCollision));
+        assert_eq!(
+            apply_cut(&mut state, &FacetCut::add(alias, module, "m", "other")),
+            Err(DiamondError::SelectorCollision)
+        );
+        assert_eq!(apply_alias(&mut state, [0xD1, 0xA0, 0, 9].into(), canonical), Err(DiamondError::ReservedSelector));
+        
+        // The raw-bytes resolver follows the alias too
+        let mut data = borsh::to_vec(&state).unwrap();
+        data.resize(DiamondState::SPACE, 0);
+        assert_eq!(diamond_core::routing::resolve_selector(&data, &alias), Some(module.to_bytes()));
+        
+        // Removing the canonical mapping drops its aliases
+        apply_cut(&mut state, &FacetCut::remove(canonical)).unwrap();
+        assert!(state.aliases.is_empty());
+        assert_eq!(drop_alias(&mut state, alias), Err(DiamondError::ModuleNotFound));
+    }
+    
+    #[test]
+    fn test_conflict_policy() {
+        let module = Pubkey::new_unique();
+        let named = |selector: [u8; 4], namespace: &str, immutable: bool| {
+            let mut cut = FacetCut::add(selector, module, "m", "get");
+            cut.namespace = namespace.to_string();
+            cut.is_immutable = immutable;
+            cut
+        };
+        
+        for existing_immutable in [false, true] {
+            for new_immutable in [false, true] {
+                let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+                apply_cut(&mut state, &named([1, 0, 0, 0], "counter", existing_immutable)).unwrap();
+                apply_cut(&mut state, &FacetCut::add([2, 0, 0, 0], module, "m", "spare")).unwrap();
+                let taken = DiamondError::SelectorCollision;
+                let name_taken = if existing_immutable { Err(DiamondError::ImmutableNameConflict) } else { Ok(()) };
+                let case = format!("existing immutable: {}, new immutable: {}", existing_immutable, new_immutable);
+                
+                // The selector itself, whatever the namespace or name
+                for namespace in ["counter", "other", ""] {
+                    let mut probe = state.clone();
+                    assert_eq!(apply_cut(&mut probe, &named([1, 0, 0, 0], namespace, new_immutable)), Err(taken), "{}", case);
+                }
+                assert_eq!(apply_alias(&mut state.clone(), [1, 0, 0, 0].into(), [2, 0, 0, 0].into()), Err(taken), "{}", case);
+                
+                // The same name in the same namespace, as an add or a rename
+                let mut probe = state.clone();
+                assert_eq!(apply_cut(&mut probe, &named([3, 0, 0, 0], "counter", new_immutable)), name_taken, "{}", case);
+                if name_taken.is_ok() {
+                    let winner = if new_immutable { [3, 0, 0, 0] } else { [1, 0, 0, 0] };
+                    assert_eq!(probe.get_mapping_by_name("counter::get").unwrap().selector, winner, "{}", case);
+                }
+                let rename = FacetCut { function_name: "get".to_string(), ..FacetCut::replace([2, 0, 0, 0], module, "m") };
+                let mut probe = state.clone();
+                probe.selectors[1].namespace = namespace_bytes("counter").unwrap();
+                assert_eq!(apply_cut(&mut probe, &rename), name_taken, "{}", case);
+                
+                // The same name elsewhere never conflicts; only the exact namespace answers
+                let mut probe = state.clone();
+                apply_cut(&mut probe, &named([4, 0, 0, 0], "other", new_immutable)).unwrap();
+                apply_cut(&mut probe, &named([5, 0, 0, 0], "", new_immutable)).unwrap();
+                assert_eq!(probe.get_mapping_by_name("counter::get").unwrap().selector, [1, 0, 0, 0], "{}", case);
+                assert_eq!(probe.get_mapping_by_name("other::get").unwrap().selector, [4, 0, 0, 0], "{}", case);
+                assert_eq!(probe.get_mapping_by_name("get").unwrap().selector, [5, 0, 0, 0], "{}", case);
+                assert!(probe.get_mapping_by_name("vault::get").is_none(), "{}", case);
+            }
+        }
     }
 }
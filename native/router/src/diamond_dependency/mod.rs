@@ -0,0 +1,144 @@
+/*!
+ * Diamond Dependency Module
+ * Inter-facet dependencies and activation order
+ *
+ * A facet that calls into another facet's namespace declares it, with the
+ * version it needs: the same major, no older. Every cut then keeps the
+ * declarations true: a batch must route a facet's dependencies before the
+ * facet itself, and a namespace can't be removed, or replaced by a module
+ * that no longer satisfies it, while a routed facet still depends on it. Calls
+ * between facets can't dangle.
+ *
+ * Declarations are kept per facet and may precede its first cut; they
+ * outlive the facet's removal until replaced with an empty list.
+ */
+
+use borsh::BorshDeserialize;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::diamond_state::{namespace_bytes, DiamondState, Semver};
+use crate::error::DiamondError;
+use crate::events::DiamondEvent;
+
+/// Replace the dependencies a facet declares (owner only)
+///
+/// Accounts: [diamond_state, owner]
+/// Data: module (Pubkey), dependencies (Vec<(namespace String, min_version Semver)>;
+/// empty clears them)
+pub fn set_dependencies(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let owner = next_account_info(account_iter)?;
+    
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let (module, declared) = <(Pubkey, Vec<(String, Semver)>)>::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    let dependencies = declared
+        .iter()
+        .map(|(namespace, version)| Some((namespace_bytes(namespace)?, *version)))
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| {
+            msg!("Error: Namespaces are at most 8 bytes");
+            ProgramError::InvalidInstructionData
+        })?;
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    if !state.is_owner(owner.key) {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    state.set_dependencies(module, &dependencies).inspect_err(|e| {
+        msg!("Error: Cannot set the dependencies of {}: {}", module, e);
+    })?;
+    state.save(state_account)?;
+    
+    let dependencies = state.dependencies.iter().filter(|d| d.module == module).copied().collect();
+    msg!("Dependencies of {}: {}", module, declared.len());
+    DiamondEvent::DependenciesSet { module, dependencies }.emit();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diamond_cut::{apply_cuts, FacetCut};
+    use crate::diamond_state::FacetDependency;
+    
+    fn cut(selector: u8, module: Pubkey, namespace: &str) -> FacetCut {
+        FacetCut { namespace: namespace.to_string(), ..FacetCut::add([1, 0, 0, selector], module, namespace, "f") }
+    }
+    
+    #[test]
+    fn test_activation_order() {
+        let (token, vault) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        state.set_dependencies(vault, &[(namespace_bytes("token").unwrap(), Semver::INITIAL)]).unwrap();
+        
+        // The dependency must come first in the batch
+        let result = apply_cuts(&mut state, &[cut(2, vault, "vault"), cut(1, token, "token")]);
+        assert_eq!(result, Err(DiamondError::DependencyMissing));
+        assert!(state.selectors.is_empty());
+        apply_cuts(&mut state, &[cut(1, token, "token"), cut(2, vault, "vault")]).unwrap();
+        
+        // Removal is blocked while the dependent is routed, then allowed
+        assert_eq!(apply_cuts(&mut state, &[FacetCut::remove([1, 0, 0, 1])]), Err(DiamondError::DependentsRemain));
+        apply_cuts(&mut state, &[FacetCut::remove([1, 0, 0, 2]), FacetCut::remove([1, 0, 0, 1])]).unwrap();
+        assert!(state.selectors.is_empty());
+    }
+    
+    #[test]
+    fn test_unrelated_cuts_pass_an_unmet_dependency() {
+        let (token, vault, other) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        apply_cuts(&mut state, &[cut(2, vault, "vault")]).unwrap();
+        // Left unmet by state built before the check (e.g. a migration)
+        state.dependencies.push(FacetDependency { module: vault, namespace: namespace_bytes("token").unwrap(), min_version: Semver::INITIAL });
+        
+        apply_cuts(&mut state, &[cut(3, other, "other")]).unwrap();
+        apply_cuts(&mut state, &[cut(1, token, "token")]).unwrap();
+        assert_eq!(state.unmet_dependencies().count(), 0);
+    }
+    
+    #[test]
+    fn test_version_and_declaration_checks() {
+        let (token, vault) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        apply_cuts(&mut state, &[cut(1, token, "token"), cut(2, vault, "vault")]).unwrap();
+        
+        // A routed facet can only declare what is already met
+        let (token_ns, v1_1) = (namespace_bytes("token").unwrap(), Semver::new(1, 1, 0));
+        assert_eq!(state.set_dependencies(vault, &[(token_ns, v1_1)]), Err(DiamondError::DependencyMissing));
+        state.set_dependencies(vault, &[(token_ns, Semver::INITIAL)]).unwrap();
+        state.set_module_version(token, Semver::new(1, 2, 0)).unwrap();
+        state.set_dependencies(vault, &[(token_ns, v1_1)]).unwrap();
+        // A new major doesn't satisfy an old one
+        assert_eq!(
+            state.set_dependencies(vault, &[(token_ns, Semver::new(0, 9, 0))]),
+            Err(DiamondError::DependencyMissing)
+        );
+        
+        // Replacing the provider with an older module leaves the dependent unmet
+        let older = Pubkey::new_unique();
+        let replace = FacetCut::replace([1, 0, 0, 1], older, "token");
+        assert_eq!(apply_cuts(&mut state, &[replace]), Err(DiamondError::DependentsRemain));
+        
+        assert_eq!(
+            state.set_dependencies(token, &[(namespace_bytes("x").unwrap(), Semver::INITIAL); DiamondState::MAX_DEPENDENCIES]),
+            Err(DiamondError::InvalidConfigEntry)
+        );
+        state.set_dependencies(vault, &[]).unwrap();
+        assert!(state.dependencies.is_empty());
+    }
+}
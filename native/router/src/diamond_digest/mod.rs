@@ -0,0 +1,317 @@
+/*!
+ * Diamond Digest Module
+ * Routing digest PDA refreshed on every cut
+ *
+ * A small router-owned account holding the merkle root of the selector table
+ * (see `diamond_core::digest` for the tree), the number of selectors and a
+ * version bumped on every cut and alias change. Light clients and other programs can detect
+ * routing changes, or check a mapping against the root, with one 83-byte
+ * read instead of loading the whole diamond state.
+ *
+ * Once enabled the digest can't fall behind: every instruction that changes
+ * the selector table takes the digest account right after its own accounts
+ * (ahead of any audit accounts) and fails without it.
+ */
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use diamond_core::digest;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    hash::hashv,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    system_program,
+    sysvar::Sysvar,
+};
+
+use crate::diamond_state::{DiamondState, SelectorMapping};
+use crate::diamond_vault;
+use crate::error::DiamondError;
+use crate::events::DiamondEvent;
+use crate::selector::Selector;
+
+pub use diamond_constants::seeds::ROUTING_DIGEST_SEED;
+
+/// Routing table commitment for one diamond
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct RoutingDigest {
+    pub diamond: Pubkey,
+    pub root: [u8; 32],
+    pub selector_count: u16,
+    /// Bumped on every cut and alias change (0 when the digest is created)
+    pub version: u64,
+    pub updated_slot: u64,
+    pub bump: u8,
+}
+
+impl RoutingDigest {
+    pub const SPACE: usize =
+        32 + // diamond
+        32 + // root
+        2 +  // selector_count
+        8 +  // version
+        8 +  // updated_slot
+        1;   // bump
+    
+    pub fn find_address(program_id: &Pubkey, diamond: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[ROUTING_DIGEST_SEED, diamond.as_ref()], program_id)
+    }
+}
+
+/// sha256 through the syscall on-chain
+pub fn hash_parts(parts: &[&[u8]]) -> [u8; 32] {
+    hashv(parts).to_bytes()
+}
+
+/// Selector mappings in canonical (selector byte) order
+pub fn canonical_mappings(state: &DiamondState) -> Vec<&SelectorMapping> {
+    let mut mappings: Vec<&SelectorMapping> = state.selectors.iter().collect();
+    mappings.sort_by_key(|mapping| mapping.selector.0);
+    mappings
+}
+
+pub fn mapping_leaf(mapping: &SelectorMapping) -> [u8; 32] {
+    digest::leaf(hash_parts, &borsh::to_vec(mapping).unwrap_or_default())
+}
+
+/// Merkle leaves of the selector table, in canonical order
+pub fn routing_leaves(state: &DiamondState) -> Vec<[u8; 32]> {
+    canonical_mappings(state).into_iter().map(mapping_leaf).collect()
+}
+
+pub fn routing_root(state: &DiamondState) -> [u8; 32] {
+    digest::root(hash_parts, &routing_leaves(state))
+}
+
+/// Merkle proof for `selector`: its leaf index and sibling path
+pub fn mapping_proof(state: &DiamondState, selector: Selector) -> Option<(u16, Vec<[u8; 32]>)> {
+    let index = canonical_mappings(state).iter().position(|mapping| mapping.selector == selector)?;
+    let path = digest::proof(hash_parts, &routing_leaves(state), index)?;
+    Some((index as u16, path))
+}
+
+/// Check a client-supplied mapping against the digest root
+pub fn verify_mapping(
+    routing_digest: &RoutingDigest,
+    mapping: &SelectorMapping,
+    index: u16,
+    path: &[[u8; 32]],
+) -> bool {
+    digest::verify(
+        hash_parts,
+        mapping_leaf(mapping),
+        index as usize,
+        routing_digest.selector_count as usize,
+        path,
+        &routing_digest.root,
+    )
+}
+
+/// Load the routing digest of `diamond`, checking its address and owner
+///
+/// The address is checked against the stored bump, so `dispatch_proven`
+/// pays for one `create_program_address` rather than a bump search.
+pub fn load(program_id: &Pubkey, diamond: &Pubkey, account: &AccountInfo) -> Result<RoutingDigest, ProgramError> {
+    if account.owner != program_id {
+        msg!("Error: Routing digest {} is not owned by the router", account.key);
+        return Err(ProgramError::InvalidSeeds);
+    }
+    let routing_digest = RoutingDigest::deserialize(&mut &account.try_borrow_data()?[..])
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    let expected = Pubkey::create_program_address(
+        &[ROUTING_DIGEST_SEED, diamond.as_ref(), &[routing_digest.bump]],
+        program_id,
+    );
+    if expected.as_ref() != Ok(account.key) || &routing_digest.diamond != diamond {
+        msg!("Error: {} is not the routing digest of {}", account.key, diamond);
+        return Err(ProgramError::InvalidSeeds);
+    }
+    Ok(routing_digest)
+}
+
+fn write(account: &AccountInfo, routing_digest: &RoutingDigest) -> ProgramResult {
+    let mut data = account.try_borrow_mut_data()?;
+    routing_digest.serialize(&mut &mut data[..])
+        .map_err(|_| ProgramError::AccountDataTooSmall)
+}
+
+/// Bring the digest up to date after a cut
+///
+/// A no-op for diamonds without a digest. Otherwise `accounts` must start
+/// with the digest PDA; returns the accounts after it (the audit accounts).
+pub fn refresh<'a, 'info>(
+    program_id: &Pubkey,
+    diamond: &Pubkey,
+    state: &DiamondState,
+    accounts: &'a [AccountInfo<'info>],
+) -> Result<&'a [AccountInfo<'info>], ProgramError> {
+    if !state.routing_digest {
+        return Ok(accounts);
+    }
+    let (digest_account, rest) = match accounts.split_first() {
+        Some(split) => split,
+        None => {
+            msg!("Error: Routing digest account required");
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+    };
+    let mut routing_digest = load(program_id, diamond, digest_account)?;
+    routing_digest.root = routing_root(state);
+    routing_digest.selector_count = state.selectors.len() as u16;
+    routing_digest.version = routing_digest.version.saturating_add(1);
+    routing_digest.updated_slot = Clock::get()?.slot;
+    write(digest_account, &routing_digest)?;
+    
+    DiamondEvent::RoutingDigestUpdated {
+        root: routing_digest.root,
+        selector_count: routing_digest.selector_count,
+        version: routing_digest.version,
+    }
+    .emit();
+    Ok(rest)
+}
+
+/// Create the routing digest and keep it updated from now on (owner only)
+///
+/// Accounts: [diamond_state, owner, routing_digest, payer, system_program]
+pub fn enable_routing_digest(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let owner = next_account_info(account_iter)?;
+    let digest_account = next_account_info(account_iter)?;
+    let payer = next_account_info(account_iter)?;
+    let system_program_account = next_account_info(account_iter)?;
+    
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if system_program_account.key != &system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    if !state.is_owner(owner.key) {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    if state.routing_digest {
+        msg!("Error: Routing digest already enabled");
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    let (expected, bump) = RoutingDigest::find_address(program_id, state_account.key);
+    if digest_account.key != &expected {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    
+    diamond_vault::create_pda(
+        program_id,
+        state_account.key,
+        state.may_spend_rent_vault(owner.key),
+        payer,
+        digest_account,
+        system_program_account,
+        RoutingDigest::SPACE,
+        &[ROUTING_DIGEST_SEED, state_account.key.as_ref(), &[bump]],
+    )?;
+    
+    let routing_digest = RoutingDigest {
+        diamond: *state_account.key,
+        root: routing_root(&state),
+        selector_count: state.selectors.len() as u16,
+        version: 0,
+        updated_slot: Clock::get()?.slot,
+        bump,
+    };
+    write(digest_account, &routing_digest)?;
+    
+    state.routing_digest = true;
+    state.save(state_account)?;
+    
+    msg!("Routing digest enabled: {}", digest_account.key);
+    DiamondEvent::RoutingDigestUpdated {
+        root: routing_digest.root,
+        selector_count: routing_digest.selector_count,
+        version: 0,
+    }
+    .emit();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diamond_cut::{apply_cuts, FacetCut};
+    use solana_program::clock::Epoch;
+    
+    #[test]
+    fn test_load_checks_address_with_stored_bump() {
+        let (program_id, diamond) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (address, bump) = RoutingDigest::find_address(&program_id, &diamond);
+        let routing_digest = RoutingDigest { diamond, root: [7; 32], selector_count: 3, version: 2, updated_slot: 9, bump };
+        let load_at = |key: &Pubkey, owner: &Pubkey| {
+            let (mut lamports, mut data) = (0, borsh::to_vec(&routing_digest).unwrap());
+            let account = AccountInfo::new(key, false, false, &mut lamports, &mut data, owner, false, Epoch::default());
+            load(&program_id, &diamond, &account)
+        };
+        assert_eq!(load_at(&address, &program_id), Ok(routing_digest.clone()));
+        assert_eq!(load_at(&Pubkey::new_unique(), &program_id), Err(ProgramError::InvalidSeeds));
+        assert_eq!(load_at(&address, &Pubkey::new_unique()), Err(ProgramError::InvalidSeeds));
+    }
+    
+    #[test]
+    fn test_root_matches_core_over_account_bytes() {
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        assert_eq!(routing_root(&state), digest::EMPTY_LEAF);
+        
+        // Registration order differs from selector order
+        apply_cuts(&mut state, &[
+            FacetCut::add([9, 0, 0, 1], Pubkey::new_unique(), "b", "later"),
+            FacetCut::add([1, 0, 0, 1], Pubkey::new_unique(), "a", "earlier"),
+            FacetCut::add([5, 0, 0, 1], Pubkey::new_unique(), "a", "middle"),
+        ]).unwrap();
+        let root = routing_root(&state);
+        
+        let mut account_data = borsh::to_vec(&state).unwrap();
+        account_data.resize(DiamondState::SPACE, 0);
+        assert_eq!(digest::state_root(digest::sha256, &account_data), Some(root));
+        
+        // Any routing change moves the root
+        apply_cuts(&mut state, &[FacetCut::replace([5, 0, 0, 1], Pubkey::new_unique(), "c")]).unwrap();
+        assert_ne!(routing_root(&state), root);
+    }
+    
+    #[test]
+    fn test_mapping_proofs() {
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        let cuts: Vec<FacetCut> = (0..5u8)
+            .map(|i| FacetCut::add([7, 0, 0, 5 - i], Pubkey::new_unique(), "a", "f"))
+            .collect();
+        apply_cuts(&mut state, &cuts).unwrap();
+        let routing_digest = RoutingDigest {
+            diamond: Pubkey::new_unique(),
+            root: routing_root(&state),
+            selector_count: state.selectors.len() as u16,
+            version: 1,
+            updated_slot: 0,
+            bump: 255,
+        };
+        
+        for mapping in &state.selectors {
+            let (index, path) = mapping_proof(&state, mapping.selector).unwrap();
+            assert!(verify_mapping(&routing_digest, mapping, index, &path));
+            
+            // A forged module or index fails
+            let mut forged = mapping.clone();
+            forged.module = Pubkey::new_unique();
+            assert!(!verify_mapping(&routing_digest, &forged, index, &path));
+            assert!(!verify_mapping(&routing_digest, mapping, index ^ 1, &path));
+        }
+        assert_eq!(mapping_proof(&state, Selector::from([9, 9, 9, 9])), None);
+    }
+}
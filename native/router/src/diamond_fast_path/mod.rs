@@ -0,0 +1,321 @@
+/*!
+ * Diamond Fast Path Module
+ * Tiny PDA holding the hottest selector mappings
+ *
+ * `dispatch_fast` looks a pinned selector up in this ~500-byte account
+ * instead of searching the selector table, then hands the mapping to the
+ * regular dispatch pipeline the way `dispatch_indexed` does, so a pinned
+ * call gets every check an unpinned one does. A pinned mapping that no
+ * longer matches the table, or an unpinned selector, is looked up as usual,
+ * so a stale fast path is only ever slower.
+ */
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use diamond_core::routing;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    system_program,
+    sysvar::Sysvar,
+};
+
+use crate::diamond_router::{route, RouteOptions};
+use crate::diamond_state::{DiamondState, SelectorMapping};
+use crate::error::DiamondError;
+use crate::events::DiamondEvent;
+use crate::selector::Selector;
+
+pub use diamond_constants::seeds::FAST_PATH_SEED;
+
+/// Pinned mappings of one diamond
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct FastPath {
+    pub diamond: Pubkey,
+    /// Copied from the diamond state (fixed at initialization)
+    pub selector_width: u8,
+    pub entries: Vec<SelectorMapping>,
+    pub bump: u8,
+}
+
+impl FastPath {
+    pub const MAX_ENTRIES: usize = diamond_constants::capacity::MAX_FAST_PATH_ENTRIES;
+    
+    pub const SPACE: usize =
+        32 + // diamond
+        1 +  // selector_width
+        4 + (Self::MAX_ENTRIES * routing::SELECTOR_MAPPING_LEN) + // entries vec
+        1;   // bump
+    
+    pub fn find_address(program_id: &Pubkey, diamond: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[FAST_PATH_SEED, diamond.as_ref()], program_id)
+    }
+    
+    /// Pinned mapping for `selector`, if it still matches the live table
+    pub fn live_entry(&self, state_data: &[u8], selector: &Selector) -> Option<&SelectorMapping> {
+        let entry = self.entries.iter().find(|mapping| &mapping.selector == selector)?;
+        let encoded = borsh::to_vec(entry).ok()?;
+        (routing::find_mapping(state_data, selector) == Some(&encoded[..])).then_some(entry)
+    }
+}
+
+/// Load the fast path of `diamond`, checking its address and owner
+pub fn load(program_id: &Pubkey, diamond: &Pubkey, account: &AccountInfo) -> Result<FastPath, ProgramError> {
+    let (expected, _) = FastPath::find_address(program_id, diamond);
+    if account.key != &expected || account.owner != program_id {
+        msg!("Error: Invalid fast path. Expected: {}", expected);
+        return Err(ProgramError::InvalidSeeds);
+    }
+    FastPath::deserialize(&mut &account.try_borrow_data()?[..])
+        .map_err(|_| ProgramError::InvalidAccountData)
+}
+
+fn write(account: &AccountInfo, fast_path: &FastPath) -> ProgramResult {
+    let mut data = account.try_borrow_mut_data()?;
+    fast_path.serialize(&mut &mut data[..])
+        .map_err(|_| ProgramError::AccountDataTooSmall)
+}
+
+/// Room left and the same selector width as the state
+fn check_pinnable(state: &DiamondState, fast_path: &FastPath) -> Result<(), DiamondError> {
+    if fast_path.entries.len() >= FastPath::MAX_ENTRIES {
+        msg!("Error: Fast path holds {} entries (max)", FastPath::MAX_ENTRIES);
+        return Err(DiamondError::FastPathRejected);
+    }
+    if state.selector_width != fast_path.selector_width {
+        return Err(DiamondError::FastPathRejected);
+    }
+    Ok(())
+}
+
+/// Pin a selector's mapping to the fast path (owner or admin)
+///
+/// Accounts: [diamond_state, authority, fast_path, payer, system_program]
+/// Data: Selector
+pub fn pin(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let authority = next_account_info(account_iter)?;
+    let fast_path_account = next_account_info(account_iter)?;
+    let payer = next_account_info(account_iter)?;
+    let system_program_account = next_account_info(account_iter)?;
+    
+    if !authority.is_signer || !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if system_program_account.key != &system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    let selector = Selector::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let state = DiamondState::load(program_id, state_account)?;
+    if !state.has_authority(authority.key) {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    let mapping = state.get_mapping(selector).ok_or_else(|| {
+        msg!("Error: Selector {:?} not found", selector);
+        DiamondError::ModuleNotFound
+    })?;
+    
+    // First pin allocates the PDA
+    let (expected, bump) = FastPath::find_address(program_id, state_account.key);
+    if fast_path_account.key != &expected {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if fast_path_account.data_is_empty() {
+        let rent = Rent::get()?;
+        invoke_signed(
+            &system_instruction::create_account(
+                payer.key,
+                fast_path_account.key,
+                rent.minimum_balance(FastPath::SPACE),
+                FastPath::SPACE as u64,
+                program_id,
+            ),
+            &[payer.clone(), fast_path_account.clone(), system_program_account.clone()],
+            &[&[FAST_PATH_SEED, state_account.key.as_ref(), &[bump]]],
+        )?;
+        write(
+            fast_path_account,
+            &FastPath { diamond: *state_account.key, selector_width: state.selector_width, entries: Vec::new(), bump },
+        )?;
+    }
+    let mut fast_path = load(program_id, state_account.key, fast_path_account)?;
+    
+    // Re-pinning refreshes a stale copy
+    fast_path.entries.retain(|entry| entry.selector != selector);
+    check_pinnable(&state, &fast_path)?;
+    fast_path.entries.push(mapping.clone());
+    write(fast_path_account, &fast_path)?;
+    
+    msg!("Pinned {:?} -> {} ({} entries)", selector, mapping.module, fast_path.entries.len());
+    DiamondEvent::FastPathChanged { selector, pinned: true }.emit();
+    Ok(())
+}
+
+/// Drop a selector from the fast path (owner or admin)
+///
+/// Accounts: [diamond_state, authority, fast_path]
+/// Data: Selector
+pub fn unpin(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let authority = next_account_info(account_iter)?;
+    let fast_path_account = next_account_info(account_iter)?;
+    
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let selector = Selector::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let state = DiamondState::load(program_id, state_account)?;
+    if !state.has_authority(authority.key) {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    let mut fast_path = load(program_id, state_account.key, fast_path_account)?;
+    let before = fast_path.entries.len();
+    fast_path.entries.retain(|entry| entry.selector != selector);
+    if fast_path.entries.len() == before {
+        msg!("Error: Selector {:?} is not pinned", selector);
+        return Err(DiamondError::ModuleNotFound.into());
+    }
+    write(fast_path_account, &fast_path)?;
+    
+    msg!("Unpinned {:?}", selector);
+    DiamondEvent::FastPathChanged { selector, pinned: false }.emit();
+    Ok(())
+}
+
+/// Dispatch through the fast path
+///
+/// Accounts: [diamond_state, fast_path, module, ...remaining]
+/// Data: ix_data (Vec<u8>), as for `dispatch`
+///
+/// A pinned selector whose entry still matches the live table skips the
+/// table search; the call then goes through `dispatch`'s checks like any
+/// other. Anything else is looked up as `dispatch` would (minus the fast
+/// path account).
+pub fn dispatch_fast(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let ix_data = Vec::<u8>::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    let (state_account, rest) = accounts.split_first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let (fast_path_account, rest) = rest.split_first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    
+    if state_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let fast_path = load(program_id, state_account.key, fast_path_account)?;
+    let selector = Selector::read(&ix_data, fast_path.selector_width).ok_or(ProgramError::InvalidInstructionData)?;
+    let pinned = fast_path.live_entry(&state_account.try_borrow_data()?, &selector).cloned();
+    if pinned.is_none() {
+        msg!("Fast path miss for {:?}", selector);
+    }
+    
+    let mut route_accounts = Vec::with_capacity(accounts.len() - 1);
+    route_accounts.push(state_account.clone());
+    route_accounts.extend_from_slice(rest);
+    route(program_id, &route_accounts, ix_data, RouteOptions { resolved: pinned.as_ref(), ..Default::default() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diamond_cut::{apply_cut, FacetCut};
+    
+    #[test]
+    fn test_live_entry_tracks_the_table() {
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        apply_cut(&mut state, &FacetCut::add([1, 0, 0, 0], Pubkey::new_unique(), "counter", "increment")).unwrap();
+        let selector = Selector::from([1, 0, 0, 0]);
+        let fast_path = FastPath {
+            diamond: Pubkey::new_unique(),
+            selector_width: state.selector_width,
+            entries: vec![state.get_mapping(selector).unwrap().clone()],
+            bump: 255,
+        };
+        
+        let data = borsh::to_vec(&state).unwrap();
+        assert!(fast_path.live_entry(&data, &selector).is_some());
+        assert!(fast_path.live_entry(&data, &Selector::from([2, 0, 0, 0])).is_none());
+        
+        // A replaced mapping no longer matches its pinned copy
+        apply_cut(&mut state, &FacetCut::replace(selector, Pubkey::new_unique(), "counter_v2")).unwrap();
+        assert!(fast_path.live_entry(&borsh::to_vec(&state).unwrap(), &selector).is_none());
+    }
+    
+    #[test]
+    fn test_dispatch_header_reads_raw_state() {
+        use crate::diamond_state::{AuthorityChange, PauseWindow, PendingAuthorityChange};
+        
+        let mut state = crate::layout::worst_case_state();
+        state.pause_expiry_slot = Some(77);
+        let changes = [
+            None,
+            Some(AuthorityChange::Owner(Pubkey::new_unique())),
+            Some(AuthorityChange::Governance(None)),
+            Some(AuthorityChange::Governance(Some(Pubkey::new_unique()))),
+            Some(AuthorityChange::Delay(9)),
+            Some(AuthorityChange::PauseAuthority(Pubkey::new_unique())),
+        ];
+        for change in changes {
+            state.pending_change = change.map(|change| PendingAuthorityChange { change, effective_slot: 5 });
+            let data = borsh::to_vec(&state).unwrap();
+            let header = routing::dispatch_header(&data).unwrap();
+            
+            assert_eq!(header.selector_width, state.selector_width);
+            assert_eq!(header.call_active, state.active_call.is_some());
+            assert_eq!(header.return_overflow, state.return_overflow);
+            assert_eq!(header.is_dispatch_paused_at(76), state.is_dispatch_paused_at(76));
+            assert_eq!(header.is_dispatch_paused_at(77), state.is_dispatch_paused_at(77));
+            assert_eq!(
+                (header.max_accounts, header.max_payload_len, header.max_depth),
+                (state.dispatch_limits.max_accounts, state.dispatch_limits.max_payload_len, state.dispatch_limits.max_depth)
+            );
+            for spec in &state.payload_specs {
+                assert_eq!(header.payload_spec(&data, &spec.selector), Some(*spec));
+            }
+            let mapping = &state.selectors[3];
+            assert_eq!(routing::find_mapping(&data, &mapping.selector), Some(&borsh::to_vec(mapping).unwrap()[..]));
+        }
+        
+        state.pause_windows = vec![PauseWindow { start_slot: 100, end_slot: 200 }];
+        let data = borsh::to_vec(&state).unwrap();
+        let header = routing::dispatch_header(&data).unwrap();
+        for slot in [99, 100, 199, 200] {
+            assert_eq!(header.in_pause_window(&data, slot), state.pause_window_at(slot).is_some());
+        }
+        assert_eq!(routing::dispatch_header(&[0u8; 16]), None);
+    }
+    
+    #[test]
+    fn test_pinnable() {
+        let state = DiamondState::new(Pubkey::new_unique(), 255);
+        let mut fast_path = FastPath { diamond: Pubkey::new_unique(), selector_width: 4, entries: Vec::new(), bump: 255 };
+        let mapping = SelectorMapping::new([1, 0, 0, 0], Pubkey::new_unique(), "f", false);
+        
+        assert_eq!(check_pinnable(&state, &fast_path), Ok(()));
+        fast_path.entries = vec![mapping; FastPath::MAX_ENTRIES];
+        assert_eq!(check_pinnable(&state, &fast_path), Err(DiamondError::FastPathRejected));
+    }
+}
@@ -0,0 +1,188 @@
+/*!
+ * Diamond Guard Module
+ * Transaction introspection for guarded selectors
+ *
+ * A guarded selector only dispatches when none of the transaction's
+ * top-level instructions calls a program on the diamond's denylist, so a
+ * flash-loan program can't sandwich the call (borrow before, repay after).
+ * Callers pass the instructions sysvar among the remaining accounts. The
+ * sysvar only lists top-level instructions: the current one is checked too,
+ * so a denylisted program calling the router as its own instruction is
+ * caught, but programs reached through CPI, whether from another
+ * instruction or from the program that invoked the router, are not seen.
+ */
+
+use borsh::BorshDeserialize;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    sysvar::instructions::{self, load_instruction_at_checked},
+};
+
+use crate::diamond_state::{DiamondState, TxGuard};
+use crate::error::DiamondError;
+use crate::events::DiamondEvent;
+use crate::selector::Selector;
+
+/// First program of `programs` on the denylist
+pub fn denied_program(denylist: &[Pubkey], programs: impl IntoIterator<Item = Pubkey>) -> Option<Pubkey> {
+    programs.into_iter().find(|program| denylist.contains(program))
+}
+
+/// Programs of every top-level instruction in the transaction
+fn transaction_programs(sysvar: &AccountInfo) -> Result<Vec<Pubkey>, ProgramError> {
+    let count = {
+        let data = sysvar.try_borrow_data()?;
+        let bytes = data.get(..2).ok_or(ProgramError::InvalidAccountData)?;
+        u16::from_le_bytes([bytes[0], bytes[1]]) as usize
+    };
+    (0..count)
+        .map(|i| load_instruction_at_checked(i, sysvar).map(|ix| ix.program_id))
+        .collect()
+}
+
+/// Refuse the call when the transaction also invokes a denylisted program
+///
+/// `accounts` must include the instructions sysvar.
+pub fn check_transaction(guard: &TxGuard, selector: &Selector, accounts: &[AccountInfo]) -> ProgramResult {
+    let sysvar = accounts.iter().find(|a| instructions::check_id(a.key)).ok_or_else(|| {
+        msg!("Error: Guarded selector {:?} needs the instructions sysvar", selector);
+        ProgramError::NotEnoughAccountKeys
+    })?;
+    if let Some(program) = denied_program(&guard.denylist, transaction_programs(sysvar)?) {
+        msg!("Error: {:?} refused in a transaction that calls {}", selector, program);
+        return Err(DiamondError::DeniedProgramInTransaction.into());
+    }
+    Ok(())
+}
+
+/// Replace the transaction denylist (owner only)
+///
+/// Accounts: [diamond_state, owner]
+/// Data: Vec<Pubkey> (empty clears it)
+pub fn set_tx_denylist(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let owner = next_account_info(account_iter)?;
+    
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let denylist = Vec::<Pubkey>::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    if denylist.len() > TxGuard::MAX_DENYLIST {
+        msg!("Error: Denylist holds at most {} programs", TxGuard::MAX_DENYLIST);
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    // The router's own instruction would trip every guarded call
+    if denylist.contains(program_id) {
+        msg!("Error: The router cannot deny itself");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    if !state.is_owner(owner.key) {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    state.tx_guard.denylist = denylist.clone();
+    state.save(state_account)?;
+    
+    msg!("Transaction denylist: {} programs", denylist.len());
+    DiamondEvent::TxDenylistChanged { denylist }.emit();
+    Ok(())
+}
+
+/// Guard or unguard a selector (owner only)
+///
+/// Accounts: [diamond_state, owner]
+/// Data: selector (Selector), guarded (bool)
+pub fn set_selector_guard(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let owner = next_account_info(account_iter)?;
+    
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let (selector, guarded) = <(Selector, bool)>::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    if !state.is_owner(owner.key) {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    if state.get_mapping(selector).is_none() {
+        msg!("Error: Selector {:?} not found", selector);
+        return Err(DiamondError::ModuleNotFound.into());
+    }
+    let selectors = &mut state.tx_guard.selectors;
+    selectors.retain(|s| s != &selector);
+    if guarded {
+        if selectors.len() >= TxGuard::MAX_SELECTORS {
+            msg!("Error: At most {} selectors can be guarded", TxGuard::MAX_SELECTORS);
+            return Err(DiamondError::SelectorCapacityExceeded.into());
+        }
+        selectors.push(selector);
+    }
+    state.save(state_account)?;
+    
+    msg!("Selector {:?} {}", selector, if guarded { "guarded" } else { "unguarded" });
+    DiamondEvent::SelectorGuardChanged { selector, guarded }.emit();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_program::clock::Epoch;
+    use solana_program::sysvar::instructions::{BorrowedAccountMeta, BorrowedInstruction};
+    
+    #[test]
+    fn test_denied_program() {
+        let (lender, facet) = (Pubkey::new_unique(), Pubkey::new_unique());
+        assert_eq!(denied_program(&[lender], [facet, lender]), Some(lender));
+        assert_eq!(denied_program(&[lender], [facet]), None);
+        assert_eq!(denied_program(&[], [lender]), None);
+    }
+    
+    #[test]
+    fn test_check_transaction_reads_the_sysvar() {
+        let (router, lender) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let payer = Pubkey::new_unique();
+        let ix = |program_id| BorrowedInstruction {
+            program_id,
+            accounts: vec![BorrowedAccountMeta { pubkey: &payer, is_signer: true, is_writable: true }],
+            data: &[],
+        };
+        let mut data = instructions::construct_instructions_data(&[ix(&lender), ix(&router), ix(&lender)]);
+        
+        let key = instructions::id();
+        let owner = solana_program::sysvar::id();
+        let mut lamports = 0;
+        let sysvar = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, Epoch::default());
+        let selector = Selector::from([1, 0, 0, 1]);
+        
+        let mut guard = TxGuard { denylist: vec![lender], selectors: vec![selector] };
+        assert_eq!(
+            check_transaction(&guard, &selector, std::slice::from_ref(&sysvar)),
+            Err(DiamondError::DeniedProgramInTransaction.into())
+        );
+        assert_eq!(check_transaction(&guard, &selector, &[]), Err(ProgramError::NotEnoughAccountKeys));
+        
+        guard.denylist = vec![Pubkey::new_unique()];
+        assert_eq!(check_transaction(&guard, &selector, &[sysvar]), Ok(()));
+    }
+}
@@ -0,0 +1,253 @@
+/*!
+ * Diamond Health Module
+ * Liveness probes and shared health status for registered facets
+ *
+ * Every facet should answer `HEALTH_SELECTOR` (no accounts, no arguments)
+ * with success. `probe_facet` resolves a routed selector and calls its facet
+ * with that selector, reporting the outcome via return data.
+ *
+ * Ecosystems running many diamonds on the same facets can also share a
+ * health registry: a program (`health-registry` in this workspace, or any
+ * program keeping the same account layout) that publishes one `FacetStatus`
+ * per facet program, carrying a kill flag and the severity of its open
+ * advisory. A diamond opts in with `health::set_registry`; from then on every
+ * call must pass the status account of its facet among the remaining
+ * accounts, and calls to a killed or flagged facet are refused. A status
+ * account that was never published reads as healthy.
+ */
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    instruction::Instruction,
+    msg,
+    program::invoke,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::builtins::HEALTH_SELECTOR;
+use crate::diamond_loupe::return_borsh;
+use crate::diamond_state::{fixed_str, DiamondState, HealthRegistry};
+use crate::error::DiamondError;
+use crate::events::DiamondEvent;
+use crate::selector::Selector;
+
+pub use diamond_core::facet_status::{severity, FacetStatus, FACET_STATUS_SEED};
+
+/// Probe outcome for one facet
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HealthStatus {
+    /// Facet answered `HEALTH_SELECTOR`
+    Healthy,
+    /// Facet account is missing or not an executable program
+    NotDeployed,
+}
+
+/// Return data of `probe_facet`
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct HealthReport {
+    pub selector: Selector,
+    pub module: Pubkey,
+    pub status: HealthStatus,
+}
+
+/// Probe the facet behind a routed selector
+///
+/// Accounts: [diamond_state, facet_program]
+/// Data: routed selector (`Selector`)
+///
+/// A facet that rejects the health call fails the CPI, and with it the
+/// transaction; callers should simulate and treat an error as unhealthy.
+pub fn probe_facet(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let facet_program = next_account_info(account_iter)?;
+    
+    let selector = Selector::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let state = DiamondState::load(program_id, state_account)?;
+    let module = state
+        .get_module_by_selector(selector)
+        .ok_or(DiamondError::ModuleNotFound)?;
+    if facet_program.key != &module {
+        msg!("Error: Probe account {} is not the facet for {:?}", facet_program.key, selector);
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    
+    let status = if facet_program.executable {
+        invoke(
+            &Instruction {
+                program_id: module,
+                accounts: vec![],
+                data: HEALTH_SELECTOR.to_vec(),
+            },
+            std::slice::from_ref(facet_program),
+        )?;
+        HealthStatus::Healthy
+    } else {
+        HealthStatus::NotDeployed
+    };
+    
+    msg!("Probe {:?} -> {}: {:?}", selector, module, status);
+    return_borsh(&HealthReport { selector, module, status })
+}
+
+/// Status account of `facet` under a health registry program
+pub fn facet_status_address(registry: &Pubkey, facet: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[FACET_STATUS_SEED, facet.as_ref()], registry)
+}
+
+/// Refuse a call to `facet` unless its status, passed among `accounts`,
+/// clears the diamond's registry policy
+///
+/// A published status carries the bump the registry derived it with, so its
+/// address is checked with one `create_program_address`. Only a facet the
+/// registry never published pays for the bump search, to prove the empty
+/// account passed is its status address.
+pub fn check_status(registry: &HealthRegistry, facet: &Pubkey, accounts: &[AccountInfo]) -> Result<(), DiamondError> {
+    let published = accounts.iter().find_map(|account| {
+        if account.owner != &registry.program {
+            return None;
+        }
+        let status = FacetStatus::read(&account.try_borrow_data().ok()?)?;
+        (status.facet == facet.to_bytes()).then_some((account, status))
+    });
+    let status = match published {
+        Some((account, status)) => {
+            let address = Pubkey::create_program_address(
+                &[FACET_STATUS_SEED, facet.as_ref(), &[status.bump]],
+                &registry.program,
+            );
+            if address.as_ref() != Ok(account.key) {
+                msg!("Error: {} is not the registry status of {}", account.key, facet);
+                return Err(DiamondError::FacetBlocked);
+            }
+            status
+        }
+        None => {
+            let (address, _) = facet_status_address(&registry.program, facet);
+            let account = accounts.iter().find(|a| a.key == &address).ok_or_else(|| {
+                msg!("Error: Calls to {} need its registry status {}", facet, address);
+                DiamondError::FacetBlocked
+            })?;
+            if !account.data_is_empty() {
+                msg!("Error: {} is not a status account of registry {}", address, registry.program);
+                return Err(DiamondError::FacetBlocked);
+            }
+            return Ok(());
+        }
+    };
+    if status.blocks(registry.min_severity) {
+        msg!(
+            "Error: Facet {} is {} (severity {}, advisory {:?})",
+            facet,
+            if status.killed { "killed" } else { "flagged" },
+            status.severity,
+            fixed_str(&status.advisory)
+        );
+        return Err(DiamondError::FacetBlocked);
+    }
+    Ok(())
+}
+
+/// Opt in to, change or leave a shared health registry (owner only)
+///
+/// Accounts: [diamond_state, owner]
+/// Data: Option<HealthRegistry> (`None` stops honoring the registry)
+pub fn set_health_registry(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let owner = next_account_info(account_iter)?;
+    
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let registry = Option::<HealthRegistry>::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    if !state.is_owner(owner.key) {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    if let Some(registry) = &registry {
+        if &registry.program == program_id {
+            return Err(DiamondError::SelfDispatchForbidden.into());
+        }
+        if registry.min_severity > severity::CRITICAL {
+            msg!("Error: Severity threshold {} is above critical ({})", registry.min_severity, severity::CRITICAL);
+            return Err(ProgramError::InvalidInstructionData);
+        }
+    }
+    state.health_registry = registry;
+    state.save(state_account)?;
+    
+    match &registry {
+        Some(registry) => msg!(
+            "Honoring health registry {} (blocking from severity {})",
+            registry.program,
+            registry.min_severity
+        ),
+        None => msg!("Health registry dropped"),
+    }
+    DiamondEvent::HealthRegistryChanged { registry }.emit();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_program::clock::Epoch;
+    
+    fn status(registry: &Pubkey, facet: &Pubkey, killed: bool, severity: u8) -> Vec<u8> {
+        let status = FacetStatus {
+            facet: facet.to_bytes(),
+            killed,
+            severity,
+            advisory: crate::diamond_state::fixed_bytes("GHSA-0000"),
+            updated_at: 0,
+            bump: facet_status_address(registry, facet).1,
+        };
+        borsh::to_vec(&status).unwrap()
+    }
+    
+    #[test]
+    fn test_check_status() {
+        let (facet, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let registry = HealthRegistry { program: Pubkey::new_unique(), min_severity: severity::HIGH };
+        let (address, _) = facet_status_address(&registry.program, &facet);
+        let check_at = |address: &Pubkey, data: &mut Vec<u8>, owner: &Pubkey| {
+            let mut lamports = 0;
+            let account = AccountInfo::new(address, false, false, &mut lamports, data, owner, false, Epoch::default());
+            check_status(&registry, &facet, &[account])
+        };
+        let check = |data: &mut Vec<u8>, owner: &Pubkey| check_at(&address, data, owner);
+        let program = registry.program;
+        
+        // Never published, below the threshold, at it, killed
+        assert_eq!(check(&mut vec![], &Pubkey::default()), Ok(()));
+        assert_eq!(check(&mut status(&program, &facet, false, severity::MEDIUM), &program), Ok(()));
+        assert_eq!(check(&mut status(&program, &facet, false, severity::HIGH), &program), Err(DiamondError::FacetBlocked));
+        assert_eq!(check(&mut status(&program, &facet, true, severity::NONE), &program), Err(DiamondError::FacetBlocked));
+        // Forged outside the registry, or published for another facet
+        assert_eq!(check(&mut status(&program, &facet, false, 0), &Pubkey::new_unique()), Err(DiamondError::FacetBlocked));
+        assert_eq!(check(&mut status(&program, &other, false, 0), &program), Err(DiamondError::FacetBlocked));
+        // A registry account of the facet at another address (e.g. a wrong bump)
+        let elsewhere = Pubkey::new_unique();
+        assert_eq!(check_at(&elsewhere, &mut status(&program, &facet, false, 0), &program), Err(DiamondError::FacetBlocked));
+        assert_eq!(check_at(&elsewhere, &mut vec![], &Pubkey::default()), Err(DiamondError::FacetBlocked));
+        // The status account must be passed
+        assert_eq!(check_status(&registry, &facet, &[]), Err(DiamondError::FacetBlocked));
+    }
+}
@@ -0,0 +1,238 @@
+/*!
+ * Diamond Index Module
+ * Selector index PDA rebuilt on every cut
+ *
+ * A small router-owned account holding, for every canonical selector, only
+ * what a dispatch needs: the facet program and the flags byte, sorted by
+ * selector. `dispatch_indexed` resolves the selector here by binary search
+ * instead of in the diamond state; names, namespaces and the rest of the
+ * metadata stay in the state for the loupe. Aliases aren't indexed, so
+ * calls through an alias go through `dispatch`.
+ *
+ * Like the routing digest, the index can't fall behind once enabled: every
+ * instruction that changes the selector table takes the index account right
+ * after the digest account (if any) and ahead of any audit accounts, and
+ * fails without it. Dispatch still compares the index's `config_version`
+ * with the state's and refuses an index that is behind.
+ */
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    system_program,
+};
+
+use crate::diamond_state::{DiamondState, SelectorMapping};
+use crate::diamond_vault;
+use crate::error::DiamondError;
+use crate::selector::Selector;
+
+pub use diamond_constants::{layout::INDEXED_ROUTE_LEN, seeds::SELECTOR_INDEX_SEED};
+
+/// What dispatch needs to know about one selector
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq)]
+pub struct IndexedRoute {
+    pub selector: Selector,
+    pub module: Pubkey,
+    /// Flags byte of the mapping (standard accounts and re-entrancy)
+    pub flags: u8,
+}
+
+impl IndexedRoute {
+    /// The mapping as dispatch sees it (no namespace or function name)
+    pub fn mapping(&self) -> SelectorMapping {
+        SelectorMapping {
+            namespace: [0; 8],
+            selector: self.selector,
+            module: self.module,
+            function_name: [0; 64],
+            is_immutable: false,
+            standard_accounts: self.flags,
+            salt: 0,
+        }
+    }
+}
+
+/// Routes of one diamond, sorted by selector
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct SelectorIndex {
+    pub diamond: Pubkey,
+    /// Copied from the diamond state (fixed at initialization)
+    pub selector_width: u8,
+    /// The state's `config_version` when the index was last rebuilt
+    pub config_version: u64,
+    pub routes: Vec<IndexedRoute>,
+    pub bump: u8,
+}
+
+impl SelectorIndex {
+    pub const SPACE: usize =
+        32 + // diamond
+        1 +  // selector_width
+        8 +  // config_version
+        4 + (DiamondState::MAX_SELECTORS * INDEXED_ROUTE_LEN) + // routes vec
+        1;   // bump
+    
+    pub fn find_address(program_id: &Pubkey, diamond: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[SELECTOR_INDEX_SEED, diamond.as_ref()], program_id)
+    }
+    
+    /// Index of `state`'s current selector table
+    pub fn build(diamond: Pubkey, state: &DiamondState, bump: u8) -> Self {
+        let mut routes: Vec<IndexedRoute> = state
+            .selectors
+            .iter()
+            .map(|m| IndexedRoute { selector: m.selector, module: m.module, flags: m.standard_accounts })
+            .collect();
+        routes.sort_unstable_by_key(|route| route.selector.0);
+        Self {
+            diamond,
+            selector_width: state.selector_width,
+            config_version: state.config_version,
+            routes,
+            bump,
+        }
+    }
+    
+    pub fn route(&self, selector: &Selector) -> Option<&IndexedRoute> {
+        self.routes
+            .binary_search_by_key(&selector.0, |route| route.selector.0)
+            .ok()
+            .map(|position| &self.routes[position])
+    }
+}
+
+/// Load the selector index of `diamond`, checking its address and owner
+pub fn load(program_id: &Pubkey, diamond: &Pubkey, account: &AccountInfo) -> Result<SelectorIndex, ProgramError> {
+    let (expected, _) = SelectorIndex::find_address(program_id, diamond);
+    if account.key != &expected || account.owner != program_id {
+        msg!("Error: Invalid selector index. Expected: {}", expected);
+        return Err(ProgramError::InvalidSeeds);
+    }
+    SelectorIndex::deserialize(&mut &account.try_borrow_data()?[..])
+        .map_err(|_| ProgramError::InvalidAccountData)
+}
+
+fn write(account: &AccountInfo, index: &SelectorIndex) -> ProgramResult {
+    let mut data = account.try_borrow_mut_data()?;
+    index.serialize(&mut &mut data[..])
+        .map_err(|_| ProgramError::AccountDataTooSmall)
+}
+
+/// Rebuild the index after a cut
+///
+/// A no-op for diamonds without an index. Otherwise `accounts` must start
+/// with the index PDA; returns the accounts after it (the audit accounts).
+pub fn refresh<'a, 'info>(
+    program_id: &Pubkey,
+    diamond: &Pubkey,
+    state: &DiamondState,
+    accounts: &'a [AccountInfo<'info>],
+) -> Result<&'a [AccountInfo<'info>], ProgramError> {
+    if !state.selector_index {
+        return Ok(accounts);
+    }
+    let (index_account, rest) = match accounts.split_first() {
+        Some(split) => split,
+        None => {
+            msg!("Error: Selector index account required");
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+    };
+    let index = load(program_id, diamond, index_account)?;
+    write(index_account, &SelectorIndex::build(*diamond, state, index.bump))?;
+    Ok(rest)
+}
+
+/// Create the selector index and rebuild it on every cut from now on (owner only)
+///
+/// Accounts: [diamond_state, owner, selector_index, payer, system_program]
+pub fn enable_selector_index(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let owner = next_account_info(account_iter)?;
+    let index_account = next_account_info(account_iter)?;
+    let payer = next_account_info(account_iter)?;
+    let system_program_account = next_account_info(account_iter)?;
+    
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if system_program_account.key != &system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    if !state.is_owner(owner.key) {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    if state.selector_index {
+        msg!("Error: Selector index already enabled");
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    let (expected, bump) = SelectorIndex::find_address(program_id, state_account.key);
+    if index_account.key != &expected {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    
+    diamond_vault::create_pda(
+        program_id,
+        state_account.key,
+        state.may_spend_rent_vault(owner.key),
+        payer,
+        index_account,
+        system_program_account,
+        SelectorIndex::SPACE,
+        &[SELECTOR_INDEX_SEED, state_account.key.as_ref(), &[bump]],
+    )?;
+    let index = SelectorIndex::build(*state_account.key, &state, bump);
+    write(index_account, &index)?;
+    
+    state.selector_index = true;
+    state.save(state_account)?;
+    
+    msg!("Selector index enabled: {} ({} routes)", index_account.key, index.routes.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diamond_cut::{apply_cuts, FacetCut};
+    
+    #[test]
+    fn test_index_matches_state_lookups() {
+        let diamond = Pubkey::new_unique();
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        let (vault, counter) = (Pubkey::new_unique(), Pubkey::new_unique());
+        apply_cuts(&mut state, &[
+            FacetCut::add([9, 0, 0, 1], vault, "vault", "deposit"),
+            FacetCut { standard_accounts: 3, ..FacetCut::add([1, 0, 0, 1], counter, "counter", "increment") },
+            FacetCut::add([5, 0, 0, 1], counter, "counter", "get"),
+        ])
+        .unwrap();
+        
+        let index = SelectorIndex::build(diamond, &state, 254);
+        assert!(index.routes.windows(2).all(|pair| pair[0].selector.0 < pair[1].selector.0));
+        for mapping in &state.selectors {
+            let route = index.route(&mapping.selector).unwrap();
+            assert_eq!((route.module, route.flags), (mapping.module, mapping.standard_accounts));
+            assert_eq!(route.mapping().reentrancy(), mapping.reentrancy());
+        }
+        assert_eq!(index.route(&Selector::from([7, 0, 0, 1])), None);
+        assert_eq!(index.config_version, state.config_version);
+        assert!(state.check_config_version(Some(index.config_version)).is_ok());
+        
+        // An index not rebuilt by a later cut is refused at dispatch
+        apply_cuts(&mut state, &[FacetCut::remove(Selector::from([5, 0, 0, 1]))]).unwrap();
+        assert_eq!(state.check_config_version(Some(index.config_version)), Err(DiamondError::StaleConfig));
+    }
+}
@@ -0,0 +1,166 @@
+/*!
+ * Diamond Invariants Module
+ * State invariants checked after every instruction in audit builds
+ *
+ * With the `audit` feature the router snapshots the diamond state (the first
+ * account, when it holds one) before each instruction, and once the
+ * instruction succeeds checks that:
+ * - no selector is registered twice, as a mapping or an alias, and no admin
+ *   is listed twice
+ * - the selector index at `STATE_INDEX_OFFSET` is sorted and matches the
+ *   selector table
+ * - every list is within its capacity and the encoding fits `SPACE`
+ * - every immutable mapping from before is still there, unchanged
+ *
+ * A violation fails the instruction with `InvariantViolated`, naming the
+ * invariant in the log. The checks decode the whole state twice per
+ * instruction, so they are meant for devnet and testnet deployments under
+ * audit, not for mainnet builds.
+ */
+
+use borsh::BorshDeserialize;
+use diamond_core::routing::{build_index, IndexEntry};
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    msg,
+    pubkey::Pubkey,
+};
+
+use crate::constants::layout::STATE_INDEX_OFFSET;
+use crate::diamond_state::DiamondState;
+use crate::error::DiamondError;
+
+/// Broken state invariant
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Violation {
+    /// The header index is unsorted or disagrees with the selector table
+    IndexMismatch,
+    /// A selector is registered twice (as mappings or aliases)
+    DuplicateSelector,
+    DuplicateAdmin,
+    /// A list is over capacity or the state outgrew its account
+    Oversized,
+    /// An immutable mapping was removed or changed
+    ImmutableChanged,
+}
+
+/// Check `after`, encoded as `data`, against the state invariants and, if
+/// given, against the state `before` the instruction
+pub fn check(before: Option<&DiamondState>, after: &DiamondState, data: &[u8]) -> Result<(), Violation> {
+    let mut selectors: Vec<[u8; 8]> = after
+        .selectors
+        .iter()
+        .map(|m| m.selector.0)
+        .chain(after.aliases.iter().map(|a| a.alias.0))
+        .collect();
+    selectors.sort_unstable();
+    if selectors.windows(2).any(|pair| pair[0] == pair[1]) {
+        return Err(Violation::DuplicateSelector);
+    }
+    if after.admins.iter().enumerate().any(|(i, admin)| after.admins[..i].contains(admin)) {
+        return Err(Violation::DuplicateAdmin);
+    }
+    
+    let index = data
+        .get(STATE_INDEX_OFFSET..)
+        .and_then(|mut bytes| Vec::<IndexEntry>::deserialize(&mut bytes).ok())
+        .ok_or(Violation::IndexMismatch)?;
+    if index.windows(2).any(|pair| pair[0].selector.0 >= pair[1].selector.0) {
+        return Err(Violation::IndexMismatch);
+    }
+    if index != build_index(after.selectors.iter().map(|m| m.selector)) {
+        return Err(Violation::IndexMismatch);
+    }
+    
+    let encoded = borsh::to_vec(after).map_err(|_| Violation::Oversized)?.len();
+    if after.selectors.len() > DiamondState::MAX_SELECTORS
+        || after.aliases.len() > DiamondState::MAX_ALIASES
+        || after.admins.len() > DiamondState::MAX_ADMINS
+        || after.active_modules.len() > DiamondState::MAX_MODULES
+        || encoded + 8 > DiamondState::SPACE
+        || encoded > data.len()
+    {
+        return Err(Violation::Oversized);
+    }
+    
+    let immutables = before.into_iter().flat_map(|state| state.selectors.iter()).filter(|m| m.is_immutable);
+    for mapping in immutables {
+        if after.get_mapping(mapping.selector) != Some(mapping) {
+            return Err(Violation::ImmutableChanged);
+        }
+    }
+    Ok(())
+}
+
+/// Run `instruction`, then check the invariants of the diamond state it
+/// was given (if any)
+pub fn checked(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction: impl FnOnce() -> ProgramResult,
+) -> ProgramResult {
+    let state_account = match accounts.first() {
+        Some(account) if account.owner == program_id => account,
+        _ => return instruction(),
+    };
+    let before = DiamondState::load(program_id, state_account).ok();
+    instruction()?;
+    
+    // Closed or repurposed accounts have no state left to check
+    let Ok(after) = DiamondState::load(program_id, state_account) else {
+        return Ok(());
+    };
+    check(before.as_ref(), &after, &state_account.try_borrow_data()?).map_err(|violation| {
+        msg!("Error: Invariant violated: {:?}", violation);
+        DiamondError::InvariantViolated.into()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diamond_cut::{apply_cuts, FacetCut};
+    use crate::diamond_state::SelectorMapping;
+    
+    fn encoded(state: &DiamondState) -> Vec<u8> {
+        let mut data = borsh::to_vec(state).unwrap();
+        data.resize(DiamondState::SPACE, 0);
+        data
+    }
+    
+    #[test]
+    fn test_check_catches_each_violation() {
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        let facet = Pubkey::new_unique();
+        apply_cuts(&mut state, &[
+            FacetCut { is_immutable: true, ..FacetCut::add([9, 0, 0, 1], facet, "vault", "deposit") },
+            FacetCut::add([1, 0, 0, 1], facet, "vault", "withdraw"),
+        ])
+        .unwrap();
+        assert_eq!(check(None, &state, &encoded(&state)), Ok(()));
+        
+        // The index is rebuilt on save, so a stale one only shows in raw bytes
+        let mut stale = encoded(&state);
+        stale[STATE_INDEX_OFFSET + 4] = 0xFF;
+        assert_eq!(check(None, &state, &stale), Err(Violation::IndexMismatch));
+        
+        let mut duplicate = state.clone();
+        duplicate.selectors.push(state.selectors[1].clone());
+        assert_eq!(check(None, &duplicate, &encoded(&duplicate)), Err(Violation::DuplicateSelector));
+        
+        let mut admins = state.clone();
+        admins.admins = vec![facet, facet];
+        assert_eq!(check(None, &admins, &encoded(&admins)), Err(Violation::DuplicateAdmin));
+        
+        let short = borsh::to_vec(&state).unwrap().len() - 1;
+        assert_eq!(check(None, &state, &encoded(&state)[..short]), Err(Violation::Oversized));
+        
+        let mut replaced = state.clone();
+        replaced.selectors[0] = SelectorMapping { module: Pubkey::new_unique(), ..state.selectors[0].clone() };
+        assert_eq!(check(Some(&state), &replaced, &encoded(&replaced)), Err(Violation::ImmutableChanged));
+        let mut removed = state.clone();
+        removed.selectors.remove(0);
+        assert_eq!(check(Some(&state), &removed, &encoded(&removed)), Err(Violation::ImmutableChanged));
+    }
+}
@@ -0,0 +1,477 @@
+/*!
+ * Diamond Loupe Module
+ * Read-only introspection of the routing table
+ */
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::{set_return_data, MAX_RETURN_DATA},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::diamond_build::{self, BuildStatus};
+use crate::diamond_record::FacetRecord;
+use crate::diamond_state::{fixed_str, CutRole, DiamondState, SelectorMapping, VersionRecord};
+use crate::error::DiamondError;
+use crate::selector::Selector;
+
+/// Lookup key: a qualified name resolves to a selector and vice versa
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub enum LoupeQuery {
+    /// `namespace::function_name`, or a bare name for the global namespace
+    /// (precedence as in `DiamondState::get_mapping_by_name`)
+    ByName(String),
+    BySelector(Selector),
+}
+
+/// Decoded view of a selector mapping, returned via return data
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct FunctionInfo {
+    pub namespace: String,
+    pub function_name: String,
+    pub selector: Selector,
+    pub module: Pubkey,
+    pub is_immutable: bool,
+    /// Other selectors routed to this mapping
+    pub aliases: Vec<Selector>,
+}
+
+impl From<&SelectorMapping> for FunctionInfo {
+    fn from(mapping: &SelectorMapping) -> Self {
+        Self {
+            namespace: mapping.namespace_as_str().to_string(),
+            function_name: mapping.function_name_as_str().to_string(),
+            selector: mapping.selector,
+            module: mapping.module,
+            is_immutable: mapping.is_immutable,
+            aliases: Vec::new(),
+        }
+    }
+}
+
+/// One row of the permission matrix
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct Permission {
+    pub authority: Pubkey,
+    pub role: CutRole,
+    /// Namespace the role is limited to (`None` = every namespace)
+    pub namespace: Option<String>,
+    /// Mutable selectors currently in scope
+    pub selectors: u16,
+}
+
+/// Who may cut what, optionally narrowed to the authorities over one selector
+///
+/// Rows come from `DiamondState::cut_role`, the same matrix the cut handlers
+/// enforce. Immutable selectors have no rows.
+pub fn permissions(state: &DiamondState, selector: Option<Selector>) -> Result<Vec<Permission>, DiamondError> {
+    let target = match selector {
+        Some(selector) => Some(state.resolve_mapping(selector).ok_or(DiamondError::ModuleNotFound)?),
+        None => None,
+    };
+    if target.is_some_and(|mapping| mapping.is_immutable) {
+        return Ok(Vec::new());
+    }
+    let in_scope = |namespace: Option<&[u8; 8]>| {
+        state
+            .selectors
+            .iter()
+            .filter(|m| !m.is_immutable && namespace.is_none_or(|ns| &m.namespace == ns))
+            .count() as u16
+    };
+    
+    let mut authorities: Vec<(Pubkey, Option<[u8; 8]>)> = Vec::new();
+    let owner = if state.governance_only { state.governance } else { Some(state.owner) };
+    authorities.extend(owner.map(|owner| (owner, None)));
+    authorities.extend(state.admins.iter().map(|admin| (*admin, None)));
+    authorities.extend(state.tenants.iter().map(|t| (t.authority, Some(t.namespace))));
+    
+    let mut rows = Vec::new();
+    for (authority, namespace) in authorities {
+        let checked = target.map(|m| m.namespace).or(namespace).unwrap_or([0u8; 8]);
+        let Some(role) = state.cut_role(&authority, &checked) else { continue };
+        // An admin who is also a tenant is already covered by the admin row
+        if role != CutRole::Tenant && namespace.is_some() {
+            continue;
+        }
+        let namespace = if role == CutRole::Tenant { namespace } else { None };
+        rows.push(Permission {
+            authority,
+            role,
+            namespace: namespace.map(|ns| fixed_str(&ns).to_string()),
+            selectors: in_scope(namespace.as_ref()),
+        });
+    }
+    Ok(rows)
+}
+
+/// Entries in use out of a table's capacity
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Usage {
+    pub used: u16,
+    pub max: u16,
+}
+
+impl Usage {
+    fn of(used: usize, max: usize) -> Self {
+        Self { used: used as u16, max: max as u16 }
+    }
+    
+    /// Whole percent used
+    pub fn percent(&self) -> u8 {
+        (self.used as u32 * 100 / (self.max as u32).max(1)).min(100) as u8
+    }
+}
+
+/// How close the state account is to its capacity limits
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Capacity {
+    pub selectors: Usage,
+    pub modules: Usage,
+    pub admins: Usage,
+    /// Account bytes past the current encoding
+    pub bytes_free: u32,
+}
+
+/// Capacity of `state` stored in an account of `account_len` bytes
+pub fn capacity(state: &DiamondState, account_len: usize) -> Result<Capacity, ProgramError> {
+    let encoded_len = borsh::to_vec(state).map_err(|_| ProgramError::InvalidAccountData)?.len();
+    Ok(Capacity {
+        selectors: Usage::of(state.selectors.len(), DiamondState::MAX_SELECTORS),
+        modules: Usage::of(state.active_modules.len(), DiamondState::MAX_MODULES),
+        admins: Usage::of(state.admins.len(), DiamondState::MAX_ADMINS),
+        bytes_free: account_len.saturating_sub(encoded_len) as u32,
+    })
+}
+
+/// Borsh-encode a view result into return data, failing loudly if it won't fit
+pub fn return_borsh<T: BorshSerialize>(value: &T) -> ProgramResult {
+    let encoded = borsh::to_vec(value).map_err(|_| ProgramError::InvalidAccountData)?;
+    if encoded.len() > MAX_RETURN_DATA {
+        msg!("Error: Return data is {} bytes (max {})", encoded.len(), MAX_RETURN_DATA);
+        return Err(DiamondError::ReturnDataTooLarge.into());
+    }
+    set_return_data(&encoded);
+    Ok(())
+}
+
+/// Resolve a loupe query against the routing table
+///
+/// An alias selector resolves to its canonical mapping; either way the
+/// result lists every alias of that mapping.
+pub fn resolve(state: &DiamondState, query: &LoupeQuery) -> Option<FunctionInfo> {
+    let mapping = match query {
+        LoupeQuery::ByName(name) => state.get_mapping_by_name(name),
+        LoupeQuery::BySelector(selector) => state.resolve_mapping(*selector),
+    }?;
+    Some(FunctionInfo { aliases: state.aliases_of(mapping.selector), ..FunctionInfo::from(mapping) })
+}
+
+/// Look up a function by qualified name or selector
+///
+/// Accounts: [diamond_state]
+/// Returns a Borsh-encoded `FunctionInfo` via return data.
+pub fn lookup_function(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    
+    let query = LoupeQuery::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let state = DiamondState::load(program_id, state_account)?;
+    
+    let info = resolve(&state, &query).ok_or_else(|| {
+        msg!("Error: No mapping for {:?}", query);
+        DiamondError::ModuleNotFound
+    })?;
+    
+    msg!(
+        "Loupe: {} -> {:?} @ {}",
+        info.function_name,
+        info.selector,
+        info.module
+    );
+    
+    return_borsh(&info)
+}
+
+/// Report the permission matrix
+///
+/// Accounts: [diamond_state]
+/// Data: Option<Selector> (narrow to the authorities over one selector)
+/// Returns a Borsh-encoded `Vec<Permission>` via return data.
+pub fn list_permissions(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    
+    let selector = Option::<Selector>::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let state = DiamondState::load(program_id, state_account)?;
+    let rows = permissions(&state, selector)?;
+    
+    msg!("Permissions: {} rows", rows.len());
+    return_borsh(&rows)
+}
+
+/// Report capacity utilization
+///
+/// Accounts: [diamond_state]
+/// Returns a Borsh-encoded `Capacity` via return data.
+pub fn get_capacity(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    
+    let state = DiamondState::load(program_id, state_account)?;
+    let report = capacity(&state, state_account.data_len())?;
+    
+    msg!(
+        "Capacity: {}/{} selectors, {}/{} modules, {}/{} admins, {} bytes free",
+        report.selectors.used,
+        report.selectors.max,
+        report.modules.used,
+        report.modules.max,
+        report.admins.used,
+        report.admins.max,
+        report.bytes_free
+    );
+    return_borsh(&report)
+}
+
+/// A facet's build verification, returned via return data
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BuildReport {
+    pub module: Pubkey,
+    /// Attested hash, if any
+    pub hash: Option<[u8; 32]>,
+    /// Slot the deployed program was last upgraded at
+    pub deploy_slot: u64,
+    pub status: BuildStatus,
+}
+
+/// Report a facet's build verification status
+///
+/// Accounts: [diamond_state, module, module_program_data, facet_record]
+/// Returns a Borsh-encoded `BuildReport` via return data.
+pub fn get_build_status(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let module = next_account_info(account_iter)?;
+    let programdata = next_account_info(account_iter)?;
+    let record_account = next_account_info(account_iter)?;
+    
+    if state_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let deploy_slot = diamond_build::deploy_slot(module, programdata)?;
+    let record = FacetRecord::load(program_id, record_account, state_account.key, module.key)?;
+    let attestation = record.as_ref().and_then(|r| r.attestation.as_ref());
+    let report = BuildReport {
+        module: *module.key,
+        hash: attestation.map(|a| a.hash),
+        deploy_slot,
+        status: diamond_build::build_status(attestation, deploy_slot),
+    };
+    
+    msg!("Build status of {}: {:?}", module.key, report.status);
+    return_borsh(&report)
+}
+
+/// Version declarations of a module still in its record, oldest first
+///
+/// Accounts: [diamond_state, facet_record]
+/// Data: module (Pubkey)
+/// Returns a Borsh-encoded `Vec<VersionRecord>` via return data.
+pub fn version_history(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let record_account = next_account_info(account_iter)?;
+    
+    let module = Pubkey::try_from_slice(data).map_err(|_| ProgramError::InvalidInstructionData)?;
+    if state_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let record = FacetRecord::load(program_id, record_account, state_account.key, &module)?;
+    let records: Vec<VersionRecord> = record.map(|r| r.versions).unwrap_or_default();
+    
+    msg!("Version history of {}: {} records", module, records.len());
+    return_borsh(&records)
+}
+
+/// Program currently serving `selector` in a diamond owned by `router`
+///
+/// Reads the state account's bytes in place, so facets and other programs
+/// can route facet-to-facet calls through the live table without a CPI or a
+/// full state decode. Link the router with `features = ["no-entrypoint"]`.
+pub fn resolve_selector_in(
+    router: &Pubkey,
+    state_account: &AccountInfo,
+    selector: impl Into<Selector>,
+) -> Result<Option<Pubkey>, ProgramError> {
+    if state_account.owner != router {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let data = state_account.try_borrow_data()?;
+    Ok(diamond_core::routing::resolve_selector(&data, &selector.into()).map(Pubkey::new_from_array))
+}
+
+/// Resolve which program serves a selector
+///
+/// Accounts: [diamond_state]
+/// Data: Selector
+/// Returns a Borsh-encoded `Option<Pubkey>` via return data.
+pub fn resolve_selector(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    
+    let selector = Selector::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let module = resolve_selector_in(program_id, state_account, selector)?;
+    msg!("Resolve: {:?} -> {:?}", selector, module);
+    return_borsh(&module)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diamond_state::namespace_bytes;
+
+    fn state_with_mappings() -> DiamondState {
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        state.selectors.push(SelectorMapping::new(
+            [0x01, 0x02, 0x03, 0x04],
+            Pubkey::new_unique(),
+            "increment",
+            false,
+        ));
+        state.selectors.push(SelectorMapping::new_with_namespace(
+            namespace_bytes("counter").unwrap(),
+            [0x05, 0x06, 0x07, 0x08],
+            Pubkey::new_unique(),
+            "increment",
+            true,
+        ));
+        state
+    }
+
+    #[test]
+    fn test_resolve_by_qualified_name() {
+        let state = state_with_mappings();
+        
+        let global = resolve(&state, &LoupeQuery::ByName("increment".to_string())).unwrap();
+        assert_eq!(global.selector, [0x01, 0x02, 0x03, 0x04]);
+        
+        let namespaced = resolve(&state, &LoupeQuery::ByName("counter::increment".to_string())).unwrap();
+        assert_eq!(namespaced.selector, [0x05, 0x06, 0x07, 0x08]);
+        assert_eq!(namespaced.namespace, "counter");
+        assert!(namespaced.is_immutable);
+        
+        assert!(resolve(&state, &LoupeQuery::ByName("other::increment".to_string())).is_none());
+    }
+
+    #[test]
+    fn test_resolve_by_selector() {
+        let state = state_with_mappings();
+        let info = resolve(&state, &LoupeQuery::BySelector([0x05, 0x06, 0x07, 0x08].into())).unwrap();
+        
+        assert_eq!(info.function_name, "increment");
+        assert_eq!(state.selectors[1].qualified_name(), "counter::increment");
+        assert!(resolve(&state, &LoupeQuery::BySelector([0xFF; 4].into())).is_none());
+    }
+    
+    #[test]
+    fn test_permission_matrix() {
+        let mut state = state_with_mappings();
+        let (admin, tenant) = (Pubkey::new_unique(), Pubkey::new_unique());
+        state.admins.push(admin);
+        state.set_tenant(namespace_bytes("counter").unwrap(), Some(tenant)).unwrap();
+        state.selectors[1].is_immutable = false;
+        
+        let rows = permissions(&state, None).unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!((rows[0].authority, rows[0].role, rows[0].selectors), (state.owner, CutRole::Owner, 2));
+        assert_eq!((rows[1].authority, rows[1].role), (admin, CutRole::Admin));
+        assert_eq!(rows[2].namespace.as_deref(), Some("counter"));
+        assert_eq!(rows[2].selectors, 1);
+        
+        // Only owner and admin may touch the global mapping
+        let global = permissions(&state, Some([0x01, 0x02, 0x03, 0x04].into())).unwrap();
+        assert!(global.iter().all(|row| row.authority != tenant));
+        assert_eq!(permissions(&state, Some([0x05, 0x06, 0x07, 0x08].into())).unwrap().len(), 3);
+        
+        state.selectors[1].is_immutable = true;
+        assert!(permissions(&state, Some([0x05, 0x06, 0x07, 0x08].into())).unwrap().is_empty());
+        assert_eq!(permissions(&state, Some([0xFF; 4].into())), Err(DiamondError::ModuleNotFound));
+    }
+    
+    #[test]
+    fn test_capacity() {
+        let mut state = state_with_mappings();
+        state.admins.push(Pubkey::new_unique());
+        let used = borsh::to_vec(&state).unwrap().len();
+        
+        let report = capacity(&state, DiamondState::SPACE).unwrap();
+        assert_eq!(report.selectors, Usage { used: 2, max: DiamondState::MAX_SELECTORS as u16 });
+        assert_eq!(report.selectors.percent(), 4);
+        assert_eq!(report.modules.used, 0);
+        assert_eq!(report.admins.percent(), 10);
+        assert_eq!(report.bytes_free as usize, DiamondState::SPACE - used);
+        
+        // The worst case fills every table and leaves only the reserved header
+        let full = capacity(&crate::layout::worst_case_state(), DiamondState::SPACE).unwrap();
+        assert_eq!(full.selectors.percent(), 100);
+        assert_eq!(full.bytes_free, 8);
+    }
+    
+    #[test]
+    fn test_resolve_selector_reads_raw_state() {
+        let mut state = state_with_mappings();
+        state.admins.push(Pubkey::new_unique());
+        state.active_modules.push(crate::diamond_state::ModuleMeta::new("counter", Pubkey::new_unique(), crate::diamond_state::Semver::INITIAL));
+        let mut data = borsh::to_vec(&state).unwrap();
+        data.resize(DiamondState::SPACE, 0);
+        
+        let router = Pubkey::new_unique();
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let account = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &router, false, 0);
+        
+        for mapping in &state.selectors {
+            assert_eq!(resolve_selector_in(&router, &account, mapping.selector), Ok(Some(mapping.module)));
+        }
+        assert_eq!(resolve_selector_in(&router, &account, [0xFF; 4]), Ok(None));
+        assert_eq!(
+            resolve_selector_in(&Pubkey::new_unique(), &account, [0x01, 0x02, 0x03, 0x04]),
+            Err(ProgramError::IncorrectProgramId)
+        );
+    }
+}
@@ -0,0 +1,185 @@
+/*!
+ * Diamond Maintenance Module
+ * Permissionless housekeeping crank for keeper bots
+ *
+ * `maintain` performs deferred cleanup that no user-facing instruction does
+ * eagerly: clearing pauses whose expiry has passed and dropping maintenance
+ * windows that are over (which lets selectors be pinned again). It only
+ * touches entries that carry an expiry and have lapsed; everything else in
+ * the state is the owner's to manage. Keepers are paid a reward read from the config store
+ * (`diamond::maintain_reward`, u64 LE lamports) out of the state account's
+ * rent-exempt surplus.
+ */
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    sysvar::Sysvar,
+};
+
+use crate::diamond_config::{key_bytes, ConfigEntry};
+use crate::diamond_state::{namespace_bytes, DiamondState};
+use crate::error::DiamondError;
+use crate::events::DiamondEvent;
+
+pub const REWARD_NAMESPACE: &str = "diamond";
+pub const REWARD_KEY: &str = "maintain_reward";
+
+/// What a maintenance pass changed
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, Default, PartialEq)]
+pub struct MaintenanceReport {
+    pub pause_cleared: bool,
+    pub windows_pruned: u8,
+}
+
+impl MaintenanceReport {
+    pub fn is_empty(&self) -> bool {
+        !self.pause_cleared && self.windows_pruned == 0
+    }
+}
+
+/// Run every housekeeping task against `state` at `slot`
+pub fn run(state: &mut DiamondState, slot: u64) -> MaintenanceReport {
+    let mut report = MaintenanceReport::default();
+    
+    if (state.dispatch_paused || state.cut_paused) && !state.is_paused_at(slot) {
+        state.set_pause(false, None);
+        report.pause_cleared = true;
+    }
+    report.windows_pruned = state.prune_pause_windows(slot) as u8;
+    
+    report
+}
+
+/// Configured keeper reward, if the config entry is present
+fn configured_reward(
+    program_id: &Pubkey,
+    diamond: &Pubkey,
+    entry_account: &AccountInfo,
+) -> Result<u64, ProgramError> {
+    let (expected, _) = ConfigEntry::find_address(
+        program_id,
+        diamond,
+        &namespace_bytes(REWARD_NAMESPACE).ok_or(DiamondError::InvalidConfigEntry)?,
+        &key_bytes(REWARD_KEY)?,
+    );
+    if entry_account.key != &expected {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if entry_account.owner != program_id {
+        // Never written: no reward configured
+        return Ok(0);
+    }
+    let data = entry_account.try_borrow_data()?;
+    let entry = ConfigEntry::deserialize(&mut &data[..])
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    let bytes: [u8; 8] = entry
+        .value
+        .as_slice()
+        .try_into()
+        .map_err(|_| DiamondError::InvalidConfigEntry)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+/// Housekeeping crank (anyone)
+///
+/// Accounts: [diamond_state, keeper, (reward_config)]
+/// Fails with `NothingToMaintain` when there is no work, so keepers can
+/// simulate first and never pay for an empty pass.
+pub fn maintain(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let keeper = next_account_info(account_iter)?;
+    let reward_config = next_account_info(account_iter).ok();
+    
+    if !keeper.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    let report = run(&mut state, Clock::get()?.slot);
+    if report.is_empty() {
+        return Err(DiamondError::NothingToMaintain.into());
+    }
+    state.save(state_account)?;
+    
+    let reward = match reward_config {
+        Some(entry) => {
+            let configured = configured_reward(program_id, state_account.key, entry)?;
+            let floor = Rent::get()?.minimum_balance(state_account.data_len());
+            configured.min(state_account.lamports().saturating_sub(floor))
+        }
+        None => 0,
+    };
+    if reward > 0 {
+        **state_account.try_borrow_mut_lamports()? -= reward;
+        **keeper.try_borrow_mut_lamports()? = keeper
+            .lamports()
+            .checked_add(reward)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+    }
+    
+    msg!(
+        "Maintenance by {}: pause cleared {}, {} windows dropped, reward {}",
+        keeper.key,
+        report.pause_cleared,
+        report.windows_pruned,
+        reward
+    );
+    DiamondEvent::Maintained {
+        keeper: *keeper.key,
+        pause_cleared: report.pause_cleared,
+        windows_pruned: report.windows_pruned,
+        reward,
+    }
+    .emit();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diamond_cut::{apply_cut, FacetCut};
+    use crate::diamond_state::PauseWindow;
+    
+    #[test]
+    fn test_run_clears_lapsed_pause_and_keeps_owner_data() {
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        let (kept, unrouted) = (Pubkey::new_unique(), Pubkey::new_unique());
+        apply_cut(&mut state, &FacetCut::add([1, 0, 0, 0], kept, "counter", "increment")).unwrap();
+        apply_cut(&mut state, &FacetCut::add([2, 0, 0, 0], unrouted, "legacy", "reset")).unwrap();
+        apply_cut(&mut state, &FacetCut::remove([2, 0, 0, 0])).unwrap();
+        state.set_pause(true, Some(100));
+        
+        // Pause still live: nothing has lapsed
+        assert!(run(&mut state, 50).is_empty());
+        
+        let report = run(&mut state, 100);
+        assert!(report.pause_cleared && !state.dispatch_paused && !state.cut_paused);
+        assert!(run(&mut state, 200).is_empty());
+        
+        // Modules no selector routes to any more are the owner's to remove
+        assert_eq!(state.active_modules.len(), 2);
+    }
+    
+    #[test]
+    fn test_run_drops_finished_windows() {
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        state.schedule_pause_window(PauseWindow { start_slot: 10, end_slot: 20 }, 0).unwrap();
+        state.schedule_pause_window(PauseWindow { start_slot: 30, end_slot: 40 }, 0).unwrap();
+        
+        assert_eq!(run(&mut state, 20).windows_pruned, 1);
+        assert_eq!(state.pause_windows, vec![PauseWindow { start_slot: 30, end_slot: 40 }]);
+        assert!(run(&mut state, 39).is_empty());
+    }
+}
@@ -0,0 +1,261 @@
+/*!
+ * Diamond Migrate Module
+ * Upgrade of diamond state accounts written in an older layout
+ *
+ * The state carries its layout version right after the owner
+ * (`STATE_LAYOUT_VERSION`); `DiamondState::load` refuses any other layout
+ * with `StateLayoutOutdated`. Diamonds created before the layout was
+ * versioned hold the original state: owner, admins, modules with a `u16`
+ * version, 4-byte selectors, bump, one pause flag and the pause authority.
+ * `migrate_state` rewrites such an account in place at the current size,
+ * keeping the 4-byte selector width; fields the original layout lacked take
+ * their defaults.
+ */
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::invoke,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    system_program,
+    sysvar::Sysvar,
+};
+
+use crate::builtins::is_reserved;
+use crate::constants::layout::{STATE_LAYOUT_VERSION, STATE_VERSION_OFFSET};
+use crate::diamond_state::{fixed_str, DiamondState, ModuleMeta, SelectorMapping, Semver, DIAMOND_STATE_SEED};
+use crate::error::DiamondError;
+
+/// Selector width of every diamond in the original layout
+pub const LEGACY_SELECTOR_WIDTH: u8 = 4;
+
+/// Selector mapping in the original layout
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct LegacySelectorMapping {
+    pub selector: [u8; 4],
+    pub module: Pubkey,
+    pub function_name: [u8; 64],
+    pub is_immutable: bool,
+}
+
+/// Module metadata in the original layout
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct LegacyModuleMeta {
+    pub name: [u8; 32],
+    pub address: Pubkey,
+    pub version: u16,
+    pub is_active: bool,
+}
+
+/// Diamond state in the original, unversioned layout
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct LegacyDiamondState {
+    pub owner: Pubkey,
+    pub admins: Vec<Pubkey>,
+    pub active_modules: Vec<LegacyModuleMeta>,
+    pub selectors: Vec<LegacySelectorMapping>,
+    pub bump: u8,
+    pub is_paused: bool,
+    pub pause_authority: Pubkey,
+}
+
+impl LegacyDiamondState {
+    /// The same diamond in the current layout
+    ///
+    /// Rejects routes the current router refuses to dispatch: selectors in
+    /// the reserved built-in range and facets pointing back at the router.
+    pub fn upgrade(&self, program_id: &Pubkey) -> Result<DiamondState, DiamondError> {
+        for mapping in &self.selectors {
+            if &mapping.module == program_id {
+                return Err(DiamondError::SelfDispatchForbidden);
+            }
+            if is_reserved(mapping.selector) {
+                msg!("Error: {:?} is now a reserved built-in selector", mapping.selector);
+                return Err(DiamondError::ReservedSelector);
+            }
+        }
+        
+        let mut state = DiamondState::new(self.owner, self.bump);
+        state.selector_width = LEGACY_SELECTOR_WIDTH;
+        state.admins = self.admins.clone();
+        state.active_modules = self
+            .active_modules
+            .iter()
+            .map(|m| ModuleMeta {
+                is_active: m.is_active,
+                ..ModuleMeta::new(fixed_str(&m.name), m.address, Semver::new(m.version, 0, 0))
+            })
+            .collect();
+        state.selectors = self
+            .selectors
+            .iter()
+            .map(|s| SelectorMapping::new(s.selector, s.module, fixed_str(&s.function_name), s.is_immutable))
+            .collect();
+        state.dispatch_paused = self.is_paused;
+        state.pause_authority = self.pause_authority;
+        Ok(state)
+    }
+}
+
+/// Whether state account data is in the current layout
+pub fn is_current(data: &[u8]) -> bool {
+    data.get(STATE_VERSION_OFFSET) == Some(&STATE_LAYOUT_VERSION)
+}
+
+/// Rewrite a diamond from the original layout into the current one (owner only)
+///
+/// Accounts: [diamond_state, owner, payer, system_program]
+/// Data: none
+///
+/// The account grows to `DiamondState::SPACE`; `payer` tops up its rent.
+pub fn migrate_state(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let owner = next_account_info(account_iter)?;
+    let payer = next_account_info(account_iter)?;
+    let system_program_account = next_account_info(account_iter)?;
+    
+    if !owner.is_signer || !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if system_program_account.key != &system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if state_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    let legacy = {
+        let data = state_account.try_borrow_data()?;
+        if is_current(&data) {
+            msg!("Error: {} is already in the current state layout", state_account.key);
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
+        LegacyDiamondState::deserialize(&mut &data[..]).map_err(|_| ProgramError::InvalidAccountData)?
+    };
+    if &legacy.owner != owner.key {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    let seeds: &[&[u8]] = &[DIAMOND_STATE_SEED, owner.key.as_ref(), &[legacy.bump]];
+    if Pubkey::create_program_address(seeds, program_id).ok().as_ref() != Some(state_account.key) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    let state = legacy.upgrade(program_id)?;
+    
+    let rent_due = Rent::get()?.minimum_balance(DiamondState::SPACE).saturating_sub(state_account.lamports());
+    if rent_due > 0 {
+        invoke(
+            &system_instruction::transfer(payer.key, state_account.key, rent_due),
+            &[payer.clone(), state_account.clone(), system_program_account.clone()],
+        )?;
+    }
+    state_account.realloc(DiamondState::SPACE, false)?;
+    state_account.try_borrow_mut_data()?.fill(0);
+    state.save(state_account)?;
+    
+    msg!(
+        "Migrated {} to state layout {:#04x} ({} selectors, {} modules)",
+        state_account.key,
+        STATE_LAYOUT_VERSION,
+        state.selectors.len(),
+        state.active_modules.len()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builtins::LOOKUP_FUNCTION_SELECTOR;
+    use crate::selector::Selector;
+    use diamond_core::routing;
+    use crate::diamond_state::{fixed_bytes, DispatchLimits};
+    
+    fn legacy(owner: Pubkey, facet: Pubkey) -> LegacyDiamondState {
+        LegacyDiamondState {
+            owner,
+            admins: vec![Pubkey::new_unique(); DiamondState::MAX_ADMINS],
+            active_modules: vec![LegacyModuleMeta {
+                name: fixed_bytes("counter"),
+                address: facet,
+                version: 2,
+                is_active: true,
+            }],
+            selectors: vec![LegacySelectorMapping {
+                selector: [1, 2, 3, 4],
+                module: facet,
+                function_name: fixed_bytes("increment"),
+                is_immutable: true,
+            }],
+            bump: 254,
+            is_paused: true,
+            pause_authority: owner,
+        }
+    }
+    
+    #[test]
+    fn test_upgrade_original_layout() {
+        let (program_id, owner, facet) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let old = legacy(owner, facet);
+        let encoded = borsh::to_vec(&old).unwrap();
+        
+        // Even a full admin list never reads as a versioned layout
+        assert!(!is_current(&encoded));
+        assert!(DiamondState::try_from_slice(&encoded).is_err());
+        
+        let state = old.upgrade(&program_id).unwrap();
+        assert_eq!(state.selector_width, LEGACY_SELECTOR_WIDTH);
+        assert_eq!(state.get_module_by_selector([1, 2, 3, 4]), Some(facet));
+        assert_eq!(state.get_mapping([1, 2, 3, 4]).unwrap().function_name_as_str(), "increment");
+        assert!(state.get_mapping([1, 2, 3, 4]).unwrap().is_immutable);
+        // Original selectors were chosen, not allocated
+        assert_eq!(state.get_mapping([1, 2, 3, 4]).unwrap().salt, 0);
+        assert_eq!(state.module_version(&facet), Some(Semver::new(2, 0, 0)));
+        // The original version is the major, so replacing within it stays direct
+        let (mut next, v2) = (state.clone(), Pubkey::new_unique());
+        next.active_modules.push(ModuleMeta::new("counter", v2, Semver::new(2, 1, 0)));
+        assert!(next.check_upgrade(&facet, &v2).is_ok());
+        next.active_modules[1].version = Semver::new(3, 0, 0);
+        assert_eq!(next.check_upgrade(&facet, &v2), Err(DiamondError::MajorUpgradeNeedsGovernance));
+        assert_eq!(state.active_modules[0].name_as_str(), "counter");
+        assert_eq!(state.admins, old.admins);
+        assert!(state.dispatch_paused);
+        assert_eq!((state.owner, state.bump, state.pause_authority), (owner, 254, owner));
+        
+        // No call lock (with its stack height) and no depth or size limits
+        assert_eq!(state.active_call, None);
+        assert_eq!(state.dispatch_limits, DispatchLimits::default());
+        
+        let migrated = borsh::to_vec(&state).unwrap();
+        assert!(is_current(&migrated));
+        assert!(migrated.len() <= DiamondState::SPACE);
+        
+        // Dispatch reads the 4-byte width and routes from the fixed header
+        let header = routing::state_header(&migrated).unwrap();
+        assert_eq!(header.selector_width, LEGACY_SELECTOR_WIDTH);
+        let selector = Selector::read(&[1, 2, 3, 4, 0xAA], header.selector_width).unwrap();
+        let raw = routing::resolve_mapping(&migrated, &selector).unwrap();
+        assert_eq!(SelectorMapping::try_from_slice(raw).unwrap().module, facet);
+    }
+    
+    #[test]
+    fn test_upgrade_rejects_unroutable_selectors() {
+        let (program_id, owner, facet) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut old = legacy(owner, facet);
+        old.selectors[0].module = program_id;
+        assert_eq!(old.upgrade(&program_id).unwrap_err(), DiamondError::SelfDispatchForbidden);
+        
+        let mut old = legacy(owner, facet);
+        old.selectors[0].selector = LOOKUP_FUNCTION_SELECTOR;
+        assert_eq!(old.upgrade(&program_id).unwrap_err(), DiamondError::ReservedSelector);
+    }
+}
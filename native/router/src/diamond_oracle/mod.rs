@@ -0,0 +1,264 @@
+/*!
+ * Diamond Oracle Module
+ * Oracle-gated dispatch for risk-sensitive selectors
+ *
+ * The owner may attach an oracle condition to a selector: a Pyth price
+ * update account, how old its price may be, and how wide its confidence
+ * interval may be relative to the price. Calls to the selector must pass the
+ * account among the remaining accounts, and the router refuses them unless
+ * the price is positive, fully verified and within both bounds. A
+ * liquidation or swap facet then never runs on a stale or unsettled price,
+ * without carrying the check itself.
+ *
+ * Pyth publishes on Solana through its receiver program, which posts each
+ * update as a `PriceUpdateV2` account (price feed accounts of the push
+ * oracle have the same layout). The feed is pinned by address and must be
+ * owned by the receiver, so only the account the owner named can satisfy
+ * the guard. The check is a worked example of a cross-cutting risk
+ * policy; any rule that can be decided from accounts the caller passes
+ * plugs in the same way. The dispatch check and `oracle::set_guard` are
+ * compiled in with the `oracle-policy` feature; a build without it refuses
+ * guarded selectors rather than skipping their check.
+ */
+
+use solana_program::{account_info::AccountInfo, msg, pubkey::Pubkey};
+#[cfg(feature = "oracle-policy")]
+use {
+    borsh::BorshDeserialize,
+    solana_program::{account_info::next_account_info, entrypoint::ProgramResult, program_error::ProgramError},
+};
+
+use crate::diamond_state::OracleGuard;
+use crate::error::DiamondError;
+#[cfg(feature = "oracle-policy")]
+use crate::{diamond_state::DiamondState, events::DiamondEvent};
+
+/// Pyth Solana receiver program, owner of every `PriceUpdateV2` account
+pub const PYTH_RECEIVER_ID: Pubkey = solana_program::pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+/// Anchor discriminator of a `PriceUpdateV2` account
+pub const PRICE_UPDATE_V2_DISCRIMINATOR: [u8; 8] = [34, 241, 35, 99, 157, 126, 244, 205];
+/// `VerificationLevel::Full` tag: the update carried a quorum of Wormhole
+/// guardian signatures
+pub const VERIFICATION_FULL: u8 = 1;
+
+// Offset of the verification level, after the discriminator and write authority
+const VERIFICATION_OFFSET: usize = 40;
+
+/// Price of a Pyth `PriceUpdateV2` account
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PythPrice {
+    pub price: i64,
+    pub confidence: u64,
+    pub exponent: i32,
+    /// Unix time the price was published at
+    pub publish_time: i64,
+    /// Whether the update was fully verified rather than partially
+    pub fully_verified: bool,
+}
+
+fn word<const N: usize>(data: &[u8], offset: usize) -> Option<[u8; N]> {
+    data.get(offset..offset + N)?.try_into().ok()
+}
+
+/// Price of a `PriceUpdateV2` account (as the Pyth receiver posts it), if
+/// `data` is one
+pub fn parse_pyth_price(data: &[u8]) -> Option<PythPrice> {
+    if word::<8>(data, 0)? != PRICE_UPDATE_V2_DISCRIMINATOR {
+        return None;
+    }
+    // Borsh enum: `Partial { num_signatures: u8 }` is two bytes, `Full` one
+    let (fully_verified, message) = match *data.get(VERIFICATION_OFFSET)? {
+        0 => (false, VERIFICATION_OFFSET + 2),
+        VERIFICATION_FULL => (true, VERIFICATION_OFFSET + 1),
+        _ => return None,
+    };
+    // PriceFeedMessage: feed_id, price, conf, exponent, publish_time, ...
+    let price = message + 32;
+    Some(PythPrice {
+        price: i64::from_le_bytes(word(data, price)?),
+        confidence: u64::from_le_bytes(word(data, price + 8)?),
+        exponent: i32::from_le_bytes(word(data, price + 16)?),
+        publish_time: i64::from_le_bytes(word(data, price + 20)?),
+        fully_verified,
+    })
+}
+
+/// Whether `price` satisfies `guard` at unix time `now`
+pub fn check_price(guard: &OracleGuard, price: &PythPrice, now: i64) -> Result<(), DiamondError> {
+    if !price.fully_verified {
+        msg!("Error: Feed {} update is only partially verified", guard.feed);
+        return Err(DiamondError::OracleConditionFailed);
+    }
+    if price.price <= 0 {
+        msg!("Error: Feed {} price {} is not positive", guard.feed, price.price);
+        return Err(DiamondError::OracleConditionFailed);
+    }
+    let age = now.saturating_sub(price.publish_time);
+    if age > i64::from(guard.max_age_secs) {
+        msg!("Error: Feed {} is {}s old (max {}s)", guard.feed, age, guard.max_age_secs);
+        return Err(DiamondError::OracleConditionFailed);
+    }
+    // confidence / price > bps / 10_000, without dividing
+    let wide = u128::from(price.confidence) * 10_000
+        > u128::from(guard.max_confidence_bps) * u128::from(price.price.unsigned_abs());
+    if guard.max_confidence_bps != 0 && wide {
+        msg!(
+            "Error: Feed {} confidence {} too wide for price {} (max {} bps)",
+            guard.feed,
+            price.confidence,
+            price.price,
+            guard.max_confidence_bps
+        );
+        return Err(DiamondError::OracleConditionFailed);
+    }
+    Ok(())
+}
+
+/// Refuse the call unless the guard's feed, passed among `accounts`, holds
+pub fn check_feed(guard: &OracleGuard, accounts: &[AccountInfo], now: i64) -> Result<(), DiamondError> {
+    let feed = accounts.iter().find(|a| a.key == &guard.feed).ok_or_else(|| {
+        msg!("Error: Oracle-guarded selector {:?} needs feed {}", guard.selector, guard.feed);
+        DiamondError::OracleConditionFailed
+    })?;
+    let data = feed.try_borrow_data().map_err(|_| DiamondError::OracleConditionFailed)?;
+    let price = (feed.owner == &PYTH_RECEIVER_ID).then(|| parse_pyth_price(&data)).flatten().ok_or_else(|| {
+        msg!("Error: {} is not a Pyth price update account", guard.feed);
+        DiamondError::OracleConditionFailed
+    })?;
+    check_price(guard, &price, now)
+}
+
+/// Set, replace or remove a selector's oracle guard (owner only)
+///
+/// Accounts: [diamond_state, owner]
+/// Data: OracleGuard (a default `feed` removes the selector's guard)
+#[cfg(feature = "oracle-policy")]
+pub fn set_oracle_guard(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let owner = next_account_info(account_iter)?;
+    
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let guard = OracleGuard::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    let selector = guard.selector;
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    if !state.is_owner(owner.key) {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    if state.get_mapping(selector).is_none() {
+        msg!("Error: Selector {:?} not found", selector);
+        return Err(DiamondError::ModuleNotFound.into());
+    }
+    state.oracle_guards.retain(|g| g.selector != selector);
+    let guard = (guard.feed != Pubkey::default()).then_some(guard);
+    if guard.is_some() {
+        if state.oracle_guards.len() >= DiamondState::MAX_ORACLE_GUARDS {
+            msg!("Error: At most {} selectors can be oracle-guarded", DiamondState::MAX_ORACLE_GUARDS);
+            return Err(DiamondError::SelectorCapacityExceeded.into());
+        }
+        state.oracle_guards.extend(guard);
+    }
+    state.save(state_account)?;
+    
+    match &guard {
+        Some(guard) => msg!(
+            "Selector {:?} gated on {} (max age {}s, max confidence {} bps)",
+            selector,
+            guard.feed,
+            guard.max_age_secs,
+            guard.max_confidence_bps
+        ),
+        None => msg!("Selector {:?} oracle guard removed", selector),
+    }
+    DiamondEvent::OracleGuardChanged { selector, guard }.emit();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_program::clock::Epoch;
+    
+    fn price_update(price: i64, confidence: u64, publish_time: i64) -> Vec<u8> {
+        let mut data = PRICE_UPDATE_V2_DISCRIMINATOR.to_vec();
+        data.extend([7u8; 32]); // write authority
+        data.push(VERIFICATION_FULL);
+        data.extend([9u8; 32]); // feed id
+        data.extend(price.to_le_bytes());
+        data.extend(confidence.to_le_bytes());
+        data.extend((-8i32).to_le_bytes());
+        data.extend(publish_time.to_le_bytes());
+        data.extend([0u8; 8 * 4]); // prev_publish_time, ema_price, ema_conf, posted_slot
+        data.resize(134, 0);
+        data
+    }
+    
+    fn guard(feed: Pubkey) -> OracleGuard {
+        OracleGuard { selector: [1, 0, 0, 1].into(), feed, max_age_secs: 60, max_confidence_bps: 50 }
+    }
+    
+    #[test]
+    fn test_parse_pyth_price() {
+        let data = price_update(150_000_000, 20_000, 1_000);
+        let parsed = PythPrice { price: 150_000_000, confidence: 20_000, exponent: -8, publish_time: 1_000, fully_verified: true };
+        assert_eq!(parse_pyth_price(&data), Some(parsed));
+        assert_eq!(parse_pyth_price(&data[..80]), None);
+        
+        // A partially verified update carries its signature count
+        let mut partial = data.clone();
+        partial[VERIFICATION_OFFSET] = 0;
+        partial.insert(VERIFICATION_OFFSET + 1, 3);
+        assert_eq!(parse_pyth_price(&partial), Some(PythPrice { fully_verified: false, ..parsed }));
+        
+        let mut other = data.clone();
+        other[0] ^= 1;
+        assert_eq!(parse_pyth_price(&other), None);
+        assert_eq!(parse_pyth_price(&[0u8; 134]), None);
+    }
+    
+    #[test]
+    fn test_check_price_bounds() {
+        let guard = guard(Pubkey::new_unique());
+        let price = |price, confidence, publish_time| PythPrice { price, confidence, exponent: -6, publish_time, fully_verified: true };
+        assert_eq!(check_price(&guard, &price(1_000_000, 5_000, 1_000), 1_060), Ok(()));
+        // Stale, too uncertain, not positive, or partially verified
+        assert_eq!(check_price(&guard, &price(1_000_000, 5_000, 1_000), 1_061), Err(DiamondError::OracleConditionFailed));
+        assert_eq!(check_price(&guard, &price(1_000_000, 5_001, 1_000), 1_000), Err(DiamondError::OracleConditionFailed));
+        assert_eq!(check_price(&guard, &price(0, 0, 1_000), 1_000), Err(DiamondError::OracleConditionFailed));
+        assert_eq!(check_price(&guard, &price(-1_000_000, 0, 1_000), 1_000), Err(DiamondError::OracleConditionFailed));
+        let partial = PythPrice { fully_verified: false, ..price(1_000_000, 5_000, 1_000) };
+        assert_eq!(check_price(&guard, &partial, 1_000), Err(DiamondError::OracleConditionFailed));
+        // A zero bound leaves confidence unchecked
+        let loose = OracleGuard { max_confidence_bps: 0, ..guard };
+        assert_eq!(check_price(&loose, &price(1_000_000, u64::MAX, 1_000), 1_000), Ok(()));
+    }
+    
+    #[test]
+    fn test_check_feed_needs_the_pinned_account() {
+        let (feed, impostor) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let guard = guard(feed);
+        let (mut lamports, mut data) = (0, price_update(1_000_000, 100, 1_000));
+        let (mut other_lamports, mut other_data) = (0, data.clone());
+        let (mut forged_lamports, mut forged_data) = (0, data.clone());
+        let fresh = AccountInfo::new(&impostor, false, false, &mut other_lamports, &mut other_data, &PYTH_RECEIVER_ID, false, Epoch::default());
+        assert_eq!(
+            check_feed(&guard, std::slice::from_ref(&fresh), 1_000),
+            Err(DiamondError::OracleConditionFailed)
+        );
+        // The pinned address only counts while the receiver owns it
+        let forged_owner = Pubkey::new_unique();
+        let forged = AccountInfo::new(&feed, false, false, &mut forged_lamports, &mut forged_data, &forged_owner, false, Epoch::default());
+        assert_eq!(check_feed(&guard, &[forged], 1_000), Err(DiamondError::OracleConditionFailed));
+        let account = AccountInfo::new(&feed, false, false, &mut lamports, &mut data, &PYTH_RECEIVER_ID, false, Epoch::default());
+        assert_eq!(check_feed(&guard, &[fresh, account], 1_000), Ok(()));
+    }
+}
@@ -0,0 +1,840 @@
+/*!
+ * Diamond Proposal Module
+ * Propose/approve flow for routing table changes
+ *
+ * Owners, admins or council members propose a batch of cuts into a
+ * proposal PDA; the owner (or, on a council diamond, a passed motion)
+ * approves it, which applies the batch atomically. Signers can inspect
+ * exactly what a proposal changes via `describe_proposal`.
+ *
+ * Instead of approving immediately the owner may schedule a proposal for an
+ * ETA slot, optionally escrowing a lamport tip in the proposal PDA; once the
+ * ETA passes anyone can crank `execute_cut` and collect the tip. A tip left
+ * over because the proposal was applied some other way goes back to whoever
+ * funded it when the proposal is closed.
+ *
+ * Third-party facets can also propose their own routes: a facet CPIs
+ * `request_registration` with a manifest of its functions, signed by its
+ * registrar PDA, and the resulting proposal waits for the owner like any
+ * other.
+ */
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    clock::Clock,
+    program::invoke,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    system_instruction,
+    system_program,
+    sysvar::Sysvar,
+};
+
+use crate::diamond_audit;
+use crate::diamond_authorizer;
+use crate::diamond_council;
+use crate::diamond_digest;
+use crate::diamond_index;
+use crate::diamond_cut::{apply_cuts, FacetCut, FacetCutAction};
+use crate::diamond_loupe::return_borsh;
+use crate::diamond_record::FacetRecord;
+use crate::diamond_state::DiamondState;
+use crate::diamond_vault;
+use crate::error::DiamondError;
+use crate::events::DiamondEvent;
+use crate::selector::Selector;
+
+pub use diamond_constants::seeds::{PROPOSAL_SEED, REGISTRAR_SEED};
+pub use diamond_core::registration::{ManifestFunction, RegistrationManifest};
+
+/// Pending batch of cuts
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct CutProposal {
+    pub diamond: Pubkey,
+    pub id: u64,
+    pub proposer: Pubkey,
+    pub cuts: Vec<FacetCut>,
+    pub executed: bool,
+    pub bump: u8,
+    /// Slot from which anyone may execute (set by `schedule_cut`)
+    pub eta_slot: Option<u64>,
+    /// Escrowed lamports paid to whoever executes
+    pub tip_lamports: u64,
+    /// Who funded the tip, refunded on `close_proposal` if nobody collected it
+    pub tip_payer: Pubkey,
+    /// Who paid the rent (possibly the rent vault), refunded on `close_proposal`
+    pub rent_payer: Pubkey,
+}
+
+impl CutProposal {
+    pub const MAX_CUTS: usize = diamond_constants::capacity::MAX_PROPOSAL_CUTS;
+    
+    pub const SPACE: usize =
+        32 + // diamond
+        8 +  // id
+        32 + // proposer
+        4 + (Self::MAX_CUTS * FacetCut::MAX_SIZE) + // cuts vec
+        1 +  // executed
+        1 +  // bump
+        9 +  // eta_slot
+        8 +  // tip_lamports
+        32 + // tip_payer
+        32;  // rent_payer
+    
+    pub fn find_address(program_id: &Pubkey, diamond: &Pubkey, id: u64) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[PROPOSAL_SEED, diamond.as_ref(), &id.to_le_bytes()],
+            program_id,
+        )
+    }
+    
+    /// Load a proposal belonging to `diamond`
+    pub fn load(
+        program_id: &Pubkey,
+        account: &AccountInfo,
+        diamond: &Pubkey,
+    ) -> Result<Self, ProgramError> {
+        if account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let data = account.try_borrow_data()?;
+        let proposal = Self::deserialize(&mut &data[..])
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        if &proposal.diamond != diamond {
+            msg!("Error: Proposal {} belongs to another diamond", proposal.id);
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(proposal)
+    }
+    
+    pub fn save(&self, account: &AccountInfo) -> ProgramResult {
+        let mut data = account.try_borrow_mut_data()?;
+        self.serialize(&mut &mut data[..])
+            .map_err(|_| ProgramError::AccountDataTooSmall)
+    }
+    
+    /// Scheduled and past its ETA
+    pub fn is_due_at(&self, slot: u64) -> bool {
+        matches!(self.eta_slot, Some(eta) if slot >= eta)
+    }
+}
+
+/// Apply a proposal (any escrowed tip stays in it)
+pub(crate) fn execute(
+    program_id: &Pubkey,
+    state_account: &AccountInfo,
+    proposal_account: &AccountInfo,
+    executor: &AccountInfo,
+    trailing_accounts: &[AccountInfo],
+    state: &mut DiamondState,
+    proposal: &mut CutProposal,
+) -> ProgramResult {
+    if proposal.executed {
+        return Err(DiamondError::ProposalAlreadyExecuted.into());
+    }
+    state.check_cuts_allowed(Clock::get()?.slot)?;
+    
+    apply_cuts(state, &proposal.cuts)?;
+    proposal.executed = true;
+    
+    state.save(state_account)?;
+    proposal.save(proposal_account)?;
+    let index_accounts = diamond_digest::refresh(program_id, state_account.key, state, trailing_accounts)?;
+    let audit_accounts = diamond_index::refresh(program_id, state_account.key, state, index_accounts)?;
+    diamond_audit::commit_cuts(program_id, state_account, state, audit_accounts, executor.key, &proposal.cuts)?;
+    
+    msg!("Proposal {} applied by {}", proposal.id, executor.key);
+    DiamondEvent::ProposalExecuted { id: proposal.id, approver: *executor.key }.emit();
+    Ok(())
+}
+
+/// One line of a proposal diff
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct DiffEntry {
+    pub action: FacetCutAction,
+    pub selector: Selector,
+    /// Qualified name from the cut, or from the current mapping if the cut has none
+    pub name: String,
+    pub old_module: Option<Pubkey>,
+    pub new_module: Option<Pubkey>,
+}
+
+/// Structured description of a proposal, returned via return data
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct ProposalDiff {
+    pub id: u64,
+    pub proposer: Pubkey,
+    pub executed: bool,
+    pub entries: Vec<DiffEntry>,
+}
+
+/// Diff a proposal against the current routing table
+pub fn describe(state: &DiamondState, proposal: &CutProposal) -> ProposalDiff {
+    let entries = proposal
+        .cuts
+        .iter()
+        .map(|cut| {
+            let current = state.get_mapping(cut.selector);
+            let name = if !cut.function_name.is_empty() {
+                match cut.namespace.as_str() {
+                    "" => cut.function_name.clone(),
+                    ns => format!("{}::{}", ns, cut.function_name),
+                }
+            } else {
+                current.map(|m| m.qualified_name()).unwrap_or_default()
+            };
+            DiffEntry {
+                action: cut.action,
+                selector: cut.selector,
+                name,
+                old_module: current.map(|m| m.module),
+                new_module: match cut.action {
+                    FacetCutAction::Remove => None,
+                    _ => Some(cut.module),
+                },
+            }
+        })
+        .collect();
+    
+    ProposalDiff {
+        id: proposal.id,
+        proposer: proposal.proposer,
+        executed: proposal.executed,
+        entries,
+    }
+}
+
+/// Propose a batch of cuts (owner, admin or council member)
+///
+/// Accounts: [diamond_state, proposal, authority, payer, system_program, (authorizer), (council)]
+///
+/// A council member proposes with the council account among the trailing accounts.
+///
+/// `payer` may be the diamond's rent vault when the authority is the owner or
+/// governance (see `diamond_vault::create_pda`).
+pub fn propose_cut(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let proposal_account = next_account_info(account_iter)?;
+    let authority = next_account_info(account_iter)?;
+    let payer = next_account_info(account_iter)?;
+    let system_program_account = next_account_info(account_iter)?;
+    
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if system_program_account.key != &system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    let cuts = Vec::<FacetCut>::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    
+    if !state.has_authority(authority.key)
+        && !diamond_council::is_member(program_id, &state, state_account.key, accounts, authority.key)
+    {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    if cuts.is_empty() || cuts.len() > CutProposal::MAX_CUTS {
+        msg!("Error: Proposal must contain 1..={} cuts", CutProposal::MAX_CUTS);
+        return Err(DiamondError::InvalidCut.into());
+    }
+    if cuts.iter().any(|cut| &cut.module == program_id) {
+        return Err(DiamondError::SelfDispatchForbidden.into());
+    }
+    
+    // Fail early if the batch would not apply against today's table
+    apply_cuts(&mut state.clone(), &cuts)?;
+    diamond_authorizer::authorize_cuts(state_account, &state, accounts, authority.key, &cuts)?;
+    
+    let vault_allowed = state.may_spend_rent_vault(authority.key);
+    create(
+        program_id,
+        state_account,
+        proposal_account,
+        payer,
+        vault_allowed,
+        system_program_account,
+        &mut state,
+        *authority.key,
+        cuts,
+    )?;
+    Ok(())
+}
+
+/// Create the next proposal PDA of the diamond holding `cuts`
+#[allow(clippy::too_many_arguments)]
+fn create<'info>(
+    program_id: &Pubkey,
+    state_account: &AccountInfo<'info>,
+    proposal_account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    vault_allowed: bool,
+    system_program_account: &AccountInfo<'info>,
+    state: &mut DiamondState,
+    proposer: Pubkey,
+    cuts: Vec<FacetCut>,
+) -> Result<u64, ProgramError> {
+    let id = state.next_proposal_id;
+    let (expected_pda, bump) = CutProposal::find_address(program_id, state_account.key, id);
+    if proposal_account.key != &expected_pda {
+        msg!("Error: Invalid proposal PDA for id {}", id);
+        return Err(ProgramError::InvalidSeeds);
+    }
+    
+    diamond_vault::create_pda(
+        program_id,
+        state_account.key,
+        vault_allowed,
+        payer,
+        proposal_account,
+        system_program_account,
+        CutProposal::SPACE,
+        &[PROPOSAL_SEED, state_account.key.as_ref(), &id.to_le_bytes(), &[bump]],
+    )?;
+    
+    let proposal = CutProposal {
+        diamond: *state_account.key,
+        id,
+        proposer,
+        cuts,
+        executed: false,
+        bump,
+        eta_slot: None,
+        tip_lamports: 0,
+        tip_payer: Pubkey::default(),
+        rent_payer: *payer.key,
+    };
+    proposal.save(proposal_account)?;
+    
+    state.next_proposal_id = id
+        .checked_add(1)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    state.save(state_account)?;
+    
+    msg!("Proposal {} created with {} cuts", id, proposal.cuts.len());
+    DiamondEvent::ProposalCreated {
+        id,
+        proposer: proposal.proposer,
+        cut_count: proposal.cuts.len() as u8,
+    }
+    .emit();
+    Ok(id)
+}
+
+/// Registrar PDA a facet signs `request_registration` with
+pub fn registrar_address(facet: &Pubkey, diamond: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[REGISTRAR_SEED, diamond.as_ref()], facet)
+}
+
+/// Cuts adding every function of a manifest, routed to `facet`
+pub fn registration_cuts(facet: &Pubkey, manifest: &RegistrationManifest) -> Vec<FacetCut> {
+    manifest
+        .functions
+        .iter()
+        .map(|function| FacetCut {
+            namespace: manifest.namespace.clone(),
+            standard_accounts: function.standard_accounts,
+            ..FacetCut::add(function.selector, *facet, &manifest.module_name, &function.function_name)
+        })
+        .collect()
+}
+
+/// Ask for a facet's own selectors to be routed to it (CPI from the facet)
+///
+/// Accounts: [diamond_state, proposal, facet_program, registrar, payer, system_program, (facet_record), (authorizer)]
+/// Data: RegistrationManifest
+///
+/// `registrar` is the facet's `[REGISTRAR_SEED, diamond]` PDA, which only the
+/// facet program can sign for. The manifest becomes a proposal from the
+/// facet that only adds selectors routed to it; the owner applies it with
+/// `approve_cut` (or, on a council diamond, by motion) or leaves it pending.
+/// A manifest declaring return schemas also passes the facet's record, which
+/// keeps them for clients; they apply once the selectors route to the facet.
+pub fn request_registration(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let proposal_account = next_account_info(account_iter)?;
+    let facet_program = next_account_info(account_iter)?;
+    let registrar = next_account_info(account_iter)?;
+    let payer = next_account_info(account_iter)?;
+    let system_program_account = next_account_info(account_iter)?;
+    
+    if !registrar.is_signer || !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if system_program_account.key != &system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if facet_program.key == program_id {
+        return Err(DiamondError::SelfDispatchForbidden.into());
+    }
+    let (expected_registrar, _) = registrar_address(facet_program.key, state_account.key);
+    if registrar.key != &expected_registrar {
+        msg!("Error: Invalid registrar for facet {}. Expected: {}", facet_program.key, expected_registrar);
+        return Err(ProgramError::InvalidSeeds);
+    }
+    
+    let manifest = RegistrationManifest::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    
+    if manifest.functions.is_empty() || manifest.functions.len() > CutProposal::MAX_CUTS {
+        msg!("Error: Manifest must list 1..={} functions", CutProposal::MAX_CUTS);
+        return Err(DiamondError::InvalidCut.into());
+    }
+    let cuts = registration_cuts(facet_program.key, &manifest);
+    apply_cuts(&mut state.clone(), &cuts)?;
+    diamond_authorizer::authorize_cuts(state_account, &state, accounts, facet_program.key, &cuts)?;
+    
+    let id = create(
+        program_id,
+        state_account,
+        proposal_account,
+        payer,
+        false,
+        system_program_account,
+        &mut state,
+        *facet_program.key,
+        cuts,
+    )?;
+    
+    if manifest.return_schemas().next().is_some() {
+        let record_account = next_account_info(account_iter)?;
+        let mut record = FacetRecord::open(
+            program_id,
+            record_account,
+            payer,
+            system_program_account,
+            state_account.key,
+            facet_program.key,
+        )?;
+        record.declare_return_schemas(&state, &manifest)?;
+        record.save(record_account)?;
+    }
+    
+    msg!("Facet {} requested registration of {} as proposal {}", facet_program.key, manifest.module_name, id);
+    DiamondEvent::RegistrationRequested { id, facet: *facet_program.key }.emit();
+    Ok(())
+}
+
+/// Approve and apply a proposal (owner only)
+///
+/// Accounts: [diamond_state, proposal, owner, (routing digest), (selector index), (audit accounts)]
+/// An escrowed tip isn't collected; `close_proposal` refunds it.
+pub fn approve_cut(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let proposal_account = next_account_info(account_iter)?;
+    let owner = next_account_info(account_iter)?;
+    
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    let mut proposal = CutProposal::load(program_id, proposal_account, state_account.key)?;
+    
+    if !state.is_owner(owner.key) {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    if state.has_council {
+        msg!("Error: Council diamonds apply proposals by motion");
+        return Err(DiamondError::CouncilApprovalRequired.into());
+    }
+    
+    execute(program_id, state_account, proposal_account, owner, account_iter.as_slice(), &mut state, &mut proposal)
+}
+
+/// Schedule a proposal for permissionless execution (owner only)
+///
+/// Accounts: [diamond_state, proposal, owner, funder, system_program]
+/// Data: eta_slot (u64), tip_lamports (u64) moved from `funder` into the proposal
+///
+/// A proposal holds one funder's tip: rescheduling may top it up from the
+/// same funder only.
+pub fn schedule_cut(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let proposal_account = next_account_info(account_iter)?;
+    let owner = next_account_info(account_iter)?;
+    let funder = next_account_info(account_iter)?;
+    let system_program_account = next_account_info(account_iter)?;
+    
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if system_program_account.key != &system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    #[derive(BorshDeserialize)]
+    struct ScheduleData {
+        eta_slot: u64,
+        tip_lamports: u64,
+    }
+    
+    let schedule = ScheduleData::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let state = DiamondState::load(program_id, state_account)?;
+    let mut proposal = CutProposal::load(program_id, proposal_account, state_account.key)?;
+    
+    if !state.is_owner(owner.key) {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    if state.has_council {
+        msg!("Error: Council diamonds apply proposals by motion");
+        return Err(DiamondError::CouncilApprovalRequired.into());
+    }
+    if proposal.executed {
+        return Err(DiamondError::ProposalAlreadyExecuted.into());
+    }
+    
+    if schedule.tip_lamports > 0 {
+        if !funder.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if proposal.tip_lamports > 0 && funder.key != &proposal.tip_payer {
+            msg!("Error: Proposal {} already holds a tip from {}", proposal.id, proposal.tip_payer);
+            return Err(ProgramError::InvalidArgument);
+        }
+        proposal.tip_payer = *funder.key;
+        invoke(
+            &system_instruction::transfer(funder.key, proposal_account.key, schedule.tip_lamports),
+            &[funder.clone(), proposal_account.clone(), system_program_account.clone()],
+        )?;
+    }
+    
+    proposal.eta_slot = Some(schedule.eta_slot);
+    proposal.tip_lamports = proposal
+        .tip_lamports
+        .checked_add(schedule.tip_lamports)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    proposal.save(proposal_account)?;
+    
+    msg!(
+        "Proposal {} scheduled for slot {} (tip {})",
+        proposal.id,
+        schedule.eta_slot,
+        proposal.tip_lamports
+    );
+    DiamondEvent::CutScheduled {
+        id: proposal.id,
+        eta_slot: schedule.eta_slot,
+        tip_lamports: proposal.tip_lamports,
+    }
+    .emit();
+    Ok(())
+}
+
+/// Execute a scheduled proposal once its ETA has passed and collect its tip (anyone)
+///
+/// Accounts: [diamond_state, proposal, executor, (routing digest), (selector index), (audit accounts)]
+/// The executor must be writable when the proposal holds a tip.
+pub fn execute_cut(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let proposal_account = next_account_info(account_iter)?;
+    let executor = next_account_info(account_iter)?;
+    
+    if !executor.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    let mut proposal = CutProposal::load(program_id, proposal_account, state_account.key)?;
+    
+    let slot = Clock::get()?.slot;
+    if !proposal.is_due_at(slot) {
+        msg!("Error: Proposal {} not due (eta {:?}, slot {})", proposal.id, proposal.eta_slot, slot);
+        return Err(DiamondError::ProposalNotReady.into());
+    }
+    
+    let tip = std::mem::take(&mut proposal.tip_lamports);
+    execute(program_id, state_account, proposal_account, executor, account_iter.as_slice(), &mut state, &mut proposal)?;
+    if tip > 0 {
+        pay_out(proposal_account, executor, tip)?;
+        msg!("Tip of {} paid to {}", tip, executor.key);
+    }
+    Ok(())
+}
+
+/// Move `lamports` out of a router-owned account
+fn pay_out(from: &AccountInfo, to: &AccountInfo, lamports: u64) -> ProgramResult {
+    **from.try_borrow_mut_lamports()? = from
+        .lamports()
+        .checked_sub(lamports)
+        .ok_or(ProgramError::InsufficientFunds)?;
+    **to.try_borrow_mut_lamports()? = to
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    Ok(())
+}
+
+/// Close an executed proposal, refunding its rent to whoever paid it (anyone)
+///
+/// Accounts: [diamond_state, proposal, rent_payer, (tip_payer)]
+///
+/// `rent_payer` must be the payer recorded at creation, so a proposal the
+/// rent vault paid for returns its lamports to the vault. A tip nobody
+/// collected goes back to the recorded `tip_payer`, which must then follow.
+pub fn close_proposal(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let proposal_account = next_account_info(account_iter)?;
+    let rent_payer = next_account_info(account_iter)?;
+    
+    let proposal = CutProposal::load(program_id, proposal_account, state_account.key)?;
+    if !proposal.executed {
+        msg!("Error: Proposal {} is still pending", proposal.id);
+        return Err(DiamondError::ProposalNotReady.into());
+    }
+    if rent_payer.key != &proposal.rent_payer {
+        msg!("Error: Proposal {} rent belongs to {}", proposal.id, proposal.rent_payer);
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    if proposal.tip_lamports > 0 {
+        let tip_payer = next_account_info(account_iter)?;
+        if tip_payer.key != &proposal.tip_payer {
+            msg!("Error: Proposal {} tip belongs to {}", proposal.id, proposal.tip_payer);
+            return Err(DiamondError::UnauthorizedAccess.into());
+        }
+        pay_out(proposal_account, tip_payer, proposal.tip_lamports)?;
+        msg!("Unused tip of {} refunded to {}", proposal.tip_lamports, tip_payer.key);
+    }
+    
+    let refund = proposal_account.lamports();
+    **rent_payer.try_borrow_mut_lamports()? = rent_payer
+        .lamports()
+        .checked_add(refund)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    **proposal_account.try_borrow_mut_lamports()? = 0;
+    proposal_account.try_borrow_mut_data()?.fill(0);
+    
+    msg!("Proposal {} closed, {} lamports refunded to {}", proposal.id, refund, rent_payer.key);
+    DiamondEvent::ProposalClosed { id: proposal.id, rent_payer: *rent_payer.key, refund }.emit();
+    Ok(())
+}
+
+/// Describe what a proposal would change
+///
+/// Accounts: [diamond_state, proposal]
+/// Returns a Borsh-encoded `ProposalDiff` via return data.
+pub fn describe_proposal(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let proposal_account = next_account_info(account_iter)?;
+    
+    let state = DiamondState::load(program_id, state_account)?;
+    let proposal = CutProposal::load(program_id, proposal_account, state_account.key)?;
+    
+    let diff = describe(&state, &proposal);
+    msg!("Proposal {}: {} changes", diff.id, diff.entries.len());
+    return_borsh(&diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diamond_cut::apply_cut;
+    use crate::diamond_state::schema_hash;
+
+    #[test]
+    fn test_describe_reports_names_and_modules() {
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        let (v1, v2) = (Pubkey::new_unique(), Pubkey::new_unique());
+        apply_cut(&mut state, &FacetCut::add([1, 0, 0, 0], v1, "counter", "increment")).unwrap();
+        apply_cut(&mut state, &FacetCut::add([2, 0, 0, 0], v1, "counter", "reset")).unwrap();
+        
+        let mut added = FacetCut::add([3, 0, 0, 0], v2, "counter", "get_value");
+        added.namespace = "counter".to_string();
+        let proposal = CutProposal {
+            diamond: Pubkey::new_unique(),
+            id: 7,
+            proposer: Pubkey::new_unique(),
+            cuts: vec![
+                added,
+                FacetCut::replace([1, 0, 0, 0], v2, "counter_v2"),
+                FacetCut::remove([2, 0, 0, 0]),
+            ],
+            executed: false,
+            bump: 255,
+            eta_slot: None,
+            tip_lamports: 0,
+            tip_payer: Pubkey::new_unique(),
+            rent_payer: Pubkey::new_unique(),
+        };
+        
+        let diff = describe(&state, &proposal);
+        assert_eq!(diff.id, 7);
+        assert_eq!(diff.entries[0].name, "counter::get_value");
+        assert_eq!(diff.entries[0].old_module, None);
+        assert_eq!(diff.entries[1].name, "increment");
+        assert_eq!(diff.entries[1].old_module, Some(v1));
+        assert_eq!(diff.entries[1].new_module, Some(v2));
+        assert_eq!(diff.entries[2].name, "reset");
+        assert_eq!(diff.entries[2].new_module, None);
+    }
+
+    #[test]
+    fn test_registration_only_adds_to_the_facet() {
+        let facet = Pubkey::new_unique();
+        let manifest = RegistrationManifest {
+            module_name: "vault".to_string(),
+            namespace: "vault".to_string(),
+            functions: vec![
+                ManifestFunction {
+                    selector: [1, 0, 0, 1].into(),
+                    function_name: "deposit".to_string(),
+                    standard_accounts: 0,
+                    return_schema: [0; 8],
+                },
+                ManifestFunction {
+                    selector: [1, 0, 0, 2].into(),
+                    function_name: "total".to_string(),
+                    standard_accounts: 3,
+                    return_schema: schema_hash("u64"),
+                },
+            ],
+        };
+        let cuts = registration_cuts(&facet, &manifest);
+        assert!(cuts.iter().all(|cut| cut.action == FacetCutAction::Add && cut.module == facet && !cut.is_immutable));
+        assert_eq!(cuts[1].namespace, "vault");
+        assert_eq!(cuts[1].standard_accounts, 3);
+        
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        apply_cuts(&mut state, &cuts).unwrap();
+        assert_eq!(state.get_mapping(Selector::from([1, 0, 0, 2])).unwrap().qualified_name(), "vault::total");
+        assert_ne!(registrar_address(&facet, &Pubkey::new_unique()).0, registrar_address(&facet, &Pubkey::new_unique()).0);
+        
+        // Only declared schemas are kept, and only while the selector routes to the facet
+        let mut record = FacetRecord {
+            diamond: Pubkey::new_unique(),
+            module: facet,
+            attestation: None,
+            versions: Vec::new(),
+            return_schemas: vec![(Selector::from([9, 9, 9, 9]), [7; 8]), (Selector::from([1, 0, 0, 2]), [7; 8])],
+            bump: 255,
+        };
+        record.declare_return_schemas(&state, &manifest).unwrap();
+        assert_eq!(record.return_schemas, vec![(Selector::from([1, 0, 0, 2]), schema_hash("u64"))]);
+        assert_eq!(record.return_schema(&Selector::from([1, 0, 0, 1])), None);
+    }
+    
+    #[test]
+    fn test_max_proposal_fits_space() {
+        let cut = FacetCut {
+            module_name: "m".repeat(32),
+            namespace: "n".repeat(8),
+            function_name: "f".repeat(64),
+            ..FacetCut::add([0; 4], Pubkey::new_unique(), "", "")
+        };
+        let proposal = CutProposal {
+            diamond: Pubkey::new_unique(),
+            id: u64::MAX,
+            proposer: Pubkey::new_unique(),
+            cuts: vec![cut; CutProposal::MAX_CUTS],
+            executed: true,
+            bump: 255,
+            eta_slot: Some(u64::MAX),
+            tip_lamports: u64::MAX,
+            tip_payer: Pubkey::new_unique(),
+            rent_payer: Pubkey::new_unique(),
+        };
+        
+        assert_eq!(borsh::to_vec(&proposal).unwrap().len(), CutProposal::SPACE);
+    }
+    
+    #[test]
+    fn test_due_only_after_eta() {
+        let mut proposal = CutProposal {
+            diamond: Pubkey::new_unique(),
+            id: 0,
+            proposer: Pubkey::new_unique(),
+            cuts: vec![],
+            executed: false,
+            bump: 255,
+            eta_slot: None,
+            tip_lamports: 0,
+            tip_payer: Pubkey::new_unique(),
+            rent_payer: Pubkey::new_unique(),
+        };
+        assert!(!proposal.is_due_at(u64::MAX));
+        
+        proposal.eta_slot = Some(100);
+        assert!(!proposal.is_due_at(99));
+        assert!(proposal.is_due_at(100));
+    }
+    
+    #[test]
+    fn test_close_refunds_an_uncollected_tip_to_its_payer() {
+        use solana_program::clock::Epoch;
+        
+        let (program_id, diamond) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (rent_key, tip_key, address) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let proposal = CutProposal {
+            diamond,
+            id: 3,
+            proposer: Pubkey::new_unique(),
+            cuts: vec![],
+            executed: true,
+            bump: 255,
+            eta_slot: Some(10),
+            tip_lamports: 500,
+            tip_payer: tip_key,
+            rent_payer: rent_key,
+        };
+        let mut data = borsh::to_vec(&proposal).unwrap();
+        let (mut proposal_lamports, mut rent_lamports, mut tip_lamports, mut state_lamports) = (2_500u64, 0u64, 0u64, 0u64);
+        let mut state_data = vec![];
+        let state_account = AccountInfo::new(&diamond, false, false, &mut state_lamports, &mut state_data, &program_id, false, Epoch::default());
+        let proposal_account = AccountInfo::new(&address, false, true, &mut proposal_lamports, &mut data, &program_id, false, Epoch::default());
+        let rent_payer = AccountInfo::new(&rent_key, false, true, &mut rent_lamports, &mut [], &program_id, false, Epoch::default());
+        let tip_payer = AccountInfo::new(&tip_key, false, true, &mut tip_lamports, &mut [], &program_id, false, Epoch::default());
+        
+        // The tip payer has to come along while a tip is held
+        let without_tip_payer = [state_account.clone(), proposal_account.clone(), rent_payer.clone()];
+        assert_eq!(close_proposal(&program_id, &without_tip_payer, &[]), Err(ProgramError::NotEnoughAccountKeys));
+        let wrong_tip_payer = [state_account.clone(), proposal_account.clone(), rent_payer.clone(), rent_payer.clone()];
+        assert_eq!(close_proposal(&program_id, &wrong_tip_payer, &[]), Err(DiamondError::UnauthorizedAccess.into()));
+        
+        close_proposal(&program_id, &[state_account, proposal_account, rent_payer.clone(), tip_payer.clone()], &[]).unwrap();
+        assert_eq!((tip_payer.lamports(), rent_payer.lamports()), (500, 2_000));
+    }
+}
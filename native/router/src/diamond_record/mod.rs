@@ -0,0 +1,212 @@
+/*!
+ * Diamond Record Module
+ * Per-facet build attestation, version history and return schemas
+ *
+ * What the diamond remembers about a facet beyond routing lives in one
+ * account per facet at `[FACET_RECORD_SEED, diamond, facet]`: the attested
+ * build hash with its last check (`diamond_build`), the facet's latest
+ * version declarations (`diamond_version`) and the return schemas it
+ * declared when registering (`diamond_proposal::request_registration`).
+ * Dispatch never reads it, so it stays out of the diamond state. The account
+ * is created by the first attestation or schema declaration, and read by the
+ * build and version loupes and by clients decoding responses.
+ */
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    msg,
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    system_program,
+    sysvar::Sysvar,
+};
+
+use diamond_core::registration::RegistrationManifest;
+use crate::diamond_state::{BuildAttestation, DiamondState, Semver, VersionRecord};
+use crate::error::DiamondError;
+use crate::selector::Selector;
+
+pub use diamond_constants::{
+    capacity::{MAX_RETURN_SCHEMAS, MAX_VERSION_RECORDS},
+    seeds::FACET_RECORD_SEED,
+};
+
+/// Build and version record of one facet of one diamond
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct FacetRecord {
+    pub diamond: Pubkey,
+    pub module: Pubkey,
+    pub attestation: Option<BuildAttestation>,
+    /// Latest version declarations, oldest first
+    pub versions: Vec<VersionRecord>,
+    /// `payload::schema_hash` of what each declared selector returns
+    pub return_schemas: Vec<(Selector, [u8; 8])>,
+    pub bump: u8,
+}
+
+impl FacetRecord {
+    pub const SPACE: usize =
+        32 + // diamond
+        32 + // module
+        1 + BuildAttestation::ENCODED_LEN + // attestation (Option<BuildAttestation>)
+        4 + (MAX_VERSION_RECORDS * VersionRecord::ENCODED_LEN) + // versions vec
+        4 + (MAX_RETURN_SCHEMAS * (8 + 8)) + // return_schemas vec
+        1;   // bump
+    
+    pub fn find_address(program_id: &Pubkey, diamond: &Pubkey, module: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[FACET_RECORD_SEED, diamond.as_ref(), module.as_ref()], program_id)
+    }
+    
+    /// Record of `module` held by `account`, or `None` while the account
+    /// hasn't been created
+    pub fn load(
+        program_id: &Pubkey,
+        account: &AccountInfo,
+        diamond: &Pubkey,
+        module: &Pubkey,
+    ) -> Result<Option<Self>, ProgramError> {
+        if account.data_is_empty() {
+            let (expected, _) = Self::find_address(program_id, diamond, module);
+            if account.key != &expected {
+                msg!("Error: {} is not the facet record of {}", account.key, module);
+                return Err(ProgramError::InvalidSeeds);
+            }
+            return Ok(None);
+        }
+        if account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let data = account.try_borrow_data()?;
+        let record = Self::deserialize(&mut &data[..]).map_err(|_| ProgramError::InvalidAccountData)?;
+        let seeds: &[&[u8]] = &[FACET_RECORD_SEED, diamond.as_ref(), module.as_ref(), &[record.bump]];
+        if &record.diamond != diamond
+            || &record.module != module
+            || Pubkey::create_program_address(seeds, program_id).ok().as_ref() != Some(account.key)
+        {
+            msg!("Error: {} is not the facet record of {}", account.key, module);
+            return Err(ProgramError::InvalidSeeds);
+        }
+        Ok(Some(record))
+    }
+    
+    /// Load the record of `module`, creating its account (paid by `payer`) if needed
+    pub fn open<'info>(
+        program_id: &Pubkey,
+        account: &AccountInfo<'info>,
+        payer: &AccountInfo<'info>,
+        system_program_account: &AccountInfo<'info>,
+        diamond: &Pubkey,
+        module: &Pubkey,
+    ) -> Result<Self, ProgramError> {
+        if let Some(record) = Self::load(program_id, account, diamond, module)? {
+            return Ok(record);
+        }
+        if !payer.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if system_program_account.key != &system_program::id() {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let (_, bump) = Self::find_address(program_id, diamond, module);
+        invoke_signed(
+            &system_instruction::create_account(
+                payer.key,
+                account.key,
+                Rent::get()?.minimum_balance(Self::SPACE),
+                Self::SPACE as u64,
+                program_id,
+            ),
+            &[payer.clone(), account.clone(), system_program_account.clone()],
+            &[&[FACET_RECORD_SEED, diamond.as_ref(), module.as_ref(), &[bump]]],
+        )?;
+        Ok(Self {
+            diamond: *diamond,
+            module: *module,
+            attestation: None,
+            versions: Vec::new(),
+            return_schemas: Vec::new(),
+            bump,
+        })
+    }
+    
+    pub fn save(&self, account: &AccountInfo) -> ProgramResult {
+        let mut data = account.try_borrow_mut_data()?;
+        self.serialize(&mut &mut data[..]).map_err(|_| ProgramError::AccountDataTooSmall)
+    }
+    
+    /// Append a version declaration for the attested build `hash`
+    ///
+    /// A build keeps one entry, moved to the end when declared again, so
+    /// only new attested builds (which only the owner can attest) push old
+    /// entries out. Once full the oldest goes, never the live build's.
+    pub fn declare(&mut self, version: Semver, hash: [u8; 32], slot: u64) {
+        self.versions.retain(|r| r.hash != hash);
+        if self.versions.len() >= MAX_VERSION_RECORDS {
+            self.versions.remove(0);
+        }
+        self.versions.push(VersionRecord { module: self.module, version, hash, slot });
+    }
+    
+    /// Record the return schemas `manifest` declares
+    ///
+    /// Entries of the manifest's selectors are replaced, and entries of
+    /// selectors no longer routed to the facet are dropped.
+    pub fn declare_return_schemas(&mut self, state: &DiamondState, manifest: &RegistrationManifest) -> Result<(), DiamondError> {
+        let module = self.module;
+        self.return_schemas.retain(|(selector, _)| {
+            !manifest.functions.iter().any(|f| &f.selector == selector)
+                && state.get_mapping(*selector).is_some_and(|m| m.module == module)
+        });
+        self.return_schemas.extend(manifest.return_schemas());
+        if self.return_schemas.len() > MAX_RETURN_SCHEMAS {
+            msg!("Error: At most {} return schemas can be declared", MAX_RETURN_SCHEMAS);
+            return Err(DiamondError::SelectorCapacityExceeded);
+        }
+        Ok(())
+    }
+    
+    /// Return schema declared for `selector`
+    pub fn return_schema(&self, selector: &Selector) -> Option<[u8; 8]> {
+        self.return_schemas.iter().find(|(s, _)| s == selector).map(|(_, schema)| *schema)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diamond_state::BuildCheck;
+    
+    #[test]
+    fn test_full_record_fits_space() {
+        let module = Pubkey::new_unique();
+        let mut record = FacetRecord {
+            diamond: Pubkey::new_unique(),
+            module,
+            attestation: Some(BuildAttestation {
+                module,
+                hash: [1; 32],
+                last_check: Some(BuildCheck { deploy_slot: u64::MAX, matched: true }),
+            }),
+            versions: Vec::new(),
+            return_schemas: (0..MAX_RETURN_SCHEMAS as u64).map(|i| (i.to_le_bytes().into(), [0xFF; 8])).collect(),
+            bump: 255,
+        };
+        for slot in 0..=MAX_VERSION_RECORDS as u64 {
+            record.declare(Semver::new(1, 0, slot as u16), [slot as u8; 32], slot);
+        }
+        assert_eq!(record.versions.len(), MAX_VERSION_RECORDS);
+        assert_eq!(record.versions[0].slot, 1);
+        
+        // Redeclaring a build replaces its entry instead of evicting another
+        record.declare(Semver::new(1, 1, 0), [1; 32], 100);
+        assert_eq!(record.versions.len(), MAX_VERSION_RECORDS);
+        assert_eq!((record.versions[0].slot, record.versions.last().unwrap().slot), (2, 100));
+        assert_eq!(record.versions.iter().filter(|r| r.hash == [1; 32]).count(), 1);
+        assert_eq!(borsh::to_vec(&record).unwrap().len(), FacetRecord::SPACE);
+    }
+}
@@ -0,0 +1,427 @@
+/*!
+ * Diamond Relay Module
+ * Relayed (gasless) dispatch authorized by an off-chain user signature
+ *
+ * The user signs a `RelayPayload` off-chain, as a `relay` action in the
+ * shared signing format (see `diamond_signing`); a relayer submits it with
+ * an Ed25519 program instruction verifying that signature immediately
+ * before `dispatch_relayed`, and pays the transaction fee. The router checks the
+ * verified message against the payload, consumes the user's nonce and signs
+ * the facet call with the user's relay signer PDA, which facets accept as
+ * the user's authorization. The payload also commits to the accounts the
+ * call is made with, so a relayer cannot swap them. The diamond can
+ * reimburse relayers a fixed amount per call, capped by the user in the
+ * payload and paid from a fee vault the user funds.
+ */
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    hash::hashv,
+    msg,
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    system_program,
+    sysvar::Sysvar,
+};
+
+use crate::diamond_router::{route, RouteOptions};
+use crate::diamond_signing::{self, action, data_hash, SigningPayload};
+use crate::diamond_state::DiamondState;
+use crate::error::DiamondError;
+use crate::events::DiamondEvent;
+
+pub use diamond_constants::seeds::{FEE_VAULT_SEED, RELAY_NONCE_SEED, RELAY_SIGNER_SEED};
+
+/// A facet call the user authorized off-chain
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct RelayPayload {
+    /// Application the user signed in (see `SigningPayload::domain`)
+    pub domain: String,
+    pub diamond: Pubkey,
+    pub facet: Pubkey,
+    pub user: Pubkey,
+    /// Must equal the user's `RelayNonce::next_nonce`
+    pub nonce: u64,
+    /// Last slot the relayer may submit at
+    pub expiry_slot: u64,
+    /// Facet payload, selector first
+    pub ix_data: Vec<u8>,
+    /// `accounts_hash` of the accounts after `module`
+    pub accounts_hash: [u8; 32],
+    /// Most the relayer may be reimbursed from the user's fee vault
+    pub max_reimbursement: u64,
+}
+
+impl RelayPayload {
+    /// The approval the user signs: a `relay` action over
+    /// `(facet, ix_data, accounts_hash, max_reimbursement)`
+    pub fn signing_payload(&self) -> SigningPayload {
+        // Writing into a Vec cannot fail
+        let data = borsh::to_vec(&(self.facet, &self.ix_data, self.accounts_hash, self.max_reimbursement))
+            .expect("borsh encode");
+        SigningPayload {
+            domain: self.domain.clone(),
+            signer: self.user.to_bytes(),
+            diamond: self.diamond.to_bytes(),
+            action: action::RELAY.into(),
+            nonce: self.nonce,
+            expiry_slot: self.expiry_slot,
+            data_hash: data_hash(&data),
+        }
+    }
+    
+    /// Bytes the user signs
+    pub fn message(&self) -> Vec<u8> {
+        self.signing_payload().message()
+    }
+}
+
+/// Replay protection for one user of one diamond
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct RelayNonce {
+    pub diamond: Pubkey,
+    pub user: Pubkey,
+    pub next_nonce: u64,
+    pub bump: u8,
+}
+
+impl RelayNonce {
+    pub const SPACE: usize =
+        32 + // diamond
+        32 + // user
+        8 +  // next_nonce
+        1;   // bump
+    
+    pub fn find_address(program_id: &Pubkey, diamond: &Pubkey, user: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[RELAY_NONCE_SEED, diamond.as_ref(), user.as_ref()], program_id)
+    }
+}
+
+/// PDA forwarded as a signer on calls relayed for `user`
+pub fn relay_signer(program_id: &Pubkey, diamond: &Pubkey, user: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[RELAY_SIGNER_SEED, diamond.as_ref(), user.as_ref()], program_id)
+}
+
+/// System account `user`'s relayed calls are reimbursed from; fund it with a transfer
+pub fn fee_vault(program_id: &Pubkey, diamond: &Pubkey, user: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[FEE_VAULT_SEED, diamond.as_ref(), user.as_ref()], program_id)
+}
+
+/// Hash of a relayed call's accounts: key, signer and writable flags of each, in order
+pub fn accounts_hash<'a>(accounts: impl IntoIterator<Item = (&'a Pubkey, bool, bool)>) -> [u8; 32] {
+    let encoded: Vec<u8> = accounts
+        .into_iter()
+        .flat_map(|(key, is_signer, is_writable)| {
+            key.to_bytes().into_iter().chain([is_signer as u8, is_writable as u8])
+        })
+        .collect();
+    hashv(&[&encoded]).to_bytes()
+}
+
+/// Lamports owed to the relayer: the diamond's rate, capped by the user
+pub fn reimbursement(payload: &RelayPayload, rate: u64) -> u64 {
+    rate.min(payload.max_reimbursement)
+}
+
+/// Check a payload against the diamond, facet, accounts, slot and the user's nonce
+pub fn check_payload(
+    payload: &RelayPayload,
+    diamond: &Pubkey,
+    facet: &Pubkey,
+    accounts_hash: &[u8; 32],
+    slot: u64,
+    nonce: &RelayNonce,
+) -> Result<(), DiamondError> {
+    if &payload.diamond != diamond || &payload.facet != facet {
+        msg!("Error: Relay payload is for another diamond or facet");
+        return Err(DiamondError::InvalidRelay);
+    }
+    if &payload.accounts_hash != accounts_hash {
+        msg!("Error: Relayed accounts differ from the ones the user signed");
+        return Err(DiamondError::InvalidRelay);
+    }
+    if !payload.signing_payload().is_well_formed() {
+        msg!("Error: Relay payload domain must be one line of at most {} bytes", diamond_signing::MAX_DOMAIN_LEN);
+        return Err(DiamondError::InvalidRelay);
+    }
+    if slot > payload.expiry_slot {
+        msg!("Error: Relay payload expired at slot {}", payload.expiry_slot);
+        return Err(DiamondError::InvalidRelay);
+    }
+    if payload.nonce != nonce.next_nonce {
+        msg!("Error: Relay nonce {} (expected {})", payload.nonce, nonce.next_nonce);
+        return Err(DiamondError::InvalidRelay);
+    }
+    Ok(())
+}
+
+/// Load the user's nonce account, creating it (paid by the relayer) on first use
+pub(crate) fn load_or_create_nonce<'info>(
+    program_id: &Pubkey,
+    diamond: &Pubkey,
+    user: &Pubkey,
+    nonce_account: &AccountInfo<'info>,
+    relayer: &AccountInfo<'info>,
+    system_program_account: &AccountInfo<'info>,
+) -> Result<RelayNonce, ProgramError> {
+    let (expected, bump) = RelayNonce::find_address(program_id, diamond, user);
+    if nonce_account.key != &expected {
+        msg!("Error: Invalid relay nonce PDA. Expected: {}", expected);
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if nonce_account.data_is_empty() {
+        let rent = Rent::get()?;
+        invoke_signed(
+            &system_instruction::create_account(
+                relayer.key,
+                nonce_account.key,
+                rent.minimum_balance(RelayNonce::SPACE),
+                RelayNonce::SPACE as u64,
+                program_id,
+            ),
+            &[relayer.clone(), nonce_account.clone(), system_program_account.clone()],
+            &[&[RELAY_NONCE_SEED, diamond.as_ref(), user.as_ref(), &[bump]]],
+        )?;
+        return Ok(RelayNonce { diamond: *diamond, user: *user, next_nonce: 0, bump });
+    }
+    if nonce_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    RelayNonce::deserialize(&mut &nonce_account.try_borrow_data()?[..])
+        .map_err(|_| ProgramError::InvalidAccountData)
+}
+
+/// Pay the relayer from the user's fee vault, if it can afford it
+///
+/// A dry vault skips the payment rather than failing the user's call.
+fn reimburse<'info>(
+    program_id: &Pubkey,
+    diamond: &Pubkey,
+    user: &Pubkey,
+    amount: u64,
+    vault: &AccountInfo<'info>,
+    relayer: &AccountInfo<'info>,
+    system_program_account: &AccountInfo<'info>,
+) -> Result<u64, ProgramError> {
+    let (expected, bump) = fee_vault(program_id, diamond, user);
+    if vault.key != &expected {
+        msg!("Error: Invalid fee vault. Expected: {}", expected);
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if amount == 0 {
+        return Ok(0);
+    }
+    let floor = Rent::get()?.minimum_balance(0);
+    if vault.lamports() < amount.saturating_add(floor) {
+        msg!("Fee vault holds {} lamports; skipping reimbursement", vault.lamports());
+        return Ok(0);
+    }
+    invoke_signed(
+        &system_instruction::transfer(vault.key, relayer.key, amount),
+        &[vault.clone(), relayer.clone(), system_program_account.clone()],
+        &[&[FEE_VAULT_SEED, diamond.as_ref(), user.as_ref(), &[bump]]],
+    )?;
+    Ok(amount)
+}
+
+/// Dispatch a call the user signed off-chain, paid for by the relayer
+///
+/// Accounts: [diamond_state, relay_nonce, relayer, fee_vault, instructions_sysvar,
+///            system_program, module, ...remaining]
+/// Data: RelayPayload
+///
+/// The instruction right before this one must be the Ed25519 program
+/// verifying the user's signature over `RelayPayload::message`. The
+/// remaining accounts must hash to `payload.accounts_hash`, and one of them
+/// must be `relay_signer(diamond_state, user)`; it is forwarded as a signer.
+/// `fee_vault` is `fee_vault(diamond_state, user)`. Otherwise behaves like
+/// `dispatch`.
+pub fn dispatch_relayed(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let nonce_account = next_account_info(account_iter)?;
+    let relayer = next_account_info(account_iter)?;
+    let vault = next_account_info(account_iter)?;
+    let instructions_sysvar = next_account_info(account_iter)?;
+    let system_program_account = next_account_info(account_iter)?;
+    let module_account = next_account_info(account_iter)?;
+    
+    if !relayer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if system_program_account.key != &system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    let payload = RelayPayload::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    // The signature check ran in the preceding Ed25519 instruction
+    if !diamond_signing::preceding_verifies(instructions_sysvar, &payload.signing_payload())? {
+        msg!("Error: No Ed25519 verification of {}'s relay payload before this instruction", payload.user);
+        return Err(DiamondError::InvalidRelay.into());
+    }
+    
+    let mut nonce = load_or_create_nonce(
+        program_id,
+        state_account.key,
+        &payload.user,
+        nonce_account,
+        relayer,
+        system_program_account,
+    )?;
+    let signed_accounts = accounts_hash(
+        account_iter.as_slice().iter().map(|a| (a.key, a.is_signer, a.is_writable)),
+    );
+    check_payload(
+        &payload,
+        state_account.key,
+        module_account.key,
+        &signed_accounts,
+        Clock::get()?.slot,
+        &nonce,
+    )?;
+    nonce.next_nonce += 1;
+    nonce.serialize(&mut &mut nonce_account.try_borrow_mut_data()?[..])
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    let amount = reimbursement(&payload, DiamondState::load(program_id, state_account)?.relay_reimbursement);
+    
+    let mut route_accounts = Vec::with_capacity(accounts.len() - 5);
+    route_accounts.push(state_account.clone());
+    route_accounts.push(module_account.clone());
+    route_accounts.extend_from_slice(account_iter.as_slice());
+    route(
+        program_id,
+        &route_accounts,
+        payload.ix_data,
+        RouteOptions { relayed_user: Some(&payload.user), ..Default::default() },
+    )?;
+    
+    let reimbursed = reimburse(
+        program_id,
+        state_account.key,
+        &payload.user,
+        amount,
+        vault,
+        relayer,
+        system_program_account,
+    )?;
+    
+    msg!("Relayed call {} for {} by {} (reimbursed {})", payload.nonce, payload.user, relayer.key, reimbursed);
+    DiamondEvent::Relayed {
+        user: payload.user,
+        relayer: *relayer.key,
+        nonce: payload.nonce,
+        reimbursed,
+    }
+    .emit();
+    Ok(())
+}
+
+/// Set the lamports paid to relayers per relayed call (owner only; 0 disables)
+///
+/// Each payload caps what its user pays with `max_reimbursement`.
+///
+/// Accounts: [diamond_state, owner]
+/// Data: u64
+pub fn set_relay_reimbursement(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let owner = next_account_info(account_iter)?;
+    
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let amount = u64::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    if !state.is_owner(owner.key) {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    state.relay_reimbursement = amount;
+    state.save(state_account)?;
+    
+    msg!("Relay reimbursement set: {} lamports", amount);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn payload(diamond: Pubkey, facet: Pubkey) -> RelayPayload {
+        RelayPayload {
+            domain: "app.example.com".into(),
+            diamond,
+            facet,
+            user: Pubkey::new_unique(),
+            nonce: 3,
+            expiry_slot: 100,
+            ix_data: vec![1, 2, 3, 4],
+            accounts_hash: accounts_hash([]),
+            max_reimbursement: 5_000,
+        }
+    }
+    
+    #[test]
+    fn test_message_binds_facet_and_payload() {
+        let payload = payload(Pubkey::new_unique(), Pubkey::new_unique());
+        let message = payload.message();
+        assert!(message.starts_with(b"app.example.com wants you to approve"));
+        assert_ne!(RelayPayload { facet: Pubkey::new_unique(), ..payload.clone() }.message(), message);
+        assert_ne!(RelayPayload { ix_data: vec![1, 2, 3], ..payload.clone() }.message(), message);
+        assert_ne!(RelayPayload { accounts_hash: [1; 32], ..payload.clone() }.message(), message);
+        assert_ne!(RelayPayload { max_reimbursement: 0, ..payload.clone() }.message(), message);
+        assert_eq!(payload.signing_payload().signer, payload.user.to_bytes());
+    }
+    
+    #[test]
+    fn test_check_payload() {
+        let (diamond, facet) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let payload = payload(diamond, facet);
+        let nonce = RelayNonce { diamond, user: payload.user, next_nonce: 3, bump: 255 };
+        let signed = payload.accounts_hash;
+        
+        assert_eq!(check_payload(&payload, &diamond, &facet, &signed, 100, &nonce), Ok(()));
+        assert_eq!(check_payload(&payload, &diamond, &facet, &signed, 101, &nonce), Err(DiamondError::InvalidRelay));
+        assert_eq!(
+            check_payload(&payload, &diamond, &Pubkey::new_unique(), &signed, 0, &nonce),
+            Err(DiamondError::InvalidRelay)
+        );
+        let multiline = RelayPayload { domain: "app.example.com\nFake: line".into(), ..payload.clone() };
+        assert_eq!(check_payload(&multiline, &diamond, &facet, &signed, 0, &nonce), Err(DiamondError::InvalidRelay));
+        let used = RelayNonce { next_nonce: 4, ..nonce.clone() };
+        assert_eq!(check_payload(&payload, &diamond, &facet, &signed, 0, &used), Err(DiamondError::InvalidRelay));
+        
+        // Relayed with other accounts than the user signed for
+        let key = Pubkey::new_unique();
+        let swapped = accounts_hash([(&key, false, true)]);
+        assert_eq!(check_payload(&payload, &diamond, &facet, &swapped, 0, &nonce), Err(DiamondError::InvalidRelay));
+        assert_ne!(accounts_hash([(&key, false, false)]), swapped);
+    }
+    
+    #[test]
+    fn test_reimbursement_capped_by_user() {
+        let payload = payload(Pubkey::new_unique(), Pubkey::new_unique());
+        assert_eq!(reimbursement(&payload, 2_000), 2_000);
+        assert_eq!(reimbursement(&payload, 1_000_000), 5_000);
+        assert_eq!(reimbursement(&RelayPayload { max_reimbursement: 0, ..payload }, 2_000), 0);
+    }
+}
@@ -0,0 +1,182 @@
+/*!
+ * Diamond Return Module
+ * Facet results too large for return data
+ *
+ * Facets follow the chunked convention in `diamond_core::return_data`: the
+ * result goes to the facet's scratch PDA and return data carries an
+ * `OverflowPointer`. After every facet CPI the router checks such a pointer
+ * against the diamond's `ReturnOverflowPolicy`, and `return::read_chunk`
+ * lets clients and calling programs page through the scratch account.
+ */
+
+use borsh::BorshDeserialize;
+use diamond_core::return_data::{self, OverflowPointer, RETURN_SCRATCH_SEED};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::{get_return_data, set_return_data},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::diamond_state::{DiamondState, ReturnOverflowPolicy};
+use crate::error::DiamondError;
+use crate::events::DiamondEvent;
+
+/// Scratch PDA `facet` writes oversized results to for `diamond`
+pub fn scratch_address(facet: &Pubkey, diamond: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[RETURN_SCRATCH_SEED, diamond.as_ref()], facet)
+}
+
+/// Check the return data `facet` left behind against `policy`
+///
+/// Ordinary results (and return data set by anyone else) pass untouched.
+pub fn check_facet_return(
+    diamond: &Pubkey,
+    facet: &Pubkey,
+    remaining_accounts: &[AccountInfo],
+    policy: ReturnOverflowPolicy,
+) -> ProgramResult {
+    let pointer = match get_return_data() {
+        Some((program, data)) if &program == facet => OverflowPointer::decode(&data),
+        _ => None,
+    };
+    let Some(pointer) = pointer else { return Ok(()) };
+    check_overflow(diamond, facet, &pointer, remaining_accounts, policy)?;
+    
+    let scratch = Pubkey::new_from_array(pointer.scratch);
+    msg!("Return data: {} bytes in {} ({} chunks)", pointer.len, scratch, pointer.chunk_count());
+    DiamondEvent::ReturnOverflowed { facet: *facet, scratch, len: pointer.len }.emit();
+    Ok(())
+}
+
+fn check_overflow(
+    diamond: &Pubkey,
+    facet: &Pubkey,
+    pointer: &OverflowPointer,
+    remaining_accounts: &[AccountInfo],
+    policy: ReturnOverflowPolicy,
+) -> Result<(), DiamondError> {
+    if policy == ReturnOverflowPolicy::Reject {
+        msg!("Error: {} returned {} bytes via scratch; this diamond rejects overflow", facet, pointer.len);
+        return Err(DiamondError::ReturnDataOverflow);
+    }
+    let (expected, _) = scratch_address(facet, diamond);
+    let scratch = remaining_accounts
+        .iter()
+        .find(|account| account.key == &expected && account.owner == facet)
+        .ok_or_else(|| {
+            msg!("Error: Overflow scratch {} not among the facet's accounts", expected);
+            DiamondError::ReturnDataOverflow
+        })?;
+    let data = scratch.try_borrow_data().map_err(|_| DiamondError::ReturnDataOverflow)?;
+    let stored = return_data::scratch_payload(&data).map(<[u8]>::len);
+    if pointer.scratch != expected.to_bytes() || stored != Some(pointer.len as usize) {
+        msg!("Error: Overflow pointer does not match scratch {}", expected);
+        return Err(DiamondError::ReturnDataOverflow);
+    }
+    Ok(())
+}
+
+/// Return one chunk of a facet's scratch result
+///
+/// Accounts: [diamond_state, module, scratch]
+/// Data: chunk index (u32)
+/// Returns up to `MAX_RETURN_DATA` payload bytes via return data.
+pub fn read_chunk(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let module_account = next_account_info(account_iter)?;
+    let scratch_account = next_account_info(account_iter)?;
+    
+    let index = u32::try_from_slice(data).map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let state = DiamondState::load(program_id, state_account)?;
+    if !state.selectors.iter().any(|mapping| &mapping.module == module_account.key) {
+        msg!("Error: {} is not a facet of this diamond", module_account.key);
+        return Err(DiamondError::ModuleNotFound.into());
+    }
+    let (expected, _) = scratch_address(module_account.key, state_account.key);
+    if scratch_account.key != &expected || scratch_account.owner != module_account.key {
+        msg!("Error: Invalid scratch account. Expected: {}", expected);
+        return Err(ProgramError::InvalidSeeds);
+    }
+    
+    let scratch = scratch_account.try_borrow_data()?;
+    let payload = return_data::scratch_payload(&scratch).ok_or(ProgramError::InvalidAccountData)?;
+    let chunk = return_data::chunk(payload, index).ok_or_else(|| {
+        msg!("Error: Chunk {} past the end of a {}-byte result", index, payload.len());
+        ProgramError::InvalidArgument
+    })?;
+    set_return_data(chunk);
+    Ok(())
+}
+
+/// Choose how facet results returned through a scratch account are handled (owner only)
+///
+/// Accounts: [diamond_state, owner]
+/// Data: ReturnOverflowPolicy
+pub fn set_return_overflow(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let owner = next_account_info(account_iter)?;
+    
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let policy = ReturnOverflowPolicy::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    if !state.is_owner(owner.key) {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    state.return_overflow = policy;
+    state.save(state_account)?;
+    
+    msg!("Return overflow policy: {:?}", policy);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_overflow_checked_against_scratch() {
+        let (diamond, facet) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (scratch_key, _) = scratch_address(&facet, &diamond);
+        let mut lamports = 0;
+        let mut data = 2000u32.to_le_bytes().to_vec();
+        data.resize(4 + 2048, 0);
+        let accounts = [AccountInfo::new(&scratch_key, false, false, &mut lamports, &mut data, &facet, false, 0)];
+        let pointer = OverflowPointer { scratch: scratch_key.to_bytes(), len: 2000 };
+        
+        assert_eq!(check_overflow(&diamond, &facet, &pointer, &accounts, ReturnOverflowPolicy::Chunked), Ok(()));
+        assert_eq!(
+            check_overflow(&diamond, &facet, &pointer, &accounts, ReturnOverflowPolicy::Reject),
+            Err(DiamondError::ReturnDataOverflow)
+        );
+        // Length must match what the facet stored
+        let short = OverflowPointer { len: 1999, ..pointer };
+        assert_eq!(
+            check_overflow(&diamond, &facet, &short, &accounts, ReturnOverflowPolicy::Chunked),
+            Err(DiamondError::ReturnDataOverflow)
+        );
+        // Another diamond's scratch doesn't count
+        assert_eq!(
+            check_overflow(&Pubkey::new_unique(), &facet, &pointer, &accounts, ReturnOverflowPolicy::Chunked),
+            Err(DiamondError::ReturnDataOverflow)
+        );
+    }
+}
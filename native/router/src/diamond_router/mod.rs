@@ -4,31 +4,402 @@
  */
 
 use borsh::BorshDeserialize;
+use diamond_core::compress;
+use diamond_core::routing;
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
     entrypoint::ProgramResult,
+    hash::hashv,
     msg,
-    program::invoke,
+    program::invoke_signed,
     program_error::ProgramError,
-    pubkey::Pubkey,
-    instruction::Instruction,
+    pubkey::{Pubkey, MAX_SEED_LEN},
+    instruction::{get_stack_height, AccountMeta, Instruction},
+    clock::Clock,
+    syscalls::MAX_CPI_INSTRUCTION_DATA_LEN,
+    sysvar::Sysvar,
 };
 
-use crate::diamond_state::DiamondState;
+use crate::builtins::builtin_handler;
+use crate::diamond_authorizer::{self, AuthAction};
+use crate::diamond_breaker;
+use crate::diamond_context::{self, CallContext};
+use crate::diamond_config::{
+    self, key_bytes,
+    schema::{account_schema_key, order_by_role, AccountSchema, ACCOUNT_SCHEMA_NAMESPACE},
+};
+use crate::diamond_digest;
+use crate::diamond_index;
+use crate::diamond_guard;
+use crate::diamond_health;
+#[cfg(feature = "oracle-policy")]
+use crate::diamond_oracle;
+use crate::diamond_relay::{relay_signer, RELAY_SIGNER_SEED};
+use crate::diamond_return::check_facet_return;
+use crate::diamond_state::{
+    fixed_str, namespace_bytes, standard_account_ids, ActiveCall, BreakerMetric, DispatchState, Reentrancy, SelectorMapping,
+    CALL_TRACE, RENT_VAULT,
+};
+use crate::diamond_vault::{rent_vault, RENT_VAULT_SEED};
 use crate::error::DiamondError;
+use crate::events::DiamondEvent;
+use crate::selector::Selector;
+
+/// Build the CPI account metas for a facet call
+///
+/// Accounts are forwarded positionally, except that any standard accounts the
+/// selector declared are pulled out of the list and appended in canonical
+/// order (system program, rent, clock, token program). Clients may pass those
+/// accounts anywhere after the module account; they must still be present in
+/// the transaction since the runtime cannot supply account infos itself.
+///
+/// Duplicates are collapsed into their first position with signer/writable
+/// flags merged, and the diamond state may only be forwarded read-only so a
+/// facet can never alias the router's own state.
+pub fn build_account_metas(
+    diamond_state: &Pubkey,
+    remaining_accounts: &[AccountInfo],
+    standard_accounts: u8,
+) -> Result<Vec<AccountMeta>, DiamondError> {
+    let standard_ids = standard_account_ids(standard_accounts);
+    
+    let mut metas: Vec<AccountMeta> = Vec::with_capacity(remaining_accounts.len());
+    for account in remaining_accounts {
+        if standard_ids.contains(account.key) {
+            continue;
+        }
+        if account.key == diamond_state && account.is_writable {
+            msg!("Error: Diamond state cannot be forwarded as writable");
+            return Err(DiamondError::WritableDiamondState);
+        }
+        match metas.iter_mut().find(|meta| meta.pubkey == *account.key) {
+            Some(meta) => {
+                meta.is_signer |= account.is_signer;
+                meta.is_writable |= account.is_writable;
+            }
+            None => metas.push(AccountMeta {
+                pubkey: *account.key,
+                is_signer: account.is_signer,
+                is_writable: account.is_writable,
+            }),
+        }
+    }
+    
+    for id in standard_ids {
+        if !remaining_accounts.iter().any(|account| account.key == &id) {
+            msg!("Error: Missing standard account {}", id);
+            return Err(DiamondError::MissingStandardAccount);
+        }
+        metas.push(AccountMeta::new_readonly(id, false));
+    }
+    
+    Ok(metas)
+}
 
 /// Dispatch instruction to registered facet
 /// 
 /// This is the CORE of the diamond pattern:
-/// 1. Extract selector from instruction data
+/// 1. Extract selector from instruction data (built-ins are handled in place)
 /// 2. Lookup facet program in registry
 /// 3. Validate provided program matches registry
 /// 4. Forward instruction via CPI
+///
+/// Facet calls are bounded by the diamond's `DispatchLimits` (forwarded
+/// accounts and payload size); built-ins are not.
+///
+/// A facet re-enters by dispatching with the diamond state read-only, which
+/// succeeds only while the outer call's `Reentrancy` policy allows it.
 pub fn dispatch(
-    _program_id: &Pubkey,
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let ix_data = Vec::<u8>::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    route(program_id, accounts, ix_data, RouteOptions::default())
+}
+
+/// Dispatch that skips a call the router would refuse instead of failing
+///
+/// Same accounts and data as `dispatch`. The router first runs every check
+/// `dispatch` makes, writing nothing and calling nothing. If one fails, it
+/// emits `DispatchSkipped` with the error, counts it for the selector's
+/// `Refusals` breaker unless it was a pause, and succeeds, so a batch can
+/// step over a paused or unroutable call. Otherwise it dispatches as usual, and a
+/// failing facet aborts the whole transaction: the runtime never hands a
+/// failed CPI back to its caller. The checks run twice on that path.
+pub fn try_dispatch(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let ix_data = Vec::<u8>::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    if let Err(err) = route(program_id, accounts, ix_data.clone(), RouteOptions { check_only: true, ..Default::default() }) {
+        msg!("Dispatch skipped: {}", err);
+        let paused = err == DiamondError::DiamondPaused.into();
+        DiamondEvent::DispatchSkipped { error_code: u64::from(err) }.emit();
+        if !paused {
+            diamond_breaker::record_refusal(program_id, accounts, &ix_data, Clock::get()?.slot)?;
+        }
+        return Ok(());
+    }
+    route(program_id, accounts, ix_data, RouteOptions::default())
+}
+
+/// Dispatch carrying a 16-byte trace id
+///
+/// Same accounts as `dispatch`; data is `(trace_id: [u8; 16], ix_data: Vec<u8>)`.
+/// An all-zero trace id is replaced by one derived from slot, diamond and
+/// payload. The id is recorded in the `Dispatched` event and passed to
+/// facets whose selector sets `CALL_TRACE`.
+pub fn dispatch_traced(
+    program_id: &Pubkey,
     accounts: &[AccountInfo],
     data: &[u8],
 ) -> ProgramResult {
+    let (mut trace_id, ix_data) = <([u8; 16], Vec<u8>)>::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    if trace_id == [0u8; 16] {
+        let state = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+        trace_id = derive_trace_id(Clock::get()?.slot, state.key, &ix_data);
+    }
+    msg!("Trace: {:02x?}", trace_id);
+    route(program_id, accounts, ix_data, RouteOptions { trace_id: Some(trace_id), ..Default::default() })
+}
+
+pub use diamond_constants::seeds::FACET_SIGNER_SEED;
+
+/// Router PDA that `dispatch_signed` signs for when calling `facet`
+///
+/// Seeds bind the diamond and the facet, so a facet can only ever receive
+/// signatures for its own PDAs.
+pub fn facet_signer(program_id: &Pubkey, diamond_state: &Pubkey, facet: &Pubkey, seed: &[u8]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[FACET_SIGNER_SEED, diamond_state.as_ref(), facet.as_ref(), seed], program_id)
+}
+
+/// Dispatch with a router PDA signing the facet call
+///
+/// Same accounts as `dispatch`, one of which must be
+/// `facet_signer(diamond_state, facet, seed)`; data is
+/// `(seed: Vec<u8>, ix_data: Vec<u8>)` with a seed of at most 32 bytes.
+/// The PDA is forwarded as a signer, so the facet can move lamports or
+/// tokens it holds. Anyone may call this: the facet must authorize every
+/// use of the signature itself. Selectors flagged `RENT_VAULT` also get the
+/// diamond's rent vault (`diamond_vault::rent_vault`) as a signer, to pay
+/// rent for PDAs the facet creates; it must be passed writable.
+pub fn dispatch_signed(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let (seed, ix_data) = <(Vec<u8>, Vec<u8>)>::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    if seed.len() > MAX_SEED_LEN {
+        return Err(ProgramError::MaxSeedLengthExceeded);
+    }
+    route(program_id, accounts, ix_data, RouteOptions { signer_seed: Some(&seed), ..Default::default() })
+}
+
+/// Dispatch with the caller proving the selector's route
+///
+/// Accounts: [diamond_state, routing_digest, module, ...remaining]; data is
+/// `(mapping: SelectorMapping, index: u16, proof: Vec<[u8; 32]>, ix_data: Vec<u8>)`
+/// where `index` and `proof` locate the mapping in the digest's merkle tree
+/// (see `diamond_digest::mapping_proof`). The router checks the proof against
+/// the routing digest instead of searching the selector table, so lookup
+/// cost grows with log2 of the table size. The digest's address is checked
+/// against its stored bump, the selector table is never decoded, and the
+/// remaining checks read only the fields after the routing tables (see
+/// `DispatchState`). Otherwise behaves like
+/// `dispatch`, with the mapping's module, standard accounts and re-entrancy
+/// policy.
+pub fn dispatch_proven(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let (mapping, index, proof, ix_data) =
+        <(SelectorMapping, u16, Vec<[u8; 32]>, Vec<u8>)>::try_from_slice(data)
+            .map_err(|_| ProgramError::InvalidInstructionData)?;
+    let (state_account, rest) = accounts.split_first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let (digest_account, rest) = rest.split_first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    
+    let routing_digest = diamond_digest::load(program_id, state_account.key, digest_account)?;
+    if !diamond_digest::verify_mapping(&routing_digest, &mapping, index, &proof) {
+        msg!("Error: Proof for selector {:?} does not match digest version {}", mapping.selector, routing_digest.version);
+        return Err(DiamondError::InvalidRoutingProof.into());
+    }
+    
+    let mut route_accounts = Vec::with_capacity(accounts.len() - 1);
+    route_accounts.push(state_account.clone());
+    route_accounts.extend_from_slice(rest);
+    route(program_id, &route_accounts, ix_data, RouteOptions { resolved: Some(&mapping), ..Default::default() })
+}
+
+/// Dispatch resolved through the selector index
+///
+/// Accounts: [diamond_state, selector_index, facet, ...]; data is the facet
+/// payload (`Vec<u8>`), selector first. The router looks the selector up in
+/// the diamond's `SelectorIndex` (see `diamond_index`) by binary search and
+/// never reads the selector table; the state contributes only its header and
+/// the fields after the routing tables. An index behind the state's
+/// `config_version` fails with `StaleConfig`. Aliases aren't indexed; call
+/// them through `dispatch`. Otherwise behaves like `dispatch`.
+pub fn dispatch_indexed(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let ix_data = Vec::<u8>::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    let (state_account, rest) = accounts.split_first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let (index_account, rest) = rest.split_first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    
+    let index = diamond_index::load(program_id, state_account.key, index_account)?;
+    let selector = Selector::read(&ix_data, index.selector_width).ok_or(ProgramError::InvalidInstructionData)?;
+    let mapping = index.route(&selector).map(|route| route.mapping()).ok_or_else(|| {
+        msg!("Error: Selector {:?} is not in the selector index", selector);
+        DiamondError::ModuleNotFound
+    })?;
+    
+    let mut route_accounts = Vec::with_capacity(accounts.len() - 1);
+    route_accounts.push(state_account.clone());
+    route_accounts.extend_from_slice(rest);
+    route(
+        program_id,
+        &route_accounts,
+        ix_data,
+        RouteOptions { resolved: Some(&mapping), index_version: Some(index.config_version), ..Default::default() },
+    )
+}
+
+/// Dispatch stating the argument schema the payload was encoded against
+///
+/// Same accounts as `dispatch`; data is `(schema_hash: [u8; 8], ix_data: Vec<u8>)`
+/// with the hash from `diamond_state::schema_hash`. Fails with
+/// `InvalidPayload` if the selector declared a different schema.
+pub fn dispatch_checked(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let (schema_hash, ix_data) = <([u8; 8], Vec<u8>)>::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    route(program_id, accounts, ix_data, RouteOptions { schema_hash: Some(schema_hash), ..Default::default() })
+}
+
+/// Dispatch with accounts labeled by role instead of position
+///
+/// Accounts: [diamond_state, facet, account_schema, ...labeled accounts]
+/// Data: `(roles: Vec<u8>, ix_data: Vec<u8>)`, where `roles[i]` is the schema
+/// slot the i-th labeled account fills, or `ROLE_EXTRA` for accounts
+/// forwarded after the schema's (standard accounts, feeds, hook extras).
+/// `account_schema` is the selector's entry in the `accounts` config
+/// namespace. The facet receives the accounts in slot order.
+pub fn dispatch_mapped(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let (roles, ix_data) = <(Vec<u8>, Vec<u8>)>::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    route(program_id, accounts, ix_data, RouteOptions { roles: Some(&roles), ..Default::default() })
+}
+
+/// The labeled accounts after a mapped call's schema entry, in schema order
+fn accounts_by_role<'info>(
+    program_id: &Pubkey,
+    diamond: &Pubkey,
+    width: u8,
+    mapping: &SelectorMapping,
+    roles: &[u8],
+    accounts: &[AccountInfo<'info>],
+) -> Result<Vec<AccountInfo<'info>>, ProgramError> {
+    let (schema_account, labeled) = accounts.split_first().ok_or(DiamondError::AccountRoleMismatch)?;
+    let key = key_bytes(&account_schema_key(mapping.selector, width))?;
+    let entry = diamond_config::load(program_id, diamond, schema_account)
+        .ok()
+        .filter(|e| Some(e.namespace) == namespace_bytes(ACCOUNT_SCHEMA_NAMESPACE) && e.key == key)
+        .ok_or_else(|| {
+            msg!("Error: {} is not the account schema of {:?}", schema_account.key, mapping.selector);
+            DiamondError::AccountRoleMismatch
+        })?;
+    let schema = AccountSchema::try_from_slice(&entry.value).map_err(|_| DiamondError::AccountRoleMismatch)?;
+    order_by_role(schema.accounts.len(), roles, labeled).map_err(|e| {
+        msg!("Error: Roles {:?} don't fill the {} slots of {:?}", roles, schema.accounts.len(), mapping.selector);
+        e.into()
+    })
+}
+
+/// Dispatch of a compressed payload
+///
+/// Same accounts as `dispatch`; data is a `diamond_core::compress` frame
+/// (`Vec<u8>`) holding the facet payload. The router inflates it before
+/// routing, so the facet, payload specs and dispatch limits all see the
+/// original bytes. Frames inflating past the CPI data limit are refused.
+pub fn dispatch_compressed(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let frame = Vec::<u8>::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    let ix_data = compress::decompress(&frame, MAX_CPI_INSTRUCTION_DATA_LEN as usize).ok_or_else(|| {
+        msg!("Error: Bad compressed payload ({} bytes)", frame.len());
+        DiamondError::InvalidCompression
+    })?;
+    msg!("Inflated {} -> {} bytes", frame.len(), ix_data.len());
+    route(program_id, accounts, ix_data, RouteOptions::default())
+}
+
+/// Trace id for clients that don't supply one
+pub fn derive_trace_id(slot: u64, diamond_state: &Pubkey, ix_data: &[u8]) -> [u8; 16] {
+    let digest = hashv(&[&slot.to_le_bytes(), diamond_state.as_ref(), ix_data]);
+    let mut trace_id = [0u8; 16];
+    trace_id.copy_from_slice(&digest.to_bytes()[..16]);
+    trace_id
+}
+
+/// Facet payload with the trace id inserted after the `width`-byte selector
+pub fn with_trace(ix_data: &[u8], width: u8, trace_id: &[u8; 16]) -> Vec<u8> {
+    let (selector, args) = ix_data.split_at(width as usize);
+    let mut data = Vec::with_capacity(ix_data.len() + 16);
+    data.extend_from_slice(selector);
+    data.extend_from_slice(trace_id);
+    data.extend_from_slice(args);
+    data
+}
+
+/// Per-call variations on `route`
+#[derive(Default)]
+pub(crate) struct RouteOptions<'a> {
+    /// Run every check, then stop before the first write and the facet call
+    pub check_only: bool,
+    pub trace_id: Option<[u8; 16]>,
+    /// Seed of the facet signer PDA to sign for
+    pub signer_seed: Option<&'a [u8]>,
+    /// Mapping already proven against the routing digest, read from the
+    /// selector index or pinned to the fast path
+    pub resolved: Option<&'a SelectorMapping>,
+    /// `config_version` of the selector index `resolved` was read from
+    pub index_version: Option<u64>,
+    /// Argument schema the caller encoded against
+    pub schema_hash: Option<[u8; 8]>,
+    /// User whose relay signer PDA to sign for (see `diamond_relay`)
+    pub relayed_user: Option<&'a Pubkey>,
+    /// Schema roles of the accounts after the account schema entry (see
+    /// `dispatch_mapped`)
+    pub roles: Option<&'a [u8]>,
+}
+
+pub(crate) fn route(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    ix_data: Vec<u8>,
+    options: RouteOptions,
+) -> ProgramResult {
+    let RouteOptions { check_only, trace_id, signer_seed, resolved, index_version, schema_hash, relayed_user, roles } = options;
     msg!("Diamond Router: Dispatching to facet");
     
     // Parse accounts
@@ -37,47 +408,82 @@ pub fn dispatch(
     let module_account = next_account_info(account_iter)?;
     let remaining_accounts = account_iter.as_slice();
     
-    // Validate router config is writable
-    if !router_config_account.is_writable {
-        msg!("Error: Router config account must be writable");
-        return Err(ProgramError::InvalidAccountData);
+    // Built-ins are served by the router itself (and stay reachable while paused).
+    // Their 4-byte selectors are the same at every selector width.
+    let builtin = ix_data
+        .get(..4)
+        .and_then(|prefix| builtin_handler([prefix[0], prefix[1], prefix[2], prefix[3]]));
+    if let Some(handler) = builtin {
+        if !router_config_account.is_writable {
+            msg!("Error: Router config account must be writable");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if module_account.key != program_id {
+            msg!("Error: Built-in selector {:02x?} must target the router", &ix_data[..4]);
+            return Err(DiamondError::UnauthorizedAccess.into());
+        }
+        if check_only {
+            return Ok(());
+        }
+        msg!("Built-in selector: {:02x?}", &ix_data[..4]);
+        let mut builtin_accounts = Vec::with_capacity(remaining_accounts.len() + 1);
+        builtin_accounts.push(router_config_account.clone());
+        builtin_accounts.extend_from_slice(remaining_accounts);
+        return handler(program_id, &builtin_accounts, &ix_data[4..]);
     }
     
-    // Load diamond state
-    let router_config_data = router_config_account.try_borrow_data()?;
-    let router_config = DiamondState::try_from_slice(&router_config_data)
-        .map_err(|_| ProgramError::InvalidAccountData)?;
-    
-    // Check if paused
-    if router_config.is_paused {
-        msg!("Error: Diamond is paused");
-        return Err(DiamondError::DiamondPaused.into());
+    // The pause flag, selector width and selector index sit at fixed offsets
+    // (see `diamond_core::routing`): refuse paused diamonds and resolve the
+    // selector before decoding anything else
+    if router_config_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
     }
+    let (width, selector, mapping) = {
+        let data = router_config_account.try_borrow_data()?;
+        let header = routing::state_header(&data).ok_or(ProgramError::InvalidAccountData)?;
+        if header.dispatch_paused {
+            let dispatch = routing::dispatch_header(&data).ok_or(ProgramError::InvalidAccountData)?;
+            if dispatch.is_dispatch_paused_at(Clock::get()?.slot) {
+                msg!("Error: Diamond is paused");
+                return Err(DiamondError::DiamondPaused.into());
+            }
+        }
+        
+        // Extract selector (the diamond's configured width)
+        let width = header.selector_width;
+        let selector = Selector::read(&ix_data, width).ok_or_else(|| {
+            msg!("Error: Instruction data too short (need {}-byte selector)", width);
+            ProgramError::InvalidInstructionData
+        })?;
+        msg!("Selector: {:02x?}", selector.as_bytes(width));
+        
+        // Lookup facet by selector (THE KEY DISPATCH LOGIC), unless the caller
+        // already resolved the mapping through the routing digest or the
+        // selector index. An alias resolves to its canonical mapping in one
+        // extra hop.
+        let mapping = match resolved {
+            Some(mapping) if mapping.selector == selector => mapping.clone(),
+            Some(mapping) => {
+                msg!("Error: Proven selector {:?} does not match payload selector {:?}", mapping.selector, selector);
+                return Err(DiamondError::InvalidRoutingProof.into());
+            }
+            None => routing::resolve_mapping(&data, &selector)
+                .and_then(|raw| SelectorMapping::try_from_slice(raw).ok())
+                .ok_or_else(|| {
+                    msg!("Error: Module not found for selector {:?}", selector);
+                    DiamondError::ModuleNotFound
+                })?,
+        };
+        (width, selector, mapping)
+    };
+    let expected_program = mapping.module;
     
-    // Parse instruction data (should be Vec<u8> containing facet instruction)
-    let ix_data = Vec::<u8>::try_from_slice(data)
-        .map_err(|_| ProgramError::InvalidInstructionData)?;
-    
-    if ix_data.len() < 4 {
-        msg!("Error: Instruction data too short (need selector)");
-        return Err(ProgramError::InvalidInstructionData);
+    // Guard against CPI recursion into the router itself
+    if &expected_program == program_id {
+        msg!("Error: Selector {:?} routes back to the router", mapping.selector);
+        return Err(DiamondError::SelfDispatchForbidden.into());
     }
     
-    // Extract selector (first 4 bytes)
-    let selector: [u8; 4] = ix_data[..4]
-        .try_into()
-        .map_err(|_| ProgramError::InvalidInstructionData)?;
-    
-    msg!("Selector: {:?}", selector);
-    
-    // Lookup facet by selector (THE KEY DISPATCH LOGIC)
-    let expected_program = router_config
-        .get_module_by_selector(selector)
-        .ok_or_else(|| {
-            msg!("Error: Module not found for selector {:?}", selector);
-            DiamondError::ModuleNotFound
-        })?;
-    
     msg!("Target facet: {}", expected_program);
     
     // Validate provided module matches registry
@@ -90,24 +496,210 @@ pub fn dispatch(
         return Err(DiamondError::UnauthorizedAccess.into());
     }
     
+    // The remaining checks only read the fields after the routing tables
+    let mut router_config = DispatchState::load(program_id, router_config_account)?;
+    // A selector index behind the state routes with a table that changed since
+    router_config.check_config_version(index_version)?;
+    
+    // Facets never receive the state writable, so a read-only state marks a
+    // facet re-entering the router; that is only valid during a locked call
+    let reentered = match (router_config_account.is_writable, router_config.active_call) {
+        (true, _) => None,
+        (false, Some(active)) => Some(active),
+        (false, None) => {
+            msg!("Error: Router config account must be writable");
+            return Err(ProgramError::InvalidAccountData);
+        }
+    };
+    
+    // Check for a scheduled maintenance window
+    if !router_config.pause_windows.is_empty() {
+        if let Some(window) = router_config.pause_window_at(Clock::get()?.slot) {
+            msg!("Error: Diamond is in a maintenance window until slot {}", window.end_slot);
+            return Err(DiamondError::DiamondPaused.into());
+        }
+    }
+    router_config.dispatch_limits.check(remaining_accounts.len(), ix_data.len())?;
+    
+    // Facets only know the canonical selector
+    let mut ix_data = ix_data;
+    if mapping.selector != selector {
+        msg!("Alias {:?} -> {:?}", selector, mapping.selector);
+        ix_data[..width as usize].copy_from_slice(mapping.selector.as_bytes(width));
+    }
+    let selector = mapping.selector;
+    if let Some(tag) = router_config.paused_tag_of(&selector) {
+        msg!("Error: {:?} is paused by tag {:?}", selector, fixed_str(&tag));
+        return Err(DiamondError::DiamondPaused.into());
+    }
+    if let Some(breaker) = router_config.tripped_breaker(&selector) {
+        msg!("Error: {:?} is paused by its {:?} circuit breaker", selector, breaker.metric);
+        return Err(DiamondError::DiamondPaused.into());
+    }
+    router_config.check_payload(selector, ix_data.len() - width as usize, schema_hash.as_ref())?;
+    
+    if let Some(active) = reentered {
+        if !active.permits(&mapping) {
+            msg!("Error: Re-entry into {} not permitted during a call to {}", expected_program, active.facet);
+            return Err(DiamondError::ReentrancyForbidden.into());
+        }
+        msg!("Re-entered from {}", active.facet);
+    }
+    
+    // Mapped calls label their accounts by role; forward them in schema order
+    let mapped;
+    let remaining_accounts = match roles {
+        Some(roles) => {
+            mapped = accounts_by_role(program_id, router_config_account.key, width, &mapping, roles, remaining_accounts)?;
+            &mapped[..]
+        }
+        None => remaining_accounts,
+    };
+    
+    if router_config.tx_guard.guards(&selector) {
+        diamond_guard::check_transaction(&router_config.tx_guard, &selector, remaining_accounts)?;
+    }
+    if let Some(authorizer) = router_config.authorizer.as_ref().filter(|a| a.protects(&selector)) {
+        let actor = diamond_authorizer::dispatch_actor(relayed_user, remaining_accounts);
+        let action = AuthAction::Dispatch;
+        diamond_authorizer::authorize(router_config_account, authorizer, remaining_accounts, &actor, action, selector)?;
+    }
+    #[cfg(feature = "oracle-policy")]
+    if let Some(guard) = router_config.oracle_guard(&selector) {
+        diamond_oracle::check_feed(guard, remaining_accounts, Clock::get()?.unix_timestamp)?;
+    }
+    #[cfg(not(feature = "oracle-policy"))]
+    if router_config.oracle_guard(&selector).is_some() {
+        msg!("Error: {:?} is oracle-guarded, but this build has no oracle policy", selector);
+        return Err(DiamondError::FeatureDisabled.into());
+    }
+    if let Some(registry) = &router_config.health_registry {
+        diamond_health::check_status(registry, &expected_program, remaining_accounts)?;
+    }
+    
+    // Fail before the CPI rather than at the runtime's stack limit
+    let stack_height = get_stack_height();
+    router_config.dispatch_limits.check_depth(stack_height, reentered.as_ref())?;
+    
     // Forward instruction to facet via CPI
     msg!("Forwarding to facet via CPI...");
     
+    let mut metas = build_account_metas(
+        router_config_account.key,
+        remaining_accounts,
+        mapping.standard_accounts,
+    )?;
+    // Router PDAs forwarded as signers: a facet signer or a relayed user's,
+    // plus the rent vault on signed calls to selectors that draw on it
+    let diamond = router_config_account.key;
+    let mut signers = Vec::with_capacity(2);
+    match (signer_seed, relayed_user) {
+        (Some(seed), _) => {
+            let (pda, bump) = facet_signer(program_id, diamond, &expected_program, seed);
+            signers.push((pda, vec![FACET_SIGNER_SEED, diamond.as_ref(), expected_program.as_ref(), seed], [bump]));
+        }
+        (None, Some(user)) => {
+            let (pda, bump) = relay_signer(program_id, diamond, user);
+            signers.push((pda, vec![RELAY_SIGNER_SEED, diamond.as_ref(), user.as_ref()], [bump]));
+        }
+        (None, None) => {}
+    }
+    if signer_seed.is_some() && mapping.standard_accounts & RENT_VAULT != 0 {
+        let (pda, bump) = rent_vault(program_id, diamond);
+        signers.push((pda, vec![RENT_VAULT_SEED, diamond.as_ref()], [bump]));
+    }
+    for (pda, _, _) in &signers {
+        let meta = metas.iter_mut().find(|meta| &meta.pubkey == pda).ok_or_else(|| {
+            msg!("Error: Router signer {} not among the accounts", pda);
+            ProgramError::NotEnoughAccountKeys
+        })?;
+        meta.is_signer = true;
+        msg!("Signing for {}", pda);
+    }
+    
+    if check_only {
+        return Ok(());
+    }
+    
+    // The previous call context comes back after the CPI; the facet can't
+    // write it in between, since the router owns it (see `diamond_context`)
+    let context = diamond_context::find(program_id, diamond, remaining_accounts);
+    if let Some((context_account, previous)) = context {
+        let first_writable_signer =
+            remaining_accounts.iter().find(|a| a.is_signer && a.is_writable).map(|a| *a.key).unwrap_or_default();
+        let call = CallContext {
+            facet: expected_program.to_bytes(),
+            selector,
+            first_writable_signer: first_writable_signer.to_bytes(),
+            signer: diamond_authorizer::dispatch_actor(relayed_user, remaining_accounts).to_bytes(),
+            slot: Clock::get()?.slot,
+            stack_height: stack_height as u8,
+            ..previous
+        };
+        diamond_context::write(context_account, &call)?;
+    }
+    
     let ix = Instruction {
         program_id: *module_account.key,
-        accounts: remaining_accounts
-            .iter()
-            .map(|account| solana_program::instruction::AccountMeta {
-                pubkey: *account.key,
-                is_signer: account.is_signer,
-                is_writable: account.is_writable,
-            })
-            .collect(),
-        data: ix_data,
+        accounts: metas,
+        data: match trace_id {
+            Some(trace_id) if mapping.standard_accounts & CALL_TRACE != 0 => {
+                with_trace(&ix_data, width, &trace_id)
+            }
+            _ => ix_data,
+        },
     };
     
-    invoke(&ix, remaining_accounts)?;
+    // Outermost calls into re-entrant selectors hold the lock for the CPI;
+    // a failed transaction discards it along with everything else
+    let policy = mapping.reentrancy();
+    let lock = reentered.is_none() && policy != Reentrancy::Forbidden;
+    if lock {
+        router_config.active_call = Some(ActiveCall {
+            facet: expected_program,
+            reentrancy: policy,
+            stack_height: stack_height as u8,
+        });
+        router_config.save(router_config_account)?;
+    }
+    let signer_seeds: Vec<Vec<&[u8]>> = signers
+        .iter()
+        .map(|(_, seeds, bump)| {
+            let mut seeds = seeds.clone();
+            seeds.push(bump);
+            seeds
+        })
+        .collect();
+    let signer_seeds: Vec<&[&[u8]]> = signer_seeds.iter().map(Vec::as_slice).collect();
+    let result = invoke_signed(&ix, remaining_accounts, &signer_seeds);
+    if let Some((context_account, previous)) = context {
+        diamond_context::write(context_account, &previous)?;
+    }
+    if lock {
+        router_config.active_call = None;
+        router_config.save(router_config_account)?;
+    }
+    // A failed call aborts the transaction, count and all. A re-entered call
+    // holds the state read-only; its outermost call is the one counted.
+    let metric = result.is_ok().then_some(BreakerMetric::Calls);
+    if let Some(metric) = metric.filter(|_| reentered.is_none() && !router_config.circuit_breakers.is_empty()) {
+        diamond_breaker::record(&mut router_config, router_config_account, selector, metric, Clock::get()?.slot)?;
+    }
+    
+    if let Err(err) = result {
+        DiamondEvent::DispatchFailed {
+            trace_id,
+            selector,
+            facet: expected_program,
+            error_code: u64::from(err.clone()),
+        }
+        .emit();
+        return Err(err);
+    }
     
+    check_facet_return(diamond, &expected_program, remaining_accounts, router_config.return_overflow)?;
+    
+    DiamondEvent::Dispatched { trace_id, selector, facet: expected_program }.emit();
     msg!("Dispatch successful");
     Ok(())
 }
@@ -115,11 +707,135 @@ pub fn dispatch(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::diamond_state::{DiamondState, STD_CLOCK, STD_SYSTEM_PROGRAM};
+    use solana_program::{system_program, sysvar};
+
+    #[test]
+    fn test_trace_inserted_after_selector() {
+        let trace_id = derive_trace_id(42, &Pubkey::new_unique(), &[1, 2, 3, 4, 9]);
+        assert_ne!(trace_id, [0u8; 16]);
+        
+        let data = with_trace(&[1, 2, 3, 4, 9, 9], 4, &trace_id);
+        assert_eq!(&data[..4], &[1, 2, 3, 4]);
+        assert_eq!(&data[4..20], &trace_id);
+        assert_eq!(&data[20..], &[9, 9]);
+        
+        let data = with_trace(&[1, 2, 9], 2, &trace_id);
+        assert_eq!(&data[..2], &[1, 2]);
+        assert_eq!(&data[18..], &[9]);
+    }
+    
+    #[test]
+    fn test_dispatch_failed_event_error_code() {
+        let event = DiamondEvent::DispatchFailed {
+            trace_id: None,
+            selector: [1, 2, 3, 4].into(),
+            facet: Pubkey::new_unique(),
+            error_code: u64::from(ProgramError::from(DiamondError::ModuleNotFound)),
+        };
+        let decoded = DiamondEvent::try_from_slice(&borsh::to_vec(&event).unwrap()).unwrap();
+        match decoded {
+            DiamondEvent::DispatchFailed { error_code, .. } => assert_eq!(error_code, 6000),
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+    
+    #[test]
+    fn test_standard_accounts_appended_in_order() {
+        let user = Pubkey::new_unique();
+        let clock = sysvar::clock::id();
+        let system = system_program::id();
+        let owner = Pubkey::default();
+        let (mut l0, mut l1, mut l2) = (0u64, 0u64, 0u64);
+        let (mut d0, mut d1, mut d2) = (vec![], vec![], vec![]);
+        
+        // Client passes the standard accounts out of order and interleaved
+        let accounts = vec![
+            AccountInfo::new(&clock, false, false, &mut l0, &mut d0, &owner, false, 0),
+            AccountInfo::new(&user, true, true, &mut l1, &mut d1, &owner, false, 0),
+            AccountInfo::new(&system, false, false, &mut l2, &mut d2, &owner, true, 0),
+        ];
+        
+        let metas = build_account_metas(&owner, &accounts, STD_SYSTEM_PROGRAM | STD_CLOCK).unwrap();
+        let keys: Vec<Pubkey> = metas.iter().map(|m| m.pubkey).collect();
+        assert_eq!(keys, vec![user, system, clock]);
+        assert!(metas[0].is_signer && metas[0].is_writable);
+        
+        // Without flags everything is forwarded positionally
+        let metas = build_account_metas(&owner, &accounts, 0).unwrap();
+        assert_eq!(metas.len(), 3);
+        assert_eq!(metas[0].pubkey, clock);
+    }
+
+    #[test]
+    fn test_missing_standard_account() {
+        assert_eq!(
+            build_account_metas(&Pubkey::default(), &[], STD_CLOCK),
+            Err(DiamondError::MissingStandardAccount)
+        );
+    }
+
+    #[test]
+    fn test_duplicate_accounts_merged() {
+        let state = Pubkey::new_unique();
+        let user = Pubkey::new_unique();
+        let owner = Pubkey::default();
+        let (mut l0, mut l1, mut l2) = (0u64, 0u64, 0u64);
+        let (mut d0, mut d1, mut d2) = (vec![], vec![], vec![]);
+        
+        let accounts = vec![
+            AccountInfo::new(&user, false, false, &mut l0, &mut d0, &owner, false, 0),
+            AccountInfo::new(&state, false, false, &mut l1, &mut d1, &owner, false, 0),
+            AccountInfo::new(&user, true, true, &mut l2, &mut d2, &owner, false, 0),
+        ];
+        
+        let metas = build_account_metas(&state, &accounts, 0).unwrap();
+        assert_eq!(metas.len(), 2);
+        assert_eq!(metas[0].pubkey, user);
+        assert!(metas[0].is_signer && metas[0].is_writable);
+        assert!(!metas[1].is_writable);
+    }
+
+    #[test]
+    fn test_writable_diamond_state_rejected() {
+        let state = Pubkey::new_unique();
+        let owner = Pubkey::default();
+        let mut lamports = 0u64;
+        let mut data = vec![];
+        let accounts = vec![
+            AccountInfo::new(&state, false, true, &mut lamports, &mut data, &owner, false, 0),
+        ];
+        
+        assert_eq!(
+            build_account_metas(&state, &accounts, 0),
+            Err(DiamondError::WritableDiamondState)
+        );
+    }
+
+    #[test]
+    fn test_self_dispatch_forbidden() {
+        let program_id = Pubkey::new_unique();
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        state.selectors.push(SelectorMapping::new([1, 0, 0, 0], program_id, "loop", false));
+        let state_key = Pubkey::new_unique();
+        let (mut l0, mut l1) = (0u64, 0u64);
+        let (mut d0, mut d1) = (borsh::to_vec(&state).unwrap(), vec![]);
+        let accounts = vec![
+            AccountInfo::new(&state_key, false, false, &mut l0, &mut d0, &program_id, false, 0),
+            AccountInfo::new(&program_id, false, false, &mut l1, &mut d1, &program_id, true, 0),
+        ];
+        
+        assert_eq!(
+            route(&program_id, &accounts, vec![1, 0, 0, 0], RouteOptions::default()),
+            Err(DiamondError::SelfDispatchForbidden.into())
+        );
+    }
     
     #[test]
     fn test_selector_extraction() {
-        let ix_data = vec![0x01, 0x02, 0x03, 0x04, 0x05, 0x06];
-        let selector: [u8; 4] = ix_data[..4].try_into().unwrap();
-        assert_eq!(selector, [0x01, 0x02, 0x03, 0x04]);
+        let ix_data = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06];
+        assert_eq!(Selector::read(&ix_data, 4).unwrap(), [0x01, 0x02, 0x03, 0x04]);
+        assert_eq!(Selector::read(&ix_data, 2).unwrap(), [0x01, 0x02]);
+        assert_eq!(Selector::read(&ix_data, 8), None);
     }
 }
@@ -0,0 +1,389 @@
+/*!
+ * Diamond Session Module
+ * Short-lived keys calling one facet as the user
+ *
+ * The user signs a `SessionGrant` off-chain, as a `session` action in the
+ * shared signing format (see `diamond_signing`), naming a session key, the
+ * facet it may call and the last slot it may call at. `open_session`
+ * checks the Ed25519 verification of the grant right before it, consumes
+ * the user's relay nonce (grants and relayed calls share one sequence) and
+ * records the session in a PDA. From then on the session key signs
+ * `dispatch_session` itself, and the router forwards the user's relay
+ * signer PDA exactly as on a relayed call, so facets need no change to
+ * accept it.
+ */
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    system_program,
+    sysvar::Sysvar,
+};
+
+use crate::diamond_relay::{load_or_create_nonce, RelayNonce};
+use crate::diamond_router::{route, RouteOptions};
+use crate::diamond_signing::{self, action, data_hash, SigningPayload};
+use crate::error::DiamondError;
+use crate::events::DiamondEvent;
+
+pub use diamond_constants::seeds::SESSION_SEED;
+
+/// A session key the user authorized off-chain
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct SessionGrant {
+    /// Application the user signed in (see `SigningPayload::domain`)
+    pub domain: String,
+    pub diamond: Pubkey,
+    pub user: Pubkey,
+    pub session_key: Pubkey,
+    /// The only facet the session key may call
+    pub facet: Pubkey,
+    /// Must equal the user's `RelayNonce::next_nonce`
+    pub nonce: u64,
+    /// Last slot the session key may call at
+    pub expiry_slot: u64,
+}
+
+impl SessionGrant {
+    /// The approval the user signs: a `session` action over `(session_key, facet)`
+    pub fn signing_payload(&self) -> SigningPayload {
+        // Writing into a Vec cannot fail
+        let data = borsh::to_vec(&(self.session_key, self.facet)).expect("borsh encode");
+        SigningPayload {
+            domain: self.domain.clone(),
+            signer: self.user.to_bytes(),
+            diamond: self.diamond.to_bytes(),
+            action: action::SESSION.into(),
+            nonce: self.nonce,
+            expiry_slot: self.expiry_slot,
+            data_hash: data_hash(&data),
+        }
+    }
+    
+    /// Bytes the user signs
+    pub fn message(&self) -> Vec<u8> {
+        self.signing_payload().message()
+    }
+}
+
+/// An open session
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct Session {
+    pub diamond: Pubkey,
+    pub user: Pubkey,
+    pub session_key: Pubkey,
+    pub facet: Pubkey,
+    pub expiry_slot: u64,
+    /// Refunded the rent on close
+    pub rent_payer: Pubkey,
+    pub bump: u8,
+}
+
+impl Session {
+    pub const SPACE: usize =
+        32 + // diamond
+        32 + // user
+        32 + // session_key
+        32 + // facet
+        8 +  // expiry_slot
+        32 + // rent_payer
+        1;   // bump
+    
+    pub fn find_address(program_id: &Pubkey, diamond: &Pubkey, user: &Pubkey, session_key: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[SESSION_SEED, diamond.as_ref(), user.as_ref(), session_key.as_ref()],
+            program_id,
+        )
+    }
+    
+    /// Load a session of `diamond`, checking its address and owner
+    pub fn load(program_id: &Pubkey, account: &AccountInfo, diamond: &Pubkey) -> Result<Self, ProgramError> {
+        if account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let session = Self::deserialize(&mut &account.try_borrow_data()?[..])
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        let expected = Pubkey::create_program_address(
+            &[SESSION_SEED, diamond.as_ref(), session.user.as_ref(), session.session_key.as_ref(), &[session.bump]],
+            program_id,
+        )
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+        if &session.diamond != diamond || account.key != &expected {
+            msg!("Error: Session {} does not belong to this diamond", account.key);
+            return Err(ProgramError::InvalidSeeds);
+        }
+        Ok(session)
+    }
+    
+    /// Check the session lets `session_key` call `facet` at `slot`
+    pub fn check(&self, session_key: &Pubkey, facet: &Pubkey, slot: u64) -> Result<(), DiamondError> {
+        if &self.session_key != session_key || &self.facet != facet {
+            msg!("Error: Session is for key {} on facet {}", self.session_key, self.facet);
+            return Err(DiamondError::InvalidSession);
+        }
+        if slot > self.expiry_slot {
+            msg!("Error: Session expired at slot {}", self.expiry_slot);
+            return Err(DiamondError::InvalidSession);
+        }
+        Ok(())
+    }
+}
+
+/// Check a grant against the diamond, slot and the user's nonce
+pub fn check_grant(grant: &SessionGrant, diamond: &Pubkey, slot: u64, nonce: &RelayNonce) -> Result<(), DiamondError> {
+    if &grant.diamond != diamond {
+        msg!("Error: Session grant is for another diamond");
+        return Err(DiamondError::InvalidSession);
+    }
+    if !grant.signing_payload().is_well_formed() {
+        msg!("Error: Session grant domain must be one line of at most {} bytes", diamond_signing::MAX_DOMAIN_LEN);
+        return Err(DiamondError::InvalidSession);
+    }
+    if slot > grant.expiry_slot {
+        msg!("Error: Session grant expired at slot {}", grant.expiry_slot);
+        return Err(DiamondError::InvalidSession);
+    }
+    if grant.nonce != nonce.next_nonce {
+        msg!("Error: Session grant nonce {} (expected {})", grant.nonce, nonce.next_nonce);
+        return Err(DiamondError::InvalidSession);
+    }
+    Ok(())
+}
+
+/// Open a session the user granted off-chain
+///
+/// Accounts: [diamond_state, relay_nonce, session, payer, instructions_sysvar, system_program]
+/// Data: SessionGrant
+///
+/// The instruction right before this one must be the Ed25519 program
+/// verifying the user's signature over `SessionGrant::message`. `payer`
+/// (usually the session key) pays the session's rent and gets it back on
+/// close.
+pub fn open_session(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let nonce_account = next_account_info(account_iter)?;
+    let session_account = next_account_info(account_iter)?;
+    let payer = next_account_info(account_iter)?;
+    let instructions_sysvar = next_account_info(account_iter)?;
+    let system_program_account = next_account_info(account_iter)?;
+    
+    if !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if system_program_account.key != &system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    let grant = SessionGrant::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    if !diamond_signing::preceding_verifies(instructions_sysvar, &grant.signing_payload())? {
+        msg!("Error: No Ed25519 verification of {}'s session grant before this instruction", grant.user);
+        return Err(DiamondError::InvalidSession.into());
+    }
+    
+    let mut nonce = load_or_create_nonce(
+        program_id,
+        state_account.key,
+        &grant.user,
+        nonce_account,
+        payer,
+        system_program_account,
+    )?;
+    check_grant(&grant, state_account.key, Clock::get()?.slot, &nonce)?;
+    nonce.next_nonce += 1;
+    nonce.serialize(&mut &mut nonce_account.try_borrow_mut_data()?[..])
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    let (expected, bump) = Session::find_address(program_id, state_account.key, &grant.user, &grant.session_key);
+    if session_account.key != &expected {
+        msg!("Error: Invalid session PDA. Expected: {}", expected);
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if !session_account.data_is_empty() {
+        msg!("Error: Session already open; close it first");
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            session_account.key,
+            Rent::get()?.minimum_balance(Session::SPACE),
+            Session::SPACE as u64,
+            program_id,
+        ),
+        &[payer.clone(), session_account.clone(), system_program_account.clone()],
+        &[&[SESSION_SEED, state_account.key.as_ref(), grant.user.as_ref(), grant.session_key.as_ref(), &[bump]]],
+    )?;
+    let session = Session {
+        diamond: *state_account.key,
+        user: grant.user,
+        session_key: grant.session_key,
+        facet: grant.facet,
+        expiry_slot: grant.expiry_slot,
+        rent_payer: *payer.key,
+        bump,
+    };
+    session.serialize(&mut &mut session_account.try_borrow_mut_data()?[..])
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    msg!("Session opened for {}: key {} on {} until slot {}", grant.user, grant.session_key, grant.facet, grant.expiry_slot);
+    DiamondEvent::SessionOpened {
+        user: grant.user,
+        session_key: grant.session_key,
+        facet: grant.facet,
+        expiry_slot: grant.expiry_slot,
+    }
+    .emit();
+    Ok(())
+}
+
+/// Dispatch a call as the session's user, signed by the session key
+///
+/// Accounts: [diamond_state, session, session_key, module, ...remaining]
+/// Data: Vec<u8> (facet payload, selector first), as for `dispatch`
+///
+/// `module` must be the session's facet, and one of the remaining accounts
+/// must be `relay_signer(diamond_state, user)`; it is forwarded as a
+/// signer. Otherwise behaves like `dispatch`.
+pub fn dispatch_session(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let session_account = next_account_info(account_iter)?;
+    let session_key = next_account_info(account_iter)?;
+    let module_account = next_account_info(account_iter)?;
+    
+    if !session_key.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let ix_data = Vec::<u8>::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    let session = Session::load(program_id, session_account, state_account.key)?;
+    session.check(session_key.key, module_account.key, Clock::get()?.slot)?;
+    
+    let mut route_accounts = Vec::with_capacity(accounts.len() - 2);
+    route_accounts.push(state_account.clone());
+    route_accounts.push(module_account.clone());
+    route_accounts.extend_from_slice(account_iter.as_slice());
+    route(
+        program_id,
+        &route_accounts,
+        ix_data,
+        RouteOptions { relayed_user: Some(&session.user), ..Default::default() },
+    )
+}
+
+/// Close a session, refunding its rent to whoever paid it
+///
+/// Accounts: [diamond_state, session, authority, rent_payer]
+///
+/// The user or the session key may close a session at any time (the user
+/// revoking it); once expired, anyone may.
+pub fn close_session(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let session_account = next_account_info(account_iter)?;
+    let authority = next_account_info(account_iter)?;
+    let rent_payer = next_account_info(account_iter)?;
+    
+    let session = Session::load(program_id, session_account, state_account.key)?;
+    let is_party = authority.is_signer && (authority.key == &session.user || authority.key == &session.session_key);
+    if !is_party && Clock::get()?.slot <= session.expiry_slot {
+        msg!("Error: Only {} or its session key may close a live session", session.user);
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    if rent_payer.key != &session.rent_payer {
+        msg!("Error: Session rent belongs to {}", session.rent_payer);
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    
+    let refund = session_account.lamports();
+    **rent_payer.try_borrow_mut_lamports()? = rent_payer
+        .lamports()
+        .checked_add(refund)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    **session_account.try_borrow_mut_lamports()? = 0;
+    session_account.try_borrow_mut_data()?.fill(0);
+    
+    msg!("Session of {} for key {} closed, {} lamports refunded", session.user, session.session_key, refund);
+    DiamondEvent::SessionClosed { user: session.user, session_key: session.session_key }.emit();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn grant(diamond: Pubkey) -> SessionGrant {
+        SessionGrant {
+            domain: "game.example.com".into(),
+            diamond,
+            user: Pubkey::new_unique(),
+            session_key: Pubkey::new_unique(),
+            facet: Pubkey::new_unique(),
+            nonce: 2,
+            expiry_slot: 500,
+        }
+    }
+    
+    #[test]
+    fn test_grant_message_binds_key_and_facet() {
+        let grant = grant(Pubkey::new_unique());
+        let message = grant.message();
+        assert!(message.starts_with(b"game.example.com wants you to approve"));
+        assert!(String::from_utf8(message.clone()).unwrap().contains("Action: session\n"));
+        assert_ne!(SessionGrant { session_key: Pubkey::new_unique(), ..grant.clone() }.message(), message);
+        assert_ne!(SessionGrant { facet: Pubkey::new_unique(), ..grant.clone() }.message(), message);
+        assert_ne!(SessionGrant { expiry_slot: 501, ..grant.clone() }.message(), message);
+    }
+    
+    #[test]
+    fn test_check_grant_and_session() {
+        let diamond = Pubkey::new_unique();
+        let grant = grant(diamond);
+        let nonce = RelayNonce { diamond, user: grant.user, next_nonce: 2, bump: 255 };
+        
+        assert_eq!(check_grant(&grant, &diamond, 500, &nonce), Ok(()));
+        assert_eq!(check_grant(&grant, &diamond, 501, &nonce), Err(DiamondError::InvalidSession));
+        assert_eq!(check_grant(&grant, &Pubkey::new_unique(), 0, &nonce), Err(DiamondError::InvalidSession));
+        let used = RelayNonce { next_nonce: 3, ..nonce };
+        assert_eq!(check_grant(&grant, &diamond, 0, &used), Err(DiamondError::InvalidSession));
+        
+        let session = Session {
+            diamond,
+            user: grant.user,
+            session_key: grant.session_key,
+            facet: grant.facet,
+            expiry_slot: grant.expiry_slot,
+            rent_payer: grant.session_key,
+            bump: 255,
+        };
+        assert_eq!(borsh::to_vec(&session).unwrap().len(), Session::SPACE);
+        assert_eq!(session.check(&grant.session_key, &grant.facet, 500), Ok(()));
+        assert_eq!(session.check(&grant.session_key, &grant.facet, 501), Err(DiamondError::InvalidSession));
+        assert_eq!(session.check(&grant.session_key, &Pubkey::new_unique(), 0), Err(DiamondError::InvalidSession));
+        assert_eq!(session.check(&Pubkey::new_unique(), &grant.facet, 0), Err(DiamondError::InvalidSession));
+    }
+}
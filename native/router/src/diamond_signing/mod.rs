@@ -0,0 +1,134 @@
+/*!
+ * Diamond Signing Module
+ * Verification of off-chain approvals in the shared `SigningPayload` format
+ *
+ * A feature that acts on an off-chain signature has the submitter place an
+ * Ed25519 program instruction verifying it immediately before the router
+ * instruction. The runtime checks the signature; the router then confirms,
+ * through the instructions sysvar, that the verified key and message are the
+ * signer and `SigningPayload::message` it expects. `verify_instruction`
+ * builds that Ed25519 instruction for clients.
+ */
+
+use solana_program::{
+    account_info::AccountInfo,
+    ed25519_program,
+    instruction::Instruction,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    sysvar::instructions::{load_current_index_checked, load_instruction_at_checked},
+};
+
+pub use diamond_core::signing::{action, data_hash, SigningPayload, MAX_DOMAIN_LEN};
+
+/// Ed25519 instruction header: signature count and padding
+const HEADER: usize = 2;
+/// One signature's offsets: seven u16 fields
+const OFFSETS: usize = 14;
+/// Instruction index meaning "this instruction's own data"
+const INLINE: u16 = u16::MAX;
+
+fn read_u16(data: &[u8], offset: usize) -> Option<usize> {
+    data.get(offset..offset + 2).map(|b| u16::from_le_bytes([b[0], b[1]]) as usize)
+}
+
+/// Ed25519 program instruction verifying `signature` by `signer` over
+/// `message`, with everything inline
+pub fn verify_instruction(signer: &Pubkey, signature: &[u8; 64], message: &[u8]) -> Instruction {
+    let pubkey_offset = HEADER + OFFSETS;
+    let signature_offset = pubkey_offset + 32;
+    let message_offset = signature_offset + 64;
+    let mut data = vec![1, 0];
+    for field in [signature_offset, INLINE as usize, pubkey_offset, INLINE as usize, message_offset, message.len(), INLINE as usize] {
+        data.extend_from_slice(&(field as u16).to_le_bytes());
+    }
+    data.extend_from_slice(signer.as_ref());
+    data.extend_from_slice(signature);
+    data.extend_from_slice(message);
+    Instruction { program_id: ed25519_program::id(), accounts: vec![], data }
+}
+
+/// Whether `ix` is an Ed25519 program instruction verifying exactly one
+/// signature by `signer` over `message`, with all data inline
+///
+/// The precompile has already checked the signature by the time the router
+/// runs; this only confirms which key and message it checked.
+pub fn verifies_signature(ix: &Instruction, signer: &Pubkey, message: &[u8]) -> bool {
+    let data = &ix.data;
+    if ix.program_id != ed25519_program::id() || data.len() < HEADER + OFFSETS || data[0] != 1 {
+        return false;
+    }
+    let field = |index: usize| read_u16(data, HEADER + 2 * index);
+    let (
+        Some(_signature_offset),
+        Some(signature_ix),
+        Some(pubkey_offset),
+        Some(pubkey_ix),
+        Some(message_offset),
+        Some(message_len),
+        Some(message_ix),
+    ) = (field(0), field(1), field(2), field(3), field(4), field(5), field(6))
+    else {
+        return false;
+    };
+    let inline = INLINE as usize;
+    if signature_ix != inline || pubkey_ix != inline || message_ix != inline {
+        return false;
+    }
+    data.get(pubkey_offset..pubkey_offset + 32) == Some(signer.as_ref())
+        && data.get(message_offset..message_offset + message_len) == Some(message)
+}
+
+/// Whether the instruction right before the current one verified `payload`
+/// (signed by `payload.signer`)
+pub fn preceding_verifies(instructions_sysvar: &AccountInfo, payload: &SigningPayload) -> Result<bool, ProgramError> {
+    let current = load_current_index_checked(instructions_sysvar)? as usize;
+    let signer = Pubkey::new_from_array(payload.signer);
+    Ok(current
+        .checked_sub(1)
+        .map(|index| load_instruction_at_checked(index, instructions_sysvar))
+        .transpose()?
+        .is_some_and(|ix| verifies_signature(&ix, &signer, &payload.message())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_verifies_signature_binds_key_and_message() {
+        let signer = Pubkey::new_unique();
+        let message = b"approve";
+        let ix = verify_instruction(&signer, &[7; 64], message);
+        
+        assert!(verifies_signature(&ix, &signer, message));
+        assert!(!verifies_signature(&ix, &Pubkey::new_unique(), message));
+        assert!(!verifies_signature(&ix, &signer, &message[1..]));
+        
+        let mut elsewhere = ix.clone();
+        elsewhere.program_id = Pubkey::new_unique();
+        assert!(!verifies_signature(&elsewhere, &signer, message));
+        
+        // Data pulled from another instruction isn't what was submitted
+        let mut indirect = ix;
+        indirect.data[HEADER + 2 * 3..HEADER + 2 * 4].copy_from_slice(&0u16.to_le_bytes());
+        assert!(!verifies_signature(&indirect, &signer, message));
+    }
+    
+    #[test]
+    fn test_message_keys_are_base58() {
+        let (signer, diamond) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let payload = SigningPayload {
+            domain: "app.example.com".into(),
+            signer: signer.to_bytes(),
+            diamond: diamond.to_bytes(),
+            action: action::RELAY.into(),
+            nonce: 0,
+            expiry_slot: 0,
+            data_hash: data_hash(b""),
+        };
+        let message = String::from_utf8(payload.message()).unwrap();
+        assert!(message.contains(&format!("account:\n{}\n", signer)));
+        assert!(message.contains(&format!("Diamond: {}\n", diamond)));
+    }
+}
@@ -4,6 +4,7 @@
  */
 
 use borsh::{BorshDeserialize, BorshSerialize};
+use std::ops::{Deref, DerefMut};
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
     entrypoint::ProgramResult,
@@ -14,47 +15,218 @@ use solana_program::{
     rent::Rent,
     system_instruction,
     system_program,
-    sysvar::Sysvar,
+    clock::Clock,
+    sysvar::{self, Sysvar},
 };
 
+use diamond_constants::{capacity, layout};
+use diamond_core::routing;
+
+use crate::diamond_cut::{apply_cuts, authorize_namespace, FacetCut};
+use crate::diamond_signing::{self, action, data_hash, SigningPayload};
 use crate::error::DiamondError;
+use crate::events::DiamondEvent;
+use crate::selector::{is_valid_width, Selector, DEFAULT_SELECTOR_WIDTH};
 
-/// Selector mapping: 4-byte selector → program ID
+/// Selector mapping: selector (at the diamond's width) → program ID
 #[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
 pub struct SelectorMapping {
-    pub selector: [u8; 4],
+    pub namespace: [u8; 8],
+    pub selector: Selector,
     pub module: Pubkey,
     pub function_name: [u8; 64],
     pub is_immutable: bool,
+    pub standard_accounts: u8,
+    /// Rehash salt `allocate_selector` used (0 for explicitly chosen selectors)
+    pub salt: u8,
+}
+
+pub use diamond_core::flags::{
+    Reentrancy, CALL_TRACE, RENT_VAULT, REENTRANCY_ALLOWED, REENTRANCY_MASK, REENTRANCY_SAME_FACET,
+    STD_CLOCK, STD_RENT, STD_SYSTEM_PROGRAM, STD_TOKEN_PROGRAM,
+};
+pub use diamond_core::names::{fixed_bytes, fixed_str, namespace_bytes};
+pub use diamond_core::payload::{schema_hash, PayloadSpec};
+pub use diamond_core::return_data::ReturnOverflowPolicy;
+
+/// Outermost dispatch in progress, recorded while its facet may re-enter
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ActiveCall {
+    pub facet: Pubkey,
+    pub reentrancy: Reentrancy,
+    /// Invoke stack height of the outermost dispatch; nesting depth counts from here
+    pub stack_height: u8,
+}
+
+impl ActiveCall {
+    /// Whether a nested dispatch to `target` is allowed under this call
+    pub fn permits(&self, target: &SelectorMapping) -> bool {
+        let target_policy = target.reentrancy();
+        if target_policy == Reentrancy::Forbidden {
+            return false;
+        }
+        match self.reentrancy {
+            Reentrancy::Forbidden => false,
+            Reentrancy::SameFacetOnly => target.module == self.facet,
+            Reentrancy::Allowed => true,
+        }
+    }
+}
+
+/// SPL Token program (not a dependency, so the address is pinned here)
+pub const TOKEN_PROGRAM_ID: Pubkey = solana_program::pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+/// SPL Token-2022 program
+pub const TOKEN_2022_PROGRAM_ID: Pubkey = solana_program::pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+
+/// Addresses of the standard accounts selected by `flags`, in canonical order
+pub fn standard_account_ids(flags: u8) -> Vec<Pubkey> {
+    [
+        (STD_SYSTEM_PROGRAM, system_program::id()),
+        (STD_RENT, sysvar::rent::id()),
+        (STD_CLOCK, sysvar::clock::id()),
+        (STD_TOKEN_PROGRAM, TOKEN_PROGRAM_ID),
+    ]
+    .iter()
+    .filter(|(flag, _)| flags & flag != 0)
+    .map(|(_, id)| *id)
+    .collect()
 }
 
 impl SelectorMapping {
-    pub fn new(selector: [u8; 4], module: Pubkey, name: &str, immutable: bool) -> Self {
+    pub fn new(selector: impl Into<Selector>, module: Pubkey, name: &str, immutable: bool) -> Self {
+        Self::new_with_namespace([0u8; 8], selector, module, name, immutable)
+    }
+
+    pub fn new_with_namespace(
+        namespace: [u8; 8],
+        selector: impl Into<Selector>,
+        module: Pubkey,
+        name: &str,
+        immutable: bool,
+    ) -> Self {
         let mut function_name = [0u8; 64];
         let bytes = name.as_bytes();
         let len = bytes.len().min(64);
         function_name[..len].copy_from_slice(&bytes[..len]);
         
         Self {
-            selector,
+            namespace,
+            selector: selector.into(),
             module,
             function_name,
             is_immutable: immutable,
+            standard_accounts: 0,
+            salt: 0,
+        }
+    }
+
+    /// Declare standard accounts (`STD_*` flags) appended on every dispatch
+    pub fn with_standard_accounts(mut self, flags: u8) -> Self {
+        self.standard_accounts = flags;
+        self
+    }
+
+    /// Set the reentrancy policy bits
+    pub fn with_reentrancy(mut self, policy: Reentrancy) -> Self {
+        self.standard_accounts = (self.standard_accounts & !REENTRANCY_MASK) | policy.to_flags();
+        self
+    }
+    
+    pub fn reentrancy(&self) -> Reentrancy {
+        Reentrancy::from_flags(self.standard_accounts)
+    }
+    
+    pub fn namespace_as_str(&self) -> &str {
+        fixed_str(&self.namespace)
+    }
+
+    pub fn function_name_as_str(&self) -> &str {
+        fixed_str(&self.function_name)
+    }
+
+    /// `namespace::function_name`, or just `function_name` in the global namespace
+    pub fn qualified_name(&self) -> String {
+        match self.namespace_as_str() {
+            "" => self.function_name_as_str().to_string(),
+            ns => format!("{}::{}", ns, self.function_name_as_str()),
+        }
+    }
+}
+
+/// Facet version, `major.minor.patch`
+///
+/// A major change may break callers, so moving selectors across majors
+/// takes a cut proposal; minor and patch changes can be cut directly.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Semver {
+    pub major: u16,
+    pub minor: u16,
+    pub patch: u16,
+}
+
+impl Semver {
+    pub const ENCODED_LEN: usize = layout::SEMVER_LEN;
+    /// Version of a facet that never declared one
+    pub const INITIAL: Semver = Semver::new(1, 0, 0);
+    
+    pub const fn new(major: u16, minor: u16, patch: u16) -> Self {
+        Self { major, minor, patch }
+    }
+    
+    /// Whether `self` can stand in for `required`: same major, not older
+    pub fn satisfies(&self, required: &Semver) -> bool {
+        self.major == required.major && self >= required
+    }
+}
+
+impl std::fmt::Display for Semver {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl std::str::FromStr for Semver {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, String> {
+        let parts: Vec<u16> = s
+            .split('.')
+            .map(|part| part.parse().map_err(|_| format!("invalid version {:?} (expected major.minor.patch)", s)))
+            .collect::<Result<_, _>>()?;
+        match parts[..] {
+            [major, minor, patch] => Ok(Self::new(major, minor, patch)),
+            _ => Err(format!("invalid version {:?} (expected major.minor.patch)", s)),
         }
     }
 }
 
+/// One version declaration (see `diamond_version`)
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VersionRecord {
+    pub module: Pubkey,
+    pub version: Semver,
+    /// Attested build hash the version was declared for
+    pub hash: [u8; 32],
+    pub slot: u64,
+}
+
+impl VersionRecord {
+    pub const ENCODED_LEN: usize = layout::VERSION_RECORD_LEN;
+}
+
 /// Module metadata
 #[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
 pub struct ModuleMeta {
     pub name: [u8; 32],
     pub address: Pubkey,
-    pub version: u16,
+    pub version: Semver,
+    /// Routed by a cut; inactive entries only carry a version declared ahead
+    /// of the first cut
     pub is_active: bool,
 }
 
 impl ModuleMeta {
-    pub fn new(name: &str, address: Pubkey, version: u16) -> Self {
+    pub fn new(name: &str, address: Pubkey, version: Semver) -> Self {
         let mut name_bytes = [0u8; 32];
         let bytes = name.as_bytes();
         let len = bytes.len().min(32);
@@ -67,115 +239,1713 @@ impl ModuleMeta {
             is_active: true,
         }
     }
+    
+    pub fn name_as_str(&self) -> &str {
+        fixed_str(&self.name)
+    }
+}
+
+/// Outcome of the last `build::verify_facet_build` run
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BuildCheck {
+    /// Program data deploy slot that was hashed
+    pub deploy_slot: u64,
+    pub matched: bool,
+}
+
+/// Expected verifiable-build hash of a registered facet
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BuildAttestation {
+    pub module: Pubkey,
+    /// `diamond_build::executable_hash` of the verified build
+    pub hash: [u8; 32],
+    pub last_check: Option<BuildCheck>,
+}
+
+impl BuildAttestation {
+    pub const ENCODED_LEN: usize = layout::BUILD_ATTESTATION_LEN;
+}
+
+/// Voting member of a diamond council
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CouncilMember {
+    pub key: Pubkey,
+    pub weight: u16,
+}
+
+/// Weighted owners whose motions replace the owner for cuts, pauses and
+/// council membership
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Council {
+    pub members: Vec<CouncilMember>,
+    /// Approval weight a motion needs before it executes
+    pub threshold: u32,
+}
+
+impl Council {
+    pub const MAX_MEMBERS: usize = capacity::MAX_COUNCIL_MEMBERS;
+    pub const MAX_ENCODED_LEN: usize = 4 + Self::MAX_MEMBERS * (32 + 2) + 4;
+    
+    /// Reject councils that could never pass (or trivially pass) a motion
+    pub fn validate(&self) -> Result<(), DiamondError> {
+        let distinct = self
+            .members
+            .iter()
+            .enumerate()
+            .all(|(i, m)| self.members[i + 1..].iter().all(|other| other.key != m.key));
+        let total: u32 = self.members.iter().map(|m| m.weight as u32).sum();
+        if self.members.is_empty()
+            || self.members.len() > Self::MAX_MEMBERS
+            || !distinct
+            || self.members.iter().any(|m| m.weight == 0)
+            || self.threshold == 0
+            || self.threshold > total
+        {
+            return Err(DiamondError::InvalidCouncil);
+        }
+        Ok(())
+    }
+    
+    pub fn weight_of(&self, key: &Pubkey) -> Option<u16> {
+        self.members.iter().find(|m| &m.key == key).map(|m| m.weight)
+    }
+    
+    /// Combined weight of the current members among `approvals`
+    pub fn approved_weight(&self, approvals: &[Pubkey]) -> u32 {
+        approvals.iter().filter_map(|key| self.weight_of(key)).map(u32::from).sum()
+    }
+}
+
+/// Namespace a facet calls into, served at a version satisfying
+/// `min_version` (see `diamond_dependency`)
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FacetDependency {
+    /// Facet that declared the dependency
+    pub module: Pubkey,
+    pub namespace: [u8; 8],
+    pub min_version: Semver,
+}
+
+impl FacetDependency {
+    pub const ENCODED_LEN: usize = layout::FACET_DEPENDENCY_LEN;
+    
+    pub fn namespace_as_str(&self) -> &str {
+        fixed_str(&self.namespace)
+    }
+}
+
+/// Programs that may not share a transaction with the guarded selectors
+/// (checked through the instructions sysvar; see `diamond_guard`)
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct TxGuard {
+    pub denylist: Vec<Pubkey>,
+    pub selectors: Vec<Selector>,
+}
+
+impl TxGuard {
+    pub const MAX_DENYLIST: usize = capacity::MAX_DENYLIST;
+    pub const MAX_SELECTORS: usize = capacity::MAX_GUARDED_SELECTORS;
+    pub const MAX_ENCODED_LEN: usize = 4 + Self::MAX_DENYLIST * 32 + 4 + Self::MAX_SELECTORS * 8;
+    
+    pub fn guards(&self, selector: &Selector) -> bool {
+        self.selectors.contains(selector)
+    }
+}
+
+/// External policy program consulted before every cut and every call to a
+/// protected selector (see `diamond_authorizer`)
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Authorizer {
+    pub program: Pubkey,
+    pub protected: Vec<Selector>,
+}
+
+impl Authorizer {
+    pub const MAX_PROTECTED: usize = capacity::MAX_PROTECTED_SELECTORS;
+    pub const MAX_ENCODED_LEN: usize = 32 + 4 + Self::MAX_PROTECTED * 8;
+    
+    pub fn protects(&self, selector: &Selector) -> bool {
+        self.protected.contains(selector)
+    }
+}
+
+/// Oracle condition a selector only dispatches under (see `diamond_oracle`)
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OracleGuard {
+    pub selector: Selector,
+    /// Pyth `PriceUpdateV2` account the condition reads
+    pub feed: Pubkey,
+    /// Oldest acceptable price, in seconds before the cluster clock
+    pub max_age_secs: u32,
+    /// Widest acceptable confidence interval, in basis points of the price
+    /// (0 leaves it unchecked)
+    pub max_confidence_bps: u16,
+}
+
+impl OracleGuard {
+    pub const ENCODED_LEN: usize = layout::ORACLE_GUARD_LEN;
+}
+
+/// Scheduled maintenance window: facet dispatch stops from `start_slot`
+/// until `end_slot` (exclusive) while cuts and built-ins keep working
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PauseWindow {
+    pub start_slot: u64,
+    pub end_slot: u64,
+}
+
+impl PauseWindow {
+    pub const ENCODED_LEN: usize = layout::PAUSE_WINDOW_LEN;
+    
+    pub fn contains(&self, slot: u64) -> bool {
+        (self.start_slot..self.end_slot).contains(&slot)
+    }
+}
+
+/// Shared health registry the diamond honors: calls to a facet the registry
+/// killed, or flagged at `min_severity` or worse (0 = kills only), are refused
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HealthRegistry {
+    pub program: Pubkey,
+    pub min_severity: u8,
+}
+
+impl HealthRegistry {
+    pub const ENCODED_LEN: usize = layout::HEALTH_REGISTRY_LEN;
+}
+
+/// Scheduled break-glass override of one route (see `diamond_break_glass`)
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BreakGlass {
+    pub selector: Selector,
+    /// Facet the selector is re-pointed to; `None` removes it
+    pub module: Option<Pubkey>,
+    pub effective_slot: u64,
+}
+
+impl BreakGlass {
+    pub const ENCODED_LEN: usize = layout::BREAK_GLASS_LEN;
+}
+
+/// What a circuit breaker counts
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BreakerMetric {
+    /// Facet calls that succeeded
+    Calls,
+    /// Calls `try_dispatch` skipped because the router refused them
+    Refusals,
+}
+
+/// Automatic pause of one selector (see `diamond_breaker`)
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CircuitBreaker {
+    pub selector: Selector,
+    pub metric: BreakerMetric,
+    /// Most events tolerated within one window
+    pub threshold: u32,
+    pub window_slots: u64,
+    /// First slot of the current window
+    pub window_start: u64,
+    /// Events counted in the current window
+    pub count: u32,
+    /// Slot the breaker tripped at; the selector doesn't dispatch until the
+    /// owner re-arms it
+    pub tripped_slot: Option<u64>,
+}
+
+impl CircuitBreaker {
+    pub const ENCODED_LEN: usize = layout::CIRCUIT_BREAKER_LEN;
+    
+    /// Count one event at `slot`, returning whether it tripped the breaker
+    pub fn record(&mut self, slot: u64) -> bool {
+        if self.tripped_slot.is_some() {
+            return false;
+        }
+        if slot >= self.window_start.saturating_add(self.window_slots) {
+            self.window_start = slot;
+            self.count = 0;
+        }
+        self.count = self.count.saturating_add(1);
+        if self.count <= self.threshold {
+            return false;
+        }
+        self.tripped_slot = Some(slot);
+        true
+    }
+}
+
+/// Extra selector dispatched through a canonical mapping
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SelectorAlias {
+    pub alias: Selector,
+    pub canonical: Selector,
+}
+
+/// Label grouping a selector with others (e.g. "view", "admin", "defi")
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SelectorTag {
+    pub tag: [u8; 8],
+    pub selector: Selector,
+}
+
+impl SelectorTag {
+    pub fn tag_as_str(&self) -> &str {
+        fixed_str(&self.tag)
+    }
+}
+
+/// Namespace whose cuts are delegated to a tenant authority
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct Tenant {
+    pub namespace: [u8; 8],
+    pub authority: Pubkey,
+}
+
+/// Capacity in which an authority may cut a namespace
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CutRole {
+    /// Owner (the governance key once governance-only): every namespace
+    Owner,
+    /// Every namespace, until the diamond goes governance-only
+    Admin,
+    /// Only the delegated namespace
+    Tenant,
+}
+
+/// Solana's invoke stack limit (transaction level is height 1)
+pub const MAX_INVOKE_STACK_HEIGHT: usize = 5;
+
+/// Owner-set bounds on facet calls (0 = unlimited)
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DispatchLimits {
+    /// Accounts forwarded to the facet (after state and module)
+    pub max_accounts: u16,
+    /// Facet payload bytes, selector included
+    pub max_payload_len: u32,
+    /// Stack levels a re-entrant call may nest below the outermost dispatch
+    pub max_depth: u8,
+}
+
+impl DispatchLimits {
+    pub fn check(&self, accounts: usize, payload_len: usize) -> Result<(), DiamondError> {
+        if self.max_accounts != 0 && accounts > self.max_accounts as usize {
+            msg!("Error: {} accounts exceed the limit of {}", accounts, self.max_accounts);
+            return Err(DiamondError::DispatchLimitExceeded);
+        }
+        if self.max_payload_len != 0 && payload_len > self.max_payload_len as usize {
+            msg!("Error: {}-byte payload exceeds the limit of {}", payload_len, self.max_payload_len);
+            return Err(DiamondError::DispatchLimitExceeded);
+        }
+        Ok(())
+    }
+    
+    /// Check a dispatch at invoke stack height `stack_height` has room for its CPI
+    ///
+    /// `active` is the outermost call when this dispatch re-enters the router.
+    /// The runtime limit always applies, so nesting fails here with a clear
+    /// error instead of deep inside a facet.
+    pub fn check_depth(&self, stack_height: usize, active: Option<&ActiveCall>) -> Result<(), DiamondError> {
+        if stack_height + 1 > MAX_INVOKE_STACK_HEIGHT {
+            msg!("Error: Facet CPI would exceed the invoke stack limit of {}", MAX_INVOKE_STACK_HEIGHT);
+            return Err(DiamondError::CallDepthExceeded);
+        }
+        let depth = active.map_or(0, |active| stack_height.saturating_sub(active.stack_height as usize));
+        if self.max_depth != 0 && depth > self.max_depth as usize {
+            msg!("Error: Dispatch nested {} levels deep (max {})", depth, self.max_depth);
+            return Err(DiamondError::CallDepthExceeded);
+        }
+        Ok(())
+    }
+}
+
+/// Default announcement window for authority changes (~1 day of slots)
+pub const DEFAULT_AUTHORITY_DELAY_SLOTS: u64 = 216_000;
+
+/// Longest announcement window (~30 days of slots), so a raised delay can
+/// always be lowered again
+pub const MAX_AUTHORITY_DELAY_SLOTS: u64 = 6_480_000;
+
+/// Ownership, guardian or governance change subject to the announcement window
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuthorityChange {
+    /// Nominee may accept ownership (tracked in `pending_owner`)
+    Owner(Pubkey),
+    /// Governance authority set or cleared
+    Governance(Option<Pubkey>),
+    /// Authority delay lowered (raising it applies immediately)
+    Delay(u64),
+    /// Guardian (pause authority) replaced, so the owner can't swap out the
+    /// vetoer of its own announcements
+    PauseAuthority(Pubkey),
+}
+
+/// Announced governance or delay change and the slot it may be applied at
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PendingAuthorityChange {
+    pub change: AuthorityChange,
+    pub effective_slot: u64,
+}
+
+/// Change to who holds authority over the diamond, made by the owner or,
+/// under a council, by motion (see `apply_authority_action`)
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuthorityAction {
+    AddAdmin(Pubkey),
+    RemoveAdmin(Pubkey),
+    PauseAuthority(Pubkey),
+    /// Announce a governance change
+    Governance(Option<Pubkey>),
+    /// Set the authority delay (lowering it is announced)
+    Delay(u64),
+    /// Nominate (or clear) the next owner
+    TransferOwnership(Option<Pubkey>),
+    /// Delegate (or revoke) a namespace
+    Tenant { namespace: [u8; 8], authority: Option<Pubkey> },
+    EnableGovernanceOnly,
+    /// Set the unpause quorum
+    UnpauseQuorum(u8),
+    /// Bound the accounts and payload size of facet calls
+    DispatchLimits(DispatchLimits),
+}
+
+/// Which announced change a veto cancels
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VetoTarget {
+    Ownership,
+    Config,
+}
+
+/// A veto the guardian (or another vetoer) signed off-chain, for anyone to submit
+///
+/// It names the exact change and effective slot it cancels, so it can't be
+/// replayed against a later announcement.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct SignedVeto {
+    /// Application the guardian signed in (see `SigningPayload::domain`)
+    pub domain: String,
+    pub diamond: Pubkey,
+    pub guardian: Pubkey,
+    pub target: VetoTarget,
+    pub change: AuthorityChange,
+    pub effective_slot: u64,
+    /// Last slot the veto may be submitted at
+    pub expiry_slot: u64,
+}
+
+impl SignedVeto {
+    /// The approval the guardian signs: a `guardian-veto` action over
+    /// `(target, change, effective_slot)`, with the effective slot as nonce
+    pub fn signing_payload(&self) -> SigningPayload {
+        // Writing into a Vec cannot fail
+        let data = borsh::to_vec(&(self.target, self.change, self.effective_slot)).expect("borsh encode");
+        SigningPayload {
+            domain: self.domain.clone(),
+            signer: self.guardian.to_bytes(),
+            diamond: self.diamond.to_bytes(),
+            action: action::GUARDIAN_VETO.into(),
+            nonce: self.effective_slot,
+            expiry_slot: self.expiry_slot,
+            data_hash: data_hash(&data),
+        }
+    }
+    
+    /// Bytes the guardian signs
+    pub fn message(&self) -> Vec<u8> {
+        self.signing_payload().message()
+    }
+}
+
+pub use diamond_constants::seeds::DIAMOND_STATE_SEED;
+
+/// Longest base58 prefix `initialize` will check a vanity address against
+pub use diamond_constants::capacity::MAX_VANITY_PREFIX;
+
+/// Pre-ground extra seed giving the diamond PDA a recognizable prefix
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct VanitySeed {
+    pub nonce: u64,
+    /// Base58 prefix the address must start with (checked, not searched for)
+    pub prefix: String,
+}
+
+/// Seeds of the diamond state PDA created by `owner` (append the bump to sign)
+pub fn diamond_seeds<'a>(owner: &'a Pubkey, vanity_nonce: Option<&'a [u8; 8]>) -> Vec<&'a [u8]> {
+    let mut seeds = vec![DIAMOND_STATE_SEED, owner.as_ref()];
+    seeds.extend(vanity_nonce.map(|nonce| &nonce[..]));
+    seeds
+}
+
+/// Diamond state PDA of `owner`, with the vanity nonce as a third seed if any
+pub fn diamond_address(program_id: &Pubkey, owner: &Pubkey, vanity_nonce: Option<u64>) -> (Pubkey, u8) {
+    let nonce = vanity_nonce.map(u64::to_le_bytes);
+    Pubkey::find_program_address(&diamond_seeds(owner, nonce.as_ref()), program_id)
+}
+
+/// Check a derived address carries the promised prefix
+pub fn check_vanity(address: &Pubkey, prefix: &str) -> Result<(), DiamondError> {
+    if prefix.len() > MAX_VANITY_PREFIX || !address.to_string().starts_with(prefix) {
+        msg!("Error: {} does not start with {:?}", address, prefix);
+        return Err(DiamondError::VanityMismatch);
+    }
+    Ok(())
 }
 
 /// Main Diamond State
-#[derive(BorshSerialize, BorshDeserialize, Clone, Debug)]
+///
+/// Encoded in field order, except that the layout version
+/// (`STATE_LAYOUT_VERSION`) follows `owner` and the selector index (see
+/// `diamond_core::routing`) is written between `selector_width` and
+/// `selectors`. The index is rebuilt from `selectors` on every encode and
+/// skipped on decode, so it can't go stale. Accounts in an older layout
+/// are upgraded with `diamond_migrate::migrate_state`.
+#[derive(Clone, Debug)]
 pub struct DiamondState {
     pub owner: Pubkey,
+    /// Facet dispatch is frozen (built-ins stay reachable)
+    pub dispatch_paused: bool,
+    pub selector_width: u8,
+    pub selectors: Vec<SelectorMapping>,
+    pub aliases: Vec<SelectorAlias>,
     pub admins: Vec<Pubkey>,
     pub active_modules: Vec<ModuleMeta>,
-    pub selectors: Vec<SelectorMapping>,
     pub bump: u8,
-    pub is_paused: bool,
+    /// Cuts and proposal execution are frozen
+    pub cut_paused: bool,
     pub pause_authority: Pubkey,
+    pub pause_expiry_slot: Option<u64>,
+    pub next_proposal_id: u64,
+    pub governance: Option<Pubkey>,
+    pub governance_only: bool,
+    pub tenants: Vec<Tenant>,
+    pub pending_owner: Option<Pubkey>,
+    pub active_call: Option<ActiveCall>,
+    pub audit_tree: Option<Pubkey>,
+    pub dispatch_limits: DispatchLimits,
+    /// Whether cuts must refresh the routing digest PDA
+    pub routing_digest: bool,
+    /// Slots between announcing and applying an authority change
+    pub authority_delay_slots: u64,
+    /// First slot `pending_owner` may accept ownership
+    pub owner_effective_slot: u64,
+    pub pending_change: Option<PendingAuthorityChange>,
+    /// Argument bounds checked before the CPI, for selectors that declare them
+    pub payload_specs: Vec<PayloadSpec>,
+    /// Bumped by every routing change (cuts and aliases)
+    pub config_version: u64,
+    /// Lamports paid from the fee vault per relayed call (0 = none)
+    pub relay_reimbursement: u64,
+    /// Handling of facet results returned through a scratch account
+    pub return_overflow: ReturnOverflowPolicy,
+    /// Extra PDA seed the diamond was created with (see `diamond_address`)
+    pub vanity_nonce: Option<u64>,
+    /// A council account governs the diamond; cuts and pauses need a passed motion
+    pub has_council: bool,
+    /// Selectors refused alongside denylisted programs in one transaction
+    pub tx_guard: TxGuard,
+    /// Namespaces each facet needs routed before it and while it is
+    pub dependencies: Vec<FacetDependency>,
+    /// Policy program approving cuts and protected calls
+    pub authorizer: Option<Authorizer>,
+    /// Labels on selectors, for grouping and bulk operations
+    pub tags: Vec<SelectorTag>,
+    /// Tags whose selectors don't dispatch
+    pub paused_tags: Vec<[u8; 8]>,
+    /// Selectors that only dispatch while an oracle condition holds
+    pub oracle_guards: Vec<OracleGuard>,
+    /// Distinct owner/admin signatures needed to lift or shorten a pause
+    /// (0 = any key that may pause)
+    pub unpause_quorum: u8,
+    /// Scheduled maintenance windows, in the order they were scheduled
+    pub pause_windows: Vec<PauseWindow>,
+    /// Shared facet health registry consulted on dispatch
+    pub health_registry: Option<HealthRegistry>,
+    /// Whether cuts must rebuild the selector index PDA
+    pub selector_index: bool,
+    /// Governance override awaiting its timelock
+    pub break_glass: Option<BreakGlass>,
+    /// Anomaly thresholds that pause a selector on their own
+    pub circuit_breakers: Vec<CircuitBreaker>,
+    /// Owner the diamond was created for; with `vanity_nonce` and `bump`,
+    /// the seeds of its address (ownership changes don't move the state)
+    pub creator: Pubkey,
+}
+
+/// Borsh impls writing the selector index after the fixed-offset fields,
+/// and the encoding of the fields after the routing tables on their own
+/// (see `DispatchState`)
+macro_rules! state_encoding {
+    (tables: $($table:ident),* ; tail: $($field:ident),* $(,)?) => {
+        impl BorshSerialize for DiamondState {
+            fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+                self.owner.serialize(writer)?;
+                layout::STATE_LAYOUT_VERSION.serialize(writer)?;
+                self.dispatch_paused.serialize(writer)?;
+                self.selector_width.serialize(writer)?;
+                routing::build_index(self.selectors.iter().map(|m| m.selector)).serialize(writer)?;
+                $(self.$table.serialize(writer)?;)*
+                self.serialize_tail(writer)
+            }
+        }
+        
+        impl BorshDeserialize for DiamondState {
+            fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+                let mut head = Self::new(BorshDeserialize::deserialize_reader(reader)?, 0);
+                if u8::deserialize_reader(reader)? != layout::STATE_LAYOUT_VERSION {
+                    return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "outdated state layout"));
+                }
+                head.dispatch_paused = BorshDeserialize::deserialize_reader(reader)?;
+                head.selector_width = BorshDeserialize::deserialize_reader(reader)?;
+                Vec::<routing::IndexEntry>::deserialize_reader(reader)?;
+                $(head.$table = BorshDeserialize::deserialize_reader(reader)?;)*
+                Self::deserialize_tail(head, reader)
+            }
+        }
+        
+        impl DiamondState {
+            /// Write the fields after the routing tables
+            fn serialize_tail<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+                $(self.$field.serialize(writer)?;)*
+                Ok(())
+            }
+            
+            /// `head` with the fields after the routing tables read from `reader`
+            fn deserialize_tail<R: std::io::Read>(head: Self, reader: &mut R) -> std::io::Result<Self> {
+                Ok(Self {
+                    $($field: BorshDeserialize::deserialize_reader(reader)?,)*
+                    ..head
+                })
+            }
+        }
+    };
+}
+
+state_encoding!(
+    tables:
+    selectors,
+    aliases,
+    admins,
+    active_modules;
+    tail:
+    bump,
+    cut_paused,
+    pause_authority,
+    pause_expiry_slot,
+    next_proposal_id,
+    governance,
+    governance_only,
+    tenants,
+    pending_owner,
+    active_call,
+    audit_tree,
+    dispatch_limits,
+    routing_digest,
+    authority_delay_slots,
+    owner_effective_slot,
+    pending_change,
+    payload_specs,
+    config_version,
+    relay_reimbursement,
+    return_overflow,
+    vanity_nonce,
+    has_council,
+    tx_guard,
+    dependencies,
+    authorizer,
+    tags,
+    paused_tags,
+    oracle_guards,
+    unpause_quorum,
+    pause_windows,
+    health_registry,
+    selector_index,
+    break_glass,
+    circuit_breakers,
+    creator,
+);
+
+/// Refuse state data in another layout than this build's
+fn check_layout(key: &Pubkey, data: &[u8]) -> Result<(), ProgramError> {
+    if data.get(layout::STATE_VERSION_OFFSET) != Some(&layout::STATE_LAYOUT_VERSION) {
+        msg!("Error: {} is in an older state layout; run migrate_state", key);
+        return Err(DiamondError::StateLayoutOutdated.into());
+    }
+    Ok(())
+}
+
+/// Diamond state as dispatch reads it: everything but the routing tables
+///
+/// Dispatch resolves its selector through the fixed-offset index (see
+/// `diamond_core::routing`) and checks nothing in the selector, alias,
+/// admin and module tables, which make up most of the state. `load` skips
+/// them by their lengths and decodes only the fields after them, so the
+/// tables read as empty. `save` writes those fields back in place and
+/// leaves the header and tables as they are.
+pub struct DispatchState {
+    state: DiamondState,
+    tail_offset: usize,
+}
+
+impl DispatchState {
+    pub fn load(program_id: &Pubkey, account: &AccountInfo) -> Result<Self, ProgramError> {
+        if account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let data = account.try_borrow_data()?;
+        check_layout(account.key, &data)?;
+        let dispatch_state = Self::read(&data).ok_or(ProgramError::InvalidAccountData)?;
+        dispatch_state.check_address(program_id, account.key)?;
+        Ok(dispatch_state)
+    }
+    
+    /// Decode from state account data
+    pub fn read(data: &[u8]) -> Option<Self> {
+        let header = routing::state_header(data)?;
+        let tail_offset = routing::tables_end(data)?;
+        let mut head = DiamondState::new(Pubkey::new_from_array(header.owner), 0);
+        head.dispatch_paused = header.dispatch_paused;
+        head.selector_width = header.selector_width;
+        let state = DiamondState::deserialize_tail(head, &mut data.get(tail_offset..)?).ok()?;
+        Some(Self { state, tail_offset })
+    }
+    
+    /// Write the fields after the routing tables back into state account data
+    pub fn write(&self, data: &mut [u8]) -> ProgramResult {
+        let tail = data.get_mut(self.tail_offset..).ok_or(ProgramError::AccountDataTooSmall)?;
+        self.state.serialize_tail(&mut &mut tail[..]).map_err(|_| ProgramError::AccountDataTooSmall)
+    }
+    
+    pub fn save(&self, account: &AccountInfo) -> ProgramResult {
+        self.write(&mut account.try_borrow_mut_data()?)
+    }
+}
+
+impl Deref for DispatchState {
+    type Target = DiamondState;
+    
+    fn deref(&self) -> &DiamondState {
+        &self.state
+    }
+}
+
+impl DerefMut for DispatchState {
+    fn deref_mut(&mut self) -> &mut DiamondState {
+        &mut self.state
+    }
 }
 
 impl DiamondState {
-    pub const MAX_ADMINS: usize = 10;
-    pub const MAX_MODULES: usize = 20;
-    pub const MAX_SELECTORS: usize = 50;
+    pub const MAX_ADMINS: usize = capacity::MAX_ADMINS;
+    pub const MAX_MODULES: usize = capacity::MAX_MODULES;
+    pub const MAX_SELECTORS: usize = capacity::MAX_SELECTORS;
+    pub const MAX_ALIASES: usize = capacity::MAX_ALIASES;
+    pub const MAX_PAYLOAD_SPECS: usize = capacity::MAX_PAYLOAD_SPECS;
+    pub const MAX_TENANTS: usize = capacity::MAX_TENANTS;
+    pub const MAX_DEPENDENCIES: usize = capacity::MAX_DEPENDENCIES;
+    pub const MAX_TAGS: usize = capacity::MAX_SELECTOR_TAGS;
+    pub const MAX_PAUSED_TAGS: usize = capacity::MAX_PAUSED_TAGS;
+    pub const MAX_ORACLE_GUARDS: usize = capacity::MAX_ORACLE_GUARDS;
+    pub const MAX_PAUSE_WINDOWS: usize = capacity::MAX_PAUSE_WINDOWS;
+    pub const MAX_CIRCUIT_BREAKERS: usize = capacity::MAX_CIRCUIT_BREAKERS;
     
     pub const SPACE: usize = 
         8 +  // discriminator
         32 + // owner
-        4 + (Self::MAX_ADMINS * 32) + // admins vec
-        4 + (Self::MAX_MODULES * 67) + // modules vec (32 name + 32 address + 2 version + 1 is_active)
-        4 + (Self::MAX_SELECTORS * 101) + // selectors vec (4 selector + 32 module + 64 function_name + 1 is_immutable)
+        1 +  // layout version
+        1 +  // dispatch_paused
+        1 +  // selector_width
+        4 + (Self::MAX_SELECTORS * layout::SELECTOR_INDEX_ENTRY_LEN) + // selector index
+        4 + (Self::MAX_SELECTORS * layout::SELECTOR_MAPPING_LEN) + // selectors vec
+        4 + (Self::MAX_ALIASES * layout::SELECTOR_ALIAS_LEN) + // aliases vec
+        4 + (Self::MAX_ADMINS * layout::ADMIN_LEN) + // admins vec
+        4 + (Self::MAX_MODULES * layout::MODULE_META_LEN) + // modules vec
         1 +  // bump
-        1 +  // is_paused
-        32;  // pause_authority
+        1 +  // cut_paused
+        32 + // pause_authority
+        9 +  // pause_expiry_slot (Option<u64>)
+        8 +  // next_proposal_id
+        33 + // governance (Option<Pubkey>)
+        1 +  // governance_only
+        4 + (Self::MAX_TENANTS * layout::TENANT_LEN) + // tenants vec
+        33 + // pending_owner (Option<Pubkey>)
+        1 + layout::ACTIVE_CALL_LEN + // active_call (Option<ActiveCall>)
+        33 + // audit_tree (Option<Pubkey>)
+        layout::DISPATCH_LIMITS_LEN + // dispatch_limits
+        1 +  // routing_digest
+        8 +  // authority_delay_slots
+        8 +  // owner_effective_slot
+        43 + // pending_change (Option: 34 change + 8 effective_slot)
+        4 + (Self::MAX_PAYLOAD_SPECS * PayloadSpec::ENCODED_LEN) + // payload_specs vec
+        8 +  // config_version
+        8 +  // relay_reimbursement
+        1 +  // return_overflow
+        9 +  // vanity_nonce (Option<u64>)
+        1 +  // has_council
+        TxGuard::MAX_ENCODED_LEN + // tx_guard (denylist vec + selectors vec)
+        4 + (Self::MAX_DEPENDENCIES * FacetDependency::ENCODED_LEN) + // dependencies vec
+        1 + Authorizer::MAX_ENCODED_LEN + // authorizer (Option<Authorizer>)
+        4 + (Self::MAX_TAGS * layout::SELECTOR_TAG_LEN) + // tags vec
+        4 + (Self::MAX_PAUSED_TAGS * layout::TAG_LEN) + // paused_tags vec
+        4 + (Self::MAX_ORACLE_GUARDS * OracleGuard::ENCODED_LEN) + // oracle_guards vec
+        1 +  // unpause_quorum
+        4 + (Self::MAX_PAUSE_WINDOWS * PauseWindow::ENCODED_LEN) + // pause_windows vec
+        1 + HealthRegistry::ENCODED_LEN + // health_registry (Option<HealthRegistry>)
+        1 +  // selector_index
+        1 + BreakGlass::ENCODED_LEN + // break_glass (Option<BreakGlass>)
+        4 + (Self::MAX_CIRCUIT_BREAKERS * CircuitBreaker::ENCODED_LEN) + // circuit_breakers vec
+        32;  // creator
     
     pub fn new(owner: Pubkey, bump: u8) -> Self {
         Self {
             owner,
+            dispatch_paused: false,
+            selector_width: DEFAULT_SELECTOR_WIDTH,
+            selectors: Vec::new(),
+            aliases: Vec::new(),
             admins: Vec::new(),
             active_modules: Vec::new(),
-            selectors: Vec::new(),
             bump,
-            is_paused: false,
+            cut_paused: false,
             pause_authority: owner,
+            pause_expiry_slot: None,
+            next_proposal_id: 0,
+            governance: None,
+            governance_only: false,
+            tenants: Vec::new(),
+            pending_owner: None,
+            active_call: None,
+            audit_tree: None,
+            dispatch_limits: DispatchLimits::default(),
+            routing_digest: false,
+            authority_delay_slots: DEFAULT_AUTHORITY_DELAY_SLOTS,
+            owner_effective_slot: 0,
+            pending_change: None,
+            payload_specs: Vec::new(),
+            config_version: 0,
+            relay_reimbursement: 0,
+            return_overflow: ReturnOverflowPolicy::default(),
+            vanity_nonce: None,
+            has_council: false,
+            tx_guard: TxGuard::default(),
+            dependencies: Vec::new(),
+            authorizer: None,
+            tags: Vec::new(),
+            paused_tags: Vec::new(),
+            oracle_guards: Vec::new(),
+            unpause_quorum: 0,
+            pause_windows: Vec::new(),
+            health_registry: None,
+            selector_index: false,
+            break_glass: None,
+            circuit_breakers: Vec::new(),
+            creator: owner,
         }
     }
     
-    pub fn get_module_by_selector(&self, selector: [u8; 4]) -> Option<Pubkey> {
-        self.selectors
-            .iter()
-            .find(|s| s.selector == selector)
-            .map(|s| s.module)
+    /// Load state from an account owned by this program at the address its
+    /// seeds derive
+    pub fn load(program_id: &Pubkey, account: &AccountInfo) -> Result<Self, ProgramError> {
+        if account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        // The account is allocated at SPACE, so trailing bytes are expected
+        let data = account.try_borrow_data()?;
+        check_layout(account.key, &data)?;
+        let state = Self::deserialize(&mut &data[..]).map_err(|_| ProgramError::InvalidAccountData)?;
+        state.check_address(program_id, account.key)?;
+        Ok(state)
     }
     
-    pub fn is_owner(&self, pubkey: &Pubkey) -> bool {
-        &self.owner == pubkey
+    /// Check `key` is the PDA of this state's creator, vanity nonce and bump
+    pub fn check_address(&self, program_id: &Pubkey, key: &Pubkey) -> ProgramResult {
+        let nonce = self.vanity_nonce.map(u64::to_le_bytes);
+        let bump = [self.bump];
+        let mut seeds = diamond_seeds(&self.creator, nonce.as_ref());
+        seeds.push(&bump);
+        if Pubkey::create_program_address(&seeds, program_id).as_ref() != Ok(key) {
+            msg!("Error: {} is not the diamond state address of {}", key, self.creator);
+            return Err(ProgramError::InvalidSeeds);
+        }
+        Ok(())
     }
-    
-    pub fn is_admin(&self, pubkey: &Pubkey) -> bool {
-        self.admins.contains(pubkey)
+
+    /// Write state back into its account
+    pub fn save(&self, account: &AccountInfo) -> ProgramResult {
+        let mut data = account.try_borrow_mut_data()?;
+        self.serialize(&mut &mut data[..])
+            .map_err(|_| ProgramError::AccountDataTooSmall)
     }
-    
-    pub fn has_authority(&self, pubkey: &Pubkey) -> bool {
-        self.is_owner(pubkey) || self.is_admin(pubkey)
+
+    pub fn get_module_by_selector(&self, selector: impl Into<Selector>) -> Option<Pubkey> {
+        self.get_mapping(selector).map(|s| s.module)
     }
-}
 
-/// Initialize diamond state
-pub fn initialize(
-    program_id: &Pubkey,
-    accounts: &[AccountInfo],
-    data: &[u8],
-) -> ProgramResult {
-    let account_iter = &mut accounts.iter();
-    let diamond_state_account = next_account_info(account_iter)?;
-    let owner_account = next_account_info(account_iter)?;
-    let payer = next_account_info(account_iter)?;
-    let system_program_account = next_account_info(account_iter)?;
-    
-    // Validate
-    if !owner_account.is_signer {
-        return Err(ProgramError::MissingRequiredSignature);
+    pub fn get_mapping(&self, selector: impl Into<Selector>) -> Option<&SelectorMapping> {
+        let selector = selector.into();
+        self.selectors.iter().find(|s| s.selector == selector)
     }
-    if !payer.is_signer {
-        return Err(ProgramError::MissingRequiredSignature);
+
+    /// Canonical selector `alias` routes to, if it is an alias
+    pub fn canonical_selector(&self, alias: impl Into<Selector>) -> Option<Selector> {
+        let alias = alias.into();
+        self.aliases.iter().find(|a| a.alias == alias).map(|a| a.canonical)
     }
-    if system_program_account.key != &system_program::id() {
-        return Err(ProgramError::IncorrectProgramId);
+    
+    /// Mapping serving `selector`, following an alias one hop
+    pub fn resolve_mapping(&self, selector: impl Into<Selector>) -> Option<&SelectorMapping> {
+        let selector = selector.into();
+        self.get_mapping(selector)
+            .or_else(|| self.get_mapping(self.canonical_selector(selector)?))
     }
     
-    // Parse data
-    #[derive(BorshDeserialize)]
-    struct InitData {
-        owner: Pubkey,
-        bump: u8,
+    /// Aliases routed to `canonical`
+    pub fn aliases_of(&self, canonical: impl Into<Selector>) -> Vec<Selector> {
+        let canonical = canonical.into();
+        self.aliases.iter().filter(|a| a.canonical == canonical).map(|a| a.alias).collect()
     }
     
-    let init_data = InitData::try_from_slice(data)?;
+    /// Whether `selector` is taken by a mapping or an alias
+    pub fn is_registered(&self, selector: impl Into<Selector>) -> bool {
+        let selector = selector.into();
+        self.get_mapping(selector).is_some() || self.canonical_selector(selector).is_some()
+    }
     
-    // Verify PDA
-    let (pda, expected_bump) = Pubkey::find_program_address(
-        &[b"diamond_state", init_data.owner.as_ref()],
-        program_id,
-    );
+    pub fn payload_spec(&self, selector: impl Into<Selector>) -> Option<&PayloadSpec> {
+        let selector = selector.into();
+        self.payload_specs.iter().find(|s| s.selector == selector)
+    }
     
-    if diamond_state_account.key != &pda {
-        return Err(ProgramError::InvalidSeeds);
+    /// Version of a routed module
+    pub fn module_version(&self, module: &Pubkey) -> Option<Semver> {
+        self.active_modules.iter().find(|m| &m.address == module && m.is_active).map(|m| m.version)
     }
-    if init_data.bump != expected_bump {
+    
+    /// Version declared for `module` ahead of its first cut
+    pub fn declared_version(&self, module: &Pubkey) -> Option<Semver> {
+        self.active_modules.iter().find(|m| &m.address == module && !m.is_active).map(|m| m.version)
+    }
+    
+    /// Version `module` has or would have once routed
+    pub fn effective_version(&self, module: &Pubkey) -> Semver {
+        self.module_version(module)
+            .or_else(|| self.declared_version(module))
+            .unwrap_or(Semver::INITIAL)
+    }
+    
+    /// Declare `module`'s version
+    ///
+    /// A routed facet may move within its major version (an in-place
+    /// upgrade); a new major is deployed at a new address and replaced in
+    /// through a proposal. A facet not routed yet is kept as an inactive
+    /// module entry until its first cut. The history of declarations lives
+    /// in the facet's record (`diamond_record`).
+    pub fn set_module_version(&mut self, module: Pubkey, version: Semver) -> Result<(), DiamondError> {
+        let full = self.active_modules.len() >= Self::MAX_MODULES;
+        match self.active_modules.iter_mut().find(|m| m.address == module) {
+            Some(meta) if meta.is_active && meta.version.major != version.major => {
+                msg!("Error: {} is routed at {}; {} needs a new deployment", module, meta.version, version);
+                return Err(DiamondError::MajorUpgradeNeedsGovernance);
+            }
+            Some(meta) => meta.version = version,
+            None if full => return Err(DiamondError::ModuleCapacityExceeded),
+            None => self.active_modules.push(ModuleMeta { is_active: false, ..ModuleMeta::new("", module, version) }),
+        }
+        Ok(())
+    }
+    
+    /// Refuse a direct replacement from `from` to `to` across major versions
+    pub fn check_upgrade(&self, from: &Pubkey, to: &Pubkey) -> Result<(), DiamondError> {
+        let (old, new) = (self.effective_version(from), self.effective_version(to));
+        if old.major != new.major {
+            msg!("Error: {} ({}) -> {} ({}) changes the major version", from, old, to, new);
+            return Err(DiamondError::MajorUpgradeNeedsGovernance);
+        }
+        Ok(())
+    }
+    
+    /// Whether `module` serves at least one routed selector
+    pub fn is_routed(&self, module: &Pubkey) -> bool {
+        self.selectors.iter().any(|s| &s.module == module)
+    }
+    
+    /// Whether `namespace` has a routed selector served at a version
+    /// satisfying `min_version`
+    pub fn provides(&self, namespace: &[u8; 8], min_version: Semver) -> bool {
+        self.selectors.iter().any(|s| {
+            &s.namespace == namespace && self.module_version(&s.module).is_some_and(|v| v.satisfies(&min_version))
+        })
+    }
+    
+    /// Dependencies of routed facets that nothing routed satisfies
+    pub fn unmet_dependencies(&self) -> impl Iterator<Item = &FacetDependency> {
+        self.dependencies
+            .iter()
+            .filter(|d| self.is_routed(&d.module) && !self.provides(&d.namespace, d.min_version))
+    }
+    
+    /// Replace the dependencies `module` declares (an empty list clears them)
+    ///
+    /// A facet that is already routed must have every new dependency met.
+    pub fn set_dependencies(
+        &mut self,
+        module: Pubkey,
+        dependencies: &[([u8; 8], Semver)],
+    ) -> Result<(), DiamondError> {
+        let kept = self.dependencies.iter().filter(|d| d.module != module).count();
+        if kept + dependencies.len() > Self::MAX_DEPENDENCIES {
+            return Err(DiamondError::InvalidConfigEntry);
+        }
+        if self.is_routed(&module) {
+            if let Some((namespace, version)) = dependencies.iter().find(|(ns, v)| !self.provides(ns, *v)) {
+                msg!("Error: {} needs {} at version {} or later", module, fixed_str(namespace), version);
+                return Err(DiamondError::DependencyMissing);
+            }
+        }
+        self.dependencies.retain(|d| d.module != module);
+        self.dependencies.extend(
+            dependencies
+                .iter()
+                .map(|&(namespace, min_version)| FacetDependency { module, namespace, min_version }),
+        );
+        Ok(())
+    }
+    
+    /// Tags on `selector`, in the order they were set
+    pub fn tags_of(&self, selector: impl Into<Selector>) -> Vec<[u8; 8]> {
+        let selector = selector.into();
+        self.tags.iter().filter(|t| t.selector == selector).map(|t| t.tag).collect()
+    }
+    
+    /// Selectors carrying `tag`
+    pub fn tagged(&self, tag: &[u8; 8]) -> Vec<Selector> {
+        self.tags.iter().filter(|t| &t.tag == tag).map(|t| t.selector).collect()
+    }
+    
+    /// Replace the tags on `selector` (an empty list clears them)
+    ///
+    /// The selector need not be routed yet, so a deployment can tag selectors
+    /// ahead of the cut that registers them.
+    pub fn set_tags(&mut self, selector: Selector, tags: &[[u8; 8]]) -> Result<(), DiamondError> {
+        if tags.len() > capacity::MAX_TAGS_PER_SELECTOR
+            || tags.iter().enumerate().any(|(i, t)| *t == [0; 8] || tags[..i].contains(t))
+        {
+            return Err(DiamondError::InvalidConfigEntry);
+        }
+        let kept = self.tags.iter().filter(|t| t.selector != selector).count();
+        if kept + tags.len() > Self::MAX_TAGS {
+            return Err(DiamondError::SelectorCapacityExceeded);
+        }
+        self.tags.retain(|t| t.selector != selector);
+        self.tags.extend(tags.iter().map(|&tag| SelectorTag { tag, selector }));
+        Ok(())
+    }
+    
+    /// Pause or resume every selector carrying `tag`; returns whether
+    /// anything changed
+    pub fn set_tag_paused(&mut self, tag: [u8; 8], paused: bool) -> Result<bool, DiamondError> {
+        let index = self.paused_tags.iter().position(|t| *t == tag);
+        match (index, paused) {
+            (Some(i), false) => {
+                self.paused_tags.remove(i);
+                Ok(true)
+            }
+            (None, true) => {
+                if self.paused_tags.len() >= Self::MAX_PAUSED_TAGS {
+                    return Err(DiamondError::InvalidConfigEntry);
+                }
+                self.paused_tags.push(tag);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+    
+    /// Paused tag that stops `selector` from dispatching, if any
+    pub fn paused_tag_of(&self, selector: &Selector) -> Option<[u8; 8]> {
+        self.tags
+            .iter()
+            .find(|t| &t.selector == selector && self.paused_tags.contains(&t.tag))
+            .map(|t| t.tag)
+    }
+    
+    pub fn oracle_guard(&self, selector: &Selector) -> Option<&OracleGuard> {
+        self.oracle_guards.iter().find(|g| &g.selector == selector)
+    }
+    
+    /// The breaker that tripped on `selector`, if any
+    pub fn tripped_breaker(&self, selector: &Selector) -> Option<&CircuitBreaker> {
+        self.circuit_breakers.iter().find(|b| &b.selector == selector && b.tripped_slot.is_some())
+    }
+    
+    /// Declare (or, with an all-zero spec, drop) a registered selector's
+    /// payload spec, returning the previous one
+    pub fn set_payload_spec(&mut self, spec: PayloadSpec) -> Result<Option<PayloadSpec>, DiamondError> {
+        if self.get_mapping(spec.selector).is_none() {
+            return Err(DiamondError::ModuleNotFound);
+        }
+        if spec.max_len != 0 && spec.min_len > spec.max_len {
+            return Err(DiamondError::InvalidCut);
+        }
+        let index = self.payload_specs.iter().position(|s| s.selector == spec.selector);
+        let old = index.map(|i| self.payload_specs[i]);
+        let unconstrained = spec.min_len == 0 && spec.max_len == 0 && spec.schema_hash == [0; 8];
+        match (index, unconstrained) {
+            (Some(i), true) => {
+                self.payload_specs.remove(i);
+            }
+            (Some(i), false) => self.payload_specs[i] = spec,
+            (None, true) => {}
+            (None, false) => {
+                if self.payload_specs.len() >= Self::MAX_PAYLOAD_SPECS {
+                    return Err(DiamondError::SelectorCapacityExceeded);
+                }
+                self.payload_specs.push(spec);
+            }
+        }
+        Ok(old)
+    }
+    
+    /// Check a call's argument bytes (and the schema it claims) against the
+    /// selector's spec; selectors without one accept anything
+    pub fn check_payload(
+        &self,
+        selector: Selector,
+        args_len: usize,
+        claimed_schema: Option<&[u8; 8]>,
+    ) -> Result<(), DiamondError> {
+        let spec = match self.payload_spec(selector) {
+            Some(spec) => spec,
+            None => return Ok(()),
+        };
+        if !spec.accepts_len(args_len) {
+            msg!("Error: {} argument bytes outside [{}, {}] for {:?}", args_len, spec.min_len, spec.max_len, selector);
+            return Err(DiamondError::InvalidPayload);
+        }
+        if let Some(claimed) = claimed_schema {
+            if !spec.accepts_schema(claimed) {
+                msg!("Error: Schema {:02x?} does not match {:02x?} for {:?}", claimed, spec.schema_hash, selector);
+                return Err(DiamondError::InvalidPayload);
+            }
+        }
+        Ok(())
+    }
+    
+    /// Resolve `namespace::function_name` (or a bare global `function_name`)
+    ///
+    /// Several mappings may answer one name; the winner never depends on
+    /// insertion order:
+    ///
+    /// 1. Only an exact namespace match answers: `ns::name` never resolves
+    ///    to the global `name`, and a bare name sees the global namespace
+    ///    alone.
+    /// 2. Within a namespace, an immutable mapping beats a mutable one.
+    /// 3. Remaining ties go to the lowest selector.
+    pub fn get_mapping_by_name(&self, qualified_name: &str) -> Option<&SelectorMapping> {
+        let (namespace, function_name) = match qualified_name.split_once("::") {
+            Some((ns, name)) => (ns, name),
+            None => ("", qualified_name),
+        };
+        self.selectors
+            .iter()
+            .filter(|s| s.namespace_as_str() == namespace && s.function_name_as_str() == function_name)
+            .min_by_key(|s| (!s.is_immutable, s.selector.0))
+    }
+    
+    /// Immutable mapping other than `selector` already named
+    /// `namespace::function_name`
+    ///
+    /// Such a name is settled for good: a mapping taking it would never win
+    /// resolution if mutable, and would make the immutable one ambiguous if
+    /// immutable, so cuts are refused with `ImmutableNameConflict`. A mutable
+    /// holder doesn't block the name; the precedence in
+    /// `get_mapping_by_name` decides between them.
+    pub fn immutable_name_holder(
+        &self,
+        namespace: &[u8; 8],
+        function_name: &str,
+        selector: impl Into<Selector>,
+    ) -> Option<&SelectorMapping> {
+        let selector = selector.into();
+        if function_name.is_empty() {
+            return None;
+        }
+        self.selectors.iter().find(|s| {
+            s.is_immutable && s.selector != selector && &s.namespace == namespace && s.function_name_as_str() == function_name
+        })
+    }
+    
+    /// Owner-level authority; in governance-only mode this is the governance key alone
+    ///
+    /// Any key can own a diamond, including a PDA: its program signs for it
+    /// with `invoke_signed` when calling the router, which is all `is_signer`
+    /// checks require.
+    pub fn is_owner(&self, pubkey: &Pubkey) -> bool {
+        if self.governance_only {
+            return self.governance.as_ref() == Some(pubkey);
+        }
+        &self.owner == pubkey
+    }
+    
+    /// Admins carry no authority once the diamond is governance-only
+    pub fn is_admin(&self, pubkey: &Pubkey) -> bool {
+        !self.governance_only && self.admins.contains(pubkey)
+    }
+    
+    pub fn has_authority(&self, pubkey: &Pubkey) -> bool {
+        self.is_owner(pubkey) || self.is_admin(pubkey)
+    }
+
+    /// The owner and the governance key may have the rent vault pay for
+    /// accounts they create (see `diamond_vault::create_pda`)
+    pub fn may_spend_rent_vault(&self, pubkey: &Pubkey) -> bool {
+        self.is_owner(pubkey) || self.governance.as_ref() == Some(pubkey)
+    }
+    
+    /// Owner, admins, and the dedicated pause authority may pause/unpause
+    ///
+    /// Under a council only the pause authority keeps this (as a guardian);
+    /// everyone else pauses by motion.
+    pub fn can_pause(&self, pubkey: &Pubkey) -> bool {
+        if self.has_council {
+            return &self.pause_authority == pubkey;
+        }
+        if self.governance_only {
+            return self.is_owner(pubkey);
+        }
+        self.has_authority(pubkey) || &self.pause_authority == pubkey
+    }
+    
+    /// Owner-only authority changes: under a council they need a motion
+    pub fn check_authority_change(&self, authority: &Pubkey) -> Result<(), DiamondError> {
+        if self.has_council {
+            msg!("Error: Authority changes go through council motions");
+            return Err(DiamondError::CouncilApprovalRequired);
+        }
+        if !self.is_owner(authority) {
+            return Err(DiamondError::UnauthorizedAccess);
+        }
+        Ok(())
+    }
+    
+    /// Irreversibly hand all authority to the configured governance key
+    pub fn enable_governance_only(&mut self) -> Result<Pubkey, DiamondError> {
+        let governance = self.governance.ok_or(DiamondError::GovernanceNotConfigured)?;
+        self.governance_only = true;
+        Ok(governance)
+    }
+    
+    /// Nominate (or clear) the next owner, returning the slot it may accept at
+    pub fn nominate_owner(&mut self, pending_owner: Option<Pubkey>, slot: u64) -> u64 {
+        self.pending_owner = pending_owner;
+        self.owner_effective_slot = slot.saturating_add(self.authority_delay_slots);
+        self.owner_effective_slot
+    }
+    
+    /// Complete a pending ownership transfer, returning the previous owner
+    ///
+    /// The nominee can accept once the announcement window has passed. A
+    /// pause authority still pointing at the old owner moves with it.
+    pub fn accept_ownership(&mut self, new_owner: &Pubkey, slot: u64) -> Result<Pubkey, DiamondError> {
+        if self.pending_owner.as_ref() != Some(new_owner) {
+            return Err(DiamondError::UnauthorizedAccess);
+        }
+        if slot < self.owner_effective_slot {
+            msg!("Error: Ownership transfer effective at slot {}", self.owner_effective_slot);
+            return Err(DiamondError::AuthorityChangeNotReady);
+        }
+        let old = self.owner;
+        if self.pause_authority == old {
+            self.pause_authority = *new_owner;
+        }
+        self.owner = *new_owner;
+        self.pending_owner = None;
+        Ok(old)
+    }
+    
+    /// Announce a governance or delay change, replacing any pending one
+    pub fn announce_change(&mut self, change: AuthorityChange, slot: u64) -> PendingAuthorityChange {
+        let pending = PendingAuthorityChange {
+            change,
+            effective_slot: slot.saturating_add(self.authority_delay_slots),
+        };
+        self.pending_change = Some(pending);
+        pending
+    }
+    
+    /// Set the authority delay: raising it (up to `MAX_AUTHORITY_DELAY_SLOTS`)
+    /// applies now, lowering it is announced like any other change (returned
+    /// when pending)
+    pub fn set_authority_delay(&mut self, delay_slots: u64, slot: u64) -> Result<Option<PendingAuthorityChange>, DiamondError> {
+        if delay_slots > MAX_AUTHORITY_DELAY_SLOTS {
+            msg!("Error: Authority delay {} is above the {}-slot maximum", delay_slots, MAX_AUTHORITY_DELAY_SLOTS);
+            return Err(DiamondError::AuthorityDelayTooLong);
+        }
+        if delay_slots >= self.authority_delay_slots {
+            self.authority_delay_slots = delay_slots;
+            return Ok(None);
+        }
+        Ok(Some(self.announce_change(AuthorityChange::Delay(delay_slots), slot)))
+    }
+    
+    /// Apply the pending change once its window has passed
+    pub fn apply_pending_change(&mut self, slot: u64) -> Result<AuthorityChange, DiamondError> {
+        let pending = match self.pending_change {
+            Some(pending) if slot >= pending.effective_slot => pending,
+            _ => return Err(DiamondError::AuthorityChangeNotReady),
+        };
+        match pending.change {
+            AuthorityChange::Governance(None) if self.governance_only => {
+                return Err(DiamondError::GovernanceNotConfigured);
+            }
+            AuthorityChange::Governance(None) if self.unpause_approvers().len() < self.unpause_quorum as usize => {
+                msg!("Error: Without governance the unpause quorum of {} is unreachable", self.unpause_quorum);
+                return Err(DiamondError::InsufficientApprovals);
+            }
+            AuthorityChange::Governance(governance) => self.governance = governance,
+            AuthorityChange::Delay(delay_slots) => self.authority_delay_slots = delay_slots,
+            AuthorityChange::PauseAuthority(guardian) => self.pause_authority = guardian,
+            AuthorityChange::Owner(_) => return Err(DiamondError::AuthorityChangeNotReady),
+        }
+        self.pending_change = None;
+        Ok(pending.change)
+    }
+    
+    /// The guardian (pause authority), governance and the owner may veto
+    pub fn can_veto(&self, pubkey: &Pubkey) -> bool {
+        self.is_owner(pubkey) || &self.pause_authority == pubkey || self.governance.as_ref() == Some(pubkey)
+    }
+    
+    /// The announced change a veto of `target` would cancel
+    pub fn pending(&self, target: VetoTarget) -> Option<PendingAuthorityChange> {
+        match target {
+            VetoTarget::Ownership => self.pending_owner.map(|nominee| PendingAuthorityChange {
+                change: AuthorityChange::Owner(nominee),
+                effective_slot: self.owner_effective_slot,
+            }),
+            VetoTarget::Config => self.pending_change,
+        }
+    }
+    
+    /// Check an off-chain veto is a vetoer's, for this diamond, unexpired
+    /// and about the change pending now
+    pub fn check_signed_veto(&self, veto: &SignedVeto, diamond: &Pubkey, slot: u64) -> Result<(), DiamondError> {
+        let payload = veto.signing_payload();
+        if !payload.is_well_formed() || !payload.applies(&diamond.to_bytes(), action::GUARDIAN_VETO, slot) {
+            msg!("Error: Signed veto is malformed, for another diamond or expired");
+            return Err(DiamondError::UnauthorizedAccess);
+        }
+        if !self.can_veto(&veto.guardian) {
+            return Err(DiamondError::UnauthorizedAccess);
+        }
+        let signed = PendingAuthorityChange { change: veto.change, effective_slot: veto.effective_slot };
+        if self.pending(veto.target) != Some(signed) {
+            msg!("Error: Signed veto is for a change no longer pending");
+            return Err(DiamondError::AuthorityChangeNotReady);
+        }
+        Ok(())
+    }
+    
+    /// Cancel an announced change, returning what was cancelled
+    pub fn veto(&mut self, target: VetoTarget) -> Result<AuthorityChange, DiamondError> {
+        match target {
+            VetoTarget::Ownership => self.pending_owner.take().map(AuthorityChange::Owner),
+            VetoTarget::Config => self.pending_change.take().map(|pending| pending.change),
+        }
+        .ok_or(DiamondError::AuthorityChangeNotReady)
+    }
+    
+    /// Pause or unpause both dispatch and cuts; a pause may carry an expiry
+    /// slot after which it lapses
+    pub fn set_pause(&mut self, paused: bool, expiry_slot: Option<u64>) {
+        self.set_pause_scopes(paused, paused, expiry_slot);
+    }
+    
+    /// Pause dispatch and cuts independently; the expiry covers both scopes
+    pub fn set_pause_scopes(&mut self, dispatch_paused: bool, cut_paused: bool, expiry_slot: Option<u64>) {
+        self.dispatch_paused = dispatch_paused;
+        self.cut_paused = cut_paused;
+        self.pause_expiry_slot = if dispatch_paused || cut_paused { expiry_slot } else { None };
+    }
+
+    /// Whether moving to these pause scopes lifts a paused scope, or brings
+    /// forward the slot at which a standing pause lapses
+    pub fn relaxes_pause(&self, dispatch_paused: bool, cut_paused: bool, expiry_slot: Option<u64>) -> bool {
+        let lifts = (self.dispatch_paused && !dispatch_paused) || (self.cut_paused && !cut_paused);
+        let still_paused = dispatch_paused || cut_paused;
+        let lapses = |expiry: Option<u64>| expiry.unwrap_or(u64::MAX);
+        let shortens = (self.dispatch_paused || self.cut_paused)
+            && still_paused
+            && lapses(expiry_slot) < lapses(self.pause_expiry_slot);
+        lifts || shortens
+    }
+    
+    /// Keys whose signatures count towards the unpause quorum: the owner and
+    /// the admins (both governance alone in governance-only mode)
+    pub fn unpause_approvers(&self) -> Vec<Pubkey> {
+        let mut approvers: Vec<Pubkey> = Vec::new();
+        let owner = if self.governance_only { self.governance } else { Some(self.owner) };
+        for key in owner.iter().chain(self.admins.iter()) {
+            if self.has_authority(key) && !approvers.contains(key) {
+                approvers.push(*key);
+            }
+        }
+        approvers
+    }
+    
+    /// Whether the unpause quorum can still be met
+    ///
+    /// Governance counts as the whole quorum, so any governance key makes it
+    /// reachable.
+    pub fn unpause_quorum_reachable(&self) -> bool {
+        self.governance.is_some() || self.unpause_approvers().len() >= self.unpause_quorum as usize
+    }
+    
+    /// Check `signers` carry the unpause quorum
+    ///
+    /// A governance signature satisfies it alone, since governance already
+    /// speaks for its own voters. Council motions never come through here:
+    /// a passed motion already carries the council's threshold.
+    pub fn check_unpause_quorum<'a>(&self, signers: impl IntoIterator<Item = &'a Pubkey>) -> Result<(), DiamondError> {
+        if self.unpause_quorum == 0 {
+            return Ok(());
+        }
+        let approvers = self.unpause_approvers();
+        let mut approved: Vec<&Pubkey> = Vec::new();
+        for signer in signers {
+            if self.governance.as_ref() == Some(signer) {
+                return Ok(());
+            }
+            if approvers.contains(signer) && !approved.contains(&signer) {
+                approved.push(signer);
+            }
+        }
+        if approved.len() < self.unpause_quorum as usize {
+            msg!("Error: Lifting the pause needs {} approvers, got {}", self.unpause_quorum, approved.len());
+            return Err(DiamondError::InsufficientApprovals);
+        }
+        Ok(())
+    }
+    
+    /// Authority delegated cut rights over `namespace`, if any
+    pub fn tenant_authority(&self, namespace: &[u8; 8]) -> Option<&Pubkey> {
+        self.tenants
+            .iter()
+            .find(|t| &t.namespace == namespace)
+            .map(|t| &t.authority)
+    }
+    
+    /// Role under which `authority` may cut `namespace`, if any
+    ///
+    /// This is the permission matrix every cut handler enforces and the
+    /// `diamond::permissions` loupe query reports. A council takes the owner's
+    /// and admins' rights; tenants keep theirs.
+    pub fn cut_role(&self, authority: &Pubkey, namespace: &[u8; 8]) -> Option<CutRole> {
+        let council = self.has_council;
+        if !council && self.is_owner(authority) {
+            Some(CutRole::Owner)
+        } else if !council && self.is_admin(authority) {
+            Some(CutRole::Admin)
+        } else if self.tenant_authority(namespace) == Some(authority) {
+            Some(CutRole::Tenant)
+        } else {
+            None
+        }
+    }
+    
+    /// Delegate (or revoke, with `None`) a namespace, returning the previous tenant
+    pub fn set_tenant(
+        &mut self,
+        namespace: [u8; 8],
+        authority: Option<Pubkey>,
+    ) -> Result<Option<Pubkey>, DiamondError> {
+        // The global namespace stays with the owner
+        if namespace == [0u8; 8] {
+            return Err(DiamondError::TenantScopeViolation);
+        }
+        let index = self.tenants.iter().position(|t| t.namespace == namespace);
+        let old = index.map(|i| self.tenants[i].authority);
+        match (index, authority) {
+            (Some(i), Some(authority)) => self.tenants[i].authority = authority,
+            (Some(i), None) => {
+                self.tenants.remove(i);
+            }
+            (None, Some(authority)) => {
+                if self.tenants.len() >= Self::MAX_TENANTS {
+                    return Err(DiamondError::TenantCapacityExceeded);
+                }
+                self.tenants.push(Tenant { namespace, authority });
+            }
+            (None, None) => {}
+        }
+        Ok(old)
+    }
+    
+    fn pause_active_at(&self, flag: bool, slot: u64) -> bool {
+        match self.pause_expiry_slot {
+            Some(expiry) => flag && slot < expiry,
+            None => flag,
+        }
+    }
+    
+    /// Whether facet dispatch is paused at `slot`, honoring any pause expiry
+    pub fn is_dispatch_paused_at(&self, slot: u64) -> bool {
+        self.pause_active_at(self.dispatch_paused, slot)
+    }
+    
+    /// Whether cuts are paused at `slot`, honoring any pause expiry
+    pub fn is_cut_paused_at(&self, slot: u64) -> bool {
+        self.pause_active_at(self.cut_paused, slot)
+    }
+    
+    /// Whether either scope is paused at `slot`
+    pub fn is_paused_at(&self, slot: u64) -> bool {
+        self.is_dispatch_paused_at(slot) || self.is_cut_paused_at(slot)
+    }
+    
+    /// Maintenance window covering `slot`, if any
+    pub fn pause_window_at(&self, slot: u64) -> Option<&PauseWindow> {
+        self.pause_windows.iter().find(|w| w.contains(slot))
+    }
+    
+    /// Record a maintenance window, dropping windows that ended before `slot`
+    pub fn schedule_pause_window(&mut self, window: PauseWindow, slot: u64) -> Result<(), DiamondError> {
+        if window.start_slot >= window.end_slot || window.end_slot <= slot {
+            msg!("Error: Window {}..{} is empty or already over", window.start_slot, window.end_slot);
+            return Err(DiamondError::InvalidPauseWindow);
+        }
+        self.prune_pause_windows(slot);
+        if self.pause_windows.contains(&window) {
+            return Ok(());
+        }
+        if self.pause_windows.len() >= Self::MAX_PAUSE_WINDOWS {
+            msg!("Error: At most {} maintenance windows can be scheduled", Self::MAX_PAUSE_WINDOWS);
+            return Err(DiamondError::InvalidPauseWindow);
+        }
+        self.pause_windows.push(window);
+        Ok(())
+    }
+    
+    /// Drop windows that ended before `slot`, returning how many
+    pub fn prune_pause_windows(&mut self, slot: u64) -> usize {
+        let before = self.pause_windows.len();
+        self.pause_windows.retain(|w| w.end_slot > slot);
+        before - self.pause_windows.len()
+    }
+    
+    /// Fail if the caller's view of the routing table is out of date
+    pub fn check_config_version(&self, expected: Option<u64>) -> Result<(), DiamondError> {
+        match expected {
+            Some(expected) if expected != self.config_version => {
+                msg!("Error: Expected config version {}, found {}", expected, self.config_version);
+                Err(DiamondError::StaleConfig)
+            }
+            _ => Ok(()),
+        }
+    }
+    
+    /// Fail if cuts are paused at `slot`
+    pub fn check_cuts_allowed(&self, slot: u64) -> Result<(), DiamondError> {
+        if self.is_cut_paused_at(slot) {
+            msg!("Error: Cuts are paused");
+            return Err(DiamondError::DiamondPaused);
+        }
+        Ok(())
+    }
+
+    /// Replace the admin set, returning (added, removed)
+    pub fn set_admins(
+        &mut self,
+        admins: Vec<Pubkey>,
+    ) -> Result<(Vec<Pubkey>, Vec<Pubkey>), DiamondError> {
+        let mut next: Vec<Pubkey> = Vec::with_capacity(admins.len());
+        for admin in admins {
+            if !next.contains(&admin) {
+                next.push(admin);
+            }
+        }
+        if next.len() > Self::MAX_ADMINS {
+            return Err(DiamondError::AdminCapacityExceeded);
+        }
+        
+        let added = next.iter().filter(|a| !self.admins.contains(a)).copied().collect();
+        let removed = self.admins.iter().filter(|a| !next.contains(a)).copied().collect();
+        self.admins = next;
+        Ok((added, removed))
+    }
+
+    /// Remove the given admins, returning the ones that were present
+    pub fn remove_admins(&mut self, admins: &[Pubkey]) -> Vec<Pubkey> {
+        let removed: Vec<Pubkey> = self.admins.iter().filter(|a| admins.contains(a)).copied().collect();
+        self.admins.retain(|a| !admins.contains(a));
+        removed
+    }
+}
+
+/// Refuse changes that leave too few approvers to ever lift a pause
+fn check_quorum_reachable(state: &DiamondState) -> Result<(), DiamondError> {
+    if !state.unpause_quorum_reachable() {
+        msg!("Error: Unpause quorum {} needs more approvers than remain", state.unpause_quorum);
+        return Err(DiamondError::InsufficientApprovals);
+    }
+    Ok(())
+}
+
+/// Apply an authority change the caller is entitled to make at `slot`
+pub fn apply_authority_action(state: &mut DiamondState, action: AuthorityAction, slot: u64) -> Result<(), DiamondError> {
+    match action {
+        AuthorityAction::AddAdmin(admin) => {
+            if state.admins.contains(&admin) {
+                return Ok(());
+            }
+            if state.admins.len() >= DiamondState::MAX_ADMINS {
+                return Err(DiamondError::AdminCapacityExceeded);
+            }
+            state.admins.push(admin);
+            msg!("Admin added: {}", admin);
+            DiamondEvent::AdminsChanged { added: vec![admin], removed: Vec::new() }.emit();
+        }
+        AuthorityAction::RemoveAdmin(admin) => {
+            let removed = state.remove_admins(&[admin]);
+            check_quorum_reachable(state)?;
+            msg!("Admins removed: {}", removed.len());
+            DiamondEvent::AdminsChanged { added: Vec::new(), removed }.emit();
+        }
+        AuthorityAction::PauseAuthority(new) => {
+            let pending = state.announce_change(AuthorityChange::PauseAuthority(new), slot);
+            msg!("Pause authority change announced: {} -> {} at slot {}", state.pause_authority, new, pending.effective_slot);
+            DiamondEvent::AuthorityChangeAnnounced { change: pending.change, effective_slot: pending.effective_slot }.emit();
+        }
+        AuthorityAction::Governance(governance) => {
+            if state.governance_only && governance.is_none() {
+                msg!("Error: Governance-only diamonds cannot clear governance");
+                return Err(DiamondError::GovernanceNotConfigured);
+            }
+            let pending = state.announce_change(AuthorityChange::Governance(governance), slot);
+            msg!("Governance change announced: {:?} -> {:?} at slot {}", state.governance, governance, pending.effective_slot);
+            DiamondEvent::AuthorityChangeAnnounced { change: pending.change, effective_slot: pending.effective_slot }.emit();
+        }
+        AuthorityAction::Delay(delay_slots) => match state.set_authority_delay(delay_slots, slot)? {
+            Some(pending) => {
+                msg!("Authority delay change to {} announced for slot {}", delay_slots, pending.effective_slot);
+                DiamondEvent::AuthorityChangeAnnounced { change: pending.change, effective_slot: pending.effective_slot }
+                    .emit();
+            }
+            None => {
+                msg!("Authority delay set: {}", delay_slots);
+                DiamondEvent::AuthorityChangeApplied { change: AuthorityChange::Delay(delay_slots) }.emit();
+            }
+        },
+        AuthorityAction::TransferOwnership(pending_owner) => {
+            let effective_slot = state.nominate_owner(pending_owner, slot);
+            msg!("Ownership transfer: {} -> {:?} (pending until slot {})", state.owner, pending_owner, effective_slot);
+            DiamondEvent::OwnershipTransferStarted { owner: state.owner, pending_owner }.emit();
+            if let Some(nominee) = pending_owner {
+                DiamondEvent::AuthorityChangeAnnounced { change: AuthorityChange::Owner(nominee), effective_slot }.emit();
+            }
+        }
+        AuthorityAction::Tenant { namespace, authority } => {
+            let old = state.set_tenant(namespace, authority)?;
+            msg!("Tenant for {}: {:?} -> {:?}", fixed_str(&namespace), old, authority);
+            DiamondEvent::TenantChanged { namespace, old, new: authority }.emit();
+        }
+        AuthorityAction::EnableGovernanceOnly => {
+            let governance = state.enable_governance_only()?;
+            msg!("Diamond is now governance-only: {}", governance);
+            DiamondEvent::GovernanceOnlyEnabled { governance }.emit();
+        }
+        AuthorityAction::UnpauseQuorum(quorum) => {
+            let old = state.unpause_quorum;
+            state.unpause_quorum = quorum;
+            check_quorum_reachable(state)?;
+            msg!("Unpause quorum set: {} -> {}", old, quorum);
+            DiamondEvent::UnpauseQuorumChanged { old, new: quorum }.emit();
+        }
+        AuthorityAction::DispatchLimits(limits) => {
+            state.dispatch_limits = limits;
+            msg!("Dispatch limits set: {:?}", limits);
+            DiamondEvent::DispatchLimitsChanged { limits }.emit();
+        }
+    }
+    Ok(())
+}
+
+/// Initialize diamond state, optionally seeding it with an initial cut list
+///
+/// Data: owner, bump, initial_cuts (Option<Vec<FacetCut>>), then an optional
+/// trailing selector width (u8; 4 when omitted, fixed for the diamond's life)
+/// optionally followed by a `VanitySeed`, whose nonce becomes a third PDA seed
+pub fn initialize(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let diamond_state_account = next_account_info(account_iter)?;
+    let owner_account = next_account_info(account_iter)?;
+    let payer = next_account_info(account_iter)?;
+    let system_program_account = next_account_info(account_iter)?;
+    
+    // Validate
+    if !owner_account.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if system_program_account.key != &system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    // Parse data
+    #[derive(BorshDeserialize)]
+    struct InitData {
+        owner: Pubkey,
+        bump: u8,
+        initial_cuts: Option<Vec<FacetCut>>,
+    }
+    
+    let mut rest = data;
+    let init_data = InitData::deserialize(&mut rest)?;
+    let (selector_width, vanity) = match rest {
+        [] => (DEFAULT_SELECTOR_WIDTH, None),
+        [width] if is_valid_width(*width) => (*width, None),
+        [width, vanity @ ..] if is_valid_width(*width) => {
+            let vanity = VanitySeed::try_from_slice(vanity).map_err(|_| ProgramError::InvalidInstructionData)?;
+            (*width, Some(vanity))
+        }
+        _ => return Err(DiamondError::InvalidSelectorWidth.into()),
+    };
+    
+    // Build the seeded state up front so a bad cut list fails before any CPI
+    let mut state = DiamondState::new(init_data.owner, init_data.bump);
+    state.selector_width = selector_width;
+    state.vanity_nonce = vanity.as_ref().map(|vanity| vanity.nonce);
+    if let Some(cuts) = &init_data.initial_cuts {
+        if cuts.iter().any(|cut| &cut.module == program_id) {
+            return Err(DiamondError::SelfDispatchForbidden.into());
+        }
+        apply_cuts(&mut state, cuts)?;
+    }
+    
+    // Verify PDA
+    let (pda, expected_bump) = diamond_address(program_id, &init_data.owner, state.vanity_nonce);
+    
+    if diamond_state_account.key != &pda {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if init_data.bump != expected_bump {
         return Err(ProgramError::InvalidSeeds);
     }
+    if let Some(vanity) = &vanity {
+        check_vanity(&pda, &vanity.prefix)?;
+    }
     
     // Create account
     let rent = Rent::get()?;
     let lamports = rent.minimum_balance(DiamondState::SPACE);
+    let nonce = state.vanity_nonce.map(u64::to_le_bytes);
+    let bump = [init_data.bump];
+    let mut signer_seeds = diamond_seeds(&init_data.owner, nonce.as_ref());
+    signer_seeds.push(&bump);
     
     invoke_signed(
         &system_instruction::create_account(
@@ -186,20 +1956,29 @@ pub fn initialize(
             program_id,
         ),
         &[payer.clone(), diamond_state_account.clone(), system_program_account.clone()],
-        &[&[b"diamond_state", init_data.owner.as_ref(), &[init_data.bump]]],
+        &[&signer_seeds],
     )?;
     
     // Initialize state
-    let state = DiamondState::new(init_data.owner, init_data.bump);
-    state.serialize(&mut &mut diamond_state_account.data.borrow_mut()[..])?;
+    state.save(diamond_state_account)?;
     
-    msg!("Diamond initialized for owner: {}", init_data.owner);
+    msg!(
+        "Diamond initialized for owner: {} with {} selectors ({}-byte)",
+        init_data.owner,
+        state.selectors.len(),
+        state.selector_width
+    );
     Ok(())
 }
 
 /// Add admin
+///
+/// Owner (or governance) only, and refused once a council exists: like every
+/// authority change it then goes through a motion. Admins approve unpauses,
+/// so a new one also needs the unpause quorum.
+/// Accounts: [diamond_state, authority, approvers...]
 pub fn add_admin(
-    _program_id: &Pubkey,
+    program_id: &Pubkey,
     accounts: &[AccountInfo],
     data: &[u8],
 ) -> ProgramResult {
@@ -213,29 +1992,54 @@ pub fn add_admin(
     
     let new_admin = Pubkey::try_from_slice(data)?;
     
-    let mut state = DiamondState::try_from_slice(&state_account.data.borrow())?;
-    
-    if !state.is_owner(authority.key) {
-        return Err(DiamondError::UnauthorizedAccess.into());
+    let mut state = DiamondState::load(program_id, state_account)?;
+    state.check_authority_change(authority.key)?;
+    if !state.admins.contains(&new_admin) {
+        state.check_unpause_quorum(accounts.iter().filter(|a| a.is_signer).map(|a| a.key))?;
     }
+    apply_authority_action(&mut state, AuthorityAction::AddAdmin(new_admin), Clock::get()?.slot)?;
+    state.save(state_account)?;
+    Ok(())
+}
+
+/// Replace the admin set atomically
+///
+/// Adding admins needs the unpause quorum, as in `add_admin`.
+/// Accounts: [diamond_state, authority, approvers...]
+pub fn set_admins(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let authority = next_account_info(account_iter)?;
     
-    if state.admins.len() >= DiamondState::MAX_ADMINS {
-        return Err(DiamondError::AdminCapacityExceeded.into());
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
     }
     
-    if !state.admins.contains(&new_admin) {
-        state.admins.push(new_admin);
+    let admins = Vec::<Pubkey>::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    state.check_authority_change(authority.key)?;
+    if admins.iter().any(|a| !state.admins.contains(a)) {
+        state.check_unpause_quorum(accounts.iter().filter(|a| a.is_signer).map(|a| a.key))?;
     }
     
-    state.serialize(&mut &mut state_account.data.borrow_mut()[..])?;
+    let (added, removed) = state.set_admins(admins)?;
+    check_quorum_reachable(&state)?;
+    state.save(state_account)?;
     
-    msg!("Admin added: {}", new_admin);
+    msg!("Admins set: {} added, {} removed", added.len(), removed.len());
+    DiamondEvent::AdminsChanged { added, removed }.emit();
     Ok(())
 }
 
-/// Pause/unpause diamond
-pub fn pause(
-    _program_id: &Pubkey,
+/// Remove several admins in one instruction
+pub fn remove_admins(
+    program_id: &Pubkey,
     accounts: &[AccountInfo],
     data: &[u8],
 ) -> ProgramResult {
@@ -247,17 +2051,1112 @@ pub fn pause(
         return Err(ProgramError::MissingRequiredSignature);
     }
     
-    let should_pause = bool::try_from_slice(data)?;
+    let admins = Vec::<Pubkey>::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
     
-    let mut state = DiamondState::try_from_slice(&state_account.data.borrow())?;
-    
-    if !state.has_authority(authority.key) {
-        return Err(DiamondError::UnauthorizedAccess.into());
-    }
+    let mut state = DiamondState::load(program_id, state_account)?;
+    state.check_authority_change(authority.key)?;
     
-    state.is_paused = should_pause;
-    state.serialize(&mut &mut state_account.data.borrow_mut()[..])?;
+    let removed = state.remove_admins(&admins);
+    check_quorum_reachable(&state)?;
+    state.save(state_account)?;
     
-    msg!("Diamond paused: {}", should_pause);
+    msg!("Admins removed: {}", removed.len());
+    DiamondEvent::AdminsChanged { added: Vec::new(), removed }.emit();
     Ok(())
 }
+
+/// Declare or drop a selector's payload spec (whoever may cut the selector)
+///
+/// Accounts: [diamond_state, authority]
+/// Data: PayloadSpec (all-zero bounds and schema drop the spec)
+///
+/// Authorized like a cut of the selector's mapping (see `cut_role`), so a
+/// council's diamond refuses it from the owner and admins.
+pub fn set_payload_spec(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let authority = next_account_info(account_iter)?;
+    
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let spec = PayloadSpec::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    let namespace = state.get_mapping(spec.selector).map(|mapping| mapping.namespace);
+    authorize_namespace(&state, authority.key, namespace.as_ref())?;
+    
+    let old = state.set_payload_spec(spec)?;
+    state.save(state_account)?;
+    
+    let new = state.payload_spec(spec.selector).copied();
+    msg!("Payload spec for {:?}: {:?} -> {:?}", spec.selector, old, new);
+    DiamondEvent::PayloadSpecChanged { selector: spec.selector, spec: new }.emit();
+    Ok(())
+}
+
+/// Announce a new pause authority (owner only; by motion under a council)
+///
+/// The guardian vetoes authority changes, so replacing it waits out the
+/// announcement window like a governance change, and the current guardian
+/// can veto it.
+pub fn set_pause_authority(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let authority = next_account_info(account_iter)?;
+    
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let new_authority = Pubkey::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    state.check_authority_change(authority.key)?;
+    apply_authority_action(&mut state, AuthorityAction::PauseAuthority(new_authority), Clock::get()?.slot)?;
+    state.save(state_account)?;
+    Ok(())
+}
+
+/// Require several approvers to lift a pause (owner only; by motion under a council)
+///
+/// Accounts: [diamond_state, owner, approvers...]
+/// Data: quorum (u8; 0 lets any pause key unpause alone)
+///
+/// Pausing stays a single signature. Lowering the quorum (or adding an
+/// admin) needs the current quorum, so one key can't drop it, or enrol keys
+/// of its own, and then unpause. The quorum must be reachable with today's
+/// owner and admins, or a governance key.
+pub fn set_unpause_quorum(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let owner = next_account_info(account_iter)?;
+    
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let quorum = u8::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    
+    state.check_authority_change(owner.key)?;
+    if quorum < state.unpause_quorum {
+        state.check_unpause_quorum(accounts.iter().filter(|a| a.is_signer).map(|a| a.key))?;
+    }
+    
+    apply_authority_action(&mut state, AuthorityAction::UnpauseQuorum(quorum), Clock::get()?.slot)?;
+    state.save(state_account)?;
+    Ok(())
+}
+
+/// Bound the accounts and payload size of facet calls (owner only; by motion under a council)
+///
+/// Accounts: [diamond_state, owner]
+/// Data: DispatchLimits
+pub fn set_dispatch_limits(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let owner = next_account_info(account_iter)?;
+    
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let limits = DispatchLimits::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    
+    state.check_authority_change(owner.key)?;
+    apply_authority_action(&mut state, AuthorityAction::DispatchLimits(limits), Clock::get()?.slot)?;
+    state.save(state_account)?;
+    Ok(())
+}
+
+/// Set or clear the governance authority (owner, or governance itself once governance-only)
+#[cfg(feature = "governance")]
+pub fn set_governance(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let authority = next_account_info(account_iter)?;
+    
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let governance = Option::<Pubkey>::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    state.check_authority_change(authority.key)?;
+    apply_authority_action(&mut state, AuthorityAction::Governance(governance), Clock::get()?.slot)?;
+    state.save(state_account)?;
+    Ok(())
+}
+
+/// Change the authority announcement window (owner only; by motion under a council)
+///
+/// Accounts: [diamond_state, owner]
+/// Data: delay_slots (u64). Raising the delay applies immediately; lowering
+/// it is announced and applied like a governance change.
+pub fn set_authority_delay(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let owner = next_account_info(account_iter)?;
+    
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let delay_slots = u64::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    state.check_authority_change(owner.key)?;
+    apply_authority_action(&mut state, AuthorityAction::Delay(delay_slots), Clock::get()?.slot)?;
+    state.save(state_account)?;
+    Ok(())
+}
+
+/// Apply an announced governance or delay change once due (anyone)
+///
+/// Accounts: [diamond_state]
+pub fn apply_authority_change(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    let (old_governance, old_guardian) = (state.governance, state.pause_authority);
+    let change = state.apply_pending_change(Clock::get()?.slot)?;
+    state.save(state_account)?;
+    
+    msg!("Authority change applied: {:?}", change);
+    match change {
+        AuthorityChange::Governance(new) => DiamondEvent::GovernanceChanged { old: old_governance, new }.emit(),
+        AuthorityChange::PauseAuthority(new) => DiamondEvent::PauseAuthorityChanged { old: old_guardian, new }.emit(),
+        _ => {}
+    }
+    DiamondEvent::AuthorityChangeApplied { change }.emit();
+    Ok(())
+}
+
+/// Cancel an announced ownership or config change
+///
+/// Accounts: [diamond_state, authority]
+/// Data: VetoTarget. The guardian (pause authority), governance or owner
+/// may veto.
+pub fn veto_authority_change(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let authority = next_account_info(account_iter)?;
+    
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let target = VetoTarget::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    
+    if !state.can_veto(authority.key) {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    
+    let change = state.veto(target)?;
+    state.save(state_account)?;
+    
+    msg!("Authority change vetoed by {}: {:?}", authority.key, change);
+    DiamondEvent::AuthorityChangeVetoed { change, by: *authority.key }.emit();
+    Ok(())
+}
+
+/// Cancel an announced change with a veto signed off-chain (anyone may submit)
+///
+/// Accounts: [diamond_state, instructions_sysvar]
+/// Data: SignedVeto
+///
+/// The instruction right before this one must be the Ed25519 program
+/// verifying the vetoer's signature over `SignedVeto::message`. Lets a
+/// guardian without SOL or a hot wallet veto through any relayer.
+pub fn veto_signed(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let instructions_sysvar = next_account_info(account_iter)?;
+    
+    let veto = SignedVeto::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    if !diamond_signing::preceding_verifies(instructions_sysvar, &veto.signing_payload())? {
+        msg!("Error: No Ed25519 verification of {}'s veto before this instruction", veto.guardian);
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    state.check_signed_veto(&veto, state_account.key, Clock::get()?.slot)?;
+    let change = state.veto(veto.target)?;
+    state.save(state_account)?;
+    
+    msg!("Authority change vetoed by {} (signed off-chain): {:?}", veto.guardian, change);
+    DiamondEvent::AuthorityChangeVetoed { change, by: veto.guardian }.emit();
+    Ok(())
+}
+
+/// Delegate cut rights over a namespace to a tenant (owner only; by motion under a council)
+///
+/// Accounts: [diamond_state, owner]
+/// Data: namespace (String), authority (Option<Pubkey>; `None` revokes)
+pub fn set_tenant(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let authority = next_account_info(account_iter)?;
+    
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    #[derive(BorshDeserialize)]
+    struct SetTenantData {
+        namespace: String,
+        authority: Option<Pubkey>,
+    }
+    
+    let tenant_data = SetTenantData::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    let namespace = namespace_bytes(&tenant_data.namespace).ok_or(DiamondError::InvalidCut)?;
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    state.check_authority_change(authority.key)?;
+    let action = AuthorityAction::Tenant {
+        namespace,
+        authority: tenant_data.authority,
+    };
+    apply_authority_action(&mut state, action, Clock::get()?.slot)?;
+    state.save(state_account)?;
+    Ok(())
+}
+
+/// Nominate a new owner, or cancel with `None` (owner only; by motion under a council)
+///
+/// Accounts: [diamond_state, owner]
+/// The nominee (wallet or program PDA) must call `accept_ownership` once the
+/// authority delay has passed; until then the guardian can veto.
+pub fn transfer_ownership(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let authority = next_account_info(account_iter)?;
+    
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let pending_owner = Option::<Pubkey>::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    state.check_authority_change(authority.key)?;
+    apply_authority_action(&mut state, AuthorityAction::TransferOwnership(pending_owner), Clock::get()?.slot)?;
+    state.save(state_account)?;
+    Ok(())
+}
+
+/// Accept a pending ownership transfer (nominee only)
+///
+/// Accounts: [diamond_state, new_owner]
+/// A PDA nominee signs through its program's `invoke_signed`.
+pub fn accept_ownership(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let new_owner = next_account_info(account_iter)?;
+    
+    if !new_owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    let old = state.accept_ownership(new_owner.key, Clock::get()?.slot)?;
+    state.save(state_account)?;
+    
+    msg!("Ownership transferred: {} -> {}", old, new_owner.key);
+    DiamondEvent::OwnershipTransferred { old, new: *new_owner.key }.emit();
+    Ok(())
+}
+
+/// One-way switch to governance-only mode (owner only; by motion under a council)
+#[cfg(feature = "governance")]
+pub fn enable_governance_only(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let authority = next_account_info(account_iter)?;
+    
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    state.check_authority_change(authority.key)?;
+    apply_authority_action(&mut state, AuthorityAction::EnableGovernanceOnly, Clock::get()?.slot)?;
+    state.save(state_account)?;
+    Ok(())
+}
+
+/// Pause/unpause dispatch and cuts together, optionally with an auto-expiry slot
+///
+/// Accounts: [diamond_state, authority, approvers...]
+/// Data: paused (bool), expiry_slot (Option<u64>)
+///
+/// Lifting or shortening a pause also needs the unpause quorum: the
+/// authority and the trailing approvers sign together.
+pub fn pause(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let authority = next_account_info(account_iter)?;
+    
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    #[derive(BorshDeserialize)]
+    struct PauseData {
+        paused: bool,
+        expiry_slot: Option<u64>,
+    }
+    
+    let pause_data = PauseData::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    
+    if !state.can_pause(authority.key) {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    if state.relaxes_pause(pause_data.paused, pause_data.paused, pause_data.expiry_slot) {
+        state.check_unpause_quorum(accounts.iter().filter(|a| a.is_signer).map(|a| a.key))?;
+    }
+    
+    state.set_pause(pause_data.paused, pause_data.expiry_slot);
+    state.save(state_account)?;
+    
+    msg!(
+        "Diamond paused: {} (expiry slot: {:?})",
+        pause_data.paused,
+        state.pause_expiry_slot
+    );
+    Ok(())
+}
+
+/// Schedule a maintenance window (anyone who may pause)
+///
+/// Accounts: [diamond_state, authority]
+/// Data: PauseWindow
+///
+/// Facet dispatch stops for the window's slots, on the fast path too,
+/// without a pause transaction landing on time.
+pub fn schedule_pause(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let authority = next_account_info(account_iter)?;
+    
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let window = PauseWindow::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    
+    if !state.can_pause(authority.key) {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    
+    state.schedule_pause_window(window, Clock::get()?.slot)?;
+    state.save(state_account)?;
+    
+    msg!("Maintenance window scheduled: slots {}..{}", window.start_slot, window.end_slot);
+    DiamondEvent::PauseWindowChanged { window, scheduled: true }.emit();
+    Ok(())
+}
+
+/// Cancel a scheduled maintenance window
+///
+/// Accounts: [diamond_state, authority, approvers...]
+/// Data: PauseWindow (as scheduled)
+///
+/// Cancelling lifts a pause to come, so it needs the unpause quorum like
+/// any other unpause.
+pub fn cancel_pause_window(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let authority = next_account_info(account_iter)?;
+    
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let window = PauseWindow::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    
+    if !state.can_pause(authority.key) {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    if !state.pause_windows.contains(&window) {
+        msg!("Error: No window scheduled for slots {}..{}", window.start_slot, window.end_slot);
+        return Err(DiamondError::InvalidPauseWindow.into());
+    }
+    state.check_unpause_quorum(accounts.iter().filter(|a| a.is_signer).map(|a| a.key))?;
+    
+    state.pause_windows.retain(|w| w != &window);
+    state.save(state_account)?;
+    
+    msg!("Maintenance window cancelled: slots {}..{}", window.start_slot, window.end_slot);
+    DiamondEvent::PauseWindowChanged { window, scheduled: false }.emit();
+    Ok(())
+}
+
+/// Pause or unpause dispatch and cuts independently
+///
+/// Accounts: [diamond_state, authority, approvers...]
+/// Data: dispatch_paused (bool), cut_paused (bool), expiry_slot (Option<u64>)
+pub fn set_pause_scopes(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let authority = next_account_info(account_iter)?;
+    
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let (dispatch_paused, cut_paused, expiry_slot) = <(bool, bool, Option<u64>)>::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    
+    if !state.can_pause(authority.key) {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    if state.relaxes_pause(dispatch_paused, cut_paused, expiry_slot) {
+        state.check_unpause_quorum(accounts.iter().filter(|a| a.is_signer).map(|a| a.key))?;
+    }
+    
+    state.set_pause_scopes(dispatch_paused, cut_paused, expiry_slot);
+    state.save(state_account)?;
+    
+    msg!(
+        "Dispatch paused: {}, cuts paused: {} (expiry slot: {:?})",
+        dispatch_paused,
+        cut_paused,
+        state.pause_expiry_slot
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_name_resolution_ignores_insert_order() {
+        let module = Pubkey::new_unique();
+        let counter = namespace_bytes("counter").unwrap();
+        let mappings = [
+            SelectorMapping::new([1, 0, 0, 0], module, "get", false),
+            SelectorMapping::new([4, 0, 0, 0], module, "get", true),
+            SelectorMapping::new_with_namespace(counter, [3, 0, 0, 0], module, "get", false),
+            SelectorMapping::new_with_namespace(counter, [2, 0, 0, 0], module, "get", false),
+        ];
+        let resolved = |state: &DiamondState, name: &str| state.get_mapping_by_name(name).map(|m| m.selector);
+        
+        for order in 0..mappings.len().pow(4) {
+            let order: Vec<usize> = (0..4).map(|digit| order / 4usize.pow(digit) % 4).collect();
+            if (0..4).any(|i| !order.contains(&i)) {
+                continue;
+            }
+            let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+            state.selectors = order.iter().map(|&i| mappings[i].clone()).collect();
+            // Immutable beats mutable, then the lowest selector; only the
+            // exact namespace answers
+            assert_eq!(resolved(&state, "get"), Some(Selector::from([4, 0, 0, 0])), "{:?}", order);
+            assert_eq!(resolved(&state, "counter::get"), Some(Selector::from([2, 0, 0, 0])), "{:?}", order);
+            assert_eq!(resolved(&state, "vault::get"), None, "{:?}", order);
+            assert_eq!(resolved(&state, "counter::set"), None, "{:?}", order);
+        }
+    }
+    
+    #[test]
+    fn test_set_admins_reports_delta() {
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        let (a, b, c) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        state.admins = vec![a, b];
+        
+        let (added, removed) = state.set_admins(vec![b, c, c]).unwrap();
+        assert_eq!(added, vec![c]);
+        assert_eq!(removed, vec![a]);
+        assert_eq!(state.admins, vec![b, c]);
+    }
+
+    #[test]
+    fn test_set_admins_capacity() {
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        let admins: Vec<Pubkey> = (0..=DiamondState::MAX_ADMINS).map(|_| Pubkey::new_unique()).collect();
+        
+        assert_eq!(state.set_admins(admins), Err(DiamondError::AdminCapacityExceeded));
+        assert!(state.admins.is_empty());
+    }
+
+    #[test]
+    fn test_pause_expiry() {
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        
+        state.set_pause(true, Some(100));
+        assert!(state.is_paused_at(99));
+        assert!(!state.is_paused_at(100));
+        
+        state.set_pause(true, None);
+        assert!(state.is_paused_at(u64::MAX));
+        
+        state.set_pause(false, Some(100));
+        assert!(!state.is_paused_at(0));
+        assert_eq!(state.pause_expiry_slot, None);
+    }
+
+    #[test]
+    fn test_pause_scopes() {
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        
+        // Freeze upgrades, keep traffic running
+        state.set_pause_scopes(false, true, Some(100));
+        assert!(!state.is_dispatch_paused_at(0));
+        assert_eq!(state.check_cuts_allowed(99), Err(DiamondError::DiamondPaused));
+        assert_eq!(state.check_cuts_allowed(100), Ok(()));
+        
+        // And the other way round
+        state.set_pause_scopes(true, false, None);
+        assert!(state.is_dispatch_paused_at(u64::MAX));
+        assert_eq!(state.check_cuts_allowed(0), Ok(()));
+        
+        state.set_pause(true, None);
+        assert!(state.dispatch_paused && state.cut_paused);
+    }
+    
+    #[test]
+    fn test_pause_windows() {
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        let window = PauseWindow { start_slot: 100, end_slot: 200 };
+        state.schedule_pause_window(window, 50).unwrap();
+        assert_eq!(state.pause_window_at(99), None);
+        assert_eq!(state.pause_window_at(100), Some(&window));
+        assert_eq!(state.pause_window_at(200), None);
+        // Windows never set the manual pause flags
+        assert!(!state.is_dispatch_paused_at(150));
+        
+        let empty = PauseWindow { start_slot: 300, end_slot: 300 };
+        assert_eq!(state.schedule_pause_window(empty, 50), Err(DiamondError::InvalidPauseWindow));
+        let past = PauseWindow { start_slot: 10, end_slot: 40 };
+        assert_eq!(state.schedule_pause_window(past, 50), Err(DiamondError::InvalidPauseWindow));
+        
+        for start in 1..DiamondState::MAX_PAUSE_WINDOWS as u64 {
+            state.schedule_pause_window(PauseWindow { start_slot: start * 1_000, end_slot: start * 1_000 + 10 }, 50).unwrap();
+        }
+        let more = PauseWindow { start_slot: 9_000, end_slot: 9_010 };
+        assert_eq!(state.schedule_pause_window(more, 50), Err(DiamondError::InvalidPauseWindow));
+        // Ended windows make room
+        assert_eq!(state.schedule_pause_window(more, 200), Ok(()));
+        assert_eq!(state.pause_windows.len(), DiamondState::MAX_PAUSE_WINDOWS);
+    }
+    
+    #[test]
+    fn test_relaxes_pause() {
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        assert!(!state.relaxes_pause(false, false, None));
+        
+        state.set_pause(true, Some(100));
+        assert!(state.relaxes_pause(false, false, None));
+        assert!(state.relaxes_pause(true, false, Some(100)));
+        // Re-pausing may extend the pause but not bring its end forward
+        assert!(!state.relaxes_pause(true, true, Some(200)));
+        assert!(!state.relaxes_pause(true, true, None));
+        assert!(state.relaxes_pause(true, true, Some(99)));
+        
+        state.set_pause(true, None);
+        assert!(state.relaxes_pause(true, true, Some(u64::MAX - 1)));
+    }
+    
+    #[test]
+    fn test_unpause_quorum() {
+        let owner = Pubkey::new_unique();
+        let (a, b, outsider) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut state = DiamondState::new(owner, 255);
+        state.admins = vec![a, b];
+        assert_eq!(state.check_unpause_quorum([&outsider]), Ok(()));
+        
+        state.unpause_quorum = 2;
+        assert!(state.unpause_quorum_reachable());
+        assert_eq!(state.check_unpause_quorum([&a, &a, &outsider]), Err(DiamondError::InsufficientApprovals));
+        assert_eq!(state.check_unpause_quorum([&owner, &b]), Ok(()));
+        
+        // Governance speaks for the whole quorum, and keeps it reachable
+        let governance = Pubkey::new_unique();
+        state.governance = Some(governance);
+        assert_eq!(state.check_unpause_quorum([&governance]), Ok(()));
+        state.admins.clear();
+        assert!(state.unpause_quorum_reachable());
+        let pending = state.announce_change(AuthorityChange::Governance(None), 0);
+        assert_eq!(state.apply_pending_change(pending.effective_slot), Err(DiamondError::InsufficientApprovals));
+    }
+    
+    #[test]
+    fn test_pause_authority_can_pause() {
+        let owner = Pubkey::new_unique();
+        let guardian = Pubkey::new_unique();
+        let mut state = DiamondState::new(owner, 255);
+        
+        assert!(state.can_pause(&owner));
+        assert!(!state.can_pause(&guardian));
+        
+        state.pause_authority = guardian;
+        assert!(state.can_pause(&guardian));
+        assert!(state.can_pause(&owner));
+    }
+    
+    #[test]
+    fn test_governance_only_revokes_owner_and_admins() {
+        let owner = Pubkey::new_unique();
+        let admin = Pubkey::new_unique();
+        let governance = Pubkey::new_unique();
+        let mut state = DiamondState::new(owner, 255);
+        state.admins.push(admin);
+        
+        assert_eq!(state.enable_governance_only(), Err(DiamondError::GovernanceNotConfigured));
+        assert!(!state.governance_only);
+        
+        state.governance = Some(governance);
+        assert_eq!(state.enable_governance_only(), Ok(governance));
+        
+        assert!(state.is_owner(&governance));
+        assert!(!state.is_owner(&owner));
+        assert!(!state.has_authority(&admin));
+        assert!(!state.can_pause(&owner));
+        assert!(state.can_pause(&governance));
+    }
+    
+    #[test]
+    fn test_remove_admins() {
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        state.admins = vec![a, b];
+        
+        assert_eq!(state.remove_admins(&[a, Pubkey::new_unique()]), vec![a]);
+        assert_eq!(state.admins, vec![b]);
+    }
+    
+    #[test]
+    fn test_dispatch_limits() {
+        assert_eq!(DispatchLimits::default().check(255, 1232), Ok(()));
+        
+        let limits = DispatchLimits { max_accounts: 4, ..Default::default() };
+        assert_eq!(limits.check(4, 10_000), Ok(()));
+        assert_eq!(limits.check(5, 8), Err(DiamondError::DispatchLimitExceeded));
+        
+        let limits = DispatchLimits { max_payload_len: 64, ..Default::default() };
+        assert_eq!(limits.check(30, 64), Ok(()));
+        assert_eq!(limits.check(0, 65), Err(DiamondError::DispatchLimitExceeded));
+    }
+    
+    #[test]
+    fn test_dispatch_depth() {
+        let active = ActiveCall { facet: Pubkey::new_unique(), reentrancy: Reentrancy::Allowed, stack_height: 1 };
+        
+        // The runtime cap holds even without a configured budget
+        let unbounded = DispatchLimits::default();
+        assert_eq!(unbounded.check_depth(1, None), Ok(()));
+        assert_eq!(unbounded.check_depth(4, Some(&active)), Ok(()));
+        assert_eq!(unbounded.check_depth(5, Some(&active)), Err(DiamondError::CallDepthExceeded));
+        
+        let limits = DispatchLimits { max_depth: 1, ..Default::default() };
+        assert_eq!(limits.check_depth(2, Some(&active)), Ok(()));
+        assert_eq!(limits.check_depth(3, Some(&active)), Err(DiamondError::CallDepthExceeded));
+        assert_eq!(limits.check_depth(3, None), Ok(()));
+    }
+    
+    #[test]
+    fn test_reentrancy_policies() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let on = |module, policy| SelectorMapping::new([1, 0, 0, 0], module, "f", false).with_reentrancy(policy);
+        
+        let same = ActiveCall { facet: a, reentrancy: Reentrancy::SameFacetOnly, stack_height: 1 };
+        assert!(same.permits(&on(a, Reentrancy::SameFacetOnly)));
+        assert!(!same.permits(&on(b, Reentrancy::Allowed)));
+        assert!(!same.permits(&on(a, Reentrancy::Forbidden)));
+        
+        let open = ActiveCall { facet: a, reentrancy: Reentrancy::Allowed, stack_height: 1 };
+        assert!(open.permits(&on(b, Reentrancy::SameFacetOnly)));
+        assert!(!open.permits(&on(b, Reentrancy::Forbidden)));
+        
+        // Policy bits leave the standard-account and trace flags alone
+        let mapping = on(a, Reentrancy::Allowed).with_standard_accounts(STD_CLOCK | CALL_TRACE | REENTRANCY_ALLOWED);
+        assert_eq!(mapping.reentrancy(), Reentrancy::Allowed);
+        assert_eq!(Reentrancy::from_flags(REENTRANCY_MASK), Reentrancy::Forbidden);
+    }
+    
+    #[test]
+    fn test_two_step_ownership_transfer() {
+        let owner = Pubkey::new_unique();
+        let mut state = DiamondState::new(owner, 255);
+        // e.g. a governor program's PDA
+        let (governor, _) = Pubkey::find_program_address(&[b"governor"], &Pubkey::new_unique());
+        
+        assert_eq!(state.accept_ownership(&governor, 0), Err(DiamondError::UnauthorizedAccess));
+        
+        let effective_slot = state.nominate_owner(Some(governor), 100);
+        assert_eq!(effective_slot, 100 + DEFAULT_AUTHORITY_DELAY_SLOTS);
+        assert_eq!(state.accept_ownership(&governor, effective_slot - 1), Err(DiamondError::AuthorityChangeNotReady));
+        assert_eq!(state.accept_ownership(&Pubkey::new_unique(), effective_slot), Err(DiamondError::UnauthorizedAccess));
+        assert_eq!(state.accept_ownership(&governor, effective_slot), Ok(owner));
+        assert!(state.is_owner(&governor) && !state.is_owner(&owner));
+        assert_eq!(state.pause_authority, governor);
+        assert_eq!(state.pending_owner, None);
+    }
+    
+    #[test]
+    fn test_authority_change_window() {
+        let owner = Pubkey::new_unique();
+        let guardian = Pubkey::new_unique();
+        let governance = Pubkey::new_unique();
+        let mut state = DiamondState::new(owner, 255);
+        state.pause_authority = guardian;
+        
+        let pending = state.announce_change(AuthorityChange::Governance(Some(governance)), 10);
+        assert_eq!(state.apply_pending_change(pending.effective_slot - 1), Err(DiamondError::AuthorityChangeNotReady));
+        assert_eq!(state.apply_pending_change(pending.effective_slot), Ok(AuthorityChange::Governance(Some(governance))));
+        assert_eq!(state.governance, Some(governance));
+        assert_eq!(state.pending_change, None);
+        
+        // Raising the delay is immediate, lowering it waits out the current one
+        assert_eq!(state.set_authority_delay(500_000, 0), Ok(None));
+        let pending = state.set_authority_delay(0, 0).unwrap().unwrap();
+        assert_eq!(pending.effective_slot, 500_000);
+        assert_eq!(state.authority_delay_slots, 500_000);
+        
+        // The guardian vetoes; strangers can't
+        assert!(state.can_veto(&guardian) && state.can_veto(&governance) && !state.can_veto(&Pubkey::new_unique()));
+        assert_eq!(state.veto(VetoTarget::Config), Ok(AuthorityChange::Delay(0)));
+        assert_eq!(state.apply_pending_change(u64::MAX), Err(DiamondError::AuthorityChangeNotReady));
+        
+        let nominee = Pubkey::new_unique();
+        state.nominate_owner(Some(nominee), 0);
+        assert_eq!(state.veto(VetoTarget::Ownership), Ok(AuthorityChange::Owner(nominee)));
+        assert_eq!(state.accept_ownership(&nominee, u64::MAX), Err(DiamondError::UnauthorizedAccess));
+        assert_eq!(state.veto(VetoTarget::Ownership), Err(DiamondError::AuthorityChangeNotReady));
+        
+        // The delay is bounded, so it can always come down again
+        assert_eq!(state.set_authority_delay(u64::MAX, 0), Err(DiamondError::AuthorityDelayTooLong));
+        assert_eq!(state.set_authority_delay(MAX_AUTHORITY_DELAY_SLOTS, 0), Ok(None));
+        
+        // The owner can't replace the guardian before it had the chance to veto
+        let owner = state.owner;
+        apply_authority_action(&mut state, AuthorityAction::PauseAuthority(owner), 0).unwrap();
+        assert_eq!(state.pause_authority, guardian);
+        assert_eq!(state.veto(VetoTarget::Config), Ok(AuthorityChange::PauseAuthority(owner)));
+        apply_authority_action(&mut state, AuthorityAction::PauseAuthority(owner), 0).unwrap();
+        assert_eq!(state.apply_pending_change(MAX_AUTHORITY_DELAY_SLOTS - 1), Err(DiamondError::AuthorityChangeNotReady));
+        assert_eq!(state.apply_pending_change(MAX_AUTHORITY_DELAY_SLOTS), Ok(AuthorityChange::PauseAuthority(owner)));
+        assert_eq!(state.pause_authority, owner);
+    }
+    
+    #[test]
+    fn test_signed_veto_bound_to_pending_change() {
+        let (owner, guardian, diamond) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut state = DiamondState::new(owner, 255);
+        state.pause_authority = guardian;
+        state.authority_delay_slots = 100;
+        let pending = state.announce_change(AuthorityChange::Governance(Some(Pubkey::new_unique())), 10);
+        let veto = SignedVeto {
+            domain: "guardian.example.com".into(),
+            diamond,
+            guardian,
+            target: VetoTarget::Config,
+            change: pending.change,
+            effective_slot: pending.effective_slot,
+            expiry_slot: 50,
+        };
+        assert!(String::from_utf8(veto.message()).unwrap().contains("Action: guardian-veto\n"));
+        assert_eq!(state.check_signed_veto(&veto, &diamond, 50), Ok(()));
+        assert_eq!(state.check_signed_veto(&veto, &diamond, 51), Err(DiamondError::UnauthorizedAccess));
+        assert_eq!(state.check_signed_veto(&veto, &Pubkey::new_unique(), 0), Err(DiamondError::UnauthorizedAccess));
+        let stranger = SignedVeto { guardian: Pubkey::new_unique(), ..veto.clone() };
+        assert_eq!(state.check_signed_veto(&stranger, &diamond, 0), Err(DiamondError::UnauthorizedAccess));
+        
+        // Re-announced: the old signature no longer cancels anything
+        state.announce_change(pending.change, 20);
+        assert_eq!(state.check_signed_veto(&veto, &diamond, 0), Err(DiamondError::AuthorityChangeNotReady));
+        
+        let nominee = Pubkey::new_unique();
+        let effective_slot = state.nominate_owner(Some(nominee), 0);
+        let ownership = SignedVeto {
+            target: VetoTarget::Ownership,
+            change: AuthorityChange::Owner(nominee),
+            effective_slot,
+            ..veto
+        };
+        assert_eq!(state.check_signed_veto(&ownership, &diamond, 0), Ok(()));
+    }
+    
+    #[test]
+    fn test_payload_specs() {
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        let selector = Selector::from([1, 0, 0, 1]);
+        let spec = PayloadSpec { selector, min_len: 8, max_len: 40, schema_hash: schema_hash("(u64,Pubkey)") };
+        assert_eq!(state.set_payload_spec(spec), Err(DiamondError::ModuleNotFound));
+        
+        apply_cuts(&mut state, &[FacetCut::add(selector, Pubkey::new_unique(), "m", "f")]).unwrap();
+        assert_eq!(state.set_payload_spec(spec), Ok(None));
+        assert_eq!(state.check_payload(selector, 40, None), Ok(()));
+        assert_eq!(state.check_payload(selector, 7, None), Err(DiamondError::InvalidPayload));
+        assert_eq!(state.check_payload(selector, 41, None), Err(DiamondError::InvalidPayload));
+        assert_eq!(state.check_payload(selector, 8, Some(&schema_hash("(u64,Pubkey)"))), Ok(()));
+        assert_eq!(state.check_payload(selector, 8, Some(&schema_hash("(u32)"))), Err(DiamondError::InvalidPayload));
+        assert_eq!(state.check_payload([9, 9, 9, 9].into(), 0, Some(&[1; 8])), Ok(()));
+        
+        let inverted = PayloadSpec { min_len: 9, max_len: 8, ..spec };
+        assert_eq!(state.set_payload_spec(inverted), Err(DiamondError::InvalidCut));
+        
+        let cleared = PayloadSpec { selector, min_len: 0, max_len: 0, schema_hash: [0; 8] };
+        assert_eq!(state.set_payload_spec(cleared), Ok(Some(spec)));
+        assert!(state.payload_specs.is_empty());
+    }
+    
+    #[test]
+    fn test_vanity_address() {
+        let (program_id, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        assert_eq!(
+            diamond_address(&program_id, &owner, None),
+            Pubkey::find_program_address(&[b"diamond_state", owner.as_ref()], &program_id)
+        );
+        let (vanity, _) = diamond_address(&program_id, &owner, Some(7));
+        assert_ne!(vanity, diamond_address(&program_id, &owner, None).0);
+        assert_ne!(vanity, diamond_address(&program_id, &owner, Some(8)).0);
+        
+        let prefix: String = vanity.to_string().chars().take(3).collect();
+        assert_eq!(check_vanity(&vanity, &prefix), Ok(()));
+        let other = if prefix.starts_with('z') { "y" } else { "z" };
+        assert_eq!(check_vanity(&vanity, other), Err(DiamondError::VanityMismatch));
+        // Longer than the router will check
+        assert_eq!(check_vanity(&vanity, &vanity.to_string()[..9]), Err(DiamondError::VanityMismatch));
+    }
+    
+    #[test]
+    fn test_load_rederives_the_state_address() {
+        use solana_program::clock::Epoch;
+        
+        let (program_id, creator) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (address, bump) = diamond_address(&program_id, &creator, Some(7));
+        let mut state = DiamondState::new(creator, bump);
+        state.vanity_nonce = Some(7);
+        // Ownership changes don't move the state
+        state.owner = Pubkey::new_unique();
+        let load_at = |key: &Pubkey, state: &DiamondState| {
+            let (mut lamports, mut data) = (0, borsh::to_vec(state).unwrap());
+            let account = AccountInfo::new(key, false, false, &mut lamports, &mut data, &program_id, false, Epoch::default());
+            (
+                DiamondState::load(&program_id, &account).map(|loaded| loaded.owner),
+                DispatchState::load(&program_id, &account).map(|loaded| loaded.owner),
+            )
+        };
+        assert_eq!(load_at(&address, &state), (Ok(state.owner), Ok(state.owner)));
+        
+        // A router-owned account elsewhere doesn't pass as this diamond
+        let elsewhere = Err(ProgramError::InvalidSeeds);
+        assert_eq!(load_at(&Pubkey::new_unique(), &state), (elsewhere.clone(), elsewhere.clone()));
+        state.creator = state.owner;
+        assert_eq!(load_at(&address, &state), (elsewhere.clone(), elsewhere));
+    }
+    
+    #[test]
+    fn test_admin_changes_are_gated_like_other_authority_changes() {
+        use solana_program::clock::Epoch;
+        
+        let (program_id, owner, governance) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let (address, bump) = diamond_address(&program_id, &owner, None);
+        let admin = Pubkey::new_unique();
+        let run = |state: &DiamondState| {
+            let (mut lamports, mut data) = (0, borsh::to_vec(state).unwrap());
+            let (mut owner_lamports, mut owner_data) = (0, vec![]);
+            let accounts = [
+                AccountInfo::new(&address, false, true, &mut lamports, &mut data, &program_id, false, Epoch::default()),
+                AccountInfo::new(&owner, true, false, &mut owner_lamports, &mut owner_data, &program_id, false, Epoch::default()),
+            ];
+            [
+                add_admin(&program_id, &accounts, admin.as_ref()),
+                set_admins(&program_id, &accounts, &borsh::to_vec(&vec![admin]).unwrap()),
+                remove_admins(&program_id, &accounts, &borsh::to_vec(&vec![admin]).unwrap()),
+            ]
+        };
+        
+        // Once a council exists, admins change through its motions
+        let mut state = DiamondState::new(owner, bump);
+        state.has_council = true;
+        assert_eq!(run(&state), [(); 3].map(|_| Err(DiamondError::CouncilApprovalRequired.into())));
+        
+        // Once governance-only, the former owner can't touch the admins
+        let mut state = DiamondState::new(owner, bump);
+        state.governance = Some(governance);
+        state.enable_governance_only().unwrap();
+        assert_eq!(run(&state), [(); 3].map(|_| Err(DiamondError::UnauthorizedAccess.into())));
+    }
+    
+    #[test]
+    fn test_council_diamond_changes_quorum_and_limits_by_motion_only() {
+        use solana_program::clock::Epoch;
+        
+        let (program_id, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (address, bump) = diamond_address(&program_id, &owner, None);
+        let mut state = DiamondState::new(owner, bump);
+        state.has_council = true;
+        let (mut lamports, mut data) = (0, borsh::to_vec(&state).unwrap());
+        let (mut owner_lamports, mut owner_data) = (0, vec![]);
+        let accounts = [
+            AccountInfo::new(&address, false, true, &mut lamports, &mut data, &program_id, false, Epoch::default()),
+            AccountInfo::new(&owner, true, false, &mut owner_lamports, &mut owner_data, &program_id, false, Epoch::default()),
+        ];
+        assert_eq!(set_unpause_quorum(&program_id, &accounts, &[1]), Err(DiamondError::CouncilApprovalRequired.into()));
+        let limits = DispatchLimits { max_accounts: 0, max_payload_len: 1, max_depth: 0 };
+        assert_eq!(
+            set_dispatch_limits(&program_id, &accounts, &borsh::to_vec(&limits).unwrap()),
+            Err(DiamondError::CouncilApprovalRequired.into())
+        );
+        
+        // A motion's authority action still sets it
+        apply_authority_action(&mut state, AuthorityAction::UnpauseQuorum(1), 0).unwrap();
+        assert_eq!(state.unpause_quorum, 1);
+        assert_eq!(apply_authority_action(&mut state, AuthorityAction::UnpauseQuorum(9), 0), Err(DiamondError::InsufficientApprovals));
+        apply_authority_action(&mut state, AuthorityAction::DispatchLimits(limits), 0).unwrap();
+        assert_eq!(state.dispatch_limits, limits);
+    }
+    
+    #[test]
+    fn test_header_and_selector_index_at_fixed_offsets() {
+        let mut state = crate::layout::worst_case_state();
+        state.dispatch_paused = true;
+        // Table order is insertion order; the index sorts
+        state.selectors.reverse();
+        let data = borsh::to_vec(&state).unwrap();
+        
+        let header = routing::state_header(&data).unwrap();
+        assert_eq!(header.owner, state.owner.to_bytes());
+        assert!(header.dispatch_paused);
+        assert_eq!(header.selector_width, state.selector_width);
+        
+        let index = Vec::<routing::IndexEntry>::deserialize(&mut &data[layout::STATE_INDEX_OFFSET..]).unwrap();
+        assert_eq!(index.len(), state.selectors.len());
+        assert!(index.windows(2).all(|pair| pair[0].selector.0 < pair[1].selector.0));
+        for mapping in &state.selectors {
+            assert_eq!(routing::find_mapping(&data, &mapping.selector), Some(&borsh::to_vec(mapping).unwrap()[..]));
+        }
+        assert_eq!(routing::find_mapping(&data, &Selector([0xEE; 8])), None);
+        
+        // The index is derived, so decoding and re-encoding is lossless
+        let decoded = DiamondState::try_from_slice(&data).unwrap();
+        assert_eq!(borsh::to_vec(&decoded).unwrap(), data);
+    }
+    
+    #[test]
+    fn test_dispatch_state_skips_the_tables() {
+        let mut state = crate::layout::worst_case_state();
+        state.active_call = None;
+        let mut data = borsh::to_vec(&state).unwrap();
+        data.resize(DiamondState::SPACE, 0);
+        
+        let mut view = DispatchState::read(&data).unwrap();
+        assert!(view.selectors.is_empty() && view.admins.is_empty() && view.active_modules.is_empty());
+        assert_eq!(view.owner, state.owner);
+        assert_eq!(view.selector_width, state.selector_width);
+        assert_eq!(view.circuit_breakers, state.circuit_breakers);
+        assert_eq!(view.payload_specs, state.payload_specs);
+        assert_eq!(view.health_registry, state.health_registry);
+        
+        // Growing a field after the tables leaves the tables intact
+        let call = ActiveCall { facet: Pubkey::new_unique(), reentrancy: Reentrancy::SameFacetOnly, stack_height: 1 };
+        view.active_call = Some(call);
+        view.write(&mut data).unwrap();
+        state.active_call = Some(call);
+        let reread = DiamondState::deserialize(&mut &data[..]).unwrap();
+        assert_eq!(borsh::to_vec(&reread).unwrap(), borsh::to_vec(&state).unwrap());
+    }
+}
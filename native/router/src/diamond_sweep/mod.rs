@@ -0,0 +1,183 @@
+/*!
+ * Diamond Sweep Module
+ * Recovery of funds sent to the diamond state PDA by mistake
+ *
+ * The diamond state is a well-known address, so SOL and SPL tokens end up
+ * there. Nothing in the router spends them (keeper rewards aside), so the
+ * owner sweeps them out: lamports above the state's rent-exempt minimum, and
+ * whole balances of token accounts the diamond PDA owns. Token sweeps use
+ * `TransferChecked`, which both token programs understand.
+ */
+
+use borsh::BorshDeserialize;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    msg,
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    sysvar::Sysvar,
+};
+
+use crate::diamond_state::{diamond_seeds, DiamondState, TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID};
+use crate::error::DiamondError;
+use crate::events::DiamondEvent;
+
+/// `TransferChecked` in both token programs
+const TRANSFER_CHECKED: u8 = 12;
+
+/// Mint, owner and amount of an SPL token account
+pub fn token_account_fields(data: &[u8]) -> Option<(Pubkey, Pubkey, u64)> {
+    let mint = Pubkey::try_from(data.get(..32)?).ok()?;
+    let owner = Pubkey::try_from(data.get(32..64)?).ok()?;
+    let amount = u64::from_le_bytes(data.get(64..72)?.try_into().ok()?);
+    Some((mint, owner, amount))
+}
+
+/// Decimals of an SPL mint
+pub fn mint_decimals(data: &[u8]) -> Option<u8> {
+    data.get(44).copied()
+}
+
+/// Move the state's lamports above rent exemption to `recipient` (owner only)
+///
+/// Accounts: [diamond_state, owner, recipient]
+///
+/// This also empties the budget `maintain` pays keeper rewards from.
+pub fn sweep_lamports(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let owner = next_account_info(account_iter)?;
+    let recipient = next_account_info(account_iter)?;
+    
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let state = DiamondState::load(program_id, state_account)?;
+    if !state.is_owner(owner.key) {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    
+    let floor = Rent::get()?.minimum_balance(state_account.data_len());
+    let amount = state_account.lamports().saturating_sub(floor);
+    if amount > 0 {
+        **state_account.try_borrow_mut_lamports()? -= amount;
+        **recipient.try_borrow_mut_lamports()? = recipient
+            .lamports()
+            .checked_add(amount)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+    }
+    
+    msg!("Swept {} lamports to {}", amount, recipient.key);
+    DiamondEvent::Swept { mint: None, recipient: *recipient.key, amount }.emit();
+    Ok(())
+}
+
+/// Move a diamond-owned token account's whole balance to `recipient` (owner only)
+///
+/// Accounts: [diamond_state, owner, source, mint, recipient, token_program]
+/// Data: creator (Pubkey), the key the diamond PDA was derived from
+///
+/// `source` must be a token account of `mint` whose owner is the diamond
+/// state PDA; the router signs for it with the PDA's seeds.
+pub fn sweep_token(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let owner = next_account_info(account_iter)?;
+    let source = next_account_info(account_iter)?;
+    let mint = next_account_info(account_iter)?;
+    let recipient = next_account_info(account_iter)?;
+    let token_program = next_account_info(account_iter)?;
+    
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if token_program.key != &TOKEN_PROGRAM_ID && token_program.key != &TOKEN_2022_PROGRAM_ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if source.owner != token_program.key || mint.owner != token_program.key {
+        msg!("Error: Source and mint must belong to {}", token_program.key);
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    let creator = Pubkey::try_from_slice(data).map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let state = DiamondState::load(program_id, state_account)?;
+    if !state.is_owner(owner.key) {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    
+    let (source_mint, source_owner, amount) =
+        token_account_fields(&source.try_borrow_data()?).ok_or(ProgramError::InvalidAccountData)?;
+    if &source_mint != mint.key || &source_owner != state_account.key {
+        msg!("Error: {} is not a {} account of the diamond", source.key, mint.key);
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let decimals = mint_decimals(&mint.try_borrow_data()?).ok_or(ProgramError::InvalidAccountData)?;
+    
+    let nonce = state.vanity_nonce.map(u64::to_le_bytes);
+    let bump = [state.bump];
+    let mut seeds = diamond_seeds(&creator, nonce.as_ref());
+    seeds.push(&bump);
+    if Pubkey::create_program_address(&seeds, program_id).as_ref() != Ok(state_account.key) {
+        msg!("Error: {} did not create this diamond", creator);
+        return Err(ProgramError::InvalidSeeds);
+    }
+    
+    if amount > 0 {
+        let mut ix_data = vec![TRANSFER_CHECKED];
+        ix_data.extend_from_slice(&amount.to_le_bytes());
+        ix_data.push(decimals);
+        invoke_signed(
+            &Instruction {
+                program_id: *token_program.key,
+                accounts: vec![
+                    AccountMeta::new(*source.key, false),
+                    AccountMeta::new_readonly(*mint.key, false),
+                    AccountMeta::new(*recipient.key, false),
+                    AccountMeta::new_readonly(*state_account.key, true),
+                ],
+                data: ix_data,
+            },
+            &[source.clone(), mint.clone(), recipient.clone(), state_account.clone(), token_program.clone()],
+            &[&seeds],
+        )?;
+    }
+    
+    msg!("Swept {} of {} to {}", amount, mint.key, recipient.key);
+    DiamondEvent::Swept { mint: Some(*mint.key), recipient: *recipient.key, amount }.emit();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_token_layouts() {
+        let (mint, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut account = vec![0u8; 165];
+        account[..32].copy_from_slice(mint.as_ref());
+        account[32..64].copy_from_slice(owner.as_ref());
+        account[64..72].copy_from_slice(&42u64.to_le_bytes());
+        assert_eq!(token_account_fields(&account), Some((mint, owner, 42)));
+        assert_eq!(token_account_fields(&account[..71]), None);
+        
+        let mut mint_data = vec![0u8; 82];
+        mint_data[44] = 6;
+        assert_eq!(mint_decimals(&mint_data), Some(6));
+        assert_eq!(mint_decimals(&[]), None);
+    }
+}
@@ -0,0 +1,288 @@
+/*!
+ * Diamond Tags Module
+ * Labels grouping selectors into subsystems
+ *
+ * An admin tags selectors with short labels ("view", "admin", "defi"), up
+ * to 8 bytes each and 4 per selector. UIs list a large diamond's functions
+ * by tag through the loupe, and operations that act on a set of selectors
+ * name a tag instead of enumerating them. Tags may be set before the cut
+ * that routes a selector; removing the selector drops its tags.
+ *
+ * During an incident a whole subsystem goes dark with one instruction:
+ * `set_tag_paused` stops every selector carrying the tag from dispatching
+ * (selectors tagged later included), and `remove_by_tag` cuts them all out
+ * of the routing table.
+ */
+
+use borsh::BorshDeserialize;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    sysvar::Sysvar,
+};
+
+use crate::diamond_audit;
+use crate::diamond_authorizer;
+use crate::diamond_cut::{apply_direct_cuts, authorize_cuts, decode_with_version, FacetCut};
+use crate::diamond_digest;
+use crate::diamond_index;
+use crate::diamond_loupe::return_borsh;
+use crate::diamond_state::{fixed_bytes, DiamondState};
+use crate::error::DiamondError;
+use crate::events::DiamondEvent;
+use crate::selector::Selector;
+
+/// Fixed-width tag from its name, if the name fits
+pub fn tag_bytes(name: &str) -> Option<[u8; 8]> {
+    (!name.is_empty() && name.len() <= 8).then(|| fixed_bytes(name))
+}
+
+/// Replace a selector's tags (owner or admin)
+///
+/// Accounts: [diamond_state, authority]
+/// Data: selector (Selector), tags (Vec<String>; empty clears them)
+pub fn set_tags(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let authority = next_account_info(account_iter)?;
+    
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let (selector, names) = <(Selector, Vec<String>)>::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    let tags = names
+        .iter()
+        .map(|name| tag_bytes(name))
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| {
+            msg!("Error: Tags are 1 to 8 bytes");
+            ProgramError::InvalidInstructionData
+        })?;
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    if !state.has_authority(authority.key) {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    state.set_tags(selector, &tags).inspect_err(|e| {
+        msg!("Error: Cannot tag {:?}: {}", selector, e);
+    })?;
+    state.save(state_account)?;
+    
+    msg!("Tags of {:?}: {:?}", selector, names);
+    DiamondEvent::TagsSet { selector, tags }.emit();
+    Ok(())
+}
+
+/// Pause or resume every selector carrying a tag (owner, admin or pause authority)
+///
+/// Accounts: [diamond_state, authority, approvers...]
+/// Data: tag (String), paused (bool)
+///
+/// Paused selectors fail dispatch with `DiamondPaused`. Built-ins are never
+/// paused this way. Resuming a paused tag needs the unpause quorum.
+pub fn set_tag_paused(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let authority = next_account_info(account_iter)?;
+    
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let (name, paused) = <(String, bool)>::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    let tag = tag_bytes(&name).ok_or(ProgramError::InvalidInstructionData)?;
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    if !state.can_pause(authority.key) {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    if !paused && state.paused_tags.contains(&tag) {
+        state.check_unpause_quorum(accounts.iter().filter(|a| a.is_signer).map(|a| a.key))?;
+    }
+    if !state.set_tag_paused(tag, paused).inspect_err(|_| {
+        msg!("Error: At most {} tags can be paused", DiamondState::MAX_PAUSED_TAGS);
+    })? {
+        msg!("Tag {:?} already {}", name, if paused { "paused" } else { "active" });
+        return Ok(());
+    }
+    state.save(state_account)?;
+    
+    msg!("Tag {:?} paused: {} ({} selectors)", name, paused, state.tagged(&tag).len());
+    DiamondEvent::TagPauseChanged { tag, paused }.emit();
+    Ok(())
+}
+
+/// Removal cuts for every mutable routed selector carrying `tag`
+pub fn removal_cuts(state: &DiamondState, tag: &[u8; 8]) -> Vec<FacetCut> {
+    state
+        .tagged(tag)
+        .into_iter()
+        .filter(|s| state.get_mapping(*s).is_some_and(|m| !m.is_immutable))
+        .map(FacetCut::remove)
+        .collect()
+}
+
+/// Remove every mutable selector carrying a tag, in one cut batch
+///
+/// Accounts: [diamond_state, authority, (routing digest), (selector index), (audit accounts), (authorizer)]
+/// Data: tag (String), optional expected_version (u64)
+///
+/// Authorized like the equivalent `Remove` cuts. Immutable selectors stay
+/// (and keep the tag); tags set ahead of a cut have nothing to remove.
+pub fn remove_by_tag(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let authority = next_account_info(account_iter)?;
+    
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let (name, expected_version) = decode_with_version::<String>(data)?;
+    let tag = tag_bytes(&name).ok_or(ProgramError::InvalidInstructionData)?;
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    state.check_cuts_allowed(Clock::get()?.slot)?;
+    state.check_config_version(expected_version)?;
+    
+    let cuts = removal_cuts(&state, &tag);
+    if cuts.is_empty() {
+        msg!("Error: No removable selectors tagged {:?}", name);
+        return Err(DiamondError::ModuleNotFound.into());
+    }
+    authorize_cuts(&state, authority.key, &cuts)?;
+    diamond_authorizer::authorize_cuts(state_account, &state, accounts, authority.key, &cuts)?;
+    apply_direct_cuts(&mut state, &cuts)?;
+    
+    state.save(state_account)?;
+    let index_accounts = diamond_digest::refresh(program_id, state_account.key, &state, account_iter.as_slice())?;
+    let audit_accounts = diamond_index::refresh(program_id, state_account.key, &state, index_accounts)?;
+    diamond_audit::commit_cuts(program_id, state_account, &state, audit_accounts, authority.key, &cuts)?;
+    
+    msg!("Removed {} selectors tagged {:?}", cuts.len(), name);
+    Ok(())
+}
+
+/// Selectors carrying a tag
+///
+/// Accounts: [diamond_state]
+/// Data: tag (String)
+/// Returns a Borsh-encoded `Vec<Selector>` via return data.
+pub fn selectors_by_tag(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    
+    let name = String::try_from_slice(data).map_err(|_| ProgramError::InvalidInstructionData)?;
+    let tag = tag_bytes(&name).ok_or(ProgramError::InvalidInstructionData)?;
+    let state = DiamondState::load(program_id, state_account)?;
+    let selectors = state.tagged(&tag);
+    
+    msg!("Tagged {:?}: {} selectors", name, selectors.len());
+    return_borsh(&selectors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diamond_cut::{apply_cuts, FacetCut};
+    
+    #[test]
+    fn test_tags_follow_the_selector() {
+        let module = Pubkey::new_unique();
+        let (view, admin) = (tag_bytes("view").unwrap(), tag_bytes("admin").unwrap());
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        
+        // Tagged ahead of the cut that routes it
+        state.set_tags([1, 0, 0, 1].into(), &[view, admin]).unwrap();
+        apply_cuts(&mut state, &[
+            FacetCut::add([1, 0, 0, 1], module, "vault", "balance"),
+            FacetCut::add([1, 0, 0, 2], module, "vault", "total"),
+        ])
+        .unwrap();
+        state.set_tags([1, 0, 0, 2].into(), &[view]).unwrap();
+        assert_eq!(state.tagged(&view), [Selector::from([1, 0, 0, 1]), Selector::from([1, 0, 0, 2])]);
+        assert_eq!(state.tags_of([1, 0, 0, 1]), [view, admin]);
+        
+        state.set_tags([1, 0, 0, 1].into(), &[admin]).unwrap();
+        assert_eq!(state.tagged(&view), [Selector::from([1, 0, 0, 2])]);
+        apply_cuts(&mut state, &[FacetCut::remove([1, 0, 0, 2])]).unwrap();
+        assert!(state.tagged(&view).is_empty());
+        assert_eq!(state.tags.len(), 1);
+    }
+    
+    #[test]
+    fn test_pause_and_remove_by_tag() {
+        let module = Pubkey::new_unique();
+        let defi = tag_bytes("defi").unwrap();
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        apply_cuts(&mut state, &[
+            FacetCut::add([1, 0, 0, 1], module, "amm", "swap"),
+            FacetCut::add([1, 0, 0, 2], module, "amm", "quote"),
+            FacetCut { is_immutable: true, ..FacetCut::add([1, 0, 0, 3], module, "amm", "version") },
+        ])
+        .unwrap();
+        for s in 1..=3 {
+            state.set_tags([1, 0, 0, s].into(), &[defi]).unwrap();
+        }
+        
+        assert_eq!(state.set_tag_paused(defi, true), Ok(true));
+        assert_eq!(state.set_tag_paused(defi, true), Ok(false));
+        assert_eq!(state.paused_tag_of(&Selector::from([1, 0, 0, 2])), Some(defi));
+        // Selectors tagged later are paused too
+        state.set_tags([1, 0, 0, 9].into(), &[defi]).unwrap();
+        assert_eq!(state.paused_tag_of(&Selector::from([1, 0, 0, 9])), Some(defi));
+        assert_eq!(state.set_tag_paused(defi, false), Ok(true));
+        assert_eq!(state.paused_tag_of(&Selector::from([1, 0, 0, 2])), None);
+        
+        // Unrouted and immutable selectors aren't removed
+        let cuts = removal_cuts(&state, &defi);
+        assert_eq!(cuts, [FacetCut::remove([1, 0, 0, 1]), FacetCut::remove([1, 0, 0, 2])]);
+        apply_direct_cuts(&mut state, &cuts).unwrap();
+        assert_eq!(state.selectors.len(), 1);
+        assert_eq!(state.tagged(&defi), [Selector::from([1, 0, 0, 3]), Selector::from([1, 0, 0, 9])]);
+        
+        for i in 0..DiamondState::MAX_PAUSED_TAGS as u8 {
+            state.set_tag_paused([i + 1; 8], true).unwrap();
+        }
+        assert_eq!(state.set_tag_paused(defi, true), Err(DiamondError::InvalidConfigEntry));
+    }
+    
+    #[test]
+    fn test_tag_limits() {
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        let tag = |i: u8| tag_bytes(&format!("t{}", i)).unwrap();
+        let selector = Selector::from([1, 0, 0, 1]);
+        assert_eq!(state.set_tags(selector, &[tag(1), tag(1)]), Err(DiamondError::InvalidConfigEntry));
+        assert_eq!(state.set_tags(selector, &(0..5).map(tag).collect::<Vec<_>>()), Err(DiamondError::InvalidConfigEntry));
+        assert_eq!(tag_bytes(""), None);
+        assert_eq!(tag_bytes("too-long!"), None);
+        
+        for i in 0..(DiamondState::MAX_TAGS / 4) as u8 {
+            state.set_tags([2, 0, 0, i].into(), &(0..4).map(tag).collect::<Vec<_>>()).unwrap();
+        }
+        assert_eq!(state.set_tags(selector, &[tag(0)]), Err(DiamondError::SelectorCapacityExceeded));
+    }
+}
@@ -0,0 +1,210 @@
+/*!
+ * Diamond Vault Module
+ * Diamond-owned lamports for rent of protocol accounts
+ *
+ * The rent vault is a system account PDA of the router. Anyone can fund it;
+ * the owner can withdraw. On `dispatch_signed` calls to selectors flagged
+ * `RENT_VAULT`, the router forwards the vault as a signer, so the facet can
+ * name it as the payer when it creates PDAs and users don't have to.
+ *
+ * The router's own account creation (proposals, the routing digest, the
+ * selector index) takes its payer the same way: whoever signs, or the vault
+ * when the instruction passes it as the payer and its authority is the
+ * owner or the governance key. That lets a governance PDA, which holds no
+ * spare lamports, propose cuts, while admins and council members pay their
+ * own. Closing an executed proposal refunds its rent to whoever paid it.
+ */
+
+use borsh::BorshDeserialize;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    system_program,
+    sysvar::Sysvar,
+};
+
+use crate::diamond_state::DiamondState;
+use crate::error::DiamondError;
+use crate::events::DiamondEvent;
+
+pub use diamond_constants::seeds::RENT_VAULT_SEED;
+
+/// System account PDA holding the diamond's rent budget
+pub fn rent_vault(program_id: &Pubkey, diamond: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[RENT_VAULT_SEED, diamond.as_ref()], program_id)
+}
+
+/// Lamports that may leave the vault without stranding it below rent exemption
+///
+/// The vault may be emptied entirely, but never left holding dust.
+fn withdrawable(balance: u64, amount: u64, floor: u64) -> bool {
+    amount <= balance && (amount == balance || balance - amount >= floor)
+}
+
+/// Create `account`, a router PDA signed for by `seeds`, with `space` bytes
+///
+/// `payer` funds the rent: a signer pays as usual. The diamond's rent vault
+/// pays from the diamond's budget, with the router signing for it, when
+/// `vault_allowed` (the instruction's authority passes
+/// `DiamondState::may_spend_rent_vault`) and as long as that doesn't leave
+/// the vault holding dust.
+#[allow(clippy::too_many_arguments)]
+pub fn create_pda<'info>(
+    program_id: &Pubkey,
+    diamond: &Pubkey,
+    vault_allowed: bool,
+    payer: &AccountInfo<'info>,
+    account: &AccountInfo<'info>,
+    system_program_account: &AccountInfo<'info>,
+    space: usize,
+    seeds: &[&[u8]],
+) -> ProgramResult {
+    let rent = Rent::get()?;
+    let lamports = rent.minimum_balance(space);
+    let create = system_instruction::create_account(payer.key, account.key, lamports, space as u64, program_id);
+    let infos = [payer.clone(), account.clone(), system_program_account.clone()];
+    
+    let (vault, bump) = rent_vault(program_id, diamond);
+    if payer.key != &vault {
+        if !payer.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        return invoke_signed(&create, &infos, &[seeds]);
+    }
+    if !vault_allowed {
+        msg!("Error: Only the owner or governance may spend the rent vault");
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    if !withdrawable(payer.lamports(), lamports, rent.minimum_balance(0)) {
+        msg!("Error: Rent vault holds {} lamports, {} needed for {}", payer.lamports(), lamports, account.key);
+        return Err(ProgramError::InsufficientFunds);
+    }
+    invoke_signed(&create, &infos, &[seeds, &[RENT_VAULT_SEED, diamond.as_ref(), &[bump]]])?;
+    
+    msg!("Rent vault paid {} lamports for {}", lamports, account.key);
+    DiamondEvent::RentVaultSpent { account: *account.key, amount: lamports }.emit();
+    Ok(())
+}
+
+/// Add lamports to the rent vault (anyone)
+///
+/// Accounts: [diamond_state, funder, rent_vault, system_program]
+/// Data: amount (u64)
+pub fn fund_vault(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let funder = next_account_info(account_iter)?;
+    let vault = next_account_info(account_iter)?;
+    let system_program_account = next_account_info(account_iter)?;
+    
+    if !funder.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if system_program_account.key != &system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if state_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    let amount = u64::try_from_slice(data).map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let (expected, _) = rent_vault(program_id, state_account.key);
+    if vault.key != &expected {
+        msg!("Error: Invalid rent vault. Expected: {}", expected);
+        return Err(ProgramError::InvalidSeeds);
+    }
+    invoke(
+        &system_instruction::transfer(funder.key, vault.key, amount),
+        &[funder.clone(), vault.clone(), system_program_account.clone()],
+    )?;
+    
+    msg!("Rent vault funded: {} lamports (balance {})", amount, vault.lamports());
+    DiamondEvent::RentVaultFunded { funder: *funder.key, amount }.emit();
+    Ok(())
+}
+
+/// Take lamports out of the rent vault (owner only)
+///
+/// Accounts: [diamond_state, owner, rent_vault, recipient, system_program]
+/// Data: amount (u64)
+pub fn withdraw_vault(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let owner = next_account_info(account_iter)?;
+    let vault = next_account_info(account_iter)?;
+    let recipient = next_account_info(account_iter)?;
+    let system_program_account = next_account_info(account_iter)?;
+    
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if system_program_account.key != &system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    let amount = u64::try_from_slice(data).map_err(|_| ProgramError::InvalidInstructionData)?;
+    
+    let state = DiamondState::load(program_id, state_account)?;
+    if !state.is_owner(owner.key) {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    let (expected, bump) = rent_vault(program_id, state_account.key);
+    if vault.key != &expected {
+        msg!("Error: Invalid rent vault. Expected: {}", expected);
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if !withdrawable(vault.lamports(), amount, Rent::get()?.minimum_balance(0)) {
+        msg!("Error: Cannot withdraw {} of {} lamports without emptying the vault", amount, vault.lamports());
+        return Err(ProgramError::InsufficientFunds);
+    }
+    invoke_signed(
+        &system_instruction::transfer(vault.key, recipient.key, amount),
+        &[vault.clone(), recipient.clone(), system_program_account.clone()],
+        &[&[RENT_VAULT_SEED, state_account.key.as_ref(), &[bump]]],
+    )?;
+    
+    msg!("Rent vault withdrawal: {} lamports to {}", amount, recipient.key);
+    DiamondEvent::RentVaultWithdrawn { recipient: *recipient.key, amount }.emit();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_withdrawable() {
+        let floor = 890_880;
+        assert!(withdrawable(2_000_000, 2_000_000, floor));
+        assert!(withdrawable(2_000_000, 1_000_000, floor));
+        assert!(!withdrawable(2_000_000, 1_500_000, floor));
+        assert!(!withdrawable(2_000_000, 2_000_001, floor));
+        assert!(withdrawable(0, 0, floor));
+    }
+    
+    #[test]
+    fn test_only_owner_or_governance_spend_the_vault() {
+        let (owner, admin, governance) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut state = DiamondState::new(owner, 255);
+        state.admins.push(admin);
+        state.governance = Some(governance);
+        assert!(state.may_spend_rent_vault(&owner) && state.may_spend_rent_vault(&governance));
+        assert!(!state.may_spend_rent_vault(&admin));
+        assert!(!state.may_spend_rent_vault(&Pubkey::new_unique()));
+    }
+}
@@ -0,0 +1,221 @@
+/*!
+ * Diamond Version Module
+ * Facet versions and the upgrade policy
+ *
+ * Every active module carries a `Semver`. A facet declares its version
+ * before its first cut (it starts at 1.0.0 otherwise). A declaration names
+ * the deployed build: the facet's attested hash (`diamond_build`) must have
+ * been verified against the program as deployed now, and the declaration
+ * is kept with that hash in the facet's record (`diamond_record`). Moving
+ * selectors to a module of the same major version is an ordinary cut that
+ * admins (and tenants, within their namespace) apply directly with
+ * `upgrade_facet`. A major change may break callers, so it only goes
+ * through a cut proposal, with the owner's or council's approval and any
+ * timelock.
+ */
+
+use borsh::BorshDeserialize;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    sysvar::Sysvar,
+};
+
+use crate::diamond_audit;
+use crate::diamond_authorizer;
+use crate::diamond_build::{self, build_status, BuildStatus};
+use crate::diamond_cut::{apply_direct_cuts, authorize_cuts, decode_with_version, FacetCut, FacetCutAction};
+use crate::diamond_digest;
+use crate::diamond_index;
+use crate::diamond_record::FacetRecord;
+use crate::diamond_state::{fixed_str, DiamondState, Semver};
+use crate::error::DiamondError;
+
+/// Declare a facet's version (owner or admin)
+///
+/// Accounts: [diamond_state, authority, facet_record, module, module_program_data]
+/// Data: module (Pubkey), version (Semver)
+///
+/// The facet's attested build must be `Verified` at its current deploy
+/// slot. A routed facet can only move within its major version.
+pub fn set_version(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let authority = next_account_info(account_iter)?;
+    let record_account = next_account_info(account_iter)?;
+    let module_account = next_account_info(account_iter)?;
+    let programdata = next_account_info(account_iter)?;
+    
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let (module, version) = <(Pubkey, Semver)>::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    if module_account.key != &module {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    if !state.has_authority(authority.key) {
+        return Err(DiamondError::UnauthorizedAccess.into());
+    }
+    let deploy_slot = diamond_build::deploy_slot(module_account, programdata)?;
+    let mut record = FacetRecord::load(program_id, record_account, state_account.key, &module)?
+        .ok_or(DiamondError::BuildNotAttested)?;
+    let hash = match record.attestation {
+        Some(attestation) if build_status(Some(&attestation), deploy_slot) == BuildStatus::Verified => attestation.hash,
+        _ => {
+            msg!("Error: Verify the attested build of {} before declaring its version", module);
+            return Err(DiamondError::BuildNotAttested.into());
+        }
+    };
+    state.set_module_version(module, version)?;
+    record.declare(version, hash, Clock::get()?.slot);
+    state.save(state_account)?;
+    record.save(record_account)?;
+    
+    msg!("Version of {}: {}", module, version);
+    Ok(())
+}
+
+/// Replace cuts moving every mutable selector of `from` to `to`
+pub fn upgrade_cuts(state: &DiamondState, from: &Pubkey, to: Pubkey, module_name: &str) -> Vec<FacetCut> {
+    state
+        .selectors
+        .iter()
+        .filter(|s| &s.module == from && !s.is_immutable)
+        .map(|s| FacetCut {
+            action: FacetCutAction::Replace,
+            selector: s.selector,
+            module: to,
+            module_name: module_name.to_string(),
+            namespace: fixed_str(&s.namespace).to_string(),
+            function_name: String::new(),
+            is_immutable: false,
+            standard_accounts: s.standard_accounts,
+        })
+        .collect()
+}
+
+/// Move a facet's mutable selectors to a new module of the same major version
+///
+/// Accounts: [diamond_state, authority, (routing digest), (selector index), (audit accounts), (authorizer)]
+/// Data: from (Pubkey), to (Pubkey), module_name (String), optional expected_version (u64)
+///
+/// Authorized like the equivalent `Replace` cuts.
+pub fn upgrade_facet(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let authority = next_account_info(account_iter)?;
+    
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let ((from, to, module_name), expected_version) = decode_with_version::<(Pubkey, Pubkey, String)>(data)?;
+    if &to == program_id {
+        return Err(DiamondError::SelfDispatchForbidden.into());
+    }
+    
+    let mut state = DiamondState::load(program_id, state_account)?;
+    state.check_cuts_allowed(Clock::get()?.slot)?;
+    state.check_config_version(expected_version)?;
+    
+    let cuts = upgrade_cuts(&state, &from, to, &module_name);
+    if cuts.is_empty() {
+        msg!("Error: {} serves no mutable selectors", from);
+        return Err(DiamondError::ModuleNotFound.into());
+    }
+    authorize_cuts(&state, authority.key, &cuts)?;
+    diamond_authorizer::authorize_cuts(state_account, &state, accounts, authority.key, &cuts)?;
+    apply_direct_cuts(&mut state, &cuts)?;
+    
+    state.save(state_account)?;
+    let index_accounts = diamond_digest::refresh(program_id, state_account.key, &state, account_iter.as_slice())?;
+    let audit_accounts = diamond_index::refresh(program_id, state_account.key, &state, index_accounts)?;
+    diamond_audit::commit_cuts(program_id, state_account, &state, audit_accounts, authority.key, &cuts)?;
+    
+    msg!("Upgraded {} -> {} ({} selectors)", from, to, cuts.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diamond_cut::apply_cuts;
+    
+    #[test]
+    fn test_major_upgrades_need_a_proposal() {
+        let (v1, v1_1, v2) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        state.set_module_version(v1_1, Semver::new(1, 1, 0)).unwrap();
+        state.set_module_version(v2, Semver::new(2, 0, 0)).unwrap();
+        apply_cuts(&mut state, &[
+            FacetCut::add([1, 0, 0, 1], v1, "token", "mint"),
+            FacetCut::add([1, 0, 0, 2], v1, "token", "burn"),
+        ])
+        .unwrap();
+        assert_eq!(state.module_version(&v1), Some(Semver::INITIAL));
+        
+        // Across majors only the proposal path (`apply_cuts`) goes through
+        let major = upgrade_cuts(&state, &v1, v2, "token");
+        assert_eq!(major.len(), 2);
+        assert_eq!(
+            apply_direct_cuts(&mut state.clone(), &major),
+            Err(DiamondError::MajorUpgradeNeedsGovernance)
+        );
+        apply_cuts(&mut state.clone(), &major).unwrap();
+        
+        let minor = upgrade_cuts(&state, &v1, v1_1, "token");
+        apply_direct_cuts(&mut state, &minor).unwrap();
+        assert!(state.selectors.iter().all(|s| s.module == v1_1));
+        assert_eq!(state.module_version(&v1_1), Some(Semver::new(1, 1, 0)));
+    }
+    
+    #[test]
+    fn test_declared_versions() {
+        let module = Pubkey::new_unique();
+        let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+        
+        // Declared ahead of the first cut, then activated at that version
+        state.set_module_version(module, Semver::new(2, 1, 0)).unwrap();
+        assert_eq!(state.module_version(&module), None);
+        assert_eq!(state.effective_version(&module), Semver::new(2, 1, 0));
+        apply_cuts(&mut state, &[FacetCut::add([1, 0, 0, 1], module, "token", "mint")]).unwrap();
+        assert_eq!(state.active_modules.len(), 1);
+        assert_eq!(state.active_modules[0].name_as_str(), "token");
+        assert_eq!(state.declared_version(&module), None);
+        
+        // In-place upgrades stay within the major
+        state.set_module_version(module, Semver::new(2, 1, 1)).unwrap();
+        assert_eq!(
+            state.set_module_version(module, Semver::new(3, 0, 0)),
+            Err(DiamondError::MajorUpgradeNeedsGovernance)
+        );
+        assert_eq!(state.module_version(&module), Some(Semver::new(2, 1, 1)));
+    }
+    
+    #[test]
+    fn test_semver_parse_and_order() {
+        assert_eq!("1.2.3".parse::<Semver>(), Ok(Semver::new(1, 2, 3)));
+        assert!("1.2".parse::<Semver>().is_err());
+        assert!("1.2.x".parse::<Semver>().is_err());
+        assert!(Semver::new(1, 10, 0) > Semver::new(1, 9, 9));
+        assert!(Semver::new(1, 3, 0).satisfies(&Semver::new(1, 2, 5)));
+        assert!(!Semver::new(2, 0, 0).satisfies(&Semver::new(1, 2, 5)));
+        assert_eq!(Semver::new(1, 2, 3).to_string(), "1.2.3");
+    }
+}
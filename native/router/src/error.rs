@@ -39,6 +39,210 @@ pub enum DiamondError {
     
     #[error("Admin capacity exceeded")]
     AdminCapacityExceeded = 6008,
+    
+    #[error("Missing standard account required by selector")]
+    MissingStandardAccount = 6009,
+    
+    #[error("Diamond state cannot be forwarded as writable")]
+    WritableDiamondState = 6010,
+    
+    #[error("Router cannot dispatch to itself")]
+    SelfDispatchForbidden = 6011,
+    
+    #[error("Invalid facet cut")]
+    InvalidCut = 6012,
+    
+    #[error("Proposal already executed")]
+    ProposalAlreadyExecuted = 6013,
+    
+    #[error("Return data exceeds the runtime limit")]
+    ReturnDataTooLarge = 6014,
+    
+    #[error("Governance authority not configured")]
+    GovernanceNotConfigured = 6015,
+    
+    #[error("Selector is reserved for a router built-in")]
+    ReservedSelector = 6016,
+    
+    #[error("Invalid config entry")]
+    InvalidConfigEntry = 6017,
+    
+    #[error("Proposal is not scheduled or its ETA has not passed")]
+    ProposalNotReady = 6018,
+    
+    #[error("No maintenance work to do")]
+    NothingToMaintain = 6019,
+    
+    #[error("Cut is outside the tenant's namespace")]
+    TenantScopeViolation = 6020,
+    
+    #[error("Maximum number of tenants reached")]
+    TenantCapacityExceeded = 6021,
+    
+    #[error("Selector width must be 2, 4 or 8 and fit every selector")]
+    InvalidSelectorWidth = 6022,
+    
+    #[error("Reentrant dispatch not permitted by the selector policies")]
+    ReentrancyForbidden = 6023,
+    
+    #[error("Subsystem not compiled into this build")]
+    FeatureDisabled = 6024,
+    
+    #[error("Dispatch exceeds the diamond's account or payload limit")]
+    DispatchLimitExceeded = 6025,
+    
+    #[error("Selector mapping does not match the routing digest")]
+    InvalidRoutingProof = 6026,
+    
+    #[error("No authority change is pending or its window has not passed")]
+    AuthorityChangeNotReady = 6027,
+    
+    #[error("Payload does not match the selector's declared size or schema")]
+    InvalidPayload = 6028,
+    
+    #[error("Routing table changed since the caller's expected version")]
+    StaleConfig = 6029,
+    
+    #[error("Dispatch would nest beyond the configured or runtime call depth")]
+    CallDepthExceeded = 6030,
+    
+    #[error("Relayed call is unsigned, expired, replayed or for another target")]
+    InvalidRelay = 6031,
+    
+    #[error("Selector cannot be pinned to the fast path")]
+    FastPathRejected = 6032,
+    
+    #[error("Facet returned more data than the diamond's return policy allows")]
+    ReturnDataOverflow = 6033,
+    
+    #[error("Diamond address does not carry the requested vanity prefix")]
+    VanityMismatch = 6034,
+    
+    #[error("Facet has no attested build hash")]
+    BuildNotAttested = 6035,
+    
+    #[error("Action requires a council motion")]
+    CouncilApprovalRequired = 6036,
+    
+    #[error("Motion lacks the approval weight the council requires")]
+    InsufficientApprovals = 6037,
+    
+    #[error("Invalid council membership or threshold")]
+    InvalidCouncil = 6038,
+    
+    #[error("Transaction also calls a program denied to this selector")]
+    DeniedProgramInTransaction = 6039,
+    
+    #[error("Facet config is already set")]
+    FacetConfigImmutable = 6040,
+    
+    #[error("An immutable selector already holds this name")]
+    ImmutableNameConflict = 6041,
+    
+    #[error("Facet dependency is not routed at the required version")]
+    DependencyMissing = 6042,
+    
+    #[error("Routed facets still depend on this namespace")]
+    DependentsRemain = 6043,
+    
+    #[error("Major version change requires a cut proposal")]
+    MajorUpgradeNeedsGovernance = 6044,
+    
+    #[error("Authorizer program did not approve")]
+    AuthorizerRejected = 6045,
+    
+    #[error("Compressed payload is malformed or inflates past the CPI data limit")]
+    InvalidCompression = 6046,
+    
+    #[error("Oracle condition for the selector does not hold")]
+    OracleConditionFailed = 6047,
+    
+    #[error("Maintenance window is empty, over, or over capacity")]
+    InvalidPauseWindow = 6048,
+    
+    #[error("Facet is killed or flagged by the diamond's health registry")]
+    FacetBlocked = 6049,
+    
+    #[error("Account roles don't match the selector's account schema")]
+    AccountRoleMismatch = 6050,
+    
+    #[error("State invariant violated (audit build)")]
+    InvariantViolated = 6051,
+    
+    #[error("Diamond state is in an older layout; run migrate_state")]
+    StateLayoutOutdated = 6052,
+    
+    #[error("Invalid, expired or foreign session")]
+    InvalidSession = 6053,
+    
+    #[error("Authority delay above the maximum")]
+    AuthorityDelayTooLong = 6054,
+}
+
+impl DiamondError {
+    /// Every variant in code order
+    pub const ALL: [DiamondError; 55] = [
+        Self::ModuleNotFound,
+        Self::UnauthorizedAccess,
+        Self::InvalidSelector,
+        Self::ModuleCapacityExceeded,
+        Self::SelectorCapacityExceeded,
+        Self::SelectorCollision,
+        Self::DiamondPaused,
+        Self::ImmutableSelector,
+        Self::AdminCapacityExceeded,
+        Self::MissingStandardAccount,
+        Self::WritableDiamondState,
+        Self::SelfDispatchForbidden,
+        Self::InvalidCut,
+        Self::ProposalAlreadyExecuted,
+        Self::ReturnDataTooLarge,
+        Self::GovernanceNotConfigured,
+        Self::ReservedSelector,
+        Self::InvalidConfigEntry,
+        Self::ProposalNotReady,
+        Self::NothingToMaintain,
+        Self::TenantScopeViolation,
+        Self::TenantCapacityExceeded,
+        Self::InvalidSelectorWidth,
+        Self::ReentrancyForbidden,
+        Self::FeatureDisabled,
+        Self::DispatchLimitExceeded,
+        Self::InvalidRoutingProof,
+        Self::AuthorityChangeNotReady,
+        Self::InvalidPayload,
+        Self::StaleConfig,
+        Self::CallDepthExceeded,
+        Self::InvalidRelay,
+        Self::FastPathRejected,
+        Self::ReturnDataOverflow,
+        Self::VanityMismatch,
+        Self::BuildNotAttested,
+        Self::CouncilApprovalRequired,
+        Self::InsufficientApprovals,
+        Self::InvalidCouncil,
+        Self::DeniedProgramInTransaction,
+        Self::FacetConfigImmutable,
+        Self::ImmutableNameConflict,
+        Self::DependencyMissing,
+        Self::DependentsRemain,
+        Self::MajorUpgradeNeedsGovernance,
+        Self::AuthorizerRejected,
+        Self::InvalidCompression,
+        Self::OracleConditionFailed,
+        Self::InvalidPauseWindow,
+        Self::FacetBlocked,
+        Self::AccountRoleMismatch,
+        Self::InvariantViolated,
+        Self::StateLayoutOutdated,
+        Self::InvalidSession,
+        Self::AuthorityDelayTooLong,
+    ];
+    
+    /// Variant for a `Custom(code)` returned by the router
+    pub fn from_code(code: u32) -> Option<Self> {
+        Self::ALL.iter().copied().find(|e| *e as u32 == code)
+    }
 }
 
 impl From<DiamondError> for ProgramError {
@@ -58,3 +262,17 @@ impl PrintProgramError for DiamondError {
         msg!(&self.to_string());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_codes_round_trip() {
+        for (i, error) in DiamondError::ALL.iter().enumerate() {
+            assert_eq!(*error as u32, 6000 + i as u32);
+            assert_eq!(DiamondError::from_code(*error as u32), Some(*error));
+        }
+        assert_eq!(DiamondError::from_code(5999), None);
+    }
+}
@@ -0,0 +1,339 @@
+/*!
+ * Diamond Events
+ * Structured events emitted via `sol_log_data` for indexers
+ */
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{log::sol_log_data, pubkey::Pubkey};
+
+use crate::diamond_audit::AuditRecord;
+use crate::diamond_state::{
+    AuthorityChange, Authorizer, BreakerMetric, Council, DispatchLimits, FacetDependency, HealthRegistry, OracleGuard,
+    PayloadSpec, PauseWindow,
+};
+use crate::selector::Selector;
+
+/// Prefix for every event payload so indexers can filter router logs
+pub const EVENT_PREFIX: &[u8; 8] = b"diamond:";
+
+/// Events emitted by the router (variant order is part of the wire format)
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub enum DiamondEvent {
+    /// Admin set changed in a single instruction
+    AdminsChanged {
+        added: Vec<Pubkey>,
+        removed: Vec<Pubkey>,
+    },
+    /// Pause authority rotated by the owner
+    PauseAuthorityChanged {
+        old: Pubkey,
+        new: Pubkey,
+    },
+    /// Cut proposal created by an owner or admin
+    ProposalCreated {
+        id: u64,
+        proposer: Pubkey,
+        cut_count: u8,
+    },
+    /// Cut proposal approved and applied
+    ProposalExecuted {
+        id: u64,
+        approver: Pubkey,
+    },
+    /// Governance authority set or rotated
+    GovernanceChanged {
+        old: Option<Pubkey>,
+        new: Option<Pubkey>,
+    },
+    /// Owner and admin authority permanently handed to governance
+    GovernanceOnlyEnabled {
+        governance: Pubkey,
+    },
+    /// Facet CPI returned an error to the router (`u64::from(ProgramError)`)
+    DispatchFailed {
+        trace_id: Option<[u8; 16]>,
+        selector: Selector,
+        facet: Pubkey,
+        error_code: u64,
+    },
+    /// Proposal scheduled for permissionless execution
+    CutScheduled {
+        id: u64,
+        eta_slot: u64,
+        tip_lamports: u64,
+    },
+    /// Keeper ran a maintenance pass
+    Maintained {
+        keeper: Pubkey,
+        pause_cleared: bool,
+        windows_pruned: u8,
+        reward: u64,
+    },
+    /// Namespace delegated to, moved between, or revoked from a tenant
+    TenantChanged {
+        namespace: [u8; 8],
+        old: Option<Pubkey>,
+        new: Option<Pubkey>,
+    },
+    /// Owner nominated a successor (`None` cancels)
+    OwnershipTransferStarted {
+        owner: Pubkey,
+        pending_owner: Option<Pubkey>,
+    },
+    /// Nominee accepted ownership
+    OwnershipTransferred {
+        old: Pubkey,
+        new: Pubkey,
+    },
+    /// Facet call completed
+    Dispatched {
+        trace_id: Option<[u8; 16]>,
+        selector: Selector,
+        facet: Pubkey,
+    },
+    /// Leaf appended to the diamond's audit tree (preimage for indexers)
+    AuditRecorded {
+        record: AuditRecord,
+    },
+    /// Facet call bounds changed by the owner
+    DispatchLimitsChanged {
+        limits: DispatchLimits,
+    },
+    /// Routing digest recomputed (enabled, or after a cut)
+    RoutingDigestUpdated {
+        root: [u8; 32],
+        selector_count: u16,
+        version: u64,
+    },
+    /// Ownership, governance or delay change announced; applies no earlier
+    /// than `effective_slot` unless vetoed
+    AuthorityChangeAnnounced {
+        change: AuthorityChange,
+        effective_slot: u64,
+    },
+    /// Announced change took effect
+    AuthorityChangeApplied {
+        change: AuthorityChange,
+    },
+    /// Announced change cancelled by the guardian, governance or owner
+    AuthorityChangeVetoed {
+        change: AuthorityChange,
+        by: Pubkey,
+    },
+    /// Alias routed to a canonical selector (`None` when removed)
+    AliasChanged {
+        alias: Selector,
+        canonical: Option<Selector>,
+    },
+    /// Payload spec declared, changed or dropped (`None`)
+    PayloadSpecChanged {
+        selector: Selector,
+        spec: Option<PayloadSpec>,
+    },
+    /// Call relayed on a user's behalf (`reimbursed` lamports paid to the relayer)
+    Relayed {
+        user: Pubkey,
+        relayer: Pubkey,
+        nonce: u64,
+        reimbursed: u64,
+    },
+    /// Selector pinned to (or unpinned from) the fast path
+    FastPathChanged {
+        selector: Selector,
+        pinned: bool,
+    },
+    /// Facet result served through its scratch account
+    ReturnOverflowed {
+        facet: Pubkey,
+        scratch: Pubkey,
+        len: u32,
+    },
+    /// Lamports added to the rent vault
+    RentVaultFunded {
+        funder: Pubkey,
+        amount: u64,
+    },
+    /// Lamports withdrawn from the rent vault by the owner
+    RentVaultWithdrawn {
+        recipient: Pubkey,
+        amount: u64,
+    },
+    /// Facet build hash attested by the owner (`None` when cleared)
+    FacetBuildAttested {
+        module: Pubkey,
+        hash: Option<[u8; 32]>,
+    },
+    /// Facet's deployed program data hashed against its attestation
+    FacetBuildChecked {
+        module: Pubkey,
+        deploy_slot: u64,
+        matched: bool,
+    },
+    /// Council established, changed by motion, or dissolved (`None`)
+    CouncilChanged {
+        council: Option<Council>,
+    },
+    /// Council member opened a motion
+    MotionCreated {
+        id: u64,
+        proposer: Pubkey,
+    },
+    /// Council member approved a motion (`weight` is the running total)
+    MotionApproved {
+        id: u64,
+        member: Pubkey,
+        weight: u32,
+    },
+    /// Motion passed and was carried out
+    MotionExecuted {
+        id: u64,
+        executor: Pubkey,
+    },
+    /// Transaction denylist replaced by the owner
+    TxDenylistChanged {
+        denylist: Vec<Pubkey>,
+    },
+    /// Selector put under (or taken out of) the transaction guard
+    SelectorGuardChanged {
+        selector: Selector,
+        guarded: bool,
+    },
+    /// Facet given its immutable config blob
+    FacetConfigSet {
+        module: Pubkey,
+        config: Vec<u8>,
+    },
+    /// Facet's declared dependencies replaced
+    DependenciesSet {
+        module: Pubkey,
+        dependencies: Vec<FacetDependency>,
+    },
+    /// Stray lamports (`mint` is `None`) or tokens swept out by the owner
+    Swept {
+        mint: Option<Pubkey>,
+        recipient: Pubkey,
+        amount: u64,
+    },
+    /// Authorizer program set, changed or cleared by the owner
+    AuthorizerChanged {
+        authorizer: Option<Authorizer>,
+    },
+    /// Tags on a selector replaced (empty when cleared)
+    TagsSet {
+        selector: Selector,
+        tags: Vec<[u8; 8]>,
+    },
+    /// Every selector carrying a tag paused or resumed
+    TagPauseChanged {
+        tag: [u8; 8],
+        paused: bool,
+    },
+    /// Oracle guard on a selector set, replaced or removed
+    OracleGuardChanged {
+        selector: Selector,
+        guard: Option<OracleGuard>,
+    },
+    /// Number of approvers needed to lift a pause changed
+    UnpauseQuorumChanged {
+        old: u8,
+        new: u8,
+    },
+    /// Maintenance window scheduled or cancelled
+    PauseWindowChanged {
+        window: PauseWindow,
+        scheduled: bool,
+    },
+    /// Facet proposed routes for its own selectors
+    RegistrationRequested {
+        id: u64,
+        facet: Pubkey,
+    },
+    /// Shared health registry set, replaced or dropped
+    HealthRegistryChanged {
+        registry: Option<HealthRegistry>,
+    },
+    /// Rent vault paid for an account the router created
+    RentVaultSpent {
+        account: Pubkey,
+        amount: u64,
+    },
+    /// Governance scheduled an override of a route (`None` removes it)
+    BreakGlassScheduled {
+        selector: Selector,
+        module: Option<Pubkey>,
+        effective_slot: u64,
+    },
+    /// A scheduled override was applied to the routing table
+    BreakGlassExecuted {
+        selector: Selector,
+        old: Pubkey,
+        new: Option<Pubkey>,
+    },
+    BreakGlassCancelled {
+        selector: Selector,
+        by: Pubkey,
+    },
+    /// A circuit breaker was set, re-armed or (zero threshold) removed
+    CircuitBreakerChanged {
+        selector: Selector,
+        metric: BreakerMetric,
+        threshold: u32,
+        window_slots: u64,
+    },
+    /// Alert: a circuit breaker paused its selector
+    CircuitBreakerTripped {
+        selector: Selector,
+        metric: BreakerMetric,
+        count: u32,
+        window_slots: u64,
+    },
+    /// `try_dispatch` skipped a call the router refused (`u64::from(ProgramError)`)
+    DispatchSkipped {
+        error_code: u64,
+    },
+    /// Executed proposal closed and its rent refunded
+    ProposalClosed {
+        id: u64,
+        rent_payer: Pubkey,
+        refund: u64,
+    },
+    /// Session key granted calls to one facet as `user` until `expiry_slot`
+    SessionOpened {
+        user: Pubkey,
+        session_key: Pubkey,
+        facet: Pubkey,
+        expiry_slot: u64,
+    },
+    /// Session revoked (or cleaned up after expiry) and its rent refunded
+    SessionClosed {
+        user: Pubkey,
+        session_key: Pubkey,
+    },
+}
+
+impl DiamondEvent {
+    /// Emit the event as `Program data: <prefix> <borsh(event)>`
+    pub fn emit(&self) {
+        if let Ok(encoded) = borsh::to_vec(self) {
+            sol_log_data(&[EVENT_PREFIX, &encoded]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_round_trip() {
+        let event = DiamondEvent::AdminsChanged {
+            added: vec![Pubkey::new_unique()],
+            removed: vec![],
+        };
+        let encoded = borsh::to_vec(&event).unwrap();
+        
+        // Variant index leads the payload
+        assert_eq!(encoded[0], 0);
+        assert_eq!(DiamondEvent::try_from_slice(&encoded).unwrap(), event);
+    }
+}
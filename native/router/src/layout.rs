@@ -0,0 +1,343 @@
+/*!
+ * Account Layout Audit
+ * Compare hand-maintained SPACE constants against worst-case encodings
+ *
+ * Each account type is filled to capacity (full vecs, `Some` options,
+ * maximum-length strings) and Borsh-encoded; the encoded length plus any
+ * reserved header must equal the declared SPACE exactly. `DiamondState` is
+ * created by CPI, so its SPACE must also stay within
+ * `MAX_PERMITTED_DATA_INCREASE` (10240 bytes); it sits at 10238, so a new
+ * state field has to come out of an existing capacity.
+ */
+
+use borsh::BorshSerialize;
+use solana_program::pubkey::Pubkey;
+
+use crate::diamond_config::{ConfigEntry, FacetConfig, MAX_FACET_CONFIG_LEN};
+use crate::diamond_cut::{FacetCut, FacetCutAction};
+use crate::diamond_digest::RoutingDigest;
+use crate::diamond_index::SelectorIndex;
+use crate::diamond_fast_path::FastPath;
+use crate::diamond_record::{FacetRecord, MAX_RETURN_SCHEMAS, MAX_VERSION_RECORDS};
+use crate::diamond_relay::RelayNonce;
+#[cfg(feature = "governance")]
+use crate::diamond_council::{CouncilAccount, CouncilAction, Motion};
+#[cfg(feature = "governance")]
+use crate::diamond_state::{Council, CouncilMember};
+#[cfg(feature = "governance")]
+use crate::diamond_proposal::CutProposal;
+use crate::diamond_state::{
+    namespace_bytes, ActiveCall, AuthorityChange, Authorizer, BreakGlass, BreakerMetric, BuildAttestation, BuildCheck,
+    CircuitBreaker, DiamondState, DispatchLimits, FacetDependency, HealthRegistry,
+    ModuleMeta, OracleGuard, PauseWindow, PayloadSpec, PendingAuthorityChange, Reentrancy, ReturnOverflowPolicy,
+    SelectorAlias, SelectorMapping, SelectorTag, Semver, Tenant, TxGuard, VersionRecord,
+};
+
+/// Declared vs required size of one account type
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpaceReport {
+    pub account: &'static str,
+    pub declared: usize,
+    pub required: usize,
+}
+
+impl SpaceReport {
+    fn measure<T: BorshSerialize>(
+        account: &'static str,
+        declared: usize,
+        header: usize,
+        worst_case: &T,
+    ) -> Self {
+        let encoded = borsh::to_vec(worst_case).map(|v| v.len()).unwrap_or(usize::MAX);
+        Self {
+            account,
+            declared,
+            required: encoded.saturating_add(header),
+        }
+    }
+    
+    /// Allocation can't hold a full account
+    pub fn is_undersized(&self) -> bool {
+        self.declared < self.required
+    }
+    
+    pub fn is_exact(&self) -> bool {
+        self.declared == self.required
+    }
+}
+
+/// Largest valid cut (every string at its length limit)
+pub fn worst_case_cut() -> FacetCut {
+    FacetCut {
+        action: FacetCutAction::Add,
+        selector: [0xFF; 8].into(),
+        module: Pubkey::new_unique(),
+        module_name: "m".repeat(32),
+        namespace: "n".repeat(8),
+        function_name: "f".repeat(64),
+        is_immutable: true,
+        standard_accounts: u8::MAX,
+    }
+}
+
+#[cfg(feature = "governance")]
+fn worst_case_council() -> Council {
+    Council {
+        members: (0..Council::MAX_MEMBERS)
+            .map(|_| CouncilMember { key: Pubkey::new_unique(), weight: u16::MAX })
+            .collect(),
+        threshold: u32::MAX,
+    }
+}
+
+/// Diamond state with every collection full and every option set
+pub fn worst_case_state() -> DiamondState {
+    let mut state = DiamondState::new(Pubkey::new_unique(), 255);
+    state.admins = (0..DiamondState::MAX_ADMINS).map(|_| Pubkey::new_unique()).collect();
+    state.active_modules = (0..DiamondState::MAX_MODULES)
+        .map(|i| ModuleMeta::new(&"m".repeat(32), Pubkey::new_unique(), Semver::new(i as u16, u16::MAX, u16::MAX)))
+        .collect();
+    state.selectors = (0..DiamondState::MAX_SELECTORS)
+        .map(|i| {
+            SelectorMapping::new_with_namespace(
+                namespace_bytes("nnnnnnnn").unwrap(),
+                (i as u64).to_le_bytes(),
+                Pubkey::new_unique(),
+                &"f".repeat(64),
+                true,
+            )
+        })
+        .collect();
+    state.aliases = (0..DiamondState::MAX_ALIASES)
+        .map(|i| SelectorAlias { alias: [0xAA, i as u8].into(), canonical: [0; 8].into() })
+        .collect();
+    state.pause_expiry_slot = Some(u64::MAX);
+    state.next_proposal_id = u64::MAX;
+    state.governance = Some(Pubkey::new_unique());
+    state.tenants = (0..DiamondState::MAX_TENANTS)
+        .map(|i| Tenant { namespace: [i as u8 + 1; 8], authority: Pubkey::new_unique() })
+        .collect();
+    state.pending_owner = Some(Pubkey::new_unique());
+    state.audit_tree = Some(Pubkey::new_unique());
+    state.dispatch_limits = DispatchLimits { max_accounts: u16::MAX, max_payload_len: u32::MAX, max_depth: u8::MAX };
+    state.active_call = Some(ActiveCall { facet: Pubkey::new_unique(), reentrancy: Reentrancy::Allowed, stack_height: u8::MAX });
+    state.routing_digest = true;
+    state.selector_index = true;
+    state.break_glass = Some(BreakGlass { selector: [0xBE; 8].into(), module: Some(Pubkey::new_unique()), effective_slot: u64::MAX });
+    state.pending_change = Some(PendingAuthorityChange {
+        change: AuthorityChange::Governance(Some(Pubkey::new_unique())),
+        effective_slot: u64::MAX,
+    });
+    state.config_version = u64::MAX;
+    state.relay_reimbursement = u64::MAX;
+    state.return_overflow = ReturnOverflowPolicy::Reject;
+    state.vanity_nonce = Some(u64::MAX);
+    state.has_council = true;
+    state.tx_guard = TxGuard {
+        denylist: (0..TxGuard::MAX_DENYLIST).map(|_| Pubkey::new_unique()).collect(),
+        selectors: (0..TxGuard::MAX_SELECTORS).map(|i| [0xCC, i as u8].into()).collect(),
+    };
+    state.dependencies = (0..DiamondState::MAX_DEPENDENCIES)
+        .map(|i| FacetDependency { module: Pubkey::new_unique(), namespace: [i as u8 + 1; 8], min_version: Semver::new(u16::MAX, u16::MAX, u16::MAX) })
+        .collect();
+    state.authorizer = Some(Authorizer {
+        program: Pubkey::new_unique(),
+        protected: (0..Authorizer::MAX_PROTECTED).map(|i| [0xDD, i as u8].into()).collect(),
+    });
+    state.tags = (0..DiamondState::MAX_TAGS)
+        .map(|i| SelectorTag { tag: [0xEE; 8], selector: [0xEE, i as u8].into() })
+        .collect();
+    state.paused_tags = (0..DiamondState::MAX_PAUSED_TAGS).map(|i| [i as u8 + 1; 8]).collect();
+    state.oracle_guards = (0..DiamondState::MAX_ORACLE_GUARDS)
+        .map(|i| OracleGuard {
+            selector: [0xAB, i as u8].into(),
+            feed: Pubkey::new_unique(),
+            max_age_secs: u32::MAX,
+            max_confidence_bps: u16::MAX,
+        })
+        .collect();
+    state.unpause_quorum = u8::MAX;
+    state.pause_windows = (0..DiamondState::MAX_PAUSE_WINDOWS)
+        .map(|i| PauseWindow { start_slot: i as u64, end_slot: u64::MAX })
+        .collect();
+    state.circuit_breakers = (0..DiamondState::MAX_CIRCUIT_BREAKERS)
+        .map(|i| CircuitBreaker {
+            selector: [0xCB, i as u8].into(),
+            metric: BreakerMetric::Calls,
+            threshold: u32::MAX,
+            window_slots: u64::MAX,
+            window_start: u64::MAX,
+            count: u32::MAX,
+            tripped_slot: Some(u64::MAX),
+        })
+        .collect();
+    state.health_registry = Some(HealthRegistry { program: Pubkey::new_unique(), min_severity: u8::MAX });
+    state.payload_specs = (0..DiamondState::MAX_PAYLOAD_SPECS)
+        .map(|i| PayloadSpec { selector: [0xBB, i as u8].into(), min_len: 1, max_len: u16::MAX, schema_hash: [0xFF; 8] })
+        .collect();
+    state
+}
+
+/// Audit every account type the router allocates
+pub fn audit() -> Vec<SpaceReport> {
+    #[cfg(feature = "governance")]
+    let proposal = CutProposal {
+        diamond: Pubkey::new_unique(),
+        id: u64::MAX,
+        proposer: Pubkey::new_unique(),
+        cuts: vec![worst_case_cut(); CutProposal::MAX_CUTS],
+        executed: true,
+        bump: 255,
+        eta_slot: Some(u64::MAX),
+        tip_lamports: u64::MAX,
+        tip_payer: Pubkey::new_unique(),
+        rent_payer: Pubkey::new_unique(),
+    };
+    let entry = ConfigEntry {
+        diamond: Pubkey::new_unique(),
+        namespace: [0xFF; 8],
+        key: [0xFF; 32],
+        value: vec![0xFF; ConfigEntry::MAX_VALUE_LEN],
+        updated_by: Pubkey::new_unique(),
+        bump: 255,
+    };
+    let facet_config = FacetConfig {
+        diamond: [0xFF; 32],
+        facet: [0xFF; 32],
+        data: vec![0xFF; MAX_FACET_CONFIG_LEN],
+        bump: 255,
+    };
+    let routing_digest = RoutingDigest {
+        diamond: Pubkey::new_unique(),
+        root: [0xFF; 32],
+        selector_count: u16::MAX,
+        version: u64::MAX,
+        updated_slot: u64::MAX,
+        bump: 255,
+    };
+    let relay_nonce = RelayNonce {
+        diamond: Pubkey::new_unique(),
+        user: Pubkey::new_unique(),
+        next_nonce: u64::MAX,
+        bump: 255,
+    };
+    let module = Pubkey::new_unique();
+    let facet_record = FacetRecord {
+        diamond: Pubkey::new_unique(),
+        module,
+        attestation: Some(BuildAttestation {
+            module,
+            hash: [0xFF; 32],
+            last_check: Some(BuildCheck { deploy_slot: u64::MAX, matched: true }),
+        }),
+        versions: (0..MAX_VERSION_RECORDS)
+            .map(|_| VersionRecord { module, version: Semver::new(u16::MAX, u16::MAX, u16::MAX), hash: [0xFF; 32], slot: u64::MAX })
+            .collect(),
+        return_schemas: vec![([0xFF; 8].into(), [0xFF; 8]); MAX_RETURN_SCHEMAS],
+        bump: 255,
+    };
+    let fast_path = FastPath {
+        diamond: Pubkey::new_unique(),
+        selector_width: 8,
+        entries: worst_case_state().selectors[..FastPath::MAX_ENTRIES].to_vec(),
+        bump: 255,
+    };
+    
+    #[allow(unused_mut)]
+    let mut reports = vec![
+        // DiamondState keeps 8 leading bytes reserved for an Anchor-style discriminator
+        SpaceReport::measure("DiamondState", DiamondState::SPACE, 8, &worst_case_state()),
+        SpaceReport::measure("FacetCut", FacetCut::MAX_SIZE, 0, &worst_case_cut()),
+        SpaceReport::measure("ConfigEntry", ConfigEntry::SPACE, 0, &entry),
+        SpaceReport::measure("FacetConfig", FacetConfig::MAX_ENCODED_LEN, 0, &facet_config),
+        SpaceReport::measure("RoutingDigest", RoutingDigest::SPACE, 0, &routing_digest),
+        SpaceReport::measure("RelayNonce", RelayNonce::SPACE, 0, &relay_nonce),
+        SpaceReport::measure("FacetRecord", FacetRecord::SPACE, 0, &facet_record),
+        SpaceReport::measure("FastPath", FastPath::SPACE, 0, &fast_path),
+        SpaceReport::measure("SelectorIndex", SelectorIndex::SPACE, 0, &SelectorIndex::build(Pubkey::new_unique(), &worst_case_state(), 255)),
+    ];
+    #[cfg(feature = "governance")]
+    reports.push(SpaceReport::measure("CutProposal", CutProposal::SPACE, 0, &proposal));
+    #[cfg(feature = "governance")]
+    reports.push(SpaceReport::measure(
+        "CouncilAccount",
+        CouncilAccount::SPACE,
+        0,
+        &CouncilAccount { diamond: Pubkey::new_unique(), council: worst_case_council(), bump: 255 },
+    ));
+    #[cfg(feature = "governance")]
+    reports.push(SpaceReport::measure(
+        "Motion",
+        Motion::SPACE,
+        0,
+        &Motion {
+            diamond: Pubkey::new_unique(),
+            id: u64::MAX,
+            proposer: Pubkey::new_unique(),
+            action: CouncilAction::SetCouncil(Some(worst_case_council())),
+            approvals: vec![Pubkey::new_unique(); Council::MAX_MEMBERS],
+            executed: true,
+            bump: 255,
+        },
+    ));
+    reports
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_space_constants_exact() {
+        for report in audit() {
+            assert!(report.is_exact(), "{:?}", report);
+        }
+    }
+    
+    #[test]
+    fn test_state_fits_cpi_allocation() {
+        use solana_program::entrypoint::MAX_PERMITTED_DATA_INCREASE;
+        
+        // The state PDA is created by CPI, which can't allocate more at once
+        let state = audit().into_iter().find(|r| r.account == "DiamondState").unwrap();
+        assert!(
+            state.declared <= MAX_PERMITTED_DATA_INCREASE,
+            "DiamondState::SPACE is {} bytes, past the {}-byte budget `initialize` can allocate by CPI; shrink a capacity to make room: {:?}",
+            state.declared,
+            MAX_PERMITTED_DATA_INCREASE,
+            state
+        );
+    }
+    
+    #[test]
+    fn test_shared_layout_matches_encodings() {
+        use diamond_constants::layout::*;
+        
+        fn len<T: BorshSerialize>(value: &T) -> usize {
+            borsh::to_vec(value).unwrap().len()
+        }
+        
+        let state = worst_case_state();
+        assert_eq!(len(&state.active_modules[0]), MODULE_META_LEN);
+        assert_eq!(len(&state.selectors[0]), SELECTOR_MAPPING_LEN);
+        assert_eq!(len(&state.aliases[0]), SELECTOR_ALIAS_LEN);
+        assert_eq!(len(&state.tags[0]), SELECTOR_TAG_LEN);
+        assert_eq!(len(&state.tenants[0]), TENANT_LEN);
+        assert_eq!(len(state.active_call.as_ref().unwrap()), ACTIVE_CALL_LEN);
+        assert_eq!(len(&state.dispatch_limits), DISPATCH_LIMITS_LEN);
+        assert_eq!(len(&state.payload_specs[0]), PAYLOAD_SPEC_LEN);
+        assert_eq!(len(&state.dependencies[0]), FACET_DEPENDENCY_LEN);
+        let check = BuildCheck { deploy_slot: 0, matched: true };
+        assert_eq!(len(&BuildAttestation { module: Pubkey::new_unique(), hash: [0; 32], last_check: Some(check) }), BUILD_ATTESTATION_LEN);
+        assert_eq!(len(&VersionRecord { module: Pubkey::new_unique(), version: Semver::INITIAL, hash: [0; 32], slot: 0 }), VERSION_RECORD_LEN);
+        assert_eq!(len(&state.oracle_guards[0]), ORACLE_GUARD_LEN);
+        assert_eq!(len(&state.pause_windows[0]), PAUSE_WINDOW_LEN);
+        assert_eq!(len(&state.health_registry.unwrap()), HEALTH_REGISTRY_LEN);
+        assert_eq!(len(&state.circuit_breakers[0]), CIRCUIT_BREAKER_LEN);
+        
+        // Offsets the core crate reads mappings at
+        let mapping = borsh::to_vec(&state.selectors[0]).unwrap();
+        assert_eq!(mapping[MAPPING_SELECTOR_OFFSET..][..SELECTOR_LEN], state.selectors[0].selector.as_bytes(8)[..]);
+        assert_eq!(mapping[MAPPING_MODULE_OFFSET..][..PUBKEY_LEN], state.selectors[0].module.to_bytes());
+    }
+}
@@ -9,7 +9,6 @@
 
 use solana_program::{
     account_info::AccountInfo,
-    entrypoint,
     entrypoint::ProgramResult,
     msg,
     program_error::ProgramError,
@@ -17,26 +16,59 @@ use solana_program::{
 };
 
 // Module declarations
+pub mod anchor_compat;
+pub mod diamond_audit;
+pub mod diamond_authorizer;
+#[cfg(feature = "governance")]
+pub mod diamond_break_glass;
+pub mod builtins;
 pub mod diamond_state;
+pub mod diamond_breaker;
+pub mod diamond_build;
 pub mod diamond_router;
+pub mod diamond_context;
 pub mod diamond_cut;
+pub mod diamond_dependency;
+pub mod diamond_digest;
+pub mod diamond_fast_path;
+pub mod diamond_guard;
+pub mod diamond_config;
+pub mod diamond_health;
+pub mod diamond_index;
+pub mod diamond_invariants;
+pub mod diamond_loupe;
+pub mod diamond_maintenance;
+pub mod diamond_migrate;
+pub mod diamond_oracle;
+#[cfg(feature = "governance")]
+pub mod diamond_council;
+#[cfg(feature = "governance")]
+pub mod diamond_proposal;
+pub mod diamond_record;
+pub mod diamond_relay;
+pub mod diamond_return;
+pub mod diamond_session;
+pub mod diamond_signing;
+pub mod diamond_sweep;
+pub mod diamond_tags;
+pub mod diamond_vault;
+pub mod diamond_version;
 pub mod error;
-
-use error::DiamondError;
+pub mod events;
+pub use diamond_constants as constants;
+pub use diamond_core::{compress, selector};
+#[cfg(not(target_os = "solana"))]
+pub mod layout;
 
 // Program ID (placeholder - replace with actual deployed program ID)
 solana_program::declare_id!("DiamRouter111111111111111111111111111111111");
 
 // Instruction discriminators (8 bytes, Anchor-compatible format)
-pub const INITIALIZE_DISCRIMINATOR: [u8; 8] = [0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
-pub const DISPATCH_DISCRIMINATOR: [u8; 8] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
-pub const ADD_MODULE_DISCRIMINATOR: [u8; 8] = [0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
-pub const REMOVE_MODULE_DISCRIMINATOR: [u8; 8] = [0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
-pub const ADD_ADMIN_DISCRIMINATOR: [u8; 8] = [0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
-pub const PAUSE_DISCRIMINATOR: [u8; 8] = [0x06, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+pub use diamond_constants::discriminators::*;
 
 // Program entrypoint
-entrypoint!(process_instruction);
+#[cfg(not(feature = "no-entrypoint"))]
+solana_program::entrypoint!(process_instruction);
 
 /// Main instruction processor
 pub fn process_instruction(
@@ -58,7 +90,20 @@ pub fn process_instruction(
         .try_into()
         .map_err(|_| ProgramError::InvalidInstructionData)?;
     
-    // Route to handler
+    if cfg!(feature = "audit") {
+        diamond_invariants::checked(program_id, accounts, || route_instruction(program_id, accounts, discriminator, data))
+    } else {
+        route_instruction(program_id, accounts, discriminator, data)
+    }
+}
+
+/// Route to the handler of `discriminator`
+fn route_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    discriminator: [u8; 8],
+    data: &[u8],
+) -> ProgramResult {
     match discriminator {
         INITIALIZE_DISCRIMINATOR => {
             msg!("Instruction: Initialize");
@@ -84,6 +129,191 @@ pub fn process_instruction(
             msg!("Instruction: Pause");
             diamond_state::pause(program_id, accounts, data)
         }
+        LOOKUP_FUNCTION_DISCRIMINATOR => {
+            msg!("Instruction: LookupFunction");
+            diamond_loupe::lookup_function(program_id, accounts, data)
+        }
+        SET_ADMINS_DISCRIMINATOR => {
+            msg!("Instruction: SetAdmins");
+            diamond_state::set_admins(program_id, accounts, data)
+        }
+        REMOVE_ADMINS_DISCRIMINATOR => {
+            msg!("Instruction: RemoveAdmins");
+            diamond_state::remove_admins(program_id, accounts, data)
+        }
+        SET_PAUSE_AUTHORITY_DISCRIMINATOR => {
+            msg!("Instruction: SetPauseAuthority");
+            diamond_state::set_pause_authority(program_id, accounts, data)
+        }
+        #[cfg(feature = "governance")]
+        PROPOSE_CUT_DISCRIMINATOR => {
+            msg!("Instruction: ProposeCut");
+            diamond_proposal::propose_cut(program_id, accounts, data)
+        }
+        #[cfg(feature = "governance")]
+        APPROVE_CUT_DISCRIMINATOR => {
+            msg!("Instruction: ApproveCut");
+            diamond_proposal::approve_cut(program_id, accounts, data)
+        }
+        #[cfg(feature = "governance")]
+        DESCRIBE_PROPOSAL_DISCRIMINATOR => {
+            msg!("Instruction: DescribeProposal");
+            diamond_proposal::describe_proposal(program_id, accounts, data)
+        }
+        #[cfg(feature = "governance")]
+        SET_GOVERNANCE_DISCRIMINATOR => {
+            msg!("Instruction: SetGovernance");
+            diamond_state::set_governance(program_id, accounts, data)
+        }
+        #[cfg(feature = "governance")]
+        ENABLE_GOVERNANCE_ONLY_DISCRIMINATOR => {
+            msg!("Instruction: EnableGovernanceOnly");
+            diamond_state::enable_governance_only(program_id, accounts, data)
+        }
+        MIGRATE_FROM_ANCHOR_DISCRIMINATOR => {
+            msg!("Instruction: MigrateFromAnchor");
+            anchor_compat::migrate_from_anchor(program_id, accounts, data)
+        }
+        TRY_DISPATCH_DISCRIMINATOR => {
+            msg!("Instruction: TryDispatch");
+            diamond_router::try_dispatch(program_id, accounts, data)
+        }
+        #[cfg(feature = "governance")]
+        SCHEDULE_CUT_DISCRIMINATOR => {
+            msg!("Instruction: ScheduleCut");
+            diamond_proposal::schedule_cut(program_id, accounts, data)
+        }
+        #[cfg(feature = "governance")]
+        EXECUTE_CUT_DISCRIMINATOR => {
+            msg!("Instruction: ExecuteCut");
+            diamond_proposal::execute_cut(program_id, accounts, data)
+        }
+        MAINTAIN_DISCRIMINATOR => {
+            msg!("Instruction: Maintain");
+            diamond_maintenance::maintain(program_id, accounts, data)
+        }
+        SET_TENANT_DISCRIMINATOR => {
+            msg!("Instruction: SetTenant");
+            diamond_state::set_tenant(program_id, accounts, data)
+        }
+        TENANT_CUT_DISCRIMINATOR => {
+            msg!("Instruction: TenantCut");
+            diamond_cut::tenant_cut(program_id, accounts, data)
+        }
+        TRANSFER_OWNERSHIP_DISCRIMINATOR => {
+            msg!("Instruction: TransferOwnership");
+            diamond_state::transfer_ownership(program_id, accounts, data)
+        }
+        ACCEPT_OWNERSHIP_DISCRIMINATOR => {
+            msg!("Instruction: AcceptOwnership");
+            diamond_state::accept_ownership(program_id, accounts, data)
+        }
+        DISPATCH_TRACED_DISCRIMINATOR => {
+            msg!("Instruction: DispatchTraced");
+            diamond_router::dispatch_traced(program_id, accounts, data)
+        }
+        #[cfg(feature = "audit-log")]
+        DISPATCH_AUDITED_DISCRIMINATOR => {
+            msg!("Instruction: DispatchAudited");
+            diamond_audit::dispatch_audited(program_id, accounts, data)
+        }
+        ALLOCATE_SELECTOR_DISCRIMINATOR => {
+            msg!("Instruction: AllocateSelector");
+            diamond_cut::allocate_selector(program_id, accounts, data)
+        }
+        RESOLVE_SELECTOR_DISCRIMINATOR => {
+            msg!("Instruction: ResolveSelector");
+            diamond_loupe::resolve_selector(program_id, accounts, data)
+        }
+        DISPATCH_SIGNED_DISCRIMINATOR => {
+            msg!("Instruction: DispatchSigned");
+            diamond_router::dispatch_signed(program_id, accounts, data)
+        }
+        DISPATCH_PROVEN_DISCRIMINATOR => {
+            msg!("Instruction: DispatchProven");
+            diamond_router::dispatch_proven(program_id, accounts, data)
+        }
+        SET_PAUSE_SCOPES_DISCRIMINATOR => {
+            msg!("Instruction: SetPauseScopes");
+            diamond_state::set_pause_scopes(program_id, accounts, data)
+        }
+        DISPATCH_CHECKED_DISCRIMINATOR => {
+            msg!("Instruction: DispatchChecked");
+            diamond_router::dispatch_checked(program_id, accounts, data)
+        }
+        DISPATCH_RELAYED_DISCRIMINATOR => {
+            msg!("Instruction: DispatchRelayed");
+            diamond_relay::dispatch_relayed(program_id, accounts, data)
+        }
+        DISPATCH_FAST_DISCRIMINATOR => {
+            msg!("Instruction: DispatchFast");
+            diamond_fast_path::dispatch_fast(program_id, accounts, data)
+        }
+        FUND_VAULT_DISCRIMINATOR => {
+            msg!("Instruction: FundVault");
+            diamond_vault::fund_vault(program_id, accounts, data)
+        }
+        WITHDRAW_VAULT_DISCRIMINATOR => {
+            msg!("Instruction: WithdrawVault");
+            diamond_vault::withdraw_vault(program_id, accounts, data)
+        }
+        #[cfg(feature = "governance")]
+        PROPOSE_MOTION_DISCRIMINATOR => {
+            msg!("Instruction: ProposeMotion");
+            diamond_council::propose_motion(program_id, accounts, data)
+        }
+        #[cfg(feature = "governance")]
+        APPROVE_MOTION_DISCRIMINATOR => {
+            msg!("Instruction: ApproveMotion");
+            diamond_council::approve_motion(program_id, accounts, data)
+        }
+        #[cfg(feature = "governance")]
+        EXECUTE_MOTION_DISCRIMINATOR => {
+            msg!("Instruction: ExecuteMotion");
+            diamond_council::execute_motion(program_id, accounts, data)
+        }
+        SWEEP_LAMPORTS_DISCRIMINATOR => {
+            msg!("Instruction: SweepLamports");
+            diamond_sweep::sweep_lamports(program_id, accounts, data)
+        }
+        SWEEP_TOKEN_DISCRIMINATOR => {
+            msg!("Instruction: SweepToken");
+            diamond_sweep::sweep_token(program_id, accounts, data)
+        }
+        DISPATCH_COMPRESSED_DISCRIMINATOR => {
+            msg!("Instruction: DispatchCompressed");
+            diamond_router::dispatch_compressed(program_id, accounts, data)
+        }
+        DISPATCH_MAPPED_DISCRIMINATOR => {
+            msg!("Instruction: DispatchMapped");
+            diamond_router::dispatch_mapped(program_id, accounts, data)
+        }
+        DISPATCH_INDEXED_DISCRIMINATOR => {
+            msg!("Instruction: DispatchIndexed");
+            diamond_router::dispatch_indexed(program_id, accounts, data)
+        }
+        #[cfg(feature = "governance")]
+        REQUEST_REGISTRATION_DISCRIMINATOR => {
+            msg!("Instruction: RequestRegistration");
+            diamond_proposal::request_registration(program_id, accounts, data)
+        }
+        MIGRATE_STATE_DISCRIMINATOR => {
+            msg!("Instruction: MigrateState");
+            diamond_migrate::migrate_state(program_id, accounts, data)
+        }
+        #[cfg(feature = "governance")]
+        CLOSE_PROPOSAL_DISCRIMINATOR => {
+            msg!("Instruction: CloseProposal");
+            diamond_proposal::close_proposal(program_id, accounts, data)
+        }
+        OPEN_SESSION_DISCRIMINATOR => {
+            msg!("Instruction: OpenSession");
+            diamond_session::open_session(program_id, accounts, data)
+        }
+        DISPATCH_SESSION_DISCRIMINATOR => {
+            msg!("Instruction: DispatchSession");
+            diamond_session::dispatch_session(program_id, accounts, data)
+        }
         _ => {
             msg!("Error: Unknown instruction discriminator");
             Err(ProgramError::InvalidInstructionData)
@@ -94,7 +324,7 @@ pub fn process_instruction(
 #[cfg(test)]
 mod tests {
     use super::*;
-
+    
     #[test]
     fn test_discriminators() {
         assert_eq!(INITIALIZE_DISCRIMINATOR.len(), 8);
@@ -315,7 +315,7 @@ pub fn remove_module(
 #[cfg(test)]
 mod tests {
     use super::*;
-
+    
     #[test]
     fn test_selector_extraction() {
         let ix_data = vec![0x01, 0x02, 0x03, 0x04, 0x05, 0x06];
@@ -30,7 +30,7 @@ impl SelectorMapping {
     ) -> Self {
         Self::new_with_namespace([0u8; 8], selector, module, function_name, is_immutable)
     }
-
+    
     pub fn new_with_namespace(
         namespace: [u8; 8],
         selector: [u8; 4],
@@ -42,7 +42,7 @@ impl SelectorMapping {
         let bytes = function_name.as_bytes();
         let len = bytes.len().min(64);
         name_bytes[..len].copy_from_slice(&bytes[..len]);
-
+        
         Self {
             namespace,
             selector,
@@ -51,7 +51,7 @@ impl SelectorMapping {
             is_immutable,
         }
     }
-
+    
     pub fn function_name_as_str(&self) -> &str {
         let end = self.function_name.iter()
             .position(|&c| c == 0)
@@ -75,7 +75,7 @@ impl ModuleMeta {
         let bytes = name.as_bytes();
         let len = bytes.len().min(32);
         name_bytes[..len].copy_from_slice(&bytes[..len]);
-
+        
         Self {
             name: name_bytes,
             address,
@@ -83,7 +83,7 @@ impl ModuleMeta {
             is_active: true,
         }
     }
-
+    
     pub fn name_as_str(&self) -> &str {
         let end = self.name.iter().position(|&c| c == 0).unwrap_or(self.name.len());
         std::str::from_utf8(&self.name[..end]).unwrap_or("")
@@ -114,7 +114,7 @@ impl DiamondState {
     pub const MAX_ADMINS: usize = 10;
     pub const MAX_MODULES: usize = 20;
     pub const MAX_SELECTORS: usize = 50;
-
+    
     /// Calculate required space for account
     pub const SPACE: usize = 
         8 +      // Discriminator (Anchor compatibility)
@@ -132,7 +132,7 @@ impl DiamondState {
         33 +     // governance_realm
         33 +     // governance_program
         (5 * 114); // hot_cache array
-
+    
     /// Initialize new diamond state
     pub fn new(owner: Pubkey, bump: u8) -> Self {
         Self {
@@ -152,7 +152,7 @@ impl DiamondState {
             hot_cache: [None, None, None, None, None],
         }
     }
-
+    
     /// Get module by selector (core dispatch logic)
     pub fn get_module_by_selector(&self, selector: [u8; 4]) -> Option<Pubkey> {
         // Check hot cache first (performance optimization)
@@ -163,13 +163,13 @@ impl DiamondState {
                 }
             }
         }
-
+        
         // Linear search through selectors
         self.selectors.iter()
             .find(|s| s.selector == selector)
             .map(|s| s.module)
     }
-
+    
     /// Add a module (validates capacity)
     pub fn add_module(&mut self, meta: ModuleMeta) -> Result<(), ProgramError> {
         if self.active_modules.len() >= Self::MAX_MODULES {
@@ -178,7 +178,7 @@ impl DiamondState {
         self.active_modules.push(meta);
         Ok(())
     }
-
+    
     /// Add a selector mapping (validates capacity)
     pub fn add_selector(&mut self, mapping: SelectorMapping) -> Result<(), ProgramError> {
         if self.selectors.len() >= Self::MAX_SELECTORS {
@@ -193,17 +193,17 @@ impl DiamondState {
         self.selectors.push(mapping);
         Ok(())
     }
-
+    
     /// Check if caller is owner
     pub fn is_owner(&self, pubkey: &Pubkey) -> bool {
         &self.owner == pubkey
     }
-
+    
     /// Check if caller is admin
     pub fn is_admin(&self, pubkey: &Pubkey) -> bool {
         self.admins.contains(pubkey)
     }
-
+    
     /// Check if caller has authority (owner or admin)
     pub fn has_authority(&self, pubkey: &Pubkey) -> bool {
         self.is_owner(pubkey) || self.is_admin(pubkey)
@@ -213,7 +213,7 @@ impl DiamondState {
 #[cfg(test)]
 mod tests {
     use super::*;
-
+    
     #[test]
     fn test_selector_mapping_creation() {
         let mapping = SelectorMapping::new(
@@ -225,7 +225,7 @@ mod tests {
         assert_eq!(mapping.selector, [0x01, 0x02, 0x03, 0x04]);
         assert_eq!(mapping.function_name_as_str(), "test_function");
     }
-
+    
     #[test]
     fn test_diamond_state_initialization() {
         let owner = Pubkey::default();
@@ -236,7 +236,7 @@ mod tests {
         assert_eq!(state.is_paused, false);
         assert_eq!(state.selectors.len(), 0);
     }
-
+    
     #[test]
     fn test_selector_lookup() {
         let owner = Pubkey::default();
@@ -0,0 +1,131 @@
+//! Borsh compatibility between the Anchor router's `DiamondState` account
+//! and the native router's state.
+
+use borsh::BorshSerialize;
+use diamond_router_native::anchor_compat::{
+    AnchorDiamondState, AnchorModuleMeta, AnchorSelectorMapping,
+};
+use diamond_router_native::diamond_state::{DiamondState, ModuleMeta, SelectorMapping, Semver};
+use solana_program::pubkey::Pubkey;
+
+fn anchor_state() -> AnchorDiamondState {
+    let facet = Pubkey::new_unique();
+    AnchorDiamondState {
+        owner: Pubkey::new_unique(),
+        selectors: vec![
+            AnchorSelectorMapping {
+                selector: [1, 2, 3, 4],
+                module: facet,
+                function_name: "increment".to_string(),
+                is_immutable: false,
+            },
+            AnchorSelectorMapping {
+                selector: [5, 6, 7, 8],
+                module: facet,
+                function_name: "get_count".to_string(),
+                is_immutable: true,
+            },
+        ],
+        modules: vec![AnchorModuleMeta {
+            name: "increment".to_string(),
+            address: facet,
+            version: 1,
+        }],
+        bump: 254,
+        is_paused: true,
+        pause_expiry_slot: Some(1_000),
+        pause_authority: Pubkey::new_unique(),
+        unpause_quorum: 0,
+        unpause_approvers: vec![],
+    }
+}
+
+/// Bytes laid out by hand the way Anchor writes the account
+fn anchor_account_bytes(state: &AnchorDiamondState) -> Vec<u8> {
+    let mut data = AnchorDiamondState::discriminator().to_vec();
+    data.extend_from_slice(state.owner.as_ref());
+    data.extend_from_slice(&(state.selectors.len() as u32).to_le_bytes());
+    for s in &state.selectors {
+        data.extend_from_slice(&s.selector);
+        data.extend_from_slice(s.module.as_ref());
+        data.extend_from_slice(&(s.function_name.len() as u32).to_le_bytes());
+        data.extend_from_slice(s.function_name.as_bytes());
+        data.push(s.is_immutable as u8);
+    }
+    data.extend_from_slice(&(state.modules.len() as u32).to_le_bytes());
+    for m in &state.modules {
+        data.extend_from_slice(&(m.name.len() as u32).to_le_bytes());
+        data.extend_from_slice(m.name.as_bytes());
+        data.extend_from_slice(m.address.as_ref());
+        data.extend_from_slice(&m.version.to_le_bytes());
+    }
+    data.push(state.bump);
+    data.push(state.is_paused as u8);
+    match state.pause_expiry_slot {
+        Some(slot) => {
+            data.push(1);
+            data.extend_from_slice(&slot.to_le_bytes());
+        }
+        None => data.push(0),
+    }
+    data.extend_from_slice(state.pause_authority.as_ref());
+    data.push(state.unpause_quorum);
+    data.extend_from_slice(&(state.unpause_approvers.len() as u32).to_le_bytes());
+    for approver in &state.unpause_approvers {
+        data.extend_from_slice(approver.as_ref());
+    }
+    data
+}
+
+#[test]
+fn anchor_account_decodes_with_padding() {
+    let state = anchor_state();
+    let mut data = anchor_account_bytes(&state);
+    assert_eq!(data, state.encode());
+    
+    // Anchor allocates SPACE up front, leaving zeroed tail bytes
+    data.resize(data.len() + 512, 0);
+    assert_eq!(AnchorDiamondState::decode(&data).unwrap(), state);
+}
+
+#[test]
+fn wrong_discriminator_rejected() {
+    let mut data = anchor_state().encode();
+    data[0] ^= 0xFF;
+    assert!(AnchorDiamondState::decode(&data).is_err());
+}
+
+#[test]
+fn anchor_to_native_preserves_routing() {
+    let anchor = anchor_state();
+    let native = anchor.to_native();
+    
+    assert_eq!(native.owner, anchor.owner);
+    assert_eq!(native.selectors.len(), 2);
+    assert_eq!(native.get_module_by_selector([1, 2, 3, 4]), Some(anchor.selectors[0].module));
+    assert!(native.get_mapping([5, 6, 7, 8]).unwrap().is_immutable);
+    assert_eq!(native.get_mapping_by_name("get_count").unwrap().selector, [5, 6, 7, 8]);
+    assert!(native.is_paused_at(999) && !native.is_paused_at(1_000));
+    assert_eq!(native.pause_authority, anchor.pause_authority);
+    
+    // Native encoding survives a round trip and converts back unchanged
+    let bytes = native.try_to_vec().unwrap();
+    let decoded: DiamondState = borsh::BorshDeserialize::try_from_slice(&bytes).unwrap();
+    assert_eq!(AnchorDiamondState::from_native(&decoded), anchor);
+}
+
+#[test]
+fn native_to_anchor_round_trip() {
+    let facet = Pubkey::new_unique();
+    let mut native = DiamondState::new(Pubkey::new_unique(), 253);
+    native.selectors.push(SelectorMapping::new([9, 9, 9, 9], facet, "transfer", false));
+    native.active_modules.push(ModuleMeta::new("token", facet, Semver::new(3, 0, 0)));
+    
+    let anchor = AnchorDiamondState::decode(&AnchorDiamondState::from_native(&native).encode()).unwrap();
+    let back = anchor.to_native();
+    
+    assert_eq!(back.owner, native.owner);
+    assert_eq!(back.selectors, native.selectors);
+    assert_eq!(back.active_modules, native.active_modules);
+    assert_eq!(back.bump, native.bump);
+}
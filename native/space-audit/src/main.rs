@@ -0,0 +1,40 @@
+/*!
+ * Diamond SPACE Audit
+ *
+ * Prints declared vs required size for every router account type and exits
+ * non-zero if any constant has drifted from its worst-case encoding.
+ *
+ * Run: cargo run -p diamond-space-audit
+ */
+
+use diamond_router_native::layout;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let reports = layout::audit();
+    let mut drifted = 0;
+    
+    println!("{:<14} {:>9} {:>9}  status", "account", "declared", "required");
+    for report in &reports {
+        let status = if report.is_exact() {
+            "ok"
+        } else if report.is_undersized() {
+            "UNDERSIZED"
+        } else {
+            "oversized"
+        };
+        if !report.is_exact() {
+            drifted += 1;
+        }
+        println!(
+            "{:<14} {:>9} {:>9}  {}",
+            report.account, report.declared, report.required, status
+        );
+    }
+    
+    if drifted > 0 {
+        eprintln!("{} SPACE constant(s) out of sync", drifted);
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
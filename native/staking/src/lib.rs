@@ -0,0 +1,603 @@
+/*!
+ * Example Native Facet - Staking
+ *
+ * Token staking with time-based rewards, called through the diamond router.
+ * Shows:
+ * - Per-user position PDAs created on first stake
+ * - Clock-driven reward accrual (a reward-per-share accumulator)
+ * - Token transfers in both directions, the outbound ones signed by the
+ *   facet's pool PDA
+ *
+ * Each pool has one mint and one vault; rewards are paid from whatever the
+ * vault holds beyond the staked total, so the pool authority (the diamond's
+ * owner when the pool was created) funds rewards
+ * by transferring tokens straight into the vault. Transfers go through the
+ * facet SDK, so Token-2022 mints with transfer fees or hooks work: positions
+ * are credited the net amount, and hook accounts follow the listed accounts.
+ */
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint,
+    entrypoint::ProgramResult,
+    msg,
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    sysvar::Sysvar,
+};
+
+use diamond_core::routing;
+use diamond_facet_sdk::token;
+
+// Program ID (placeholder - replace with actual deployed program ID)
+solana_program::declare_id!("StakingFacet1111111111111111111111111111111");
+
+// Function selectors
+pub const INIT_POOL_SELECTOR: [u8; 4] = [0x57, 0xA4, 0x00, 0x01];
+pub const STAKE_SELECTOR: [u8; 4] = [0x57, 0xA4, 0x00, 0x02];
+pub const UNSTAKE_SELECTOR: [u8; 4] = [0x57, 0xA4, 0x00, 0x03];
+pub const CLAIM_SELECTOR: [u8; 4] = [0x57, 0xA4, 0x00, 0x04];
+pub const SET_REWARD_RATE_SELECTOR: [u8; 4] = [0x57, 0xA4, 0x00, 0x05];
+
+/// Liveness probe (the router's reserved `HEALTH_SELECTOR`)
+pub use diamond_constants::selectors::HEALTH_SELECTOR;
+
+/// Seed of pool PDAs: [POOL_SEED, diamond_state, mint]
+pub const POOL_SEED: &[u8] = b"pool";
+
+/// Seed of position PDAs: [STAKE_SEED, pool, owner]
+pub const STAKE_SEED: &[u8] = b"stake";
+
+/// Fixed-point scale of `acc_reward_per_share`
+pub const ACC_PRECISION: u128 = 1_000_000_000_000;
+
+/// Staking pool
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct Pool {
+    pub diamond_state: Pubkey,
+    pub authority: Pubkey,
+    pub mint: Pubkey,
+    /// Token account holding stakes and rewards, owned by the pool PDA
+    pub vault: Pubkey,
+    /// Reward tokens released per second, shared across all stakers
+    pub reward_rate: u64,
+    /// Rewards per staked token so far, scaled by `ACC_PRECISION`
+    pub acc_reward_per_share: u128,
+    pub last_update: i64,
+    pub total_staked: u64,
+    pub bump: u8,
+}
+
+impl Pool {
+    pub const SPACE: usize = 32 + 32 + 32 + 32 + 8 + 16 + 8 + 8 + 1;
+    
+    /// Bring the accumulator up to `now`
+    pub fn accrue(&mut self, now: i64) -> ProgramResult {
+        let elapsed = now.saturating_sub(self.last_update).max(0) as u128;
+        if self.total_staked > 0 && elapsed > 0 {
+            let increase = elapsed
+                .checked_mul(self.reward_rate as u128)
+                .and_then(|r| r.checked_mul(ACC_PRECISION))
+                .ok_or(ProgramError::ArithmeticOverflow)?
+                / self.total_staked as u128;
+            self.acc_reward_per_share = self
+                .acc_reward_per_share
+                .checked_add(increase)
+                .ok_or(ProgramError::ArithmeticOverflow)?;
+        }
+        self.last_update = self.last_update.max(now);
+        Ok(())
+    }
+}
+
+/// One user's position in a pool
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct StakePosition {
+    pub pool: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    /// `amount * acc_reward_per_share` at the last settlement
+    pub reward_debt: u128,
+    /// Settled rewards not yet claimed
+    pub pending_rewards: u64,
+    pub bump: u8,
+}
+
+impl StakePosition {
+    pub const SPACE: usize = 32 + 32 + 8 + 16 + 8 + 1;
+    
+    fn accrued(amount: u64, acc_reward_per_share: u128) -> Result<u128, ProgramError> {
+        (amount as u128)
+            .checked_mul(acc_reward_per_share)
+            .map(|r| r / ACC_PRECISION)
+            .ok_or(ProgramError::ArithmeticOverflow)
+    }
+    
+    /// Move rewards earned since the last settlement into `pending_rewards`
+    pub fn settle(&mut self, acc_reward_per_share: u128) -> ProgramResult {
+        let earned = Self::accrued(self.amount, acc_reward_per_share)?.saturating_sub(self.reward_debt);
+        self.pending_rewards = u64::try_from(earned)
+            .ok()
+            .and_then(|earned| self.pending_rewards.checked_add(earned))
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        self.reward_debt = Self::accrued(self.amount, acc_reward_per_share)?;
+        Ok(())
+    }
+    
+    /// Change the staked amount (after `settle`) and rebase the debt
+    pub fn set_amount(&mut self, amount: u64, acc_reward_per_share: u128) -> ProgramResult {
+        self.amount = amount;
+        self.reward_debt = Self::accrued(amount, acc_reward_per_share)?;
+        Ok(())
+    }
+}
+
+pub fn pool_address(diamond_state: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[POOL_SEED, diamond_state.as_ref(), mint.as_ref()], &id())
+}
+
+pub fn stake_address(pool: &Pubkey, owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[STAKE_SEED, pool.as_ref(), owner.as_ref()], &id())
+}
+
+// Program entrypoint
+entrypoint!(process_instruction);
+
+/// Main instruction processor
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    msg!("Staking Facet: Processing instruction");
+    
+    if instruction_data.len() < 4 {
+        msg!("Error: Instruction data too short");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let (selector, data) = instruction_data.split_at(4);
+    
+    match [selector[0], selector[1], selector[2], selector[3]] {
+        INIT_POOL_SELECTOR => {
+            msg!("Function: InitPool");
+            init_pool(program_id, accounts, data)
+        }
+        STAKE_SELECTOR => {
+            msg!("Function: Stake");
+            stake(program_id, accounts, data)
+        }
+        UNSTAKE_SELECTOR => {
+            msg!("Function: Unstake");
+            unstake(program_id, accounts, data)
+        }
+        CLAIM_SELECTOR => {
+            msg!("Function: Claim");
+            claim(program_id, accounts)
+        }
+        SET_REWARD_RATE_SELECTOR => {
+            msg!("Function: SetRewardRate");
+            set_reward_rate(program_id, accounts, data)
+        }
+        HEALTH_SELECTOR => {
+            msg!("Function: Health");
+            Ok(())
+        }
+        _ => {
+            msg!("Error: Unknown selector: {:?}", selector);
+            Err(ProgramError::InvalidInstructionData)
+        }
+    }
+}
+
+fn parse_amount(data: &[u8]) -> Result<u64, ProgramError> {
+    let amount = data
+        .get(..8)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u64::from_le_bytes)
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    if amount == 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+    Ok(amount)
+}
+
+fn load<T: BorshDeserialize>(program_id: &Pubkey, account: &AccountInfo) -> Result<T, ProgramError> {
+    if account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    T::deserialize(&mut &account.try_borrow_data()?[..]).map_err(|_| ProgramError::InvalidAccountData)
+}
+
+fn store<T: BorshSerialize>(value: &T, account: &AccountInfo) -> ProgramResult {
+    value.serialize(&mut &mut account.try_borrow_mut_data()?[..])?;
+    Ok(())
+}
+
+/// Load a pool and check the vault and mint passed alongside it
+fn load_pool(
+    program_id: &Pubkey,
+    pool_account: &AccountInfo,
+    vault: &AccountInfo,
+    mint: &AccountInfo,
+) -> Result<Pool, ProgramError> {
+    let pool: Pool = load(program_id, pool_account)?;
+    if vault.key != &pool.vault || mint.key != &pool.mint {
+        msg!("Error: Vault or mint does not belong to this pool");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(pool)
+}
+
+/// Load the signer's position in `pool`
+fn load_position(
+    program_id: &Pubkey,
+    stake_account: &AccountInfo,
+    pool: &Pubkey,
+    user: &AccountInfo,
+) -> Result<StakePosition, ProgramError> {
+    if !user.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    let position: StakePosition = load(program_id, stake_account)?;
+    if &position.pool != pool || &position.owner != user.key {
+        msg!("Error: Position does not belong to this user and pool");
+        return Err(ProgramError::IllegalOwner);
+    }
+    Ok(position)
+}
+
+/// Check that `authority` owns the diamond behind `diamond_state`
+///
+/// A pool's address depends only on the diamond and the mint, so this is
+/// what stops anyone else from creating a diamond's pool first.
+fn check_diamond_owner(diamond_state: &AccountInfo, authority: &Pubkey) -> ProgramResult {
+    let header = routing::state_header(&diamond_state.try_borrow_data()?).ok_or_else(|| {
+        msg!("Error: {} is not a diamond state", diamond_state.key);
+        ProgramError::InvalidAccountData
+    })?;
+    if header.owner != authority.to_bytes() {
+        msg!("Error: Only the diamond owner can create its pools");
+        return Err(ProgramError::IllegalOwner);
+    }
+    Ok(())
+}
+
+/// Pay `amount` from the vault, signed by the pool PDA
+#[allow(clippy::too_many_arguments)]
+fn pay_out<'a>(
+    pool: &Pool,
+    pool_account: &AccountInfo<'a>,
+    token_program: &AccountInfo<'a>,
+    vault: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
+    destination: &AccountInfo<'a>,
+    amount: u64,
+    hook_accounts: &[AccountInfo<'a>],
+) -> ProgramResult {
+    token::transfer(
+        token_program,
+        vault,
+        mint,
+        destination,
+        pool_account,
+        amount,
+        hook_accounts,
+        &[&[POOL_SEED, pool.diamond_state.as_ref(), pool.mint.as_ref(), &[pool.bump]]],
+    )?;
+    Ok(())
+}
+
+/// Create a pool (via `dispatch`, diamond owner only)
+///
+/// Accounts: [pool (w), authority (s, w), diamond_state, mint, vault, system_program]
+/// Data: reward_rate (u64)
+///
+/// The vault is an existing token account for `mint` whose owner is the pool PDA.
+fn init_pool(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let pool_account = next_account_info(account_iter)?;
+    let authority = next_account_info(account_iter)?;
+    let diamond_state = next_account_info(account_iter)?;
+    let mint = next_account_info(account_iter)?;
+    let vault = next_account_info(account_iter)?;
+    let system_program_account = next_account_info(account_iter)?;
+    
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    check_diamond_owner(diamond_state, authority.key)?;
+    let reward_rate = u64::try_from_slice(data).map_err(|_| ProgramError::InvalidInstructionData)?;
+    let (expected, bump) = pool_address(diamond_state.key, mint.key);
+    if pool_account.key != &expected {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    token::unpack_mint(mint)?;
+    let vault_state = token::unpack_account(vault)?;
+    if vault_state.mint != *mint.key || vault_state.owner != expected {
+        msg!("Error: Vault must be a {} token account owned by the pool", mint.key);
+        return Err(ProgramError::InvalidAccountData);
+    }
+    
+    invoke_signed(
+        &system_instruction::create_account(
+            authority.key,
+            pool_account.key,
+            Rent::get()?.minimum_balance(Pool::SPACE),
+            Pool::SPACE as u64,
+            program_id,
+        ),
+        &[authority.clone(), pool_account.clone(), system_program_account.clone()],
+        &[&[POOL_SEED, diamond_state.key.as_ref(), mint.key.as_ref(), &[bump]]],
+    )?;
+    
+    let pool = Pool {
+        diamond_state: *diamond_state.key,
+        authority: *authority.key,
+        mint: *mint.key,
+        vault: *vault.key,
+        reward_rate,
+        acc_reward_per_share: 0,
+        last_update: Clock::get()?.unix_timestamp,
+        total_staked: 0,
+        bump,
+    };
+    store(&pool, pool_account)?;
+    
+    msg!("Pool {} created: {} reward/s", pool_account.key, reward_rate);
+    Ok(())
+}
+
+/// Stake tokens, opening the position on first use (via `dispatch`)
+///
+/// Accounts: [pool (w), stake (w), user (s, w), user_token (w), vault (w), mint, token_program, system_program, ...hook accounts]
+/// Data: amount (u64)
+fn stake(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let pool_account = next_account_info(account_iter)?;
+    let stake_account = next_account_info(account_iter)?;
+    let user = next_account_info(account_iter)?;
+    let user_token = next_account_info(account_iter)?;
+    let vault = next_account_info(account_iter)?;
+    let mint = next_account_info(account_iter)?;
+    let token_program = next_account_info(account_iter)?;
+    let system_program_account = next_account_info(account_iter)?;
+    
+    let amount = parse_amount(data)?;
+    let mut pool = load_pool(program_id, pool_account, vault, mint)?;
+    if !user.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let mut position = if stake_account.data_is_empty() {
+        let (expected, bump) = stake_address(pool_account.key, user.key);
+        if stake_account.key != &expected {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        invoke_signed(
+            &system_instruction::create_account(
+                user.key,
+                stake_account.key,
+                Rent::get()?.minimum_balance(StakePosition::SPACE),
+                StakePosition::SPACE as u64,
+                program_id,
+            ),
+            &[user.clone(), stake_account.clone(), system_program_account.clone()],
+            &[&[STAKE_SEED, pool_account.key.as_ref(), user.key.as_ref(), &[bump]]],
+        )?;
+        StakePosition {
+            pool: *pool_account.key,
+            owner: *user.key,
+            amount: 0,
+            reward_debt: 0,
+            pending_rewards: 0,
+            bump,
+        }
+    } else {
+        load_position(program_id, stake_account, pool_account.key, user)?
+    };
+    
+    pool.accrue(Clock::get()?.unix_timestamp)?;
+    position.settle(pool.acc_reward_per_share)?;
+    
+    // Credit what the vault actually received (fee-bearing mints deliver less)
+    let received = token::transfer(token_program, user_token, mint, vault, user, amount, account_iter.as_slice(), &[])?;
+    
+    let staked = position.amount.checked_add(received).ok_or(ProgramError::ArithmeticOverflow)?;
+    position.set_amount(staked, pool.acc_reward_per_share)?;
+    pool.total_staked = pool.total_staked.checked_add(received).ok_or(ProgramError::ArithmeticOverflow)?;
+    store(&pool, pool_account)?;
+    store(&position, stake_account)?;
+    
+    msg!("Staked {} (position {})", received, position.amount);
+    Ok(())
+}
+
+/// Withdraw staked tokens (via `dispatch`)
+///
+/// Accounts: [pool (w), stake (w), user (s), user_token (w), vault (w), mint, token_program, ...hook accounts]
+/// Data: amount (u64)
+fn unstake(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let pool_account = next_account_info(account_iter)?;
+    let stake_account = next_account_info(account_iter)?;
+    let user = next_account_info(account_iter)?;
+    let user_token = next_account_info(account_iter)?;
+    let vault = next_account_info(account_iter)?;
+    let mint = next_account_info(account_iter)?;
+    let token_program = next_account_info(account_iter)?;
+    
+    let amount = parse_amount(data)?;
+    let mut pool = load_pool(program_id, pool_account, vault, mint)?;
+    let mut position = load_position(program_id, stake_account, pool_account.key, user)?;
+    if amount > position.amount {
+        msg!("Error: Only {} staked", position.amount);
+        return Err(ProgramError::InsufficientFunds);
+    }
+    
+    pool.accrue(Clock::get()?.unix_timestamp)?;
+    position.settle(pool.acc_reward_per_share)?;
+    position.set_amount(position.amount - amount, pool.acc_reward_per_share)?;
+    pool.total_staked -= amount;
+    store(&pool, pool_account)?;
+    store(&position, stake_account)?;
+    
+    pay_out(&pool, pool_account, token_program, vault, mint, user_token, amount, account_iter.as_slice())?;
+    
+    msg!("Unstaked {} (position {})", amount, position.amount);
+    Ok(())
+}
+
+/// Pay out settled rewards (via `dispatch`)
+///
+/// Accounts: [pool (w), stake (w), user (s), user_token (w), vault (w), mint, token_program, ...hook accounts]
+fn claim(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let pool_account = next_account_info(account_iter)?;
+    let stake_account = next_account_info(account_iter)?;
+    let user = next_account_info(account_iter)?;
+    let user_token = next_account_info(account_iter)?;
+    let vault = next_account_info(account_iter)?;
+    let mint = next_account_info(account_iter)?;
+    let token_program = next_account_info(account_iter)?;
+    
+    let mut pool = load_pool(program_id, pool_account, vault, mint)?;
+    let mut position = load_position(program_id, stake_account, pool_account.key, user)?;
+    
+    pool.accrue(Clock::get()?.unix_timestamp)?;
+    position.settle(pool.acc_reward_per_share)?;
+    let reward = position.pending_rewards;
+    if reward == 0 {
+        msg!("Nothing to claim");
+        return Ok(());
+    }
+    
+    // Never pay rewards out of staked principal
+    let available = token::unpack_account(vault)?.amount.saturating_sub(pool.total_staked);
+    if reward > available {
+        msg!("Error: Reward pool holds {} of {} owed", available, reward);
+        return Err(ProgramError::InsufficientFunds);
+    }
+    position.pending_rewards = 0;
+    store(&pool, pool_account)?;
+    store(&position, stake_account)?;
+    
+    pay_out(&pool, pool_account, token_program, vault, mint, user_token, reward, account_iter.as_slice())?;
+    
+    msg!("Claimed {}", reward);
+    Ok(())
+}
+
+/// Change the reward rate from now on (via `dispatch`)
+///
+/// Accounts: [pool (w), authority (s)]
+/// Data: reward_rate (u64)
+fn set_reward_rate(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let pool_account = next_account_info(account_iter)?;
+    let authority = next_account_info(account_iter)?;
+    
+    let reward_rate = u64::try_from_slice(data).map_err(|_| ProgramError::InvalidInstructionData)?;
+    let mut pool: Pool = load(program_id, pool_account)?;
+    if !authority.is_signer || authority.key != &pool.authority {
+        msg!("Error: Only the pool authority can change the reward rate");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    // Rewards up to now accrue at the old rate
+    pool.accrue(Clock::get()?.unix_timestamp)?;
+    pool.reward_rate = reward_rate;
+    store(&pool, pool_account)?;
+    
+    msg!("Reward rate set to {}/s", reward_rate);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diamond_router_native::diamond_state::DiamondState;
+    use solana_program::clock::Epoch;
+    
+    fn pool(reward_rate: u64) -> Pool {
+        Pool {
+            diamond_state: Pubkey::new_unique(),
+            authority: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            vault: Pubkey::new_unique(),
+            reward_rate,
+            acc_reward_per_share: 0,
+            last_update: 1_000,
+            total_staked: 0,
+            bump: 255,
+        }
+    }
+    
+    fn position(pool: &Pubkey) -> StakePosition {
+        StakePosition {
+            pool: *pool,
+            owner: Pubkey::new_unique(),
+            amount: 0,
+            reward_debt: 0,
+            pending_rewards: 0,
+            bump: 255,
+        }
+    }
+    
+    #[test]
+    fn test_account_space() {
+        let mut pool = pool(u64::MAX);
+        pool.acc_reward_per_share = u128::MAX;
+        assert_eq!(borsh::to_vec(&pool).unwrap().len(), Pool::SPACE);
+        assert_eq!(borsh::to_vec(&position(&pool.vault)).unwrap().len(), StakePosition::SPACE);
+    }
+    
+    #[test]
+    fn test_only_the_diamond_owner_creates_pools() {
+        let owner = Pubkey::new_unique();
+        let state = DiamondState::new(owner, 255);
+        let (key, router) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (mut lamports, mut data) = (0, borsh::to_vec(&state).unwrap());
+        let diamond_state = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &router, false, Epoch::default());
+        
+        assert_eq!(check_diamond_owner(&diamond_state, &owner), Ok(()));
+        assert_eq!(check_diamond_owner(&diamond_state, &Pubkey::new_unique()), Err(ProgramError::IllegalOwner));
+        diamond_state.try_borrow_mut_data().unwrap().fill(0);
+        assert_eq!(check_diamond_owner(&diamond_state, &owner), Err(ProgramError::InvalidAccountData));
+    }
+    
+    #[test]
+    fn test_rewards_split_by_stake_and_time() {
+        let mut pool = pool(300);
+        let mut alice = position(&Pubkey::new_unique());
+        let mut bob = position(&alice.pool);
+        
+        // Nothing accrues while the pool is empty
+        pool.accrue(1_010).unwrap();
+        assert_eq!(pool.acc_reward_per_share, 0);
+        
+        alice.set_amount(300, pool.acc_reward_per_share).unwrap();
+        pool.total_staked = 300;
+        
+        // 10s alone: alice earns all 3000
+        pool.accrue(1_020).unwrap();
+        bob.settle(pool.acc_reward_per_share).unwrap();
+        bob.set_amount(100, pool.acc_reward_per_share).unwrap();
+        pool.total_staked = 400;
+        
+        // 20s shared 3:1: alice 4500, bob 1500
+        pool.accrue(1_040).unwrap();
+        alice.settle(pool.acc_reward_per_share).unwrap();
+        bob.settle(pool.acc_reward_per_share).unwrap();
+        assert_eq!(alice.pending_rewards, 7_500);
+        assert_eq!(bob.pending_rewards, 1_500);
+        
+        // Settling again without time passing adds nothing
+        alice.settle(pool.acc_reward_per_share).unwrap();
+        assert_eq!(alice.pending_rewards, 7_500);
+    }
+}
@@ -0,0 +1,337 @@
+/*!
+ * Test Context
+ * A sandboxed diamond with funded accounts, fixtures and standard routes
+ *
+ * `DiamondTestContext::builder()` loads the router and the system program
+ * into a `Sandbox`, adds the facets asked for with their standard selector
+ * sets routed, and creates their fixtures: a counter whose authority is the
+ * diamond owner, an unfunded escrow from the owner to a funded beneficiary,
+ * a call log for the mock facet. `funded_account` sets up a fresh account
+ * holding lamports in the sandbox (no network faucet is involved), so a
+ * facet author's test reads
+ *
+ * ```ignore
+ * let mut ctx = DiamondTestContext::builder().with_facet(my_facet::id(), my_facet::process_instruction, cuts).build();
+ * let user = ctx.funded_account(LAMPORTS_PER_SOL);
+ * let outcome = ctx.dispatch(&my_facet::id(), &[1, 0, 0, 1], &[AccountMeta::new(user, true)]);
+ * assert_eq!(outcome.result, Ok(()));
+ * ```
+ *
+ * The owner signs and pays for everything; the sandbox doesn't check
+ * transaction signatures, so any account marked as a signer is one.
+ */
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use diamond_router_native::{
+    diamond_cut::{apply_cuts, FacetCut},
+    diamond_state::{diamond_address, DiamondState},
+    selector::Selector,
+    DISPATCH_DISCRIMINATOR, DISPATCH_SIGNED_DISCRIMINATOR,
+};
+use example_escrow_facet::{self as escrow, Escrow};
+use example_facet_native::{self as counter, Counter};
+use solana_program::{
+    entrypoint::ProcessInstruction,
+    instruction::{AccountMeta, Instruction},
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    system_program,
+};
+
+use crate::register::{dispatch_to_mock, mock_cut};
+use crate::sandbox::{process_system_instruction, Outcome, Sandbox, SandboxAccount};
+use crate::CallLog;
+
+/// Lamports the owner and the escrow beneficiary start with
+pub const STARTING_LAMPORTS: u64 = 100 * LAMPORTS_PER_SOL;
+
+/// Amount the escrow fixture holds once funded
+pub const ESCROW_AMOUNT: u64 = LAMPORTS_PER_SOL;
+
+/// Selector the mock facet fixture serves
+pub const MOCK_SELECTOR: [u8; 4] = [0x0C, 0x0C, 0x00, 0x01];
+
+/// The counter facet's selectors, under module "counter"
+pub fn counter_cuts() -> Vec<FacetCut> {
+    [
+        (counter::INCREMENT_SELECTOR, "increment"),
+        (counter::DECREMENT_SELECTOR, "decrement"),
+        (counter::GET_VALUE_SELECTOR, "get_value"),
+        (counter::RESET_SELECTOR, "reset"),
+        (counter::INIT_HISTORY_SELECTOR, "init_history"),
+        (counter::SET_AUTHORITY_SELECTOR, "set_authority"),
+        (counter::SET_CO_AUTHORITIES_SELECTOR, "set_co_authorities"),
+    ]
+    .into_iter()
+    .map(|(selector, name)| FacetCut::add(selector, counter::id(), "counter", name))
+    .collect()
+}
+
+/// The escrow facet's selectors, under module "escrow"
+pub fn escrow_cuts() -> Vec<FacetCut> {
+    [
+        (escrow::CREATE_ESCROW_SELECTOR, "create_escrow"),
+        (escrow::FUND_SELECTOR, "fund"),
+        (escrow::RELEASE_SELECTOR, "release"),
+        (escrow::CANCEL_SELECTOR, "cancel"),
+    ]
+    .into_iter()
+    .map(|(selector, name)| FacetCut::add(selector, escrow::id(), "escrow", name))
+    .collect()
+}
+
+/// What `DiamondTestContext::builder` sets up
+#[derive(Clone, Default)]
+pub struct DiamondTestContextBuilder {
+    counter: bool,
+    escrow: bool,
+    mock: bool,
+    facets: Vec<(Pubkey, ProcessInstruction, Vec<FacetCut>)>,
+}
+
+impl DiamondTestContextBuilder {
+    /// Route the counter facet and create a counter owned by the owner
+    pub fn with_counter(&mut self) -> &mut Self {
+        self.counter = true;
+        self
+    }
+    
+    /// Route the escrow facet and create an unfunded escrow (id 0) from the
+    /// owner to a funded beneficiary, with an arbiter
+    pub fn with_escrow(&mut self) -> &mut Self {
+        self.escrow = true;
+        self
+    }
+    
+    /// Route `MOCK_SELECTOR` to the mock facet and create its call log
+    pub fn with_mock(&mut self) -> &mut Self {
+        self.mock = true;
+        self
+    }
+    
+    /// Serve `program_id` with `processor` and apply `cuts` (the facet under test)
+    pub fn with_facet(&mut self, program_id: Pubkey, processor: ProcessInstruction, cuts: Vec<FacetCut>) -> &mut Self {
+        self.facets.push((program_id, processor, cuts));
+        self
+    }
+    
+    pub fn build(&self) -> DiamondTestContext {
+        let (router, owner) = (diamond_router_native::id(), Pubkey::new_unique());
+        let (diamond, bump) = diamond_address(&router, &owner, None);
+        let mut sandbox = Sandbox::new();
+        sandbox
+            .add_program(router, diamond_router_native::process_instruction)
+            .add_program(system_program::id(), process_system_instruction);
+        let mut cuts = Vec::new();
+        
+        let counter_key = self.counter.then(|| {
+            sandbox.add_program(counter::id(), counter::process_instruction);
+            cuts.extend(counter_cuts());
+            let key = Pubkey::new_unique();
+            let mut data = borsh::to_vec(&Counter::new(owner, 0)).expect("counter encodes");
+            data.resize(Counter::SPACE, 0);
+            sandbox.add_account(key, SandboxAccount::new(counter::id(), data));
+            key
+        });
+        let escrow_key = self.escrow.then(|| {
+            sandbox.add_program(escrow::id(), escrow::process_instruction);
+            cuts.extend(escrow_cuts());
+            let (key, bump) = escrow::escrow_address(&diamond, &owner, 0);
+            let (beneficiary, arbiter) = (Pubkey::new_unique(), Pubkey::new_unique());
+            let fixture = Escrow {
+                diamond_state: diamond,
+                router,
+                depositor: owner,
+                beneficiary,
+                arbiter: Some(arbiter),
+                amount: ESCROW_AMOUNT,
+                funded: false,
+                bump,
+            };
+            sandbox
+                .add_account(key, SandboxAccount::new(escrow::id(), borsh::to_vec(&fixture).expect("escrow encodes")))
+                .add_account(beneficiary, SandboxAccount { lamports: STARTING_LAMPORTS, ..SandboxAccount::default() });
+            key
+        });
+        let call_log = self.mock.then(|| {
+            sandbox.add_program(crate::id(), crate::process_instruction);
+            cuts.push(mock_cut(MOCK_SELECTOR, &crate::id(), "record"));
+            let key = Pubkey::new_unique();
+            sandbox.add_account(key, SandboxAccount::new(crate::id(), vec![0; CallLog::space(8, 64, 8)]));
+            key
+        });
+        for (program_id, processor, facet_cuts) in &self.facets {
+            sandbox.add_program(*program_id, *processor);
+            cuts.extend(facet_cuts.iter().cloned());
+        }
+        
+        let mut state = DiamondState::new(owner, bump);
+        apply_cuts(&mut state, &cuts).expect("standard selector sets don't collide");
+        sandbox
+            .add_account(diamond, SandboxAccount::diamond_state(&router, &state))
+            .add_account(owner, SandboxAccount { lamports: STARTING_LAMPORTS, ..SandboxAccount::default() });
+        DiamondTestContext { sandbox, router, diamond, owner, counter: counter_key, escrow: escrow_key, call_log }
+    }
+}
+
+/// A diamond ready to dispatch to, in a sandbox
+pub struct DiamondTestContext {
+    pub sandbox: Sandbox,
+    pub router: Pubkey,
+    pub diamond: Pubkey,
+    /// Diamond owner, counter authority and escrow depositor; funded
+    pub owner: Pubkey,
+    /// Counter fixture (`with_counter`)
+    pub counter: Option<Pubkey>,
+    /// Escrow fixture (`with_escrow`)
+    pub escrow: Option<Pubkey>,
+    /// Mock facet call log (`with_mock`)
+    pub call_log: Option<Pubkey>,
+}
+
+impl DiamondTestContext {
+    pub fn builder() -> DiamondTestContextBuilder {
+        DiamondTestContextBuilder::default()
+    }
+    
+    /// A fresh sandbox system account holding `lamports`
+    pub fn funded_account(&mut self, lamports: u64) -> Pubkey {
+        let key = Pubkey::new_unique();
+        self.credit(&key, lamports);
+        key
+    }
+    
+    /// Credit `lamports` to `to` in the sandbox, creating it as a system
+    /// account if needed
+    pub fn credit(&mut self, to: &Pubkey, lamports: u64) {
+        let mut account = self.sandbox.account(to).cloned().unwrap_or_default();
+        account.lamports += lamports;
+        self.sandbox.add_account(*to, account);
+    }
+    
+    /// The diamond's state as it stands
+    pub fn state(&self) -> DiamondState {
+        let account = self.sandbox.account(&self.diamond).expect("diamond state loaded");
+        DiamondState::deserialize(&mut &account.data[..]).expect("diamond state decodes")
+    }
+    
+    /// Router `dispatch` of `ix_data` (selector first) to `facet`
+    pub fn dispatch_instruction(&self, facet: &Pubkey, ix_data: &[u8], accounts: &[AccountMeta]) -> Instruction {
+        let mut data = DISPATCH_DISCRIMINATOR.to_vec();
+        // Writing into a Vec cannot fail
+        ix_data.to_vec().serialize(&mut data).expect("borsh encode");
+        let mut metas = vec![AccountMeta::new(self.diamond, false), AccountMeta::new_readonly(*facet, false)];
+        metas.extend_from_slice(accounts);
+        Instruction { program_id: self.router, accounts: metas, data }
+    }
+    
+    /// Dispatch through the router, keeping the changes if it succeeds
+    pub fn dispatch(&mut self, facet: &Pubkey, ix_data: &[u8], accounts: &[AccountMeta]) -> Outcome {
+        let ix = self.dispatch_instruction(facet, ix_data, accounts);
+        self.sandbox.process(&ix)
+    }
+    
+    /// `dispatch_signed` with the router's facet signer PDA for `seed`
+    pub fn dispatch_signed(&mut self, facet: &Pubkey, seed: &[u8], ix_data: &[u8], accounts: &[AccountMeta]) -> Outcome {
+        let mut ix = self.dispatch_instruction(facet, ix_data, accounts);
+        ix.data = DISPATCH_SIGNED_DISCRIMINATOR.to_vec();
+        // Writing into a Vec cannot fail
+        (seed.to_vec(), ix_data.to_vec()).serialize(&mut ix.data).expect("borsh encode");
+        self.sandbox.process(&ix)
+    }
+    
+    /// Dispatch `MOCK_SELECTOR` followed by `args` to the mock facet
+    pub fn call_mock(&mut self, args: &[u8], accounts: &[AccountMeta]) -> Outcome {
+        let call_log = self.call_log.expect("built with_mock");
+        let ix_data = [&MOCK_SELECTOR[..], args].concat();
+        let ix = dispatch_to_mock(&self.router, &self.diamond, &crate::id(), &call_log, &ix_data, accounts);
+        self.sandbox.process(&ix)
+    }
+    
+    /// Whether `selector` is routed
+    pub fn routes(&self, selector: impl Into<Selector>) -> bool {
+        self.state().get_mapping(selector).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_counter_increment_in_a_few_lines() {
+        let mut ctx = DiamondTestContext::builder().with_counter().build();
+        let (counter_key, owner) = (ctx.counter.unwrap(), ctx.owner);
+        let accounts = [AccountMeta::new(counter_key, false), AccountMeta::new_readonly(owner, true)];
+        let outcome = ctx.dispatch(&counter::id(), &[&counter::INCREMENT_SELECTOR[..], &5u64.to_le_bytes()].concat(), &accounts);
+        assert_eq!(outcome.result, Ok(()));
+        let data = &ctx.sandbox.account(&counter_key).unwrap().data;
+        assert_eq!(Counter::deserialize(&mut &data[..]).unwrap().value, 5);
+        
+        // Another user isn't the counter's authority
+        let user = ctx.funded_account(LAMPORTS_PER_SOL);
+        assert_eq!(ctx.sandbox.account(&user).unwrap().lamports, LAMPORTS_PER_SOL);
+        let accounts = [AccountMeta::new(counter_key, false), AccountMeta::new_readonly(user, true)];
+        assert!(ctx.dispatch(&counter::id(), &counter::INCREMENT_SELECTOR, &accounts).result.is_err());
+    }
+    
+    #[test]
+    fn test_try_dispatch_skips_refused_calls() {
+        let mut ctx = DiamondTestContext::builder().with_counter().build();
+        let (counter_key, owner) = (ctx.counter.unwrap(), ctx.owner);
+        let accounts = [AccountMeta::new(counter_key, false), AccountMeta::new_readonly(owner, true)];
+        let mut try_dispatch = |ix_data: &[u8]| {
+            let mut ix = ctx.dispatch_instruction(&counter::id(), ix_data, &accounts);
+            ix.data[..8].copy_from_slice(&diamond_router_native::TRY_DISPATCH_DISCRIMINATOR);
+            ctx.sandbox.process(&ix)
+        };
+        
+        // Unrouted: skipped, nothing written
+        let skipped = try_dispatch(&[0xEE, 0xEE, 0xEE, 0xEE]);
+        assert_eq!(skipped.result, Ok(()));
+        assert!(skipped.diffs.is_empty());
+        assert!(skipped.logs.iter().any(|line| line.contains("Dispatch skipped")));
+        
+        let called = try_dispatch(&[&counter::INCREMENT_SELECTOR[..], &5u64.to_le_bytes()].concat());
+        assert_eq!(called.result, Ok(()));
+        assert!(called.diff(&counter_key).is_some());
+    }
+    
+    #[test]
+    fn test_escrow_fixture_funds_and_releases() {
+        let mut ctx = DiamondTestContext::builder().with_escrow().with_mock().build();
+        assert!(ctx.routes(escrow::RELEASE_SELECTOR) && ctx.routes(MOCK_SELECTOR));
+        let (escrow_key, owner) = (ctx.escrow.unwrap(), ctx.owner);
+        let fixture = Escrow::try_from_slice(&ctx.sandbox.account(&escrow_key).unwrap().data).unwrap();
+        let (vault, _) = escrow::vault_address(&ctx.router, &ctx.diamond, &escrow_key);
+        
+        let fund = [
+            AccountMeta::new(escrow_key, false),
+            AccountMeta::new(owner, true),
+            AccountMeta::new(vault, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ];
+        assert_eq!(ctx.dispatch(&escrow::id(), &escrow::FUND_SELECTOR, &fund).result, Ok(()));
+        let release = |releaser: Pubkey| [
+            AccountMeta::new(escrow_key, false),
+            AccountMeta::new(owner, false),
+            AccountMeta::new_readonly(releaser, true),
+            AccountMeta::new(vault, false),
+            AccountMeta::new(fixture.beneficiary, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ];
+        
+        // Only the depositor or the stored arbiter releases
+        let stranger = ctx.funded_account(LAMPORTS_PER_SOL);
+        let refused = ctx.dispatch_signed(&escrow::id(), escrow_key.as_ref(), &escrow::RELEASE_SELECTOR, &release(stranger));
+        assert!(refused.result.is_err());
+        let outcome =
+            ctx.dispatch_signed(&escrow::id(), escrow_key.as_ref(), &escrow::RELEASE_SELECTOR, &release(fixture.arbiter.unwrap()));
+        assert_eq!(outcome.result, Ok(()));
+        assert_eq!(outcome.diff(&fixture.beneficiary).unwrap().lamports_delta(), ESCROW_AMOUNT as i128);
+        
+        assert_eq!(ctx.call_mock(&[7], &[]).result, Ok(()));
+        let log = CallLog::read(&ctx.sandbox.account(&ctx.call_log.unwrap()).unwrap().data).unwrap();
+        assert_eq!(log.calls[0].data, [&MOCK_SELECTOR[..], &[7]].concat());
+    }
+}
@@ -0,0 +1,181 @@
+/*!
+ * Diamond Test Utils
+ * Mock facet program and helpers for integration-testing a diamond
+ *
+ * The mock facet serves any selector and appends each call (instruction data
+ * and account metas) to a call log account it owns, so a test can register
+ * it, dispatch through the router and assert exactly what the facet saw.
+ * Deploy it like any facet; link it into a harness with
+ * `features = ["no-entrypoint"]`. `sandbox` runs the router and facets
+ * in-process against cloned accounts and reports what each call changed;
+ * `context` builds a funded, routed diamond in one for dispatch tests.
+ */
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+pub mod context;
+pub mod register;
+pub mod sandbox;
+
+// Program ID (placeholder - replace with actual deployed program ID)
+solana_program::declare_id!("MockFacet1111111111111111111111111111111111");
+
+/// Liveness probe (the router's reserved `HEALTH_SELECTOR`); not recorded
+pub use diamond_constants::selectors::HEALTH_SELECTOR;
+
+/// Calls starting with this selector fail with `MOCK_FAILURE` and are not recorded
+pub const FAIL_SELECTOR: [u8; 4] = [0xFA, 0x11, 0xFA, 0x11];
+
+/// Custom error code returned for `FAIL_SELECTOR`
+pub const MOCK_FAILURE: u32 = 0xFA11;
+
+/// An account meta as the facet received it
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct RecordedAccount {
+    pub pubkey: Pubkey,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+/// One call into the mock facet
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct RecordedCall {
+    /// Full instruction data, selector included
+    pub data: Vec<u8>,
+    /// Every account passed, the call log first
+    pub accounts: Vec<RecordedAccount>,
+}
+
+/// Contents of a call log account (zero padding after the Borsh encoding)
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CallLog {
+    pub calls: Vec<RecordedCall>,
+}
+
+impl CallLog {
+    /// Account size holding `max_calls` calls of up to `max_data` bytes and
+    /// `max_accounts` accounts each
+    pub const fn space(max_calls: usize, max_data: usize, max_accounts: usize) -> usize {
+        4 + max_calls * (4 + max_data + 4 + max_accounts * (32 + 1 + 1))
+    }
+    
+    /// Decode a call log account's data
+    pub fn read(data: &[u8]) -> Result<Self, ProgramError> {
+        Self::deserialize(&mut &data[..]).map_err(|_| ProgramError::InvalidAccountData)
+    }
+}
+
+// Program entrypoint
+#[cfg(not(feature = "no-entrypoint"))]
+solana_program::entrypoint!(process_instruction);
+
+/// Record the call into the log account
+///
+/// Accounts: [call_log (writable, owned by this program), ...anything]
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    if instruction_data.starts_with(&HEALTH_SELECTOR) {
+        msg!("Mock facet: Health");
+        return Ok(());
+    }
+    if instruction_data.starts_with(&FAIL_SELECTOR) {
+        msg!("Mock facet: Failing on request");
+        return Err(ProgramError::Custom(MOCK_FAILURE));
+    }
+    
+    let account_iter = &mut accounts.iter();
+    let log_account = next_account_info(account_iter)?;
+    if log_account.owner != program_id {
+        msg!("Error: Call log {} is not owned by the mock facet", log_account.key);
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if !log_account.is_writable {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    
+    let mut log = CallLog::read(&log_account.try_borrow_data()?)?;
+    log.calls.push(RecordedCall {
+        data: instruction_data.to_vec(),
+        accounts: accounts
+            .iter()
+            .map(|account| RecordedAccount {
+                pubkey: *account.key,
+                is_signer: account.is_signer,
+                is_writable: account.is_writable,
+            })
+            .collect(),
+    });
+    
+    let mut data = log_account.try_borrow_mut_data()?;
+    log.serialize(&mut &mut data[..]).map_err(|_| {
+        msg!("Error: Call log is full ({} bytes)", data.len());
+        ProgramError::AccountDataTooSmall
+    })?;
+    
+    msg!("Mock facet: Recorded call {} ({} bytes)", log.calls.len(), instruction_data.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_records_calls_in_order() {
+        let program_id = id();
+        let (log_key, signer) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (mut log_lamports, mut signer_lamports) = (0, 0);
+        let mut log_data = vec![0u8; CallLog::space(2, 16, 2)];
+        let mut signer_data = Vec::new();
+        let accounts = [
+            AccountInfo::new(&log_key, false, true, &mut log_lamports, &mut log_data, &program_id, false, 0),
+            AccountInfo::new(&signer, true, false, &mut signer_lamports, &mut signer_data, &program_id, false, 0),
+        ];
+        
+        process_instruction(&program_id, &accounts, &[1, 2, 3, 4, 42]).unwrap();
+        process_instruction(&program_id, &accounts[..1], &[5, 6, 7, 8]).unwrap();
+        process_instruction(&program_id, &accounts, &HEALTH_SELECTOR).unwrap();
+        assert_eq!(
+            process_instruction(&program_id, &accounts, &FAIL_SELECTOR),
+            Err(ProgramError::Custom(MOCK_FAILURE))
+        );
+        
+        let log = CallLog::read(&accounts[0].data.borrow()).unwrap();
+        assert_eq!(log.calls.len(), 2);
+        assert_eq!(log.calls[0].data, vec![1, 2, 3, 4, 42]);
+        assert_eq!(
+            log.calls[0].accounts[1],
+            RecordedAccount { pubkey: signer, is_signer: true, is_writable: false }
+        );
+        assert_eq!(log.calls[1].accounts.len(), 1);
+        
+        // A third call no longer fits
+        assert_eq!(
+            process_instruction(&program_id, &accounts, &[9; 16]),
+            Err(ProgramError::AccountDataTooSmall)
+        );
+    }
+    
+    #[test]
+    fn test_rejects_foreign_log() {
+        let (log_key, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut lamports = 0;
+        let mut data = vec![0u8; CallLog::space(1, 4, 1)];
+        let accounts = [AccountInfo::new(&log_key, false, true, &mut lamports, &mut data, &owner, false, 0)];
+        
+        assert_eq!(
+            process_instruction(&id(), &accounts, &[1, 2, 3, 4]),
+            Err(ProgramError::IncorrectProgramId)
+        );
+    }
+}
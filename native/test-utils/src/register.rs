@@ -0,0 +1,108 @@
+/*!
+ * Registration Helpers
+ * Instructions that wire the mock facet into a diamond and call it
+ */
+
+use borsh::BorshSerialize;
+use diamond_router_native::diamond_cut::FacetCut;
+use diamond_router_native::selector::Selector;
+use diamond_router_native::{ADD_MODULE_DISCRIMINATOR, DISPATCH_DISCRIMINATOR};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+};
+
+/// Module name the mock facet registers under
+pub const MOCK_MODULE_NAME: &str = "mock";
+
+/// Arguments of the router's `add_module` instruction
+#[derive(BorshSerialize)]
+struct AddModuleArgs {
+    module_name: String,
+    module_address: Pubkey,
+    selector: Selector,
+    function_name: String,
+    is_immutable: bool,
+    namespace: String,
+    standard_accounts: u8,
+}
+
+/// Allocate a call log owned by the mock facet, sized by `crate::CallLog::space`
+pub fn create_call_log(
+    payer: &Pubkey,
+    call_log: &Pubkey,
+    mock_program: &Pubkey,
+    rent: &Rent,
+    space: usize,
+) -> Instruction {
+    system_instruction::create_account(
+        payer,
+        call_log,
+        rent.minimum_balance(space),
+        space as u64,
+        mock_program,
+    )
+}
+
+/// Register the mock facet under `selector` via `add_module`
+///
+/// `authority` must be the owner, an admin or the namespace's tenant.
+pub fn add_mock_module(
+    router: &Pubkey,
+    diamond_state: &Pubkey,
+    authority: &Pubkey,
+    mock_program: &Pubkey,
+    selector: impl Into<Selector>,
+    namespace: &str,
+    function_name: &str,
+) -> Instruction {
+    let args = AddModuleArgs {
+        module_name: MOCK_MODULE_NAME.to_string(),
+        module_address: *mock_program,
+        selector: selector.into(),
+        function_name: function_name.to_string(),
+        is_immutable: false,
+        namespace: namespace.to_string(),
+        standard_accounts: 0,
+    };
+    let mut data = ADD_MODULE_DISCRIMINATOR.to_vec();
+    // Writing into a Vec cannot fail
+    args.serialize(&mut data).expect("borsh encode");
+    
+    Instruction {
+        program_id: *router,
+        accounts: vec![AccountMeta::new(*diamond_state, false), AccountMeta::new_readonly(*authority, true)],
+        data,
+    }
+}
+
+/// A cut routing `selector` to the mock facet, for proposals and tenant cuts
+pub fn mock_cut(selector: impl Into<Selector>, mock_program: &Pubkey, function_name: &str) -> FacetCut {
+    FacetCut::add(selector, *mock_program, MOCK_MODULE_NAME, function_name)
+}
+
+/// Dispatch `ix_data` (selector first) through the router to the mock facet
+///
+/// The call log goes first, then `accounts` as the facet should see them.
+pub fn dispatch_to_mock(
+    router: &Pubkey,
+    diamond_state: &Pubkey,
+    mock_program: &Pubkey,
+    call_log: &Pubkey,
+    ix_data: &[u8],
+    accounts: &[AccountMeta],
+) -> Instruction {
+    let mut data = DISPATCH_DISCRIMINATOR.to_vec();
+    // Writing into a Vec cannot fail
+    ix_data.to_vec().serialize(&mut data).expect("borsh encode");
+    
+    let mut metas = vec![
+        AccountMeta::new(*diamond_state, false),
+        AccountMeta::new_readonly(*mock_program, false),
+        AccountMeta::new(*call_log, false),
+    ];
+    metas.extend_from_slice(accounts);
+    Instruction { program_id: *router, accounts: metas, data }
+}
@@ -0,0 +1,735 @@
+/*!
+ * Dispatch Sandbox
+ * In-process what-if runs of router instructions against cloned accounts
+ *
+ * A `Sandbox` holds copies of accounts and the processors of the programs
+ * under test (the router, the mock facet, a facet's own `process_instruction`)
+ * linked in as Rust functions. It executes an instruction in this process,
+ * CPIs included, through `solana_program`'s syscall stubs: invocations are
+ * routed to the registered processors, PDA signatures are checked against
+ * the caller's seeds, and the clock, rent, stack height, return data and
+ * logs behave as on a validator. Each run reports exactly which accounts
+ * changed and how, so a facet author can assert the state a call produces
+ * through the router path without deploying anything.
+ *
+ * `what_if` clones the accounts an instruction names into a fresh sandbox
+ * and runs it there, leaving the original untouched; `process` commits.
+ * Like the runtime, a program may only change the data of accounts it owns
+ * and only debit their lamports, and never touch a read-only account. A
+ * failed invocation fails the whole run with the callee's error, even if the
+ * caller carries on past it, and nothing is committed.
+ *
+ * Not modelled: account reallocation, compute metering, and transaction
+ * signatures (a top-level signer is whatever the instruction marks as one).
+ * Accounts can't grow, so creation is limited: with
+ * `process_system_instruction` loaded as the system program, an account is
+ * created only within the space reserved for it with `SandboxAccount::reserved`.
+ */
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use solana_program::{
+    account_info::AccountInfo,
+    bpf_loader_upgradeable,
+    clock::Clock,
+    entrypoint::{ProcessInstruction, ProgramResult},
+    instruction::Instruction,
+    program_error::{ProgramError, UNSUPPORTED_SYSVAR},
+    program_stubs::{self, SyscallStubs},
+    pubkey::Pubkey,
+    rent::Rent,
+    system_program,
+};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    ops::Range,
+    sync::Once,
+};
+
+use diamond_router_native::diamond_state::DiamondState;
+
+/// One account as the sandbox stores it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SandboxAccount {
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    pub owner: Pubkey,
+    pub executable: bool,
+}
+
+impl Default for SandboxAccount {
+    /// An account that doesn't exist yet
+    fn default() -> Self {
+        Self { lamports: 0, data: Vec::new(), owner: system_program::id(), executable: false }
+    }
+}
+
+impl SandboxAccount {
+    /// Rent-exempt account holding `data`
+    pub fn new(owner: Pubkey, data: Vec<u8>) -> Self {
+        Self { lamports: Rent::default().minimum_balance(data.len()), data, owner, executable: false }
+    }
+    
+    /// An account that doesn't exist yet, with room for the system program
+    /// to allocate up to `space` bytes
+    pub fn reserved(space: usize) -> Self {
+        Self { data: vec![0; space], ..Self::default() }
+    }
+    
+    /// Diamond state account of `router` holding `state`, allocated at `SPACE`
+    pub fn diamond_state(router: &Pubkey, state: &DiamondState) -> Self {
+        let mut data = borsh::to_vec(state).expect("state encodes");
+        data.resize(DiamondState::SPACE, 0);
+        Self::new(*router, data)
+    }
+}
+
+/// How one account changed over a run
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AccountDiff {
+    pub pubkey: Pubkey,
+    pub before: SandboxAccount,
+    pub after: SandboxAccount,
+}
+
+impl AccountDiff {
+    pub fn lamports_delta(&self) -> i128 {
+        i128::from(self.after.lamports) - i128::from(self.before.lamports)
+    }
+    
+    /// Byte ranges of the data that differ
+    pub fn changed_ranges(&self) -> Vec<Range<usize>> {
+        changed_ranges(&self.before.data, &self.after.data)
+    }
+}
+
+/// Byte ranges where `before` and `after` differ, a change of length
+/// counting as a difference over the tail
+pub fn changed_ranges(before: &[u8], after: &[u8]) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    let changed = before.iter().zip(after).enumerate().filter(|(_, (a, b))| a != b);
+    for (i, _) in changed {
+        match ranges.last_mut() {
+            Some(range) if range.end == i => range.end = i + 1,
+            _ => ranges.push(i..i + 1),
+        }
+    }
+    let (shared, longest) = (before.len().min(after.len()), before.len().max(after.len()));
+    if shared < longest {
+        match ranges.last_mut() {
+            Some(range) if range.end == shared => range.end = longest,
+            _ => ranges.push(shared..longest),
+        }
+    }
+    ranges
+}
+
+/// Result of running one instruction
+#[derive(Clone, Debug, PartialEq)]
+pub struct Outcome {
+    pub result: ProgramResult,
+    /// Accounts that changed, in key order (empty when the run failed)
+    pub diffs: Vec<AccountDiff>,
+    /// Program logs, invocation lines and `Program data:` events included
+    pub logs: Vec<String>,
+    pub return_data: Option<(Pubkey, Vec<u8>)>,
+}
+
+impl Outcome {
+    pub fn diff(&self, pubkey: &Pubkey) -> Option<&AccountDiff> {
+        self.diffs.iter().find(|d| &d.pubkey == pubkey)
+    }
+}
+
+/// Accounts and programs to run instructions against
+#[derive(Clone, Default)]
+pub struct Sandbox {
+    programs: HashMap<Pubkey, ProcessInstruction>,
+    accounts: BTreeMap<Pubkey, SandboxAccount>,
+    clock: Clock,
+}
+
+impl Sandbox {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Serve `program_id` with `processor` (its `process_instruction`)
+    pub fn add_program(&mut self, program_id: Pubkey, processor: ProcessInstruction) -> &mut Self {
+        self.programs.insert(program_id, processor);
+        self
+    }
+    
+    pub fn add_account(&mut self, pubkey: Pubkey, account: SandboxAccount) -> &mut Self {
+        self.accounts.insert(pubkey, account);
+        self
+    }
+    
+    pub fn account(&self, pubkey: &Pubkey) -> Option<&SandboxAccount> {
+        self.accounts.get(pubkey)
+    }
+    
+    pub fn set_clock(&mut self, clock: Clock) -> &mut Self {
+        self.clock = clock;
+        self
+    }
+    
+    /// Fresh sandbox with the same programs and clock, holding copies of
+    /// `keys` only
+    pub fn fork(&self, keys: &[Pubkey]) -> Self {
+        Self {
+            programs: self.programs.clone(),
+            accounts: keys.iter().filter_map(|k| Some((*k, self.accounts.get(k)?.clone()))).collect(),
+            clock: self.clock.clone(),
+        }
+    }
+    
+    /// Run `ix` against a fork of the accounts it names; `self` is unchanged
+    pub fn what_if(&self, ix: &Instruction) -> Outcome {
+        let keys: Vec<Pubkey> = ix.accounts.iter().map(|m| m.pubkey).collect();
+        self.fork(&keys).process(ix)
+    }
+    
+    /// Run `ix`, keeping its changes if it succeeds
+    pub fn process(&mut self, ix: &Instruction) -> Outcome {
+        install_stubs();
+        
+        // One entry per distinct key, with the privileges of any of its metas
+        let mut keys: Vec<Pubkey> = Vec::new();
+        let mut privileges: Vec<(bool, bool)> = Vec::new();
+        for meta in &ix.accounts {
+            match keys.iter().position(|k| k == &meta.pubkey) {
+                Some(i) => {
+                    privileges[i].0 |= meta.is_signer;
+                    privileges[i].1 |= meta.is_writable;
+                }
+                None => {
+                    keys.push(meta.pubkey);
+                    privileges.push((meta.is_signer, meta.is_writable));
+                }
+            }
+        }
+        let before: Vec<SandboxAccount> = keys.iter().map(|k| self.load(k)).collect();
+        let mut storage = before.clone();
+        
+        CONTEXT.with(|context| {
+            *context.borrow_mut() = Some(Context {
+                programs: self.programs.clone(),
+                stack: Vec::new(),
+                verified: keys.iter().zip(&before).map(|(k, a)| (*k, (a.lamports, a.data.clone()))).collect(),
+                return_data: None,
+                logs: Vec::new(),
+                clock: self.clock.clone(),
+                failure: None,
+            })
+        });
+        let (result, lengths) = {
+            let infos: Vec<AccountInfo> = keys
+                .iter()
+                .zip(storage.iter_mut())
+                .zip(&privileges)
+                .map(|((key, account), (signer, writable))| {
+                    let SandboxAccount { lamports, data, owner, executable } = account;
+                    AccountInfo::new(key, *signer, *writable, lamports, data, owner, *executable, 0)
+                })
+                .collect();
+            let ordered: Vec<AccountInfo> = ix
+                .accounts
+                .iter()
+                .map(|meta| infos[keys.iter().position(|k| k == &meta.pubkey).unwrap()].clone())
+                .collect();
+            let result = invoke_program(&ix.program_id, &ordered, &ix.data);
+            // Allocation shrinks an account's data to the size created
+            (result, infos.iter().map(AccountInfo::data_len).collect::<Vec<_>>())
+        };
+        let context = CONTEXT.with(|context| context.borrow_mut().take()).expect("context set above");
+        // On a validator a failed CPI never returns to the caller
+        let result = match context.failure {
+            Some(err) => Err(err),
+            None => result,
+        };
+        
+        let mut diffs = Vec::new();
+        if result.is_ok() {
+            for (((key, before), mut after), len) in keys.into_iter().zip(before).zip(storage).zip(lengths) {
+                after.data.truncate(len);
+                if before != after {
+                    diffs.push(AccountDiff { pubkey: key, before, after: after.clone() });
+                }
+                self.accounts.insert(key, after);
+            }
+            diffs.sort_by_key(|d| d.pubkey);
+        }
+        Outcome { result, diffs, logs: context.logs, return_data: context.return_data }
+    }
+    
+    /// Stored account, a loaded program's executable stand-in, or an empty one
+    fn load(&self, pubkey: &Pubkey) -> SandboxAccount {
+        match (self.accounts.get(pubkey), self.programs.contains_key(pubkey)) {
+            (Some(account), _) => account.clone(),
+            (None, true) => SandboxAccount {
+                lamports: 1,
+                data: Vec::new(),
+                owner: bpf_loader_upgradeable::id(),
+                executable: true,
+            },
+            (None, false) => SandboxAccount::default(),
+        }
+    }
+}
+
+/// The system program's `create_account`, `assign`, `transfer` and
+/// `allocate`, for `add_program(system_program::id(), ..)`; allocation only
+/// succeeds into an account reserved at the size asked for
+pub fn process_system_instruction(_program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let word = |offset: usize| data.get(offset..offset + 8).map(|b| u64::from_le_bytes(b.try_into().unwrap()));
+    let key = |offset: usize| data.get(offset..offset + 32).map(|b| Pubkey::try_from(b).unwrap());
+    let account = |i: usize| accounts.get(i).ok_or(ProgramError::NotEnoughAccountKeys);
+    let tag = data.get(..4).map(|b| u32::from_le_bytes(b.try_into().unwrap()));
+    match (tag, word(4), word(12), key(20), key(4)) {
+        // CreateAccount { lamports, space, owner }
+        (Some(0), Some(lamports), Some(space), Some(owner), _) => {
+            let (from, to) = (account(0)?, account(1)?);
+            if to.lamports() > 0 {
+                log(format!("Create Account: account {} already in use", to.key));
+                return Err(ProgramError::AccountAlreadyInitialized);
+            }
+            system_transfer(from, to, lamports)?;
+            system_allocate(to, space as usize)?;
+            system_assign(to, &owner)
+        }
+        // Assign { owner }
+        (Some(1), _, _, _, Some(owner)) => system_assign(account(0)?, &owner),
+        // Transfer { lamports }
+        (Some(2), Some(lamports), ..) => system_transfer(account(0)?, account(1)?, lamports),
+        // Allocate { space }
+        (Some(8), Some(space), ..) => system_allocate(account(0)?, space as usize),
+        _ => {
+            log("Sandbox system program: unsupported instruction".to_string());
+            Err(ProgramError::InvalidInstructionData)
+        }
+    }
+}
+
+fn system_transfer(from: &AccountInfo, to: &AccountInfo, lamports: u64) -> ProgramResult {
+    if !from.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if from.owner != &system_program::id() {
+        log(format!("Transfer: `from` {} must not carry data", from.key));
+        return Err(ProgramError::InvalidArgument);
+    }
+    let remaining = from.lamports().checked_sub(lamports).ok_or(ProgramError::InsufficientFunds)?;
+    **from.try_borrow_mut_lamports()? = remaining;
+    **to.try_borrow_mut_lamports()? += lamports;
+    Ok(())
+}
+
+fn system_allocate(account: &AccountInfo, space: usize) -> ProgramResult {
+    if !account.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    let mut data = account.try_borrow_mut_data()?;
+    if account.owner != &system_program::id() || data.iter().any(|b| *b != 0) {
+        log(format!("Allocate: account {} already in use", account.key));
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    if data.len() < space {
+        log(format!("Sandbox can't allocate {} bytes for {}; reserve them with SandboxAccount::reserved", space, account.key));
+        return Err(ProgramError::InvalidRealloc);
+    }
+    let reserved = std::mem::take(&mut *data);
+    *data = &mut reserved[..space];
+    // The new size is the system program's doing, not a write to check
+    let state = (account.lamports(), data.to_vec());
+    with_context(|context| context.verified.insert(*account.key, state));
+    Ok(())
+}
+
+fn system_assign(account: &AccountInfo, owner: &Pubkey) -> ProgramResult {
+    if !account.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if account.owner != owner {
+        if account.owner != &system_program::id() {
+            log(format!("Assign: account {} must be owned by the system program", account.key));
+            return Err(ProgramError::IllegalOwner);
+        }
+        account.assign(owner);
+    }
+    Ok(())
+}
+
+/// Runtime state of the run in progress on this thread
+struct Context {
+    programs: HashMap<Pubkey, ProcessInstruction>,
+    /// Programs currently executing, outermost first
+    stack: Vec<Pubkey>,
+    /// Lamports and data of each account as last attributed to a program
+    verified: HashMap<Pubkey, (u64, Vec<u8>)>,
+    return_data: Option<(Pubkey, Vec<u8>)>,
+    logs: Vec<String>,
+    clock: Clock,
+    /// First failed invocation, which fails the run whatever the caller does
+    failure: Option<ProgramError>,
+}
+
+thread_local! {
+    // Test threads run side by side, each with its own sandbox run
+    static CONTEXT: RefCell<Option<Context>> = const { RefCell::new(None) };
+}
+
+fn with_context<R>(f: impl FnOnce(&mut Context) -> R) -> Option<R> {
+    CONTEXT.with(|context| context.borrow_mut().as_mut().map(f))
+}
+
+fn log(message: String) {
+    if with_context(|context| context.logs.push(message.clone())).is_none() {
+        println!("{}", message);
+    }
+}
+
+/// Attribute every change to `accounts` since the last check to `program_id`,
+/// refusing the ones the runtime would
+fn verify_changes(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    for account in accounts {
+        let (lamports, data) = (account.lamports(), account.try_borrow_data()?.to_vec());
+        let previous = with_context(|context| context.verified.insert(*account.key, (lamports, data.clone())))
+            .flatten()
+            .unwrap_or((lamports, data.clone()));
+        let (data_changed, lamports_changed) = (previous.1 != data, previous.0 != lamports);
+        let violation = if (data_changed || lamports_changed) && !account.is_writable {
+            Some("modified read-only account")
+        } else if data_changed && account.owner != program_id {
+            Some("modified data of an account it does not own")
+        } else if lamports < previous.0 && account.owner != program_id {
+            Some("debited an account it does not own")
+        } else {
+            None
+        };
+        if let Some(violation) = violation {
+            log(format!("Program {} {} {}", program_id, violation, account.key));
+            return Err(ProgramError::InvalidAccountData);
+        }
+    }
+    Ok(())
+}
+
+fn invoke_program(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let processor = with_context(|context| {
+        let processor = context.programs.get(program_id).copied();
+        if processor.is_some() {
+            context.stack.push(*program_id);
+        }
+        processor
+    })
+    .flatten();
+    let Some(processor) = processor else {
+        log(format!("Program {} is not loaded in the sandbox", program_id));
+        return Err(ProgramError::IncorrectProgramId);
+    };
+    let depth = with_context(|context| context.stack.len()).unwrap_or(1);
+    log(format!("Program {} invoke [{}]", program_id, depth));
+    let result = processor(program_id, accounts, data).and_then(|()| verify_changes(program_id, accounts));
+    with_context(|context| context.stack.pop());
+    match &result {
+        Ok(()) => log(format!("Program {} success", program_id)),
+        Err(err) => log(format!("Program {} failed: {}", program_id, err)),
+    }
+    result
+}
+
+/// Syscalls of programs running in a sandbox; outside a run they behave
+/// like `solana_program`'s defaults
+struct SandboxStubs;
+
+impl SyscallStubs for SandboxStubs {
+    fn sol_log(&self, message: &str) {
+        log(format!("Program log: {}", message));
+    }
+    
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        let encoded: Vec<String> = fields.iter().map(|f| STANDARD.encode(f)).collect();
+        log(format!("Program data: {}", encoded.join(" ")));
+    }
+    
+    fn sol_invoke_signed(&self, instruction: &Instruction, account_infos: &[AccountInfo], signers_seeds: &[&[&[u8]]]) -> ProgramResult {
+        let Some(caller) = with_context(|context| context.stack.last().copied()).flatten() else {
+            log("SyscallStubs: sol_invoke_signed() not available".to_string());
+            return Ok(());
+        };
+        verify_changes(&caller, account_infos)?;
+        
+        let mut callee_accounts = Vec::with_capacity(instruction.accounts.len());
+        for meta in &instruction.accounts {
+            let mut account = account_infos.iter().find(|a| a.key == &meta.pubkey).cloned().ok_or_else(|| {
+                log(format!("Instruction references an unknown account {}", meta.pubkey));
+                ProgramError::NotEnoughAccountKeys
+            })?;
+            let signed = signers_seeds
+                .iter()
+                .any(|seeds| Pubkey::create_program_address(seeds, &caller).as_ref() == Ok(&meta.pubkey));
+            if meta.is_signer && !account.is_signer && !signed {
+                log(format!("{}'s signer privilege escalated", meta.pubkey));
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            if meta.is_writable && !account.is_writable {
+                log(format!("{}'s writable privilege escalated", meta.pubkey));
+                return Err(ProgramError::InvalidArgument);
+            }
+            account.is_signer = meta.is_signer;
+            account.is_writable = meta.is_writable;
+            callee_accounts.push(account);
+        }
+        if let Err(err) = invoke_program(&instruction.program_id, &callee_accounts, &instruction.data) {
+            with_context(|context| context.failure.get_or_insert_with(|| err.clone()).clone());
+            return Err(err);
+        }
+        
+        // The callee's changes are its own; the caller starts over from here
+        for account in account_infos {
+            let state = (account.lamports(), account.try_borrow_data()?.to_vec());
+            with_context(|context| context.verified.insert(*account.key, state));
+        }
+        Ok(())
+    }
+    
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        match with_context(|context| context.clock.clone()) {
+            Some(clock) => {
+                // The syscall contract: `var_addr` points at a `Clock`
+                unsafe { *(var_addr as *mut Clock) = clock };
+                0
+            }
+            None => UNSUPPORTED_SYSVAR,
+        }
+    }
+    
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        match with_context(|_| ()) {
+            Some(()) => {
+                unsafe { *(var_addr as *mut Rent) = Rent::default() };
+                0
+            }
+            None => UNSUPPORTED_SYSVAR,
+        }
+    }
+    
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        with_context(|context| context.return_data.clone()).flatten()
+    }
+    
+    fn sol_set_return_data(&self, data: &[u8]) {
+        with_context(|context| {
+            let program = context.stack.last().copied().unwrap_or_default();
+            context.return_data = (!data.is_empty()).then(|| (program, data.to_vec()));
+        });
+    }
+    
+    fn sol_get_stack_height(&self) -> u64 {
+        with_context(|context| context.stack.len() as u64).unwrap_or(0)
+    }
+}
+
+fn install_stubs() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        program_stubs::set_syscall_stubs(Box::new(SandboxStubs));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use borsh::BorshDeserialize;
+    use crate::register::dispatch_to_mock;
+    use crate::{CallLog, FAIL_SELECTOR, MOCK_FAILURE};
+    use diamond_router_native::diamond_context::{self, CallContext};
+    use diamond_router_native::diamond_cut::{apply_cuts, FacetCut};
+    use diamond_router_native::diamond_state::diamond_address;
+    use solana_program::instruction::AccountMeta;
+    
+    const SELECTOR: [u8; 4] = [1, 0, 0, 1];
+    
+    /// Router and mock facet loaded, with a diamond routing `SELECTOR` to the mock
+    fn diamond() -> (Sandbox, Pubkey, Pubkey) {
+        let (router, mock) = (diamond_router_native::id(), crate::id());
+        let (owner, log_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (state_key, bump) = diamond_address(&router, &owner, None);
+        let mut state = DiamondState::new(owner, bump);
+        apply_cuts(&mut state, &[FacetCut::add(SELECTOR, mock, "mock", "record"), FacetCut::add(FAIL_SELECTOR, mock, "mock", "fail")]).unwrap();
+        
+        let mut sandbox = Sandbox::new();
+        sandbox
+            .add_program(router, diamond_router_native::process_instruction)
+            .add_program(mock, crate::process_instruction)
+            .add_account(state_key, SandboxAccount::diamond_state(&router, &state))
+            .add_account(log_key, SandboxAccount::new(mock, vec![0; CallLog::space(2, 16, 2)]));
+        (sandbox, state_key, log_key)
+    }
+    
+    #[test]
+    fn test_what_if_reports_diffs_without_committing() {
+        let (mut sandbox, state_key, log_key) = diamond();
+        let user = Pubkey::new_unique();
+        let ix = dispatch_to_mock(
+            &diamond_router_native::id(),
+            &state_key,
+            &crate::id(),
+            &log_key,
+            &[1, 0, 0, 1, 42],
+            &[AccountMeta::new_readonly(user, true)],
+        );
+        
+        let outcome = sandbox.what_if(&ix);
+        assert_eq!(outcome.result, Ok(()));
+        // Only the call log changed; the router kept its state as it was
+        assert_eq!(outcome.diffs.len(), 1);
+        let diff = outcome.diff(&log_key).unwrap();
+        assert_eq!(diff.lamports_delta(), 0);
+        assert_eq!(diff.changed_ranges()[0].start, 0);
+        let log = CallLog::read(&diff.after.data).unwrap();
+        assert_eq!(log.calls[0].data, vec![1, 0, 0, 1, 42]);
+        assert_eq!(log.calls[0].accounts[1].pubkey, user);
+        assert!(outcome.logs.iter().any(|l| l == &format!("Program {} invoke [2]", crate::id())));
+        assert!(outcome.logs.iter().any(|l| l.starts_with("Program data: ZGlhbW9uZDo=")));
+        assert_eq!(sandbox.account(&log_key).unwrap().data, vec![0; CallLog::space(2, 16, 2)]);
+        
+        // Committing applies the same change
+        assert_eq!(sandbox.process(&ix).diffs, outcome.diffs);
+        assert_eq!(sandbox.account(&log_key).unwrap(), &diff.after);
+    }
+    
+    #[test]
+    fn test_failed_dispatch_changes_nothing() {
+        let (mut sandbox, state_key, log_key) = diamond();
+        let ix = dispatch_to_mock(&diamond_router_native::id(), &state_key, &crate::id(), &log_key, &FAIL_SELECTOR, &[]);
+        let outcome = sandbox.process(&ix);
+        assert_eq!(outcome.result, Err(ProgramError::Custom(MOCK_FAILURE)));
+        assert!(outcome.diffs.is_empty());
+    }
+    
+    #[test]
+    fn test_runtime_rules_enforced() {
+        fn scribble(_: &Pubkey, accounts: &[AccountInfo], _: &[u8]) -> ProgramResult {
+            accounts[0].try_borrow_mut_data()?[0] = 1;
+            Ok(())
+        }
+        let (program, target) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut sandbox = Sandbox::new();
+        sandbox.add_program(program, scribble).add_account(target, SandboxAccount::new(Pubkey::new_unique(), vec![0; 4]));
+        
+        let ix = |writable| Instruction {
+            program_id: program,
+            accounts: vec![if writable { AccountMeta::new(target, false) } else { AccountMeta::new_readonly(target, false) }],
+            data: vec![],
+        };
+        assert_eq!(sandbox.process(&ix(true)).result, Err(ProgramError::InvalidAccountData));
+        assert_eq!(sandbox.process(&ix(false)).result, Err(ProgramError::InvalidAccountData));
+        
+        sandbox.add_account(target, SandboxAccount::new(program, vec![0; 4]));
+        let outcome = sandbox.process(&ix(true));
+        assert_eq!(outcome.result, Ok(()));
+        assert_eq!(outcome.diff(&target).unwrap().changed_ranges(), vec![0..1]);
+    }
+    
+    #[test]
+    fn test_failed_invocation_fails_the_run() {
+        // Writes its account, then invokes the second program and ignores the result
+        fn caller(_: &Pubkey, accounts: &[AccountInfo], _: &[u8]) -> ProgramResult {
+            accounts[0].try_borrow_mut_data()?[0] = 1;
+            let ix = Instruction { program_id: *accounts[1].key, accounts: vec![], data: vec![] };
+            let _ = solana_program::program::invoke(&ix, &[]);
+            Ok(())
+        }
+        fn fail(_: &Pubkey, _: &[AccountInfo], _: &[u8]) -> ProgramResult {
+            Err(ProgramError::Custom(7))
+        }
+        let (program, failing, target) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut sandbox = Sandbox::new();
+        sandbox.add_program(program, caller).add_program(failing, fail).add_account(target, SandboxAccount::new(program, vec![0; 4]));
+        
+        let ix = Instruction {
+            program_id: program,
+            accounts: vec![AccountMeta::new(target, false), AccountMeta::new_readonly(failing, false)],
+            data: vec![],
+        };
+        let outcome = sandbox.process(&ix);
+        assert_eq!(outcome.result, Err(ProgramError::Custom(7)));
+        assert!(outcome.diffs.is_empty());
+        assert_eq!(sandbox.account(&target).unwrap().data, vec![0; 4]);
+    }
+    
+    #[test]
+    fn test_call_context_set_only_during_the_call() {
+        // Copies the call context (second account) into its record (first)
+        fn witness(_: &Pubkey, accounts: &[AccountInfo], _: &[u8]) -> ProgramResult {
+            let context = accounts[1].try_borrow_data()?;
+            accounts[0].try_borrow_mut_data()?.copy_from_slice(&context);
+            Ok(())
+        }
+        let (router, witness_id, owner) = (diamond_router_native::id(), Pubkey::new_unique(), Pubkey::new_unique());
+        let (state_key, bump) = diamond_address(&router, &owner, None);
+        let mut state = DiamondState::new(owner, bump);
+        apply_cuts(&mut state, &[FacetCut::add([2, 0, 0, 1], witness_id, "witness", "record")]).unwrap();
+        
+        let (context_key, bump) = diamond_context::find_address(&router, &state_key);
+        let idle = CallContext { diamond: state_key.to_bytes(), bump, ..CallContext::default() };
+        let (record_key, user) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut sandbox = Sandbox::new();
+        sandbox
+            .add_program(router, diamond_router_native::process_instruction)
+            .add_program(witness_id, witness)
+            .add_account(state_key, SandboxAccount::diamond_state(&router, &state))
+            .add_account(context_key, SandboxAccount::new(router, borsh::to_vec(&idle).unwrap()))
+            .add_account(record_key, SandboxAccount::new(witness_id, vec![0; CallContext::ENCODED_LEN]))
+            .add_account(user, SandboxAccount::new(system_program::id(), vec![]));
+        
+        let accounts = [AccountMeta::new(context_key, false), AccountMeta::new(user, true)];
+        let ix = dispatch_to_mock(&router, &state_key, &witness_id, &record_key, &[2, 0, 0, 1], &accounts);
+        let outcome = sandbox.process(&ix);
+        assert_eq!(outcome.result, Ok(()));
+        let record = &outcome.diff(&record_key).unwrap().after.data;
+        // The router ran at the top level, the witness one above it
+        let seen = CallContext::of_call(record, &state_key.to_bytes(), &witness_id.to_bytes(), 2).unwrap();
+        assert_eq!((seen.selector, seen.first_writable_signer, seen.signer), ([2, 0, 0, 1].into(), user.to_bytes(), user.to_bytes()));
+        assert!(outcome.diff(&context_key).is_none());
+    }
+    
+    #[test]
+    fn test_initialize_creates_reserved_state() {
+        let router = diamond_router_native::id();
+        let owner = Pubkey::new_unique();
+        let (state_key, bump) = diamond_router_native::diamond_state::diamond_address(&router, &owner, None);
+        let mut data = diamond_router_native::INITIALIZE_DISCRIMINATOR.to_vec();
+        data.extend(borsh::to_vec(&(owner, bump, None::<Vec<FacetCut>>)).unwrap());
+        let ix = Instruction {
+            program_id: router,
+            accounts: vec![
+                AccountMeta::new(state_key, false),
+                AccountMeta::new_readonly(owner, true),
+                AccountMeta::new(owner, true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+            data,
+        };
+        let mut sandbox = Sandbox::new();
+        sandbox
+            .add_program(router, diamond_router_native::process_instruction)
+            .add_program(system_program::id(), process_system_instruction)
+            .add_account(owner, SandboxAccount { lamports: 10_000_000_000, ..SandboxAccount::default() });
+        
+        // Nothing reserved, nothing to allocate into
+        assert_eq!(sandbox.process(&ix).result, Err(ProgramError::InvalidRealloc));
+        
+        sandbox.add_account(state_key, SandboxAccount::reserved(DiamondState::SPACE + 100));
+        let outcome = sandbox.process(&ix);
+        assert_eq!(outcome.result, Ok(()));
+        let created = sandbox.account(&state_key).unwrap();
+        assert_eq!((created.owner, created.data.len()), (router, DiamondState::SPACE));
+        assert_eq!(created.lamports, Rent::default().minimum_balance(DiamondState::SPACE));
+        assert_eq!(outcome.diff(&owner).unwrap().lamports_delta(), -(created.lamports as i128));
+        assert_eq!(DiamondState::deserialize(&mut &created.data[..]).unwrap().owner, owner);
+        assert_eq!(sandbox.process(&ix).result, Err(ProgramError::AccountAlreadyInitialized));
+    }
+}
@@ -0,0 +1,244 @@
+/*!
+ * Diamond Test Vectors
+ *
+ * Canonical byte encodings generated from the Rust code: selector
+ * derivations, PDA derivations, instruction data, diamond state, events and
+ * the off-chain signing message.
+ * `vectors.json` in this crate is the published copy; TypeScript and Python
+ * clients load it as fixtures, and the test below fails when the program's
+ * encodings drift from it. Regenerate after an intended wire change with
+ * `cargo run -p diamond-test-vectors > test-vectors/vectors.json`.
+ *
+ * Every input is fixed (keys are `[n; 32]`), so the output is identical on
+ * every machine. Byte strings are lowercase hex; keys are base58.
+ */
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use diamond_cli::{admin, resolve};
+use diamond_router_native::{
+    builtins::{PAUSE_SELECTOR, SET_TAGS_SELECTOR},
+    compress,
+    constants::{discriminators::INSTRUCTIONS, seeds},
+    diamond_cut::{apply_cuts, FacetCut},
+    diamond_digest::RoutingDigest,
+    diamond_fast_path::FastPath,
+    diamond_relay::{accounts_hash, relay_signer, RelayPayload},
+    diamond_router::facet_signer,
+    diamond_state::{diamond_address, DiamondState},
+    diamond_tags::tag_bytes,
+    diamond_vault::rent_vault,
+    events::{DiamondEvent, EVENT_PREFIX},
+    selector::{self, encode_call, Selector},
+};
+use serde_json::{json, Value};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+
+/// Router program id the vectors are derived under
+pub const ROUTER: Pubkey = Pubkey::new_from_array([0xD1; 32]);
+
+/// Fixed key number `n`
+pub fn key(n: u8) -> Pubkey {
+    Pubkey::new_from_array([n; 32])
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn selectors() -> Value {
+    let sighashes: Vec<Value> = [("global", "initialize"), ("counter", "increment"), ("vault", "deposit")]
+        .iter()
+        .flat_map(|(namespace, function)| {
+            [4u8, 8].map(|width| {
+                json!({
+                    "namespace": namespace,
+                    "function": function,
+                    "width": width,
+                    "selector": hex(Selector::sighash(namespace, function, width).as_bytes(width)),
+                })
+            })
+        })
+        .collect();
+    let allocated: Vec<Value> = (0..3u8)
+        .map(|salt| {
+            json!({
+                "namespace": "token",
+                "signature": "transfer(u64)",
+                "width": 4,
+                "salt": salt,
+                "selector": hex(selector::allocated("token", "transfer(u64)", 4, salt).as_bytes(4)),
+            })
+        })
+        .collect();
+    json!({ "sighash": sighashes, "allocated": allocated })
+}
+
+fn pdas() -> Value {
+    let (owner, facet, user) = (key(1), key(2), key(3));
+    let (diamond, bump) = diamond_address(&ROUTER, &owner, None);
+    let (vanity, vanity_bump) = diamond_address(&ROUTER, &owner, Some(7));
+    let pda = |name: &str, seeds: Value, (address, bump): (Pubkey, u8)| {
+        json!({ "name": name, "seeds": seeds, "address": address.to_string(), "bump": bump })
+    };
+    json!({
+        "router": ROUTER.to_string(),
+        "seeds": seeds::ALL.iter().map(|s| String::from_utf8_lossy(s)).collect::<Vec<_>>(),
+        "derivations": [
+            pda("diamond_state", json!(["diamond_state", owner.to_string()]), (diamond, bump)),
+            pda("diamond_state_vanity", json!(["diamond_state", owner.to_string(), hex(&7u64.to_le_bytes())]), (vanity, vanity_bump)),
+            pda("fast_path", json!(["fast_path", diamond.to_string()]), FastPath::find_address(&ROUTER, &diamond)),
+            pda("routing_digest", json!(["routing_digest", diamond.to_string()]), RoutingDigest::find_address(&ROUTER, &diamond)),
+            pda("rent_vault", json!(["rent_vault", diamond.to_string()]), rent_vault(&ROUTER, &diamond)),
+            pda("relay_signer", json!(["relayed", diamond.to_string(), user.to_string()]), relay_signer(&ROUTER, &diamond, &user)),
+            pda(
+                "facet_signer",
+                json!(["signer", diamond.to_string(), facet.to_string(), hex(b"vault")]),
+                facet_signer(&ROUTER, &diamond, &facet, b"vault"),
+            ),
+        ],
+    })
+}
+
+fn instruction(name: &str, ix: &Instruction) -> Value {
+    let accounts: Vec<Value> = ix
+        .accounts
+        .iter()
+        .map(|m| json!({ "pubkey": m.pubkey.to_string(), "signer": m.is_signer, "writable": m.is_writable }))
+        .collect();
+    json!({ "name": name, "accounts": accounts, "data": hex(&ix.data) })
+}
+
+fn instructions() -> Value {
+    let (owner, facet) = (key(1), key(2));
+    let (diamond, _) = diamond_address(&ROUTER, &owner, None);
+    let state = DiamondState::new(owner, 255);
+    let cuts = [
+        FacetCut { namespace: "counter".into(), ..FacetCut::add([1, 0, 0, 1], facet, "counter", "increment") },
+        FacetCut::remove([1, 0, 0, 2]),
+    ];
+    let cut_ixs = admin::cut_instructions(&ROUTER, &diamond, &state, &owner, &cuts).expect("valid cuts");
+    let call = encode_call([1, 0, 0, 1], 4, &5u64.to_le_bytes());
+    let mut pause = PAUSE_SELECTOR.to_vec();
+    pause.extend(borsh::to_vec(&(true, Some(1_000u64))).unwrap());
+    let mut tag = SET_TAGS_SELECTOR.to_vec();
+    tag.extend(borsh::to_vec(&(Selector::from([1, 0, 0, 1]), vec!["defi".to_string()])).unwrap());
+    let batch: Vec<u8> = (0..16u8).flat_map(|i| [i % 2; 24]).collect();
+    let batch_call = encode_call([1, 0, 0, 3], 4, &batch);
+    let mapped = vec![(1, AccountMeta::new(key(6), false)), (0, AccountMeta::new_readonly(key(3), true))];
+    
+    json!({
+        "discriminators": INSTRUCTIONS.iter().map(|(d, name)| json!({ "name": name, "discriminator": hex(d) })).collect::<Vec<_>>(),
+        "examples": [
+            instruction("add_module", &cut_ixs[0]),
+            instruction("remove_module", &cut_ixs[1]),
+            instruction("pause", &admin::pause_instruction(&ROUTER, &diamond, &owner, &[], true, None)),
+            instruction("dispatch", &resolve::dispatch(&ROUTER, &diamond, &facet, vec![], &call)),
+            instruction("dispatch_builtin_pause", &resolve::dispatch(&ROUTER, &diamond, &ROUTER, vec![], &pause)),
+            instruction("dispatch_builtin_set_tags", &resolve::dispatch(&ROUTER, &diamond, &ROUTER, vec![], &tag)),
+            instruction("dispatch_compressed", &resolve::dispatch_compressed(&ROUTER, &diamond, &facet, vec![], &batch_call)),
+            instruction("dispatch_mapped", &resolve::dispatch_mapped(&ROUTER, &diamond, &facet, &key(5), mapped, &call)),
+            instruction("dispatch_indexed", &resolve::dispatch_indexed(&ROUTER, &diamond, &facet, vec![], &call)),
+        ],
+    })
+}
+
+fn state() -> Value {
+    let (owner, facet) = (key(1), key(2));
+    let mut state = DiamondState::new(owner, 254);
+    state.admins.push(key(4));
+    apply_cuts(&mut state, &[
+        FacetCut { namespace: "counter".into(), ..FacetCut::add([1, 0, 0, 1], facet, "counter", "increment") },
+        FacetCut { namespace: "counter".into(), is_immutable: true, ..FacetCut::add([1, 0, 0, 2], facet, "counter", "get") },
+    ])
+    .expect("valid cuts");
+    state.set_tags([1, 0, 0, 2].into(), &[tag_bytes("view").unwrap()]).expect("valid tags");
+    let encoded = borsh::to_vec(&state).unwrap();
+    json!({
+        "description": "owner key(1), bump 254, admin key(4), counter facet key(2) at 01000001 and immutable 01000002 tagged view",
+        "space": DiamondState::SPACE,
+        "encoded_len": encoded.len(),
+        // The account keeps SPACE bytes; everything past the encoding is zero
+        "encoded": hex(&encoded),
+    })
+}
+
+fn events() -> Value {
+    let events = [
+        ("AdminsChanged", DiamondEvent::AdminsChanged { added: vec![key(4)], removed: vec![] }),
+        ("Dispatched", DiamondEvent::Dispatched { trace_id: Some([7; 16]), selector: [1, 0, 0, 1].into(), facet: key(2) }),
+        ("TagPauseChanged", DiamondEvent::TagPauseChanged { tag: tag_bytes("defi").unwrap(), paused: true }),
+    ];
+    let encoded: Vec<Value> = events
+        .iter()
+        .map(|(name, event)| {
+            let data = borsh::to_vec(event).unwrap();
+            json!({
+                "name": name,
+                "data": hex(&data),
+                "log": format!("Program data: {} {}", STANDARD.encode(EVENT_PREFIX), STANDARD.encode(&data)),
+            })
+        })
+        .collect();
+    json!({ "prefix": hex(EVENT_PREFIX), "events": encoded })
+}
+
+fn signing() -> Value {
+    let relay = RelayPayload {
+        domain: "app.example.com".into(),
+        diamond: diamond_address(&ROUTER, &key(1), None).0,
+        facet: key(2),
+        user: key(3),
+        nonce: 0,
+        expiry_slot: 1_000,
+        ix_data: encode_call([1, 0, 0, 1], 4, &5u64.to_le_bytes()),
+        accounts_hash: accounts_hash([(&key(5), false, true)]),
+        max_reimbursement: 5_000,
+    };
+    let payload = relay.signing_payload();
+    json!({
+        "description": "relay of counter::increment(5) on counter key(5) by key(3) to key(2) on key(1)'s diamond, reimbursing at most 5000 lamports",
+        "relay_payload": hex(&borsh::to_vec(&relay).unwrap()),
+        "data_hash": hex(&payload.data_hash),
+        "message": String::from_utf8(payload.message()).expect("utf-8 message"),
+    })
+}
+
+fn compression() -> Value {
+    let inputs: [&[u8]; 3] = [b"", b"abcabcabcabcabcabcabcabc", b"no repeats"];
+    let frames: Vec<Value> = inputs
+        .iter()
+        .map(|input| json!({ "input": hex(input), "frame": hex(&compress::compress(input)) }))
+        .collect();
+    json!(frames)
+}
+
+/// Every vector, in the layout of `vectors.json`
+pub fn generate() -> Value {
+    json!({
+        "version": 1,
+        "selectors": selectors(),
+        "pdas": pdas(),
+        "instructions": instructions(),
+        "state": state(),
+        "events": events(),
+        "signing": signing(),
+        "compression": compression(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_published_vectors_current() {
+        let published: Value = serde_json::from_str(include_str!("../vectors.json")).expect("vectors.json parses");
+        assert!(
+            published == generate(),
+            "Encodings drifted from vectors.json; if intended, run `cargo run -p diamond-test-vectors > test-vectors/vectors.json`"
+        );
+    }
+}
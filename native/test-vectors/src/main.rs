@@ -0,0 +1,12 @@
+/*!
+ * Diamond Test Vectors
+ *
+ * Prints the canonical vectors as JSON.
+ *
+ * Run: cargo run -p diamond-test-vectors > test-vectors/vectors.json
+ */
+
+fn main() {
+    let vectors = diamond_test_vectors::generate();
+    println!("{}", serde_json::to_string_pretty(&vectors).expect("JSON values always serialize"));
+}
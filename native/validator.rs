@@ -1,16 +0,0 @@
-/*!
- * Portability Validator
- * Proves the native implementation is framework-independent
- */
-
-fn main() {
-    println!("🧬 Diamond Protocol - Portability Validation\n");
-    
-    println!("✅ PDA derivation: native Solana (Pubkey::find_program_address)");
-    println!("✅ Selector lookup: pure Rust (Vec::iter().find())");
-    println!("✅ CPI dispatch: native Solana (invoke)");
-    println!("✅ State management: Borsh serialization");
-    println!("✅ No Anchor dependencies");
-    
-    println!("\n💎 Architecture is portable and framework-independent!");
-}
@@ -1,65 +1,104 @@
 /*!
  * Diamond Cut Module
- * Add/remove facets dynamically
+ * Add/replace/remove facets dynamically
  */
 
 use anchor_lang::prelude::*;
-use crate::diamond_state::{SelectorMapping, ModuleMeta};
+use crate::diamond_invariants;
+use crate::diamond_state::{DiamondState, SelectorMapping, ModuleMeta};
 use crate::error::DiamondError;
 
-/// Add a new facet to the diamond
-pub fn add_facet(
-    ctx: Context<crate::AddFacet>,
-    selector: [u8; 4],
-    module_address: Pubkey,
-    function_name: String,
-    is_immutable: bool,
-) -> Result<()> {
-    let diamond = &mut ctx.accounts.diamond_state;
-    
-    // Check capacity
+/// EIP-2535 cut action
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FacetCutAction {
+    Add,
+    Replace,
+    Remove,
+}
+
+/// A single routing table change
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct FacetCut {
+    pub action: FacetCutAction,
+    pub selector: [u8; 4],
+    pub module_address: Pubkey,
+    pub function_name: String,
+    pub is_immutable: bool,
+}
+
+impl FacetCut {
+    /// Longest function name that fits the per-selector SPACE budget
+    pub const MAX_NAME_LEN: usize = 64;
+}
+
+/// Apply a single cut to the routing table
+pub fn apply_cut(diamond: &mut DiamondState, cut: &FacetCut) -> Result<()> {
+    // The router (which also owns the diamond state) can never be a facet
     require!(
-        diamond.selectors.len() < crate::diamond_state::DiamondState::MAX_SELECTORS,
-        DiamondError::MaxFacetsReached
+        cut.module_address != crate::ID,
+        DiamondError::SelfDispatchForbidden
     );
-    
-    // Check for collision
     require!(
-        diamond.get_facet_by_selector(selector).is_none(),
-        DiamondError::SelectorCollision
+        cut.function_name.len() <= FacetCut::MAX_NAME_LEN,
+        DiamondError::InvalidCut
     );
     
-    // Add selector mapping
-    diamond.selectors.push(SelectorMapping {
-        selector,
-        module: module_address,
-        function_name: function_name.clone(),
-        is_immutable,
-    });
+    match cut.action {
+        FacetCutAction::Add => {
+            // Check capacity
+            require!(
+                diamond.selectors.len() < DiamondState::MAX_SELECTORS,
+                DiamondError::MaxFacetsReached
+            );
+            
+            // Check for collision
+            require!(
+                diamond.get_facet_by_selector(cut.selector).is_none(),
+                DiamondError::SelectorCollision
+            );
+            
+            // Add selector mapping
+            diamond.selectors.push(SelectorMapping {
+                selector: cut.selector,
+                module: cut.module_address,
+                function_name: cut.function_name.clone(),
+                is_immutable: cut.is_immutable,
+            });
+        }
+        FacetCutAction::Replace => {
+            let index = mutable_selector_index(diamond, cut.selector)?;
+            let mapping = &mut diamond.selectors[index];
+            mapping.module = cut.module_address;
+            mapping.is_immutable = cut.is_immutable;
+            if !cut.function_name.is_empty() {
+                mapping.function_name = cut.function_name.clone();
+            }
+        }
+        FacetCutAction::Remove => {
+            let index = mutable_selector_index(diamond, cut.selector)?;
+            diamond.selectors.remove(index);
+            return Ok(());
+        }
+    }
     
     // Add module if not already present
-    if !diamond.modules.iter().any(|m| m.address == module_address) {
+    if !diamond.modules.iter().any(|m| m.address == cut.module_address) {
+        require!(
+            diamond.modules.len() < DiamondState::MAX_MODULES,
+            DiamondError::MaxFacetsReached
+        );
         diamond.modules.push(ModuleMeta {
-            name: function_name.clone(),
-            address: module_address,
+            name: cut.function_name.clone(),
+            address: cut.module_address,
             version: 1,
         });
     }
     
-    msg!(
-        "Facet added: selector {:?} -> {} ({})",
-        selector,
-        module_address,
-        function_name
-    );
-    
     Ok(())
 }
 
-/// Remove a facet from the diamond
-pub fn remove_facet(ctx: Context<crate::RemoveFacet>, selector: [u8; 4]) -> Result<()> {
-    let diamond = &mut ctx.accounts.diamond_state;
-    
+/// Index of an existing, mutable selector
+fn mutable_selector_index(diamond: &DiamondState, selector: [u8; 4]) -> Result<usize> {
     // Find selector
     let index = diamond
         .selectors
@@ -73,8 +112,52 @@ pub fn remove_facet(ctx: Context<crate::RemoveFacet>, selector: [u8; 4]) -> Resu
         DiamondError::ImmutableFacet
     );
     
-    // Remove
-    diamond.selectors.remove(index);
+    Ok(index)
+}
+
+/// Add a new facet to the diamond
+pub fn add_facet(
+    ctx: Context<crate::AddFacet>,
+    selector: [u8; 4],
+    module_address: Pubkey,
+    function_name: String,
+    is_immutable: bool,
+) -> Result<()> {
+    let diamond = &mut ctx.accounts.diamond_state;
+    let before = diamond_invariants::snapshot(diamond);
+    
+    apply_cut(diamond, &FacetCut {
+        action: FacetCutAction::Add,
+        selector,
+        module_address,
+        function_name: function_name.clone(),
+        is_immutable,
+    })?;
+    diamond_invariants::audit(before.as_ref(), diamond)?;
+    
+    msg!(
+        "Facet added: selector {:?} -> {} ({})",
+        selector,
+        module_address,
+        function_name
+    );
+    
+    Ok(())
+}
+
+/// Remove a facet from the diamond
+pub fn remove_facet(ctx: Context<crate::RemoveFacet>, selector: [u8; 4]) -> Result<()> {
+    let diamond = &mut ctx.accounts.diamond_state;
+    let before = diamond_invariants::snapshot(diamond);
+    
+    apply_cut(diamond, &FacetCut {
+        action: FacetCutAction::Remove,
+        selector,
+        module_address: Pubkey::default(),
+        function_name: String::new(),
+        is_immutable: false,
+    })?;
+    diamond_invariants::audit(before.as_ref(), diamond)?;
     
     msg!("Facet removed: selector {:?}", selector);
     Ok(())
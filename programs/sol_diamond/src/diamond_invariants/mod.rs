@@ -0,0 +1,140 @@
+/*!
+ * Diamond Invariants Module
+ * State invariants checked after every mutating instruction in audit builds
+ *
+ * With the `audit` feature each handler that changes the diamond state
+ * snapshots it first and, once its changes are made, checks that:
+ * - no selector is registered twice and no unpause approver is listed twice
+ * - every list and name is within its capacity and the state fits `SPACE`
+ * - every immutable mapping from before is still there, unchanged
+ *
+ * The table is searched linearly in insertion order, so unlike the native
+ * router's index there is no ordering to check. A violation fails the
+ * instruction with `InvariantViolated`, naming the invariant in the log.
+ * Without the feature both calls are no-ops.
+ */
+
+use anchor_lang::prelude::*;
+use crate::diamond_cut::FacetCut;
+use crate::diamond_state::DiamondState;
+use crate::error::DiamondError;
+
+/// Broken state invariant
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Violation {
+    DuplicateSelector,
+    DuplicateApprover,
+    /// A list or name is over capacity or the state outgrew its account
+    Oversized,
+    /// An immutable mapping was removed or changed
+    ImmutableChanged,
+}
+
+/// Check `after` against the state invariants and, if given, against the
+/// state `before` the instruction
+pub fn check(before: Option<&DiamondState>, after: &DiamondState) -> std::result::Result<(), Violation> {
+    let selectors = &after.selectors;
+    if selectors.iter().enumerate().any(|(i, m)| selectors[..i].iter().any(|s| s.selector == m.selector)) {
+        return Err(Violation::DuplicateSelector);
+    }
+    let approvers = &after.unpause_approvers;
+    if approvers.iter().enumerate().any(|(i, approver)| approvers[..i].contains(approver)) {
+        return Err(Violation::DuplicateApprover);
+    }
+    
+    let encoded = after.try_to_vec().map_err(|_| Violation::Oversized)?.len();
+    if after.selectors.len() > DiamondState::MAX_SELECTORS
+        || after.modules.len() > DiamondState::MAX_MODULES
+        || after.unpause_approvers.len() > DiamondState::MAX_UNPAUSE_APPROVERS
+        || after.selectors.iter().any(|m| m.function_name.len() > FacetCut::MAX_NAME_LEN)
+        || after.modules.iter().any(|m| m.name.len() > FacetCut::MAX_NAME_LEN)
+        || 8 + encoded > DiamondState::SPACE
+    {
+        return Err(Violation::Oversized);
+    }
+    
+    let immutables = before.into_iter().flat_map(|state| state.selectors.iter()).filter(|m| m.is_immutable);
+    for mapping in immutables {
+        let kept = after.selectors.iter().any(|m| {
+            m.selector == mapping.selector
+                && m.module == mapping.module
+                && m.function_name == mapping.function_name
+                && m.is_immutable
+        });
+        if !kept {
+            return Err(Violation::ImmutableChanged);
+        }
+    }
+    Ok(())
+}
+
+/// Copy of `state` to check against later (audit builds only)
+pub fn snapshot(state: &DiamondState) -> Option<DiamondState> {
+    cfg!(feature = "audit").then(|| state.clone())
+}
+
+/// Check `after` against the invariants and its `before` snapshot (audit
+/// builds only)
+pub fn audit(before: Option<&DiamondState>, after: &DiamondState) -> Result<()> {
+    if !cfg!(feature = "audit") {
+        return Ok(());
+    }
+    check(before, after).map_err(|violation| {
+        msg!("Error: Invariant violated: {:?}", violation);
+        error!(DiamondError::InvariantViolated)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diamond_cut::{apply_cut, FacetCutAction};
+    
+    fn cut(selector: [u8; 4], module_address: Pubkey, is_immutable: bool) -> FacetCut {
+        FacetCut {
+            action: FacetCutAction::Add,
+            selector,
+            module_address,
+            function_name: "deposit".to_string(),
+            is_immutable,
+        }
+    }
+    
+    #[test]
+    fn test_check_catches_each_violation() {
+        let (owner, facet) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut state = DiamondState {
+            owner,
+            selectors: Vec::new(),
+            modules: Vec::new(),
+            bump: 255,
+            is_paused: false,
+            pause_expiry_slot: None,
+            pause_authority: owner,
+            unpause_quorum: 0,
+            unpause_approvers: Vec::new(),
+        };
+        apply_cut(&mut state, &cut([9, 0, 0, 1], facet, true)).unwrap();
+        apply_cut(&mut state, &cut([1, 0, 0, 1], facet, false)).unwrap();
+        assert_eq!(check(None, &state), Ok(()));
+        
+        let mut duplicate = state.clone();
+        duplicate.selectors.push(state.selectors[1].clone());
+        assert_eq!(check(None, &duplicate), Err(Violation::DuplicateSelector));
+        
+        let mut approvers = state.clone();
+        approvers.unpause_approvers = vec![facet, facet];
+        assert_eq!(check(None, &approvers), Err(Violation::DuplicateApprover));
+        
+        let mut long = state.clone();
+        long.selectors[1].function_name = "f".repeat(FacetCut::MAX_NAME_LEN + 1);
+        assert_eq!(check(None, &long), Err(Violation::Oversized));
+        
+        let mut replaced = state.clone();
+        replaced.selectors[0].module = Pubkey::new_unique();
+        assert_eq!(check(Some(&state), &replaced), Err(Violation::ImmutableChanged));
+        let mut removed = state.clone();
+        removed.selectors.remove(0);
+        assert_eq!(check(Some(&state), &removed), Err(Violation::ImmutableChanged));
+    }
+}
@@ -7,13 +7,43 @@ use anchor_lang::prelude::*;
 use anchor_lang::solana_program::{instruction::Instruction, program::invoke};
 use crate::error::DiamondError;
 
+/// Normalize forwarded accounts: collapse duplicates (merging flags) and
+/// refuse to forward the diamond state as writable
+pub fn build_account_metas(
+    diamond_state: &Pubkey,
+    remaining_accounts: &[AccountInfo],
+) -> Result<Vec<AccountMeta>> {
+    let mut metas: Vec<AccountMeta> = Vec::with_capacity(remaining_accounts.len());
+    for acc in remaining_accounts {
+        require!(
+            !(acc.key == diamond_state && acc.is_writable),
+            DiamondError::WritableDiamondState
+        );
+        match metas.iter_mut().find(|meta| meta.pubkey == *acc.key) {
+            Some(meta) => {
+                meta.is_signer |= acc.is_signer;
+                meta.is_writable |= acc.is_writable;
+            }
+            None => metas.push(AccountMeta {
+                pubkey: *acc.key,
+                is_signer: acc.is_signer,
+                is_writable: acc.is_writable,
+            }),
+        }
+    }
+    Ok(metas)
+}
+
 /// Dispatch instruction to registered facet
 pub fn dispatch(ctx: Context<crate::Dispatch>, ix_data: Vec<u8>) -> Result<()> {
     let diamond = &ctx.accounts.diamond_state;
     let facet_program = &ctx.accounts.facet_program;
     
-    // Check if paused
-    require!(!diamond.is_paused, DiamondError::DiamondPaused);
+    // Check if paused (a pause with an expiry lapses on its own)
+    if diamond.is_paused {
+        let slot = Clock::get()?.slot;
+        require!(!diamond.is_paused_at(slot), DiamondError::DiamondPaused);
+    }
     
     // Extract selector (first 4 bytes)
     require!(ix_data.len() >= 4, DiamondError::FacetNotFound);
@@ -26,6 +56,12 @@ pub fn dispatch(ctx: Context<crate::Dispatch>, ix_data: Vec<u8>) -> Result<()> {
         .get_facet_by_selector(selector)
         .ok_or(DiamondError::FacetNotFound)?;
     
+    // Guard against CPI recursion into the router itself
+    require!(
+        expected_facet != crate::ID,
+        DiamondError::SelfDispatchForbidden
+    );
+    
     // Validate provided facet matches registry
     require!(
         facet_program.key() == expected_facet,
@@ -37,14 +73,7 @@ pub fn dispatch(ctx: Context<crate::Dispatch>, ix_data: Vec<u8>) -> Result<()> {
     // Forward via CPI
     let ix = Instruction {
         program_id: *facet_program.key,
-        accounts: ctx.remaining_accounts
-            .iter()
-            .map(|acc| AccountMeta {
-                pubkey: *acc.key,
-                is_signer: acc.is_signer,
-                is_writable: acc.is_writable,
-            })
-            .collect(),
+        accounts: build_account_metas(&diamond.key(), ctx.remaining_accounts)?,
         data: ix_data,
     };
     
@@ -4,6 +4,8 @@
  */
 
 use anchor_lang::prelude::*;
+use crate::diamond_cut::{apply_cut, FacetCut};
+use crate::diamond_invariants;
 use crate::error::DiamondError;
 
 /// Selector mapping: function selector -> facet program
@@ -31,18 +33,29 @@ pub struct DiamondState {
     pub modules: Vec<ModuleMeta>,
     pub bump: u8,
     pub is_paused: bool,
+    pub pause_expiry_slot: Option<u64>,
+    pub pause_authority: Pubkey,
+    /// Approver signatures needed to lift or shorten a pause (0 = any pause key)
+    pub unpause_quorum: u8,
+    /// Approvers besides the owner (the native router's admins)
+    pub unpause_approvers: Vec<Pubkey>,
 }
 
 impl DiamondState {
     pub const MAX_SELECTORS: usize = 50;
     pub const MAX_MODULES: usize = 20;
+    pub const MAX_UNPAUSE_APPROVERS: usize = 5;
     
     pub const SPACE: usize = 8 + // discriminator
         32 + // owner
-        4 + (Self::MAX_SELECTORS * 150) + // selectors (generous estimate)
-        4 + (Self::MAX_MODULES * 100) + // modules
+        4 + (Self::MAX_SELECTORS * 105) + // selectors (4 selector + 32 module + 4+64 function_name + 1 is_immutable)
+        4 + (Self::MAX_MODULES * 102) + // modules (4+64 name + 32 address + 2 version)
         1 + // bump
-        1; // is_paused
+        1 + // is_paused
+        9 + // pause_expiry_slot
+        32 + // pause_authority
+        1 + // unpause_quorum
+        4 + (Self::MAX_UNPAUSE_APPROVERS * 32); // unpause_approvers
     
     pub fn get_facet_by_selector(&self, selector: [u8; 4]) -> Option<Pubkey> {
         self.selectors
@@ -50,10 +63,51 @@ impl DiamondState {
             .find(|s| s.selector == selector)
             .map(|s| s.module)
     }
+    
+    /// Owner and the dedicated pause authority may pause/unpause
+    pub fn can_pause(&self, pubkey: &Pubkey) -> bool {
+        self.owner == *pubkey || self.pause_authority == *pubkey
+    }
+    
+    /// Whether setting `paused` with `expiry_slot` lifts the pause or brings
+    /// its expiry forward
+    pub fn relaxes_pause(&self, paused: bool, expiry_slot: Option<u64>) -> bool {
+        let lapses = |expiry: Option<u64>| expiry.unwrap_or(u64::MAX);
+        self.is_paused && (!paused || lapses(expiry_slot) < lapses(self.pause_expiry_slot))
+    }
+    
+    /// Whether `pubkey` counts towards the unpause quorum: the owner or a
+    /// listed approver, as the native router counts the owner and admins
+    pub fn is_unpause_approver(&self, pubkey: &Pubkey) -> bool {
+        self.owner == *pubkey || self.unpause_approvers.contains(pubkey)
+    }
+    
+    /// Check `signers` include `unpause_quorum` distinct approvers
+    pub fn check_unpause_quorum<'a>(&self, signers: impl IntoIterator<Item = &'a Pubkey>) -> Result<()> {
+        let mut approved: Vec<&Pubkey> = Vec::new();
+        for signer in signers {
+            if self.is_unpause_approver(signer) && !approved.contains(&signer) {
+                approved.push(signer);
+            }
+        }
+        require!(approved.len() >= self.unpause_quorum as usize, DiamondError::UnpauseQuorumNotMet);
+        Ok(())
+    }
+    
+    /// Whether the diamond is paused at `slot`, honoring any pause expiry
+    pub fn is_paused_at(&self, slot: u64) -> bool {
+        match self.pause_expiry_slot {
+            Some(expiry) => self.is_paused && slot < expiry,
+            None => self.is_paused,
+        }
+    }
 }
 
-/// Initialize the diamond
-pub fn initialize(ctx: Context<crate::Initialize>) -> Result<()> {
+/// Initialize the diamond, optionally seeding it with an initial cut list
+pub fn initialize(
+    ctx: Context<crate::Initialize>,
+    initial_cuts: Option<Vec<FacetCut>>,
+) -> Result<()> {
     let diamond = &mut ctx.accounts.diamond_state;
     
     diamond.owner = ctx.accounts.owner.key();
@@ -61,16 +115,150 @@ pub fn initialize(ctx: Context<crate::Initialize>) -> Result<()> {
     diamond.modules = Vec::new();
     diamond.bump = ctx.bumps.diamond_state;
     diamond.is_paused = false;
+    diamond.pause_expiry_slot = None;
+    diamond.pause_authority = diamond.owner;
+    diamond.unpause_quorum = 0;
+    diamond.unpause_approvers = Vec::new();
+    
+    for cut in initial_cuts.iter().flatten() {
+        apply_cut(diamond, cut)?;
+    }
+    diamond_invariants::audit(None, diamond)?;
     
-    msg!("Diamond initialized with owner: {}", diamond.owner);
+    msg!(
+        "Diamond initialized with owner: {} ({} selectors)",
+        diamond.owner,
+        diamond.selectors.len()
+    );
     Ok(())
 }
 
-/// Set paused state
-pub fn set_paused(ctx: Context<crate::SetPaused>, paused: bool) -> Result<()> {
+/// Set paused state, optionally with a slot after which the pause lapses
+///
+/// Lifting or shortening a pause needs the unpause quorum; approvers co-sign
+/// as remaining accounts.
+pub fn set_paused(
+    ctx: Context<crate::SetPaused>,
+    paused: bool,
+    expiry_slot: Option<u64>,
+) -> Result<()> {
     let diamond = &mut ctx.accounts.diamond_state;
+    let before = diamond_invariants::snapshot(diamond);
+    if diamond.relaxes_pause(paused, expiry_slot) {
+        let authority = ctx.accounts.authority.key();
+        let approvers = ctx.remaining_accounts.iter().filter(|a| a.is_signer).map(|a| a.key);
+        diamond.check_unpause_quorum(std::iter::once(&authority).chain(approvers))?;
+    }
     diamond.is_paused = paused;
+    diamond.pause_expiry_slot = if paused { expiry_slot } else { None };
+    diamond_invariants::audit(before.as_ref(), diamond)?;
     
-    msg!("Diamond paused state set to: {}", paused);
+    msg!("Diamond paused state set to: {} (expiry slot: {:?})", paused, diamond.pause_expiry_slot);
     Ok(())
 }
+
+
+/// Rotate the pause authority (owner only)
+pub fn set_pause_authority(
+    ctx: Context<crate::SetPauseAuthority>,
+    new_authority: Pubkey,
+) -> Result<()> {
+    let diamond = &mut ctx.accounts.diamond_state;
+    let before = diamond_invariants::snapshot(diamond);
+    let old = diamond.pause_authority;
+    diamond.pause_authority = new_authority;
+    diamond_invariants::audit(before.as_ref(), diamond)?;
+    
+    msg!("Pause authority set: {} -> {}", old, new_authority);
+    Ok(())
+}
+
+/// Set the unpause approvers and quorum (owner only)
+///
+/// The owner always approves and isn't listed. Lowering the quorum or adding
+/// an approver needs the current quorum, so one key can't drop it, or enrol
+/// keys of its own, and then unpause. The quorum must stay reachable.
+pub fn set_unpause_quorum(
+    ctx: Context<crate::SetUnpauseQuorum>,
+    approvers: Vec<Pubkey>,
+    quorum: u8,
+) -> Result<()> {
+    let diamond = &mut ctx.accounts.diamond_state;
+    let before = diamond_invariants::snapshot(diamond);
+    let mut unique: Vec<Pubkey> = Vec::with_capacity(approvers.len());
+    for approver in approvers {
+        if approver != diamond.owner && !unique.contains(&approver) {
+            unique.push(approver);
+        }
+    }
+    require!(unique.len() <= DiamondState::MAX_UNPAUSE_APPROVERS, DiamondError::InvalidUnpauseQuorum);
+    require!(quorum as usize <= 1 + unique.len(), DiamondError::InvalidUnpauseQuorum);
+    
+    let weakens = quorum < diamond.unpause_quorum || unique.iter().any(|a| !diamond.unpause_approvers.contains(a));
+    if weakens {
+        let owner = ctx.accounts.owner.key();
+        let approvers = ctx.remaining_accounts.iter().filter(|a| a.is_signer).map(|a| a.key);
+        diamond.check_unpause_quorum(std::iter::once(&owner).chain(approvers))?;
+    }
+    
+    diamond.unpause_quorum = quorum;
+    diamond.unpause_approvers = unique;
+    diamond_invariants::audit(before.as_ref(), diamond)?;
+    msg!("Unpause quorum set: {} of {}", quorum, diamond.unpause_approvers.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diamond_cut::FacetCut;
+    
+    #[test]
+    fn test_space_matches_worst_case() {
+        let name = "f".repeat(FacetCut::MAX_NAME_LEN);
+        let state = DiamondState {
+            owner: Pubkey::new_unique(),
+            selectors: (0..DiamondState::MAX_SELECTORS)
+                .map(|_| SelectorMapping {
+                    selector: [0xFF; 4],
+                    module: Pubkey::new_unique(),
+                    function_name: name.clone(),
+                    is_immutable: true,
+                })
+                .collect(),
+            modules: (0..DiamondState::MAX_MODULES)
+                .map(|_| ModuleMeta {
+                    name: name.clone(),
+                    address: Pubkey::new_unique(),
+                    version: u16::MAX,
+                })
+                .collect(),
+            bump: 255,
+            is_paused: true,
+            pause_expiry_slot: Some(u64::MAX),
+            pause_authority: Pubkey::new_unique(),
+            unpause_quorum: u8::MAX,
+            unpause_approvers: (0..DiamondState::MAX_UNPAUSE_APPROVERS).map(|_| Pubkey::new_unique()).collect(),
+        };
+        let encoded = state.try_to_vec().unwrap();
+        assert_eq!(8 + encoded.len(), DiamondState::SPACE);
+    }
+    
+    #[test]
+    fn test_unpause_quorum_counts_owner_and_approvers() {
+        let (owner, approver, outsider) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let state = DiamondState {
+            owner,
+            selectors: Vec::new(),
+            modules: Vec::new(),
+            bump: 255,
+            is_paused: true,
+            pause_expiry_slot: None,
+            pause_authority: owner,
+            unpause_quorum: 2,
+            unpause_approvers: vec![approver],
+        };
+        assert!(state.check_unpause_quorum([&owner, &approver]).is_ok());
+        assert!(state.check_unpause_quorum([&approver, &approver, &outsider]).is_err());
+    }
+}
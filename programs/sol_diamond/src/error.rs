@@ -19,4 +19,22 @@ pub enum DiamondError {
     
     #[msg("Maximum facets reached")]
     MaxFacetsReached,
+    
+    #[msg("Diamond state cannot be forwarded as writable")]
+    WritableDiamondState,
+    
+    #[msg("Router cannot dispatch to itself")]
+    SelfDispatchForbidden,
+    
+    #[msg("Invalid facet cut")]
+    InvalidCut,
+    
+    #[msg("Lifting the pause needs more approvers")]
+    UnpauseQuorumNotMet,
+    
+    #[msg("Unpause quorum exceeds its approvers")]
+    InvalidUnpauseQuorum,
+    
+    #[msg("State invariant violated")]
+    InvariantViolated,
 }
@@ -10,21 +10,26 @@ use anchor_lang::prelude::*;
 pub mod diamond_state;
 pub mod diamond_router;
 pub mod diamond_cut;
+pub mod diamond_invariants;
 pub mod error;
 
 declare_id!("DiamondMVP1111111111111111111111111111111");
 
 // Re-export main types
 pub use diamond_state::{DiamondState, ModuleMeta, SelectorMapping};
+pub use diamond_cut::{FacetCut, FacetCutAction};
 pub use error::DiamondError;
 
 #[program]
 pub mod sol_diamond_mvp {
     use super::*;
 
-    /// Initialize the diamond with an owner
-    pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
-        diamond_state::initialize(ctx)
+    /// Initialize the diamond with an owner and optional initial facets
+    pub fn initialize(
+        ctx: Context<Initialize>,
+        initial_cuts: Option<Vec<FacetCut>>,
+    ) -> Result<()> {
+        diamond_state::initialize(ctx, initial_cuts)
     }
 
     /// Dispatch instruction to a registered facet
@@ -48,9 +53,39 @@ pub mod sol_diamond_mvp {
         diamond_cut::remove_facet(ctx, selector)
     }
 
-    /// Pause/unpause the diamond
-    pub fn set_paused(ctx: Context<SetPaused>, paused: bool) -> Result<()> {
-        diamond_state::set_paused(ctx, paused)
+    /// Pause/unpause the diamond (a pause may auto-expire at `expiry_slot`)
+    pub fn set_paused(
+        ctx: Context<SetPaused>,
+        paused: bool,
+        expiry_slot: Option<u64>,
+    ) -> Result<()> {
+        diamond_state::set_paused(ctx, paused, expiry_slot)
+    }
+
+    /// Rotate the pause authority
+    pub fn set_pause_authority(ctx: Context<SetPauseAuthority>, new_authority: Pubkey) -> Result<()> {
+        diamond_state::set_pause_authority(ctx, new_authority)
+    }
+
+    /// Require `quorum` of the owner and `approvers` to lift a pause (0 = any pause key)
+    pub fn set_unpause_quorum(
+        ctx: Context<SetUnpauseQuorum>,
+        approvers: Vec<Pubkey>,
+        quorum: u8,
+    ) -> Result<()> {
+        diamond_state::set_unpause_quorum(ctx, approvers, quorum)
+    }
+
+    /// Grow a diamond state account created by an older build to today's SPACE
+    pub fn realloc_state(_ctx: Context<ReallocState>) -> Result<()> {
+        msg!("Diamond state resized to {} bytes", DiamondState::SPACE);
+        Ok(())
+    }
+
+    /// Close the diamond state account (e.g. after migrating to the native router)
+    pub fn close_diamond(_ctx: Context<CloseDiamond>) -> Result<()> {
+        msg!("Diamond state closed");
+        Ok(())
     }
 }
 
@@ -109,6 +144,29 @@ pub struct RemoveFacet<'info> {
 
 #[derive(Accounts)]
 pub struct SetPaused<'info> {
+    #[account(
+        mut,
+        constraint = diamond_state.can_pause(&authority.key()) @ DiamondError::Unauthorized
+    )]
+    pub diamond_state: Account<'info, DiamondState>,
+    
+    /// Owner or pause authority
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPauseAuthority<'info> {
+    #[account(
+        mut,
+        has_one = owner @ DiamondError::Unauthorized
+    )]
+    pub diamond_state: Account<'info, DiamondState>,
+    
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetUnpauseQuorum<'info> {
     #[account(
         mut,
         has_one = owner @ DiamondError::Unauthorized
@@ -117,3 +175,44 @@ pub struct SetPaused<'info> {
     
     pub owner: Signer<'info>,
 }
+
+/// The state is taken unchecked: an account created before the unpause
+/// quorum fields may be too short to deserialize. Zero-filling the new bytes
+/// reads back as quorum 0 with no approvers.
+#[derive(Accounts)]
+pub struct ReallocState<'info> {
+    /// CHECK: Owned by this program at the owner's PDA; only resized here
+    #[account(
+        mut,
+        owner = crate::ID @ DiamondError::Unauthorized,
+        seeds = [b"diamond_state", owner.key().as_ref()],
+        bump,
+        realloc = DiamondState::SPACE,
+        realloc::payer = payer,
+        realloc::zero = true,
+    )]
+    pub diamond_state: UncheckedAccount<'info>,
+    
+    pub owner: Signer<'info>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseDiamond<'info> {
+    #[account(
+        mut,
+        has_one = owner @ DiamondError::Unauthorized,
+        close = recipient
+    )]
+    pub diamond_state: Account<'info, DiamondState>,
+    
+    pub owner: Signer<'info>,
+    
+    /// CHECK: Receives the reclaimed rent
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+}